@@ -399,7 +399,12 @@ func (n *AGFSNode) Symlink(ctx context.Context, target, name string, out *fuse.E
 // fillAttr fills FUSE attributes from AGFS FileInfo
 func fillAttr(out *fuse.Attr, info *agfs.FileInfo) {
 	out.Mode = modeToFileMode(info.Mode)
-	out.Size = uint64(info.Size)
+	// Files of unknown size (generated on read) show as empty; they are
+	// opened with direct I/O, so reads still go on until the plugin
+	// returns no data
+	if info.Size >= 0 {
+		out.Size = uint64(info.Size)
+	}
 	out.Mtime = uint64(info.ModTime.Unix())
 	out.Mtimensec = uint32(info.ModTime.Nanosecond())
 	out.Atime = out.Mtime
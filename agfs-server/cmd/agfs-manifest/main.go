@@ -0,0 +1,175 @@
+// Command agfs-manifest inspects and signs the manifests of WASM plugins.
+//
+//	agfs-manifest keygen -key plugin.key -pub plugin.pub
+//	agfs-manifest show plugin.wasm
+//	agfs-manifest buildinfo plugin.wasm
+//	agfs-manifest sign -key plugin.key plugin.wasm
+//	agfs-manifest verify -pub plugin.pub plugin.wasm
+//
+// sign prints the signature to embed in the plugin. Rebuild it with
+// AGFS_MANIFEST_SIGNATURE set to that value (and the same AGFS_BUILD_HASH);
+// the signature does not cover itself, so the rebuilt plugin verifies.
+//
+// buildinfo prints the agfs_build_info section (SDK and plugin versions, git
+// hash, build time) without running the plugin.
+package main
+
+import (
+	"crypto/ed25519"
+	"crypto/rand"
+	"encoding/base64"
+	"encoding/json"
+	"flag"
+	"fmt"
+	"os"
+	"strings"
+
+	"github.com/c4pt0r/agfs/agfs-server/pkg/plugin/loader"
+)
+
+func usage() {
+	fmt.Fprintln(os.Stderr, "usage: agfs-manifest <keygen|show|buildinfo|sign|verify> [flags] [plugin.wasm]")
+	os.Exit(2)
+}
+
+func main() {
+	if len(os.Args) < 2 {
+		usage()
+	}
+	cmd, args := os.Args[1], os.Args[2:]
+
+	var err error
+	switch cmd {
+	case "keygen":
+		err = keygen(args)
+	case "show":
+		err = show(args)
+	case "buildinfo":
+		err = buildInfo(args)
+	case "sign":
+		err = sign(args)
+	case "verify":
+		err = verify(args)
+	default:
+		usage()
+	}
+	if err != nil {
+		fmt.Fprintf(os.Stderr, "agfs-manifest %s: %v\n", cmd, err)
+		os.Exit(1)
+	}
+}
+
+func keygen(args []string) error {
+	fs := flag.NewFlagSet("keygen", flag.ExitOnError)
+	keyFile := fs.String("key", "plugin.key", "Where to write the private key")
+	pubFile := fs.String("pub", "plugin.pub", "Where to write the public key")
+	fs.Parse(args)
+
+	pub, priv, err := ed25519.GenerateKey(rand.Reader)
+	if err != nil {
+		return err
+	}
+	if err := os.WriteFile(*keyFile, []byte(base64.StdEncoding.EncodeToString(priv)+"\n"), 0600); err != nil {
+		return err
+	}
+	return os.WriteFile(*pubFile, []byte(base64.StdEncoding.EncodeToString(pub)+"\n"), 0644)
+}
+
+func show(args []string) error {
+	fs := flag.NewFlagSet("show", flag.ExitOnError)
+	fs.Parse(args)
+
+	manifest, err := readManifest(fs)
+	if err != nil {
+		return err
+	}
+	out, err := json.MarshalIndent(manifest, "", "  ")
+	if err != nil {
+		return err
+	}
+	fmt.Println(string(out))
+	return nil
+}
+
+func buildInfo(args []string) error {
+	fs := flag.NewFlagSet("buildinfo", flag.ExitOnError)
+	fs.Parse(args)
+
+	if fs.NArg() != 1 {
+		return fmt.Errorf("expected one plugin.wasm argument")
+	}
+	info, err := loader.ReadWASMBuildInfo(fs.Arg(0))
+	if err != nil {
+		return err
+	}
+	out, err := json.MarshalIndent(info, "", "  ")
+	if err != nil {
+		return err
+	}
+	fmt.Println(string(out))
+	return nil
+}
+
+func sign(args []string) error {
+	fs := flag.NewFlagSet("sign", flag.ExitOnError)
+	keyFile := fs.String("key", "plugin.key", "Private key from keygen")
+	fs.Parse(args)
+
+	key, err := readKey(*keyFile, ed25519.PrivateKeySize)
+	if err != nil {
+		return err
+	}
+	manifest, err := readManifest(fs)
+	if err != nil {
+		return err
+	}
+	sig, err := manifest.Sign(ed25519.PrivateKey(key))
+	if err != nil {
+		return err
+	}
+	fmt.Println(sig)
+	return nil
+}
+
+func verify(args []string) error {
+	fs := flag.NewFlagSet("verify", flag.ExitOnError)
+	pubFile := fs.String("pub", "plugin.pub", "Public key from keygen")
+	fs.Parse(args)
+
+	key, err := readKey(*pubFile, ed25519.PublicKeySize)
+	if err != nil {
+		return err
+	}
+	manifest, err := readManifest(fs)
+	if err != nil {
+		return err
+	}
+	if err := manifest.Verify(ed25519.PublicKey(key)); err != nil {
+		return err
+	}
+	fmt.Printf("%s %s: signature OK\n", manifest.Name, manifest.Version)
+	return nil
+}
+
+func readManifest(fs *flag.FlagSet) (*loader.WASMManifest, error) {
+	if fs.NArg() != 1 {
+		return nil, fmt.Errorf("expected one plugin.wasm argument")
+	}
+	return loader.ReadWASMManifest(fs.Arg(0))
+}
+
+// readKey loads a base64-encoded key of the given size
+func readKey(path string, size int) ([]byte, error) {
+	data, err := os.ReadFile(path)
+	if err != nil {
+		return nil, err
+	}
+	key, err := base64.StdEncoding.DecodeString(strings.TrimSpace(string(data)))
+	if err != nil {
+		return nil, fmt.Errorf("%s: %w", path, err)
+	}
+	if len(key) != size {
+		return nil, fmt.Errorf("%s: expected a %d-byte key, got %d bytes", path, size, len(key))
+	}
+	return key, nil
+}
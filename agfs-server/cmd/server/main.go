@@ -220,7 +220,23 @@ func main() {
 		InstanceMaxLifetime: time.Duration(wasmConfig.InstanceMaxLifetime) * time.Second,
 		InstanceMaxRequests: int64(wasmConfig.InstanceMaxRequests),
 		HealthCheckInterval: time.Duration(wasmConfig.HealthCheckInterval) * time.Second,
+		OperationTimeout:    time.Duration(wasmConfig.OperationTimeout) * time.Second,
 		EnableStatistics:    wasmConfig.EnablePoolStatistics,
+		QueueDir:            wasmConfig.QueueDir,
+	}
+	if wasmConfig.EmbeddingAPIKey != "" {
+		embedder, err := vectorfs.NewEmbeddingClient(vectorfs.EmbeddingConfig{
+			Provider: "openai",
+			APIKey:   wasmConfig.EmbeddingAPIKey,
+			Model:    wasmConfig.EmbeddingModel,
+		})
+		if err != nil {
+			log.Fatalf("Failed to create WASM embedding client: %v", err)
+		}
+		poolConfig.Embedder = embedder
+	}
+	if wasmConfig.CompletionAPIKey != "" {
+		poolConfig.Completer = api.NewOpenAICompleter(wasmConfig.CompletionURL, wasmConfig.CompletionAPIKey, wasmConfig.CompletionModel)
 	}
 
 	// Create mountable file system
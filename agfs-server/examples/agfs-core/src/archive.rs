@@ -0,0 +1,598 @@
+//! Dated archive directories of a plugin's files
+//!
+//! Some plugins should keep what they served at a point in time: the HN
+//! front page of each day, a nightly CSV dump of a SQL table. An
+//! [`ArchiveView`] keeps the copies a plugin records, with a retention limit,
+//! and serves them under `/archive/<date>/`:
+//!
+//! ```ignore
+//! // After each refresh
+//! self.archive.record_file("frontpage.xml", self.render_rss())?;
+//! self.archive.record_dir("frontpage", self.rendered_stories())?;
+//! ```
+//!
+//! An [`ExportScheduler`] holds the export jobs a plugin registers, runs the
+//! ones that are due whenever the host calls `maintain` (every
+//! `maintain_interval` of the mount config), and archives what they produce:
+//!
+//! ```ignore
+//! // In the plugin struct
+//! exports: ExportScheduler = ExportScheduler::new().job("frontpage", Duration::from_secs(3600)).keep_days(30),
+//!
+//! fn maintain(&mut self) -> Result<()> {
+//!     self.exports.run_due(|job| match job {
+//!         "frontpage" => Ok(vec![("frontpage.xml".to_string(), self.render_rss()?)]),
+//!         _ => Ok(Vec::new()),
+//!     })?;
+//!     Ok(())
+//! }
+//!
+//! fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+//!     if archive::is_archive_path(path) {
+//!         return self.exports.read(path, offset, size);
+//!     }
+//!     // ...
+//! }
+//! ```
+//!
+//! A file recorded more than once a day replaces that day's copy. Archives
+//! are kept in plugin memory for the life of the instance.
+
+use crate::error::{Error, Result};
+use crate::filesystem::read_range;
+use crate::time::DateTime;
+use crate::types::FileInfo;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+/// Directory the archived exports are served under
+pub const ARCHIVE_DIR: &str = "/archive";
+
+const DIR_NAME: &str = "archive";
+
+/// Whether `path` is [`ARCHIVE_DIR`] or below it
+pub fn is_archive_path(path: &str) -> bool {
+    path.strip_prefix(ARCHIVE_DIR)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// `now` (since the Unix epoch) as a `YYYY-MM-DD` UTC date
+pub fn date(now: Duration) -> String {
+    DateTime::from_unix(now.as_secs() as i64).date()
+}
+
+/// `now` (since the Unix epoch) as a `YYYY-MM-DDTHHMMSSZ` UTC timestamp,
+/// usable in file names
+pub fn timestamp(now: Duration) -> String {
+    let t = DateTime::from_unix(now.as_secs() as i64);
+    format!("{}T{:02}{:02}{:02}Z", t.date(), t.hour, t.minute, t.second)
+}
+
+struct Kept {
+    content: Arc<[u8]>,
+    /// Unix time the copy was taken
+    at: i64,
+}
+
+enum Entry {
+    File(Kept),
+    Dir(BTreeMap<String, Kept>),
+}
+
+/// What an archive path resolves to
+enum Node<'a> {
+    Root,
+    Snapshot(&'a str),
+    Dir(&'a str, &'a BTreeMap<String, Kept>),
+    File(&'a str, &'a Kept),
+}
+
+/// Dated copies of a plugin's virtual files, served under `/archive/`
+///
+/// Each recorded file or directory goes into the directory of the current
+/// UTC date, `/archive/2024-06-01/`, replacing the copy recorded earlier that
+/// day; [`per_refresh`](Self::per_refresh) keeps every recording instead, in
+/// directories named down to the second, `/archive/2024-06-01T120000Z/`.
+pub struct ArchiveView {
+    clock: fn() -> Duration,
+    per_refresh: bool,
+    keep: usize,
+    /// Recorded entries by archive directory, then name
+    snapshots: Mutex<BTreeMap<String, BTreeMap<String, Entry>>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for ArchiveView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ArchiveView {
+    /// Create a daily archive using the system clock
+    ///
+    /// WASM plugins have no clock of their own; use `agfs_wasm_ffi::archive::view`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn new() -> Self {
+        Self::with_clock(system_clock)
+    }
+
+    /// Create a daily archive reading the time from `clock`, which must count
+    /// from the Unix epoch: archive directories are named by UTC date
+    pub fn with_clock(clock: fn() -> Duration) -> Self {
+        Self {
+            clock,
+            per_refresh: false,
+            keep: 0,
+            snapshots: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Keep a directory per recording rather than per day
+    pub fn per_refresh(mut self) -> Self {
+        self.per_refresh = true;
+        self
+    }
+
+    /// Keep only the latest `count` archive directories (0, the default,
+    /// keeps all)
+    pub fn keep(mut self, count: usize) -> Self {
+        self.keep = count;
+        self
+    }
+
+    fn snapshots(&self) -> MutexGuard<'_, BTreeMap<String, BTreeMap<String, Entry>>> {
+        self.snapshots.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Record a copy of the file `name`
+    pub fn record_file(&self, name: &str, content: impl Into<Arc<[u8]>>) -> Result<()> {
+        check_name(name)?;
+        let now = (self.clock)();
+        let entry = Entry::File(kept(now, content));
+        self.insert(now, vec![(name.to_string(), entry)]);
+        Ok(())
+    }
+
+    /// Record a copy of the directory `name` holding `files`, replacing
+    /// every file of an earlier copy in the same archive directory
+    pub fn record_dir<I, N, C>(&self, name: &str, files: I) -> Result<()>
+    where
+        I: IntoIterator<Item = (N, C)>,
+        N: Into<String>,
+        C: Into<Arc<[u8]>>,
+    {
+        check_name(name)?;
+        let now = (self.clock)();
+        let mut dir = BTreeMap::new();
+        for (file, content) in files {
+            let file = file.into();
+            check_name(&file)?;
+            dir.insert(file, kept(now, content));
+        }
+        self.insert(now, vec![(name.to_string(), Entry::Dir(dir))]);
+        Ok(())
+    }
+
+    fn insert(&self, now: Duration, entries: Vec<(String, Entry)>) {
+        let snapshot = match self.per_refresh {
+            true => timestamp(now),
+            false => date(now),
+        };
+        let mut snapshots = self.snapshots();
+        snapshots.entry(snapshot).or_default().extend(entries);
+        if self.keep > 0 {
+            while snapshots.len() > self.keep {
+                snapshots.pop_first();
+            }
+        }
+    }
+
+    /// The `archive` entry for the plugin's root listing
+    pub fn root_entry(&self) -> FileInfo {
+        FileInfo::dir(DIR_NAME, 0o555)
+    }
+
+    pub fn stat(&self, path: &str) -> Result<FileInfo> {
+        let snapshots = self.snapshots();
+        Ok(match lookup(&snapshots, path)? {
+            Node::Root => self.root_entry(),
+            Node::Snapshot(name) | Node::Dir(name, _) => FileInfo::dir(name, 0o555),
+            Node::File(name, kept) => file_info(name, kept),
+        })
+    }
+
+    /// The archive directories under `/archive`, oldest first, or the
+    /// entries of one of them
+    pub fn readdir(&self, path: &str) -> Result<Vec<FileInfo>> {
+        let snapshots = self.snapshots();
+        match lookup(&snapshots, path)? {
+            Node::Root => Ok(snapshots
+                .keys()
+                .map(|name| FileInfo::dir(name.clone(), 0o555))
+                .collect()),
+            Node::Snapshot(name) => Ok(snapshots[name]
+                .iter()
+                .map(|(name, entry)| match entry {
+                    Entry::File(kept) => file_info(name, kept),
+                    Entry::Dir(_) => FileInfo::dir(name.clone(), 0o555),
+                })
+                .collect()),
+            Node::Dir(_, files) => Ok(files.iter().map(|(name, kept)| file_info(name, kept)).collect()),
+            Node::File(..) => Err(Error::NotDirectory),
+        }
+    }
+
+    pub fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+        let content = match lookup(&self.snapshots(), path)? {
+            Node::File(_, kept) => Arc::clone(&kept.content),
+            _ => return Err(Error::IsDirectory),
+        };
+        Ok(read_range(&content, offset, size))
+    }
+
+    /// Number of archive directories kept
+    pub fn len(&self) -> usize {
+        self.snapshots().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Directories and bytes kept, for the plugin's `stats`
+    pub fn stats(&self) -> serde_json::Value {
+        let snapshots = self.snapshots();
+        let bytes: usize = snapshots
+            .values()
+            .flat_map(|entries| entries.values())
+            .map(|entry| match entry {
+                Entry::File(kept) => kept.content.len(),
+                Entry::Dir(files) => files.values().map(|kept| kept.content.len()).sum(),
+            })
+            .sum();
+        serde_json::json!({
+            "archive_dirs": snapshots.len(),
+            "archive_bytes": bytes,
+        })
+    }
+}
+
+struct Job {
+    name: String,
+    every: Duration,
+    last_run: Option<Duration>,
+}
+
+#[derive(Default)]
+struct Runs {
+    runs: u64,
+    failures: u64,
+    last_error: Option<String>,
+}
+
+/// Periodic export jobs and the dated archive they fill
+pub struct ExportScheduler {
+    clock: fn() -> Duration,
+    jobs: Mutex<Vec<Job>>,
+    archive: ArchiveView,
+    runs: Mutex<Runs>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for ExportScheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExportScheduler {
+    /// Create a scheduler using the system clock
+    ///
+    /// WASM plugins have no clock of their own; use `agfs_wasm_ffi::archive::new`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn new() -> Self {
+        Self::with_clock(system_clock)
+    }
+
+    /// Create a scheduler reading the time from `clock`, which must count
+    /// from the Unix epoch: archive directories are named by UTC date
+    pub fn with_clock(clock: fn() -> Duration) -> Self {
+        Self {
+            clock,
+            jobs: Mutex::new(Vec::new()),
+            archive: ArchiveView::with_clock(clock),
+            runs: Mutex::new(Runs::default()),
+        }
+    }
+
+    /// Run the job `name` every `every`, the first time on the next
+    /// [`run_due`](Self::run_due)
+    ///
+    /// Jobs run no more often than the host calls `maintain`.
+    pub fn job(self, name: impl Into<String>, every: Duration) -> Self {
+        self.lock_jobs().push(Job {
+            name: name.into(),
+            every,
+            last_run: None,
+        });
+        self
+    }
+
+    /// Keep only the latest `days` dates (0, the default, keeps all)
+    pub fn keep_days(mut self, days: usize) -> Self {
+        self.archive = self.archive.keep(days);
+        self
+    }
+
+    fn lock_jobs(&self) -> MutexGuard<'_, Vec<Job>> {
+        self.jobs.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn runs(&self) -> MutexGuard<'_, Runs> {
+        self.runs.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// The archive the jobs fill
+    pub fn archive(&self) -> &ArchiveView {
+        &self.archive
+    }
+
+    /// Run the jobs that are due with `export`, archiving the files it
+    /// returns for each as `(name, content)` under today's date
+    ///
+    /// A failed job is tried again on the next call; the others still run.
+    /// Returns the number of jobs run, or the first failure.
+    pub fn run_due<F>(&self, mut export: F) -> Result<usize>
+    where
+        F: FnMut(&str) -> Result<Vec<(String, Vec<u8>)>>,
+    {
+        let now = (self.clock)();
+        let due: Vec<String> = self
+            .lock_jobs()
+            .iter()
+            .filter(|job| job.last_run.is_none_or(|last| now.saturating_sub(last) >= job.every))
+            .map(|job| job.name.clone())
+            .collect();
+
+        let mut first_error = None;
+        for name in &due {
+            let result = export(name).and_then(|files| self.archive_files(now, files));
+            let mut runs = self.runs();
+            runs.runs += 1;
+            match result {
+                Ok(()) => {
+                    if let Some(job) = self.lock_jobs().iter_mut().find(|job| job.name == *name) {
+                        job.last_run = Some(now);
+                    }
+                }
+                Err(e) => {
+                    runs.failures += 1;
+                    runs.last_error = Some(format!("{}: {}", name, e));
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(due.len()),
+        }
+    }
+
+    fn archive_files(&self, now: Duration, files: Vec<(String, Vec<u8>)>) -> Result<()> {
+        for (name, _) in &files {
+            check_name(name)?;
+        }
+        let entries = files
+            .into_iter()
+            .map(|(name, content)| (name, Entry::File(kept(now, content))))
+            .collect();
+        self.archive.insert(now, entries);
+        Ok(())
+    }
+
+    /// The `archive` entry for the plugin's root listing
+    pub fn root_entry(&self) -> FileInfo {
+        self.archive.root_entry()
+    }
+
+    pub fn stat(&self, path: &str) -> Result<FileInfo> {
+        self.archive.stat(path)
+    }
+
+    /// The dates under `/archive`, oldest first, or the files of one date
+    pub fn readdir(&self, path: &str) -> Result<Vec<FileInfo>> {
+        self.archive.readdir(path)
+    }
+
+    pub fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+        self.archive.read(path, offset, size)
+    }
+
+    /// Jobs run and failed, the last failure and the dates kept, for the
+    /// plugin's `stats`
+    pub fn stats(&self) -> serde_json::Value {
+        let runs = self.runs();
+        serde_json::json!({
+            "export_runs": runs.runs,
+            "export_failures": runs.failures,
+            "export_last_error": runs.last_error,
+            "archive_dates": self.archive.len(),
+        })
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn system_clock() -> Duration {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+fn kept(now: Duration, content: impl Into<Arc<[u8]>>) -> Kept {
+    Kept {
+        content: content.into(),
+        at: now.as_secs() as i64,
+    }
+}
+
+/// Accept a name the archive can serve: one non-empty path segment
+fn check_name(name: &str) -> Result<()> {
+    match name.is_empty() || name == "." || name == ".." || name.contains('/') {
+        true => Err(Error::InvalidInput(format!("invalid archive entry name: {}", name))),
+        false => Ok(()),
+    }
+}
+
+fn file_info(name: &str, kept: &Kept) -> FileInfo {
+    FileInfo::file(name, kept.content.len() as i64, 0o444).with_mod_time(kept.at)
+}
+
+/// Resolve an archive path: `/archive`, an archive directory, an entry of
+/// one, or a file of a recorded directory
+fn lookup<'a>(snapshots: &'a BTreeMap<String, BTreeMap<String, Entry>>, path: &'a str) -> Result<Node<'a>> {
+    let rest = path
+        .strip_prefix(ARCHIVE_DIR)
+        .filter(|rest| rest.is_empty() || rest.starts_with('/'))
+        .ok_or(Error::NotFound)?;
+    let segments: Vec<&str> = rest.split('/').filter(|segment| !segment.is_empty()).collect();
+    let Some((snapshot, rest)) = segments.split_first() else {
+        return Ok(Node::Root);
+    };
+    let entries = snapshots.get(*snapshot).ok_or(Error::NotFound)?;
+    let Some((name, rest)) = rest.split_first() else {
+        return Ok(Node::Snapshot(snapshot));
+    };
+    match (entries.get(*name).ok_or(Error::NotFound)?, rest) {
+        (Entry::File(kept), []) => Ok(Node::File(name, kept)),
+        (Entry::Dir(files), []) => Ok(Node::Dir(name, files)),
+        (Entry::Dir(files), [file]) => Ok(Node::File(file, files.get(*file).ok_or(Error::NotFound)?)),
+        _ => Err(Error::NotFound),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    // 2024-06-01T12:00:00Z
+    static NOW_SECS: AtomicU64 = AtomicU64::new(1_717_243_200);
+
+    fn fake_clock() -> Duration {
+        Duration::from_secs(NOW_SECS.load(Ordering::SeqCst))
+    }
+
+    #[test]
+    fn test_date() {
+        assert_eq!(date(Duration::ZERO), "1970-01-01");
+        assert_eq!(date(Duration::from_secs(1_709_164_800)), "2024-02-29");
+        assert_eq!(date(Duration::from_secs(1_717_243_200)), "2024-06-01");
+    }
+
+    #[test]
+    fn test_scheduled_exports() {
+        let exports = ExportScheduler::with_clock(fake_clock)
+            .job("frontpage", Duration::from_secs(6 * 3600))
+            .job("broken", Duration::from_secs(3600))
+            .keep_days(2);
+        let mut version = 0;
+        let mut export = |job: &str| match job {
+            "frontpage" => {
+                version += 1;
+                Ok(vec![(
+                    "frontpage.xml".to_string(),
+                    format!("v{}", version).into_bytes(),
+                )])
+            }
+            _ => Err(Error::TimedOut),
+        };
+
+        assert_eq!(exports.run_due(&mut export), Err(Error::TimedOut));
+        assert_eq!(exports.read("/archive/2024-06-01/frontpage.xml", 0, -1).unwrap(), b"v1");
+
+        // Not due yet; the failed job is tried again
+        NOW_SECS.fetch_add(3600, Ordering::SeqCst);
+        assert!(exports.run_due(&mut export).is_err());
+        assert_eq!(exports.stats()["export_runs"], 3);
+
+        // Later the same day the copy is replaced, the next day kept apart
+        for _ in 0..4 {
+            NOW_SECS.fetch_add(6 * 3600, Ordering::SeqCst);
+            let _ = exports.run_due(&mut export);
+        }
+        let dates: Vec<String> = exports
+            .readdir("/archive")
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        assert_eq!(dates, ["2024-06-01", "2024-06-02"]);
+        assert_eq!(exports.read("/archive/2024-06-01/frontpage.xml", 0, -1).unwrap(), b"v2");
+
+        NOW_SECS.fetch_add(24 * 3600, Ordering::SeqCst);
+        let _ = exports.run_due(&mut export);
+        let dates: Vec<String> = exports
+            .readdir("/archive/")
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        assert_eq!(dates, ["2024-06-02", "2024-06-03"]);
+        assert_eq!(exports.stat("/archive/2024-06-01").unwrap_err(), Error::NotFound);
+        assert_eq!(
+            exports.readdir("/archive/2024-06-03/frontpage.xml").unwrap_err(),
+            Error::NotDirectory
+        );
+        assert!(is_archive_path("/archive") && !is_archive_path("/archives"));
+    }
+
+    // 2024-06-01T12:00:00Z
+    static VIEW_SECS: AtomicU64 = AtomicU64::new(1_717_243_200);
+
+    fn view_clock() -> Duration {
+        Duration::from_secs(VIEW_SECS.load(Ordering::SeqCst))
+    }
+
+    #[test]
+    fn test_archive_view() {
+        let archive = ArchiveView::with_clock(view_clock).per_refresh().keep(2);
+        archive
+            .record_dir("frontpage", [("1.md", b"first".as_slice())])
+            .unwrap();
+        VIEW_SECS.fetch_add(90, Ordering::SeqCst);
+        archive
+            .record_dir("frontpage", [("1.md", &b"second"[..]), ("2.md", &b"third"[..])])
+            .unwrap();
+        archive.record_file("frontpage.xml", b"<rss/>".as_slice()).unwrap();
+        assert!(archive.record_file("a/b", b"".as_slice()).is_err());
+
+        let names = |path| -> Vec<String> { archive.readdir(path).unwrap().into_iter().map(|e| e.name).collect() };
+        assert_eq!(names("/archive"), ["2024-06-01T120000Z", "2024-06-01T120130Z"]);
+        assert_eq!(names("/archive/2024-06-01T120130Z"), ["frontpage", "frontpage.xml"]);
+        assert_eq!(names("/archive/2024-06-01T120000Z/frontpage"), ["1.md"]);
+        assert_eq!(
+            archive
+                .read("/archive/2024-06-01T120130Z/frontpage/1.md", 0, -1)
+                .unwrap(),
+            b"second"
+        );
+        assert!(archive.stat("/archive/2024-06-01T120130Z/frontpage").unwrap().is_dir());
+        assert_eq!(
+            archive
+                .read("/archive/2024-06-01T120130Z/frontpage", 0, -1)
+                .unwrap_err(),
+            Error::IsDirectory
+        );
+
+        VIEW_SECS.fetch_add(90, Ordering::SeqCst);
+        archive.record_file("frontpage.xml", b"<rss/>".as_slice()).unwrap();
+        assert_eq!(archive.len(), 2);
+        assert_eq!(
+            archive.stat("/archive/2024-06-01T120000Z").unwrap_err(),
+            Error::NotFound
+        );
+        assert_eq!(archive.stats()["archive_bytes"], 23);
+    }
+}
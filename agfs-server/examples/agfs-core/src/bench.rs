@@ -0,0 +1,89 @@
+//! Synthetic filesystem for benchmarking the SDK bindings
+//!
+//! Enabled with the `bench` feature. Content is generated on demand, so the
+//! numbers measure the binding layer rather than a backend.
+
+use crate::error::{Error, Result};
+use crate::filesystem::FileSystem;
+use crate::types::FileInfo;
+
+/// Filesystem with one file of `file_size` bytes and one directory of `entries` files
+///
+/// ```text
+/// /
+/// ├── file       file_size bytes of a repeating pattern
+/// └── dir/
+///     ├── entry-0000000
+///     └── ...    entries files with metadata and an ETag
+/// ```
+#[derive(Clone, Debug)]
+pub struct BenchFs {
+    file_size: usize,
+    entries: usize,
+}
+
+impl Default for BenchFs {
+    fn default() -> Self {
+        Self::new(1 << 20, 1000)
+    }
+}
+
+impl BenchFs {
+    /// Create a filesystem serving `file_size` bytes at `/file` and `entries` files in `/dir`
+    pub fn new(file_size: usize, entries: usize) -> Self {
+        Self { file_size, entries }
+    }
+
+    fn entry(&self, i: usize) -> FileInfo {
+        FileInfo::file(format!("entry-{:07}", i), (i * 512) as i64, 0o644)
+            .with_mod_time(1_700_000_000 + i as i64)
+            .with_etag(format!("{:x}", i))
+    }
+}
+
+impl FileSystem for BenchFs {
+    fn name(&self) -> &str {
+        "benchfs"
+    }
+
+    fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+        if path != "/file" {
+            return Err(Error::NotFound);
+        }
+        let start = (offset.max(0) as usize).min(self.file_size);
+        let end = if size < 0 {
+            self.file_size
+        } else {
+            start.saturating_add(size as usize).min(self.file_size)
+        };
+        Ok((start..end).map(|i| i as u8).collect())
+    }
+
+    fn stat(&self, path: &str) -> Result<FileInfo> {
+        match path {
+            "/" => Ok(FileInfo::dir("", 0o755)),
+            "/file" => Ok(FileInfo::file("file", self.file_size as i64, 0o644)),
+            "/dir" => Ok(FileInfo::dir("dir", 0o755)),
+            _ => Err(Error::NotFound),
+        }
+    }
+
+    fn readdir(&self, path: &str) -> Result<Vec<FileInfo>> {
+        match path {
+            "/" => Ok(vec![
+                FileInfo::file("file", self.file_size as i64, 0o644),
+                FileInfo::dir("dir", 0o755),
+            ]),
+            "/dir" => Ok((0..self.entries).map(|i| self.entry(i)).collect()),
+            _ => Err(Error::NotFound),
+        }
+    }
+
+    fn readdir_page(&self, path: &str, offset: usize, limit: usize) -> Result<Vec<FileInfo>> {
+        if path != "/dir" {
+            return self.readdir(path).map(|all| all.into_iter().skip(offset).take(limit).collect());
+        }
+        let end = offset.saturating_add(limit).min(self.entries);
+        Ok((offset.min(end)..end).map(|i| self.entry(i)).collect())
+    }
+}
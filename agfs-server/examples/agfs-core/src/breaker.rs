@@ -0,0 +1,270 @@
+//! Circuit breaker for calls to a flaky upstream
+
+use crate::error::{Error, Result};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+/// Whether a [`CircuitBreaker`] lets calls through
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Calls go through; failures are being counted
+    Closed,
+    /// Calls fail fast until the cooldown has passed
+    Open,
+    /// The cooldown has passed; one probe call decides whether to close
+    HalfOpen,
+}
+
+impl CircuitState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CircuitState::Closed => "closed",
+            CircuitState::Open => "open",
+            CircuitState::HalfOpen => "half-open",
+        }
+    }
+}
+
+/// Whether `err` says the upstream is failing, rather than the request
+pub fn is_upstream_failure(err: &Error) -> bool {
+    matches!(err, Error::Io(_) | Error::TimedOut | Error::Busy { .. })
+}
+
+#[derive(Default)]
+struct Inner {
+    failures: u32,
+    opened_at: Option<Duration>,
+    probing: bool,
+    last_error: Option<String>,
+}
+
+/// Stops calling an upstream that keeps failing
+///
+/// When the upstream is down every read otherwise waits for its own
+/// timeout. After `threshold` failures in a row the breaker opens and calls
+/// fail at once with `Busy` (retry hint: the cooldown left) or get the
+/// fallback, e.g. cached content; after `cooldown` one call probes the
+/// upstream and closes the breaker if it succeeds. Report it from the
+/// plugin's `health` and `stats`:
+///
+/// ```ignore
+/// fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+///     let body = self.breaker.call_or(
+///         || self.fetch(path),
+///         |_| self.cache.get(path).ok_or(Error::NotFound),
+///     )?;
+///     slice(&body, offset, size)
+/// }
+///
+/// fn health(&self) -> Result<()> {
+///     self.breaker.health()
+/// }
+/// ```
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    clock: fn() -> Duration,
+    trips: fn(&Error) -> bool,
+    inner: Mutex<Inner>,
+}
+
+impl CircuitBreaker {
+    /// Create a breaker using the system clock
+    ///
+    /// WASM plugins have no clock of their own; use [`CircuitBreaker::with_clock`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self::with_clock(threshold, cooldown, || {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+        })
+    }
+
+    /// Create a breaker reading the time from `clock`
+    ///
+    /// `clock` may count from any fixed point but must not go backwards.
+    pub fn with_clock(threshold: u32, cooldown: Duration, clock: fn() -> Duration) -> Self {
+        Self {
+            threshold: threshold.max(1),
+            cooldown,
+            clock,
+            trips: is_upstream_failure,
+            inner: Mutex::new(Inner::default()),
+        }
+    }
+
+    /// Count the errors `trips` accepts as failures instead of
+    /// [`is_upstream_failure`] ones
+    pub fn trip_on(mut self, trips: fn(&Error) -> bool) -> Self {
+        self.trips = trips;
+        self
+    }
+
+    fn inner(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn state_of(&self, inner: &Inner, now: Duration) -> CircuitState {
+        match inner.opened_at {
+            None => CircuitState::Closed,
+            Some(opened) if now < opened + self.cooldown => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+
+    pub fn state(&self) -> CircuitState {
+        self.state_of(&self.inner(), (self.clock)())
+    }
+
+    /// Whether a call may go to the upstream now; a `HalfOpen` breaker lets
+    /// one probe through at a time
+    ///
+    /// Report the outcome with [`record`](Self::record).
+    pub fn allow(&self) -> Result<()> {
+        let now = (self.clock)();
+        let mut inner = self.inner();
+        match self.state_of(&inner, now) {
+            CircuitState::Closed => Ok(()),
+            CircuitState::HalfOpen if !inner.probing => {
+                inner.probing = true;
+                Ok(())
+            }
+            state => {
+                let left = match (state, inner.opened_at) {
+                    (CircuitState::Open, Some(opened)) => opened + self.cooldown - now,
+                    _ => Duration::ZERO,
+                };
+                Err(Error::Busy {
+                    retry_after_ms: Some(left.as_millis() as u64),
+                })
+            }
+        }
+    }
+
+    /// Count the outcome of a call [`allow`](Self::allow) let through
+    pub fn record<T>(&self, result: &Result<T>) {
+        let mut inner = self.inner();
+        inner.probing = false;
+        match result {
+            Err(err) if (self.trips)(err) => {
+                inner.failures = inner.failures.saturating_add(1);
+                inner.last_error = Some(err.to_string());
+                if inner.failures >= self.threshold || inner.opened_at.is_some() {
+                    inner.opened_at = Some((self.clock)());
+                }
+            }
+            _ => {
+                inner.failures = 0;
+                inner.opened_at = None;
+            }
+        }
+    }
+
+    /// Run `op` unless the breaker is open, counting its outcome
+    pub fn call<T>(&self, op: impl FnOnce() -> Result<T>) -> Result<T> {
+        self.allow()?;
+        let result = op();
+        self.record(&result);
+        result
+    }
+
+    /// [`call`](Self::call), answering from `fallback` when the breaker is
+    /// open or `op` fails with an upstream failure
+    pub fn call_or<T>(&self, op: impl FnOnce() -> Result<T>, fallback: impl FnOnce(&Error) -> Result<T>) -> Result<T> {
+        match self.call(op) {
+            Err(err) if (self.trips)(&err) => fallback(&err).map_err(|_| err),
+            result => result,
+        }
+    }
+
+    /// `Ok` unless the breaker is open, for the plugin's `health`
+    pub fn health(&self) -> Result<()> {
+        let inner = self.inner();
+        match self.state_of(&inner, (self.clock)()) {
+            CircuitState::Closed => Ok(()),
+            state => Err(Error::Io(format!(
+                "upstream circuit {} after {} failures: {}",
+                state.as_str(),
+                inner.failures,
+                inner.last_error.as_deref().unwrap_or("unknown error")
+            ))),
+        }
+    }
+
+    /// State, consecutive failures and last failure, for the plugin's `stats`
+    pub fn stats(&self) -> serde_json::Value {
+        let inner = self.inner();
+        serde_json::json!({
+            "state": self.state_of(&inner, (self.clock)()).as_str(),
+            "failures": inner.failures,
+            "last_error": inner.last_error,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static NOW_MS: AtomicU64 = AtomicU64::new(0);
+
+    fn fake_clock() -> Duration {
+        Duration::from_millis(NOW_MS.load(Ordering::SeqCst))
+    }
+
+    fn down() -> Result<()> {
+        Err(Error::Io("connection refused".to_string()))
+    }
+
+    #[test]
+    fn test_opens_after_threshold_and_probes() {
+        let breaker = CircuitBreaker::with_clock(2, Duration::from_millis(500), fake_clock);
+        let start = NOW_MS.load(Ordering::SeqCst);
+
+        assert!(breaker.call(down).is_err());
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        // Errors about the request itself do not count
+        assert_eq!(breaker.call(|| Err::<(), _>(Error::NotFound)), Err(Error::NotFound));
+        assert!(breaker.call(down).is_err());
+        assert!(breaker.call(down).is_err());
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(breaker.health().is_err());
+        assert_eq!(breaker.stats()["state"], "open");
+
+        NOW_MS.store(start + 200, Ordering::SeqCst);
+        assert_eq!(
+            breaker.call(|| Ok(())),
+            Err(Error::Busy {
+                retry_after_ms: Some(300)
+            })
+        );
+
+        // A failed probe opens it again
+        NOW_MS.store(start + 500, Ordering::SeqCst);
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        assert!(breaker.allow().is_ok());
+        assert!(breaker.allow().is_err());
+        breaker.record(&down());
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        NOW_MS.store(start + 1000, Ordering::SeqCst);
+        assert_eq!(breaker.call(|| Ok(7)), Ok(7));
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert!(breaker.health().is_ok());
+    }
+
+    #[test]
+    fn test_call_or_falls_back() {
+        let breaker = CircuitBreaker::with_clock(1, Duration::from_secs(60), fake_clock);
+        assert_eq!(breaker.call_or(|| Err(Error::TimedOut), |_| Ok("cached")), Ok("cached"));
+        assert_eq!(breaker.call_or(|| Ok("fresh"), |_| Ok("cached")), Ok("cached"));
+        assert_eq!(
+            breaker.call_or(|| Ok("fresh"), |_| Err::<&str, _>(Error::NotFound)),
+            Err(Error::Busy {
+                retry_after_ms: Some(60_000)
+            })
+        );
+    }
+}
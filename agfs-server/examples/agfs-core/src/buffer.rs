@@ -0,0 +1,192 @@
+//! Write coalescing for handles over slow storage
+
+use crate::error::{Error, Result};
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// Contiguous run of buffered bytes for one handle
+struct Run {
+    offset: i64,
+    data: Vec<u8>,
+}
+
+/// Coalesces small sequential writes per handle into larger upstream writes
+///
+/// Tools that write 4KB at a time turn into one request per block against
+/// object stores or WebDAV. Route `handle_write_at` (and `handle_write`,
+/// passing the handle's cursor) through [`WriteBuffer::write_at`]; bytes are
+/// passed on once `threshold` accumulate, when a write is not contiguous with
+/// the buffered run, or when the plugin calls [`WriteBuffer::flush`] from
+/// `handle_sync` and `close_handle`:
+///
+/// ```ignore
+/// fn handle_write_at(&self, id: i64, data: &[u8], offset: i64) -> Result<usize> {
+///     self.buffer.write_at(id, data, offset, |off, chunk| self.upload(id, off, chunk))
+/// }
+///
+/// fn handle_sync(&self, id: i64) -> Result<()> {
+///     self.buffer.flush(id, |off, chunk| self.upload(id, off, chunk))
+/// }
+/// ```
+///
+/// Buffered bytes are invisible to reads until flushed, so flush before
+/// reading or stat-ing through the same handle. As with a page cache, an
+/// upstream failure surfaces on the write, sync or close that flushes it.
+/// Writing through a [`ConflictTracker`](crate::conflict::ConflictTracker)
+/// keeps a file changed upstream in the meantime from being overwritten.
+pub struct WriteBuffer {
+    threshold: usize,
+    runs: Mutex<HashMap<i64, Run>>,
+}
+
+/// Pass all of `data` to `write`, which may accept it in pieces
+fn write_all<F>(offset: i64, data: &[u8], write: &mut F) -> Result<()>
+where
+    F: FnMut(i64, &[u8]) -> Result<usize>,
+{
+    let mut done = 0;
+    while done < data.len() {
+        match write(offset + done as i64, &data[done..])? {
+            0 => return Err(Error::Io("short write".to_string())),
+            n => done += n,
+        }
+    }
+    Ok(())
+}
+
+impl WriteBuffer {
+    /// Buffer up to `threshold` bytes per handle before writing upstream
+    pub fn new(threshold: usize) -> Self {
+        Self {
+            threshold,
+            runs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn runs(&self) -> MutexGuard<'_, HashMap<i64, Run>> {
+        self.runs.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Buffer `data` for handle `id` at `offset`, writing upstream through
+    /// `write(offset, bytes) -> bytes_written` as needed
+    ///
+    /// `write` runs without the buffer's lock held.
+    pub fn write_at<F>(&self, id: i64, data: &[u8], offset: i64, mut write: F) -> Result<usize>
+    where
+        F: FnMut(i64, &[u8]) -> Result<usize>,
+    {
+        let (stale, full) = {
+            let mut runs = self.runs();
+            let stale = match runs.get_mut(&id) {
+                Some(run) if run.offset + run.data.len() as i64 == offset => {
+                    run.data.extend_from_slice(data);
+                    None
+                }
+                _ => {
+                    let run = Run {
+                        offset,
+                        data: data.to_vec(),
+                    };
+                    runs.insert(id, run)
+                }
+            };
+            let full = match runs.get(&id) {
+                Some(run) if run.data.len() >= self.threshold => runs.remove(&id),
+                _ => None,
+            };
+            (stale, full)
+        };
+
+        // A failed flush drops the run, like a page cache reporting EIO once
+        for run in stale.into_iter().chain(full) {
+            write_all(run.offset, &run.data, &mut write)?;
+        }
+        Ok(data.len())
+    }
+
+    /// Write out anything buffered for handle `id`
+    pub fn flush<F>(&self, id: i64, mut write: F) -> Result<()>
+    where
+        F: FnMut(i64, &[u8]) -> Result<usize>,
+    {
+        let run = self.runs().remove(&id);
+        match run {
+            Some(run) => write_all(run.offset, &run.data, &mut write),
+            None => Ok(()),
+        }
+    }
+
+    /// Number of bytes buffered for handle `id`
+    pub fn pending(&self, id: i64) -> usize {
+        self.runs().get(&id).map_or(0, |run| run.data.len())
+    }
+
+    /// Drop anything buffered for handle `id` without writing it
+    pub fn discard(&self, id: i64) {
+        self.runs().remove(&id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sequential_writes_coalesce() {
+        let buffer = WriteBuffer::new(10);
+        let mut upstream: Vec<(i64, Vec<u8>)> = Vec::new();
+        let mut sink = |offset: i64, data: &[u8]| {
+            upstream.push((offset, data.to_vec()));
+            Ok(data.len())
+        };
+
+        for i in 0..3 {
+            assert_eq!(buffer.write_at(1, b"abcd", i * 4, &mut sink).unwrap(), 4);
+        }
+        buffer.write_at(1, b"xy", 12, &mut sink).unwrap();
+        assert_eq!(buffer.pending(1), 2);
+        buffer.flush(1, &mut sink).unwrap();
+        buffer.flush(1, &mut sink).unwrap();
+
+        assert_eq!(upstream, [(0, b"abcdabcdabcd".to_vec()), (12, b"xy".to_vec())]);
+    }
+
+    #[test]
+    fn test_non_contiguous_write_flushes_run() {
+        let buffer = WriteBuffer::new(100);
+        let mut upstream = Vec::new();
+        let mut sink = |offset: i64, data: &[u8]| {
+            upstream.push((offset, data.to_vec()));
+            Ok(data.len())
+        };
+
+        buffer.write_at(1, b"aa", 0, &mut sink).unwrap();
+        buffer.write_at(2, b"zz", 50, &mut sink).unwrap();
+        buffer.write_at(1, b"bb", 10, &mut sink).unwrap();
+        assert_eq!(buffer.pending(1), 2);
+        assert_eq!(buffer.pending(2), 2);
+        assert_eq!(upstream, [(0, b"aa".to_vec())]);
+    }
+
+    #[test]
+    fn test_short_and_failed_upstream_writes() {
+        let buffer = WriteBuffer::new(4);
+        let mut upstream = Vec::new();
+        buffer
+            .write_at(1, b"abcdef", 0, |offset, data: &[u8]| {
+                // Accept at most two bytes per call
+                let n = data.len().min(2);
+                upstream.push((offset, data[..n].to_vec()));
+                Ok(n)
+            })
+            .unwrap();
+        assert_eq!(upstream.len(), 3);
+        assert_eq!(upstream[2], (4, b"ef".to_vec()));
+
+        buffer.write_at(1, b"g", 6, |_, _| Ok(1)).unwrap();
+        let err = buffer.flush(1, |_, _| Err(Error::Io("down".to_string())));
+        assert!(matches!(err, Err(Error::Io(_))));
+        assert_eq!(buffer.pending(1), 0);
+        assert!(matches!(buffer.flush(1, |_, _| Ok(0)), Ok(())));
+    }
+}
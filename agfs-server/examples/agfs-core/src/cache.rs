@@ -0,0 +1,746 @@
+//! Caching helpers for plugins backed by slow or remote storage
+
+use crate::error::{Error, Result};
+use crate::filesystem::{FileSystem, HandleFS, StreamFS, UploadFS};
+use crate::types::{
+    Advice, Capabilities, Config, ConfigParameter, FileInfo, FsOp, FsSchema, OpenFlag, UploadSession, WarmupProgress,
+    WriteFlag,
+};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+/// Entries kept before expired ones are swept (and, if still full, dropped)
+const DEFAULT_CAPACITY: usize = 4096;
+
+/// How long [`CachedFs::new`] remembers that a path is missing
+const DEFAULT_MISSING_TTL: Duration = Duration::from_secs(5);
+
+/// Rendered files kept before the cache is emptied
+const DEFAULT_RENDER_CAPACITY: usize = 1024;
+
+/// Remembers `NotFound` results for a short time
+///
+/// Shell completion and `ls` of paths that do not exist repeat the same
+/// failing lookups; for a remote backend each one is a round trip. Wrap
+/// the plugin in [`CachedFs`], or wrap lookups in [`NegativeCache::lookup`]
+/// and call [`NegativeCache::invalidate`] whenever the plugin creates a path:
+///
+/// ```ignore
+/// fn stat(&self, path: &str) -> Result<FileInfo> {
+///     self.missing.lookup(path, || self.remote.stat(path))
+/// }
+///
+/// fn create(&mut self, path: &str) -> Result<()> {
+///     self.missing.invalidate(path);
+///     self.remote.create(path)
+/// }
+/// ```
+pub struct NegativeCache {
+    ttl: Duration,
+    capacity: usize,
+    clock: fn() -> Duration,
+    entries: Mutex<HashMap<String, Duration>>,
+}
+
+impl NegativeCache {
+    /// Create a cache using the system clock
+    ///
+    /// WASM plugins have no clock of their own; use [`NegativeCache::with_clock`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn new(ttl: Duration) -> Self {
+        Self::with_clock(ttl, || {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+        })
+    }
+
+    /// Create a cache reading the time from `clock`
+    ///
+    /// `clock` may count from any fixed point but must not go backwards.
+    pub fn with_clock(ttl: Duration, clock: fn() -> Duration) -> Self {
+        Self {
+            ttl,
+            capacity: DEFAULT_CAPACITY,
+            clock,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Limit the number of remembered paths
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    fn entries(&self) -> std::sync::MutexGuard<'_, HashMap<String, Duration>> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Returns true if `path` was recently found missing
+    pub fn is_missing(&self, path: &str) -> bool {
+        let now = (self.clock)();
+        let mut entries = self.entries();
+        match entries.get(path) {
+            Some(&expires) if now < expires => true,
+            Some(_) => {
+                entries.remove(path);
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Remember that `path` does not exist
+    pub fn insert(&self, path: &str) {
+        if self.capacity == 0 {
+            return;
+        }
+        let now = (self.clock)();
+        let mut entries = self.entries();
+        if entries.len() >= self.capacity && !entries.contains_key(path) {
+            entries.retain(|_, expires| now < *expires);
+            if entries.len() >= self.capacity {
+                entries.clear();
+            }
+        }
+        entries.insert(path.to_string(), now + self.ttl);
+    }
+
+    /// Forget `path` and its ancestors, which exist once `path` is created
+    pub fn invalidate(&self, path: &str) {
+        let mut entries = self.entries();
+        let mut current = path.trim_end_matches('/');
+        loop {
+            entries.remove(if current.is_empty() { "/" } else { current });
+            match current.rfind('/') {
+                Some(i) => current = &current[..i],
+                None => break,
+            }
+        }
+    }
+
+    /// Forget everything
+    pub fn clear(&self) {
+        self.entries().clear();
+    }
+
+    /// Run `op` unless `path` is known to be missing, caching a `NotFound` result
+    pub fn lookup<T, F>(&self, path: &str, op: F) -> Result<T>
+    where
+        F: FnOnce() -> Result<T>,
+    {
+        if self.is_missing(path) {
+            return Err(Error::NotFound);
+        }
+        let result = op();
+        if matches!(result, Err(Error::NotFound)) {
+            self.insert(path);
+        }
+        result
+    }
+}
+
+/// Filesystem wrapper answering repeated lookups of missing paths from a
+/// [`NegativeCache`]
+///
+/// `read`, `stat`, `readlink` and listings that fail with `NotFound` are not
+/// passed to the plugin again until the TTL runs out. Paths this wrapper
+/// creates, by `write`, `create`, `mkdir`, `symlink`, `rename` or opening a
+/// handle or stream, are forgotten first; calls that can change the tree in
+/// other ways (`ctl`, `maintain`, batches, sessions, uploads) forget
+/// everything. Paths created behind the plugin's back show up once the TTL
+/// runs out.
+///
+/// ```ignore
+/// type Exported = CachedFs<S3FS>;
+/// export_plugin!(Exported);
+/// ```
+pub struct CachedFs<F> {
+    inner: F,
+    missing: NegativeCache,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<F: Default> Default for CachedFs<F> {
+    fn default() -> Self {
+        Self::new(F::default())
+    }
+}
+
+impl<F> CachedFs<F> {
+    /// Wrap `inner`, remembering missing paths for a few seconds by the
+    /// system clock
+    ///
+    /// WASM plugins have no clock of their own; use [`CachedFs::with_clock`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn new(inner: F) -> Self {
+        Self::with_cache(inner, NegativeCache::new(DEFAULT_MISSING_TTL))
+    }
+
+    /// Wrap `inner`, remembering missing paths for a few seconds by `clock`
+    pub fn with_clock(inner: F, clock: fn() -> Duration) -> Self {
+        Self::with_cache(inner, NegativeCache::with_clock(DEFAULT_MISSING_TTL, clock))
+    }
+
+    /// Wrap `inner`, remembering missing paths in `missing`
+    pub fn with_cache(inner: F, missing: NegativeCache) -> Self {
+        Self { inner, missing }
+    }
+
+    /// The wrapped filesystem
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    /// The paths known to be missing
+    pub fn missing(&self) -> &NegativeCache {
+        &self.missing
+    }
+}
+
+impl<F: FileSystem> FileSystem for CachedFs<F> {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn readme(&self) -> &str {
+        self.inner.readme()
+    }
+
+    fn config_params(&self) -> Vec<ConfigParameter> {
+        self.inner.config_params()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    fn schema(&self) -> FsSchema {
+        self.inner.schema()
+    }
+
+    fn validate(&self, config: &Config) -> Result<()> {
+        self.inner.validate(config)
+    }
+
+    fn initialize(&mut self, config: &Config) -> Result<()> {
+        self.missing.clear();
+        self.inner.initialize(config)
+    }
+
+    fn shutdown(&mut self) -> Result<()> {
+        self.inner.shutdown()
+    }
+
+    fn stats(&self) -> serde_json::Value {
+        self.inner.stats()
+    }
+
+    fn health(&self) -> Result<()> {
+        self.inner.health()
+    }
+
+    fn ctl(&mut self, command: &str) -> Result<()> {
+        self.missing.clear();
+        self.inner.ctl(command)
+    }
+
+    fn maintain(&mut self) -> Result<()> {
+        self.missing.clear();
+        self.inner.maintain()
+    }
+
+    fn warmup(&mut self, budget: Duration) -> Result<WarmupProgress> {
+        self.inner.warmup(budget)
+    }
+
+    fn export_state(&self) -> Result<Vec<u8>> {
+        self.inner.export_state()
+    }
+
+    fn import_state(&mut self, state: &[u8]) -> Result<()> {
+        self.missing.clear();
+        self.inner.import_state(state)
+    }
+
+    fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+        self.missing.lookup(path, || self.inner.read(path, offset, size))
+    }
+
+    fn poll_read(&self, path: &str, offset: i64, size: i64, timeout: Duration) -> Result<Vec<u8>> {
+        self.missing.lookup(path, || self.inner.poll_read(path, offset, size, timeout))
+    }
+
+    fn stat(&self, path: &str) -> Result<FileInfo> {
+        self.missing.lookup(path, || self.inner.stat(path))
+    }
+
+    fn readdir(&self, path: &str) -> Result<Vec<FileInfo>> {
+        self.missing.lookup(path, || self.inner.readdir(path))
+    }
+
+    fn readdir_page(&self, path: &str, offset: usize, limit: usize) -> Result<Vec<FileInfo>> {
+        self.missing.lookup(path, || self.inner.readdir_page(path, offset, limit))
+    }
+
+    fn readdir_filtered(&self, path: &str, glob: &str, limit: usize) -> Result<Vec<FileInfo>> {
+        self.missing.lookup(path, || self.inner.readdir_filtered(path, glob, limit))
+    }
+
+    fn walk(&self, path: &str, depth: usize) -> Result<Vec<(String, FileInfo)>> {
+        self.missing.lookup(path, || self.inner.walk(path, depth))
+    }
+
+    fn dir_generation(&self, path: &str) -> u64 {
+        self.inner.dir_generation(path)
+    }
+
+    fn write(&mut self, path: &str, data: &[u8], offset: i64, flags: WriteFlag) -> Result<i64> {
+        self.missing.invalidate(path);
+        self.inner.write(path, data, offset, flags)
+    }
+
+    fn create(&mut self, path: &str) -> Result<()> {
+        self.missing.invalidate(path);
+        self.inner.create(path)
+    }
+
+    fn mkdir(&mut self, path: &str, perm: u32) -> Result<()> {
+        self.missing.invalidate(path);
+        self.inner.mkdir(path, perm)
+    }
+
+    fn remove(&mut self, path: &str) -> Result<()> {
+        self.inner.remove(path)
+    }
+
+    fn remove_all(&mut self, path: &str) -> Result<()> {
+        self.inner.remove_all(path)
+    }
+
+    fn rename(&mut self, old_path: &str, new_path: &str) -> Result<()> {
+        self.missing.invalidate(new_path);
+        self.inner.rename(old_path, new_path)
+    }
+
+    fn chmod(&mut self, path: &str, mode: u32) -> Result<()> {
+        self.inner.chmod(path, mode)
+    }
+
+    fn symlink(&mut self, target: &str, link_path: &str) -> Result<()> {
+        self.missing.invalidate(link_path);
+        self.inner.symlink(target, link_path)
+    }
+
+    fn readlink(&self, path: &str) -> Result<String> {
+        self.missing.lookup(path, || self.inner.readlink(path))
+    }
+
+    fn get_xattr(&self, path: &str, name: &str) -> Result<Vec<u8>> {
+        self.inner.get_xattr(path, name)
+    }
+
+    fn set_xattr(&mut self, path: &str, name: &str, value: &[u8]) -> Result<()> {
+        self.inner.set_xattr(path, name, value)
+    }
+
+    fn list_xattr(&self, path: &str) -> Result<Vec<String>> {
+        self.inner.list_xattr(path)
+    }
+
+    fn read_if_changed(&self, path: &str, etag: &str) -> Result<Option<Vec<u8>>> {
+        self.missing.lookup(path, || self.inner.read_if_changed(path, etag))
+    }
+
+    fn read_many(&self, paths: &[&str]) -> Vec<Result<Vec<u8>>> {
+        self.inner.read_many(paths)
+    }
+
+    fn write_if(&mut self, path: &str, data: &[u8], expected_etag: &str) -> Result<i64> {
+        self.missing.invalidate(path);
+        self.inner.write_if(path, data, expected_etag)
+    }
+
+    fn batch(&mut self, ops: Vec<FsOp>) -> Vec<Result<()>> {
+        self.missing.clear();
+        self.inner.batch(ops)
+    }
+
+    fn atomic_batch(&self) -> bool {
+        self.inner.atomic_batch()
+    }
+
+    fn begin_write_session(&mut self) -> Result<()> {
+        self.inner.begin_write_session()
+    }
+
+    fn commit_session(&mut self, message: &str) -> Result<()> {
+        self.missing.clear();
+        self.inner.commit_session(message)
+    }
+
+    fn abort_session(&mut self) -> Result<()> {
+        self.inner.abort_session()
+    }
+
+    fn advise(&self, path: &str, offset: i64, len: i64, advice: Advice) -> Result<()> {
+        self.inner.advise(path, offset, len, advice)
+    }
+}
+
+impl<F: HandleFS> HandleFS for CachedFs<F> {
+    fn open_handle(&mut self, path: &str, flags: OpenFlag, mode: u32) -> Result<i64> {
+        self.missing.invalidate(path);
+        self.inner.open_handle(path, flags, mode)
+    }
+
+    fn handle_read(&mut self, id: i64, buf: &mut [u8]) -> Result<usize> {
+        self.inner.handle_read(id, buf)
+    }
+
+    fn handle_read_at(&self, id: i64, buf: &mut [u8], offset: i64) -> Result<usize> {
+        self.inner.handle_read_at(id, buf, offset)
+    }
+
+    fn handle_write(&mut self, id: i64, data: &[u8]) -> Result<usize> {
+        self.inner.handle_write(id, data)
+    }
+
+    fn handle_write_at(&self, id: i64, data: &[u8], offset: i64) -> Result<usize> {
+        self.inner.handle_write_at(id, data, offset)
+    }
+
+    fn handle_seek(&mut self, id: i64, offset: i64, whence: i32) -> Result<i64> {
+        self.inner.handle_seek(id, offset, whence)
+    }
+
+    fn handle_sync(&self, id: i64) -> Result<()> {
+        self.inner.handle_sync(id)
+    }
+
+    fn handle_stat(&self, id: i64) -> Result<FileInfo> {
+        self.inner.handle_stat(id)
+    }
+
+    fn handle_info(&self, id: i64) -> Result<(String, OpenFlag)> {
+        self.inner.handle_info(id)
+    }
+
+    fn close_handle(&mut self, id: i64) -> Result<()> {
+        self.inner.close_handle(id)
+    }
+}
+
+impl<F: StreamFS> StreamFS for CachedFs<F> {
+    fn open_stream(&mut self, path: &str) -> Result<i64> {
+        self.missing.invalidate(path);
+        self.inner.open_stream(path)
+    }
+
+    fn stream_read(&mut self, id: i64, buf: &mut [u8]) -> Result<Option<usize>> {
+        self.inner.stream_read(id, buf)
+    }
+
+    fn stream_write(&mut self, id: i64, data: &[u8]) -> Result<usize> {
+        self.inner.stream_write(id, data)
+    }
+
+    fn close_stream(&mut self, id: i64) -> Result<()> {
+        self.inner.close_stream(id)
+    }
+}
+
+impl<F: UploadFS> UploadFS for CachedFs<F> {
+    fn begin_upload(&mut self, path: &str) -> Result<UploadSession> {
+        self.inner.begin_upload(path)
+    }
+
+    fn upload_status(&self, id: &str) -> Result<UploadSession> {
+        self.inner.upload_status(id)
+    }
+
+    fn upload_append(&mut self, id: &str, offset: u64, data: &[u8]) -> Result<UploadSession> {
+        self.inner.upload_append(id, offset, data)
+    }
+
+    fn commit_upload(&mut self, id: &str) -> Result<()> {
+        self.missing.clear();
+        self.inner.commit_upload(id)
+    }
+
+    fn abort_upload(&mut self, id: &str) -> Result<()> {
+        self.inner.abort_upload(id)
+    }
+
+    fn list_uploads(&self) -> Result<Vec<UploadSession>> {
+        self.inner.list_uploads()
+    }
+}
+
+/// Rendered content kept until the data it was rendered from changes
+///
+/// Plugins that render files from upstream items (Markdown from a story, a
+/// table from rows) are asked for the same content by every read of the
+/// file. [`RenderCache::render`] renders once per version of the source
+/// item; the version is anything the caller derives from it that changes
+/// with what the rendering uses: an `updated_at`, an ETag, or a
+/// [`fingerprint`] of the fields:
+///
+/// ```ignore
+/// fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+///     let story = self.story(path)?;
+///     let version = cache::fingerprint(&(&story.title, story.score, story.comments));
+///     let content = self.rendered.render(story.id, version, || self.to_markdown(&story))?;
+///     Ok(read_range(&content, offset, size))
+/// }
+/// ```
+pub struct RenderCache<K> {
+    capacity: usize,
+    entries: Mutex<HashMap<K, Rendered>>,
+}
+
+struct Rendered {
+    version: u64,
+    content: Arc<[u8]>,
+}
+
+impl<K: Eq + Hash> Default for RenderCache<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Eq + Hash> RenderCache<K> {
+    pub fn new() -> Self {
+        Self {
+            capacity: DEFAULT_RENDER_CAPACITY,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Limit the number of rendered files kept
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    fn entries(&self) -> std::sync::MutexGuard<'_, HashMap<K, Rendered>> {
+        self.entries.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// The content rendered for `key` at `version`, if cached
+    pub fn get(&self, key: &K, version: u64) -> Option<Arc<[u8]>> {
+        match self.entries().get(key) {
+            Some(rendered) if rendered.version == version => Some(rendered.content.clone()),
+            _ => None,
+        }
+    }
+
+    /// The content of `key` at `version`, calling `render` unless it is cached
+    ///
+    /// Errors of `render` are returned and not cached.
+    pub fn render<F, T>(&self, key: K, version: u64, render: F) -> Result<Arc<[u8]>>
+    where
+        F: FnOnce() -> Result<T>,
+        T: Into<Vec<u8>>,
+    {
+        if let Some(content) = self.get(&key, version) {
+            return Ok(content);
+        }
+        // Not locked while rendering, which may render other keys
+        let content: Arc<[u8]> = render()?.into().into();
+        if self.capacity > 0 {
+            let mut entries = self.entries();
+            if entries.len() >= self.capacity && !entries.contains_key(&key) {
+                entries.clear();
+            }
+            entries.insert(
+                key,
+                Rendered {
+                    version,
+                    content: content.clone(),
+                },
+            );
+        }
+        Ok(content)
+    }
+
+    /// Forget what was rendered for `key`
+    pub fn invalidate(&self, key: &K) {
+        self.entries().remove(key);
+    }
+
+    /// Forget everything
+    pub fn clear(&self) {
+        self.entries().clear();
+    }
+}
+
+/// A version for [`RenderCache`] from the values a rendering depends on
+///
+/// Only stable within one run of the plugin; do not persist it.
+pub fn fingerprint<T: Hash + ?Sized>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::{Cell, RefCell};
+    use std::collections::HashSet;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static NOW_MS: AtomicU64 = AtomicU64::new(0);
+
+    fn fake_clock() -> Duration {
+        Duration::from_millis(NOW_MS.load(Ordering::SeqCst))
+    }
+
+    #[test]
+    fn test_lookup_caches_not_found_until_ttl() {
+        let cache = NegativeCache::with_clock(Duration::from_millis(100), fake_clock);
+        let calls = Cell::new(0);
+        let stat = || {
+            calls.set(calls.get() + 1);
+            Err::<(), _>(Error::NotFound)
+        };
+
+        let start = NOW_MS.load(Ordering::SeqCst);
+        assert_eq!(cache.lookup("/nope", stat), Err(Error::NotFound));
+        assert_eq!(cache.lookup("/nope", stat), Err(Error::NotFound));
+        assert_eq!(calls.get(), 1);
+
+        NOW_MS.store(start + 100, Ordering::SeqCst);
+        assert!(!cache.is_missing("/nope"));
+        assert_eq!(cache.lookup("/nope", stat), Err(Error::NotFound));
+        assert_eq!(calls.get(), 2);
+
+        // Other errors and successes are not cached
+        assert_eq!(cache.lookup("/denied", || Err::<(), _>(Error::PermissionDenied)), Err(Error::PermissionDenied));
+        assert!(!cache.is_missing("/denied"));
+    }
+
+    #[test]
+    fn test_invalidate_clears_ancestors() {
+        let cache = NegativeCache::new(Duration::from_secs(60));
+        for path in ["/", "/a", "/a/b", "/a/b/c", "/a/x"] {
+            cache.insert(path);
+        }
+
+        cache.invalidate("/a/b/");
+        assert!(!cache.is_missing("/a/b"));
+        assert!(!cache.is_missing("/a"));
+        assert!(!cache.is_missing("/"));
+        assert!(cache.is_missing("/a/b/c"));
+        assert!(cache.is_missing("/a/x"));
+    }
+
+    #[test]
+    fn test_capacity_bounds_entries() {
+        let cache = NegativeCache::new(Duration::from_secs(60)).with_capacity(2);
+        cache.insert("/a");
+        cache.insert("/b");
+        cache.insert("/c");
+        assert!(cache.is_missing("/c"));
+        assert!(cache.entries().len() <= 2);
+
+        let disabled = NegativeCache::new(Duration::from_secs(60)).with_capacity(0);
+        disabled.insert("/a");
+        assert!(!disabled.is_missing("/a"));
+    }
+
+    /// Directories only, counting the lookups that reach it
+    #[derive(Default)]
+    struct Remote {
+        dirs: RefCell<HashSet<String>>,
+        lookups: Cell<usize>,
+    }
+
+    impl FileSystem for Remote {
+        fn name(&self) -> &str {
+            "remote"
+        }
+
+        fn stat(&self, path: &str) -> Result<FileInfo> {
+            self.lookups.set(self.lookups.get() + 1);
+            match self.dirs.borrow().contains(path) {
+                true => Ok(FileInfo::dir(&path[1..], 0o755)),
+                false => Err(Error::NotFound),
+            }
+        }
+
+        fn readdir(&self, path: &str) -> Result<Vec<FileInfo>> {
+            self.stat(path).map(|_| Vec::new())
+        }
+
+        fn mkdir(&mut self, path: &str, _perm: u32) -> Result<()> {
+            self.dirs.borrow_mut().insert(path.to_string());
+            Ok(())
+        }
+
+        fn rename(&mut self, old_path: &str, new_path: &str) -> Result<()> {
+            let mut dirs = self.dirs.borrow_mut();
+            if !dirs.remove(old_path) {
+                return Err(Error::NotFound);
+            }
+            dirs.insert(new_path.to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_cached_fs_skips_repeated_missing_lookups() {
+        let mut fs = CachedFs::new(Remote::default());
+        assert_eq!(fs.stat("/a").unwrap_err(), Error::NotFound);
+        assert_eq!(fs.stat("/a").unwrap_err(), Error::NotFound);
+        assert_eq!(fs.readdir("/a").unwrap_err(), Error::NotFound);
+        assert_eq!(fs.inner().lookups.get(), 1);
+
+        fs.mkdir("/a", 0o755).unwrap();
+        assert!(fs.stat("/a").is_ok());
+        assert!(fs.readdir("/a").is_ok());
+        assert_eq!(fs.inner().lookups.get(), 3);
+
+        assert_eq!(fs.stat("/b").unwrap_err(), Error::NotFound);
+        fs.rename("/a", "/b").unwrap();
+        assert!(fs.stat("/b").is_ok());
+        assert_eq!(fs.stat("/a").unwrap_err(), Error::NotFound);
+
+        assert!(fs.missing().is_missing("/a"));
+        fs.ctl("sync").unwrap_err();
+        assert!(!fs.missing().is_missing("/a"));
+    }
+
+    #[test]
+    fn test_render_cache_rerenders_on_new_version() {
+        let cache = RenderCache::new();
+        let renders = Cell::new(0);
+        let render = |text: &str| {
+            renders.set(renders.get() + 1);
+            Ok::<_, Error>(text.to_string())
+        };
+
+        let version = fingerprint(&("title", 10));
+        assert_eq!(&*cache.render(1, version, || render("v1")).unwrap(), b"v1");
+        assert_eq!(&*cache.render(1, version, || render("v1")).unwrap(), b"v1");
+        assert_eq!(renders.get(), 1);
+
+        let changed = fingerprint(&("title", 11));
+        assert_ne!(changed, version);
+        assert_eq!(&*cache.render(1, changed, || render("v2")).unwrap(), b"v2");
+        assert!(cache.get(&1, version).is_none());
+        assert_eq!(renders.get(), 2);
+
+        assert_eq!(cache.render(2, version, || Err::<String, _>(Error::TimedOut)), Err(Error::TimedOut));
+        assert!(cache.get(&2, version).is_none());
+
+        cache.invalidate(&1);
+        assert!(cache.get(&1, changed).is_none());
+    }
+}
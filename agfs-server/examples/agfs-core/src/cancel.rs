@@ -0,0 +1,62 @@
+//! Cooperative cancellation for long-running operations
+
+use crate::error::{Error, Result};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Flag the host raises when the caller of an operation goes away
+///
+/// Clones share the flag. Long reads and searches should call
+/// [`CancellationToken::check`] between chunks so an interrupted `cat` stops
+/// the work instead of running it to completion:
+///
+/// ```ignore
+/// for chunk in remote.chunks(path)? {
+///     cancel.check()?;
+///     out.extend_from_slice(&chunk?);
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Create a token that has not been cancelled
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation of every operation holding a clone of this token
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Release);
+    }
+
+    /// Whether cancellation has been requested
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Acquire)
+    }
+
+    /// Return `Error::Cancelled` once cancellation has been requested
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            Err(Error::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clones_share_cancellation() {
+        let token = CancellationToken::new();
+        let job = token.clone();
+        assert!(job.check().is_ok());
+
+        token.cancel();
+        assert!(job.is_cancelled());
+        assert_eq!(job.check(), Err(Error::Cancelled));
+    }
+}
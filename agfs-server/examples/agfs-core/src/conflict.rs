@@ -0,0 +1,266 @@
+//! Conflict handling for buffered writes
+//!
+//! A plugin buffering writes (see [`WriteBuffer`](crate::buffer::WriteBuffer))
+//! passes them upstream some time after the file was opened. If someone
+//! else changed the file upstream in between, writing it would silently
+//! throw their change away. A [`ConflictTracker`] remembers the upstream
+//! version of each file when a handle opens it and, on the handle's first
+//! upstream write, compares it with the current version and applies the
+//! mount's [`ConflictPolicy`], set by the `conflict_policy` config key:
+//!
+//! ```ignore
+//! fn open_handle(&mut self, path: &str, flags: OpenFlag, _mode: u32) -> Result<i64> {
+//!     let id = self.next_id();
+//!     self.conflicts.opened(id, path, self.api.etag(path)?);
+//!     Ok(id)
+//! }
+//!
+//! fn handle_write_at(&self, id: i64, data: &[u8], offset: i64) -> Result<usize> {
+//!     self.buffer.write_at(id, data, offset, |off, chunk| {
+//!         match self.conflicts.target(id, |path| self.api.etag(path))? {
+//!             Target::Path(path) => self.api.put_range(&path, off, chunk),
+//!             Target::Discard => Ok(chunk.len()),
+//!         }
+//!     })
+//! }
+//! ```
+//!
+//! Call [`ConflictTracker::closed`] from `close_handle` after the final flush.
+
+use crate::archive::timestamp;
+use crate::error::{Error, Result};
+use crate::types::{Config, ConfigParameter};
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+/// Config key selecting the [`ConflictPolicy`]
+pub const CONFLICT_POLICY_CONFIG_KEY: &str = "conflict_policy";
+
+/// What to do with buffered writes to a file that changed upstream since it
+/// was opened
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Write anyway, replacing the upstream change
+    Ours,
+    /// Keep the upstream change and drop the buffered writes
+    Theirs,
+    /// Fail the write with `Stale`
+    Fail,
+    /// Write to a conflicted copy next to the file, `<path>.conflict-<timestamp>`
+    #[default]
+    Rename,
+}
+
+impl ConflictPolicy {
+    /// The policy under [`CONFLICT_POLICY_CONFIG_KEY`], [`Rename`](Self::Rename)
+    /// if the key is absent
+    pub fn from_config(config: &Config) -> Result<Self> {
+        match config.get_str(CONFLICT_POLICY_CONFIG_KEY) {
+            None | Some("") => Ok(Self::default()),
+            Some(policy) => match policy.to_ascii_lowercase().as_str() {
+                "ours" => Ok(Self::Ours),
+                "theirs" => Ok(Self::Theirs),
+                "fail" => Ok(Self::Fail),
+                "rename" | "rename-conflicted-copy" => Ok(Self::Rename),
+                _ => Err(Error::InvalidInput(format!(
+                    "invalid {}: {} (expected ours, theirs, fail or rename)",
+                    CONFLICT_POLICY_CONFIG_KEY, policy
+                ))),
+            },
+        }
+    }
+
+    /// The parameter to list in the plugin's `config_params`
+    pub fn config_param() -> ConfigParameter {
+        ConfigParameter::new(
+            CONFLICT_POLICY_CONFIG_KEY,
+            "string",
+            false,
+            "rename",
+            "When a file changed upstream under buffered writes: ours, theirs, fail or rename (write a conflicted copy)",
+        )
+    }
+}
+
+/// Where a handle's upstream writes go
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Target {
+    /// Write to this path: the file itself, or its conflicted copy
+    Path(String),
+    /// Drop the writes; the upstream change wins
+    Discard,
+}
+
+struct Tracked {
+    path: String,
+    etag: String,
+    target: Option<Target>,
+}
+
+/// Upstream versions of the files open for writing, checked under a
+/// [`ConflictPolicy`] before the first write of each handle
+pub struct ConflictTracker {
+    policy: ConflictPolicy,
+    clock: fn() -> Duration,
+    handles: Mutex<HashMap<i64, Tracked>>,
+    conflicts: Mutex<u64>,
+}
+
+impl ConflictTracker {
+    /// Create a tracker using the system clock to name conflicted copies
+    ///
+    /// WASM plugins have no clock of their own; use `agfs_wasm_ffi::conflict::tracker`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn new(policy: ConflictPolicy) -> Self {
+        Self::with_clock(policy, || {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+        })
+    }
+
+    /// Create a tracker reading the time since the Unix epoch from `clock`
+    pub fn with_clock(policy: ConflictPolicy, clock: fn() -> Duration) -> Self {
+        Self {
+            policy,
+            clock,
+            handles: Mutex::new(HashMap::new()),
+            conflicts: Mutex::new(0),
+        }
+    }
+
+    pub fn policy(&self) -> ConflictPolicy {
+        self.policy
+    }
+
+    fn handles(&self) -> MutexGuard<'_, HashMap<i64, Tracked>> {
+        self.handles.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Remember that handle `id` writes `path`, whose upstream version is
+    /// `etag` (empty if the file does not exist yet)
+    pub fn opened(&self, id: i64, path: impl Into<String>, etag: impl Into<String>) {
+        let tracked = Tracked {
+            path: path.into(),
+            etag: etag.into(),
+            target: None,
+        };
+        self.handles().insert(id, tracked);
+    }
+
+    /// Where the writes of handle `id` go
+    ///
+    /// The first call asks `current` for the file's upstream version and
+    /// applies the policy if it moved on since [`opened`](Self::opened);
+    /// later calls return the same target without asking again. With
+    /// [`ConflictPolicy::Fail`] the write fails with `Stale`, and so do later
+    /// ones while the versions differ.
+    pub fn target<F>(&self, id: i64, current: F) -> Result<Target>
+    where
+        F: FnOnce(&str) -> Result<String>,
+    {
+        let (path, etag) = {
+            let handles = self.handles();
+            let tracked = handles
+                .get(&id)
+                .ok_or_else(|| Error::InvalidInput(format!("handle {} is not tracked", id)))?;
+            if let Some(target) = &tracked.target {
+                return Ok(target.clone());
+            }
+            (tracked.path.clone(), tracked.etag.clone())
+        };
+
+        // Ask upstream without the lock held
+        let target = match current(&path)? == etag {
+            true => Target::Path(path),
+            false => {
+                *self.conflicts.lock().unwrap_or_else(PoisonError::into_inner) += 1;
+                match self.policy {
+                    ConflictPolicy::Ours => Target::Path(path),
+                    ConflictPolicy::Theirs => Target::Discard,
+                    ConflictPolicy::Fail => return Err(Error::Stale),
+                    ConflictPolicy::Rename => Target::Path(conflicted_copy(&path, (self.clock)())),
+                }
+            }
+        };
+        if let Some(tracked) = self.handles().get_mut(&id) {
+            tracked.target = Some(target.clone());
+        }
+        Ok(target)
+    }
+
+    /// Forget handle `id`
+    pub fn closed(&self, id: i64) {
+        self.handles().remove(&id);
+    }
+
+    /// Conflicts found and handles open, for the plugin's `stats`
+    pub fn stats(&self) -> serde_json::Value {
+        serde_json::json!({
+            "conflicts": *self.conflicts.lock().unwrap_or_else(PoisonError::into_inner),
+            "conflict_tracked_handles": self.handles().len(),
+        })
+    }
+}
+
+/// The conflicted copy of `path` made at `now`
+pub fn conflicted_copy(path: &str, now: Duration) -> String {
+    format!("{}.conflict-{}", path.trim_end_matches('/'), timestamp(now))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn fixed_clock() -> Duration {
+        // 2024-06-01T12:00:00Z
+        Duration::from_secs(1_717_243_200)
+    }
+
+    #[test]
+    fn test_policy_from_config() {
+        let config = |policy: &str| Config::from(json!({ CONFLICT_POLICY_CONFIG_KEY: policy }));
+        assert_eq!(
+            ConflictPolicy::from_config(&Config::default()),
+            Ok(ConflictPolicy::Rename)
+        );
+        assert_eq!(
+            ConflictPolicy::from_config(&config("Theirs")),
+            Ok(ConflictPolicy::Theirs)
+        );
+        assert!(ConflictPolicy::from_config(&config("merge")).is_err());
+    }
+
+    #[test]
+    fn test_conflict_targets() {
+        let upstream = |_: &str| Ok("v2".to_string());
+        let target = |policy| {
+            let tracker = ConflictTracker::with_clock(policy, fixed_clock);
+            tracker.opened(1, "/notes.md", "v1");
+            tracker.opened(2, "/todo.md", "v2");
+            assert_eq!(tracker.target(2, upstream), Ok(Target::Path("/todo.md".to_string())));
+            let first = tracker.target(1, upstream);
+            // Later writes of the handle go the same way without asking again
+            if first.is_ok() {
+                assert_eq!(tracker.target(1, |_| unreachable!()), first);
+            }
+            first
+        };
+
+        assert_eq!(target(ConflictPolicy::Ours), Ok(Target::Path("/notes.md".to_string())));
+        assert_eq!(target(ConflictPolicy::Theirs), Ok(Target::Discard));
+        assert_eq!(target(ConflictPolicy::Fail), Err(Error::Stale));
+        assert_eq!(
+            target(ConflictPolicy::Rename),
+            Ok(Target::Path("/notes.md.conflict-2024-06-01T120000Z".to_string()))
+        );
+
+        let tracker = ConflictTracker::with_clock(ConflictPolicy::Fail, fixed_clock);
+        assert!(tracker.target(3, upstream).is_err());
+        tracker.opened(3, "/new.md", "");
+        tracker.closed(3);
+        assert_eq!(tracker.stats()["conflict_tracked_handles"], 0);
+    }
+}
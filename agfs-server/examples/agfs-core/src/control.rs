@@ -0,0 +1,833 @@
+//! Standard `/.agfs/` control files
+//!
+//! Every exported plugin gets the same introspection layout next to its own
+//! files, so users learn it once:
+//!
+//! | Path            | Content                                                  |
+//! |-----------------|----------------------------------------------------------|
+//! | `/.agfs/readme` | [`FileSystem::readme`]                                   |
+//! | `/.agfs/schema.json` | [`FileSystem::schema`] plus these files, as JSON    |
+//! | `/.agfs/config` | config passed to `initialize`, credentials redacted      |
+//! | `/.agfs/stats`  | SDK call counters plus [`FileSystem::stats`], as JSON    |
+//! | `/.agfs/health` | `ok`, or `error: ...` when [`FileSystem::health`] fails  |
+//! | `/.agfs/log`    | latest output of [`eprintln!`](crate::eprintln), credentials redacted |
+//! | `/.agfs/ctl`    | write-only; each line written goes to [`FileSystem::ctl`] |
+//!
+//! While [`FileSystem::warmup`] is in progress, or after it failed, `health`
+//! has a second line saying so.
+//!
+//! `export_plugin!` wraps the plugin in [`ControlFs`], which answers these
+//! paths itself and passes everything else through. A plugin path of its own
+//! under `/.agfs` is therefore hidden.
+
+use crate::error::{Error, Result};
+use crate::filesystem::{
+    filter_entries, read_range, reject_batch, split_batch, walk_tree, FileSystem, HandleFS, StreamFS, UploadFS,
+};
+use crate::log;
+use crate::redact::{is_secret_key, redact, REDACTED};
+use crate::types::{
+    Advice, Capabilities, Config, ConfigParameter, FileInfo, FsOp, FsSchema, OpenFlag, PathSchema, UploadSession,
+    WarmupProgress, WriteFlag,
+};
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Directory holding the control files
+pub const CONTROL_DIR: &str = "/.agfs";
+
+/// Command file; writing to it calls [`FileSystem::ctl`]
+pub const CTL_PATH: &str = "/.agfs/ctl";
+
+const DIR_NAME: &str = ".agfs";
+
+/// The control files, in listing order
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ControlFile {
+    Readme,
+    Schema,
+    Config,
+    Stats,
+    Health,
+    Log,
+    Ctl,
+}
+
+impl ControlFile {
+    const ALL: [ControlFile; 7] =
+        [Self::Readme, Self::Schema, Self::Config, Self::Stats, Self::Health, Self::Log, Self::Ctl];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Readme => "readme",
+            Self::Schema => "schema.json",
+            Self::Config => "config",
+            Self::Stats => "stats",
+            Self::Health => "health",
+            Self::Log => "log",
+            Self::Ctl => "ctl",
+        }
+    }
+
+    fn mode(self) -> u32 {
+        match self {
+            Self::Ctl => 0o200,
+            _ => 0o444,
+        }
+    }
+}
+
+/// Where a path points relative to the control directory
+enum Route {
+    Plugin,
+    Dir,
+    File(ControlFile),
+    Missing,
+}
+
+fn route(path: &str) -> Route {
+    let Some(rest) = path.strip_prefix(CONTROL_DIR) else {
+        return Route::Plugin;
+    };
+    match rest.trim_end_matches('/') {
+        "" => Route::Dir,
+        name if rest.starts_with('/') => {
+            match ControlFile::ALL.iter().find(|f| f.name() == &name[1..]) {
+                Some(file) => Route::File(*file),
+                None => Route::Missing,
+            }
+        }
+        _ => Route::Plugin, // e.g. `/.agfsrc`
+    }
+}
+
+/// Whether `path` is answered by [`ControlFs`] instead of the plugin
+pub fn is_control_path(path: &str) -> bool {
+    !matches!(route(path), Route::Plugin)
+}
+
+fn is_root(path: &str) -> bool {
+    path.is_empty() || path == "/"
+}
+
+/// Filesystem wrapper serving the `/.agfs/` control files
+///
+/// The export macros apply it to every plugin; use it directly only to test
+/// the control files of a filesystem.
+pub struct ControlFs<F> {
+    inner: F,
+    config: Value,
+    reads: AtomicU64,
+    writes: AtomicU64,
+    errors: AtomicU64,
+    /// Outcome of the latest `warmup` slice
+    warmup: Option<Result<WarmupProgress>>,
+}
+
+impl<F: Default> Default for ControlFs<F> {
+    fn default() -> Self {
+        Self::new(F::default())
+    }
+}
+
+impl<F> ControlFs<F> {
+    pub fn new(inner: F) -> Self {
+        Self {
+            inner,
+            config: json!({}),
+            reads: AtomicU64::new(0),
+            writes: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            warmup: None,
+        }
+    }
+
+    /// The wrapped filesystem
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    fn count<T>(&self, counter: &AtomicU64, result: Result<T>) -> Result<T> {
+        counter.fetch_add(1, Ordering::Relaxed);
+        if result.is_err() {
+            self.errors.fetch_add(1, Ordering::Relaxed);
+        }
+        result
+    }
+}
+
+impl<F: FileSystem> ControlFs<F> {
+    fn content(&self, file: ControlFile) -> Result<Vec<u8>> {
+        let text = match file {
+            ControlFile::Readme => redact(self.inner.readme()).into_owned(),
+            ControlFile::Schema => pretty(&serde_json::to_value(self.schema()).expect("schema serializes")),
+            ControlFile::Config => pretty(&self.config),
+            ControlFile::Stats => {
+                let mut stats = json!({
+                    "name": self.inner.name(),
+                    "reads": self.reads.load(Ordering::Relaxed),
+                    "writes": self.writes.load(Ordering::Relaxed),
+                    "errors": self.errors.load(Ordering::Relaxed),
+                });
+                if let Value::Object(extra) = self.inner.stats() {
+                    stats.as_object_mut().expect("built as object").extend(extra);
+                }
+                pretty(&stats)
+            }
+            ControlFile::Health => {
+                let mut text = match self.inner.health() {
+                    Ok(()) => "ok\n".to_string(),
+                    Err(e) => format!("error: {}\n", redact(&e.to_string())),
+                };
+                match &self.warmup {
+                    Some(Ok(progress)) if !progress.is_complete() => {
+                        text += &format!("warming up: {}/{}\n", progress.done, progress.total);
+                    }
+                    Some(Err(e)) => text += &format!("warmup failed: {}\n", redact(&e.to_string())),
+                    _ => {}
+                }
+                text
+            }
+            ControlFile::Log => redact(&String::from_utf8_lossy(&log::contents())).into_owned(),
+            ControlFile::Ctl => return Err(Error::PermissionDenied),
+        };
+        Ok(text.into_bytes())
+    }
+
+    fn control_info(&self, file: ControlFile) -> FileInfo {
+        let size = self.content(file).map_or(0, |data| data.len() as i64);
+        FileInfo::file(file.name(), size, file.mode())
+    }
+
+    /// Run each non-empty line of `data` as a command
+    fn run_ctl(&mut self, data: &[u8]) -> Result<i64> {
+        let text = std::str::from_utf8(data).map_err(|_| Error::InvalidInput("ctl commands must be UTF-8".to_string()))?;
+        for command in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            self.inner.ctl(command)?;
+        }
+        Ok(data.len() as i64)
+    }
+}
+
+/// Config as shown in `/.agfs/config`, with credentials replaced
+fn redacted_config(config: &Config, params: &[ConfigParameter]) -> Value {
+    let fields = config.inner.iter().map(|(key, value)| {
+        let declared = params.iter().any(|p| p.name == *key && p.param_type == "secret");
+        let value = match value {
+            _ if declared || is_secret_key(key) => Value::from(REDACTED),
+            Value::String(s) => Value::from(redact(s).into_owned()),
+            other => other.clone(),
+        };
+        (key.clone(), value)
+    });
+    Value::Object(fields.collect())
+}
+
+fn pretty(value: &Value) -> String {
+    serde_json::to_string_pretty(value).expect("JSON values serialize") + "\n"
+}
+
+impl<F: FileSystem> FileSystem for ControlFs<F> {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn readme(&self) -> &str {
+        self.inner.readme()
+    }
+
+    fn config_params(&self) -> Vec<ConfigParameter> {
+        self.inner.config_params()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    /// The plugin's schema followed by the control files
+    fn schema(&self) -> FsSchema {
+        let mut schema = self.inner.schema();
+        schema.paths.extend([
+            PathSchema::dir(CONTROL_DIR, "Standard control files of every AGFS plugin"),
+            PathSchema::file("/.agfs/readme", "Plugin documentation").format("text/markdown"),
+            PathSchema::file("/.agfs/schema.json", "This description of the tree").format("application/json"),
+            PathSchema::file("/.agfs/config", "Mount config, credentials redacted").format("application/json"),
+            PathSchema::file("/.agfs/stats", "Call counters and plugin statistics").format("application/json"),
+            PathSchema::file("/.agfs/health", "`ok`, or `error: ` and the reason").format("text/plain"),
+            PathSchema::file("/.agfs/log", "Latest debug output of the plugin").format("text/plain"),
+            PathSchema::file(CTL_PATH, "Write-only; each line written is run as a command").writable(),
+        ]);
+        schema
+    }
+
+    fn validate(&self, config: &Config) -> Result<()> {
+        self.inner.validate(config)
+    }
+
+    fn initialize(&mut self, config: &Config) -> Result<()> {
+        self.config = redacted_config(config, &self.inner.config_params());
+        self.inner.initialize(config)
+    }
+
+    fn shutdown(&mut self) -> Result<()> {
+        self.inner.shutdown()
+    }
+
+    fn stats(&self) -> Value {
+        self.inner.stats()
+    }
+
+    fn health(&self) -> Result<()> {
+        self.inner.health()
+    }
+
+    fn ctl(&mut self, command: &str) -> Result<()> {
+        self.inner.ctl(command)
+    }
+
+    fn maintain(&mut self) -> Result<()> {
+        self.inner.maintain()
+    }
+
+    fn warmup(&mut self, budget: Duration) -> Result<WarmupProgress> {
+        let result = self.inner.warmup(budget);
+        self.warmup = Some(result.clone());
+        result
+    }
+
+    fn export_state(&self) -> Result<Vec<u8>> {
+        self.inner.export_state()
+    }
+
+    fn import_state(&mut self, state: &[u8]) -> Result<()> {
+        self.inner.import_state(state)
+    }
+
+    fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+        match route(path) {
+            Route::Plugin => self.count(&self.reads, self.inner.read(path, offset, size)),
+            Route::Dir => Err(Error::IsDirectory),
+            Route::File(file) => Ok(read_range(&self.content(file)?, offset, size)),
+            Route::Missing => Err(Error::NotFound),
+        }
+    }
+
+    fn poll_read(&self, path: &str, offset: i64, size: i64, timeout: Duration) -> Result<Vec<u8>> {
+        match route(path) {
+            Route::Plugin => self.count(&self.reads, self.inner.poll_read(path, offset, size, timeout)),
+            _ => self.read(path, offset, size),
+        }
+    }
+
+    fn stat(&self, path: &str) -> Result<FileInfo> {
+        match route(path) {
+            Route::Plugin => self.inner.stat(path),
+            Route::Dir => Ok(FileInfo::dir(DIR_NAME, 0o555)),
+            Route::File(file) => Ok(self.control_info(file)),
+            Route::Missing => Err(Error::NotFound),
+        }
+    }
+
+    /// The root listing starts with `.agfs`
+    fn readdir(&self, path: &str) -> Result<Vec<FileInfo>> {
+        match route(path) {
+            Route::Plugin if is_root(path) => {
+                let mut entries = vec![FileInfo::dir(DIR_NAME, 0o555)];
+                entries.extend(self.inner.readdir(path)?.into_iter().filter(|e| e.name != DIR_NAME));
+                Ok(entries)
+            }
+            Route::Plugin => self.inner.readdir(path),
+            Route::Dir => Ok(ControlFile::ALL.iter().map(|f| self.control_info(*f)).collect()),
+            Route::File(_) => Err(Error::NotDirectory),
+            Route::Missing => Err(Error::NotFound),
+        }
+    }
+
+    fn readdir_page(&self, path: &str, offset: usize, limit: usize) -> Result<Vec<FileInfo>> {
+        match route(path) {
+            // `.agfs` takes the first slot, the plugin's entries shift by one
+            Route::Plugin if is_root(path) && offset == 0 && limit > 0 => {
+                let mut entries = vec![FileInfo::dir(DIR_NAME, 0o555)];
+                entries.extend(self.inner.readdir_page(path, 0, limit - 1)?);
+                Ok(entries)
+            }
+            Route::Plugin if is_root(path) && offset > 0 => self.inner.readdir_page(path, offset - 1, limit),
+            Route::Plugin => self.inner.readdir_page(path, offset, limit),
+            _ => Ok(self.readdir(path)?.into_iter().skip(offset).take(limit).collect()),
+        }
+    }
+
+    fn readdir_filtered(&self, path: &str, glob: &str, limit: usize) -> Result<Vec<FileInfo>> {
+        match route(path) {
+            Route::Plugin if is_root(path) => {
+                let mut entries = filter_entries(vec![FileInfo::dir(DIR_NAME, 0o555)], glob, 0);
+                // A limit of 0 means no limit, so stop rather than pass one down
+                if limit > 0 && entries.len() == limit {
+                    return Ok(entries);
+                }
+                let rest = limit.saturating_sub(entries.len());
+                entries.extend(self.inner.readdir_filtered(path, glob, rest)?);
+                Ok(entries)
+            }
+            Route::Plugin => self.inner.readdir_filtered(path, glob, limit),
+            _ => Ok(filter_entries(self.readdir(path)?, glob, limit)),
+        }
+    }
+
+    fn walk(&self, path: &str, depth: usize) -> Result<Vec<(String, FileInfo)>> {
+        match route(path) {
+            Route::Plugin if is_root(path) => {
+                let mut entries = vec![(CONTROL_DIR.to_string(), FileInfo::dir(DIR_NAME, 0o555))];
+                if depth != 1 {
+                    entries.extend(walk_tree(self, CONTROL_DIR, 1)?);
+                }
+                let inner = self.inner.walk(path, depth)?;
+                entries.extend(inner.into_iter().filter(|(entry, _)| !is_control_path(entry)));
+                Ok(entries)
+            }
+            Route::Plugin => self.inner.walk(path, depth),
+            _ => walk_tree(self, path, depth),
+        }
+    }
+
+    fn dir_generation(&self, path: &str) -> u64 {
+        // The control directory's stats change on every call
+        match route(path) {
+            Route::Plugin => self.inner.dir_generation(path),
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, path: &str, data: &[u8], offset: i64, flags: WriteFlag) -> Result<i64> {
+        match route(path) {
+            Route::Plugin => {
+                let result = self.inner.write(path, data, offset, flags);
+                self.count(&self.writes, result)
+            }
+            Route::File(ControlFile::Ctl) => self.run_ctl(data),
+            _ => Err(Error::PermissionDenied),
+        }
+    }
+
+    fn create(&mut self, path: &str) -> Result<()> {
+        match route(path) {
+            Route::Plugin => self.inner.create(path),
+            _ => Err(Error::PermissionDenied),
+        }
+    }
+
+    fn mkdir(&mut self, path: &str, perm: u32) -> Result<()> {
+        match route(path) {
+            Route::Plugin => self.inner.mkdir(path, perm),
+            _ => Err(Error::PermissionDenied),
+        }
+    }
+
+    fn remove(&mut self, path: &str) -> Result<()> {
+        match route(path) {
+            Route::Plugin => self.inner.remove(path),
+            _ => Err(Error::PermissionDenied),
+        }
+    }
+
+    fn remove_all(&mut self, path: &str) -> Result<()> {
+        match route(path) {
+            Route::Plugin => self.inner.remove_all(path),
+            _ => Err(Error::PermissionDenied),
+        }
+    }
+
+    fn rename(&mut self, old_path: &str, new_path: &str) -> Result<()> {
+        match (route(old_path), route(new_path)) {
+            (Route::Plugin, Route::Plugin) => self.inner.rename(old_path, new_path),
+            _ => Err(Error::PermissionDenied),
+        }
+    }
+
+    fn chmod(&mut self, path: &str, mode: u32) -> Result<()> {
+        match route(path) {
+            Route::Plugin => self.inner.chmod(path, mode),
+            _ => Err(Error::PermissionDenied),
+        }
+    }
+
+    fn symlink(&mut self, target: &str, link_path: &str) -> Result<()> {
+        match route(link_path) {
+            Route::Plugin => self.inner.symlink(target, link_path),
+            _ => Err(Error::PermissionDenied),
+        }
+    }
+
+    fn readlink(&self, path: &str) -> Result<String> {
+        match route(path) {
+            Route::Plugin => self.inner.readlink(path),
+            Route::Missing => Err(Error::NotFound),
+            _ => Err(Error::InvalidInput("not a symlink".to_string())),
+        }
+    }
+
+    fn get_xattr(&self, path: &str, name: &str) -> Result<Vec<u8>> {
+        match route(path) {
+            Route::Plugin => self.inner.get_xattr(path, name),
+            Route::Missing => Err(Error::NotFound),
+            _ => Err(Error::NoAttribute),
+        }
+    }
+
+    fn set_xattr(&mut self, path: &str, name: &str, value: &[u8]) -> Result<()> {
+        match route(path) {
+            Route::Plugin => self.inner.set_xattr(path, name, value),
+            _ => Err(Error::PermissionDenied),
+        }
+    }
+
+    fn list_xattr(&self, path: &str) -> Result<Vec<String>> {
+        match route(path) {
+            Route::Plugin => self.inner.list_xattr(path),
+            Route::Missing => Err(Error::NotFound),
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    fn write_if(&mut self, path: &str, data: &[u8], expected_etag: &str) -> Result<i64> {
+        match route(path) {
+            Route::Plugin => {
+                let result = self.inner.write_if(path, data, expected_etag);
+                self.count(&self.writes, result)
+            }
+            _ => Err(Error::PermissionDenied),
+        }
+    }
+
+    fn read_if_changed(&self, path: &str, etag: &str) -> Result<Option<Vec<u8>>> {
+        match route(path) {
+            Route::Plugin => self.count(&self.reads, self.inner.read_if_changed(path, etag)),
+            _ => self.read(path, 0, -1).map(Some),
+        }
+    }
+
+    /// Control files are driven one write at a time, so a batch touching
+    /// them is rejected as a whole
+    fn batch(&mut self, ops: Vec<FsOp>) -> Vec<Result<()>> {
+        if let Some(i) = ops.iter().position(|op| op.paths().into_iter().any(is_control_path)) {
+            return reject_batch(ops.len(), i, Error::PermissionDenied);
+        }
+        let results = self.inner.batch(ops);
+        results.into_iter().map(|result| self.count(&self.writes, result)).collect()
+    }
+
+    fn atomic_batch(&self) -> bool {
+        self.inner.atomic_batch()
+    }
+
+    fn begin_write_session(&mut self) -> Result<()> {
+        self.inner.begin_write_session()
+    }
+
+    fn commit_session(&mut self, message: &str) -> Result<()> {
+        let result = self.inner.commit_session(message);
+        self.count(&self.writes, result)
+    }
+
+    fn abort_session(&mut self) -> Result<()> {
+        self.inner.abort_session()
+    }
+
+    fn read_many(&self, paths: &[&str]) -> Vec<Result<Vec<u8>>> {
+        split_batch(
+            paths,
+            |path| is_control_path(path).then(|| self.read(path, 0, -1)),
+            |rest| self.inner.read_many(rest).into_iter().map(|r| self.count(&self.reads, r)).collect(),
+        )
+    }
+
+    fn advise(&self, path: &str, offset: i64, len: i64, advice: Advice) -> Result<()> {
+        match route(path) {
+            Route::Plugin => self.inner.advise(path, offset, len, advice),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Control files cannot be opened as handles; read and write them by path
+impl<F: HandleFS> HandleFS for ControlFs<F> {
+    fn open_handle(&mut self, path: &str, flags: OpenFlag, mode: u32) -> Result<i64> {
+        match route(path) {
+            Route::Plugin => self.inner.open_handle(path, flags, mode),
+            Route::Missing => Err(Error::NotFound),
+            _ => Err(Error::PermissionDenied),
+        }
+    }
+
+    fn handle_read(&mut self, id: i64, buf: &mut [u8]) -> Result<usize> {
+        self.inner.handle_read(id, buf)
+    }
+
+    fn handle_read_at(&self, id: i64, buf: &mut [u8], offset: i64) -> Result<usize> {
+        self.inner.handle_read_at(id, buf, offset)
+    }
+
+    fn handle_write(&mut self, id: i64, data: &[u8]) -> Result<usize> {
+        self.inner.handle_write(id, data)
+    }
+
+    fn handle_write_at(&self, id: i64, data: &[u8], offset: i64) -> Result<usize> {
+        self.inner.handle_write_at(id, data, offset)
+    }
+
+    fn handle_seek(&mut self, id: i64, offset: i64, whence: i32) -> Result<i64> {
+        self.inner.handle_seek(id, offset, whence)
+    }
+
+    fn handle_sync(&self, id: i64) -> Result<()> {
+        self.inner.handle_sync(id)
+    }
+
+    fn handle_stat(&self, id: i64) -> Result<FileInfo> {
+        self.inner.handle_stat(id)
+    }
+
+    fn handle_info(&self, id: i64) -> Result<(String, OpenFlag)> {
+        self.inner.handle_info(id)
+    }
+
+    fn close_handle(&mut self, id: i64) -> Result<()> {
+        self.inner.close_handle(id)
+    }
+}
+
+impl<F: StreamFS> StreamFS for ControlFs<F> {
+    fn open_stream(&mut self, path: &str) -> Result<i64> {
+        match route(path) {
+            Route::Plugin => self.inner.open_stream(path),
+            Route::Missing => Err(Error::NotFound),
+            _ => Err(Error::PermissionDenied),
+        }
+    }
+
+    fn stream_read(&mut self, id: i64, buf: &mut [u8]) -> Result<Option<usize>> {
+        self.inner.stream_read(id, buf)
+    }
+
+    fn stream_write(&mut self, id: i64, data: &[u8]) -> Result<usize> {
+        self.inner.stream_write(id, data)
+    }
+
+    fn close_stream(&mut self, id: i64) -> Result<()> {
+        self.inner.close_stream(id)
+    }
+}
+
+impl<F: UploadFS> UploadFS for ControlFs<F> {
+    fn begin_upload(&mut self, path: &str) -> Result<UploadSession> {
+        match route(path) {
+            Route::Plugin => self.inner.begin_upload(path),
+            _ => Err(Error::PermissionDenied),
+        }
+    }
+
+    fn upload_status(&self, id: &str) -> Result<UploadSession> {
+        self.inner.upload_status(id)
+    }
+
+    fn upload_append(&mut self, id: &str, offset: u64, data: &[u8]) -> Result<UploadSession> {
+        let result = self.inner.upload_append(id, offset, data);
+        self.count(&self.writes, result)
+    }
+
+    fn commit_upload(&mut self, id: &str) -> Result<()> {
+        self.inner.commit_upload(id)
+    }
+
+    fn abort_upload(&mut self, id: &str) -> Result<()> {
+        self.inner.abort_upload(id)
+    }
+
+    fn list_uploads(&self) -> Result<Vec<UploadSession>> {
+        self.inner.list_uploads()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct TestFS {
+        commands: Vec<String>,
+        down: bool,
+        warmed: u64,
+    }
+
+    impl FileSystem for TestFS {
+        fn name(&self) -> &str {
+            "testfs"
+        }
+
+        fn readme(&self) -> &str {
+            "# TestFS\n"
+        }
+
+        fn config_params(&self) -> Vec<ConfigParameter> {
+            vec![ConfigParameter::new("passphrase", "secret", false, "", "Unlocks the store")]
+        }
+
+        fn schema(&self) -> FsSchema {
+            FsSchema::new("Test tree").path(PathSchema::file("/hello", "Greeting").format("text/plain"))
+        }
+
+        fn stats(&self) -> Value {
+            json!({"commands": self.commands.len()})
+        }
+
+        fn health(&self) -> Result<()> {
+            match self.down {
+                true => Err(Error::Io("backend unreachable".to_string())),
+                false => Ok(()),
+            }
+        }
+
+        fn ctl(&mut self, command: &str) -> Result<()> {
+            match command {
+                "down" => self.down = true,
+                "flush" => {}
+                _ => return Err(Error::InvalidInput(format!("unknown command: {}", command))),
+            }
+            self.commands.push(command.to_string());
+            Ok(())
+        }
+
+        /// Three slices, then a failure if `down`
+        fn warmup(&mut self, _budget: Duration) -> Result<WarmupProgress> {
+            if self.down {
+                return Err(Error::Io("backend unreachable".to_string()));
+            }
+            self.warmed += 1;
+            Ok(WarmupProgress::new(self.warmed, 3))
+        }
+
+        fn read(&self, path: &str, _offset: i64, _size: i64) -> Result<Vec<u8>> {
+            match path {
+                "/hello" => Ok(b"hi".to_vec()),
+                _ => Err(Error::NotFound),
+            }
+        }
+
+        fn stat(&self, path: &str) -> Result<FileInfo> {
+            match path {
+                "/" => Ok(FileInfo::dir("", 0o755)),
+                "/hello" => Ok(FileInfo::file("hello", 2, 0o644)),
+                _ => Err(Error::NotFound),
+            }
+        }
+
+        fn readdir(&self, _path: &str) -> Result<Vec<FileInfo>> {
+            Ok(vec![FileInfo::file("hello", 2, 0o644)])
+        }
+    }
+
+    fn text(fs: &ControlFs<TestFS>, path: &str) -> String {
+        String::from_utf8(fs.read(path, 0, -1).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_listing() {
+        let fs = ControlFs::new(TestFS::default());
+        let names: Vec<String> = fs.readdir("/").unwrap().into_iter().map(|e| e.name).collect();
+        assert_eq!(names, [".agfs", "hello"]);
+        let names: Vec<String> = fs.readdir("/.agfs").unwrap().into_iter().map(|e| e.name).collect();
+        assert_eq!(names, ["readme", "schema.json", "config", "stats", "health", "log", "ctl"]);
+
+        assert_eq!(fs.readdir_page("/", 0, 1).unwrap()[0].name, ".agfs");
+        assert_eq!(fs.readdir_page("/", 1, 10).unwrap()[0].name, "hello");
+        let names = |glob, limit| -> Vec<String> {
+            fs.readdir_filtered("/", glob, limit).unwrap().into_iter().map(|e| e.name).collect()
+        };
+        assert_eq!(names("*", 0), [".agfs", "hello"]);
+        assert_eq!(names("*", 1), [".agfs"]);
+        assert_eq!(names("h*", 1), ["hello"]);
+        assert_eq!(fs.readdir_filtered("/.agfs", "s*", 0).unwrap().len(), 2);
+
+        let walked: Vec<String> = fs.walk("/", 0).unwrap().into_iter().map(|(p, _)| p).collect();
+        assert_eq!(walked.len(), 9);
+        assert_eq!(walked[..2], ["/.agfs", "/.agfs/readme"]);
+        assert_eq!(walked[8], "/hello");
+        assert_eq!(fs.walk("/", 1).unwrap().len(), 2);
+        assert!(fs.stat("/.agfs").unwrap().is_dir());
+        assert_eq!(fs.stat("/.agfs/readme").unwrap().size, 9);
+        assert!(matches!(fs.stat("/.agfs/nope"), Err(Error::NotFound)));
+        assert!(matches!(fs.stat("/.agfsrc"), Err(Error::NotFound)));
+        assert!(is_control_path("/.agfs/nope"));
+        assert!(!is_control_path("/.agfsrc"));
+    }
+
+    #[test]
+    fn test_control_files() {
+        let mut fs = ControlFs::new(TestFS::default());
+        let config = Config::from_json(r#"{"passphrase": "hunter2hunter2", "api_token": "abc", "region": "eu"}"#).unwrap();
+        fs.initialize(&config).unwrap();
+
+        assert_eq!(text(&fs, "/.agfs/readme"), "# TestFS\n");
+        assert_eq!(fs.read("/.agfs/readme", 2, 4).unwrap(), b"Test");
+        let shown: Value = serde_json::from_str(&text(&fs, "/.agfs/config")).unwrap();
+        assert_eq!(shown, json!({"passphrase": REDACTED, "api_token": REDACTED, "region": "eu"}));
+
+        fs.read("/hello", 0, -1).unwrap();
+        assert!(fs.read("/missing", 0, -1).is_err());
+        let stats: Value = serde_json::from_str(&text(&fs, "/.agfs/stats")).unwrap();
+        assert_eq!(stats["reads"], 2);
+        assert_eq!(stats["errors"], 1);
+        assert_eq!(stats["commands"], 0);
+        assert_eq!(text(&fs, "/.agfs/health"), "ok\n");
+
+        crate::eprintln!("fetching with Bearer abc.def-123");
+        assert!(text(&fs, "/.agfs/log").contains(&format!("fetching with Bearer {}\n", REDACTED)));
+
+        let ops = vec![
+            FsOp::Remove { path: "/hello".to_string() },
+            FsOp::Rename { old_path: "/hello".to_string(), new_path: "/.agfs/ctl".to_string() },
+        ];
+        assert_eq!(fs.batch(ops), [Err(Error::Cancelled), Err(Error::PermissionDenied)]);
+        let results = fs.read_many(&["/.agfs/readme", "/hello", "/missing"]);
+        assert_eq!(results[0].as_deref().unwrap(), b"# TestFS\n");
+        assert_eq!(results[1].as_deref().unwrap(), b"hi");
+        assert_eq!(results[2], Err(Error::NotFound));
+
+        let schema: FsSchema = serde_json::from_str(&text(&fs, "/.agfs/schema.json")).unwrap();
+        assert_eq!(schema.description, "Test tree");
+        assert_eq!(schema.lookup("/hello").unwrap().format.as_deref(), Some("text/plain"));
+        assert!(schema.lookup(CTL_PATH).unwrap().writable);
+    }
+
+    #[test]
+    fn test_warmup_health() {
+        let mut fs = ControlFs::new(TestFS::default());
+        let budget = Duration::from_millis(10);
+        assert_eq!(fs.warmup(budget), Ok(WarmupProgress::new(1, 3)));
+        assert_eq!(text(&fs, "/.agfs/health"), "ok\nwarming up: 1/3\n");
+        fs.warmup(budget).unwrap();
+        assert!(fs.warmup(budget).unwrap().is_complete());
+        assert_eq!(text(&fs, "/.agfs/health"), "ok\n");
+
+        fs.ctl("down").unwrap();
+        assert!(fs.warmup(budget).is_err());
+        assert_eq!(
+            text(&fs, "/.agfs/health"),
+            "error: I/O error: backend unreachable\nwarmup failed: I/O error: backend unreachable\n"
+        );
+    }
+
+    #[test]
+    fn test_ctl() {
+        let mut fs = ControlFs::new(TestFS::default());
+        assert_eq!(fs.write(CTL_PATH, b"flush\n\ndown\n", 0, WriteFlag::NONE).unwrap(), 12);
+        assert_eq!(fs.inner().commands, ["flush", "down"]);
+        assert_eq!(text(&fs, "/.agfs/health"), "error: I/O error: backend unreachable\n");
+
+        assert!(matches!(fs.write(CTL_PATH, b"reboot", 0, WriteFlag::NONE), Err(Error::InvalidInput(_))));
+        assert!(matches!(fs.read(CTL_PATH, 0, -1), Err(Error::PermissionDenied)));
+        assert!(matches!(fs.write("/.agfs/readme", b"x", 0, WriteFlag::NONE), Err(Error::PermissionDenied)));
+        assert!(matches!(fs.remove("/.agfs"), Err(Error::PermissionDenied)));
+    }
+}
@@ -0,0 +1,869 @@
+//! Unified diffs of a file against its previous version
+//!
+//! Reviewing a change to a config-backed mount means comparing what a file
+//! holds now with what it held before the last write. [`DiffFs`] keeps the
+//! content each file had before it was last overwritten and serves
+//! `<path>.diff` next to it, a unified diff from that version to the current
+//! one:
+//!
+//! ```ignore
+//! type Exported = DiffFs<ConfigFS>;
+//! export_plugin!(Exported);
+//! ```
+//!
+//! ```text
+//! $ echo 'replicas: 3' > /config/app.yaml
+//! $ cat /config/app.yaml.diff
+//! --- a/config/app.yaml
+//! +++ b/config/app.yaml
+//! @@ -1 +1 @@
+//! -replicas: 2
+//! +replicas: 3
+//! ```
+//!
+//! [`unified`] renders the same format for any two texts, such as two dated
+//! copies of an [`ArchiveView`](crate::archive::ArchiveView).
+
+use crate::error::{Error, Result};
+use crate::filesystem::{
+    filter_entries, read_range, reject_batch, split_batch, walk_tree, FileSystem, HandleFS, StreamFS, UploadFS,
+};
+use crate::types::{
+    Advice, Capabilities, Config, ConfigParameter, FileInfo, FsOp, FsSchema, OpenFlag, UploadSession, WarmupProgress,
+    WriteFlag,
+};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+/// Suffix of the diff files
+pub const DIFF_SUFFIX: &str = ".diff";
+
+/// Unchanged lines shown around each change
+pub const CONTEXT_LINES: usize = 3;
+
+/// Largest file whose previous version is kept
+pub const MAX_VERSION_SIZE: usize = 1 << 20;
+
+/// Largest number of line pairs compared exactly; beyond it, the changed
+/// middle of the files is shown as removed and added whole
+const MAX_COMPARISONS: usize = 4_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Line {
+    Same,
+    Removed,
+    Added,
+}
+
+/// A unified diff from `old` to `new`, with `old_name` and `new_name` in the
+/// header; empty when the texts are equal
+pub fn unified(old: &str, new: &str, old_name: &str, new_name: &str) -> String {
+    let old: Vec<&str> = old.split_inclusive('\n').collect();
+    let new: Vec<&str> = new.split_inclusive('\n').collect();
+    let edits = edits(&old, &new);
+    if edits.iter().all(|edit| *edit == Line::Same) {
+        return String::new();
+    }
+
+    let mut out = format!("--- {}\n+++ {}\n", old_name, new_name);
+    // Line numbers (0-based) in old and new where each edit starts
+    let mut positions = Vec::with_capacity(edits.len() + 1);
+    let (mut i, mut j) = (0, 0);
+    for edit in &edits {
+        positions.push((i, j));
+        match edit {
+            Line::Same => (i, j) = (i + 1, j + 1),
+            Line::Removed => i += 1,
+            Line::Added => j += 1,
+        }
+    }
+    positions.push((i, j));
+
+    let changes: Vec<usize> = (0..edits.len()).filter(|&k| edits[k] != Line::Same).collect();
+    let mut k = 0;
+    while k < changes.len() {
+        let start = changes[k].saturating_sub(CONTEXT_LINES);
+        let mut last = changes[k];
+        while k + 1 < changes.len() && changes[k + 1] - last <= 2 * CONTEXT_LINES {
+            k += 1;
+            last = changes[k];
+        }
+        k += 1;
+        let end = (last + 1 + CONTEXT_LINES).min(edits.len());
+
+        let (old_start, new_start) = positions[start];
+        let (old_end, new_end) = positions[end];
+        out.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_start, old_end - old_start),
+            hunk_range(new_start, new_end - new_start)
+        ));
+        for (edit, &(i, j)) in edits[start..end].iter().zip(&positions[start..end]) {
+            let (prefix, line) = match edit {
+                Line::Same => (' ', old[i]),
+                Line::Removed => ('-', old[i]),
+                Line::Added => ('+', new[j]),
+            };
+            out.push(prefix);
+            out.push_str(line);
+            if !line.ends_with('\n') {
+                out.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+    }
+    out
+}
+
+/// `start,count` of a hunk, GNU style: the count is left out when it is 1,
+/// and an empty range starts at the line before it
+fn hunk_range(start: usize, count: usize) -> String {
+    match count {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        count => format!("{},{}", start + 1, count),
+    }
+}
+
+/// The edits turning `old` into `new`, from a longest common subsequence of
+/// the lines between their common prefix and suffix
+fn edits(old: &[&str], new: &[&str]) -> Vec<Line> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let (old_rest, new_rest) = (&old[prefix..], &new[prefix..]);
+    let suffix = old_rest
+        .iter()
+        .rev()
+        .zip(new_rest.iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (a, b) = (
+        &old_rest[..old_rest.len() - suffix],
+        &new_rest[..new_rest.len() - suffix],
+    );
+
+    let mut edits = vec![Line::Same; prefix];
+    if a.len().saturating_mul(b.len()) > MAX_COMPARISONS {
+        edits.extend(std::iter::repeat_n(Line::Removed, a.len()));
+        edits.extend(std::iter::repeat_n(Line::Added, b.len()));
+    } else {
+        // lcs[i][j]: length of the longest common subsequence of a[i..], b[j..]
+        let width = b.len() + 1;
+        let mut lcs = vec![0u32; (a.len() + 1) * width];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                lcs[i * width + j] = match a[i] == b[j] {
+                    true => lcs[(i + 1) * width + j + 1] + 1,
+                    false => lcs[(i + 1) * width + j].max(lcs[i * width + j + 1]),
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() || j < b.len() {
+            if i < a.len() && j < b.len() && a[i] == b[j] {
+                edits.push(Line::Same);
+                (i, j) = (i + 1, j + 1);
+            } else if j < b.len() && (i == a.len() || lcs[i * width + j + 1] >= lcs[(i + 1) * width + j]) {
+                edits.push(Line::Added);
+                j += 1;
+            } else {
+                edits.push(Line::Removed);
+                i += 1;
+            }
+        }
+        // Show removals before additions within each change, as diff(1) does
+        let mut k = prefix;
+        while k < edits.len() {
+            let run = edits[k..].iter().take_while(|edit| **edit != Line::Same).count();
+            edits[k..k + run].sort_by_key(|edit| *edit == Line::Added);
+            k += run.max(1);
+        }
+    }
+    edits.extend(std::iter::repeat_n(Line::Same, suffix));
+    edits
+}
+
+/// What a batch operation does to the kept versions once it succeeds
+enum Effect {
+    None,
+    Keep(String, Option<Arc<[u8]>>),
+    Forget(String),
+    Move(String, String),
+}
+
+/// Filesystem wrapper serving `<path>.diff` for every file overwritten since
+/// the plugin started
+///
+/// The previous version is read through the plugin just before a write at
+/// offset 0, a `write_if`, a batch `Write`, or opening a handle or upload
+/// for writing, so the plugin's reads must be free of side effects. Files
+/// over [`MAX_VERSION_SIZE`] get no diff. Versions are kept in memory and
+/// follow renames; removing a file forgets its version.
+pub struct DiffFs<F> {
+    inner: F,
+    previous: Mutex<HashMap<String, Arc<[u8]>>>,
+}
+
+impl<F: Default> Default for DiffFs<F> {
+    fn default() -> Self {
+        Self::new(F::default())
+    }
+}
+
+impl<F> DiffFs<F> {
+    /// Wrap `inner`, serving diffs of the files written through the wrapper
+    pub fn new(inner: F) -> Self {
+        Self {
+            inner,
+            previous: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The wrapped filesystem
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    fn versions(&self) -> MutexGuard<'_, HashMap<String, Arc<[u8]>>> {
+        self.previous.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// The content `path` had before it was last overwritten
+    pub fn previous(&self, path: &str) -> Option<Vec<u8>> {
+        self.versions().get(normalize(path)).map(|content| content.to_vec())
+    }
+
+    /// The file whose diff `path` is, if it has a previous version
+    fn diff_target<'a>(&self, path: &'a str) -> Option<&'a str> {
+        let target = normalize(path).strip_suffix(DIFF_SUFFIX)?;
+        self.versions().contains_key(target).then_some(target)
+    }
+
+    fn is_diff(&self, path: &str) -> bool {
+        self.diff_target(path).is_some()
+    }
+
+    fn touches_diff(&self, op: &FsOp) -> bool {
+        op.paths().into_iter().any(|path| self.is_diff(path))
+    }
+
+    fn keep(&self, path: &str, previous: Option<Arc<[u8]>>) {
+        let mut versions = self.versions();
+        match previous {
+            Some(previous) => versions.insert(normalize(path).to_string(), previous),
+            None => versions.remove(normalize(path)),
+        };
+    }
+
+    /// Forget the versions of `path` and everything below it
+    fn forget(&self, path: &str) {
+        let path = normalize(path);
+        self.versions().retain(|kept, _| !is_within(kept, path));
+    }
+
+    /// Move the versions of `old_path` and everything below it to `new_path`
+    fn moved(&self, old_path: &str, new_path: &str) {
+        let (old_path, new_path) = (normalize(old_path), normalize(new_path));
+        let mut versions = self.versions();
+        versions.retain(|kept, _| !is_within(kept, new_path));
+        let moved: Vec<String> = versions
+            .keys()
+            .filter(|kept| is_within(kept, old_path))
+            .cloned()
+            .collect();
+        for kept in moved {
+            if let Some(content) = versions.remove(&kept) {
+                versions.insert(format!("{}{}", new_path, &kept[old_path.len()..]), content);
+            }
+        }
+    }
+
+    fn apply(&self, effect: Effect) {
+        match effect {
+            Effect::None => {}
+            Effect::Keep(path, previous) => self.keep(&path, previous),
+            Effect::Forget(path) => self.forget(&path),
+            Effect::Move(old_path, new_path) => self.moved(&old_path, &new_path),
+        }
+    }
+
+    /// Diffs of the files directly inside the directory `path` that are
+    /// listed in `entries`
+    fn children(&self, path: &str, entries: &[FileInfo]) -> Vec<FileInfo> {
+        let dir = normalize(path);
+        let versions = self.versions();
+        let mut diffs: Vec<FileInfo> = versions
+            .keys()
+            .filter(|kept| parent(kept) == dir)
+            .map(|kept| kept.rsplit('/').next().unwrap_or_default())
+            .filter(|name| entries.iter().any(|entry| entry.name == *name))
+            .map(|name| diff_info(&format!("{}{}", name, DIFF_SUFFIX)))
+            .collect();
+        diffs.sort_by(|a, b| a.name.cmp(&b.name));
+        diffs
+    }
+
+    fn has_children(&self, path: &str) -> bool {
+        let dir = normalize(path);
+        self.versions().keys().any(|kept| parent(kept) == dir)
+    }
+
+    fn has_descendants(&self, path: &str) -> bool {
+        let dir = normalize(path);
+        self.versions().keys().any(|kept| dir == "/" || is_within(kept, dir))
+    }
+}
+
+impl<F: FileSystem> DiffFs<F> {
+    /// The content of `path` now, to keep as its previous version once it is
+    /// overwritten; `None` if it is missing, not a file or too large
+    fn current(&self, path: &str) -> Option<Arc<[u8]>> {
+        let info = self.inner.stat(path).ok()?;
+        if info.is_dir() || info.size > MAX_VERSION_SIZE as i64 {
+            return None;
+        }
+        let content = self.inner.read(path, 0, -1).ok()?;
+        (content.len() <= MAX_VERSION_SIZE).then(|| content.into())
+    }
+
+    /// The diff of `path` from its kept version to its current content
+    fn render(&self, path: &str) -> Result<Vec<u8>> {
+        let previous = self.versions().get(path).cloned().ok_or(Error::NotFound)?;
+        let current = self.inner.read(path, 0, -1)?;
+        let (old_name, new_name) = (format!("a{}", path), format!("b{}", path));
+        let diff = match (std::str::from_utf8(&previous), std::str::from_utf8(&current)) {
+            (Ok(old), Ok(new)) => unified(old, new, &old_name, &new_name),
+            _ if *previous == *current => String::new(),
+            _ => format!("Binary files {} and {} differ\n", old_name, new_name),
+        };
+        Ok(diff.into_bytes())
+    }
+
+    fn effect(&self, op: &FsOp) -> Effect {
+        match op {
+            FsOp::Write { path, .. } => Effect::Keep(path.clone(), self.current(path)),
+            FsOp::Remove { path } | FsOp::RemoveAll { path } => Effect::Forget(path.clone()),
+            FsOp::Rename { old_path, new_path } => Effect::Move(old_path.clone(), new_path.clone()),
+            FsOp::Create { .. } | FsOp::Mkdir { .. } | FsOp::Chmod { .. } => Effect::None,
+        }
+    }
+}
+
+/// `path` without trailing slashes, `/` for the root
+fn normalize(path: &str) -> &str {
+    match path.trim_end_matches('/') {
+        "" => "/",
+        path => path,
+    }
+}
+
+fn parent(path: &str) -> &str {
+    match path.rfind('/') {
+        Some(0) | None => "/",
+        Some(i) => &path[..i],
+    }
+}
+
+/// Whether `path` is `dir` or below it
+fn is_within(path: &str, dir: &str) -> bool {
+    path.strip_prefix(dir)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+fn diff_info(name: &str) -> FileInfo {
+    FileInfo::generated(name, 0o444).with_content_type("text/x-diff")
+}
+
+impl<F: FileSystem> FileSystem for DiffFs<F> {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn readme(&self) -> &str {
+        self.inner.readme()
+    }
+
+    fn config_params(&self) -> Vec<ConfigParameter> {
+        self.inner.config_params()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    fn schema(&self) -> FsSchema {
+        self.inner.schema()
+    }
+
+    fn validate(&self, config: &Config) -> Result<()> {
+        self.inner.validate(config)
+    }
+
+    fn initialize(&mut self, config: &Config) -> Result<()> {
+        self.inner.initialize(config)
+    }
+
+    fn shutdown(&mut self) -> Result<()> {
+        self.inner.shutdown()
+    }
+
+    /// The plugin's stats plus the number of files with a previous version
+    fn stats(&self) -> serde_json::Value {
+        let mut stats = self.inner.stats();
+        if let serde_json::Value::Object(fields) = &mut stats {
+            fields.insert("diff_versions".to_string(), self.versions().len().into());
+        }
+        stats
+    }
+
+    fn health(&self) -> Result<()> {
+        self.inner.health()
+    }
+
+    fn ctl(&mut self, command: &str) -> Result<()> {
+        self.inner.ctl(command)
+    }
+
+    fn maintain(&mut self) -> Result<()> {
+        self.inner.maintain()
+    }
+
+    fn warmup(&mut self, budget: Duration) -> Result<WarmupProgress> {
+        self.inner.warmup(budget)
+    }
+
+    fn export_state(&self) -> Result<Vec<u8>> {
+        self.inner.export_state()
+    }
+
+    fn import_state(&mut self, state: &[u8]) -> Result<()> {
+        self.inner.import_state(state)
+    }
+
+    fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+        match self.diff_target(path) {
+            Some(target) => Ok(read_range(&self.render(target)?, offset, size)),
+            None => self.inner.read(path, offset, size),
+        }
+    }
+
+    fn poll_read(&self, path: &str, offset: i64, size: i64, timeout: Duration) -> Result<Vec<u8>> {
+        match self.is_diff(path) {
+            true => self.read(path, offset, size),
+            false => self.inner.poll_read(path, offset, size, timeout),
+        }
+    }
+
+    fn stat(&self, path: &str) -> Result<FileInfo> {
+        match self.diff_target(path) {
+            Some(target) => {
+                self.inner.stat(target)?;
+                Ok(diff_info(normalize(path).rsplit('/').next().unwrap_or_default()))
+            }
+            None => self.inner.stat(path),
+        }
+    }
+
+    /// The plugin's entries followed by the diffs of the files in `path`,
+    /// which hide plugin entries of the same name
+    fn readdir(&self, path: &str) -> Result<Vec<FileInfo>> {
+        let mut entries = self.inner.readdir(path)?;
+        let diffs = self.children(path, &entries);
+        if !diffs.is_empty() {
+            entries.retain(|entry| !diffs.iter().any(|diff| diff.name == entry.name));
+            entries.extend(diffs);
+        }
+        Ok(entries)
+    }
+
+    fn readdir_page(&self, path: &str, offset: usize, limit: usize) -> Result<Vec<FileInfo>> {
+        match self.has_children(path) {
+            true => Ok(self.readdir(path)?.into_iter().skip(offset).take(limit).collect()),
+            false => self.inner.readdir_page(path, offset, limit),
+        }
+    }
+
+    fn readdir_filtered(&self, path: &str, glob: &str, limit: usize) -> Result<Vec<FileInfo>> {
+        match self.has_children(path) {
+            true => Ok(filter_entries(self.readdir(path)?, glob, limit)),
+            false => self.inner.readdir_filtered(path, glob, limit),
+        }
+    }
+
+    fn walk(&self, path: &str, depth: usize) -> Result<Vec<(String, FileInfo)>> {
+        match self.has_descendants(path) {
+            true => walk_tree(self, path, depth),
+            false => self.inner.walk(path, depth),
+        }
+    }
+
+    fn dir_generation(&self, path: &str) -> u64 {
+        self.inner.dir_generation(path)
+    }
+
+    /// A write at offset 0 starts a new version; later writes at other
+    /// offsets continue it
+    fn write(&mut self, path: &str, data: &[u8], offset: i64, flags: WriteFlag) -> Result<i64> {
+        if self.is_diff(path) {
+            return Err(Error::PermissionDenied);
+        }
+        if offset != 0 || flags.contains(WriteFlag::APPEND) {
+            return self.inner.write(path, data, offset, flags);
+        }
+        let previous = self.current(path);
+        let written = self.inner.write(path, data, offset, flags)?;
+        self.keep(path, previous);
+        Ok(written)
+    }
+
+    fn create(&mut self, path: &str) -> Result<()> {
+        match self.is_diff(path) {
+            true => Err(Error::PermissionDenied),
+            false => self.inner.create(path),
+        }
+    }
+
+    fn mkdir(&mut self, path: &str, perm: u32) -> Result<()> {
+        match self.is_diff(path) {
+            true => Err(Error::AlreadyExists),
+            false => self.inner.mkdir(path, perm),
+        }
+    }
+
+    fn remove(&mut self, path: &str) -> Result<()> {
+        if self.is_diff(path) {
+            return Err(Error::PermissionDenied);
+        }
+        self.inner.remove(path)?;
+        self.forget(path);
+        Ok(())
+    }
+
+    fn remove_all(&mut self, path: &str) -> Result<()> {
+        if self.is_diff(path) {
+            return Err(Error::PermissionDenied);
+        }
+        self.inner.remove_all(path)?;
+        self.forget(path);
+        Ok(())
+    }
+
+    fn rename(&mut self, old_path: &str, new_path: &str) -> Result<()> {
+        if self.is_diff(old_path) || self.is_diff(new_path) {
+            return Err(Error::PermissionDenied);
+        }
+        self.inner.rename(old_path, new_path)?;
+        self.moved(old_path, new_path);
+        Ok(())
+    }
+
+    fn chmod(&mut self, path: &str, mode: u32) -> Result<()> {
+        match self.is_diff(path) {
+            true => Err(Error::PermissionDenied),
+            false => self.inner.chmod(path, mode),
+        }
+    }
+
+    fn symlink(&mut self, target: &str, link_path: &str) -> Result<()> {
+        match self.is_diff(link_path) {
+            true => Err(Error::AlreadyExists),
+            false => self.inner.symlink(target, link_path),
+        }
+    }
+
+    fn readlink(&self, path: &str) -> Result<String> {
+        match self.is_diff(path) {
+            true => Err(Error::InvalidInput("not a symlink".to_string())),
+            false => self.inner.readlink(path),
+        }
+    }
+
+    fn get_xattr(&self, path: &str, name: &str) -> Result<Vec<u8>> {
+        match self.is_diff(path) {
+            true => Err(Error::NoAttribute),
+            false => self.inner.get_xattr(path, name),
+        }
+    }
+
+    fn set_xattr(&mut self, path: &str, name: &str, value: &[u8]) -> Result<()> {
+        match self.is_diff(path) {
+            true => Err(Error::PermissionDenied),
+            false => self.inner.set_xattr(path, name, value),
+        }
+    }
+
+    fn list_xattr(&self, path: &str) -> Result<Vec<String>> {
+        match self.is_diff(path) {
+            true => Ok(Vec::new()),
+            false => self.inner.list_xattr(path),
+        }
+    }
+
+    /// A diff has no etag of its own and is always rendered
+    fn read_if_changed(&self, path: &str, etag: &str) -> Result<Option<Vec<u8>>> {
+        match self.is_diff(path) {
+            true => self.read(path, 0, -1).map(Some),
+            false => self.inner.read_if_changed(path, etag),
+        }
+    }
+
+    fn read_many(&self, paths: &[&str]) -> Vec<Result<Vec<u8>>> {
+        split_batch(
+            paths,
+            |path| self.is_diff(path).then(|| self.read(path, 0, -1)),
+            |rest| self.inner.read_many(rest),
+        )
+    }
+
+    fn write_if(&mut self, path: &str, data: &[u8], expected_etag: &str) -> Result<i64> {
+        if self.is_diff(path) {
+            return Err(Error::PermissionDenied);
+        }
+        let previous = self.current(path);
+        let written = self.inner.write_if(path, data, expected_etag)?;
+        self.keep(path, previous);
+        Ok(written)
+    }
+
+    /// Diffs are read-only, so a batch touching one is rejected as a whole
+    fn batch(&mut self, ops: Vec<FsOp>) -> Vec<Result<()>> {
+        if let Some(i) = ops.iter().position(|op| self.touches_diff(op)) {
+            return reject_batch(ops.len(), i, Error::PermissionDenied);
+        }
+        let effects: Vec<Effect> = ops.iter().map(|op| self.effect(op)).collect();
+        let results = self.inner.batch(ops);
+        for (effect, result) in effects.into_iter().zip(&results) {
+            if result.is_ok() {
+                self.apply(effect);
+            }
+        }
+        results
+    }
+
+    fn atomic_batch(&self) -> bool {
+        self.inner.atomic_batch()
+    }
+
+    fn begin_write_session(&mut self) -> Result<()> {
+        self.inner.begin_write_session()
+    }
+
+    fn commit_session(&mut self, message: &str) -> Result<()> {
+        self.inner.commit_session(message)
+    }
+
+    fn abort_session(&mut self) -> Result<()> {
+        self.inner.abort_session()
+    }
+
+    fn advise(&self, path: &str, offset: i64, len: i64, advice: Advice) -> Result<()> {
+        match self.is_diff(path) {
+            true => Ok(()),
+            false => self.inner.advise(path, offset, len, advice),
+        }
+    }
+}
+
+/// Opening a file for writing starts a new version; diffs are read by path
+impl<F: HandleFS> HandleFS for DiffFs<F> {
+    fn open_handle(&mut self, path: &str, flags: OpenFlag, mode: u32) -> Result<i64> {
+        if self.is_diff(path) {
+            return Err(Error::PermissionDenied);
+        }
+        if !flags.is_writable() || flags.contains(OpenFlag::O_APPEND) {
+            return self.inner.open_handle(path, flags, mode);
+        }
+        let previous = self.current(path);
+        let id = self.inner.open_handle(path, flags, mode)?;
+        self.keep(path, previous);
+        Ok(id)
+    }
+
+    fn handle_read(&mut self, id: i64, buf: &mut [u8]) -> Result<usize> {
+        self.inner.handle_read(id, buf)
+    }
+
+    fn handle_read_at(&self, id: i64, buf: &mut [u8], offset: i64) -> Result<usize> {
+        self.inner.handle_read_at(id, buf, offset)
+    }
+
+    fn handle_write(&mut self, id: i64, data: &[u8]) -> Result<usize> {
+        self.inner.handle_write(id, data)
+    }
+
+    fn handle_write_at(&self, id: i64, data: &[u8], offset: i64) -> Result<usize> {
+        self.inner.handle_write_at(id, data, offset)
+    }
+
+    fn handle_seek(&mut self, id: i64, offset: i64, whence: i32) -> Result<i64> {
+        self.inner.handle_seek(id, offset, whence)
+    }
+
+    fn handle_sync(&self, id: i64) -> Result<()> {
+        self.inner.handle_sync(id)
+    }
+
+    fn handle_stat(&self, id: i64) -> Result<FileInfo> {
+        self.inner.handle_stat(id)
+    }
+
+    fn handle_info(&self, id: i64) -> Result<(String, OpenFlag)> {
+        self.inner.handle_info(id)
+    }
+
+    fn close_handle(&mut self, id: i64) -> Result<()> {
+        self.inner.close_handle(id)
+    }
+}
+
+impl<F: StreamFS> StreamFS for DiffFs<F> {
+    fn open_stream(&mut self, path: &str) -> Result<i64> {
+        match self.is_diff(path) {
+            true => Err(Error::PermissionDenied),
+            false => self.inner.open_stream(path),
+        }
+    }
+
+    fn stream_read(&mut self, id: i64, buf: &mut [u8]) -> Result<Option<usize>> {
+        self.inner.stream_read(id, buf)
+    }
+
+    fn stream_write(&mut self, id: i64, data: &[u8]) -> Result<usize> {
+        self.inner.stream_write(id, data)
+    }
+
+    fn close_stream(&mut self, id: i64) -> Result<()> {
+        self.inner.close_stream(id)
+    }
+}
+
+/// Beginning an upload starts a new version of its path
+impl<F: UploadFS> UploadFS for DiffFs<F> {
+    fn begin_upload(&mut self, path: &str) -> Result<UploadSession> {
+        if self.is_diff(path) {
+            return Err(Error::PermissionDenied);
+        }
+        let previous = self.current(path);
+        let session = self.inner.begin_upload(path)?;
+        self.keep(path, previous);
+        Ok(session)
+    }
+
+    fn upload_status(&self, id: &str) -> Result<UploadSession> {
+        self.inner.upload_status(id)
+    }
+
+    fn upload_append(&mut self, id: &str, offset: u64, data: &[u8]) -> Result<UploadSession> {
+        self.inner.upload_append(id, offset, data)
+    }
+
+    fn commit_upload(&mut self, id: &str) -> Result<()> {
+        self.inner.commit_upload(id)
+    }
+
+    fn abort_upload(&mut self, id: &str) -> Result<()> {
+        self.inner.abort_upload(id)
+    }
+
+    fn list_uploads(&self) -> Result<Vec<UploadSession>> {
+        self.inner.list_uploads()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[derive(Default)]
+    struct MemFS {
+        files: BTreeMap<String, Vec<u8>>,
+    }
+
+    impl FileSystem for MemFS {
+        fn name(&self) -> &str {
+            "memfs"
+        }
+
+        fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+            let data = self.files.get(path).ok_or(Error::NotFound)?;
+            Ok(read_range(data, offset, size))
+        }
+
+        fn stat(&self, path: &str) -> Result<FileInfo> {
+            match path {
+                "/" => Ok(FileInfo::dir("", 0o755)),
+                _ => {
+                    let data = self.files.get(path).ok_or(Error::NotFound)?;
+                    Ok(FileInfo::file(&path[1..], data.len() as i64, 0o644))
+                }
+            }
+        }
+
+        fn readdir(&self, _path: &str) -> Result<Vec<FileInfo>> {
+            Ok(self
+                .files
+                .iter()
+                .map(|(path, data)| FileInfo::file(&path[1..], data.len() as i64, 0o644))
+                .collect())
+        }
+
+        fn write(&mut self, path: &str, data: &[u8], offset: i64, _flags: WriteFlag) -> Result<i64> {
+            let file = self.files.entry(path.to_string()).or_default();
+            file.truncate(offset as usize);
+            file.extend_from_slice(data);
+            Ok(data.len() as i64)
+        }
+
+        fn rename(&mut self, old_path: &str, new_path: &str) -> Result<()> {
+            let data = self.files.remove(old_path).ok_or(Error::NotFound)?;
+            self.files.insert(new_path.to_string(), data);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_unified() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\nk\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\nk\nl";
+        assert_eq!(
+            unified(old, new, "a/x", "b/x"),
+            "--- a/x\n+++ b/x\n\
+             @@ -1,5 +1,5 @@\n a\n-b\n+B\n c\n d\n e\n\
+             @@ -9,3 +9,4 @@\n i\n j\n k\n+l\n\\ No newline at end of file\n"
+        );
+        assert_eq!(unified("x\n", "x\n", "a", "b"), "");
+        assert_eq!(unified("", "new\n", "a", "b"), "--- a\n+++ b\n@@ -0,0 +1 @@\n+new\n");
+        assert_eq!(
+            unified("1\n2\n3\n", "1\n3\n4\n", "a", "b"),
+            "--- a\n+++ b\n@@ -1,3 +1,3 @@\n 1\n-2\n 3\n+4\n"
+        );
+    }
+
+    #[test]
+    fn test_diff_fs() {
+        let mut fs = DiffFs::<MemFS>::default();
+        fs.write("/app.yaml", b"replicas: 2\n", 0, WriteFlag::NONE).unwrap();
+        assert_eq!(fs.stat("/app.yaml.diff").unwrap_err(), Error::NotFound);
+
+        fs.write("/app.yaml", b"replicas: 3\n", 0, WriteFlag::TRUNCATE).unwrap();
+        fs.write("/app.yaml", b"image: v2\n", 12, WriteFlag::NONE).unwrap();
+        let names: Vec<String> = fs.readdir("/").unwrap().into_iter().map(|e| e.name).collect();
+        assert_eq!(names, ["app.yaml", "app.yaml.diff"]);
+        assert_eq!(
+            String::from_utf8(fs.read("/app.yaml.diff", 0, -1).unwrap()).unwrap(),
+            "--- a/app.yaml\n+++ b/app.yaml\n@@ -1 +1,2 @@\n-replicas: 2\n+replicas: 3\n+image: v2\n"
+        );
+        assert_eq!(
+            fs.write("/app.yaml.diff", b"x", 0, WriteFlag::NONE),
+            Err(Error::PermissionDenied)
+        );
+
+        fs.rename("/app.yaml", "/prod.yaml").unwrap();
+        assert!(fs.stat("/app.yaml.diff").is_err());
+        assert_eq!(fs.previous("/prod.yaml").unwrap(), b"replicas: 2\n");
+        assert!(fs
+            .read("/prod.yaml.diff", 0, -1)
+            .unwrap()
+            .starts_with(b"--- a/prod.yaml"));
+    }
+}
@@ -0,0 +1,167 @@
+//! Charset conversion of legacy-encoded upstream content to UTF-8
+//!
+//! Old mail, FTP listings and many web pages are not UTF-8; read as UTF-8
+//! they turn into mojibake. [`decode`] finds the charset the way browsers
+//! do — a byte order mark, the declared charset (e.g. from `Content-Type`),
+//! a `<meta charset>` or `<?xml encoding?>` declaration in the content, and
+//! finally statistical detection — and converts to UTF-8:
+//!
+//! ```
+//! use agfs_core::encoding;
+//!
+//! let latin1 = b"caf\xe9";
+//! assert_eq!(encoding::decode(latin1, Some("iso-8859-1")), "café");
+//! assert_eq!(encoding::charset_of_content_type("text/html; charset=\"Shift_JIS\""), Some("Shift_JIS"));
+//! ```
+//!
+//! Charset names are WHATWG labels, so aliases like `latin1`, `sjis` or
+//! `gb2312` are understood.
+
+use crate::error::{Error, Result};
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_8};
+use std::borrow::Cow;
+
+/// How far into the content a `<meta>` or `<?xml?>` declaration is looked for
+const DECLARATION_SCAN_BYTES: usize = 1024;
+
+/// The `charset` parameter of a `Content-Type` value, unquoted
+pub fn charset_of_content_type(content_type: &str) -> Option<&str> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        if !name.trim().eq_ignore_ascii_case("charset") {
+            return None;
+        }
+        let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+        (!value.is_empty()).then_some(value)
+    })
+}
+
+/// `bytes` as UTF-8 text, in the charset `declared` or else the one found in
+/// or detected from the content
+///
+/// An unknown `declared` label is ignored. Bytes that are invalid in the
+/// chosen charset become U+FFFD; valid UTF-8 without a conflicting
+/// declaration is returned without copying.
+pub fn decode<'a>(bytes: &'a [u8], declared: Option<&str>) -> Cow<'a, str> {
+    let (text, _) = charset(bytes, declared).decode_with_bom_removal(bytes);
+    text
+}
+
+/// `bytes` as UTF-8 text in the charset named `label`, byte order marks
+/// aside
+///
+/// For plugins whose upstream charset is configured, e.g. an FTP server known
+/// to list names in `cp1251`. Fails with `InvalidInput` for an unknown label.
+pub fn decode_as<'a>(bytes: &'a [u8], label: &str) -> Result<Cow<'a, str>> {
+    let encoding = lookup(label).ok_or_else(|| {
+        Error::InvalidInput(format!(
+            "unknown charset: {} (expected e.g. utf-8, latin1, cp1252)",
+            label
+        ))
+    })?;
+    let (text, _) = encoding.decode_with_bom_removal(bytes);
+    Ok(text)
+}
+
+/// The canonical name of the charset [`decode`] would use, e.g.
+/// `windows-1252` or `Shift_JIS`
+pub fn detect(bytes: &[u8], declared: Option<&str>) -> &'static str {
+    charset(bytes, declared).name()
+}
+
+fn charset(bytes: &[u8], declared: Option<&str>) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return encoding;
+    }
+    if let Some(encoding) = declared.and_then(lookup) {
+        return encoding;
+    }
+    if std::str::from_utf8(bytes).is_ok() {
+        return UTF_8;
+    }
+    if let Some(encoding) = in_document_declaration(bytes) {
+        return encoding;
+    }
+    let mut detector = EncodingDetector::new();
+    detector.feed(bytes, true);
+    detector.guess(None, true)
+}
+
+/// The encoding for a label; UTF-16 labels are taken as UTF-8, since
+/// ASCII-compatible declarations cannot be UTF-16 content (as browsers do)
+fn lookup(label: &str) -> Option<&'static Encoding> {
+    Encoding::for_label(label.trim().as_bytes()).map(|encoding| encoding.output_encoding())
+}
+
+/// The charset of a `<meta charset="...">`, `<meta http-equiv content="...;
+/// charset=...">` or `<?xml ... encoding="..."?>` near the start of `bytes`
+fn in_document_declaration(bytes: &[u8]) -> Option<&'static Encoding> {
+    let head = &bytes[..bytes.len().min(DECLARATION_SCAN_BYTES)];
+    // Declarations are ASCII, so a lossy ASCII view finds them in any charset
+    let head: String = head
+        .iter()
+        .map(|&b| {
+            if b.is_ascii() {
+                b.to_ascii_lowercase() as char
+            } else {
+                ' '
+            }
+        })
+        .collect();
+    ["charset=", "encoding="].iter().find_map(|key| {
+        let start = head.find(key)? + key.len();
+        let value = head[start..].trim_start_matches(['"', '\'', ' ']);
+        let end = value
+            .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':')))
+            .unwrap_or(value.len());
+        lookup(&value[..end])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        // Declared charsets win over detection, unknown labels are ignored
+        assert_eq!(decode(b"na\xefve", Some("latin1")), "naïve");
+        assert_eq!(decode(b"\x93quoted\x94", Some("cp1252")), "\u{201c}quoted\u{201d}");
+        assert_eq!(decode("grüß".as_bytes(), Some("x-unknown")), "grüß");
+        assert!(matches!(decode(b"plain ascii", None), Cow::Borrowed(_)));
+
+        // A byte order mark beats the declaration
+        assert_eq!(decode(b"\xef\xbb\xbfcaf\xc3\xa9", Some("latin1")), "café");
+        assert_eq!(decode(b"\xff\xfeh\x00i\x00", None), "hi");
+
+        // Declarations inside the document
+        let html = b"<html><head><meta charset=\"windows-1251\"></head><body>\xcf\xf0\xe8\xe2\xe5\xf2</body>";
+        assert!(decode(html, None).contains("Привет"));
+        let xml = b"<?xml version=\"1.0\" encoding=\"ISO-8859-15\"?><price>10 \xa4</price>";
+        assert!(decode(xml, None).contains("10 €"));
+
+        // Detection of undeclared legacy text
+        let sjis = b"\x82\xb1\x82\xf1\x82\xc9\x82\xbf\x82\xcd\x81\x41\x90\xa2\x8a\x45\x81\x42\x93\xfa\x96\x7b\x8c\xea\x82\xcc\x83\x65\x83\x4c\x83\x58\x83\x67\x82\xc5\x82\xb7\x81\x42";
+        assert_eq!(detect(sjis, None), "Shift_JIS");
+        assert_eq!(decode(sjis, None), "こんにちは、世界。日本語のテキストです。");
+    }
+
+    #[test]
+    fn test_charset_labels() {
+        assert_eq!(
+            charset_of_content_type("text/html; charset=ISO-8859-1"),
+            Some("ISO-8859-1")
+        );
+        assert_eq!(
+            charset_of_content_type("text/plain;Charset='koi8-r' ; format=flowed"),
+            Some("koi8-r")
+        );
+        assert_eq!(charset_of_content_type("application/json"), None);
+        assert_eq!(charset_of_content_type("text/html; charset="), None);
+
+        assert_eq!(decode_as(b"\xf0\xd2\xc9\xd7\xc5\xd4", "koi8-r").unwrap(), "Привет");
+        assert!(matches!(decode_as(b"x", "klingon"), Err(Error::InvalidInput(_))));
+        assert_eq!(detect(b"", Some("utf-16")), "UTF-8");
+    }
+}
@@ -0,0 +1,304 @@
+//! Error types for filesystem operations
+
+/// Result type for filesystem operations
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Errors that can occur during filesystem operations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// File or directory not found
+    NotFound,
+    /// Permission denied
+    PermissionDenied,
+    /// File or directory already exists
+    AlreadyExists,
+    /// Is a directory (when a file was expected)
+    IsDirectory,
+    /// Not a directory
+    NotDirectory,
+    /// Directory not empty
+    DirectoryNotEmpty,
+    /// Operation not supported (e.g., writes on read-only filesystem)
+    ReadOnly,
+    /// Path argument is missing or not valid UTF-8
+    InvalidPath,
+    /// Extended attribute does not exist
+    NoAttribute,
+    /// Operation ran past the deadline set by the host
+    TimedOut,
+    /// Operation was cancelled by the host
+    Cancelled,
+    /// Resource is held by another writer, or the backend asks for backoff
+    /// (e.g. an upstream rate limit); retrying later may succeed.
+    /// `retry_after_ms` is the backend's hint of when to retry.
+    Busy { retry_after_ms: Option<u64> },
+    /// Content changed since the caller last saw it (conditional write failed)
+    Stale,
+    /// Invalid argument
+    InvalidInput(String),
+    /// General I/O error
+    Io(String),
+    /// Custom error with message
+    Other(String),
+}
+
+impl Error {
+    /// Numeric code reported to the host alongside the message
+    ///
+    /// Codes follow Linux errno values so the host can map them straight onto
+    /// POSIX errors; untyped failures report `EIO`.
+    pub fn code(&self) -> i32 {
+        match self {
+            Error::NotFound => 2,                              // ENOENT
+            Error::PermissionDenied => 13,                     // EACCES
+            Error::AlreadyExists => 17,                        // EEXIST
+            Error::Busy { retry_after_ms: Some(_) } => 11,     // EAGAIN
+            Error::Busy { retry_after_ms: None } => 16,        // EBUSY
+            Error::NotDirectory => 20,                         // ENOTDIR
+            Error::IsDirectory => 21,                          // EISDIR
+            Error::InvalidPath | Error::InvalidInput(_) => 22, // EINVAL
+            Error::ReadOnly => 30,                             // EROFS
+            Error::DirectoryNotEmpty => 39,                    // ENOTEMPTY
+            Error::NoAttribute => 61,                          // ENODATA
+            Error::TimedOut => 110,                            // ETIMEDOUT
+            Error::Stale => 116,                               // ESTALE
+            Error::Cancelled => 125,                           // ECANCELED
+            Error::Io(_) | Error::Other(_) => 5,               // EIO
+        }
+    }
+}
+
+impl Error {
+    /// Error for a `code` reported by the host, the inverse of [`code`](Self::code)
+    ///
+    /// `message` is kept for the codes whose variants carry one, and the
+    /// retry hint of `EAGAIN` is read back from it; unknown codes become
+    /// `Other`.
+    pub fn from_code(code: i32, message: impl Into<String>) -> Self {
+        match code {
+            11 => Error::Busy {
+                retry_after_ms: Some(retry_after_ms(&message.into()).unwrap_or(0)),
+            },
+            2 => Error::NotFound,
+            13 => Error::PermissionDenied,
+            16 => Error::Busy { retry_after_ms: None },
+            17 => Error::AlreadyExists,
+            20 => Error::NotDirectory,
+            21 => Error::IsDirectory,
+            22 => Error::InvalidInput(message.into()),
+            30 => Error::ReadOnly,
+            39 => Error::DirectoryNotEmpty,
+            61 => Error::NoAttribute,
+            110 => Error::TimedOut,
+            116 => Error::Stale,
+            125 => Error::Cancelled,
+            5 => Error::Io(message.into()),
+            _ => Error::Other(message.into()),
+        }
+    }
+}
+
+impl Error {
+    /// Error for an unsuccessful HTTP response from an upstream API, `None`
+    /// for a 1xx-3xx status
+    ///
+    /// Maps the status onto the error a POSIX client expects (404 is
+    /// `NotFound`, 401/403 `PermissionDenied`, 409 `AlreadyExists`, 429/503
+    /// `Busy`, ...). The message of the API's error payload, if `body` has
+    /// one, goes into the variants that carry a message.
+    pub fn from_http(status: i32, body: &[u8]) -> Option<Self> {
+        if status < 400 {
+            return None;
+        }
+        let message = || match api_error_message(body) {
+            Some(message) => format!("HTTP {}: {}", status, message),
+            None => format!("HTTP {}", status),
+        };
+        Some(match status {
+            401 | 403 => Error::PermissionDenied,
+            404 | 410 => Error::NotFound,
+            409 => Error::AlreadyExists,
+            412 => Error::Stale,
+            408 | 504 => Error::TimedOut,
+            429 | 503 => Error::Busy {
+                retry_after_ms: Some(0),
+            },
+            400 | 411 | 413 | 414 | 415 | 416 | 422 => Error::InvalidInput(message()),
+            _ => Error::Io(message()),
+        })
+    }
+}
+
+/// Longest API error message kept in an error
+const MAX_API_MESSAGE: usize = 200;
+
+/// Where the common JSON error payloads keep their message
+const API_MESSAGE_POINTERS: [&str; 7] = [
+    "/message",
+    "/error_description",
+    "/error/message",
+    "/error",
+    "/errors/0/message",
+    "/errors/0",
+    "/detail",
+];
+
+/// The message of a JSON error payload, or of a plain-text body
+fn api_error_message(body: &[u8]) -> Option<String> {
+    let message = match serde_json::from_slice::<serde_json::Value>(body) {
+        Ok(json) => API_MESSAGE_POINTERS
+            .iter()
+            .find_map(|pointer| json.pointer(pointer)?.as_str().map(str::to_string))?,
+        Err(_) => {
+            let text = std::str::from_utf8(body).ok()?.trim();
+            // HTML error pages say nothing useful in their first line
+            if text.starts_with('<') {
+                return None;
+            }
+            text.to_string()
+        }
+    };
+    let message = message.trim();
+    if message.is_empty() {
+        return None;
+    }
+    Some(match message.char_indices().nth(MAX_API_MESSAGE) {
+        Some((end, _)) => format!("{}...", &message[..end]),
+        None => message.to_string(),
+    })
+}
+
+/// Marks the retry hint in the message of a backoff error; the host parses
+/// it from there (`backoffMessagePrefix` in the server's plugin_api.go)
+const RETRY_AFTER: &str = "retry after ";
+
+/// The retry hint in the message of a backoff error, e.g. `retry after 1500ms`
+fn retry_after_ms(message: &str) -> Option<u64> {
+    let rest = &message[message.find(RETRY_AFTER)? + RETRY_AFTER.len()..];
+    let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+    rest[..digits].parse().ok()
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::NotFound => write!(f, "file not found"),
+            Error::PermissionDenied => write!(f, "permission denied"),
+            Error::AlreadyExists => write!(f, "file already exists"),
+            Error::IsDirectory => write!(f, "is a directory"),
+            Error::NotDirectory => write!(f, "not a directory"),
+            Error::DirectoryNotEmpty => write!(f, "directory not empty"),
+            Error::ReadOnly => write!(f, "operation not supported: read-only filesystem"),
+            Error::InvalidPath => write!(f, "invalid path"),
+            Error::NoAttribute => write!(f, "no such attribute"),
+            Error::TimedOut => write!(f, "operation timed out"),
+            Error::Cancelled => write!(f, "operation cancelled"),
+            Error::Busy { retry_after_ms: None } => write!(f, "resource busy"),
+            Error::Busy {
+                retry_after_ms: Some(ms),
+            } => write!(f, "backend busy, {}{}ms", RETRY_AFTER, ms),
+            Error::Stale => write!(f, "content changed since it was read"),
+            Error::InvalidInput(msg) => write!(f, "invalid input: {}", msg),
+            Error::Io(msg) => write!(f, "I/O error: {}", msg),
+            Error::Other(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        match err.kind() {
+            std::io::ErrorKind::TimedOut => Error::TimedOut,
+            _ => Error::Io(err.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_display() {
+        assert_eq!(Error::NotFound.to_string(), "file not found");
+        assert_eq!(
+            Error::ReadOnly.to_string(),
+            "operation not supported: read-only filesystem"
+        );
+        assert_eq!(Error::Other("test error".to_string()).to_string(), "test error");
+    }
+
+    #[test]
+    fn test_error_from_code() {
+        let busy = Error::Busy { retry_after_ms: None };
+        for err in [Error::NotFound, busy, Error::ReadOnly, Error::Cancelled, Error::Io("disk".to_string())] {
+            assert_eq!(Error::from_code(err.code(), "disk"), err);
+        }
+
+        let backoff = Error::Busy {
+            retry_after_ms: Some(1500),
+        };
+        assert_eq!(backoff.to_string(), "backend busy, retry after 1500ms");
+        assert_eq!(Error::from_code(backoff.code(), backoff.to_string()), backoff);
+        assert_eq!(
+            Error::from_code(11, "rate limited"),
+            Error::Busy {
+                retry_after_ms: Some(0)
+            }
+        );
+        assert_eq!(Error::from_code(22, "bad offset"), Error::InvalidInput("bad offset".to_string()));
+        assert_eq!(Error::from_code(999, "odd"), Error::Other("odd".to_string()));
+    }
+
+    #[test]
+    fn test_error_from_http() {
+        assert_eq!(Error::from_http(204, b""), None);
+        assert_eq!(Error::from_http(404, br#"{"message":"Not Found"}"#), Some(Error::NotFound));
+        assert_eq!(Error::from_http(403, b""), Some(Error::PermissionDenied));
+        assert_eq!(Error::from_http(409, b""), Some(Error::AlreadyExists));
+        assert!(matches!(Error::from_http(429, b""), Some(Error::Busy { .. })));
+        assert_eq!(
+            Error::from_http(422, br#"{"message":"Validation Failed","errors":[{"message":"title is empty"}]}"#),
+            Some(Error::InvalidInput("HTTP 422: Validation Failed".to_string()))
+        );
+        assert_eq!(
+            Error::from_http(500, br#"{"error":{"code":13,"message":"backend exploded"}}"#),
+            Some(Error::Io("HTTP 500: backend exploded".to_string()))
+        );
+        assert_eq!(
+            Error::from_http(502, b"<html><body>Bad Gateway</body></html>"),
+            Some(Error::Io("HTTP 502".to_string()))
+        );
+        assert_eq!(
+            Error::from_http(400, b"missing parameter q\n"),
+            Some(Error::InvalidInput("HTTP 400: missing parameter q".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_io_error_conversion() {
+        let io_err = std::io::Error::new(std::io::ErrorKind::NotFound, "test");
+        let fs_err: Error = io_err.into();
+        assert!(matches!(fs_err, Error::Io(_)));
+
+        let timeout = std::io::Error::new(std::io::ErrorKind::TimedOut, "slow");
+        assert_eq!(Error::from(timeout), Error::TimedOut);
+    }
+
+    #[test]
+    fn test_error_codes() {
+        assert_eq!(Error::NotFound.code(), 2);
+        assert_eq!(Error::PermissionDenied.code(), 13);
+        assert_eq!(Error::ReadOnly.code(), 30);
+        assert_eq!(Error::InvalidInput("x".to_string()).code(), 22);
+        assert_eq!(Error::NoAttribute.code(), 61);
+        assert_eq!(Error::TimedOut.code(), 110);
+        assert_eq!(Error::Busy { retry_after_ms: None }.code(), 16);
+        assert_eq!(Error::Busy { retry_after_ms: Some(0) }.code(), 11);
+        assert_eq!(Error::Stale.code(), 116);
+        assert_eq!(Error::Cancelled.code(), 125);
+        assert_eq!(Error::Other("x".to_string()).code(), 5);
+    }
+}
@@ -0,0 +1,633 @@
+//! Expiry of stale virtual entries
+//!
+//! Cache-like plugins, such as pastes, temporary shares or a weather
+//! forecast, serve entries that are only good for a while. Plugins mark them
+//! with [`FileInfo::with_expiry`], and [`ExpiryFs`] stops serving them once
+//! that time has passed:
+//!
+//! ```ignore
+//! fn stat(&self, path: &str) -> Result<FileInfo> {
+//!     let paste = self.pastes.get(path).ok_or(Error::NotFound)?;
+//!     Ok(FileInfo::file(&paste.id, paste.size, 0o444).with_expiry(paste.created + 3600))
+//! }
+//!
+//! type Exported = ExpiryFs<PasteFS>;
+//! export_plugin!(Exported);
+//! ```
+//!
+//! An expired entry is hidden from listings and fails with `NotFound` when
+//! accessed, and is removed from the plugin on the next call that can
+//! change it: a write, `maintain`, or `expire` written to `/.agfs/ctl`,
+//! which also sweeps the whole tree. Plugins that can fetch a fresh copy
+//! instead register a refresh function with [`ExpiryFs::refresh_with`],
+//! which is called when an expired entry is accessed.
+
+use crate::error::{Error, Result};
+use crate::filesystem::{filter_entries, split_batch, walk_tree, FileSystem, HandleFS, StreamFS, UploadFS};
+use crate::types::{
+    Advice, Capabilities, Config, ConfigParameter, FileInfo, FsOp, FsSchema, OpenFlag, UploadSession, WarmupProgress,
+    WriteFlag,
+};
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+/// Filesystem wrapper hiding and removing entries past their `expires_at`
+///
+/// Every access by path stats the entry first, so plugins should answer
+/// `stat` cheaply. Listings are filtered after the plugin pages them, so a
+/// page may hold fewer than `limit` entries.
+pub struct ExpiryFs<F> {
+    inner: F,
+    clock: fn() -> Duration,
+    refresh: Option<fn(&F, &str) -> Result<()>>,
+    /// Expired paths seen since the last purge, in removal order
+    expired: Mutex<BTreeSet<String>>,
+    hidden: AtomicU64,
+    refreshed: AtomicU64,
+    removed: AtomicU64,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<F: Default> Default for ExpiryFs<F> {
+    fn default() -> Self {
+        Self::new(F::default())
+    }
+}
+
+impl<F> ExpiryFs<F> {
+    /// Wrap `inner`, expiring its entries by the system clock
+    ///
+    /// WASM plugins have no clock of their own; use [`ExpiryFs::with_clock`].
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn new(inner: F) -> Self {
+        Self::with_clock(inner, || {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+        })
+    }
+
+    /// Wrap `inner`, with `clock` giving the time since the Unix epoch
+    pub fn with_clock(inner: F, clock: fn() -> Duration) -> Self {
+        Self {
+            inner,
+            clock,
+            refresh: None,
+            expired: Mutex::new(BTreeSet::new()),
+            hidden: AtomicU64::new(0),
+            refreshed: AtomicU64::new(0),
+            removed: AtomicU64::new(0),
+        }
+    }
+
+    /// Call `refresh` with the path of an expired entry when it is accessed,
+    /// rather than hiding it
+    ///
+    /// The entry is served if it is fresh afterwards. A failed refresh fails
+    /// the access and leaves the entry out of listings.
+    pub fn refresh_with(mut self, refresh: fn(&F, &str) -> Result<()>) -> Self {
+        self.refresh = Some(refresh);
+        self
+    }
+
+    /// The wrapped filesystem
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    fn now(&self) -> i64 {
+        (self.clock)().as_secs() as i64
+    }
+
+    fn expired(&self) -> MutexGuard<'_, BTreeSet<String>> {
+        self.expired.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<F: FileSystem> ExpiryFs<F> {
+    /// `info` of the entry at `path` if it is fresh, refreshed if possible;
+    /// otherwise `NotFound`, queueing the entry for removal
+    fn fresh(&self, path: &str, info: FileInfo) -> Result<FileInfo> {
+        if !info.is_expired(self.now()) {
+            return Ok(info);
+        }
+        if let Some(refresh) = self.refresh {
+            refresh(&self.inner, path)?;
+            let info = self.inner.stat(path)?;
+            if !info.is_expired(self.now()) {
+                self.refreshed.fetch_add(1, Ordering::Relaxed);
+                return Ok(info);
+            }
+        }
+        self.hidden.fetch_add(1, Ordering::Relaxed);
+        self.expired().insert(path.trim_end_matches('/').to_string());
+        Err(Error::NotFound)
+    }
+
+    fn check(&self, path: &str) -> Result<FileInfo> {
+        self.fresh(path, self.inner.stat(path)?)
+    }
+
+    /// `NotFound` if the directory `path` has expired; directories the
+    /// plugin cannot stat are listed as before
+    fn check_dir(&self, path: &str) -> Result<()> {
+        match self.inner.stat(path) {
+            Ok(info) => self.fresh(path, info).map(drop),
+            Err(_) => Ok(()),
+        }
+    }
+
+    /// The fresh ones among the entries of the directory `path`
+    fn fresh_entries(&self, path: &str, entries: Vec<FileInfo>) -> Vec<FileInfo> {
+        let dir = path.trim_end_matches('/');
+        entries
+            .into_iter()
+            .filter_map(|entry| self.fresh(&format!("{}/{}", dir, entry.name), entry).ok())
+            .collect()
+    }
+
+    /// Remove expired `paths` and the queued entries from the plugin before
+    /// a call that may change them
+    fn settle(&mut self, paths: &[&str]) {
+        for path in paths {
+            if let Ok(info) = self.inner.stat(path) {
+                let _ = self.fresh(path, info);
+            }
+        }
+        self.purge();
+    }
+
+    /// Remove the expired entries seen since the last purge from the plugin,
+    /// returning how many were removed
+    ///
+    /// Entries refreshed or replaced in the meantime are kept. Removal
+    /// failures are not retried until the entry is seen again.
+    pub fn purge(&mut self) -> usize {
+        let queued = std::mem::take(&mut *self.expired());
+        let now = self.now();
+        let mut removed = 0;
+        // Parents sort before their children, which go with them
+        for path in queued {
+            let removal = match self.inner.stat(&path) {
+                Ok(info) if info.is_expired(now) && info.is_dir() => self.inner.remove_all(&path),
+                Ok(info) if info.is_expired(now) => self.inner.remove(&path),
+                _ => continue,
+            };
+            if removal.is_ok() {
+                removed += 1;
+            }
+        }
+        self.removed.fetch_add(removed as u64, Ordering::Relaxed);
+        removed
+    }
+}
+
+impl<F: FileSystem> FileSystem for ExpiryFs<F> {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn readme(&self) -> &str {
+        self.inner.readme()
+    }
+
+    fn config_params(&self) -> Vec<ConfigParameter> {
+        self.inner.config_params()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    fn schema(&self) -> FsSchema {
+        self.inner.schema()
+    }
+
+    fn validate(&self, config: &Config) -> Result<()> {
+        self.inner.validate(config)
+    }
+
+    fn initialize(&mut self, config: &Config) -> Result<()> {
+        self.inner.initialize(config)
+    }
+
+    fn shutdown(&mut self) -> Result<()> {
+        self.inner.shutdown()
+    }
+
+    /// The plugin's stats plus the expired entries hidden, refreshed and
+    /// removed
+    fn stats(&self) -> serde_json::Value {
+        let mut stats = match self.inner.stats() {
+            serde_json::Value::Null => serde_json::json!({}),
+            stats => stats,
+        };
+        if let serde_json::Value::Object(fields) = &mut stats {
+            fields.insert("expired_hidden".to_string(), self.hidden.load(Ordering::Relaxed).into());
+            fields.insert(
+                "expired_refreshed".to_string(),
+                self.refreshed.load(Ordering::Relaxed).into(),
+            );
+            fields.insert("expired_removed".to_string(), self.removed.load(Ordering::Relaxed).into());
+        }
+        stats
+    }
+
+    fn health(&self) -> Result<()> {
+        self.inner.health()
+    }
+
+    /// `expire` sweeps the whole tree and removes every expired entry
+    fn ctl(&mut self, command: &str) -> Result<()> {
+        match command.trim() {
+            "expire" => {
+                walk_tree(self, "/", 0)?;
+                self.purge();
+                Ok(())
+            }
+            _ => self.inner.ctl(command),
+        }
+    }
+
+    fn maintain(&mut self) -> Result<()> {
+        self.purge();
+        self.inner.maintain()
+    }
+
+    fn warmup(&mut self, budget: Duration) -> Result<WarmupProgress> {
+        self.inner.warmup(budget)
+    }
+
+    fn export_state(&self) -> Result<Vec<u8>> {
+        self.inner.export_state()
+    }
+
+    fn import_state(&mut self, state: &[u8]) -> Result<()> {
+        self.inner.import_state(state)
+    }
+
+    fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+        self.check(path)?;
+        self.inner.read(path, offset, size)
+    }
+
+    fn poll_read(&self, path: &str, offset: i64, size: i64, timeout: Duration) -> Result<Vec<u8>> {
+        self.check(path)?;
+        self.inner.poll_read(path, offset, size, timeout)
+    }
+
+    fn stat(&self, path: &str) -> Result<FileInfo> {
+        self.check(path)
+    }
+
+    fn readdir(&self, path: &str) -> Result<Vec<FileInfo>> {
+        self.check_dir(path)?;
+        Ok(self.fresh_entries(path, self.inner.readdir(path)?))
+    }
+
+    fn readdir_page(&self, path: &str, offset: usize, limit: usize) -> Result<Vec<FileInfo>> {
+        self.check_dir(path)?;
+        Ok(self.fresh_entries(path, self.inner.readdir_page(path, offset, limit)?))
+    }
+
+    fn readdir_filtered(&self, path: &str, glob: &str, limit: usize) -> Result<Vec<FileInfo>> {
+        // Filtered here, so expired entries do not count towards the limit
+        Ok(filter_entries(self.readdir(path)?, glob, limit))
+    }
+
+    /// Walked through [`Self::readdir`], so expired directories are not
+    /// descended into
+    fn walk(&self, path: &str, depth: usize) -> Result<Vec<(String, FileInfo)>> {
+        walk_tree(self, path, depth)
+    }
+
+    fn dir_generation(&self, path: &str) -> u64 {
+        self.inner.dir_generation(path)
+    }
+
+    fn write(&mut self, path: &str, data: &[u8], offset: i64, flags: WriteFlag) -> Result<i64> {
+        self.settle(&[path]);
+        self.inner.write(path, data, offset, flags)
+    }
+
+    fn create(&mut self, path: &str) -> Result<()> {
+        self.settle(&[path]);
+        self.inner.create(path)
+    }
+
+    fn mkdir(&mut self, path: &str, perm: u32) -> Result<()> {
+        self.settle(&[path]);
+        self.inner.mkdir(path, perm)
+    }
+
+    fn remove(&mut self, path: &str) -> Result<()> {
+        self.settle(&[path]);
+        self.inner.remove(path)
+    }
+
+    fn remove_all(&mut self, path: &str) -> Result<()> {
+        self.settle(&[path]);
+        self.inner.remove_all(path)
+    }
+
+    fn rename(&mut self, old_path: &str, new_path: &str) -> Result<()> {
+        self.settle(&[old_path, new_path]);
+        self.inner.rename(old_path, new_path)
+    }
+
+    fn chmod(&mut self, path: &str, mode: u32) -> Result<()> {
+        self.settle(&[path]);
+        self.inner.chmod(path, mode)
+    }
+
+    fn symlink(&mut self, target: &str, link_path: &str) -> Result<()> {
+        self.settle(&[link_path]);
+        self.inner.symlink(target, link_path)
+    }
+
+    fn readlink(&self, path: &str) -> Result<String> {
+        self.check(path)?;
+        self.inner.readlink(path)
+    }
+
+    fn get_xattr(&self, path: &str, name: &str) -> Result<Vec<u8>> {
+        self.check(path)?;
+        self.inner.get_xattr(path, name)
+    }
+
+    fn set_xattr(&mut self, path: &str, name: &str, value: &[u8]) -> Result<()> {
+        self.settle(&[path]);
+        self.inner.set_xattr(path, name, value)
+    }
+
+    fn list_xattr(&self, path: &str) -> Result<Vec<String>> {
+        self.check(path)?;
+        self.inner.list_xattr(path)
+    }
+
+    fn read_if_changed(&self, path: &str, etag: &str) -> Result<Option<Vec<u8>>> {
+        self.check(path)?;
+        self.inner.read_if_changed(path, etag)
+    }
+
+    /// Expired paths fail individually; the rest go to the plugin as one batch
+    fn read_many(&self, paths: &[&str]) -> Vec<Result<Vec<u8>>> {
+        split_batch(
+            paths,
+            |path| self.check(path).err().map(Err),
+            |fresh| self.inner.read_many(fresh),
+        )
+    }
+
+    fn write_if(&mut self, path: &str, data: &[u8], expected_etag: &str) -> Result<i64> {
+        self.settle(&[path]);
+        self.inner.write_if(path, data, expected_etag)
+    }
+
+    fn batch(&mut self, ops: Vec<FsOp>) -> Vec<Result<()>> {
+        let paths: Vec<&str> = ops.iter().flat_map(FsOp::paths).collect();
+        self.settle(&paths);
+        self.inner.batch(ops)
+    }
+
+    fn atomic_batch(&self) -> bool {
+        self.inner.atomic_batch()
+    }
+
+    fn begin_write_session(&mut self) -> Result<()> {
+        self.inner.begin_write_session()
+    }
+
+    fn commit_session(&mut self, message: &str) -> Result<()> {
+        self.inner.commit_session(message)
+    }
+
+    fn abort_session(&mut self) -> Result<()> {
+        self.inner.abort_session()
+    }
+
+    fn advise(&self, path: &str, offset: i64, len: i64, advice: Advice) -> Result<()> {
+        self.check(path)?;
+        self.inner.advise(path, offset, len, advice)
+    }
+}
+
+/// Expiry is checked when a handle is opened; an open handle keeps working
+/// after its file expires
+impl<F: HandleFS> HandleFS for ExpiryFs<F> {
+    fn open_handle(&mut self, path: &str, flags: OpenFlag, mode: u32) -> Result<i64> {
+        self.settle(&[path]);
+        self.inner.open_handle(path, flags, mode)
+    }
+
+    fn handle_read(&mut self, id: i64, buf: &mut [u8]) -> Result<usize> {
+        self.inner.handle_read(id, buf)
+    }
+
+    fn handle_read_at(&self, id: i64, buf: &mut [u8], offset: i64) -> Result<usize> {
+        self.inner.handle_read_at(id, buf, offset)
+    }
+
+    fn handle_write(&mut self, id: i64, data: &[u8]) -> Result<usize> {
+        self.inner.handle_write(id, data)
+    }
+
+    fn handle_write_at(&self, id: i64, data: &[u8], offset: i64) -> Result<usize> {
+        self.inner.handle_write_at(id, data, offset)
+    }
+
+    fn handle_seek(&mut self, id: i64, offset: i64, whence: i32) -> Result<i64> {
+        self.inner.handle_seek(id, offset, whence)
+    }
+
+    fn handle_sync(&self, id: i64) -> Result<()> {
+        self.inner.handle_sync(id)
+    }
+
+    fn handle_stat(&self, id: i64) -> Result<FileInfo> {
+        self.inner.handle_stat(id)
+    }
+
+    fn handle_info(&self, id: i64) -> Result<(String, OpenFlag)> {
+        self.inner.handle_info(id)
+    }
+
+    fn close_handle(&mut self, id: i64) -> Result<()> {
+        self.inner.close_handle(id)
+    }
+}
+
+impl<F: StreamFS> StreamFS for ExpiryFs<F> {
+    fn open_stream(&mut self, path: &str) -> Result<i64> {
+        self.check(path)?;
+        self.inner.open_stream(path)
+    }
+
+    fn stream_read(&mut self, id: i64, buf: &mut [u8]) -> Result<Option<usize>> {
+        self.inner.stream_read(id, buf)
+    }
+
+    fn stream_write(&mut self, id: i64, data: &[u8]) -> Result<usize> {
+        self.inner.stream_write(id, data)
+    }
+
+    fn close_stream(&mut self, id: i64) -> Result<()> {
+        self.inner.close_stream(id)
+    }
+}
+
+impl<F: UploadFS> UploadFS for ExpiryFs<F> {
+    fn begin_upload(&mut self, path: &str) -> Result<UploadSession> {
+        self.settle(&[path]);
+        self.inner.begin_upload(path)
+    }
+
+    fn upload_status(&self, id: &str) -> Result<UploadSession> {
+        self.inner.upload_status(id)
+    }
+
+    fn upload_append(&mut self, id: &str, offset: u64, data: &[u8]) -> Result<UploadSession> {
+        self.inner.upload_append(id, offset, data)
+    }
+
+    fn commit_upload(&mut self, id: &str) -> Result<()> {
+        self.inner.commit_upload(id)
+    }
+
+    fn abort_upload(&mut self, id: &str) -> Result<()> {
+        self.inner.abort_upload(id)
+    }
+
+    fn list_uploads(&self) -> Result<Vec<UploadSession>> {
+        self.inner.list_uploads()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::read_range;
+    use std::collections::BTreeMap;
+
+    /// Files with an expiry time each; `/weather` is refetched for another
+    /// hour when refreshed
+    #[derive(Default)]
+    struct PasteFS {
+        files: BTreeMap<String, (Vec<u8>, Option<i64>)>,
+        fetches: Mutex<u32>,
+    }
+
+    impl PasteFS {
+        fn refetch(&self, path: &str) -> Result<()> {
+            match path {
+                "/weather" => {
+                    *self.fetches.lock().unwrap() += 1;
+                    Ok(())
+                }
+                _ => Err(Error::Io("upstream unavailable".to_string())),
+            }
+        }
+    }
+
+    impl FileSystem for PasteFS {
+        fn name(&self) -> &str {
+            "pastefs"
+        }
+
+        fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+            let (data, _) = self.files.get(path).ok_or(Error::NotFound)?;
+            Ok(read_range(data, offset, size))
+        }
+
+        fn stat(&self, path: &str) -> Result<FileInfo> {
+            if path == "/" {
+                return Ok(FileInfo::dir("", 0o755));
+            }
+            let (data, expires_at) = self.files.get(path).ok_or(Error::NotFound)?;
+            let info = FileInfo::file(&path[1..], data.len() as i64, 0o644);
+            match (path, expires_at) {
+                ("/weather", Some(at)) => Ok(info.with_expiry(at + 3600 * *self.fetches.lock().unwrap() as i64)),
+                (_, Some(at)) => Ok(info.with_expiry(*at)),
+                (_, None) => Ok(info),
+            }
+        }
+
+        fn readdir(&self, _path: &str) -> Result<Vec<FileInfo>> {
+            self.files.keys().map(|path| self.stat(path)).collect()
+        }
+
+        fn write(&mut self, path: &str, data: &[u8], _offset: i64, _flags: WriteFlag) -> Result<i64> {
+            self.files.insert(path.to_string(), (data.to_vec(), None));
+            Ok(data.len() as i64)
+        }
+
+        fn remove(&mut self, path: &str) -> Result<()> {
+            self.files.remove(path).map(drop).ok_or(Error::NotFound)
+        }
+    }
+
+    fn clock() -> Duration {
+        Duration::from_secs(1_000)
+    }
+
+    fn pastes() -> PasteFS {
+        let mut fs = PasteFS::default();
+        for (path, expires_at) in [("/kept", None), ("/fresh", Some(1_001)), ("/stale", Some(1_000))] {
+            fs.files.insert(path.to_string(), (b"paste".to_vec(), expires_at));
+        }
+        fs
+    }
+
+    fn names(entries: Vec<FileInfo>) -> Vec<String> {
+        entries.into_iter().map(|entry| entry.name).collect()
+    }
+
+    #[test]
+    fn test_expired_entries_are_hidden_and_removed() {
+        let mut fs = ExpiryFs::with_clock(pastes(), clock);
+        assert_eq!(fs.read("/fresh", 0, -1).unwrap(), b"paste");
+        assert!(matches!(fs.stat("/stale"), Err(Error::NotFound)));
+        assert_eq!(fs.read("/stale", 0, -1), Err(Error::NotFound));
+        assert_eq!(names(fs.readdir("/").unwrap()), ["fresh", "kept"]);
+        let results = fs.read_many(&["/stale", "/kept"]);
+        assert_eq!(results[0], Err(Error::NotFound));
+        assert_eq!(results[1].as_deref().unwrap(), b"paste");
+        assert!(fs.inner().files.contains_key("/stale"), "removed only on a mutable call");
+
+        fs.maintain().unwrap();
+        assert!(!fs.inner().files.contains_key("/stale"));
+        assert_eq!(fs.stats()["expired_removed"], 1);
+
+        // Writing over an expired entry does not lose the new content
+        fs.inner.files.insert("/stale".to_string(), (b"old".to_vec(), Some(0)));
+        assert!(matches!(fs.stat("/stale"), Err(Error::NotFound)));
+        fs.write("/stale", b"new", 0, WriteFlag::CREATE).unwrap();
+        fs.maintain().unwrap();
+        assert_eq!(fs.read("/stale", 0, -1).unwrap(), b"new");
+
+        // A sweep finds entries nobody accessed
+        fs.inner.files.insert("/unseen".to_string(), (b"x".to_vec(), Some(999)));
+        fs.ctl("expire").unwrap();
+        assert!(!fs.inner().files.contains_key("/unseen"));
+        assert_eq!(fs.stats()["expired_removed"], 3);
+    }
+
+    #[test]
+    fn test_refresh() {
+        let mut inner = pastes();
+        inner.files.insert("/weather".to_string(), (b"sunny".to_vec(), Some(900)));
+        let mut fs = ExpiryFs::with_clock(inner, clock).refresh_with(PasteFS::refetch);
+        assert_eq!(fs.stat("/weather").unwrap().expires_at, Some(4_500));
+        assert_eq!(fs.read("/weather", 0, -1).unwrap(), b"sunny");
+        assert_eq!(*fs.inner().fetches.lock().unwrap(), 1);
+        assert_eq!(fs.stats()["expired_refreshed"], 1);
+
+        // A failed refresh fails the access but does not remove the entry
+        assert!(matches!(fs.read("/stale", 0, -1), Err(Error::Io(_))));
+        assert_eq!(names(fs.readdir("/").unwrap()), ["fresh", "kept", "weather"]);
+        fs.maintain().unwrap();
+        assert!(fs.inner().files.contains_key("/stale"));
+    }
+}
@@ -0,0 +1,727 @@
+//! FIFO files: reads take the next message, writes enqueue one
+//!
+//! Some files are channels rather than documents: a prompt file whose reply
+//! is read back from the same path, or a job queue fed by one shell and
+//! drained by another. [`FifoFs`] serves the paths a plugin declares in
+//! [`FifoFiles`]. Each holds a queue of messages; a write appends one, a
+//! read takes the next (or the next `size` bytes of it) and returns no data
+//! when the queue is empty. Hosts that support `poll_read` (the HTTP API's
+//! `wait=`) keep such a read open until a message arrives:
+//!
+//! ```ignore
+//! impl FifoFiles for LlmFs {
+//!     fn fifos() -> Fifos<Self> {
+//!         // echo "Why is the sky blue?" > /ask && cat /ask
+//!         Fifos::new().command("/ask", Self::complete).fifo("/jobs", 0o666)
+//!     }
+//! }
+//!
+//! type Exported = FifoFs<LlmFs>;
+//! export_plugin!(Exported);
+//! ```
+//!
+//! FIFO paths are listed in their parent directory, which the plugin must
+//! provide, and are reported with [`FileKind::Fifo`] and an unknown size.
+//! Everything else passes through to the plugin.
+
+use crate::error::{Error, Result};
+use crate::filesystem::{
+    filter_entries, reject_batch, split_batch, walk_tree, FileSystem, HandleFS, StreamFS, UploadFS,
+};
+use crate::types::{
+    Advice, Capabilities, Config, ConfigParameter, FileInfo, FileKind, FsOp, FsSchema, OpenFlag, PathSchema,
+    UploadSession, WarmupProgress, WriteFlag,
+};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+/// Messages a FIFO holds before writes fail with `Busy`
+pub const DEFAULT_CAPACITY: usize = 1024;
+
+/// Runs a message written to a command FIFO and returns the reply to queue
+pub type CommandHandler<F> = fn(&mut F, &[u8]) -> Result<Vec<u8>>;
+
+struct Fifo<F> {
+    path: String,
+    mode: u32,
+    handler: Option<CommandHandler<F>>,
+}
+
+/// The FIFO files a plugin serves, as declared by [`FifoFiles::fifos`]
+pub struct Fifos<F> {
+    entries: Vec<Fifo<F>>,
+    capacity: usize,
+}
+
+impl<F> Default for Fifos<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F> Fifos<F> {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            capacity: DEFAULT_CAPACITY,
+        }
+    }
+
+    /// Serve `path` as a FIFO: whatever is written to it is read back in
+    /// order, one message per write
+    pub fn fifo(mut self, path: impl Into<String>, mode: u32) -> Self {
+        self.entries.push(Fifo {
+            path: path.into(),
+            mode,
+            handler: None,
+        });
+        self
+    }
+
+    /// Serve `path` as a command FIFO: each message written to it is passed
+    /// to `handler`, and the reply is queued for readers of the same path
+    ///
+    /// The write fails with the handler's error, queueing nothing; an empty
+    /// reply is not queued either.
+    pub fn command(mut self, path: impl Into<String>, handler: CommandHandler<F>) -> Self {
+        self.entries.push(Fifo {
+            path: path.into(),
+            mode: 0o666,
+            handler: Some(handler),
+        });
+        self
+    }
+
+    /// Hold at most `capacity` messages per FIFO
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+}
+
+/// A plugin serving FIFO files through [`FifoFs`]
+pub trait FifoFiles: Sized {
+    /// The FIFOs to serve, e.g. `Fifos::new().command("/ask", Self::ask)`
+    fn fifos() -> Fifos<Self>;
+}
+
+/// Filesystem wrapper serving the plugin's [`FifoFiles`]
+///
+/// Export the wrapper in place of the filesystem:
+///
+/// ```ignore
+/// type Exported = FifoFs<MyFS>;
+/// export_plugin!(Exported);
+/// ```
+pub struct FifoFs<F> {
+    inner: F,
+    fifos: Fifos<F>,
+    queues: Mutex<HashMap<String, VecDeque<Vec<u8>>>>,
+}
+
+impl<F: FifoFiles + Default> Default for FifoFs<F> {
+    fn default() -> Self {
+        Self::new(F::default())
+    }
+}
+
+impl<F: FifoFiles> FifoFs<F> {
+    /// Wrap `inner`, serving the FIFOs it declares
+    pub fn new(inner: F) -> Self {
+        Self {
+            inner,
+            fifos: F::fifos(),
+            queues: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<F> FifoFs<F> {
+    /// The wrapped filesystem
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    fn queues(&self) -> MutexGuard<'_, HashMap<String, VecDeque<Vec<u8>>>> {
+        self.queues.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn find(&self, path: &str) -> Option<&Fifo<F>> {
+        let path = normalize(path);
+        self.fifos.entries.iter().find(|fifo| normalize(&fifo.path) == path)
+    }
+
+    fn is_fifo(&self, path: &str) -> bool {
+        self.find(path).is_some()
+    }
+
+    /// Queue `message` on the FIFO at `path`, as a write from a client would
+    /// without running a command handler
+    ///
+    /// Fails with `NotFound` if `path` is not a FIFO and `Busy` if it is full.
+    pub fn push(&self, path: &str, message: impl Into<Vec<u8>>) -> Result<()> {
+        let fifo = self.find(path).ok_or(Error::NotFound)?;
+        let mut queues = self.queues();
+        let queue = queues.entry(fifo.path.clone()).or_default();
+        if queue.len() >= self.fifos.capacity {
+            return Err(Error::Busy { retry_after_ms: None });
+        }
+        queue.push_back(message.into());
+        Ok(())
+    }
+
+    /// Take the next message of the FIFO at `path`, whole
+    pub fn pop(&self, path: &str) -> Option<Vec<u8>> {
+        self.take(path, -1)
+    }
+
+    /// Messages waiting in the FIFO at `path`
+    pub fn pending(&self, path: &str) -> usize {
+        self.find(path)
+            .and_then(|fifo| self.queues().get(&fifo.path).map(VecDeque::len))
+            .unwrap_or(0)
+    }
+
+    /// Up to `size` bytes of the next message (all of it if `size` is
+    /// negative), leaving the rest for the next read
+    fn take(&self, path: &str, size: i64) -> Option<Vec<u8>> {
+        let fifo = self.find(path)?;
+        let mut queues = self.queues();
+        let queue = queues.get_mut(&fifo.path)?;
+        let front = queue.front_mut()?;
+        match usize::try_from(size) {
+            Ok(size) if size < front.len() => Some(front.drain(..size).collect()),
+            _ => queue.pop_front(),
+        }
+    }
+
+    fn info(&self, fifo: &Fifo<F>) -> FileInfo {
+        let name = fifo.path.rsplit('/').next().unwrap_or_default();
+        FileInfo {
+            kind: FileKind::Fifo,
+            ..FileInfo::generated(name, fifo.mode)
+        }
+    }
+
+    /// FIFOs directly inside the directory `path`
+    fn children(&self, path: &str) -> Vec<FileInfo> {
+        let dir = normalize(path);
+        self.fifos
+            .entries
+            .iter()
+            .filter(|fifo| parent(normalize(&fifo.path)) == dir)
+            .map(|fifo| self.info(fifo))
+            .collect()
+    }
+
+    /// Whether any FIFO lies at or below the directory `path`
+    fn has_descendants(&self, path: &str) -> bool {
+        let dir = normalize(path);
+        self.fifos.entries.iter().any(|fifo| {
+            let fifo = normalize(&fifo.path);
+            dir == "/" || fifo.strip_prefix(dir).is_some_and(|rest| rest.starts_with('/'))
+        })
+    }
+
+    fn touches_fifo(&self, op: &FsOp) -> bool {
+        op.paths().into_iter().any(|path| self.is_fifo(path))
+    }
+}
+
+/// `path` without trailing slashes, `/` for the root
+fn normalize(path: &str) -> &str {
+    match path.trim_end_matches('/') {
+        "" => "/",
+        path => path,
+    }
+}
+
+fn parent(path: &str) -> &str {
+    match path.rfind('/') {
+        Some(0) | None => "/",
+        Some(i) => &path[..i],
+    }
+}
+
+impl<F: FileSystem> FifoFs<F> {
+    fn enqueue(&mut self, path: &str, data: &[u8]) -> Result<i64> {
+        let fifo = self.find(path).ok_or(Error::NotFound)?;
+        let (path, handler) = (fifo.path.clone(), fifo.handler);
+        match handler {
+            Some(handler) => {
+                let reply = handler(&mut self.inner, data)?;
+                if !reply.is_empty() {
+                    self.push(&path, reply)?;
+                }
+            }
+            None => self.push(&path, data)?,
+        }
+        Ok(data.len() as i64)
+    }
+}
+
+impl<F: FileSystem> FileSystem for FifoFs<F> {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn readme(&self) -> &str {
+        self.inner.readme()
+    }
+
+    fn config_params(&self) -> Vec<ConfigParameter> {
+        self.inner.config_params()
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    /// The plugin's schema followed by the FIFOs
+    fn schema(&self) -> FsSchema {
+        let mut schema = self.inner.schema();
+        schema.paths.extend(self.fifos.entries.iter().map(|fifo| {
+            let description = match fifo.handler {
+                Some(_) => "Command FIFO; write a request, then read the reply",
+                None => "FIFO; each read takes the next message written",
+            };
+            PathSchema::file(fifo.path.clone(), description).writable()
+        }));
+        schema
+    }
+
+    fn validate(&self, config: &Config) -> Result<()> {
+        self.inner.validate(config)
+    }
+
+    fn initialize(&mut self, config: &Config) -> Result<()> {
+        self.inner.initialize(config)
+    }
+
+    fn shutdown(&mut self) -> Result<()> {
+        self.inner.shutdown()
+    }
+
+    /// The plugin's stats plus the messages waiting in each FIFO
+    fn stats(&self) -> serde_json::Value {
+        let mut stats = self.inner.stats();
+        if let serde_json::Value::Object(fields) = &mut stats {
+            let pending = self
+                .fifos
+                .entries
+                .iter()
+                .map(|fifo| (fifo.path.clone(), self.pending(&fifo.path).into()));
+            fields.insert("fifo_pending".to_string(), serde_json::Value::Object(pending.collect()));
+        }
+        stats
+    }
+
+    fn health(&self) -> Result<()> {
+        self.inner.health()
+    }
+
+    fn ctl(&mut self, command: &str) -> Result<()> {
+        self.inner.ctl(command)
+    }
+
+    fn maintain(&mut self) -> Result<()> {
+        self.inner.maintain()
+    }
+
+    fn warmup(&mut self, budget: Duration) -> Result<WarmupProgress> {
+        self.inner.warmup(budget)
+    }
+
+    fn export_state(&self) -> Result<Vec<u8>> {
+        self.inner.export_state()
+    }
+
+    fn import_state(&mut self, state: &[u8]) -> Result<()> {
+        self.inner.import_state(state)
+    }
+
+    /// On a FIFO, the next message regardless of `offset`; no data when none
+    /// is waiting
+    fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+        match self.is_fifo(path) {
+            true => Ok(self.take(path, size).unwrap_or_default()),
+            false => self.inner.read(path, offset, size),
+        }
+    }
+
+    /// The host does the waiting: a FIFO answers at once, empty until a
+    /// message arrives
+    fn poll_read(&self, path: &str, offset: i64, size: i64, timeout: Duration) -> Result<Vec<u8>> {
+        match self.is_fifo(path) {
+            true => self.read(path, offset, size),
+            false => self.inner.poll_read(path, offset, size, timeout),
+        }
+    }
+
+    fn stat(&self, path: &str) -> Result<FileInfo> {
+        match self.find(path) {
+            Some(fifo) => Ok(self.info(fifo)),
+            None => self.inner.stat(path),
+        }
+    }
+
+    /// The plugin's entries followed by the FIFOs in `path`, which hide
+    /// plugin entries of the same name
+    fn readdir(&self, path: &str) -> Result<Vec<FileInfo>> {
+        let fifos = self.children(path);
+        let mut entries = self.inner.readdir(path)?;
+        if !fifos.is_empty() {
+            entries.retain(|entry| !fifos.iter().any(|fifo| fifo.name == entry.name));
+            entries.extend(fifos);
+        }
+        Ok(entries)
+    }
+
+    fn readdir_page(&self, path: &str, offset: usize, limit: usize) -> Result<Vec<FileInfo>> {
+        match self.children(path).is_empty() {
+            true => self.inner.readdir_page(path, offset, limit),
+            false => Ok(self.readdir(path)?.into_iter().skip(offset).take(limit).collect()),
+        }
+    }
+
+    fn readdir_filtered(&self, path: &str, glob: &str, limit: usize) -> Result<Vec<FileInfo>> {
+        match self.children(path).is_empty() {
+            true => self.inner.readdir_filtered(path, glob, limit),
+            false => Ok(filter_entries(self.readdir(path)?, glob, limit)),
+        }
+    }
+
+    fn walk(&self, path: &str, depth: usize) -> Result<Vec<(String, FileInfo)>> {
+        match self.has_descendants(path) {
+            true => walk_tree(self, path, depth),
+            false => self.inner.walk(path, depth),
+        }
+    }
+
+    fn dir_generation(&self, path: &str) -> u64 {
+        self.inner.dir_generation(path)
+    }
+
+    fn write(&mut self, path: &str, data: &[u8], offset: i64, flags: WriteFlag) -> Result<i64> {
+        match self.is_fifo(path) {
+            true => self.enqueue(path, data),
+            false => self.inner.write(path, data, offset, flags),
+        }
+    }
+
+    /// Creating a FIFO that exists succeeds, so `>` redirection works
+    fn create(&mut self, path: &str) -> Result<()> {
+        match self.is_fifo(path) {
+            true => Ok(()),
+            false => self.inner.create(path),
+        }
+    }
+
+    fn mkdir(&mut self, path: &str, perm: u32) -> Result<()> {
+        match self.is_fifo(path) {
+            true => Err(Error::AlreadyExists),
+            false => self.inner.mkdir(path, perm),
+        }
+    }
+
+    fn remove(&mut self, path: &str) -> Result<()> {
+        match self.is_fifo(path) {
+            true => Err(Error::PermissionDenied),
+            false => self.inner.remove(path),
+        }
+    }
+
+    fn remove_all(&mut self, path: &str) -> Result<()> {
+        match self.is_fifo(path) || self.has_descendants(path) {
+            true => Err(Error::PermissionDenied),
+            false => self.inner.remove_all(path),
+        }
+    }
+
+    fn rename(&mut self, old_path: &str, new_path: &str) -> Result<()> {
+        match self.is_fifo(old_path) || self.is_fifo(new_path) {
+            true => Err(Error::PermissionDenied),
+            false => self.inner.rename(old_path, new_path),
+        }
+    }
+
+    fn chmod(&mut self, path: &str, mode: u32) -> Result<()> {
+        match self.is_fifo(path) {
+            true => Err(Error::PermissionDenied),
+            false => self.inner.chmod(path, mode),
+        }
+    }
+
+    fn symlink(&mut self, target: &str, link_path: &str) -> Result<()> {
+        match self.is_fifo(link_path) {
+            true => Err(Error::AlreadyExists),
+            false => self.inner.symlink(target, link_path),
+        }
+    }
+
+    fn readlink(&self, path: &str) -> Result<String> {
+        match self.is_fifo(path) {
+            true => Err(Error::InvalidInput("not a symlink".to_string())),
+            false => self.inner.readlink(path),
+        }
+    }
+
+    fn get_xattr(&self, path: &str, name: &str) -> Result<Vec<u8>> {
+        match self.is_fifo(path) {
+            true => Err(Error::NoAttribute),
+            false => self.inner.get_xattr(path, name),
+        }
+    }
+
+    fn set_xattr(&mut self, path: &str, name: &str, value: &[u8]) -> Result<()> {
+        match self.is_fifo(path) {
+            true => Err(Error::PermissionDenied),
+            false => self.inner.set_xattr(path, name, value),
+        }
+    }
+
+    fn list_xattr(&self, path: &str) -> Result<Vec<String>> {
+        match self.is_fifo(path) {
+            true => Ok(Vec::new()),
+            false => self.inner.list_xattr(path),
+        }
+    }
+
+    /// A FIFO has no version to compare; a waiting message is always new
+    fn read_if_changed(&self, path: &str, etag: &str) -> Result<Option<Vec<u8>>> {
+        match self.is_fifo(path) {
+            true => Ok(self.take(path, -1)),
+            false => self.inner.read_if_changed(path, etag),
+        }
+    }
+
+    fn read_many(&self, paths: &[&str]) -> Vec<Result<Vec<u8>>> {
+        split_batch(
+            paths,
+            |path| self.is_fifo(path).then(|| self.read(path, 0, -1)),
+            |rest| self.inner.read_many(rest),
+        )
+    }
+
+    fn write_if(&mut self, path: &str, data: &[u8], expected_etag: &str) -> Result<i64> {
+        match self.is_fifo(path) {
+            true => Err(Error::PermissionDenied),
+            false => self.inner.write_if(path, data, expected_etag),
+        }
+    }
+
+    /// Messages are queued one write at a time, so a batch touching a FIFO
+    /// is rejected as a whole
+    fn batch(&mut self, ops: Vec<FsOp>) -> Vec<Result<()>> {
+        if let Some(i) = ops.iter().position(|op| self.touches_fifo(op)) {
+            return reject_batch(ops.len(), i, Error::PermissionDenied);
+        }
+        self.inner.batch(ops)
+    }
+
+    fn atomic_batch(&self) -> bool {
+        self.inner.atomic_batch()
+    }
+
+    fn begin_write_session(&mut self) -> Result<()> {
+        self.inner.begin_write_session()
+    }
+
+    fn commit_session(&mut self, message: &str) -> Result<()> {
+        self.inner.commit_session(message)
+    }
+
+    fn abort_session(&mut self) -> Result<()> {
+        self.inner.abort_session()
+    }
+
+    fn advise(&self, path: &str, offset: i64, len: i64, advice: Advice) -> Result<()> {
+        match self.is_fifo(path) {
+            true => Ok(()),
+            false => self.inner.advise(path, offset, len, advice),
+        }
+    }
+}
+
+/// FIFOs cannot be opened as handles or streams; read and write them by path
+impl<F: HandleFS> HandleFS for FifoFs<F> {
+    fn open_handle(&mut self, path: &str, flags: OpenFlag, mode: u32) -> Result<i64> {
+        match self.is_fifo(path) {
+            true => Err(Error::PermissionDenied),
+            false => self.inner.open_handle(path, flags, mode),
+        }
+    }
+
+    fn handle_read(&mut self, id: i64, buf: &mut [u8]) -> Result<usize> {
+        self.inner.handle_read(id, buf)
+    }
+
+    fn handle_read_at(&self, id: i64, buf: &mut [u8], offset: i64) -> Result<usize> {
+        self.inner.handle_read_at(id, buf, offset)
+    }
+
+    fn handle_write(&mut self, id: i64, data: &[u8]) -> Result<usize> {
+        self.inner.handle_write(id, data)
+    }
+
+    fn handle_write_at(&self, id: i64, data: &[u8], offset: i64) -> Result<usize> {
+        self.inner.handle_write_at(id, data, offset)
+    }
+
+    fn handle_seek(&mut self, id: i64, offset: i64, whence: i32) -> Result<i64> {
+        self.inner.handle_seek(id, offset, whence)
+    }
+
+    fn handle_sync(&self, id: i64) -> Result<()> {
+        self.inner.handle_sync(id)
+    }
+
+    fn handle_stat(&self, id: i64) -> Result<FileInfo> {
+        self.inner.handle_stat(id)
+    }
+
+    fn handle_info(&self, id: i64) -> Result<(String, OpenFlag)> {
+        self.inner.handle_info(id)
+    }
+
+    fn close_handle(&mut self, id: i64) -> Result<()> {
+        self.inner.close_handle(id)
+    }
+}
+
+impl<F: StreamFS> StreamFS for FifoFs<F> {
+    fn open_stream(&mut self, path: &str) -> Result<i64> {
+        match self.is_fifo(path) {
+            true => Err(Error::PermissionDenied),
+            false => self.inner.open_stream(path),
+        }
+    }
+
+    fn stream_read(&mut self, id: i64, buf: &mut [u8]) -> Result<Option<usize>> {
+        self.inner.stream_read(id, buf)
+    }
+
+    fn stream_write(&mut self, id: i64, data: &[u8]) -> Result<usize> {
+        self.inner.stream_write(id, data)
+    }
+
+    fn close_stream(&mut self, id: i64) -> Result<()> {
+        self.inner.close_stream(id)
+    }
+}
+
+impl<F: UploadFS> UploadFS for FifoFs<F> {
+    fn begin_upload(&mut self, path: &str) -> Result<UploadSession> {
+        match self.is_fifo(path) {
+            true => Err(Error::PermissionDenied),
+            false => self.inner.begin_upload(path),
+        }
+    }
+
+    fn upload_status(&self, id: &str) -> Result<UploadSession> {
+        self.inner.upload_status(id)
+    }
+
+    fn upload_append(&mut self, id: &str, offset: u64, data: &[u8]) -> Result<UploadSession> {
+        self.inner.upload_append(id, offset, data)
+    }
+
+    fn commit_upload(&mut self, id: &str) -> Result<()> {
+        self.inner.commit_upload(id)
+    }
+
+    fn abort_upload(&mut self, id: &str) -> Result<()> {
+        self.inner.abort_upload(id)
+    }
+
+    fn list_uploads(&self) -> Result<Vec<UploadSession>> {
+        self.inner.list_uploads()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct TestFS {
+        asked: usize,
+    }
+
+    impl FileSystem for TestFS {
+        fn name(&self) -> &str {
+            "testfs"
+        }
+
+        fn stat(&self, path: &str) -> Result<FileInfo> {
+            match path {
+                "/" => Ok(FileInfo::dir("", 0o755)),
+                "/hello" => Ok(FileInfo::file("hello", 2, 0o644)),
+                _ => Err(Error::NotFound),
+            }
+        }
+
+        fn readdir(&self, _path: &str) -> Result<Vec<FileInfo>> {
+            Ok(vec![FileInfo::file("hello", 2, 0o644)])
+        }
+    }
+
+    fn shout(fs: &mut TestFS, prompt: &[u8]) -> Result<Vec<u8>> {
+        fs.asked += 1;
+        match prompt {
+            b"" => Err(Error::InvalidInput("empty prompt".to_string())),
+            _ => Ok(prompt.to_ascii_uppercase()),
+        }
+    }
+
+    impl FifoFiles for TestFS {
+        fn fifos() -> Fifos<Self> {
+            Fifos::new()
+                .fifo("/jobs", 0o666)
+                .command("/ask", shout)
+                .with_capacity(2)
+        }
+    }
+
+    fn fifo_fs() -> FifoFs<TestFS> {
+        FifoFs::default()
+    }
+
+    #[test]
+    fn test_fifo_queue() {
+        let mut fs = fifo_fs();
+        let names: Vec<String> = fs.readdir("/").unwrap().into_iter().map(|e| e.name).collect();
+        assert_eq!(names, ["hello", "jobs", "ask"]);
+        let info = fs.stat("/jobs").unwrap();
+        assert_eq!((info.kind, info.size_known()), (FileKind::Fifo, false));
+
+        assert_eq!(fs.read("/jobs", 0, -1).unwrap(), b"");
+        fs.write("/jobs", b"first", 0, WriteFlag::NONE).unwrap();
+        fs.write("/jobs", b"second", 0, WriteFlag::NONE).unwrap();
+        assert_eq!(
+            fs.write("/jobs", b"third", 0, WriteFlag::NONE),
+            Err(Error::Busy { retry_after_ms: None })
+        );
+        assert_eq!(fs.pending("/jobs"), 2);
+
+        // Reads in small chunks finish a message before starting the next
+        assert_eq!(fs.read("/jobs", 0, 3).unwrap(), b"fir");
+        assert_eq!(fs.read("/jobs", 3, 4096).unwrap(), b"st");
+        assert_eq!(fs.pop("/jobs").unwrap(), b"second");
+        assert_eq!(fs.read("/jobs", 0, -1).unwrap(), b"");
+        assert_eq!(fs.read("/hello", 0, -1), Err(Error::ReadOnly));
+        assert_eq!(fs.remove("/jobs"), Err(Error::PermissionDenied));
+    }
+
+    #[test]
+    fn test_command_fifo() {
+        let mut fs = fifo_fs();
+        assert_eq!(fs.write("/ask/", b"hi", 0, WriteFlag::NONE), Ok(2));
+        assert_eq!(fs.poll_read("/ask", 0, -1, Duration::ZERO).unwrap(), b"HI");
+        assert!(fs.write("/ask", b"", 0, WriteFlag::NONE).is_err());
+        assert_eq!(fs.pending("/ask"), 0);
+        assert_eq!(fs.inner().asked, 2);
+        assert_eq!(fs.push("/hello", "x"), Err(Error::NotFound));
+    }
+}
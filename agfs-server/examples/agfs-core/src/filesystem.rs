@@ -0,0 +1,1099 @@
+//! FileSystem trait definitions shared by the WASM and native SDKs
+
+use crate::error::{Error, Result};
+use crate::types::{
+    Advice, Capabilities, Config, ConfigParameter, FileInfo, FsOp, FsSchema, Listing, OpenFlag, UploadSession,
+    WarmupProgress, WriteFlag,
+};
+use std::time::Duration;
+
+/// Main trait that all filesystem plugins must implement
+///
+/// This trait defines the interface for interacting with a filesystem plugin.
+/// Implementing this trait is all you need to do - the `export_plugin!` macro
+/// of `agfs-wasm-ffi` or `agfs-ffi` generates the bindings for either backend.
+///
+/// Methods taking `&self` may be called concurrently from several host
+/// threads by the native SDK; methods taking `&mut self` run exclusively.
+/// Keep lookups on the `&self` side so a slow read does not block unrelated
+/// operations.
+///
+/// # Example
+///
+/// ```rust
+/// use agfs_core::prelude::*;
+///
+/// #[derive(Default)]
+/// struct MyFS {
+///     initialized: bool,
+/// }
+///
+/// impl FileSystem for MyFS {
+///     fn name(&self) -> &str {
+///         "my-fs"
+///     }
+///
+///     fn readme(&self) -> &str {
+///         "# My Filesystem Plugin\n\nA custom filesystem implementation."
+///     }
+///
+///     fn initialize(&mut self, _config: &Config) -> Result<()> {
+///         self.initialized = true;
+///         Ok(())
+///     }
+///
+///     fn read(&self, path: &str, _offset: i64, _size: i64) -> Result<Vec<u8>> {
+///         if path == "/hello" {
+///             Ok(b"Hello, World!".to_vec())
+///         } else {
+///             Err(Error::NotFound)
+///         }
+///     }
+///
+///     fn stat(&self, path: &str) -> Result<FileInfo> {
+///         if path == "/" || path == "/hello" {
+///             Ok(FileInfo::file("hello", 13, 0o644))
+///         } else {
+///             Err(Error::NotFound)
+///         }
+///     }
+///
+///     fn readdir(&self, _path: &str) -> Result<Vec<FileInfo>> {
+///         Ok(vec![FileInfo::file("hello", 13, 0o644)])
+///     }
+/// }
+/// ```
+pub trait FileSystem {
+    /// Get the plugin name
+    fn name(&self) -> &str;
+
+    /// Get the plugin README (markdown format)
+    ///
+    /// [`FsSchema::to_readme`] generates one from the schema and config
+    /// parameters.
+    fn readme(&self) -> &str {
+        "# Plugin\n\nNo documentation provided."
+    }
+
+    /// Returns the list of configuration parameters this plugin supports
+    fn config_params(&self) -> Vec<ConfigParameter> {
+        Vec::new()
+    }
+
+    /// Resources this plugin uses, such as the hosts it contacts over HTTP
+    ///
+    /// Asked again after `initialize`, so the answer may depend on config.
+    /// The WASM SDK rejects `Http` calls to hosts not declared here.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::default()
+    }
+
+    /// Layout of the plugin's tree, served at `/.agfs/schema.json`
+    ///
+    /// Default implementation describes nothing beyond the control files.
+    fn schema(&self) -> FsSchema {
+        FsSchema::default()
+    }
+
+    /// Validate plugin configuration
+    fn validate(&self, _config: &Config) -> Result<()> {
+        Ok(())
+    }
+
+    /// Initialize the plugin with given configuration
+    fn initialize(&mut self, _config: &Config) -> Result<()> {
+        Ok(())
+    }
+
+    /// Shutdown the plugin
+    fn shutdown(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Plugin-specific figures for `/.agfs/stats` (a JSON object)
+    ///
+    /// Default implementation reports nothing beyond the SDK's call counters.
+    fn stats(&self) -> serde_json::Value {
+        serde_json::Value::Null
+    }
+
+    /// Check whether the plugin can serve requests, for `/.agfs/health`
+    ///
+    /// Default implementation always reports healthy.
+    fn health(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Run a command written to `/.agfs/ctl`, one per line
+    ///
+    /// Default implementation rejects every command.
+    fn ctl(&mut self, command: &str) -> Result<()> {
+        Err(Error::InvalidInput(format!("unknown command: {}", command)))
+    }
+
+    /// Periodic upkeep such as compaction, cache eviction or index rebuilds
+    ///
+    /// The host calls it every `maintain_interval` of the mount config (a
+    /// duration like `10m`; unset means never), never concurrently with
+    /// itself, so requests need not pay for upkeep. Default implementation
+    /// does nothing.
+    fn maintain(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Pre-populate caches after mount, e.g. front-page stories or bucket
+    /// prefix listings, in slices of at most `budget`
+    ///
+    /// The host calls it in the background once the mount is initialized,
+    /// again and again until it reports completion, fails, or the mount's
+    /// `warmup_budget` is spent, serving requests in between. Progress shows
+    /// in `/.agfs/health`. Default implementation has nothing to warm.
+    fn warmup(&mut self, _budget: Duration) -> Result<WarmupProgress> {
+        Ok(WarmupProgress::complete())
+    }
+
+    /// State worth handing to a replacement instance when the host upgrades
+    /// the plugin in place, such as caches, handle metadata or bookmarks
+    ///
+    /// The format is the plugin's own. Default implementation has nothing to
+    /// transfer.
+    fn export_state(&self) -> Result<Vec<u8>> {
+        Ok(Vec::new())
+    }
+
+    /// Take over `state` exported by the instance being replaced
+    ///
+    /// Called after `initialize` with the same config. A newer version must
+    /// accept what older ones exported, or fail, which keeps the old instance
+    /// mounted. Default implementation ignores the state.
+    fn import_state(&mut self, _state: &[u8]) -> Result<()> {
+        Ok(())
+    }
+
+    /// Read file contents
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File path to read
+    /// * `offset` - Byte offset to start reading from
+    /// * `size` - Maximum number of bytes to read (-1 = read all)
+    ///
+    /// # Returns
+    ///
+    /// Raw file contents; may contain NUL bytes or non-UTF-8 data. An empty
+    /// result at `offset` is the end of the file, which is how hosts find the
+    /// end of files stat'ed with an unknown size.
+    ///
+    /// Default implementation returns ReadOnly error.
+    fn read(&self, _path: &str, _offset: i64, _size: i64) -> Result<Vec<u8>> {
+        Err(Error::ReadOnly)
+    }
+
+    /// Read like `read`, waiting up to `timeout` for data to arrive when
+    /// there is none yet at `offset`, as `tail -f` needs on a growing file
+    ///
+    /// Returns an empty result if nothing arrived in time; the host calls
+    /// again for as long as the reader follows the file. Wait on the
+    /// backend, e.g. with a long-polling request for new log lines, and
+    /// never past `timeout`: the host holds the caller meanwhile.
+    ///
+    /// Default implementation reads once without waiting, leaving the host
+    /// to wait between calls.
+    fn poll_read(&self, path: &str, offset: i64, size: i64, _timeout: Duration) -> Result<Vec<u8>> {
+        self.read(path, offset, size)
+    }
+
+    /// Get file or directory information
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File or directory path
+    ///
+    /// # Returns
+    ///
+    /// FileInfo structure with metadata
+    fn stat(&self, path: &str) -> Result<FileInfo>;
+
+    /// List directory contents
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Directory path
+    ///
+    /// # Returns
+    ///
+    /// Vector of FileInfo for each entry in the directory
+    fn readdir(&self, path: &str) -> Result<Vec<FileInfo>>;
+
+    /// List up to `limit` directory entries starting at entry `offset`
+    ///
+    /// A page shorter than `limit` is the last one. Entries must come back in
+    /// a stable order for paging to be consistent.
+    ///
+    /// Default implementation slices the result of `readdir`; override it for
+    /// large directories that can be listed incrementally.
+    fn readdir_page(&self, path: &str, offset: usize, limit: usize) -> Result<Vec<FileInfo>> {
+        Ok(self.readdir(path)?.into_iter().skip(offset).take(limit).collect())
+    }
+
+    /// List the entries of `path` whose names match `glob`, at most `limit` (0 = all)
+    ///
+    /// Lets hosts push `ls *.md` or shell completion prefixes (`rep*`) down to
+    /// the plugin. In `glob`, `*` matches any run of characters and `?` one
+    /// character; an empty glob matches every name.
+    ///
+    /// Default implementation filters the result of `readdir`; override it
+    /// when an index or the backend's own filtering (see [`glob_prefix`]) can
+    /// answer without enumerating the whole directory.
+    fn readdir_filtered(&self, path: &str, glob: &str, limit: usize) -> Result<Vec<FileInfo>> {
+        Ok(filter_entries(self.readdir(path)?, glob, limit))
+    }
+
+    /// Every entry below `path` as `(full path, info)`, at most `depth` levels deep (0 = no limit)
+    ///
+    /// Lets `find` or `rg` on the host get a whole subtree in one call instead
+    /// of a readdir and stat per directory. Entries come in depth-first order,
+    /// each directory before its contents.
+    ///
+    /// Default implementation recurses with `readdir` (see [`walk_tree`]);
+    /// override it when the backend can list a subtree at once (e.g. an S3
+    /// listing without a delimiter).
+    fn walk(&self, path: &str, depth: usize) -> Result<Vec<(String, FileInfo)>> {
+        walk_tree(self, path, depth)
+    }
+
+    /// Generation of the listing of `path`: a number that changes whenever
+    /// an entry is added, removed, or changes size or type (0 = not tracked)
+    ///
+    /// Lets hosts keep a listing and ask [`readdir_if_changed`](Self::readdir_if_changed)
+    /// instead of having it serialized again on every `ls`. Generations must
+    /// not repeat across plugin restarts: a counter bumped on each change
+    /// should start from the load time, not from 1.
+    ///
+    /// Default implementation does not track generations.
+    fn dir_generation(&self, _path: &str) -> u64 {
+        0
+    }
+
+    /// The listing of `path`, or `None` if its generation is still `generation`
+    ///
+    /// Default implementation compares against `dir_generation` and lists
+    /// with `readdir`; directories whose generation is not tracked are
+    /// always listed.
+    fn readdir_if_changed(&self, path: &str, generation: u64) -> Result<Option<Listing>> {
+        let current = self.dir_generation(path);
+        if current != 0 && current == generation {
+            return Ok(None);
+        }
+        Ok(Some(Listing {
+            entries: self.readdir(path)?,
+            generation: current,
+        }))
+    }
+
+    /// Write data to a file
+    ///
+    /// # Arguments
+    /// * `path` - The file path
+    /// * `data` - Data to write
+    /// * `offset` - Position to write at (-1 for append mode behavior)
+    /// * `flags` - Write flags (CREATE, TRUNCATE, APPEND, etc.)
+    ///
+    /// # Returns
+    /// Number of bytes written
+    ///
+    /// Default implementation returns ReadOnly error.
+    fn write(&mut self, _path: &str, _data: &[u8], _offset: i64, _flags: WriteFlag) -> Result<i64> {
+        Err(Error::ReadOnly)
+    }
+
+    /// Create a new file
+    ///
+    /// Default implementation returns ReadOnly error.
+    fn create(&mut self, _path: &str) -> Result<()> {
+        Err(Error::ReadOnly)
+    }
+
+    /// Create a directory
+    ///
+    /// Default implementation returns ReadOnly error.
+    fn mkdir(&mut self, _path: &str, _perm: u32) -> Result<()> {
+        Err(Error::ReadOnly)
+    }
+
+    /// Remove a file
+    ///
+    /// Default implementation returns ReadOnly error.
+    fn remove(&mut self, _path: &str) -> Result<()> {
+        Err(Error::ReadOnly)
+    }
+
+    /// Remove a directory and all its contents
+    ///
+    /// Default implementation returns ReadOnly error.
+    fn remove_all(&mut self, _path: &str) -> Result<()> {
+        Err(Error::ReadOnly)
+    }
+
+    /// Rename a file or directory
+    ///
+    /// Default implementation returns ReadOnly error.
+    fn rename(&mut self, _old_path: &str, _new_path: &str) -> Result<()> {
+        Err(Error::ReadOnly)
+    }
+
+    /// Change file or directory permissions
+    ///
+    /// Default implementation returns ReadOnly error.
+    fn chmod(&mut self, _path: &str, _mode: u32) -> Result<()> {
+        Err(Error::ReadOnly)
+    }
+
+    /// Create a symbolic link at `link_path` pointing to `target`
+    ///
+    /// `target` is stored as-is and does not need to exist.
+    ///
+    /// Default implementation returns ReadOnly error.
+    fn symlink(&mut self, _target: &str, _link_path: &str) -> Result<()> {
+        Err(Error::ReadOnly)
+    }
+
+    /// Read the target of a symbolic link
+    ///
+    /// Default implementation treats every existing path as a regular entry
+    /// and returns InvalidInput, like `readlink(2)` on a non-link.
+    fn readlink(&self, path: &str) -> Result<String> {
+        self.stat(path)?;
+        Err(Error::InvalidInput("not a symlink".to_string()))
+    }
+
+    /// Get the value of extended attribute `name`
+    ///
+    /// Default implementation reports that no attributes exist.
+    fn get_xattr(&self, path: &str, _name: &str) -> Result<Vec<u8>> {
+        self.stat(path)?;
+        Err(Error::NoAttribute)
+    }
+
+    /// Set extended attribute `name`, creating or replacing it
+    ///
+    /// Default implementation returns ReadOnly error.
+    fn set_xattr(&mut self, _path: &str, _name: &str, _value: &[u8]) -> Result<()> {
+        Err(Error::ReadOnly)
+    }
+
+    /// List the names of the extended attributes set on `path`
+    ///
+    /// Default implementation returns an empty list for existing paths.
+    fn list_xattr(&self, path: &str) -> Result<Vec<String>> {
+        self.stat(path)?;
+        Ok(Vec::new())
+    }
+
+    /// Read the whole file unless its content version still equals `etag`
+    ///
+    /// Returns `Ok(None)` when `stat(path).etag` matches, letting hosts and
+    /// caching layers skip transferring unchanged content.
+    ///
+    /// Default implementation compares against `stat` and falls back to `read`;
+    /// override it when the backend supports conditional requests natively
+    /// (e.g. HTTP `If-None-Match`).
+    fn read_if_changed(&self, path: &str, etag: &str) -> Result<Option<Vec<u8>>> {
+        if self.stat(path)?.etag.as_deref() == Some(etag) {
+            return Ok(None);
+        }
+        self.read(path, 0, -1).map(Some)
+    }
+
+    /// Read several whole files at once, one result per path in the same order
+    ///
+    /// Spares hosts a round trip per file when many small files are needed
+    /// together, as when a static site generator loads its sources. A failed
+    /// path only fails its own entry.
+    ///
+    /// Default implementation calls `read` for each path; override it when
+    /// the backend can fetch several objects in one request.
+    fn read_many(&self, paths: &[&str]) -> Vec<Result<Vec<u8>>> {
+        paths.iter().map(|path| self.read(path, 0, -1)).collect()
+    }
+
+    /// Replace the contents of `path` only if its content version is `expected_etag`
+    ///
+    /// Prevents lost updates when two clients edit the same file: the second
+    /// writer gets `Stale` and must re-read. An empty `expected_etag` means
+    /// the file must not exist yet. Backends with a lock held by another
+    /// writer should fail with `Busy`.
+    ///
+    /// Default implementation compares against `stat` and then calls `write`
+    /// with `IF_MATCH` set, which leaves a window between the two; override it
+    /// when the backend supports conditional writes natively (e.g. HTTP
+    /// `If-Match`).
+    fn write_if(&mut self, path: &str, data: &[u8], expected_etag: &str) -> Result<i64> {
+        if expected_etag.is_empty() {
+            return match self.stat(path) {
+                Ok(_) => Err(Error::Stale),
+                Err(Error::NotFound) => {
+                    let flags = WriteFlag::CREATE | WriteFlag::EXCLUSIVE | WriteFlag::IF_MATCH;
+                    self.write(path, data, 0, flags)
+                }
+                Err(e) => Err(e),
+            };
+        }
+        if self.stat(path)?.etag.as_deref() != Some(expected_etag) {
+            return Err(Error::Stale);
+        }
+        self.write(path, data, 0, WriteFlag::TRUNCATE | WriteFlag::IF_MATCH)
+    }
+
+    /// Apply `ops` in order, one result per operation
+    ///
+    /// Lets a host submit a sequence such as rename, write, remove in one
+    /// call. When [`atomic_batch`](Self::atomic_batch) is true either every
+    /// operation takes effect or none does, and a failed batch reports the
+    /// failing operation's error and `Cancelled` for the others.
+    ///
+    /// Default implementation applies the operations one by one (see
+    /// [`apply_ops`]) and stops at the first failure, reporting `Cancelled`
+    /// for the rest; earlier operations stay applied. Override both methods
+    /// when the backend has transactions (a SQL transaction, a git commit).
+    fn batch(&mut self, ops: Vec<FsOp>) -> Vec<Result<()>> {
+        apply_ops(self, ops)
+    }
+
+    /// Whether [`batch`](Self::batch) applies all of its operations or none
+    fn atomic_batch(&self) -> bool {
+        false
+    }
+
+    /// Start grouping writes into one upstream change
+    ///
+    /// Until [`commit_session`](Self::commit_session) or
+    /// [`abort_session`](Self::abort_session), plugins that publish every
+    /// write upstream (GitFS committing, ConfigFS opening a pull request)
+    /// should stage writes instead, so an editor's many write calls become
+    /// one commit. Fails with `Busy` if a session is already open.
+    ///
+    /// Default implementation reports sessions as unsupported (`ReadOnly`);
+    /// writes then take effect one by one.
+    fn begin_write_session(&mut self) -> Result<()> {
+        Err(Error::ReadOnly)
+    }
+
+    /// Publish the writes staged since `begin_write_session` as one change
+    /// described by `message`, and end the session
+    fn commit_session(&mut self, _message: &str) -> Result<()> {
+        Err(Error::ReadOnly)
+    }
+
+    /// Discard the writes staged since `begin_write_session` and end the session
+    fn abort_session(&mut self) -> Result<()> {
+        Err(Error::ReadOnly)
+    }
+
+    /// Hint how `len` bytes of `path` starting at `offset` will be accessed
+    ///
+    /// `len <= 0` means up to the end of the file. Plugins over slow storage
+    /// can use this to start or stop read-ahead; hints never affect results.
+    ///
+    /// Default implementation ignores the hint.
+    fn advise(&self, _path: &str, _offset: i64, _len: i64, _advice: Advice) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Entries whose names match `glob`, at most `limit` of them (0 = all)
+///
+/// The filter applied by the default [`FileSystem::readdir_filtered`].
+pub fn filter_entries(entries: Vec<FileInfo>, glob: &str, limit: usize) -> Vec<FileInfo> {
+    let matching = entries
+        .into_iter()
+        .filter(|entry| glob.is_empty() || crate::policy::name_match(glob, &entry.name));
+    match limit {
+        0 => matching.collect(),
+        _ => matching.take(limit).collect(),
+    }
+}
+
+/// `size` bytes of `data` from `offset` (-1 = to the end)
+///
+/// Files stat'ed with an unknown size (see [`FileInfo::generated`]) are read
+/// in chunks until a read comes back empty, so a plugin generating content
+/// on the fly must honor `offset` and `size` with this rather than return
+/// the whole content on every read.
+pub fn read_range(data: &[u8], offset: i64, size: i64) -> Vec<u8> {
+    let start = (offset.max(0) as usize).min(data.len());
+    let end = match size {
+        size if size < 0 => data.len(),
+        size => start.saturating_add(size as usize).min(data.len()),
+    };
+    data[start..end].to_vec()
+}
+
+/// Literal text before the first wildcard of `glob`
+///
+/// Backends with prefix queries (S3 `prefix=`, SQL `LIKE 'x%'`) can fetch
+/// only candidates and leave the rest to [`filter_entries`].
+pub fn glob_prefix(glob: &str) -> &str {
+    let end = glob.find(['*', '?']).unwrap_or(glob.len());
+    &glob[..end]
+}
+
+/// Walk `path` with `readdir`, as the default [`FileSystem::walk`] does
+///
+/// Fails only if `path` itself cannot be listed; subdirectories that fail
+/// to list are reported without their contents.
+pub fn walk_tree<F: FileSystem + ?Sized>(fs: &F, path: &str, depth: usize) -> Result<Vec<(String, FileInfo)>> {
+    let mut out = Vec::new();
+    let entries = fs.readdir(path)?;
+    walk_entries(fs, path.trim_end_matches('/'), entries, 1, depth, &mut out);
+    Ok(out)
+}
+
+fn walk_entries<F: FileSystem + ?Sized>(
+    fs: &F,
+    dir: &str,
+    entries: Vec<FileInfo>,
+    level: usize,
+    depth: usize,
+    out: &mut Vec<(String, FileInfo)>,
+) {
+    for info in entries {
+        let path = format!("{}/{}", dir, info.name);
+        let descend = info.is_dir() && (depth == 0 || level < depth);
+        out.push((path.clone(), info));
+        if descend {
+            if let Ok(children) = fs.readdir(&path) {
+                walk_entries(fs, &path, children, level + 1, depth, out);
+            }
+        }
+    }
+}
+
+/// Apply `ops` one at a time, as the default [`FileSystem::batch`] does
+///
+/// Stops at the first failure; the operations after it report `Cancelled`.
+pub fn apply_ops<F: FileSystem + ?Sized>(fs: &mut F, ops: Vec<FsOp>) -> Vec<Result<()>> {
+    let mut failed = false;
+    ops.into_iter()
+        .map(|op| {
+            if failed {
+                return Err(Error::Cancelled);
+            }
+            let result = apply_op(fs, op);
+            failed = result.is_err();
+            result
+        })
+        .collect()
+}
+
+/// Apply one batch operation through the matching [`FileSystem`] method
+pub fn apply_op<F: FileSystem + ?Sized>(fs: &mut F, op: FsOp) -> Result<()> {
+    match op {
+        FsOp::Create { path } => fs.create(&path),
+        FsOp::Mkdir { path, perm } => fs.mkdir(&path, perm),
+        FsOp::Write { path, data } => fs.write(&path, &data, 0, WriteFlag::CREATE | WriteFlag::TRUNCATE).map(|_| ()),
+        FsOp::Remove { path } => fs.remove(&path),
+        FsOp::RemoveAll { path } => fs.remove_all(&path),
+        FsOp::Rename { old_path, new_path } => fs.rename(&old_path, &new_path),
+        FsOp::Chmod { path, mode } => fs.chmod(&path, mode),
+    }
+}
+
+/// Results of a batch rejected before anything was applied: `err` for the
+/// operation at `index` and `Cancelled` for the others
+pub fn reject_batch(len: usize, index: usize, err: Error) -> Vec<Result<()>> {
+    let mut results: Vec<Result<()>> = (0..len).map(|_| Err(Error::Cancelled)).collect();
+    if let Some(result) = results.get_mut(index) {
+        *result = Err(err);
+    }
+    results
+}
+
+/// Batch results as sent to the host: `null` for success, otherwise
+/// `{"code": errno, "message": text}` with secrets redacted
+pub fn batch_results_json(results: &[Result<()>]) -> serde_json::Value {
+    results
+        .iter()
+        .map(|result| match result {
+            Ok(()) => serde_json::Value::Null,
+            Err(e) => serde_json::json!({"code": e.code(), "message": crate::redact::redact(&e.to_string())}),
+        })
+        .collect()
+}
+
+/// Answer each path with `local` where it returns a result, sending the rest
+/// to `batch` in one call, and merge the results back into path order
+///
+/// Wrappers use this to forward batched calls like [`FileSystem::read_many`]
+/// while handling some paths themselves.
+pub(crate) fn split_batch<T>(
+    paths: &[&str],
+    local: impl Fn(&str) -> Option<Result<T>>,
+    batch: impl FnOnce(&[&str]) -> Vec<Result<T>>,
+) -> Vec<Result<T>> {
+    let answered: Vec<Option<Result<T>>> = paths.iter().map(|path| local(path)).collect();
+    let rest: Vec<&str> = paths.iter().zip(&answered).filter(|(_, a)| a.is_none()).map(|(p, _)| *p).collect();
+    let mut batched = if rest.is_empty() { Vec::new() } else { batch(&rest) }.into_iter();
+    answered
+        .into_iter()
+        .map(|answer| {
+            answer.unwrap_or_else(|| batched.next().unwrap_or_else(|| Err(Error::Other("missing batch result".into()))))
+        })
+        .collect()
+}
+
+/// Read-only filesystem helper
+///
+/// This trait provides common functionality for read-only filesystems.
+/// Implement this instead of `FileSystem` if your filesystem is read-only.
+pub trait ReadOnlyFileSystem {
+    /// Returns the name of this filesystem plugin
+    fn name(&self) -> &str;
+
+    /// Returns the README/documentation for this plugin
+    fn readme(&self) -> &str {
+        "No documentation available"
+    }
+
+    /// Read data from a file
+    fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>>;
+
+    /// Get file information
+    fn stat(&self, path: &str) -> Result<FileInfo>;
+
+    /// List directory contents
+    fn readdir(&self, path: &str) -> Result<Vec<FileInfo>>;
+}
+
+// Automatically implement FileSystem for any ReadOnlyFileSystem
+impl<T: ReadOnlyFileSystem> FileSystem for T {
+    fn name(&self) -> &str {
+        ReadOnlyFileSystem::name(self)
+    }
+
+    fn readme(&self) -> &str {
+        ReadOnlyFileSystem::readme(self)
+    }
+
+    fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+        ReadOnlyFileSystem::read(self, path, offset, size)
+    }
+
+    fn stat(&self, path: &str) -> Result<FileInfo> {
+        ReadOnlyFileSystem::stat(self, path)
+    }
+
+    fn readdir(&self, path: &str) -> Result<Vec<FileInfo>> {
+        ReadOnlyFileSystem::readdir(self, path)
+    }
+}
+
+/// FileHandle represents an open file handle with stateful operations
+/// This trait is used for FUSE-like operations that require maintaining
+/// file position and state across multiple read/write operations
+pub trait FileHandle {
+    /// Returns the unique identifier of this handle
+    fn id(&self) -> i64;
+
+    /// Returns the file path this handle is associated with
+    fn path(&self) -> &str;
+
+    /// Returns the open flags used when opening this handle
+    fn flags(&self) -> OpenFlag;
+
+    /// Read reads up to buf.len() bytes from the current position
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize>;
+
+    /// ReadAt reads bytes from the specified offset (pread)
+    fn read_at(&self, buf: &mut [u8], offset: i64) -> Result<usize>;
+
+    /// Write writes data at the current position
+    fn write(&mut self, data: &[u8]) -> Result<usize>;
+
+    /// WriteAt writes data at the specified offset (pwrite)
+    fn write_at(&self, data: &[u8], offset: i64) -> Result<usize>;
+
+    /// Seek moves the read/write position
+    /// whence: 0 = SEEK_SET (from start), 1 = SEEK_CUR (from current), 2 = SEEK_END (from end)
+    fn seek(&mut self, offset: i64, whence: i32) -> Result<i64>;
+
+    /// Sync synchronizes the file data to storage
+    fn sync(&self) -> Result<()>;
+
+    /// Close closes the handle and releases resources
+    fn close(&mut self) -> Result<()>;
+
+    /// Stat returns file information
+    fn stat(&self) -> Result<FileInfo>;
+}
+
+/// Optional trait for filesystems that support stateful file handles
+///
+/// Export with `export_handle_plugin!` instead of `export_plugin!` to expose
+/// the handle entry points. Handle IDs are chosen by the plugin and must be
+/// non-zero.
+pub trait HandleFS: FileSystem {
+    /// Opens a file and returns the handle ID for stateful operations
+    /// flags: OpenFlag bits (O_RDONLY, O_WRONLY, O_RDWR, O_APPEND, O_CREATE, O_EXCL, O_TRUNC)
+    /// mode: file permission mode (used when creating new files)
+    fn open_handle(&mut self, path: &str, flags: OpenFlag, mode: u32) -> Result<i64>;
+
+    /// Read from handle at current position, returns bytes read
+    fn handle_read(&mut self, id: i64, buf: &mut [u8]) -> Result<usize>;
+
+    /// Read from handle at specified offset (pread)
+    fn handle_read_at(&self, id: i64, buf: &mut [u8], offset: i64) -> Result<usize>;
+
+    /// Write to handle at current position, returns bytes written
+    fn handle_write(&mut self, id: i64, data: &[u8]) -> Result<usize>;
+
+    /// Write to handle at specified offset (pwrite)
+    fn handle_write_at(&self, id: i64, data: &[u8], offset: i64) -> Result<usize>;
+
+    /// Seek handle position
+    /// whence: 0 = SEEK_SET, 1 = SEEK_CUR, 2 = SEEK_END
+    fn handle_seek(&mut self, id: i64, offset: i64, whence: i32) -> Result<i64>;
+
+    /// Sync handle data
+    fn handle_sync(&self, _id: i64) -> Result<()> {
+        Ok(())
+    }
+
+    /// Stat via handle
+    fn handle_stat(&self, id: i64) -> Result<FileInfo>;
+
+    /// Get handle info (path, flags)
+    fn handle_info(&self, id: i64) -> Result<(String, OpenFlag)>;
+
+    /// Closes a handle by its ID
+    fn close_handle(&mut self, id: i64) -> Result<()>;
+}
+
+/// Optional trait for filesystems serving continuous data
+///
+/// Tailing logs, event feeds and FIFO-style files have no fixed size to
+/// `read`. A stream moves data through ring buffers shared with the host,
+/// which asks the plugin to refill or drain them only when they run empty or
+/// full. Stream IDs are chosen by the plugin and must be non-zero.
+pub trait StreamFS: FileSystem {
+    /// Opens a stream on `path` and returns its ID
+    fn open_stream(&mut self, path: &str) -> Result<i64>;
+
+    /// Copy data that is available now into `buf`
+    ///
+    /// Returns `Some(0)` when nothing is available yet and `None` once the
+    /// stream has ended.
+    fn stream_read(&mut self, id: i64, buf: &mut [u8]) -> Result<Option<usize>>;
+
+    /// Consume data written by the host, returning the bytes accepted
+    ///
+    /// Default implementation returns ReadOnly error.
+    fn stream_write(&mut self, _id: i64, _data: &[u8]) -> Result<usize> {
+        Err(Error::ReadOnly)
+    }
+
+    /// Closes a stream by its ID
+    fn close_stream(&mut self, id: i64) -> Result<()>;
+}
+
+/// Optional trait for resumable uploads
+///
+/// Multi-gigabyte writes should not have to start over from byte 0 when the
+/// connection drops or the plugin restarts. An upload session stages data
+/// on the server side until it is committed to `path`; a client that lost
+/// its place asks for [`upload_status`](Self::upload_status) and resumes at
+/// `received`. Sessions must survive a plugin restart, so keep staged data
+/// outside plugin memory (the WASM SDK provides `HostUploads` for this).
+/// Session IDs are chosen by the plugin.
+pub trait UploadFS: FileSystem {
+    /// Start an upload that will replace `path` once committed
+    fn begin_upload(&mut self, path: &str) -> Result<UploadSession>;
+
+    /// Current state of a session, e.g. to find where to resume
+    fn upload_status(&self, id: &str) -> Result<UploadSession>;
+
+    /// Append `data` at `offset`, returning the updated session
+    ///
+    /// `offset` must equal the bytes received so far, so a retried or
+    /// reordered chunk fails with `InvalidInput` instead of corrupting the
+    /// staged file.
+    fn upload_append(&mut self, id: &str, offset: u64, data: &[u8]) -> Result<UploadSession>;
+
+    /// Move the staged data to the session's path and end the session
+    fn commit_upload(&mut self, id: &str) -> Result<()>;
+
+    /// Discard the staged data and end the session
+    fn abort_upload(&mut self, id: &str) -> Result<()>;
+
+    /// Sessions still open, e.g. after a restart
+    fn list_uploads(&self) -> Result<Vec<UploadSession>> {
+        Ok(Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct TestFS;
+
+    impl FileSystem for TestFS {
+        fn name(&self) -> &str {
+            "test-fs"
+        }
+
+        fn read(&self, path: &str, _offset: i64, _size: i64) -> Result<Vec<u8>> {
+            match path {
+                "/test" => Ok(b"test content".to_vec()),
+                "/binary" => Ok(vec![0x00, 0xff, b'a', 0x00, 0xfe]),
+                _ => Err(Error::NotFound),
+            }
+        }
+
+        fn stat(&self, path: &str) -> Result<FileInfo> {
+            if path == "/" || path == "/test" {
+                Ok(FileInfo::file("test", 12, 0o644).with_etag("v1"))
+            } else {
+                Err(Error::NotFound)
+            }
+        }
+
+        fn readdir(&self, path: &str) -> Result<Vec<FileInfo>> {
+            if path == "/" {
+                Ok(vec![FileInfo::file("test", 12, 0o644)])
+            } else {
+                Err(Error::NotFound)
+            }
+        }
+    }
+
+    #[test]
+    fn test_filesystem_trait() {
+        let fs = TestFS;
+        assert_eq!(fs.name(), "test-fs");
+        assert!(fs.validate(&Config::default()).is_ok());
+        assert!(fs.config_params().is_empty());
+
+        let content = fs.read("/test", 0, 100).unwrap();
+        assert_eq!(content, b"test content");
+
+        let binary = fs.read("/binary", 0, -1).unwrap();
+        assert_eq!(binary, vec![0x00, 0xff, b'a', 0x00, 0xfe]);
+
+        let info = fs.stat("/test").unwrap();
+        assert_eq!(info.name, "test");
+
+        let files = fs.readdir("/").unwrap();
+        assert_eq!(files.len(), 1);
+    }
+
+    #[test]
+    fn test_default_readdir_page() {
+        let fs = TestFS;
+        assert_eq!(fs.readdir_page("/", 0, 10).unwrap().len(), 1);
+        assert!(fs.readdir_page("/", 1, 10).unwrap().is_empty());
+        assert!(fs.readdir_page("/", 0, 0).unwrap().is_empty());
+        assert!(matches!(fs.readdir_page("/missing", 0, 10), Err(Error::NotFound)));
+    }
+
+    #[test]
+    fn test_default_readonly_operations() {
+        let mut fs = TestFS;
+        assert!(matches!(fs.write("/test", b"data", 0, WriteFlag::NONE), Err(Error::ReadOnly)));
+        assert!(matches!(fs.create("/new"), Err(Error::ReadOnly)));
+        assert!(matches!(fs.mkdir("/dir", 0o755), Err(Error::ReadOnly)));
+        assert!(matches!(fs.symlink("/test", "/link"), Err(Error::ReadOnly)));
+        assert!(matches!(fs.set_xattr("/test", "user.a", b"1"), Err(Error::ReadOnly)));
+    }
+
+    #[test]
+    fn test_default_read_if_changed() {
+        let fs = TestFS;
+        assert_eq!(fs.read_if_changed("/test", "v1").unwrap(), None);
+        assert_eq!(fs.read_if_changed("/test", "v0").unwrap().unwrap(), b"test content");
+        assert!(matches!(fs.read_if_changed("/missing", "v1"), Err(Error::NotFound)));
+    }
+
+    #[test]
+    fn test_default_write_if() {
+        let mut fs = TestFS;
+        assert_eq!(fs.write_if("/test", b"x", "v0"), Err(Error::Stale));
+        assert_eq!(fs.write_if("/test", b"x", ""), Err(Error::Stale));
+        // Matching preconditions fall through to write, which TestFS lacks
+        assert_eq!(fs.write_if("/test", b"x", "v1"), Err(Error::ReadOnly));
+        assert_eq!(fs.write_if("/new", b"x", ""), Err(Error::ReadOnly));
+    }
+
+    #[test]
+    fn test_default_read_many() {
+        let fs = TestFS;
+        let results = fs.read_many(&["/test", "/missing", "/test"]);
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_deref().unwrap(), b"test content");
+        assert!(matches!(results[1], Err(Error::NotFound)));
+        assert!(results[2].is_ok());
+    }
+
+    /// Files in a map; writes to `/full` fail
+    #[derive(Default)]
+    struct MapFS(std::collections::BTreeMap<String, Vec<u8>>);
+
+    impl FileSystem for MapFS {
+        fn name(&self) -> &str {
+            "mapfs"
+        }
+
+        fn stat(&self, path: &str) -> Result<FileInfo> {
+            let data = self.0.get(path).ok_or(Error::NotFound)?;
+            Ok(FileInfo::file(path.trim_start_matches('/'), data.len() as i64, 0o644))
+        }
+
+        fn readdir(&self, _path: &str) -> Result<Vec<FileInfo>> {
+            Ok(vec![])
+        }
+
+        fn write(&mut self, path: &str, data: &[u8], _offset: i64, _flags: WriteFlag) -> Result<i64> {
+            if path == "/full" {
+                return Err(Error::Io("no space left".to_string()));
+            }
+            self.0.insert(path.to_string(), data.to_vec());
+            Ok(data.len() as i64)
+        }
+
+        fn rename(&mut self, old_path: &str, new_path: &str) -> Result<()> {
+            let data = self.0.remove(old_path).ok_or(Error::NotFound)?;
+            self.0.insert(new_path.to_string(), data);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_default_batch() {
+        let write = |path: &str| FsOp::Write {
+            path: path.to_string(),
+            data: b"x".to_vec(),
+        };
+        let rename = FsOp::Rename {
+            old_path: "/a".to_string(),
+            new_path: "/b".to_string(),
+        };
+
+        let mut fs = MapFS::default();
+        assert!(!fs.atomic_batch());
+        let results = fs.batch(vec![write("/a"), rename.clone(), write("/full"), write("/c")]);
+        assert_eq!(results[..2], [Ok(()), Ok(())]);
+        assert!(matches!(results[2], Err(Error::Io(_))));
+        assert_eq!(results[3], Err(Error::Cancelled));
+        assert!(fs.stat("/b").is_ok() && fs.stat("/c").is_err());
+
+        let json = batch_results_json(&results);
+        assert_eq!(json[0], serde_json::Value::Null);
+        assert_eq!(json[2]["code"], 5);
+        assert_eq!(reject_batch(2, 1, Error::ReadOnly), [Err(Error::Cancelled), Err(Error::ReadOnly)]);
+    }
+
+    #[test]
+    fn test_default_write_session() {
+        let mut fs = MapFS::default();
+        assert_eq!(fs.begin_write_session(), Err(Error::ReadOnly));
+        assert_eq!(fs.commit_session("msg"), Err(Error::ReadOnly));
+        assert_eq!(fs.abort_session(), Err(Error::ReadOnly));
+        assert_eq!(fs.maintain(), Ok(()));
+        assert!(fs.warmup(Duration::from_secs(1)).unwrap().is_complete());
+        assert_eq!(fs.export_state().unwrap(), b"");
+        assert!(fs.import_state(b"from an older version").is_ok());
+    }
+
+    #[test]
+    fn test_default_readdir_if_changed() {
+        struct Versioned(u64);
+
+        impl FileSystem for Versioned {
+            fn name(&self) -> &str {
+                "versioned"
+            }
+
+            fn stat(&self, _path: &str) -> Result<FileInfo> {
+                Ok(FileInfo::dir("/", 0o755))
+            }
+
+            fn readdir(&self, _path: &str) -> Result<Vec<FileInfo>> {
+                Ok(vec![FileInfo::file("a", 1, 0o644)])
+            }
+
+            fn dir_generation(&self, _path: &str) -> u64 {
+                self.0
+            }
+        }
+
+        let listing = Versioned(7).readdir_if_changed("/", 0).unwrap().unwrap();
+        assert_eq!((listing.entries.len(), listing.generation), (1, 7));
+        assert!(Versioned(7).readdir_if_changed("/", 7).unwrap().is_none());
+        assert_eq!(Versioned(8).readdir_if_changed("/", 7).unwrap().unwrap().generation, 8);
+
+        // Untracked generations always list
+        assert_eq!(TestFS.readdir_if_changed("/", 0).unwrap().unwrap().generation, 0);
+    }
+
+    #[test]
+    fn test_default_readdir_filtered() {
+        let fs = TestFS;
+        assert_eq!(fs.readdir_filtered("/", "t*", 0).unwrap().len(), 1);
+        assert_eq!(fs.readdir_filtered("/", "", 0).unwrap().len(), 1);
+        assert!(fs.readdir_filtered("/", "*.md", 0).unwrap().is_empty());
+
+        let entries = vec![
+            FileInfo::file("a.md", 1, 0o644),
+            FileInfo::file("b.txt", 1, 0o644),
+            FileInfo::file("c.md", 1, 0o644),
+        ];
+        let names: Vec<String> = filter_entries(entries, "?.md", 1).into_iter().map(|e| e.name).collect();
+        assert_eq!(names, ["a.md"]);
+        assert_eq!(glob_prefix("rep*.md"), "rep");
+        assert_eq!(glob_prefix("readme"), "readme");
+    }
+
+    /// Two levels of directories under `/`
+    struct TreeFS;
+
+    impl FileSystem for TreeFS {
+        fn name(&self) -> &str {
+            "tree-fs"
+        }
+
+        fn stat(&self, _path: &str) -> Result<FileInfo> {
+            Err(Error::NotFound)
+        }
+
+        fn readdir(&self, path: &str) -> Result<Vec<FileInfo>> {
+            match path {
+                "/" => Ok(vec![FileInfo::dir("a", 0o755), FileInfo::file("b", 1, 0o644)]),
+                "/a" => Ok(vec![FileInfo::dir("c", 0o755)]),
+                "/a/c" => Ok(vec![FileInfo::file("d", 1, 0o644)]),
+                _ => Err(Error::NotFound),
+            }
+        }
+    }
+
+    #[test]
+    fn test_default_walk() {
+        let paths = |path, depth| -> Vec<String> {
+            TreeFS.walk(path, depth).unwrap().into_iter().map(|(p, _)| p).collect()
+        };
+        assert_eq!(paths("/", 0), ["/a", "/a/c", "/a/c/d", "/b"]);
+        assert_eq!(paths("/", 1), ["/a", "/b"]);
+        assert_eq!(paths("/a", 2), ["/a/c", "/a/c/d"]);
+        assert!(matches!(TreeFS.walk("/missing", 0), Err(Error::NotFound)));
+    }
+
+    #[test]
+    fn test_default_link_and_xattr_queries() {
+        let fs = TestFS;
+        assert!(matches!(fs.readlink("/test"), Err(Error::InvalidInput(_))));
+        assert!(matches!(fs.readlink("/missing"), Err(Error::NotFound)));
+        assert!(matches!(fs.get_xattr("/test", "user.a"), Err(Error::NoAttribute)));
+        assert!(fs.list_xattr("/test").unwrap().is_empty());
+        assert!(matches!(fs.list_xattr("/missing"), Err(Error::NotFound)));
+    }
+}
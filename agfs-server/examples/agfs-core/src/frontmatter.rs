@@ -0,0 +1,530 @@
+//! YAML front matter for generated Markdown files
+//!
+//! Static site generators and note tools such as Obsidian read metadata from
+//! a YAML block at the top of a Markdown file. [`FrontMatter`] builds that
+//! block with consistent quoting, and [`parse`] reads it back from files
+//! written by users or other plugins:
+//!
+//! ```
+//! use agfs_core::frontmatter::{self, FrontMatter};
+//!
+//! let page = FrontMatter::new()
+//!     .field("title", "Show HN: agfs")
+//!     .field("score", 42)
+//!     .field("tags", vec!["rust", "wasm"])
+//!     .render("# Show HN: agfs\n");
+//! assert!(page.starts_with("---\ntitle: \"Show HN: agfs\"\nscore: 42\ntags:\n  - rust\n"));
+//!
+//! let (meta, body) = frontmatter::parse(&page).unwrap();
+//! assert_eq!(meta.get("score"), Some(&42.into()));
+//! assert_eq!(body, "# Show HN: agfs\n");
+//! ```
+//!
+//! Only the YAML most front matter uses is understood: scalars, quoted
+//! strings, `|` and `>` blocks, block and `[flow]` lists, and nested maps.
+//! Anchors, tags and multi-document streams are not.
+
+use crate::error::{Error, Result};
+use serde_json::{Map, Number, Value};
+
+const DELIMITER: &str = "---";
+
+/// Ordered metadata fields of a Markdown file
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FrontMatter {
+    fields: Vec<(String, Value)>,
+}
+
+impl FrontMatter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set `key` to `value`, keeping the position of an earlier value
+    pub fn field(mut self, key: &str, value: impl Into<Value>) -> Self {
+        let value = value.into();
+        match self.fields.iter_mut().find(|(k, _)| k == key) {
+            Some((_, v)) => *v = value,
+            None => self.fields.push((key.to_string(), value)),
+        }
+        self
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.fields.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.fields.iter().map(|(k, v)| (k.as_str(), v))
+    }
+
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// The fields as YAML, without the `---` delimiters
+    pub fn to_yaml(&self) -> String {
+        let mut out = String::new();
+        for (key, value) in &self.fields {
+            write_entry(&mut out, 0, key, value);
+        }
+        out
+    }
+
+    /// `body` preceded by the front matter block and a blank line; without
+    /// fields, just `body`
+    pub fn render(&self, body: &str) -> String {
+        if self.fields.is_empty() {
+            return body.to_string();
+        }
+        format!("{}\n{}{}\n\n{}", DELIMITER, self.to_yaml(), DELIMITER, body)
+    }
+}
+
+/// Split `text` into its front matter and the Markdown after it
+///
+/// Text without a leading `---` line has empty front matter. A block that is
+/// never closed or is not a YAML mapping fails with `InvalidInput`.
+pub fn parse(text: &str) -> Result<(FrontMatter, &str)> {
+    let Some(rest) = strip_line(text, DELIMITER) else {
+        return Ok((FrontMatter::new(), text));
+    };
+    let mut yaml_end = 0;
+    let mut body = None;
+    for line in rest.split_inclusive('\n') {
+        let trimmed = line.trim_end();
+        if trimmed == DELIMITER || trimmed == "..." {
+            let after = &rest[yaml_end + line.len()..];
+            body = Some(strip_line(after, "").unwrap_or(after));
+            break;
+        }
+        yaml_end += line.len();
+    }
+    let body = body.ok_or_else(|| syntax_error(0, "front matter is not closed with ---"))?;
+
+    let mut lines = yaml_lines(&rest[..yaml_end]);
+    let mut pos = 0;
+    let fields = match lines.first() {
+        None => Vec::new(),
+        Some(first) if first.indent > 0 => return Err(syntax_error(first.number, "unexpected indentation")),
+        Some(first) if is_sequence_item(&first.content) => {
+            return Err(syntax_error(first.number, "front matter must be a mapping"))
+        }
+        Some(_) => parse_mapping(&mut lines, &mut pos, 0)?,
+    };
+    if let Some(line) = lines.get(pos) {
+        return Err(syntax_error(line.number, "unexpected indentation"));
+    }
+    Ok((FrontMatter { fields }, body))
+}
+
+/// `text` after its first line, if that line is `line`
+fn strip_line<'a>(text: &'a str, line: &str) -> Option<&'a str> {
+    let rest = text.strip_prefix(line)?;
+    rest.strip_prefix("\r\n").or_else(|| rest.strip_prefix('\n'))
+}
+
+fn syntax_error(line: usize, message: &str) -> Error {
+    match line {
+        0 => Error::InvalidInput(format!("invalid front matter: {}", message)),
+        n => Error::InvalidInput(format!("invalid front matter line {}: {}", n, message)),
+    }
+}
+
+// --- Writing ---
+
+fn write_entry(out: &mut String, indent: usize, key: &str, value: &Value) {
+    out.push_str(&" ".repeat(indent));
+    out.push_str(&scalar_string(key));
+    out.push(':');
+    write_value(out, indent, value);
+}
+
+fn write_value(out: &mut String, indent: usize, value: &Value) {
+    match value {
+        Value::Array(items) if !items.is_empty() => {
+            out.push('\n');
+            for item in items {
+                out.push_str(&" ".repeat(indent + 2));
+                out.push('-');
+                write_value(out, indent + 2, item);
+            }
+        }
+        Value::Object(map) if !map.is_empty() => {
+            out.push('\n');
+            for (key, value) in map {
+                write_entry(out, indent + 2, key, value);
+            }
+        }
+        Value::Array(_) => out.push_str(" []\n"),
+        Value::Object(_) => out.push_str(" {}\n"),
+        Value::Null => out.push_str(" null\n"),
+        Value::Bool(b) => out.push_str(&format!(" {}\n", b)),
+        Value::Number(n) => out.push_str(&format!(" {}\n", n)),
+        Value::String(s) => out.push_str(&format!(" {}\n", scalar_string(s))),
+    }
+}
+
+/// `s` plain when YAML reads it back as the same string, else double-quoted
+///
+/// YAML 1.1 booleans such as `yes` and `off` are quoted too, for the older
+/// parsers many site generators still use.
+fn scalar_string(s: &str) -> String {
+    let yaml11_bool = ["y", "n", "yes", "no", "on", "off"]
+        .iter()
+        .any(|word| s.eq_ignore_ascii_case(word));
+    let plain = s.chars().next().is_some_and(char::is_alphabetic)
+        && !yaml11_bool
+        && !s.ends_with([' ', ':'])
+        && !s.contains(": ")
+        && !s.contains(" #")
+        && !s.chars().any(char::is_control)
+        && matches!(plain_scalar(s), Value::String(_));
+    match plain {
+        true => s.to_string(),
+        // JSON string escapes are valid in YAML double-quoted strings
+        false => Value::String(s.to_string()).to_string(),
+    }
+}
+
+// --- Reading ---
+
+struct Line {
+    /// 1-based, counting the opening `---`
+    number: usize,
+    indent: usize,
+    content: String,
+}
+
+/// Non-blank, non-comment lines of the YAML block
+fn yaml_lines(yaml: &str) -> Vec<Line> {
+    yaml.lines()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let content = line.trim();
+            (!content.is_empty() && !content.starts_with('#')).then(|| Line {
+                number: i + 2,
+                indent: line.len() - line.trim_start().len(),
+                content: content.to_string(),
+            })
+        })
+        .collect()
+}
+
+fn is_sequence_item(content: &str) -> bool {
+    content == "-" || content.starts_with("- ")
+}
+
+fn parse_mapping(lines: &mut [Line], pos: &mut usize, indent: usize) -> Result<Vec<(String, Value)>> {
+    let mut fields: Vec<(String, Value)> = Vec::new();
+    while let Some(line) = lines.get(*pos).filter(|line| line.indent == indent) {
+        let number = line.number;
+        if is_sequence_item(&line.content) {
+            return Err(syntax_error(number, "expected a key"));
+        }
+        let (key, rest) = split_key(&line.content).ok_or_else(|| syntax_error(number, "expected key: value"))?;
+        *pos += 1;
+
+        let value = if rest.is_empty() {
+            match lines.get(*pos) {
+                Some(next) if next.indent > indent => {
+                    let child = next.indent;
+                    parse_block(lines, pos, child)?
+                }
+                // Lists may sit at the indentation of their key
+                Some(next) if next.indent == indent && is_sequence_item(&next.content) => {
+                    parse_sequence(lines, pos, indent)?
+                }
+                _ => Value::Null,
+            }
+        } else if let Some(style) = rest
+            .strip_prefix(['|', '>'])
+            .map(|chomp| (rest.starts_with('|'), chomp))
+        {
+            block_scalar(lines, pos, indent, style.0, style.1 == "-")
+        } else {
+            scalar(&rest).map_err(|message| syntax_error(number, &message))?
+        };
+        match fields.iter_mut().find(|(k, _)| *k == key) {
+            Some(_) => return Err(syntax_error(number, &format!("duplicate key {}", key))),
+            None => fields.push((key, value)),
+        }
+    }
+    Ok(fields)
+}
+
+fn parse_block(lines: &mut [Line], pos: &mut usize, indent: usize) -> Result<Value> {
+    match is_sequence_item(&lines[*pos].content) {
+        true => parse_sequence(lines, pos, indent),
+        false => Ok(Value::Object(
+            parse_mapping(lines, pos, indent)?.into_iter().collect::<Map<_, _>>(),
+        )),
+    }
+}
+
+fn parse_sequence(lines: &mut [Line], pos: &mut usize, indent: usize) -> Result<Value> {
+    let mut items = Vec::new();
+    while let Some(line) = lines
+        .get(*pos)
+        .filter(|line| line.indent == indent && is_sequence_item(&line.content))
+    {
+        let number = line.number;
+        let item = line.content[1..].trim_start().to_string();
+        if item.is_empty() {
+            *pos += 1;
+            items.push(match lines.get(*pos) {
+                Some(next) if next.indent > indent => {
+                    let child = next.indent;
+                    parse_block(lines, pos, child)?
+                }
+                _ => Value::Null,
+            });
+        } else if split_key(&item).is_some() {
+            // `- key: value` starts a mapping indented to the key
+            let line = &mut lines[*pos];
+            line.indent += line.content.len() - item.len();
+            line.content = item;
+            let child = line.indent;
+            items.push(parse_block(lines, pos, child)?);
+        } else {
+            *pos += 1;
+            items.push(scalar(&item).map_err(|message| syntax_error(number, &message))?);
+        }
+    }
+    Ok(Value::Array(items))
+}
+
+/// The lines of a `|` (literal) or `>` (folded) block indented under the key
+fn block_scalar(lines: &[Line], pos: &mut usize, indent: usize, literal: bool, strip: bool) -> Value {
+    let mut parts = Vec::new();
+    while let Some(line) = lines.get(*pos).filter(|line| line.indent > indent) {
+        parts.push(line.content.as_str());
+        *pos += 1;
+    }
+    let mut text = parts.join(if literal { "\n" } else { " " });
+    if !strip && !text.is_empty() {
+        text.push('\n');
+    }
+    Value::String(text)
+}
+
+/// `key: value` split into its unquoted key and the trimmed value
+fn split_key(content: &str) -> Option<(String, String)> {
+    let (key, rest) = match content.chars().next()? {
+        '"' | '\'' => {
+            let end = quoted_end(content)?;
+            let key = match scalar(&content[..end]).ok()? {
+                Value::String(key) => key,
+                _ => return None,
+            };
+            (key, content[end..].strip_prefix(':')?)
+        }
+        _ => {
+            let colon = content.find(": ").or_else(|| content.strip_suffix(':').map(str::len))?;
+            (content[..colon].trim_end().to_string(), &content[colon + 1..])
+        }
+    };
+    if !rest.is_empty() && !rest.starts_with(' ') {
+        return None;
+    }
+    Some((key, rest.trim().to_string()))
+}
+
+/// Byte offset just past the closing quote of the string `s` starts with
+fn quoted_end(s: &str) -> Option<usize> {
+    let quote = s.chars().next()?;
+    let mut chars = s.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' if quote == '"' => {
+                chars.next();
+            }
+            // '' is an escaped quote in single-quoted strings
+            '\'' if quote == '\'' && s[i + 1..].starts_with('\'') => {
+                chars.next();
+            }
+            c if c == quote => return Some(i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn scalar(s: &str) -> std::result::Result<Value, String> {
+    match s.chars().next() {
+        Some('"') => {
+            let end = quoted_end(s).ok_or("unterminated string")?;
+            trailing_comment(&s[end..])?;
+            serde_json::from_str(&s[..end]).map_err(|e| format!("invalid string: {}", e))
+        }
+        Some('\'') => {
+            let end = quoted_end(s).ok_or("unterminated string")?;
+            trailing_comment(&s[end..])?;
+            Ok(Value::String(s[1..end - 1].replace("''", "'")))
+        }
+        Some('[') => {
+            let inner = s
+                .strip_prefix('[')
+                .and_then(|s| s.strip_suffix(']'))
+                .ok_or("unterminated list")?;
+            flow_items(inner)?
+                .into_iter()
+                .map(scalar)
+                .collect::<std::result::Result<_, _>>()
+                .map(Value::Array)
+        }
+        Some('{') if s == "{}" => Ok(Value::Object(Map::new())),
+        Some('{') => Err("flow mappings are not supported".to_string()),
+        _ => Ok(plain_scalar(s.split(" #").next().unwrap_or_default().trim_end())),
+    }
+}
+
+fn trailing_comment(rest: &str) -> std::result::Result<(), String> {
+    let rest = rest.trim_start();
+    match rest.is_empty() || rest.starts_with('#') {
+        true => Ok(()),
+        false => Err(format!("unexpected {} after string", rest)),
+    }
+}
+
+/// Comma-separated items of a `[flow]` list, leaving commas in quotes alone
+fn flow_items(inner: &str) -> std::result::Result<Vec<&str>, String> {
+    let mut items = Vec::new();
+    let mut rest = inner.trim();
+    while !rest.is_empty() {
+        let end = match rest.chars().next() {
+            Some('"' | '\'') => {
+                let end = quoted_end(rest).ok_or("unterminated string")?;
+                rest[end..].find(',').map_or(rest.len(), |comma| end + comma)
+            }
+            _ => rest.find(',').unwrap_or(rest.len()),
+        };
+        items.push(rest[..end].trim());
+        rest = rest[end..].strip_prefix(',').unwrap_or_default().trim_start();
+    }
+    Ok(items)
+}
+
+/// An unquoted scalar: null, a bool, a number, or else a string
+fn plain_scalar(s: &str) -> Value {
+    match s {
+        "" | "~" | "null" | "Null" | "NULL" => return Value::Null,
+        "true" | "True" | "TRUE" => return Value::Bool(true),
+        "false" | "False" | "FALSE" => return Value::Bool(false),
+        _ => {}
+    }
+    if let Ok(n) = s.parse::<i64>() {
+        return Value::Number(n.into());
+    }
+    let numeric = s.starts_with(|c: char| c.is_ascii_digit() || matches!(c, '-' | '+' | '.'));
+    match s.parse::<f64>().ok().filter(|_| numeric).and_then(Number::from_f64) {
+        Some(n) => Value::Number(n),
+        None => Value::String(s.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_render() {
+        let meta = FrontMatter::new()
+            .field("title", "Ask HN: what's \"good\" #code?")
+            .field("id", 42)
+            .field("url", "https://example.com/a?b=1")
+            .field("draft", false)
+            .field("tags", json!(["rust", "2024", "yes"]))
+            .field("author", json!({"name": "pg", "karma": 1.5}))
+            .field("empty", json!([]))
+            .field("id", 43);
+        assert_eq!(
+            meta.render("Body\n"),
+            "---\n\
+             title: \"Ask HN: what's \\\"good\\\" #code?\"\n\
+             id: 43\n\
+             url: https://example.com/a?b=1\n\
+             draft: false\n\
+             tags:\n  - rust\n  - \"2024\"\n  - \"yes\"\n\
+             author:\n  karma: 1.5\n  name: pg\n\
+             empty: []\n\
+             ---\n\nBody\n"
+        );
+        assert_eq!(FrontMatter::new().render("Body\n"), "Body\n");
+
+        // Everything rendered parses back to the same values
+        let page = meta.render("Body\n");
+        let (parsed, body) = parse(&page).unwrap();
+        assert_eq!(parsed, meta);
+        assert_eq!(body, "Body\n");
+        let tricky = FrontMatter::new()
+            .field("a", "line\nbreak")
+            .field("b", "true")
+            .field("c", "-1")
+            .field("d", " padded ")
+            .field("e", "ends:");
+        assert_eq!(parse(&tricky.render("")).unwrap().0, tricky);
+    }
+
+    #[test]
+    fn test_parse() {
+        let text = "---\r\n\
+                    # written by hand\n\
+                    title: 'It''s here'   \n\
+                    date: 2024-06-01\n\
+                    rating: 4.5\n\
+                    tags: [notes, \"a, b\", 3]\n\
+                    aliases:\n\
+                    - first\n\
+                    - second # comment\n\
+                    links:\n  \
+                      - url: https://a.example\n    \
+                        title: A\n  \
+                      - url: https://b.example\n\
+                    summary: >\n  \
+                      folded\n  \
+                      text\n\
+                    notes: |-\n  \
+                      line 1\n  \
+                      line 2\n\
+                    nothing:\n\
+                    ...\n\
+                    # Heading\n";
+        let (meta, body) = parse(text).unwrap();
+        assert_eq!(body, "# Heading\n");
+        assert_eq!(meta.get("title"), Some(&json!("It's here")));
+        assert_eq!(meta.get("date"), Some(&json!("2024-06-01")));
+        assert_eq!(meta.get("rating"), Some(&json!(4.5)));
+        assert_eq!(meta.get("tags"), Some(&json!(["notes", "a, b", 3])));
+        assert_eq!(meta.get("aliases"), Some(&json!(["first", "second"])));
+        assert_eq!(
+            meta.get("links"),
+            Some(&json!([{"url": "https://a.example", "title": "A"}, {"url": "https://b.example"}]))
+        );
+        assert_eq!(meta.get("summary"), Some(&json!("folded text\n")));
+        assert_eq!(meta.get("notes"), Some(&json!("line 1\nline 2")));
+        assert_eq!(meta.get("nothing"), Some(&Value::Null));
+        let keys: Vec<&str> = meta.iter().map(|(key, _)| key).collect();
+        assert_eq!(keys[..3], ["title", "date", "rating"]);
+
+        // No front matter, or an empty one
+        assert_eq!(parse("# Title\n---\n").unwrap(), (FrontMatter::new(), "# Title\n---\n"));
+        assert_eq!(parse("---\n---\nBody").unwrap(), (FrontMatter::new(), "Body"));
+
+        for bad in [
+            "---\ntitle: x\n",
+            "---\n- a\n---\n",
+            "---\ntitle: x\n  nested: y\n---\n",
+            "---\njust text\n---\n",
+            "---\na: 1\na: 2\n---\n",
+            "---\na: \"open\n---\n",
+        ] {
+            assert!(matches!(parse(bad), Err(Error::InvalidInput(_))), "{:?}", bad);
+        }
+    }
+}
@@ -0,0 +1,457 @@
+//! Hidden entries: tombstones and system files left out of listings
+//!
+//! Plugins keep entries users rarely want to see, such as tombstones of
+//! soft-deleted files or sync state, marked with [`FileInfo::hidden`].
+//! [`HiddenFs`] leaves them out of directory listings; they can still be
+//! opened by path, like dotfiles:
+//!
+//! ```ignore
+//! fn readdir(&self, path: &str) -> Result<Vec<FileInfo>> {
+//!     Ok(self.notes(path)?.map(|note| {
+//!         let info = FileInfo::file(&note.name, note.size, 0o644);
+//!         if note.deleted { info.hidden() } else { info }
+//!     }).collect())
+//! }
+//!
+//! type Exported = HiddenFs<NotesFS>;
+//! export_plugin!(Exported);
+//! ```
+//!
+//! Everything shows under [`ALL_DIR`], a view of the whole mount with hidden
+//! entries listed (`ls /mnt/notes/.all/archive`), or everywhere once the
+//! `show_hidden` config key is `true`. The view is not listed itself.
+
+use crate::error::Result;
+use crate::filesystem::{walk_tree, FileSystem, HandleFS, StreamFS, UploadFS};
+use crate::types::{
+    Advice, Capabilities, Config, ConfigParameter, FileInfo, FsOp, FsSchema, OpenFlag, PathSchema, UploadSession,
+    WarmupProgress, WriteFlag,
+};
+use std::time::Duration;
+
+/// View of the whole mount that lists hidden entries too
+pub const ALL_DIR: &str = "/.all";
+
+/// Config key listing hidden entries everywhere
+pub const SHOW_HIDDEN_CONFIG_KEY: &str = "show_hidden";
+
+/// `path` as the plugin names it, and whether it is inside [`ALL_DIR`]
+fn route(path: &str) -> (&str, bool) {
+    match path.strip_prefix(ALL_DIR) {
+        Some("") | Some("/") => ("/", true),
+        Some(rest) if rest.starts_with('/') => (rest, true),
+        _ => (path, false),
+    }
+}
+
+/// Filesystem wrapper leaving hidden entries out of listings
+///
+/// Listings are filtered after the plugin pages them, so a page may hold
+/// fewer than `limit` entries.
+pub struct HiddenFs<F> {
+    inner: F,
+    show_hidden: bool,
+}
+
+impl<F: Default> Default for HiddenFs<F> {
+    fn default() -> Self {
+        Self::new(F::default())
+    }
+}
+
+impl<F> HiddenFs<F> {
+    /// Wrap `inner`, hiding its hidden entries unless configured otherwise
+    pub fn new(inner: F) -> Self {
+        Self {
+            inner,
+            show_hidden: false,
+        }
+    }
+
+    /// List hidden entries everywhere regardless of the config
+    pub fn with_hidden_shown(mut self) -> Self {
+        self.show_hidden = true;
+        self
+    }
+
+    /// The wrapped filesystem
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    /// `entries` as listed at `path`
+    fn visible(&self, path: &str, mut entries: Vec<FileInfo>) -> Vec<FileInfo> {
+        if !self.show_hidden && !route(path).1 {
+            entries.retain(|entry| !entry.hidden);
+        }
+        entries
+    }
+}
+
+impl<F: FileSystem> FileSystem for HiddenFs<F> {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn readme(&self) -> &str {
+        self.inner.readme()
+    }
+
+    fn config_params(&self) -> Vec<ConfigParameter> {
+        let mut params = self.inner.config_params();
+        params.push(ConfigParameter::new(
+            SHOW_HIDDEN_CONFIG_KEY,
+            "bool",
+            false,
+            "false",
+            "List hidden entries such as tombstones; they always show under /.all",
+        ));
+        params
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    fn schema(&self) -> FsSchema {
+        self.inner
+            .schema()
+            .path(PathSchema::dir(ALL_DIR, "The whole mount, hidden entries included"))
+    }
+
+    fn validate(&self, config: &Config) -> Result<()> {
+        self.inner.validate(config)
+    }
+
+    fn initialize(&mut self, config: &Config) -> Result<()> {
+        if config.get_bool(SHOW_HIDDEN_CONFIG_KEY) == Some(true) {
+            self.show_hidden = true;
+        }
+        self.inner.initialize(config)
+    }
+
+    fn shutdown(&mut self) -> Result<()> {
+        self.inner.shutdown()
+    }
+
+    fn stats(&self) -> serde_json::Value {
+        self.inner.stats()
+    }
+
+    fn health(&self) -> Result<()> {
+        self.inner.health()
+    }
+
+    fn ctl(&mut self, command: &str) -> Result<()> {
+        self.inner.ctl(command)
+    }
+
+    fn maintain(&mut self) -> Result<()> {
+        self.inner.maintain()
+    }
+
+    fn warmup(&mut self, budget: Duration) -> Result<WarmupProgress> {
+        self.inner.warmup(budget)
+    }
+
+    fn export_state(&self) -> Result<Vec<u8>> {
+        self.inner.export_state()
+    }
+
+    fn import_state(&mut self, state: &[u8]) -> Result<()> {
+        self.inner.import_state(state)
+    }
+
+    fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+        self.inner.read(route(path).0, offset, size)
+    }
+
+    fn poll_read(&self, path: &str, offset: i64, size: i64, timeout: Duration) -> Result<Vec<u8>> {
+        self.inner.poll_read(route(path).0, offset, size, timeout)
+    }
+
+    fn stat(&self, path: &str) -> Result<FileInfo> {
+        let mut info = self.inner.stat(route(path).0)?;
+        if path.trim_end_matches('/') == ALL_DIR {
+            info.name = ALL_DIR[1..].to_string();
+            info.hidden = true;
+        }
+        Ok(info)
+    }
+
+    fn readdir(&self, path: &str) -> Result<Vec<FileInfo>> {
+        Ok(self.visible(path, self.inner.readdir(route(path).0)?))
+    }
+
+    fn readdir_page(&self, path: &str, offset: usize, limit: usize) -> Result<Vec<FileInfo>> {
+        Ok(self.visible(path, self.inner.readdir_page(route(path).0, offset, limit)?))
+    }
+
+    fn readdir_filtered(&self, path: &str, glob: &str, limit: usize) -> Result<Vec<FileInfo>> {
+        Ok(self.visible(path, self.inner.readdir_filtered(route(path).0, glob, limit)?))
+    }
+
+    /// Walked through [`Self::readdir`], so hidden directories are not
+    /// descended into outside [`ALL_DIR`]
+    fn walk(&self, path: &str, depth: usize) -> Result<Vec<(String, FileInfo)>> {
+        walk_tree(self, path, depth)
+    }
+
+    fn dir_generation(&self, path: &str) -> u64 {
+        self.inner.dir_generation(route(path).0)
+    }
+
+    fn write(&mut self, path: &str, data: &[u8], offset: i64, flags: WriteFlag) -> Result<i64> {
+        self.inner.write(route(path).0, data, offset, flags)
+    }
+
+    fn create(&mut self, path: &str) -> Result<()> {
+        self.inner.create(route(path).0)
+    }
+
+    fn mkdir(&mut self, path: &str, perm: u32) -> Result<()> {
+        self.inner.mkdir(route(path).0, perm)
+    }
+
+    fn remove(&mut self, path: &str) -> Result<()> {
+        self.inner.remove(route(path).0)
+    }
+
+    fn remove_all(&mut self, path: &str) -> Result<()> {
+        self.inner.remove_all(route(path).0)
+    }
+
+    fn rename(&mut self, old_path: &str, new_path: &str) -> Result<()> {
+        self.inner.rename(route(old_path).0, route(new_path).0)
+    }
+
+    fn chmod(&mut self, path: &str, mode: u32) -> Result<()> {
+        self.inner.chmod(route(path).0, mode)
+    }
+
+    fn symlink(&mut self, target: &str, link_path: &str) -> Result<()> {
+        self.inner.symlink(target, route(link_path).0)
+    }
+
+    fn readlink(&self, path: &str) -> Result<String> {
+        self.inner.readlink(route(path).0)
+    }
+
+    fn get_xattr(&self, path: &str, name: &str) -> Result<Vec<u8>> {
+        self.inner.get_xattr(route(path).0, name)
+    }
+
+    fn set_xattr(&mut self, path: &str, name: &str, value: &[u8]) -> Result<()> {
+        self.inner.set_xattr(route(path).0, name, value)
+    }
+
+    fn list_xattr(&self, path: &str) -> Result<Vec<String>> {
+        self.inner.list_xattr(route(path).0)
+    }
+
+    fn read_if_changed(&self, path: &str, etag: &str) -> Result<Option<Vec<u8>>> {
+        self.inner.read_if_changed(route(path).0, etag)
+    }
+
+    fn read_many(&self, paths: &[&str]) -> Vec<Result<Vec<u8>>> {
+        let paths: Vec<&str> = paths.iter().map(|path| route(path).0).collect();
+        self.inner.read_many(&paths)
+    }
+
+    fn write_if(&mut self, path: &str, data: &[u8], expected_etag: &str) -> Result<i64> {
+        self.inner.write_if(route(path).0, data, expected_etag)
+    }
+
+    fn batch(&mut self, mut ops: Vec<FsOp>) -> Vec<Result<()>> {
+        for path in ops.iter_mut().flat_map(FsOp::paths_mut) {
+            if let (inner, true) = route(path) {
+                *path = inner.to_string();
+            }
+        }
+        self.inner.batch(ops)
+    }
+
+    fn atomic_batch(&self) -> bool {
+        self.inner.atomic_batch()
+    }
+
+    fn begin_write_session(&mut self) -> Result<()> {
+        self.inner.begin_write_session()
+    }
+
+    fn commit_session(&mut self, message: &str) -> Result<()> {
+        self.inner.commit_session(message)
+    }
+
+    fn abort_session(&mut self) -> Result<()> {
+        self.inner.abort_session()
+    }
+
+    fn advise(&self, path: &str, offset: i64, len: i64, advice: Advice) -> Result<()> {
+        self.inner.advise(route(path).0, offset, len, advice)
+    }
+}
+
+impl<F: HandleFS> HandleFS for HiddenFs<F> {
+    fn open_handle(&mut self, path: &str, flags: OpenFlag, mode: u32) -> Result<i64> {
+        self.inner.open_handle(route(path).0, flags, mode)
+    }
+
+    fn handle_read(&mut self, id: i64, buf: &mut [u8]) -> Result<usize> {
+        self.inner.handle_read(id, buf)
+    }
+
+    fn handle_read_at(&self, id: i64, buf: &mut [u8], offset: i64) -> Result<usize> {
+        self.inner.handle_read_at(id, buf, offset)
+    }
+
+    fn handle_write(&mut self, id: i64, data: &[u8]) -> Result<usize> {
+        self.inner.handle_write(id, data)
+    }
+
+    fn handle_write_at(&self, id: i64, data: &[u8], offset: i64) -> Result<usize> {
+        self.inner.handle_write_at(id, data, offset)
+    }
+
+    fn handle_seek(&mut self, id: i64, offset: i64, whence: i32) -> Result<i64> {
+        self.inner.handle_seek(id, offset, whence)
+    }
+
+    fn handle_sync(&self, id: i64) -> Result<()> {
+        self.inner.handle_sync(id)
+    }
+
+    fn handle_stat(&self, id: i64) -> Result<FileInfo> {
+        self.inner.handle_stat(id)
+    }
+
+    fn handle_info(&self, id: i64) -> Result<(String, OpenFlag)> {
+        self.inner.handle_info(id)
+    }
+
+    fn close_handle(&mut self, id: i64) -> Result<()> {
+        self.inner.close_handle(id)
+    }
+}
+
+impl<F: StreamFS> StreamFS for HiddenFs<F> {
+    fn open_stream(&mut self, path: &str) -> Result<i64> {
+        self.inner.open_stream(route(path).0)
+    }
+
+    fn stream_read(&mut self, id: i64, buf: &mut [u8]) -> Result<Option<usize>> {
+        self.inner.stream_read(id, buf)
+    }
+
+    fn stream_write(&mut self, id: i64, data: &[u8]) -> Result<usize> {
+        self.inner.stream_write(id, data)
+    }
+
+    fn close_stream(&mut self, id: i64) -> Result<()> {
+        self.inner.close_stream(id)
+    }
+}
+
+impl<F: UploadFS> UploadFS for HiddenFs<F> {
+    fn begin_upload(&mut self, path: &str) -> Result<UploadSession> {
+        self.inner.begin_upload(route(path).0)
+    }
+
+    fn upload_status(&self, id: &str) -> Result<UploadSession> {
+        self.inner.upload_status(id)
+    }
+
+    fn upload_append(&mut self, id: &str, offset: u64, data: &[u8]) -> Result<UploadSession> {
+        self.inner.upload_append(id, offset, data)
+    }
+
+    fn commit_upload(&mut self, id: &str) -> Result<()> {
+        self.inner.commit_upload(id)
+    }
+
+    fn abort_upload(&mut self, id: &str) -> Result<()> {
+        self.inner.abort_upload(id)
+    }
+
+    fn list_uploads(&self) -> Result<Vec<UploadSession>> {
+        self.inner.list_uploads()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use crate::filesystem::ReadOnlyFileSystem;
+    use serde_json::json;
+
+    /// A note, the tombstone of a deleted one and a hidden sync directory
+    struct Notes;
+
+    impl ReadOnlyFileSystem for Notes {
+        fn name(&self) -> &str {
+            "notes"
+        }
+
+        fn read(&self, path: &str, _offset: i64, _size: i64) -> Result<Vec<u8>> {
+            match path {
+                "/todo.md" | "/old.md" => Ok(b"note".to_vec()),
+                _ => Err(Error::NotFound),
+            }
+        }
+
+        fn stat(&self, path: &str) -> Result<FileInfo> {
+            match path {
+                "/" => Ok(FileInfo::dir("", 0o755)),
+                "/old.md" => Ok(FileInfo::file("old.md", 4, 0o644).hidden()),
+                _ => Err(Error::NotFound),
+            }
+        }
+
+        fn readdir(&self, path: &str) -> Result<Vec<FileInfo>> {
+            match path {
+                "/" => Ok(vec![
+                    FileInfo::file("todo.md", 4, 0o644),
+                    FileInfo::file("old.md", 4, 0o644).hidden(),
+                    FileInfo::dir("sync", 0o755).hidden(),
+                ]),
+                "/sync" => Ok(vec![FileInfo::file("state", 0, 0o644)]),
+                _ => Err(Error::NotFound),
+            }
+        }
+    }
+
+    fn names(entries: Vec<FileInfo>) -> Vec<String> {
+        entries.into_iter().map(|entry| entry.name).collect()
+    }
+
+    #[test]
+    fn test_route() {
+        assert_eq!(route("/.all"), ("/", true));
+        assert_eq!(route("/.all/"), ("/", true));
+        assert_eq!(route("/.all/a/b"), ("/a/b", true));
+        assert_eq!(route("/.allx"), ("/.allx", false));
+        assert_eq!(route("/a/.all"), ("/a/.all", false));
+    }
+
+    #[test]
+    fn test_hidden_fs() {
+        let mut fs = HiddenFs::new(Notes);
+        assert_eq!(names(fs.readdir("/").unwrap()), ["todo.md"]);
+        assert_eq!(fs.read("/old.md", 0, -1).unwrap(), b"note", "hidden, not gone");
+        let walked: Vec<String> = fs.walk("/", 0).unwrap().into_iter().map(|(path, _)| path).collect();
+        assert_eq!(walked, ["/todo.md"]);
+
+        // The view lists everything under its own paths
+        assert_eq!(names(fs.readdir("/.all").unwrap()), ["todo.md", "old.md", "sync"]);
+        assert_eq!(fs.read("/.all/old.md", 0, -1).unwrap(), b"note");
+        let walked: Vec<String> = fs.walk("/.all", 0).unwrap().into_iter().map(|(path, _)| path).collect();
+        assert_eq!(walked, ["/.all/todo.md", "/.all/old.md", "/.all/sync", "/.all/sync/state"]);
+        let view = fs.stat("/.all/").unwrap();
+        assert!(view.is_dir() && view.hidden);
+        assert_eq!(view.name, ".all");
+
+        fs.initialize(&Config::from(json!({ SHOW_HIDDEN_CONFIG_KEY: true }))).unwrap();
+        assert_eq!(names(fs.readdir("/").unwrap()), ["todo.md", "old.md", "sync"]);
+    }
+}
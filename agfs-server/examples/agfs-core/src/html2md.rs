@@ -0,0 +1,309 @@
+//! HTML to Markdown conversion for text fields of web APIs
+//!
+//! APIs such as Hacker News return comment and story text as HTML fragments
+//! (`<p>`, `<a href>`, `<i>`, `<pre><code>`, entities). [`to_markdown`]
+//! turns such fragments into readable Markdown; it is not a full HTML parser
+//! and drops `<script>`, `<style>` and any tag it does not know.
+
+/// Decode character references such as `&amp;`, `&#x27;` and `&#39;`
+///
+/// Unknown or malformed references are left as they are.
+pub fn decode_entities(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let decoded = rest[1..]
+            .find(';')
+            .filter(|&end| end <= 10)
+            .and_then(|end| decode_entity(&rest[1..end + 1]).map(|c| (c, end + 2)));
+        match decoded {
+            Some((c, len)) => {
+                out.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn decode_entity(name: &str) -> Option<char> {
+    if let Some(num) = name.strip_prefix('#') {
+        let code = match num.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => num.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+    Some(match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        "ndash" => '–',
+        "mdash" => '—',
+        "hellip" => '…',
+        "lsquo" => '‘',
+        "rsquo" => '’',
+        "ldquo" => '“',
+        "rdquo" => '”',
+        "copy" => '©',
+        _ => return None,
+    })
+}
+
+/// Parsed tag: lowercase name, whether it closes, and its raw attributes
+struct Tag<'a> {
+    name: String,
+    closing: bool,
+    attrs: &'a str,
+}
+
+/// Split `html` into text runs and tags
+fn tokens(html: &str) -> Vec<Result<&str, Tag<'_>>> {
+    let mut out = Vec::new();
+    let mut rest = html;
+    while let Some(lt) = rest.find('<') {
+        if lt > 0 {
+            out.push(Ok(&rest[..lt]));
+        }
+        let Some(gt) = rest[lt..].find('>') else {
+            rest = &rest[lt..];
+            break;
+        };
+        let inner = &rest[lt + 1..lt + gt];
+        rest = &rest[lt + gt + 1..];
+        if inner.starts_with('!') {
+            continue; // comment or doctype
+        }
+        let (closing, inner) = match inner.strip_prefix('/') {
+            Some(inner) => (true, inner),
+            None => (false, inner),
+        };
+        let inner = inner.trim_end_matches('/');
+        let name_len = inner.find(|c: char| c.is_whitespace()).unwrap_or(inner.len());
+        out.push(Err(Tag {
+            name: inner[..name_len].to_ascii_lowercase(),
+            closing,
+            attrs: &inner[name_len..],
+        }));
+    }
+    if !rest.is_empty() {
+        out.push(Ok(rest));
+    }
+    out
+}
+
+/// Value of attribute `name` in a tag's raw attribute text
+fn attr(attrs: &str, name: &str) -> Option<String> {
+    let lower = attrs.to_ascii_lowercase();
+    let mut from = 0;
+    while let Some(i) = lower[from..].find(name).map(|i| i + from) {
+        from = i + name.len();
+        let boundary = lower[..i].chars().next_back().is_none_or(char::is_whitespace);
+        let rest = attrs[from..].trim_start();
+        let Some(value) = rest.strip_prefix('=').map(str::trim_start) else {
+            continue;
+        };
+        if !boundary {
+            continue;
+        }
+        let value = match value.chars().next() {
+            Some(q @ ('"' | '\'')) => value[1..].split(q).next().unwrap_or(""),
+            _ => value.split(|c: char| c.is_whitespace()).next().unwrap_or(""),
+        };
+        return Some(decode_entities(value));
+    }
+    None
+}
+
+/// Targets of all `<a href>` links, in order
+pub fn links(html: &str) -> Vec<String> {
+    tokens(html)
+        .into_iter()
+        .filter_map(|token| match token {
+            Err(tag) if tag.name == "a" && !tag.closing => attr(tag.attrs, "href"),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Convert an HTML fragment to Markdown
+///
+/// Handles paragraphs, line breaks, headings, emphasis, links, inline code,
+/// preformatted blocks, lists and block quotes.
+pub fn to_markdown(html: &str) -> String {
+    let mut out = String::new();
+    let mut links: Vec<Option<String>> = Vec::new();
+    let mut pre = 0usize;
+    let mut skip = 0usize;
+    let mut quote = 0usize;
+
+    for token in tokens(html) {
+        let tag = match token {
+            Ok(_) if skip > 0 => continue,
+            Ok(text) if pre > 0 => {
+                out.push_str(&decode_entities(text));
+                continue;
+            }
+            Ok(text) => {
+                let text = decode_entities(text);
+                let mut last_space = out.is_empty() || out.ends_with([' ', '\n']);
+                for c in text.chars() {
+                    if c.is_whitespace() && c != '\u{a0}' {
+                        if !last_space {
+                            out.push(' ');
+                        }
+                        last_space = true;
+                    } else {
+                        out.push(c);
+                        last_space = false;
+                    }
+                }
+                continue;
+            }
+            Err(tag) => tag,
+        };
+
+        match (tag.name.as_str(), tag.closing) {
+            ("script" | "style", closing) => {
+                skip = if closing { skip.saturating_sub(1) } else { skip + 1 };
+            }
+            (_, _) if skip > 0 => {}
+            ("p" | "div", _) => block_break(&mut out, quote),
+            ("br", _) => {
+                trim_trailing_spaces(&mut out);
+                out.push('\n');
+                push_quote(&mut out, quote);
+            }
+            ("h1" | "h2" | "h3" | "h4" | "h5" | "h6", false) => {
+                block_break(&mut out, quote);
+                let level = tag.name[1..].parse().unwrap_or(1);
+                out.push_str(&"#".repeat(level));
+                out.push(' ');
+            }
+            ("h1" | "h2" | "h3" | "h4" | "h5" | "h6", true) => block_break(&mut out, quote),
+            ("i" | "em", _) => out.push('*'),
+            ("b" | "strong", _) => out.push_str("**"),
+            ("code", _) if pre == 0 => out.push('`'),
+            ("pre", false) => {
+                block_break(&mut out, quote);
+                out.push_str("```\n");
+                pre += 1;
+            }
+            ("pre", true) if pre > 0 => {
+                pre -= 1;
+                if !out.ends_with('\n') {
+                    out.push('\n');
+                }
+                out.push_str("```");
+                block_break(&mut out, quote);
+            }
+            ("li", false) => {
+                trim_trailing_spaces(&mut out);
+                if !out.is_empty() && !out.ends_with('\n') {
+                    out.push('\n');
+                }
+                out.push_str("- ");
+            }
+            ("ul" | "ol", _) => block_break(&mut out, quote),
+            ("blockquote", closing) => {
+                quote = if closing { quote.saturating_sub(1) } else { quote + 1 };
+                block_break(&mut out, quote);
+            }
+            ("a", false) => {
+                let href = attr(tag.attrs, "href");
+                if href.is_some() {
+                    out.push('[');
+                }
+                links.push(href);
+            }
+            ("a", true) => {
+                if let Some(Some(href)) = links.pop() {
+                    out.push_str("](");
+                    out.push_str(&href);
+                    out.push(')');
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Blocks each add their own break; keep at most one blank line
+    let lines: Vec<&str> = out.lines().map(str::trim_end).collect();
+    let mut text = lines.join("\n");
+    while text.contains("\n\n\n") {
+        text = text.replace("\n\n\n", "\n\n");
+    }
+    text.trim().to_string()
+}
+
+/// End the current block with a blank line
+fn block_break(out: &mut String, quote: usize) {
+    trim_trailing_spaces(out);
+    if out.is_empty() {
+        push_quote(out, quote);
+        return;
+    }
+    if !out.ends_with('\n') {
+        out.push('\n');
+    }
+    out.push('\n');
+    push_quote(out, quote);
+}
+
+fn push_quote(out: &mut String, quote: usize) {
+    for _ in 0..quote {
+        out.push_str("> ");
+    }
+}
+
+fn trim_trailing_spaces(out: &mut String) {
+    let len = out.trim_end_matches(' ').len();
+    out.truncate(len);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_entities() {
+        assert_eq!(decode_entities("Tom &amp; Jerry&#x27;s &quot;show&quot; &#8212; &lt;3"), "Tom & Jerry's \"show\" — <3");
+        assert_eq!(decode_entities("AT&T &bogus; &#xZZ; a&b"), "AT&T &bogus; &#xZZ; a&b");
+    }
+
+    #[test]
+    fn test_hacker_news_text() {
+        let html = "I built this over a weekend.<p>See <a href=\"https:&#x2F;&#x2F;example.com&#x2F;x?a=1&amp;b=2\" rel=\"nofollow\">the repo</a> \
+                    for <i>details</i>.<p><pre><code>  cargo run\n  --release\n</code></pre>\nThoughts?";
+        assert_eq!(
+            to_markdown(html),
+            "I built this over a weekend.\n\n\
+             See [the repo](https://example.com/x?a=1&b=2) for *details*.\n\n\
+             ```\n  cargo run\n  --release\n```\n\n\
+             Thoughts?"
+        );
+        assert_eq!(links(html), vec!["https://example.com/x?a=1&b=2"]);
+    }
+
+    #[test]
+    fn test_structure() {
+        let html = "<h2>Notes</h2><ul><li>one <b>bold</b></li><li>two <code>x &lt; y</code></li></ul>\
+                    <blockquote>quoted<br>line</blockquote><script>alert(1)</script><!-- c -->end";
+        assert_eq!(
+            to_markdown(html),
+            "## Notes\n\n- one **bold**\n- two `x < y`\n\n> quoted\n> line\n\nend"
+        );
+        assert_eq!(to_markdown("plain   text\n with  spaces"), "plain text with spaces");
+        assert_eq!(to_markdown("<a name=\"top\">anchor</a>"), "anchor");
+    }
+}
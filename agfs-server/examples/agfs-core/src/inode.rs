@@ -0,0 +1,212 @@
+//! Stable inode numbers for paths
+//!
+//! FUSE frontends cache entries by inode number and misbehave when the number
+//! of a path changes between lookups. [`InodeMap`] derives the number from a
+//! hash of the path, so it is the same on every lookup and across restarts,
+//! and records each assignment so a collision is resolved once and stays
+//! resolved.
+//!
+//! Mappings made after a collision or rename depend on history, not just the
+//! path. Persist them with [`InodeMap::to_bytes`] whenever
+//! [`InodeMap::is_dirty`] says so (e.g. to a file through `HostFS`) and
+//! restore them with [`InodeMap::from_bytes`] on the next start.
+
+use crate::error::{Error, Result};
+use crate::types::FileInfo;
+use std::collections::HashMap;
+
+/// Inode number of the root directory, as on most Unix filesystems
+pub const ROOT_INO: u64 = 1;
+
+/// Path to inode number table
+#[derive(Debug, Default, Clone)]
+pub struct InodeMap {
+    by_path: HashMap<String, u64>,
+    by_ino: HashMap<u64, String>,
+    dirty: bool,
+}
+
+impl InodeMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inode number of `path`, assigning one on first use
+    pub fn ino(&mut self, path: &str) -> u64 {
+        if let Some(&ino) = self.by_path.get(path) {
+            return ino;
+        }
+        if path == "/" {
+            return ROOT_INO;
+        }
+
+        // Probe past numbers taken by other paths and the reserved 0 and 1
+        let mut ino = fnv1a(path.as_bytes());
+        while ino <= ROOT_INO || self.by_ino.contains_key(&ino) {
+            ino = ino.wrapping_add(1);
+        }
+        self.insert(path.to_string(), ino);
+        ino
+    }
+
+    /// Inode number of `path` if one has been assigned
+    pub fn get(&self, path: &str) -> Option<u64> {
+        match path {
+            "/" => Some(ROOT_INO),
+            _ => self.by_path.get(path).copied(),
+        }
+    }
+
+    /// Path with inode number `ino`
+    pub fn path(&self, ino: u64) -> Option<&str> {
+        match ino {
+            ROOT_INO => Some("/"),
+            _ => self.by_ino.get(&ino).map(String::as_str),
+        }
+    }
+
+    /// `info` with its `ino` set for `path`
+    pub fn assign(&mut self, path: &str, info: FileInfo) -> FileInfo {
+        info.with_ino(self.ino(path))
+    }
+
+    /// Carry the numbers of `from` and everything below it over to `to`
+    pub fn rename(&mut self, from: &str, to: &str) {
+        self.remove(to);
+        let prefix = format!("{}/", from.trim_end_matches('/'));
+        let moved: Vec<String> = self
+            .by_path
+            .keys()
+            .filter(|p| p.as_str() == from || p.starts_with(&prefix))
+            .cloned()
+            .collect();
+        for old in moved {
+            let ino = self.by_path.remove(&old).expect("key was just listed");
+            let new = format!("{}{}", to, &old[from.len()..]);
+            self.insert(new, ino);
+        }
+    }
+
+    /// Forget `path` and everything below it, freeing their numbers
+    pub fn remove(&mut self, path: &str) {
+        let prefix = format!("{}/", path.trim_end_matches('/'));
+        let before = self.by_path.len();
+        self.by_path.retain(|p, ino| {
+            let keep = p != path && !p.starts_with(&prefix);
+            if !keep {
+                self.by_ino.remove(ino);
+            }
+            keep
+        });
+        self.dirty |= self.by_path.len() != before;
+    }
+
+    /// Whether assignments changed since the last [`to_bytes`](Self::to_bytes)
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Serialize the table (a JSON object of path to number)
+    pub fn to_bytes(&mut self) -> Vec<u8> {
+        self.dirty = false;
+        serde_json::to_vec(&self.by_path).expect("string keys serialize")
+    }
+
+    /// Restore a table written by [`to_bytes`](Self::to_bytes)
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let by_path: HashMap<String, u64> = serde_json::from_slice(data)
+            .map_err(|e| Error::InvalidInput(format!("invalid inode table: {}", e)))?;
+        let mut map = Self::new();
+        for (path, ino) in by_path {
+            if ino <= ROOT_INO || map.by_ino.contains_key(&ino) {
+                return Err(Error::InvalidInput(format!("inode table reuses number {}", ino)));
+            }
+            map.insert(path, ino);
+        }
+        map.dirty = false;
+        Ok(map)
+    }
+
+    fn insert(&mut self, path: String, ino: u64) {
+        self.by_ino.insert(ino, path.clone());
+        self.by_path.insert(path, ino);
+        self.dirty = true;
+    }
+}
+
+/// 64-bit FNV-1a
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stable_numbers() {
+        let mut a = InodeMap::new();
+        let mut b = InodeMap::new();
+        assert_eq!(a.ino("/"), ROOT_INO);
+        let ino = a.ino("/docs/readme.md");
+        assert_eq!(a.ino("/docs/readme.md"), ino);
+        assert_eq!(b.ino("/docs/readme.md"), ino);
+        assert_ne!(a.ino("/docs"), ino);
+        assert_eq!(a.path(ino), Some("/docs/readme.md"));
+
+        let info = a.assign("/docs/readme.md", FileInfo::file("readme.md", 10, 0o644));
+        assert_eq!(info.ino, Some(ino));
+    }
+
+    #[test]
+    fn test_collision_is_probed() {
+        let mut map = InodeMap::new();
+        let hash = fnv1a(b"/b");
+        map.insert("/a".to_string(), hash);
+
+        let ino = map.ino("/b");
+        assert_eq!(ino, hash + 1);
+        assert_eq!(map.ino("/b"), ino);
+        assert_eq!(map.get("/a"), Some(hash));
+    }
+
+    #[test]
+    fn test_rename_and_remove() {
+        let mut map = InodeMap::new();
+        let dir = map.ino("/a");
+        let file = map.ino("/a/f");
+        let other = map.ino("/ab");
+
+        map.rename("/a", "/b");
+        assert_eq!(map.get("/b"), Some(dir));
+        assert_eq!(map.get("/b/f"), Some(file));
+        assert_eq!(map.get("/a/f"), None);
+        assert_eq!(map.get("/ab"), Some(other));
+
+        map.remove("/b");
+        assert_eq!(map.get("/b/f"), None);
+        assert_eq!(map.path(file), None);
+        assert_eq!(map.get("/ab"), Some(other));
+    }
+
+    #[test]
+    fn test_persistence() {
+        let mut map = InodeMap::new();
+        let hash = fnv1a(b"/b");
+        map.insert("/a".to_string(), hash);
+        let probed = map.ino("/b");
+        assert!(map.is_dirty());
+
+        let data = map.to_bytes();
+        assert!(!map.is_dirty());
+        let restored = InodeMap::from_bytes(&data).unwrap();
+        assert_eq!(restored.get("/b"), Some(probed));
+        assert_eq!(restored.path(hash), Some("/a"));
+        assert!(!restored.is_dirty());
+
+        assert!(InodeMap::from_bytes(br#"{"/x": 5, "/y": 5}"#).is_err());
+        assert!(InodeMap::from_bytes(b"not json").is_err());
+    }
+}
@@ -0,0 +1,115 @@
+//! # AGFS Plugin Core
+//!
+//! Types and traits shared by the AGFS plugin SDKs: `agfs-wasm-ffi` for WASM
+//! plugins and `agfs-ffi` for native shared libraries. Both SDKs re-export
+//! everything here, so a filesystem implemented against these traits can be
+//! built for either backend.
+//!
+//! ## Targeting both backends
+//!
+//! Select the binding layer with a cargo feature and keep the filesystem
+//! itself backend-agnostic:
+//!
+//! ```toml
+//! [features]
+//! default = ["wasm"]
+//! wasm = ["dep:agfs-wasm-ffi"]
+//! native = ["dep:agfs-ffi"]
+//!
+//! [dependencies]
+//! agfs-core = { path = "../agfs-core" }
+//! agfs-wasm-ffi = { path = "../agfs-wasm-ffi", optional = true }
+//! agfs-ffi = { path = "../hellofs-rust/agfs-ffi", optional = true }
+//! ```
+//!
+//! ```ignore
+//! use agfs_core::prelude::*;
+//!
+//! #[derive(Default)]
+//! struct MyFS;
+//!
+//! impl FileSystem for MyFS { /* ... */ }
+//!
+//! #[cfg(feature = "wasm")]
+//! agfs_wasm_ffi::export_plugin!(MyFS);
+//! #[cfg(feature = "native")]
+//! agfs_ffi::export_plugin!(MyFS);
+//! ```
+
+pub mod archive;
+#[cfg(feature = "bench")]
+pub mod bench;
+pub mod breaker;
+pub mod buffer;
+pub mod cache;
+pub mod cancel;
+pub mod conflict;
+pub mod control;
+pub mod diff;
+pub mod encoding;
+pub mod error;
+pub mod expiry;
+pub mod fifo;
+pub mod filesystem;
+pub mod frontmatter;
+pub mod hidden;
+pub mod html2md;
+pub mod inode;
+pub mod log;
+pub mod mime;
+pub mod namer;
+pub mod normalize;
+pub mod paginate;
+pub mod policy;
+pub mod ratelimit;
+pub mod redact;
+pub mod retry;
+pub mod ring;
+pub mod table;
+pub mod template;
+pub mod time;
+pub mod types;
+pub mod vector;
+pub mod verify;
+
+// Re-export serde_json so plugins can build metadata without a direct dependency
+pub use serde_json;
+
+pub use archive::{ArchiveView, ExportScheduler};
+pub use breaker::CircuitBreaker;
+pub use buffer::WriteBuffer;
+pub use cache::{CachedFs, NegativeCache, RenderCache};
+pub use cancel::CancellationToken;
+pub use conflict::{ConflictPolicy, ConflictTracker};
+pub use control::ControlFs;
+pub use diff::DiffFs;
+pub use error::{Error, Result};
+pub use expiry::ExpiryFs;
+pub use fifo::{FifoFiles, FifoFs, Fifos};
+pub use filesystem::{FileHandle, FileSystem, HandleFS, ReadOnlyFileSystem, StreamFS, UploadFS};
+pub use frontmatter::FrontMatter;
+pub use hidden::HiddenFs;
+pub use inode::InodeMap;
+pub use namer::UniqueNamer;
+pub use normalize::NormalizeFs;
+pub use paginate::{Page, Paginator};
+pub use policy::PolicyFs;
+pub use ratelimit::RateLimitGuard;
+pub use retry::RetryPolicy;
+pub use ring::RingBuffer;
+pub use types::{
+    Advice, Capabilities, Config, ConfigParameter, FileInfo, FileKind, FsOp, FsSchema, Listing, MetaData, MountGrant,
+    OpenFlag, PathSchema, RangeLock, UploadSession, WarmupProgress, WriteFlag, MODE_SYMLINK,
+};
+pub use vector::VectorIndex;
+pub use verify::VerifyFs;
+
+/// Prelude module with common imports
+pub mod prelude {
+    pub use crate::error::{Error, Result};
+    pub use crate::filesystem::{FileSystem, HandleFS, ReadOnlyFileSystem, StreamFS, UploadFS};
+    pub use crate::types::{
+        Advice, Capabilities, Config, ConfigParameter, FileInfo, FileKind, FsOp, FsSchema, Listing, MetaData, OpenFlag,
+        PathSchema, RangeLock, UploadSession, WarmupProgress, WriteFlag,
+    };
+}
@@ -0,0 +1,118 @@
+//! Debug output kept in memory and served at `/.agfs/log`
+//!
+//! Plugin stderr usually ends up in the server's log, if anywhere, which
+//! users of a mount cannot see. [`eprintln!`](crate::eprintln) writes to
+//! stderr as before and also appends the line to a ring buffer of the last
+//! [`LOG_CAPACITY`] bytes, which [`ControlFs`](crate::ControlFs) serves as
+//! `/.agfs/log`, credentials redacted. Import it to capture a plugin's
+//! existing `eprintln!` calls without touching them:
+//!
+//! ```
+//! use agfs_core::eprintln;
+//!
+//! eprintln!("fetched {} stories", 30);
+//! assert!(String::from_utf8_lossy(&agfs_core::log::contents()).contains("fetched 30 stories\n"));
+//! ```
+//!
+//! When the buffer is full the oldest lines are dropped whole.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::Write;
+use std::sync::{Mutex, PoisonError};
+
+/// Bytes of output kept for `/.agfs/log`
+pub const LOG_CAPACITY: usize = 64 * 1024;
+
+static LOG: Mutex<LogRing> = Mutex::new(LogRing::new(LOG_CAPACITY));
+
+/// The latest lines of output, at most `capacity` bytes
+struct LogRing {
+    buf: VecDeque<u8>,
+    capacity: usize,
+}
+
+impl LogRing {
+    const fn new(capacity: usize) -> Self {
+        Self {
+            buf: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    fn push(&mut self, text: &str) {
+        self.buf.extend(text.as_bytes());
+        if self.buf.len() <= self.capacity {
+            return;
+        }
+        let cut = self.buf.len() - self.capacity;
+        let at_line_start = self.buf[cut - 1] == b'\n';
+        self.buf.drain(..cut);
+        if !at_line_start {
+            // Drop the rest of the line cut in half
+            let rest = self.buf.iter().position(|&b| b == b'\n').map_or(self.buf.len(), |i| i + 1);
+            self.buf.drain(..rest);
+        }
+    }
+}
+
+/// Append `text` to the log
+pub fn write(text: &str) {
+    LOG.lock().unwrap_or_else(PoisonError::into_inner).push(text);
+}
+
+/// Everything the log holds, oldest first
+pub fn contents() -> Vec<u8> {
+    LOG.lock().unwrap_or_else(PoisonError::into_inner).buf.iter().copied().collect()
+}
+
+/// Empty the log
+pub fn clear() {
+    LOG.lock().unwrap_or_else(PoisonError::into_inner).buf.clear();
+}
+
+/// Write a line to stderr and the log; used by [`eprintln!`](crate::eprintln)
+#[doc(hidden)]
+pub fn print(args: fmt::Arguments) {
+    let line = format!("{}\n", args);
+    let _ = std::io::stderr().write_all(line.as_bytes());
+    write(&line);
+}
+
+/// `std::eprintln!` that also keeps the line for `/.agfs/log`
+#[macro_export]
+macro_rules! eprintln {
+    () => {
+        $crate::log::print(format_args!(""))
+    };
+    ($($arg:tt)*) => {
+        $crate::log::print(format_args!($($arg)*))
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(ring: &LogRing) -> String {
+        String::from_utf8(ring.buf.iter().copied().collect()).unwrap()
+    }
+
+    #[test]
+    fn test_ring_drops_whole_lines() {
+        let mut ring = LogRing::new(16);
+        ring.push("one\n");
+        ring.push("two\n");
+        assert_eq!(text(&ring), "one\ntwo\n");
+
+        ring.push("three four\n");
+        assert_eq!(text(&ring), "two\nthree four\n");
+        ring.push("five\n");
+        assert_eq!(text(&ring), "three four\nfive\n");
+
+        ring.push("a line longer than the ring\n");
+        assert_eq!(text(&ring), "");
+        ring.push("x\n");
+        assert_eq!(text(&ring), "x\n");
+    }
+}
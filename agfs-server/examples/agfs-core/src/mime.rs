@@ -0,0 +1,148 @@
+//! MIME type detection for `FileInfo::content_type`
+//!
+//! The server's HTTP gateway serves file content with the type a plugin
+//! reports in `stat`, and as `application/octet-stream` otherwise. Plugins
+//! that know their formats set it directly; [`detect`] covers the rest from
+//! the file extension and, failing that, the first bytes of the content.
+
+/// Type served when nothing more specific is known
+pub const OCTET_STREAM: &str = "application/octet-stream";
+
+const EXTENSIONS: &[(&str, &str)] = &[
+    ("txt", "text/plain; charset=utf-8"),
+    ("log", "text/plain; charset=utf-8"),
+    ("md", "text/markdown; charset=utf-8"),
+    ("markdown", "text/markdown; charset=utf-8"),
+    ("html", "text/html; charset=utf-8"),
+    ("htm", "text/html; charset=utf-8"),
+    ("css", "text/css; charset=utf-8"),
+    ("csv", "text/csv; charset=utf-8"),
+    ("tsv", "text/tab-separated-values; charset=utf-8"),
+    ("xml", "application/xml"),
+    ("rss", "application/rss+xml"),
+    ("atom", "application/atom+xml"),
+    ("json", "application/json"),
+    ("jsonl", "application/x-ndjson"),
+    ("ndjson", "application/x-ndjson"),
+    ("yaml", "application/yaml"),
+    ("yml", "application/yaml"),
+    ("toml", "application/toml"),
+    ("js", "text/javascript; charset=utf-8"),
+    ("wasm", "application/wasm"),
+    ("pdf", "application/pdf"),
+    ("zip", "application/zip"),
+    ("gz", "application/gzip"),
+    ("tar", "application/x-tar"),
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("svg", "image/svg+xml"),
+    ("ico", "image/x-icon"),
+    ("mp3", "audio/mpeg"),
+    ("wav", "audio/wav"),
+    ("mp4", "video/mp4"),
+    ("webm", "video/webm"),
+];
+
+const MAGIC: &[(&[u8], &str)] = &[
+    (b"\x89PNG\r\n\x1a\n", "image/png"),
+    (b"\xff\xd8\xff", "image/jpeg"),
+    (b"GIF87a", "image/gif"),
+    (b"GIF89a", "image/gif"),
+    (b"%PDF-", "application/pdf"),
+    (b"PK\x03\x04", "application/zip"),
+    (b"\x1f\x8b", "application/gzip"),
+    (b"\0asm", "application/wasm"),
+    (b"ID3", "audio/mpeg"),
+];
+
+/// Type for the extension of `path`, if it is a known one
+pub fn from_extension(path: &str) -> Option<&'static str> {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    let (stem, ext) = name.rsplit_once('.')?;
+    if stem.is_empty() {
+        return None; // dotfile such as `.env`
+    }
+    let ext = ext.to_ascii_lowercase();
+    EXTENSIONS.iter().find(|(e, _)| *e == ext).map(|(_, t)| *t)
+}
+
+/// Type recognized from the leading bytes of `data`
+///
+/// Binary formats are matched by signature, markup and JSON by their first
+/// non-blank character, and anything else that is valid UTF-8 without
+/// control bytes counts as plain text.
+pub fn sniff(data: &[u8]) -> Option<&'static str> {
+    if let Some((_, t)) = MAGIC.iter().find(|(magic, _)| data.starts_with(magic)) {
+        return Some(t);
+    }
+    if data.len() >= 12 && &data[..4] == b"RIFF" && &data[8..12] == b"WEBP" {
+        return Some("image/webp");
+    }
+
+    // Only the head is inspected; a cut multi-byte character at the end is fine
+    let head = &data[..data.len().min(512)];
+    let text = match std::str::from_utf8(head) {
+        Ok(text) => text,
+        Err(e) if e.valid_up_to() + 4 > head.len() && e.error_len().is_none() => {
+            std::str::from_utf8(&head[..e.valid_up_to()]).expect("prefix is valid")
+        }
+        Err(_) => return None,
+    };
+    if text.is_empty() || text.chars().any(|c| c.is_control() && !c.is_whitespace()) {
+        return None;
+    }
+    let trimmed = text.trim_start();
+    let lower = trimmed.get(..trimmed.len().min(15)).unwrap_or("").to_ascii_lowercase();
+    if lower.starts_with("<!doctype html") || lower.starts_with("<html") {
+        Some("text/html; charset=utf-8")
+    } else if lower.starts_with("<?xml") {
+        Some("application/xml")
+    } else if trimmed.starts_with('{') || trimmed.starts_with('[') {
+        Some("application/json")
+    } else {
+        Some("text/plain; charset=utf-8")
+    }
+}
+
+/// Best type for `path` with content starting with `data`
+///
+/// The extension wins when known, since a `.csv` also sniffs as plain text.
+pub fn detect(path: &str, data: &[u8]) -> &'static str {
+    from_extension(path).or_else(|| sniff(data)).unwrap_or(OCTET_STREAM)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_extension() {
+        assert_eq!(from_extension("/stories/top.md"), Some("text/markdown; charset=utf-8"));
+        assert_eq!(from_extension("/img/Logo.PNG"), Some("image/png"));
+        assert_eq!(from_extension("/a.b/c"), None);
+        assert_eq!(from_extension("/.env"), None);
+        assert_eq!(from_extension("/data.unknown"), None);
+    }
+
+    #[test]
+    fn test_sniff() {
+        assert_eq!(sniff(b"\x89PNG\r\n\x1a\n...."), Some("image/png"));
+        assert_eq!(sniff(b"RIFF\0\0\0\0WEBPVP8 "), Some("image/webp"));
+        assert_eq!(sniff(b"  <!DOCTYPE html><html>"), Some("text/html; charset=utf-8"));
+        assert_eq!(sniff(b"\n{\"id\": 1}"), Some("application/json"));
+        assert_eq!(sniff("héllo wörld\n".as_bytes()), Some("text/plain; charset=utf-8"));
+        assert_eq!(sniff(&"é".as_bytes()[..1]), None);
+        assert_eq!(sniff(b"\0\x01\x02binary"), None);
+        assert_eq!(sniff(b""), None);
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect("/table.csv", b"a,b\n1,2\n"), "text/csv; charset=utf-8");
+        assert_eq!(detect("/item/123", b"{\"title\": \"x\"}"), "application/json");
+        assert_eq!(detect("/blob", &[0xde, 0xad, 0xbe, 0xef]), OCTET_STREAM);
+    }
+}
@@ -0,0 +1,210 @@
+//! Filesystem-safe names for upstream strings
+//!
+//! Plugins that name files after upstream data, such as story titles,
+//! article titles or S3 keys containing slashes, need names that are valid
+//! path segments, unique within their directory and the same on every
+//! listing. [`UniqueNamer`] slugifies each upstream key, appends `-2`, `-3`,
+//! ... when slugs collide, and records the assignment so a name read back
+//! from a path maps to its key:
+//!
+//! ```
+//! use agfs_core::namer::UniqueNamer;
+//!
+//! let mut names = UniqueNamer::new();
+//! assert_eq!(names.name("Show HN: My Project"), "show-hn-my-project");
+//! assert_eq!(names.name("Show HN — My project!"), "show-hn-my-project-2");
+//! assert_eq!(names.key("show-hn-my-project-2"), Some("Show HN — My project!"));
+//! ```
+//!
+//! Which key got the suffix depends on the order they were named in. Persist
+//! the table like an [`InodeMap`](crate::InodeMap), with
+//! [`UniqueNamer::to_bytes`] and [`UniqueNamer::from_bytes`], when names
+//! must stay put across restarts.
+
+use crate::error::{Error, Result};
+use std::collections::HashMap;
+
+/// Default longest name, in characters, before a dedupe suffix is added
+pub const DEFAULT_MAX_LEN: usize = 64;
+
+/// Name given to keys with nothing left after slugifying
+const EMPTY_SLUG: &str = "untitled";
+
+/// `text` as a lowercase path segment of at most `max_len` characters
+///
+/// Letters and digits are kept, anything else becomes a single `-` between
+/// them. Non-ASCII letters are kept too; normalize first if the names must be
+/// ASCII.
+pub fn slugify(text: &str, max_len: usize) -> String {
+    let mut slug = String::new();
+    let mut len = 0;
+    let mut gap = false;
+    for c in text.chars().flat_map(char::to_lowercase) {
+        if !c.is_alphanumeric() {
+            gap = true;
+            continue;
+        }
+        let needed = if gap && len > 0 { 2 } else { 1 };
+        if len + needed > max_len {
+            break;
+        }
+        if needed == 2 {
+            slug.push('-');
+        }
+        slug.push(c);
+        len += needed;
+        gap = false;
+    }
+    if slug.is_empty() {
+        return EMPTY_SLUG.to_string();
+    }
+    slug
+}
+
+/// Upstream key to unique file name table
+#[derive(Debug, Clone)]
+pub struct UniqueNamer {
+    by_key: HashMap<String, String>,
+    by_name: HashMap<String, String>,
+    max_len: usize,
+    dirty: bool,
+}
+
+impl Default for UniqueNamer {
+    fn default() -> Self {
+        Self::with_max_len(DEFAULT_MAX_LEN)
+    }
+}
+
+impl UniqueNamer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cut slugs to `max_len` characters, not counting a dedupe suffix
+    pub fn with_max_len(max_len: usize) -> Self {
+        Self {
+            by_key: HashMap::new(),
+            by_name: HashMap::new(),
+            max_len: max_len.max(1),
+            dirty: false,
+        }
+    }
+
+    /// Name of `key`, assigning one on first use
+    pub fn name(&mut self, key: &str) -> &str {
+        if !self.by_key.contains_key(key) {
+            let slug = slugify(key, self.max_len);
+            let name = (1..)
+                .map(|n| if n == 1 { slug.clone() } else { format!("{}-{}", slug, n) })
+                .find(|name| !self.by_name.contains_key(name))
+                .expect("some suffix is free");
+            self.insert(key.to_string(), name);
+        }
+        &self.by_key[key]
+    }
+
+    /// Name of `key` if one has been assigned
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.by_key.get(key).map(String::as_str)
+    }
+
+    /// Key that `name` was assigned to
+    pub fn key(&self, name: &str) -> Option<&str> {
+        self.by_name.get(name).map(String::as_str)
+    }
+
+    /// Forget `key`, freeing its name
+    pub fn remove(&mut self, key: &str) {
+        if let Some(name) = self.by_key.remove(key) {
+            self.by_name.remove(&name);
+            self.dirty = true;
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.by_key.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_key.is_empty()
+    }
+
+    /// Whether assignments changed since the last [`to_bytes`](Self::to_bytes)
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Serialize the table (a JSON object of key to name)
+    pub fn to_bytes(&mut self) -> Vec<u8> {
+        self.dirty = false;
+        serde_json::to_vec(&self.by_key).expect("string keys serialize")
+    }
+
+    /// Restore a table written by [`to_bytes`](Self::to_bytes)
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let by_key: HashMap<String, String> = serde_json::from_slice(data)
+            .map_err(|e| Error::InvalidInput(format!("invalid name table: {}", e)))?;
+        let mut namer = Self::new();
+        for (key, name) in by_key {
+            if name.is_empty() || name.contains('/') || namer.by_name.contains_key(&name) {
+                return Err(Error::InvalidInput(format!("name table has invalid or duplicate name {:?}", name)));
+            }
+            namer.insert(key, name);
+        }
+        namer.dirty = false;
+        Ok(namer)
+    }
+
+    fn insert(&mut self, key: String, name: String) {
+        self.by_name.insert(name.clone(), key.clone());
+        self.by_key.insert(key, name);
+        self.dirty = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Show HN: Rust 2.0 -- finally!", 64), "show-hn-rust-2-0-finally");
+        assert_eq!(slugify("photos/2024/IMG_01.JPG", 64), "photos-2024-img-01-jpg");
+        assert_eq!(slugify("Ünïcode Café", 64), "ünïcode-café");
+        assert_eq!(slugify("a long title here", 8), "a-long-t");
+        assert_eq!(slugify("?!", 64), "untitled");
+    }
+
+    #[test]
+    fn test_unique_names() {
+        let mut names = UniqueNamer::with_max_len(10);
+        assert_eq!(names.name("Hello, world"), "hello-worl");
+        assert_eq!(names.name("hello world!"), "hello-worl-2");
+        assert_eq!(names.name("Hello, world"), "hello-worl");
+        assert_eq!(names.key("hello-worl-2"), Some("hello world!"));
+        assert_eq!(names.get("unseen"), None);
+
+        names.remove("Hello, world");
+        assert_eq!(names.key("hello-worl"), None);
+        assert_eq!(names.name("HELLO WORLD"), "hello-worl");
+    }
+
+    #[test]
+    fn test_persistence() {
+        let mut names = UniqueNamer::new();
+        names.name("a/b");
+        names.name("a b");
+        assert!(names.is_dirty());
+
+        let data = names.to_bytes();
+        assert!(!names.is_dirty());
+        let restored = UniqueNamer::from_bytes(&data).unwrap();
+        assert_eq!(restored.get("a b"), Some("a-b-2"));
+        assert_eq!(restored.key("a-b"), Some("a/b"));
+        assert!(!restored.is_dirty());
+
+        assert!(UniqueNamer::from_bytes(br#"{"x": "n", "y": "n"}"#).is_err());
+        assert!(UniqueNamer::from_bytes(br#"{"x": "a/b"}"#).is_err());
+    }
+}
@@ -0,0 +1,630 @@
+//! Normalization of the names clients send and see
+//!
+//! macOS clients send names decomposed (NFD, `e` followed by a combining
+//! accent) while most backends and other clients store them composed (NFC),
+//! so looking up a name exactly as the plugin listed it can fail although
+//! both render the same. [`NormalizeFs`] rewrites every incoming path to the
+//! form set by the `path_normalization` config key before the plugin sees it:
+//!
+//! ```json
+//! "path_normalization": "nfc"
+//! ```
+//!
+//! Without the key paths pass through unchanged. Plugins comparing names
+//! themselves, e.g. against upstream keys, can use [`names_equal`].
+//!
+//! Setting `windows_names` to `true` also keeps names Windows cannot store,
+//! such as `Re: Ask HN?`, `CON` or a trailing dot, reachable from Windows
+//! clients. The offending characters are shown mapped to the Unicode private
+//! use area at U+F000 plus the character, as Samba and macOS map them, and
+//! mapped back on the way in (see [`windows_name`]).
+
+use crate::error::{Error, Result};
+use crate::filesystem::{FileSystem, HandleFS, StreamFS, UploadFS};
+use crate::types::{
+    Advice, Capabilities, Config, ConfigParameter, FileInfo, FsOp, FsSchema, OpenFlag, UploadSession, WarmupProgress,
+    WriteFlag,
+};
+use std::borrow::Cow;
+use std::time::Duration;
+use unicode_normalization::{is_nfc, is_nfd, UnicodeNormalization};
+
+/// Config key selecting the [`Normalization`]
+pub const NORMALIZATION_CONFIG_KEY: &str = "path_normalization";
+
+/// Config key enabling the [`windows_name`] mapping
+pub const WINDOWS_NAMES_CONFIG_KEY: &str = "windows_names";
+
+/// Start of the private use block that unrepresentable ASCII is mapped into
+const PRIVATE_BASE: u32 = 0xF000;
+
+/// Names Windows reserves for devices, with or without an extension
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9", "LPT1", "LPT2",
+    "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Unicode normalization form applied to paths
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Normalization {
+    /// Composed; what Linux and Windows clients usually send
+    Nfc,
+    /// Decomposed; what macOS clients send
+    Nfd,
+}
+
+impl Normalization {
+    /// The form under [`NORMALIZATION_CONFIG_KEY`], or `None` if the key is absent
+    pub fn from_config(config: &Config) -> Result<Option<Self>> {
+        match config.get_str(NORMALIZATION_CONFIG_KEY) {
+            None | Some("") => Ok(None),
+            Some(form) => match form.to_ascii_lowercase().as_str() {
+                "nfc" => Ok(Some(Self::Nfc)),
+                "nfd" => Ok(Some(Self::Nfd)),
+                _ => Err(Error::InvalidInput(format!(
+                    "invalid {}: {} (expected nfc or nfd)",
+                    NORMALIZATION_CONFIG_KEY, form
+                ))),
+            },
+        }
+    }
+
+    /// `text` in this form, borrowed when it already is
+    pub fn apply(self, text: &str) -> Cow<'_, str> {
+        match self {
+            Self::Nfc if is_nfc(text) => Cow::Borrowed(text),
+            Self::Nfc => Cow::Owned(text.nfc().collect()),
+            Self::Nfd if is_nfd(text) => Cow::Borrowed(text),
+            Self::Nfd => Cow::Owned(text.nfd().collect()),
+        }
+    }
+}
+
+/// Whether `a` and `b` are the same name up to Unicode normalization
+pub fn names_equal(a: &str, b: &str) -> bool {
+    a == b || a.nfc().eq(b.nfc())
+}
+
+fn is_windows_invalid(c: char) -> bool {
+    matches!(c, '<' | '>' | ':' | '"' | '\\' | '|' | '?' | '*') || c < ' '
+}
+
+fn to_private(c: char) -> char {
+    char::from_u32(PRIVATE_BASE + c as u32).expect("ASCII maps into the private use area")
+}
+
+/// `name` as a Windows client can store it
+///
+/// Characters Windows rejects, a trailing dot or space, and the first letter
+/// of a reserved device name (`CON`, `lpt1.txt`) are mapped to U+F000 plus
+/// the character. [`from_windows_name`] undoes it.
+pub fn windows_name(name: &str) -> Cow<'_, str> {
+    let stem = name.split('.').next().unwrap_or_default().trim_end_matches(' ');
+    let reserved = RESERVED_NAMES.iter().any(|r| r.eq_ignore_ascii_case(stem));
+    let trailing = name.ends_with(['.', ' ']) && name != "." && name != "..";
+    if !reserved && !trailing && !name.contains(is_windows_invalid) {
+        return Cow::Borrowed(name);
+    }
+
+    let last = name.chars().count() - 1;
+    let mapped = name.chars().enumerate().map(|(i, c)| {
+        if is_windows_invalid(c) || (reserved && i == 0) || (trailing && i == last) {
+            to_private(c)
+        } else {
+            c
+        }
+    });
+    Cow::Owned(mapped.collect())
+}
+
+/// The name a [`windows_name`] was made from
+pub fn from_windows_name(name: &str) -> Cow<'_, str> {
+    let is_mapped = |c: char| (PRIVATE_BASE..PRIVATE_BASE + 0x80).contains(&(c as u32));
+    if !name.contains(is_mapped) {
+        return Cow::Borrowed(name);
+    }
+    let unmapped = name.chars().map(|c| {
+        if is_mapped(c) {
+            char::from_u32(c as u32 - PRIVATE_BASE).expect("maps back to ASCII")
+        } else {
+            c
+        }
+    });
+    Cow::Owned(unmapped.collect())
+}
+
+/// Apply `map` to every segment of `path`
+fn map_segments<'a>(path: &'a str, map: fn(&str) -> Cow<'_, str>) -> Cow<'a, str> {
+    if path.split('/').all(|segment| matches!(map(segment), Cow::Borrowed(_))) {
+        return Cow::Borrowed(path);
+    }
+    Cow::Owned(path.split('/').map(map).collect::<Vec<_>>().join("/"))
+}
+
+/// Filesystem wrapper normalizing every incoming path
+///
+/// Export the wrapper in place of the filesystem:
+///
+/// ```ignore
+/// type Exported = NormalizeFs<MyFS>;
+/// export_plugin!(Exported);
+/// ```
+pub struct NormalizeFs<F> {
+    inner: F,
+    form: Option<Normalization>,
+    windows_names: bool,
+}
+
+impl<F: Default> Default for NormalizeFs<F> {
+    fn default() -> Self {
+        Self::new(F::default())
+    }
+}
+
+impl<F> NormalizeFs<F> {
+    /// Wrap `inner`; paths pass through until a form is configured
+    pub fn new(inner: F) -> Self {
+        Self {
+            inner,
+            form: None,
+            windows_names: false,
+        }
+    }
+
+    /// Use `form` instead of the one in the config
+    pub fn with_form(mut self, form: Normalization) -> Self {
+        self.form = Some(form);
+        self
+    }
+
+    /// Map names for Windows clients regardless of the config
+    pub fn with_windows_names(mut self) -> Self {
+        self.windows_names = true;
+        self
+    }
+
+    /// The wrapped filesystem
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    /// A path from a client as the plugin names it
+    fn path<'a>(&self, path: &'a str) -> Cow<'a, str> {
+        let path = if self.windows_names {
+            map_segments(path, from_windows_name)
+        } else {
+            Cow::Borrowed(path)
+        };
+        match (self.form, path) {
+            (None, path) => path,
+            (Some(form), Cow::Borrowed(path)) => form.apply(path),
+            (Some(form), Cow::Owned(path)) => Cow::Owned(form.apply(&path).into_owned()),
+        }
+    }
+
+    /// A path from the plugin as clients see it
+    fn client_path(&self, path: String) -> String {
+        if self.windows_names {
+            map_segments(&path, windows_name).into_owned()
+        } else {
+            path
+        }
+    }
+
+    fn client_info(&self, mut info: FileInfo) -> FileInfo {
+        if self.windows_names {
+            info.name = windows_name(&info.name).into_owned();
+        }
+        info
+    }
+
+    fn client_infos(&self, infos: Result<Vec<FileInfo>>) -> Result<Vec<FileInfo>> {
+        infos.map(|infos| infos.into_iter().map(|info| self.client_info(info)).collect())
+    }
+
+    fn client_session(&self, session: Result<UploadSession>) -> Result<UploadSession> {
+        session.map(|mut session| {
+            session.path = self.client_path(session.path);
+            session
+        })
+    }
+}
+
+impl<F: FileSystem> FileSystem for NormalizeFs<F> {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn readme(&self) -> &str {
+        self.inner.readme()
+    }
+
+    fn config_params(&self) -> Vec<ConfigParameter> {
+        let mut params = self.inner.config_params();
+        params.push(ConfigParameter::new(
+            NORMALIZATION_CONFIG_KEY,
+            "string",
+            false,
+            "",
+            "Unicode form incoming paths are converted to: nfc or nfd",
+        ));
+        params.push(ConfigParameter::new(
+            WINDOWS_NAMES_CONFIG_KEY,
+            "bool",
+            false,
+            "false",
+            "Map names Windows cannot store into the Unicode private use area",
+        ));
+        params
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    fn schema(&self) -> FsSchema {
+        self.inner.schema()
+    }
+
+    fn validate(&self, config: &Config) -> Result<()> {
+        Normalization::from_config(config)?;
+        self.inner.validate(config)
+    }
+
+    fn initialize(&mut self, config: &Config) -> Result<()> {
+        if let Some(form) = Normalization::from_config(config)? {
+            self.form = Some(form);
+        }
+        if config.get_bool(WINDOWS_NAMES_CONFIG_KEY) == Some(true) {
+            self.windows_names = true;
+        }
+        self.inner.initialize(config)
+    }
+
+    fn shutdown(&mut self) -> Result<()> {
+        self.inner.shutdown()
+    }
+
+    fn stats(&self) -> serde_json::Value {
+        self.inner.stats()
+    }
+
+    fn health(&self) -> Result<()> {
+        self.inner.health()
+    }
+
+    fn ctl(&mut self, command: &str) -> Result<()> {
+        self.inner.ctl(command)
+    }
+
+    fn maintain(&mut self) -> Result<()> {
+        self.inner.maintain()
+    }
+
+    fn warmup(&mut self, budget: Duration) -> Result<WarmupProgress> {
+        self.inner.warmup(budget)
+    }
+
+    fn export_state(&self) -> Result<Vec<u8>> {
+        self.inner.export_state()
+    }
+
+    fn import_state(&mut self, state: &[u8]) -> Result<()> {
+        self.inner.import_state(state)
+    }
+
+    fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+        self.inner.read(&self.path(path), offset, size)
+    }
+
+    fn poll_read(&self, path: &str, offset: i64, size: i64, timeout: Duration) -> Result<Vec<u8>> {
+        self.inner.poll_read(&self.path(path), offset, size, timeout)
+    }
+
+    fn stat(&self, path: &str) -> Result<FileInfo> {
+        self.inner.stat(&self.path(path)).map(|info| self.client_info(info))
+    }
+
+    fn readdir(&self, path: &str) -> Result<Vec<FileInfo>> {
+        self.client_infos(self.inner.readdir(&self.path(path)))
+    }
+
+    fn readdir_page(&self, path: &str, offset: usize, limit: usize) -> Result<Vec<FileInfo>> {
+        self.client_infos(self.inner.readdir_page(&self.path(path), offset, limit))
+    }
+
+    fn readdir_filtered(&self, path: &str, glob: &str, limit: usize) -> Result<Vec<FileInfo>> {
+        self.client_infos(self.inner.readdir_filtered(&self.path(path), &self.path(glob), limit))
+    }
+
+    fn walk(&self, path: &str, depth: usize) -> Result<Vec<(String, FileInfo)>> {
+        let entries = self.inner.walk(&self.path(path), depth)?;
+        Ok(entries
+            .into_iter()
+            .map(|(path, info)| (self.client_path(path), self.client_info(info)))
+            .collect())
+    }
+
+    fn dir_generation(&self, path: &str) -> u64 {
+        self.inner.dir_generation(&self.path(path))
+    }
+
+    fn write(&mut self, path: &str, data: &[u8], offset: i64, flags: WriteFlag) -> Result<i64> {
+        let path = self.path(path).into_owned();
+        self.inner.write(&path, data, offset, flags)
+    }
+
+    fn create(&mut self, path: &str) -> Result<()> {
+        let path = self.path(path).into_owned();
+        self.inner.create(&path)
+    }
+
+    fn mkdir(&mut self, path: &str, perm: u32) -> Result<()> {
+        let path = self.path(path).into_owned();
+        self.inner.mkdir(&path, perm)
+    }
+
+    fn remove(&mut self, path: &str) -> Result<()> {
+        let path = self.path(path).into_owned();
+        self.inner.remove(&path)
+    }
+
+    fn remove_all(&mut self, path: &str) -> Result<()> {
+        let path = self.path(path).into_owned();
+        self.inner.remove_all(&path)
+    }
+
+    fn rename(&mut self, old_path: &str, new_path: &str) -> Result<()> {
+        let (old_path, new_path) = (self.path(old_path).into_owned(), self.path(new_path).into_owned());
+        self.inner.rename(&old_path, &new_path)
+    }
+
+    fn chmod(&mut self, path: &str, mode: u32) -> Result<()> {
+        let path = self.path(path).into_owned();
+        self.inner.chmod(&path, mode)
+    }
+
+    fn symlink(&mut self, target: &str, link_path: &str) -> Result<()> {
+        let (target, link_path) = (self.path(target).into_owned(), self.path(link_path).into_owned());
+        self.inner.symlink(&target, &link_path)
+    }
+
+    fn readlink(&self, path: &str) -> Result<String> {
+        self.inner.readlink(&self.path(path)).map(|target| self.client_path(target))
+    }
+
+    fn get_xattr(&self, path: &str, name: &str) -> Result<Vec<u8>> {
+        self.inner.get_xattr(&self.path(path), name)
+    }
+
+    fn set_xattr(&mut self, path: &str, name: &str, value: &[u8]) -> Result<()> {
+        let path = self.path(path).into_owned();
+        self.inner.set_xattr(&path, name, value)
+    }
+
+    fn list_xattr(&self, path: &str) -> Result<Vec<String>> {
+        self.inner.list_xattr(&self.path(path))
+    }
+
+    fn read_if_changed(&self, path: &str, etag: &str) -> Result<Option<Vec<u8>>> {
+        self.inner.read_if_changed(&self.path(path), etag)
+    }
+
+    fn read_many(&self, paths: &[&str]) -> Vec<Result<Vec<u8>>> {
+        let normalized: Vec<Cow<str>> = paths.iter().map(|path| self.path(path)).collect();
+        let paths: Vec<&str> = normalized.iter().map(|path| path.as_ref()).collect();
+        self.inner.read_many(&paths)
+    }
+
+    fn write_if(&mut self, path: &str, data: &[u8], expected_etag: &str) -> Result<i64> {
+        let path = self.path(path).into_owned();
+        self.inner.write_if(&path, data, expected_etag)
+    }
+
+    fn batch(&mut self, mut ops: Vec<FsOp>) -> Vec<Result<()>> {
+        for path in ops.iter_mut().flat_map(FsOp::paths_mut) {
+            if let Cow::Owned(normalized) = self.path(path) {
+                *path = normalized;
+            }
+        }
+        self.inner.batch(ops)
+    }
+
+    fn atomic_batch(&self) -> bool {
+        self.inner.atomic_batch()
+    }
+
+    fn begin_write_session(&mut self) -> Result<()> {
+        self.inner.begin_write_session()
+    }
+
+    fn commit_session(&mut self, message: &str) -> Result<()> {
+        self.inner.commit_session(message)
+    }
+
+    fn abort_session(&mut self) -> Result<()> {
+        self.inner.abort_session()
+    }
+
+    fn advise(&self, path: &str, offset: i64, len: i64, advice: Advice) -> Result<()> {
+        self.inner.advise(&self.path(path), offset, len, advice)
+    }
+}
+
+impl<F: HandleFS> HandleFS for NormalizeFs<F> {
+    fn open_handle(&mut self, path: &str, flags: OpenFlag, mode: u32) -> Result<i64> {
+        let path = self.path(path).into_owned();
+        self.inner.open_handle(&path, flags, mode)
+    }
+
+    fn handle_read(&mut self, id: i64, buf: &mut [u8]) -> Result<usize> {
+        self.inner.handle_read(id, buf)
+    }
+
+    fn handle_read_at(&self, id: i64, buf: &mut [u8], offset: i64) -> Result<usize> {
+        self.inner.handle_read_at(id, buf, offset)
+    }
+
+    fn handle_write(&mut self, id: i64, data: &[u8]) -> Result<usize> {
+        self.inner.handle_write(id, data)
+    }
+
+    fn handle_write_at(&self, id: i64, data: &[u8], offset: i64) -> Result<usize> {
+        self.inner.handle_write_at(id, data, offset)
+    }
+
+    fn handle_seek(&mut self, id: i64, offset: i64, whence: i32) -> Result<i64> {
+        self.inner.handle_seek(id, offset, whence)
+    }
+
+    fn handle_sync(&self, id: i64) -> Result<()> {
+        self.inner.handle_sync(id)
+    }
+
+    fn handle_stat(&self, id: i64) -> Result<FileInfo> {
+        self.inner.handle_stat(id).map(|info| self.client_info(info))
+    }
+
+    fn handle_info(&self, id: i64) -> Result<(String, OpenFlag)> {
+        let (path, flags) = self.inner.handle_info(id)?;
+        Ok((self.client_path(path), flags))
+    }
+
+    fn close_handle(&mut self, id: i64) -> Result<()> {
+        self.inner.close_handle(id)
+    }
+}
+
+impl<F: StreamFS> StreamFS for NormalizeFs<F> {
+    fn open_stream(&mut self, path: &str) -> Result<i64> {
+        let path = self.path(path).into_owned();
+        self.inner.open_stream(&path)
+    }
+
+    fn stream_read(&mut self, id: i64, buf: &mut [u8]) -> Result<Option<usize>> {
+        self.inner.stream_read(id, buf)
+    }
+
+    fn stream_write(&mut self, id: i64, data: &[u8]) -> Result<usize> {
+        self.inner.stream_write(id, data)
+    }
+
+    fn close_stream(&mut self, id: i64) -> Result<()> {
+        self.inner.close_stream(id)
+    }
+}
+
+impl<F: UploadFS> UploadFS for NormalizeFs<F> {
+    fn begin_upload(&mut self, path: &str) -> Result<UploadSession> {
+        let path = self.path(path).into_owned();
+        let session = self.inner.begin_upload(&path);
+        self.client_session(session)
+    }
+
+    fn upload_status(&self, id: &str) -> Result<UploadSession> {
+        self.client_session(self.inner.upload_status(id))
+    }
+
+    fn upload_append(&mut self, id: &str, offset: u64, data: &[u8]) -> Result<UploadSession> {
+        let session = self.inner.upload_append(id, offset, data);
+        self.client_session(session)
+    }
+
+    fn commit_upload(&mut self, id: &str) -> Result<()> {
+        self.inner.commit_upload(id)
+    }
+
+    fn abort_upload(&mut self, id: &str) -> Result<()> {
+        self.inner.abort_upload(id)
+    }
+
+    fn list_uploads(&self) -> Result<Vec<UploadSession>> {
+        let sessions = self.inner.list_uploads()?;
+        sessions.into_iter().map(|session| self.client_session(Ok(session))).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::ReadOnlyFileSystem;
+    use serde_json::json;
+
+    const COMPOSED: &str = "/caf\u{e9}.txt";
+    const DECOMPOSED: &str = "/cafe\u{301}.txt";
+
+    #[test]
+    fn test_normalization() {
+        assert_eq!(Normalization::Nfc.apply(DECOMPOSED), COMPOSED);
+        assert_eq!(Normalization::Nfd.apply(COMPOSED), DECOMPOSED);
+        assert!(matches!(Normalization::Nfc.apply(COMPOSED), Cow::Borrowed(_)));
+        assert!(names_equal(COMPOSED, DECOMPOSED));
+        assert!(!names_equal(COMPOSED, "/cafe.txt"));
+
+        let config = |form: &str| Config::from(json!({ NORMALIZATION_CONFIG_KEY: form }));
+        assert_eq!(Normalization::from_config(&config("NFD")).unwrap(), Some(Normalization::Nfd));
+        assert_eq!(Normalization::from_config(&Config::default()).unwrap(), None);
+        assert!(matches!(Normalization::from_config(&config("nfkc")), Err(Error::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_windows_name() {
+        for (name, mapped) in [
+            ("Re: Ask HN?", "Re\u{f03a} Ask HN\u{f03f}"),
+            ("con", "\u{f063}on"),
+            ("LPT1.txt", "\u{f04c}PT1.txt"),
+            ("notes.", "notes\u{f02e}"),
+            ("a\\b", "a\u{f05c}b"),
+        ] {
+            assert_eq!(windows_name(name), mapped);
+            assert_eq!(from_windows_name(mapped), name);
+        }
+        for name in ["story.md", "console", ".", "..", "caf\u{e9}"] {
+            assert!(matches!(windows_name(name), Cow::Borrowed(_)), "{}", name);
+        }
+        assert_eq!(map_segments("/s3/a:b/CON", windows_name), "/s3/a\u{f03a}b/\u{f043}ON");
+    }
+
+    /// Stores one NFC name
+    struct Cafe;
+
+    impl ReadOnlyFileSystem for Cafe {
+        fn name(&self) -> &str {
+            "cafe"
+        }
+
+        fn read(&self, path: &str, _offset: i64, _size: i64) -> Result<Vec<u8>> {
+            match path {
+                COMPOSED => Ok(b"espresso".to_vec()),
+                _ => Err(Error::NotFound),
+            }
+        }
+
+        fn stat(&self, path: &str) -> Result<FileInfo> {
+            match path {
+                "/q?" => Ok(FileInfo::file("q?", 0, 0o644)),
+                _ => Err(Error::NotFound),
+            }
+        }
+
+        fn readdir(&self, _path: &str) -> Result<Vec<FileInfo>> {
+            Ok(vec![FileInfo::file("q?", 0, 0o644)])
+        }
+    }
+
+    #[test]
+    fn test_normalize_fs() {
+        let mut fs = NormalizeFs::new(Cafe);
+        assert_eq!(fs.read(DECOMPOSED, 0, -1), Err(Error::NotFound), "no form passes through");
+
+        fs.initialize(&Config::from(json!({ NORMALIZATION_CONFIG_KEY: "nfc" }))).unwrap();
+        assert_eq!(fs.read(DECOMPOSED, 0, -1).unwrap(), b"espresso");
+        let results = fs.read_many(&[DECOMPOSED, COMPOSED]);
+        assert!(results.iter().all(|r| r.is_ok()));
+
+        assert_eq!(fs.readdir("/").unwrap()[0].name, "q?");
+        let fs = NormalizeFs::new(Cafe).with_windows_names();
+        assert_eq!(fs.readdir("/").unwrap()[0].name, "q\u{f03f}");
+        assert_eq!(fs.stat("/q\u{f03f}").unwrap().name, "q\u{f03f}");
+    }
+}
@@ -0,0 +1,216 @@
+//! Lazy iteration over cursor-paginated upstream listings
+//!
+//! APIs such as GitHub, S3 and Slack return listings a page at a time, with
+//! a cursor (a token, or a URL) naming the next page. [`Paginator`] fetches
+//! a page only when the previous one has been consumed and holds one page
+//! at a time, so a directory listing over thousands of objects does not
+//! load them all up front:
+//!
+//! ```ignore
+//! fn readdir_page(&self, path: &str, offset: usize, limit: usize) -> Result<Vec<FileInfo>> {
+//!     Paginator::new(|cursor| {
+//!         let url = cursor.map_or_else(|| self.first_page_url(path), str::to_string);
+//!         let response = Http::get(&url)?;
+//!         let next = response.headers.get("Link").and_then(|link| paginate::next_link(link));
+//!         Ok(Page::new(self.to_file_infos(&response)?, next))
+//!     })
+//!     .page(offset, limit)
+//! }
+//! ```
+
+use crate::error::Result;
+
+/// One page of a listing and the cursor of the page after it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// `None` (or an empty cursor) on the last page
+    pub next: Option<String>,
+}
+
+impl<T> Page<T> {
+    pub fn new(items: Vec<T>, next: Option<String>) -> Self {
+        Self { items, next }
+    }
+
+    /// The last page of a listing
+    pub fn last(items: Vec<T>) -> Self {
+        Self { items, next: None }
+    }
+}
+
+/// Iterates the items of a paginated listing, fetching pages on demand
+///
+/// `fetch` is called with the cursor of the page to fetch, `None` for the
+/// first one. Iteration ends after the last page, after `max_pages`, or
+/// after yielding the first error of `fetch`.
+pub struct Paginator<T, F> {
+    fetch: F,
+    cursor: Option<String>,
+    items: std::vec::IntoIter<T>,
+    pages: usize,
+    max_pages: usize,
+    done: bool,
+}
+
+impl<T, F> Paginator<T, F>
+where
+    F: FnMut(Option<&str>) -> Result<Page<T>>,
+{
+    pub fn new(fetch: F) -> Self {
+        Self {
+            fetch,
+            cursor: None,
+            items: Vec::new().into_iter(),
+            pages: 0,
+            max_pages: usize::MAX,
+            done: false,
+        }
+    }
+
+    /// Start at the page `cursor` names, as returned by
+    /// [`cursor`](Self::cursor) of an earlier paginator
+    pub fn resume(cursor: &str, fetch: F) -> Self {
+        let mut paginator = Self::new(fetch);
+        paginator.cursor = Some(cursor.to_string());
+        paginator
+    }
+
+    /// Stop after fetching `pages` pages
+    pub fn max_pages(mut self, pages: usize) -> Self {
+        self.max_pages = pages;
+        self
+    }
+
+    /// Cursor of the next page to fetch, `None` once the last one has been
+    /// fetched (or before the first)
+    pub fn cursor(&self) -> Option<&str> {
+        self.cursor.as_deref()
+    }
+
+    /// Pages fetched so far
+    pub fn pages_fetched(&self) -> usize {
+        self.pages
+    }
+
+    /// Items `offset..offset + limit`, fetching only the pages they are on
+    pub fn page(self, offset: usize, limit: usize) -> Result<Vec<T>> {
+        self.skip(offset).take(limit).collect()
+    }
+}
+
+impl<T, F> Iterator for Paginator<T, F>
+where
+    F: FnMut(Option<&str>) -> Result<Page<T>>,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Result<T>> {
+        loop {
+            if let Some(item) = self.items.next() {
+                return Some(Ok(item));
+            }
+            let exhausted = self.pages > 0 && self.cursor.is_none();
+            if self.done || exhausted || self.pages >= self.max_pages {
+                return None;
+            }
+            match (self.fetch)(self.cursor.as_deref()) {
+                Ok(page) => {
+                    self.pages += 1;
+                    self.cursor = page.next.filter(|next| !next.is_empty());
+                    self.items = page.items.into_iter();
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+/// The `rel="next"` URL of an RFC 8288 `Link` header, as GitHub sends
+pub fn next_link(link: &str) -> Option<String> {
+    link.split(',').find_map(|entry| {
+        let (url, params) = entry.split_once(';')?;
+        let is_next = params.split(';').any(|param| {
+            let param = param.trim().to_ascii_lowercase();
+            param == "rel=\"next\"" || param == "rel=next"
+        });
+        let url = url.trim().strip_prefix('<')?.strip_suffix('>')?;
+        is_next.then(|| url.to_string())
+    })
+}
+
+/// The non-empty string at JSON pointer `pointer` of a response body, e.g.
+/// `/response_metadata/next_cursor` for Slack
+pub fn json_cursor(body: &serde_json::Value, pointer: &str) -> Option<String> {
+    body.pointer(pointer)
+        .and_then(|cursor| cursor.as_str())
+        .filter(|cursor| !cursor.is_empty())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+    use std::cell::Cell;
+
+    /// Pages of three numbers, `cursor` being the first number of the page
+    fn numbers(fetched: &Cell<usize>, total: u32) -> impl FnMut(Option<&str>) -> Result<Page<u32>> + '_ {
+        move |cursor| {
+            fetched.set(fetched.get() + 1);
+            let start: u32 = cursor.map_or(0, |c| c.parse().unwrap());
+            let end = (start + 3).min(total);
+            Ok(Page::new((start..end).collect(), (end < total).then(|| end.to_string())))
+        }
+    }
+
+    #[test]
+    fn test_fetches_lazily() {
+        let fetched = Cell::new(0);
+        let all: Result<Vec<u32>> = Paginator::new(numbers(&fetched, 8)).collect();
+        assert_eq!(all.unwrap(), (0..8).collect::<Vec<_>>());
+        assert_eq!(fetched.get(), 3);
+
+        fetched.set(0);
+        assert_eq!(Paginator::new(numbers(&fetched, 100)).page(4, 3).unwrap(), [4, 5, 6]);
+        assert_eq!(fetched.get(), 3);
+
+        fetched.set(0);
+        let mut paginator = Paginator::new(numbers(&fetched, 100)).max_pages(2);
+        assert_eq!(paginator.by_ref().count(), 6);
+        assert_eq!(paginator.cursor(), Some("6"));
+        let resumed: Vec<u32> = Paginator::resume("6", numbers(&fetched, 8)).map(Result::unwrap).collect();
+        assert_eq!(resumed, [6, 7]);
+    }
+
+    #[test]
+    fn test_stops_at_error() {
+        let mut calls = 0;
+        let mut paginator = Paginator::new(|cursor: Option<&str>| {
+            calls += 1;
+            match cursor {
+                None => Ok(Page::new(vec![1], Some("2".to_string()))),
+                Some(_) => Err(Error::TimedOut),
+            }
+        });
+        assert_eq!(paginator.next(), Some(Ok(1)));
+        assert_eq!(paginator.next(), Some(Err(Error::TimedOut)));
+        assert_eq!(paginator.next(), None);
+        drop(paginator);
+        assert_eq!(calls, 2);
+    }
+
+    #[test]
+    fn test_cursor_extraction() {
+        let link = r#"<https://api.github.com/repos/o/r/issues?page=1>; rel="prev", <https://api.github.com/repos/o/r/issues?page=3>; rel="next""#;
+        assert_eq!(next_link(link).as_deref(), Some("https://api.github.com/repos/o/r/issues?page=3"));
+        assert_eq!(next_link(r#"<https://x/?page=1>; rel="first""#), None);
+
+        let body = serde_json::json!({ "response_metadata": { "next_cursor": "dGVhbTpD" } });
+        assert_eq!(json_cursor(&body, "/response_metadata/next_cursor").as_deref(), Some("dGVhbTpD"));
+        assert_eq!(json_cursor(&serde_json::json!({ "next": "" }), "/next"), None);
+    }
+}
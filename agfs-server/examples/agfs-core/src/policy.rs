@@ -0,0 +1,687 @@
+//! Declarative per-user access policy for shared mounts
+//!
+//! [`PolicyFs`] wraps a filesystem and checks every call against a list of
+//! [`Rule`]s before it reaches the plugin. A rule grants a set of [`Op`]s on
+//! the paths matching a glob to the listed users and groups; anything no rule
+//! grants is denied with `PermissionDenied`. Rules come from the `policy`
+//! config key, either as a JSON array or a string holding one:
+//!
+//! ```json
+//! "policy": [
+//!     {"path": "/",           "users": ["*"],     "ops": ["list"]},
+//!     {"path": "/public/**",  "users": ["*"],     "ops": ["read", "list"]},
+//!     {"path": "/team/**",    "groups": ["eng"],  "ops": ["read", "list", "write", "create", "delete"]},
+//!     {"path": "/home/alice/**", "users": ["alice"], "ops": ["read", "list", "write", "create", "delete"]}
+//! ]
+//! ```
+//!
+//! Without a `policy` key every call is passed through, as before.
+
+use crate::error::{Error, Result};
+use crate::filesystem::{reject_batch, split_batch, FileSystem, HandleFS, StreamFS, UploadFS};
+use crate::types::{
+    Advice, Capabilities, Config, ConfigParameter, FileInfo, FsOp, FsSchema, OpenFlag, UploadSession, WarmupProgress,
+    WriteFlag,
+};
+use serde::Deserialize;
+use std::time::Duration;
+
+/// Config key holding the rule list
+pub const POLICY_CONFIG_KEY: &str = "policy";
+
+/// Operation classes a rule can grant
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Op {
+    /// read, stat, readlink, xattr reads, read-only handles and streams
+    Read,
+    /// readdir
+    List,
+    /// write, chmod, set_xattr and writable handles
+    Write,
+    /// create, mkdir, symlink and the destination of a rename
+    Create,
+    /// remove, remove_all and the source of a rename
+    Delete,
+}
+
+/// Identity an operation is checked against
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Principal {
+    /// User name; empty for an anonymous caller
+    pub user: String,
+    /// Groups the user belongs to
+    pub groups: Vec<String>,
+}
+
+impl Principal {
+    /// Caller with no identity, matched only by `"*"` rules
+    pub fn anonymous() -> Self {
+        Self::default()
+    }
+
+    /// Named user with the given groups
+    pub fn user(user: impl Into<String>, groups: &[&str]) -> Self {
+        Self {
+            user: user.into(),
+            groups: groups.iter().map(|g| g.to_string()).collect(),
+        }
+    }
+}
+
+/// Grant of `ops` on paths matching `path` to `users` and members of `groups`
+///
+/// `"*"` in `users` or `groups` matches every caller, including anonymous
+/// ones; a rule listing neither matches no one.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct Rule {
+    /// Glob: `*` and `?` match within one path segment, `**` any number of segments
+    pub path: String,
+    #[serde(default)]
+    pub users: Vec<String>,
+    #[serde(default)]
+    pub groups: Vec<String>,
+    pub ops: Vec<Op>,
+}
+
+impl Rule {
+    fn applies_to(&self, who: &Principal) -> bool {
+        let user = self.users.iter().any(|u| u == "*" || (!who.user.is_empty() && *u == who.user));
+        let group = self.groups.iter().any(|g| g == "*" || who.groups.contains(g));
+        user || group
+    }
+}
+
+/// Set of rules; an operation is allowed if any rule grants it
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(transparent)]
+pub struct Policy {
+    rules: Vec<Rule>,
+}
+
+impl Policy {
+    /// Create a policy from rules
+    pub fn new(rules: Vec<Rule>) -> Self {
+        Self { rules }
+    }
+
+    /// Load the rules under [`POLICY_CONFIG_KEY`], or `None` if the key is absent
+    pub fn from_config(config: &Config) -> Result<Option<Self>> {
+        let parsed = match config.inner.get(POLICY_CONFIG_KEY) {
+            None => return Ok(None),
+            Some(serde_json::Value::String(json)) => serde_json::from_str(json),
+            Some(value) => serde_json::from_value(value.clone()),
+        };
+        parsed
+            .map(Some)
+            .map_err(|e| Error::InvalidInput(format!("invalid {}: {}", POLICY_CONFIG_KEY, e)))
+    }
+
+    /// Whether `who` may perform `op` on `path`
+    pub fn allows(&self, who: &Principal, op: Op, path: &str) -> bool {
+        self.rules
+            .iter()
+            .any(|r| r.ops.contains(&op) && r.applies_to(who) && glob_match(&r.path, path))
+    }
+
+    /// `PermissionDenied` unless `who` may perform `op` on `path`
+    pub fn check(&self, who: &Principal, op: Op, path: &str) -> Result<()> {
+        if self.allows(who, op, path) {
+            Ok(())
+        } else {
+            Err(Error::PermissionDenied)
+        }
+    }
+}
+
+/// Match `path` against a glob of `/`-separated segments
+pub(crate) fn glob_match(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<&str> = pattern.split('/').filter(|s| !s.is_empty()).collect();
+    let path: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    match_segments(&pattern, &path)
+}
+
+fn match_segments(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|i| match_segments(rest, &path[i..])),
+        Some((seg, rest)) => match path.split_first() {
+            Some((name, path)) => match_segment(seg.as_bytes(), name.as_bytes()) && match_segments(rest, path),
+            None => false,
+        },
+    }
+}
+
+/// Match a single name against a glob where `*` and `?` never cross `/`
+pub(crate) fn name_match(pattern: &str, name: &str) -> bool {
+    match_segment(pattern.as_bytes(), name.as_bytes())
+}
+
+fn match_segment(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((b'*', rest)) => (0..=name.len()).any(|i| match_segment(rest, &name[i..])),
+        Some((b'?', rest)) => !name.is_empty() && match_segment(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && match_segment(rest, &name[1..]),
+    }
+}
+
+/// Filesystem wrapper enforcing a [`Policy`] loaded from config
+///
+/// The caller's identity comes from the function given to
+/// [`PolicyFs::with_identity`]; by default every call is anonymous. Export
+/// the wrapper in place of the filesystem:
+///
+/// ```ignore
+/// type Exported = PolicyFs<MyFS>;
+/// export_plugin!(Exported);
+/// ```
+pub struct PolicyFs<F> {
+    inner: F,
+    policy: Option<Policy>,
+    identity: fn() -> Principal,
+}
+
+impl<F: Default> Default for PolicyFs<F> {
+    fn default() -> Self {
+        Self::new(F::default())
+    }
+}
+
+impl<F> PolicyFs<F> {
+    /// Wrap `inner`; no policy applies until one is configured
+    pub fn new(inner: F) -> Self {
+        Self {
+            inner,
+            policy: None,
+            identity: Principal::anonymous,
+        }
+    }
+
+    /// Use `identity` to find out who is making each call
+    pub fn with_identity(mut self, identity: fn() -> Principal) -> Self {
+        self.identity = identity;
+        self
+    }
+
+    /// Use `policy` instead of the one in the config
+    pub fn with_policy(mut self, policy: Policy) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    /// The wrapped filesystem
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    fn check(&self, op: Op, path: &str) -> Result<()> {
+        match &self.policy {
+            Some(policy) => policy.check(&(self.identity)(), op, path),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<F: FileSystem> PolicyFs<F> {
+    /// The checks the single-operation method for `op` makes
+    fn check_batch_op(&self, op: &FsOp) -> Result<()> {
+        match op {
+            FsOp::Create { path } | FsOp::Mkdir { path, .. } => self.check(Op::Create, path),
+            FsOp::Write { path, .. } => {
+                self.check(Op::Write, path)?;
+                if self.inner.stat(path).is_err() {
+                    self.check(Op::Create, path)?;
+                }
+                Ok(())
+            }
+            FsOp::Remove { path } | FsOp::RemoveAll { path } => self.check(Op::Delete, path),
+            FsOp::Rename { old_path, new_path } => {
+                self.check(Op::Delete, old_path)?;
+                self.check(Op::Create, new_path)
+            }
+            FsOp::Chmod { path, .. } => self.check(Op::Write, path),
+        }
+    }
+}
+
+impl<F: FileSystem> FileSystem for PolicyFs<F> {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn readme(&self) -> &str {
+        self.inner.readme()
+    }
+
+    fn config_params(&self) -> Vec<ConfigParameter> {
+        let mut params = self.inner.config_params();
+        params.push(ConfigParameter::new(
+            POLICY_CONFIG_KEY,
+            "string",
+            false,
+            "",
+            "JSON list of access rules: {path, users, groups, ops}",
+        ));
+        params
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    fn schema(&self) -> FsSchema {
+        self.inner.schema()
+    }
+
+    fn validate(&self, config: &Config) -> Result<()> {
+        Policy::from_config(config)?;
+        self.inner.validate(config)
+    }
+
+    fn initialize(&mut self, config: &Config) -> Result<()> {
+        if let Some(policy) = Policy::from_config(config)? {
+            self.policy = Some(policy);
+        }
+        self.inner.initialize(config)
+    }
+
+    fn shutdown(&mut self) -> Result<()> {
+        self.inner.shutdown()
+    }
+
+    fn stats(&self) -> serde_json::Value {
+        self.inner.stats()
+    }
+
+    fn health(&self) -> Result<()> {
+        self.inner.health()
+    }
+
+    fn ctl(&mut self, command: &str) -> Result<()> {
+        self.check(Op::Write, crate::control::CTL_PATH)?;
+        self.inner.ctl(command)
+    }
+
+    /// Started by the host rather than a user, so never denied
+    fn maintain(&mut self) -> Result<()> {
+        self.inner.maintain()
+    }
+
+    fn warmup(&mut self, budget: Duration) -> Result<WarmupProgress> {
+        self.inner.warmup(budget)
+    }
+
+    /// Upgrades are run by the host, so state transfer is never denied
+    fn export_state(&self) -> Result<Vec<u8>> {
+        self.inner.export_state()
+    }
+
+    fn import_state(&mut self, state: &[u8]) -> Result<()> {
+        self.inner.import_state(state)
+    }
+
+    fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+        self.check(Op::Read, path)?;
+        self.inner.read(path, offset, size)
+    }
+
+    fn poll_read(&self, path: &str, offset: i64, size: i64, timeout: Duration) -> Result<Vec<u8>> {
+        self.check(Op::Read, path)?;
+        self.inner.poll_read(path, offset, size, timeout)
+    }
+
+    fn stat(&self, path: &str) -> Result<FileInfo> {
+        self.check(Op::Read, path)?;
+        self.inner.stat(path)
+    }
+
+    fn readdir(&self, path: &str) -> Result<Vec<FileInfo>> {
+        self.check(Op::List, path)?;
+        self.inner.readdir(path)
+    }
+
+    fn readdir_page(&self, path: &str, offset: usize, limit: usize) -> Result<Vec<FileInfo>> {
+        self.check(Op::List, path)?;
+        self.inner.readdir_page(path, offset, limit)
+    }
+
+    fn readdir_filtered(&self, path: &str, glob: &str, limit: usize) -> Result<Vec<FileInfo>> {
+        self.check(Op::List, path)?;
+        self.inner.readdir_filtered(path, glob, limit)
+    }
+
+    fn walk(&self, path: &str, depth: usize) -> Result<Vec<(String, FileInfo)>> {
+        self.check(Op::List, path)?;
+        let mut entries = self.inner.walk(path, depth)?;
+        // Hide the contents of directories the caller may not list
+        entries.retain(|(entry, _)| {
+            let parent = entry.rsplit_once('/').map_or("/", |(dir, _)| if dir.is_empty() { "/" } else { dir });
+            self.check(Op::List, parent).is_ok()
+        });
+        Ok(entries)
+    }
+
+    fn dir_generation(&self, path: &str) -> u64 {
+        // 0 makes readdir_if_changed list, and so check, every time
+        match self.check(Op::List, path) {
+            Ok(()) => self.inner.dir_generation(path),
+            Err(_) => 0,
+        }
+    }
+
+    fn write(&mut self, path: &str, data: &[u8], offset: i64, flags: WriteFlag) -> Result<i64> {
+        self.check(Op::Write, path)?;
+        if flags.contains(WriteFlag::CREATE) && self.inner.stat(path).is_err() {
+            self.check(Op::Create, path)?;
+        }
+        self.inner.write(path, data, offset, flags)
+    }
+
+    fn create(&mut self, path: &str) -> Result<()> {
+        self.check(Op::Create, path)?;
+        self.inner.create(path)
+    }
+
+    fn mkdir(&mut self, path: &str, perm: u32) -> Result<()> {
+        self.check(Op::Create, path)?;
+        self.inner.mkdir(path, perm)
+    }
+
+    fn remove(&mut self, path: &str) -> Result<()> {
+        self.check(Op::Delete, path)?;
+        self.inner.remove(path)
+    }
+
+    fn remove_all(&mut self, path: &str) -> Result<()> {
+        self.check(Op::Delete, path)?;
+        self.inner.remove_all(path)
+    }
+
+    fn rename(&mut self, old_path: &str, new_path: &str) -> Result<()> {
+        self.check(Op::Delete, old_path)?;
+        self.check(Op::Create, new_path)?;
+        self.inner.rename(old_path, new_path)
+    }
+
+    fn chmod(&mut self, path: &str, mode: u32) -> Result<()> {
+        self.check(Op::Write, path)?;
+        self.inner.chmod(path, mode)
+    }
+
+    fn symlink(&mut self, target: &str, link_path: &str) -> Result<()> {
+        self.check(Op::Create, link_path)?;
+        self.inner.symlink(target, link_path)
+    }
+
+    fn readlink(&self, path: &str) -> Result<String> {
+        self.check(Op::Read, path)?;
+        self.inner.readlink(path)
+    }
+
+    fn get_xattr(&self, path: &str, name: &str) -> Result<Vec<u8>> {
+        self.check(Op::Read, path)?;
+        self.inner.get_xattr(path, name)
+    }
+
+    fn set_xattr(&mut self, path: &str, name: &str, value: &[u8]) -> Result<()> {
+        self.check(Op::Write, path)?;
+        self.inner.set_xattr(path, name, value)
+    }
+
+    fn list_xattr(&self, path: &str) -> Result<Vec<String>> {
+        self.check(Op::Read, path)?;
+        self.inner.list_xattr(path)
+    }
+
+    fn read_if_changed(&self, path: &str, etag: &str) -> Result<Option<Vec<u8>>> {
+        self.check(Op::Read, path)?;
+        self.inner.read_if_changed(path, etag)
+    }
+
+    /// Denied paths fail individually; the rest go to the plugin as one batch
+    fn read_many(&self, paths: &[&str]) -> Vec<Result<Vec<u8>>> {
+        split_batch(
+            paths,
+            |path| self.check(Op::Read, path).err().map(Err),
+            |allowed| self.inner.read_many(allowed),
+        )
+    }
+
+    fn write_if(&mut self, path: &str, data: &[u8], expected_etag: &str) -> Result<i64> {
+        self.check(Op::Write, path)?;
+        self.inner.write_if(path, data, expected_etag)
+    }
+
+    /// Nothing is applied unless every operation is allowed
+    fn batch(&mut self, ops: Vec<FsOp>) -> Vec<Result<()>> {
+        for (i, op) in ops.iter().enumerate() {
+            if let Err(e) = self.check_batch_op(op) {
+                return reject_batch(ops.len(), i, e);
+            }
+        }
+        self.inner.batch(ops)
+    }
+
+    fn atomic_batch(&self) -> bool {
+        self.inner.atomic_batch()
+    }
+
+    /// Writes are checked as they are staged, so sessions pass straight through
+    fn begin_write_session(&mut self) -> Result<()> {
+        self.inner.begin_write_session()
+    }
+
+    fn commit_session(&mut self, message: &str) -> Result<()> {
+        self.inner.commit_session(message)
+    }
+
+    fn abort_session(&mut self) -> Result<()> {
+        self.inner.abort_session()
+    }
+
+    fn advise(&self, path: &str, offset: i64, len: i64, advice: Advice) -> Result<()> {
+        self.check(Op::Read, path)?;
+        self.inner.advise(path, offset, len, advice)
+    }
+}
+
+/// Access is checked when a handle is opened; later calls on it pass through
+impl<F: HandleFS> HandleFS for PolicyFs<F> {
+    fn open_handle(&mut self, path: &str, flags: OpenFlag, mode: u32) -> Result<i64> {
+        if flags.is_readable() {
+            self.check(Op::Read, path)?;
+        }
+        if flags.is_writable() || flags.contains(OpenFlag::O_TRUNC) {
+            self.check(Op::Write, path)?;
+        }
+        if flags.contains(OpenFlag::O_CREATE) && self.inner.stat(path).is_err() {
+            self.check(Op::Create, path)?;
+        }
+        self.inner.open_handle(path, flags, mode)
+    }
+
+    fn handle_read(&mut self, id: i64, buf: &mut [u8]) -> Result<usize> {
+        self.inner.handle_read(id, buf)
+    }
+
+    fn handle_read_at(&self, id: i64, buf: &mut [u8], offset: i64) -> Result<usize> {
+        self.inner.handle_read_at(id, buf, offset)
+    }
+
+    fn handle_write(&mut self, id: i64, data: &[u8]) -> Result<usize> {
+        self.inner.handle_write(id, data)
+    }
+
+    fn handle_write_at(&self, id: i64, data: &[u8], offset: i64) -> Result<usize> {
+        self.inner.handle_write_at(id, data, offset)
+    }
+
+    fn handle_seek(&mut self, id: i64, offset: i64, whence: i32) -> Result<i64> {
+        self.inner.handle_seek(id, offset, whence)
+    }
+
+    fn handle_sync(&self, id: i64) -> Result<()> {
+        self.inner.handle_sync(id)
+    }
+
+    fn handle_stat(&self, id: i64) -> Result<FileInfo> {
+        self.inner.handle_stat(id)
+    }
+
+    fn handle_info(&self, id: i64) -> Result<(String, OpenFlag)> {
+        self.inner.handle_info(id)
+    }
+
+    fn close_handle(&mut self, id: i64) -> Result<()> {
+        self.inner.close_handle(id)
+    }
+}
+
+impl<F: StreamFS> StreamFS for PolicyFs<F> {
+    fn open_stream(&mut self, path: &str) -> Result<i64> {
+        self.check(Op::Read, path)?;
+        self.inner.open_stream(path)
+    }
+
+    fn stream_read(&mut self, id: i64, buf: &mut [u8]) -> Result<Option<usize>> {
+        self.inner.stream_read(id, buf)
+    }
+
+    fn stream_write(&mut self, id: i64, data: &[u8]) -> Result<usize> {
+        self.inner.stream_write(id, data)
+    }
+
+    fn close_stream(&mut self, id: i64) -> Result<()> {
+        self.inner.close_stream(id)
+    }
+}
+
+impl<F: UploadFS> UploadFS for PolicyFs<F> {
+    fn begin_upload(&mut self, path: &str) -> Result<UploadSession> {
+        self.check(Op::Write, path)?;
+        self.inner.begin_upload(path)
+    }
+
+    fn upload_status(&self, id: &str) -> Result<UploadSession> {
+        self.inner.upload_status(id)
+    }
+
+    fn upload_append(&mut self, id: &str, offset: u64, data: &[u8]) -> Result<UploadSession> {
+        self.inner.upload_append(id, offset, data)
+    }
+
+    fn commit_upload(&mut self, id: &str) -> Result<()> {
+        // The rules may have changed since the session began
+        let session = self.inner.upload_status(id)?;
+        self.check(Op::Write, &session.path)?;
+        self.inner.commit_upload(id)
+    }
+
+    fn abort_upload(&mut self, id: &str) -> Result<()> {
+        self.inner.abort_upload(id)
+    }
+
+    fn list_uploads(&self) -> Result<Vec<UploadSession>> {
+        self.inner.list_uploads()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filesystem::ReadOnlyFileSystem;
+    use serde_json::json;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("/", "/"));
+        assert!(glob_match("/docs/**", "/docs"));
+        assert!(glob_match("/docs/**", "/docs/a/b.txt"));
+        assert!(glob_match("/docs/*.md", "/docs/readme.md"));
+        assert!(!glob_match("/docs/*.md", "/docs/sub/readme.md"));
+        assert!(glob_match("/logs/202?/**/*.log", "/logs/2024/01/02/app.log"));
+        assert!(!glob_match("/docs/**", "/docsx/a"));
+        assert!(!glob_match("/", "/docs"));
+    }
+
+    #[test]
+    fn test_policy_from_config() {
+        let rules = json!([
+            {"path": "/public/**", "users": ["*"], "ops": ["read", "list"]},
+            {"path": "/team/**", "groups": ["eng"], "ops": ["read", "write"]},
+        ]);
+        let policy = Policy::from_config(&Config::from(json!({ "policy": rules })))
+            .unwrap()
+            .unwrap();
+        let from_string = Policy::from_config(&Config::from(json!({ "policy": rules.to_string() })))
+            .unwrap()
+            .unwrap();
+        assert_eq!(policy, from_string);
+
+        let alice = Principal::user("alice", &["eng"]);
+        let anon = Principal::anonymous();
+        assert!(policy.allows(&anon, Op::Read, "/public/a"));
+        assert!(!policy.allows(&anon, Op::Write, "/public/a"));
+        assert!(policy.allows(&alice, Op::Write, "/team/x"));
+        assert!(!policy.allows(&anon, Op::Read, "/team/x"));
+        assert!(!policy.allows(&alice, Op::Delete, "/team/x"));
+
+        assert_eq!(Policy::from_config(&Config::default()).unwrap(), None);
+        let bad = Config::from(json!({ "policy": [{"path": "/", "ops": ["fly"]}] }));
+        assert!(matches!(Policy::from_config(&bad), Err(Error::InvalidInput(_))));
+    }
+
+    struct Files;
+
+    impl ReadOnlyFileSystem for Files {
+        fn name(&self) -> &str {
+            "files"
+        }
+
+        fn read(&self, _path: &str, _offset: i64, _size: i64) -> Result<Vec<u8>> {
+            Ok(b"data".to_vec())
+        }
+
+        fn stat(&self, _path: &str) -> Result<FileInfo> {
+            Ok(FileInfo::file("f", 4, 0o644))
+        }
+
+        fn readdir(&self, _path: &str) -> Result<Vec<FileInfo>> {
+            Ok(vec![])
+        }
+    }
+
+    fn bob() -> Principal {
+        Principal::user("bob", &[])
+    }
+
+    #[test]
+    fn test_policy_fs_enforces_rules() {
+        let mut fs = PolicyFs::new(Files).with_identity(bob);
+        assert!(fs.read("/secret", 0, -1).is_ok(), "no policy passes through");
+
+        let config = Config::from(json!({
+            "policy": [{"path": "/home/bob/**", "users": ["bob"], "ops": ["read", "list"]}]
+        }));
+        fs.initialize(&config).unwrap();
+        assert_eq!(fs.read("/home/bob/notes", 0, -1).unwrap(), b"data");
+        assert!(fs.readdir("/home/bob").is_ok());
+        assert_eq!(fs.read("/secret", 0, -1), Err(Error::PermissionDenied));
+        assert_eq!(fs.poll_read("/secret", 0, -1, Duration::from_secs(5)), Err(Error::PermissionDenied));
+        assert_eq!(fs.poll_read("/home/bob/notes", 0, -1, Duration::ZERO).unwrap(), b"data");
+        assert_eq!(fs.remove("/home/bob/notes"), Err(Error::PermissionDenied));
+        // bob may not create either, and the first denial rejects the batch
+        let ops = vec![
+            FsOp::Create { path: "/home/bob/new".to_string() },
+            FsOp::Remove { path: "/secret".to_string() },
+        ];
+        assert_eq!(fs.batch(ops), [Err(Error::PermissionDenied), Err(Error::Cancelled)]);
+        let results = fs.read_many(&["/secret", "/home/bob/notes"]);
+        assert_eq!(results[0], Err(Error::PermissionDenied));
+        assert_eq!(results[1].as_deref().unwrap(), b"data");
+
+        let anon = PolicyFs::new(Files).with_policy(Policy::from_config(&config).unwrap().unwrap());
+        assert!(matches!(anon.stat("/home/bob/notes"), Err(Error::PermissionDenied)));
+    }
+}
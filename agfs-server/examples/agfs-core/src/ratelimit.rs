@@ -0,0 +1,247 @@
+//! Throttling calls to an upstream by its rate-limit headers
+//!
+//! APIs announce their limits in response headers: GitHub, Twitter and
+//! others send `X-RateLimit-Remaining`/`X-RateLimit-Reset`, newer ones the
+//! `RateLimit-*` fields, and throttled responses usually carry
+//! `Retry-After`. A [`RateLimitGuard`] shared by all operations of a plugin
+//! instance reads them from every response and fails later calls with
+//! `Error::Busy` until the limit resets, instead of letting them hit the
+//! API and get the plugin banned for longer:
+//!
+//! ```ignore
+//! fn fetch(&self, url: &str) -> Result<HttpResponse> {
+//!     self.limits.check()?;
+//!     let response = Http::get(url)?;
+//!     if let Some(busy) = self.limits.observe(response.status_code, &response.headers) {
+//!         return Err(busy);
+//!     }
+//!     Ok(response)
+//! }
+//! ```
+
+use crate::error::{Error, Result};
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+/// Wait after a throttled response that says nothing about how long; GitHub
+/// asks for at least a minute after a secondary rate limit
+pub const DEFAULT_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Reset values below this are seconds from now rather than a Unix time
+const DELTA_RESET_LIMIT: u64 = 1_000_000_000;
+
+#[derive(Default)]
+struct Inner {
+    blocked_until: Option<Duration>,
+    limit: Option<u64>,
+    remaining: Option<u64>,
+    throttled: u64,
+}
+
+/// Rate-limit state of one upstream, learned from its responses
+pub struct RateLimitGuard {
+    clock: fn() -> Duration,
+    reserve: u64,
+    inner: Mutex<Inner>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for RateLimitGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RateLimitGuard {
+    /// Create a guard using the system clock
+    ///
+    /// WASM plugins have no clock of their own; use `agfs_wasm_ffi::ratelimit::new`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn new() -> Self {
+        Self::with_clock(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+        })
+    }
+
+    /// Create a guard reading the time from `clock`, which must count from
+    /// the Unix epoch: `X-RateLimit-Reset` is a Unix time
+    pub fn with_clock(clock: fn() -> Duration) -> Self {
+        Self {
+            clock,
+            reserve: 0,
+            inner: Mutex::new(Inner::default()),
+        }
+    }
+
+    /// Stop once only `reserve` requests are left rather than none, keeping
+    /// them for operations that must not fail
+    pub fn reserve(mut self, reserve: u64) -> Self {
+        self.reserve = reserve;
+        self
+    }
+
+    fn inner(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Time left before calls may go out again, if they are held back
+    pub fn wait_time(&self) -> Option<Duration> {
+        let now = (self.clock)();
+        self.inner()
+            .blocked_until
+            .filter(|until| *until > now)
+            .map(|until| until - now)
+    }
+
+    /// `Busy` with the time left while the limit is exhausted
+    pub fn check(&self) -> Result<()> {
+        match self.wait_time() {
+            Some(wait) => Err(busy(wait)),
+            None => Ok(()),
+        }
+    }
+
+    /// Learn from a response's status and headers (names in any case)
+    ///
+    /// Returns `Busy` if the response itself was throttled: a 429, or a 403
+    /// that says so through `Retry-After` or an exhausted limit (GitHub's
+    /// secondary rate limit).
+    pub fn observe(&self, status: i32, headers: &HashMap<String, String>) -> Option<Error> {
+        let now = (self.clock)();
+        let header = |name: &str| {
+            headers
+                .iter()
+                .find(|(key, _)| key.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.trim())
+        };
+        let number = |names: &[&str]| names.iter().find_map(|name| header(name)?.parse::<u64>().ok());
+
+        let retry_after = number(&["Retry-After"]).map(Duration::from_secs);
+        let limit = number(&["X-RateLimit-Limit", "RateLimit-Limit"]);
+        let remaining = number(&["X-RateLimit-Remaining", "RateLimit-Remaining"]);
+        let reset = number(&["X-RateLimit-Reset", "RateLimit-Reset"]).map(|reset| {
+            if reset < DELTA_RESET_LIMIT {
+                now + Duration::from_secs(reset)
+            } else {
+                Duration::from_secs(reset)
+            }
+        });
+
+        let exhausted = remaining.is_some_and(|left| left <= self.reserve);
+        let throttled = status == 429 || (status == 403 && (retry_after.is_some() || remaining == Some(0)));
+
+        let mut inner = self.inner();
+        if limit.is_some() {
+            inner.limit = limit;
+        }
+        if remaining.is_some() {
+            inner.remaining = remaining;
+        }
+        let until = match (retry_after, exhausted || throttled) {
+            (Some(wait), _) => Some(now + wait),
+            (None, true) => Some(reset.filter(|at| *at > now).unwrap_or(now + DEFAULT_BACKOFF)),
+            (None, false) => None,
+        };
+        if let Some(until) = until {
+            inner.blocked_until = Some(inner.blocked_until.map_or(until, |current| current.max(until)));
+        }
+        if throttled {
+            inner.throttled += 1;
+            return Some(busy(until.map_or(Duration::ZERO, |until| until.saturating_sub(now))));
+        }
+        None
+    }
+
+    /// Last known limit and remaining requests, throttled responses seen and
+    /// seconds until calls may resume, for the plugin's `stats`
+    pub fn stats(&self) -> serde_json::Value {
+        let wait = self.wait_time();
+        let inner = self.inner();
+        serde_json::json!({
+            "limit": inner.limit,
+            "remaining": inner.remaining,
+            "throttled": inner.throttled,
+            "blocked_for_secs": wait.map(|wait| wait.as_secs()),
+        })
+    }
+}
+
+fn busy(wait: Duration) -> Error {
+    Error::Busy {
+        retry_after_ms: Some(wait.as_millis() as u64),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static NOW_MS: AtomicU64 = AtomicU64::new(1_700_000_000_000);
+
+    fn fake_clock() -> Duration {
+        Duration::from_millis(NOW_MS.load(Ordering::SeqCst))
+    }
+
+    fn headers(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_holds_back_until_reset() {
+        let guard = RateLimitGuard::with_clock(fake_clock);
+        let now = fake_clock().as_secs();
+
+        let ok = headers(&[("X-Ratelimit-Limit", "5000"), ("X-Ratelimit-Remaining", "12")]);
+        assert_eq!(guard.observe(200, &ok), None);
+        assert!(guard.check().is_ok());
+
+        let reset = (now + 30).to_string();
+        let last = headers(&[("X-RateLimit-Remaining", "0"), ("X-RateLimit-Reset", &reset)]);
+        assert_eq!(guard.observe(200, &last), None);
+        assert_eq!(
+            guard.check(),
+            Err(Error::Busy {
+                retry_after_ms: Some(30_000)
+            })
+        );
+        assert_eq!(guard.stats()["remaining"], 0);
+
+        NOW_MS.fetch_add(30_000, Ordering::SeqCst);
+        assert!(guard.check().is_ok());
+
+        // Reserve, with a reset given in seconds from now
+        let guard = RateLimitGuard::with_clock(fake_clock).reserve(10);
+        guard.observe(200, &headers(&[("RateLimit-Remaining", "10"), ("RateLimit-Reset", "5")]));
+        assert_eq!(guard.wait_time(), Some(Duration::from_secs(5)));
+    }
+
+    fn fixed_clock() -> Duration {
+        Duration::from_secs(1_700_000_000)
+    }
+
+    #[test]
+    fn test_throttled_responses() {
+        let guard = RateLimitGuard::with_clock(fixed_clock);
+        // GitHub's secondary rate limit
+        assert_eq!(
+            guard.observe(403, &headers(&[("retry-after", "90")])),
+            Some(Error::Busy {
+                retry_after_ms: Some(90_000)
+            })
+        );
+        assert_eq!(guard.wait_time(), Some(Duration::from_secs(90)));
+
+        // A plain 403 is a permission problem, not a limit
+        let guard = RateLimitGuard::with_clock(fixed_clock);
+        assert_eq!(guard.observe(403, &HashMap::new()), None);
+        assert!(guard.check().is_ok());
+
+        assert!(guard.observe(429, &HashMap::new()).is_some());
+        assert_eq!(guard.wait_time(), Some(DEFAULT_BACKOFF));
+        assert_eq!(guard.stats()["throttled"], 1);
+    }
+}
@@ -0,0 +1,241 @@
+//! Retrying transient failures with backoff
+//!
+//! A [`RetryPolicy`] says how often to retry, how long to wait in between and
+//! which errors are worth retrying. The SDKs apply it to host calls (`Http`,
+//! `HostFS`) and plugins can run their own operations with it:
+//!
+//! ```ignore
+//! let policy = RetryPolicy::new().max_attempts(5).backoff(Duration::from_millis(200), Duration::from_secs(10));
+//! let issues = policy.run(|| self.client.list_issues(&repo))?;
+//! ```
+//!
+//! Delays grow exponentially from the base delay up to the maximum, and are
+//! jittered so that instances hitting the same upstream spread out. An
+//! `Error::Busy` hint from the backend is waited out in full when it is
+//! longer than the backoff.
+
+use crate::error::{Error, Result};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// Attempts made by [`RetryPolicy::new`], including the first
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+/// Whether `err` is worth retrying: a busy backend or an I/O failure
+///
+/// Everything else, including `TimedOut` (the operation's own deadline has
+/// passed) and `NotFound`, fails the same way on every attempt.
+pub fn is_transient(err: &Error) -> bool {
+    matches!(err, Error::Busy { .. } | Error::Io(_))
+}
+
+/// How to retry an operation that failed
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    jitter: bool,
+    retryable: fn(&Error) -> bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(5),
+            jitter: true,
+            retryable: is_transient,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Three attempts, backing off from 100ms up to 5s, retrying
+    /// [`is_transient`] errors
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A single attempt; failures are returned as they are
+    pub fn never() -> Self {
+        Self::default().max_attempts(1)
+    }
+
+    /// Attempts in total, including the first (at least one)
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    /// Wait `base` before the first retry, doubling up to `max`
+    pub fn backoff(mut self, base: Duration, max: Duration) -> Self {
+        self.base_delay = base;
+        self.max_delay = max.max(base);
+        self
+    }
+
+    /// Randomize each delay between half and all of it (on by default)
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Retry the errors `retryable` accepts instead of [`is_transient`] ones
+    pub fn retry_if(mut self, retryable: fn(&Error) -> bool) -> Self {
+        self.retryable = retryable;
+        self
+    }
+
+    pub fn attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Whether attempt number `attempt` (1-based) failing with `err` should
+    /// be followed by another
+    pub fn should_retry(&self, attempt: u32, err: &Error) -> bool {
+        attempt < self.max_attempts && (self.retryable)(err)
+    }
+
+    /// How long to wait after attempt number `attempt` (1-based) failed
+    /// with `err`
+    pub fn delay(&self, attempt: u32, err: &Error) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(31);
+        let backoff = self.base_delay.saturating_mul(1 << exponent).min(self.max_delay);
+        let backoff = if self.jitter {
+            let half = backoff / 2;
+            half + half.mul_f64(random_fraction())
+        } else {
+            backoff
+        };
+        match err {
+            Error::Busy {
+                retry_after_ms: Some(ms),
+            } => backoff.max(Duration::from_millis(*ms)),
+            _ => backoff,
+        }
+    }
+
+    /// Run `op` until it succeeds, fails with an error not worth retrying,
+    /// or runs out of attempts, waiting with `sleep` in between
+    ///
+    /// `sleep` may fail, e.g. when the wait would pass the operation's
+    /// deadline; the last error of `op` is returned then.
+    pub fn run_with<T>(
+        &self,
+        mut sleep: impl FnMut(Duration) -> Result<()>,
+        mut op: impl FnMut() -> Result<T>,
+    ) -> Result<T> {
+        let mut attempt = 1;
+        loop {
+            match op() {
+                Err(err) if self.should_retry(attempt, &err) => {
+                    if sleep(self.delay(attempt, &err)).is_err() {
+                        return Err(err);
+                    }
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// [`run_with`](Self::run_with) sleeping the current thread
+    ///
+    /// WASM plugins cannot block a thread; they use `agfs_wasm_ffi::retry::run`,
+    /// which waits on the host.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn run<T>(&self, op: impl FnMut() -> Result<T>) -> Result<T> {
+        self.run_with(
+            |delay| {
+                std::thread::sleep(delay);
+                Ok(())
+            },
+            op,
+        )
+    }
+}
+
+/// A number in `[0, 1)`, differing between calls
+///
+/// Every `RandomState` is keyed differently, which is all jitter needs.
+fn random_fraction() -> f64 {
+    let hasher = RandomState::new().build_hasher();
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_retries_transient_errors() {
+        let policy = RetryPolicy::new().jitter(false);
+        let calls = Cell::new(0);
+        let mut waited = Vec::new();
+        let result = policy.run_with(
+            |delay| {
+                waited.push(delay);
+                Ok(())
+            },
+            || {
+                calls.set(calls.get() + 1);
+                if calls.get() < 3 {
+                    Err(Error::Io("reset".to_string()))
+                } else {
+                    Ok(calls.get())
+                }
+            },
+        );
+        assert_eq!(result, Ok(3));
+        assert_eq!(waited, [Duration::from_millis(100), Duration::from_millis(200)]);
+
+        calls.set(0);
+        let result: Result<()> = policy.run_with(|_| Ok(()), || {
+            calls.set(calls.get() + 1);
+            Err(Error::NotFound)
+        });
+        assert_eq!(result, Err(Error::NotFound));
+        assert_eq!(calls.get(), 1);
+    }
+
+    #[test]
+    fn test_gives_up() {
+        let calls = Cell::new(0);
+        let op = || -> Result<()> {
+            calls.set(calls.get() + 1);
+            Err(Error::Io("down".to_string()))
+        };
+        assert!(RetryPolicy::new().max_attempts(4).run_with(|_| Ok(()), op).is_err());
+        assert_eq!(calls.get(), 4);
+
+        // A failed wait (e.g. past the deadline) returns the last error
+        calls.set(0);
+        assert_eq!(
+            RetryPolicy::new().run_with(|_| Err(Error::TimedOut), op),
+            Err(Error::Io("down".to_string()))
+        );
+        assert_eq!(calls.get(), 1);
+        assert_eq!(RetryPolicy::never().attempts(), 1);
+    }
+
+    #[test]
+    fn test_delay() {
+        let policy = RetryPolicy::new().backoff(Duration::from_millis(100), Duration::from_millis(300));
+        let io = Error::Io("x".to_string());
+        for attempt in 1..6 {
+            let delay = policy.delay(attempt, &io);
+            let full = Duration::from_millis(100 << (attempt - 1)).min(Duration::from_millis(300));
+            assert!(delay >= full / 2 && delay <= full, "{:?} for attempt {}", delay, attempt);
+        }
+
+        let busy = Error::Busy {
+            retry_after_ms: Some(2000),
+        };
+        assert_eq!(policy.delay(1, &busy), Duration::from_secs(2));
+        assert!(RetryPolicy::new().retry_if(|err| *err == Error::NotFound).should_retry(1, &Error::NotFound));
+    }
+}
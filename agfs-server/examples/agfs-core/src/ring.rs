@@ -0,0 +1,194 @@
+//! Single-producer single-consumer byte ring shared with the host
+
+use std::alloc::{alloc_zeroed, dealloc, handle_alloc_error, Layout};
+use std::ptr::{self, NonNull};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Size of the header in front of the ring data
+pub const RING_HEADER_SIZE: usize = 16;
+
+/// Flag set once the producer will write no more data
+pub const RING_CLOSED: u32 = 1;
+
+/// Largest supported capacity; counters wrap at 2^32
+const MAX_CAPACITY: usize = 1 << 31;
+
+#[repr(C)]
+struct Header {
+    capacity: u32,
+    head: AtomicU32,
+    tail: AtomicU32,
+    flags: AtomicU32,
+}
+
+/// Fixed-size byte ring in a single allocation the host can access directly
+///
+/// ```text
+/// offset  0  capacity  u32  power of two
+/// offset  4  head      u32  bytes consumed so far, written by the consumer
+/// offset  8  tail      u32  bytes produced so far, written by the producer
+/// offset 12  flags     u32  RING_CLOSED
+/// offset 16  data      [u8; capacity]
+/// ```
+///
+/// `head` and `tail` are free-running counters that wrap at 2^32; byte `n`
+/// lives at `data[n % capacity]`. Each side publishes its counter only after
+/// the bytes it covers, so one producer and one consumer need no lock. Either
+/// side may be the host, which updates the counters in place (little-endian).
+pub struct RingBuffer {
+    ptr: NonNull<u8>,
+    capacity: u32,
+}
+
+// The ring owns its allocation; sharing it between threads would allow two
+// producers, so it is Send but not Sync
+unsafe impl Send for RingBuffer {}
+
+impl RingBuffer {
+    /// Allocate a ring holding `capacity` bytes, rounded up to a power of two
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.clamp(1, MAX_CAPACITY).next_power_of_two();
+        let layout = Self::layout(capacity);
+        let ptr = match NonNull::new(unsafe { alloc_zeroed(layout) }) {
+            Some(ptr) => ptr,
+            None => handle_alloc_error(layout),
+        };
+        let ring = Self {
+            ptr,
+            capacity: capacity as u32,
+        };
+        unsafe { (*(ring.ptr.as_ptr() as *mut Header)).capacity = ring.capacity };
+        ring
+    }
+
+    fn layout(capacity: usize) -> Layout {
+        Layout::from_size_align(RING_HEADER_SIZE + capacity, 4).unwrap()
+    }
+
+    fn header(&self) -> &Header {
+        unsafe { &*(self.ptr.as_ptr() as *const Header) }
+    }
+
+    fn data(&self) -> *mut u8 {
+        unsafe { self.ptr.as_ptr().add(RING_HEADER_SIZE) }
+    }
+
+    /// Address of the header, to hand to the host
+    pub fn as_ptr(&self) -> *mut u8 {
+        self.ptr.as_ptr()
+    }
+
+    /// Capacity in bytes
+    pub fn capacity(&self) -> usize {
+        self.capacity as usize
+    }
+
+    /// Bytes written but not yet consumed
+    pub fn len(&self) -> usize {
+        let header = self.header();
+        let tail = header.tail.load(Ordering::Acquire);
+        tail.wrapping_sub(header.head.load(Ordering::Acquire)) as usize
+    }
+
+    /// Returns true if there is nothing to consume
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Bytes that can be written before the ring is full
+    pub fn free(&self) -> usize {
+        self.capacity() - self.len()
+    }
+
+    /// Producer side: append as much of `data` as fits, returning the count
+    pub fn push(&self, data: &[u8]) -> usize {
+        let header = self.header();
+        let head = header.head.load(Ordering::Acquire);
+        let tail = header.tail.load(Ordering::Relaxed);
+        let n = data.len().min(self.capacity() - tail.wrapping_sub(head) as usize);
+
+        let start = tail as usize % self.capacity();
+        let first = n.min(self.capacity() - start);
+        unsafe {
+            ptr::copy_nonoverlapping(data.as_ptr(), self.data().add(start), first);
+            ptr::copy_nonoverlapping(data.as_ptr().add(first), self.data(), n - first);
+        }
+        header.tail.store(tail.wrapping_add(n as u32), Ordering::Release);
+        n
+    }
+
+    /// Consumer side: move up to `buf.len()` bytes out, returning the count
+    pub fn pop(&self, buf: &mut [u8]) -> usize {
+        let header = self.header();
+        let tail = header.tail.load(Ordering::Acquire);
+        let head = header.head.load(Ordering::Relaxed);
+        let n = buf.len().min(tail.wrapping_sub(head) as usize);
+
+        let start = head as usize % self.capacity();
+        let first = n.min(self.capacity() - start);
+        unsafe {
+            ptr::copy_nonoverlapping(self.data().add(start), buf.as_mut_ptr(), first);
+            ptr::copy_nonoverlapping(self.data(), buf.as_mut_ptr().add(first), n - first);
+        }
+        header.head.store(head.wrapping_add(n as u32), Ordering::Release);
+        n
+    }
+
+    /// Producer side: mark the end of the data
+    pub fn close(&self) {
+        self.header().flags.fetch_or(RING_CLOSED, Ordering::Release);
+    }
+
+    /// Returns true once the producer has closed the ring
+    pub fn is_closed(&self) -> bool {
+        self.header().flags.load(Ordering::Acquire) & RING_CLOSED != 0
+    }
+}
+
+impl Drop for RingBuffer {
+    fn drop(&mut self) {
+        unsafe { dealloc(self.ptr.as_ptr(), Self::layout(self.capacity())) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_pop_wraps_around() {
+        let ring = RingBuffer::new(6);
+        assert_eq!(ring.capacity(), 8);
+
+        let mut buf = [0u8; 8];
+        for round in 0..5u8 {
+            let data = [round; 5];
+            assert_eq!(ring.push(&data), 5);
+            assert_eq!(ring.push(&data), 3);
+            assert_eq!(ring.free(), 0);
+            assert_eq!(ring.pop(&mut buf), 8);
+            assert_eq!(buf, [round; 8]);
+            assert!(ring.is_empty());
+        }
+        assert_eq!(ring.pop(&mut buf), 0);
+    }
+
+    #[test]
+    fn test_host_layout() {
+        let ring = RingBuffer::new(4);
+        ring.push(b"abc");
+        ring.close();
+
+        // Read and consume one byte the way the host does
+        let base = ring.as_ptr();
+        let word = |offset: usize| unsafe { (base.add(offset) as *const u32).read() };
+        assert_eq!((word(0), word(4), word(8), word(12)), (4, 0, 3, RING_CLOSED));
+        assert_eq!(unsafe { *base.add(RING_HEADER_SIZE) }, b'a');
+        unsafe { (base.add(4) as *mut u32).write(1) };
+
+        let mut buf = [0u8; 4];
+        assert_eq!(ring.pop(&mut buf), 2);
+        assert_eq!(&buf[..2], b"bc");
+        assert!(ring.is_closed());
+    }
+}
@@ -0,0 +1,298 @@
+//! Tabular file rendering
+//!
+//! Data-oriented plugins expose the same rows in several formats, e.g.
+//! `result.csv`, `result.json` and `result.md`. [`render_table`] produces all
+//! three from a slice of serializable rows, so every plugin quotes CSV and
+//! aligns Markdown the same way:
+//!
+//! ```
+//! use agfs_core::table::render_table;
+//! use serde::Serialize;
+//!
+//! #[derive(Serialize)]
+//! struct Pod { name: &'static str, restarts: u32 }
+//!
+//! let out = render_table(&[Pod { name: "web-1", restarts: 0 }]).unwrap();
+//! assert_eq!(out.csv, "name,restarts\nweb-1,0\n");
+//! assert_eq!(out.md, "| name | restarts |\n| --- | ---: |\n| web-1 | 0 |\n");
+//! ```
+
+use crate::error::{Error, Result};
+use serde::de::{Deserialize, Deserializer, MapAccess, Visitor};
+use serde::Serialize;
+use serde_json::Value;
+use std::borrow::Cow;
+use std::fmt;
+
+/// Rows with named columns
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Table {
+    columns: Vec<String>,
+    rows: Vec<Vec<Value>>,
+}
+
+/// A table rendered in each supported format
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderedTable {
+    pub csv: String,
+    pub json: String,
+    pub md: String,
+}
+
+impl Table {
+    /// Empty table with the given columns
+    pub fn new<I, S>(columns: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            columns: columns.into_iter().map(Into::into).collect(),
+            rows: Vec::new(),
+        }
+    }
+
+    /// Add a row, padded with nulls or cut to the number of columns
+    pub fn row<I, V>(mut self, values: I) -> Self
+    where
+        I: IntoIterator<Item = V>,
+        V: Into<Value>,
+    {
+        let mut row: Vec<Value> = values.into_iter().map(Into::into).collect();
+        row.resize(self.columns.len(), Value::Null);
+        self.rows.push(row);
+        self
+    }
+
+    /// Table from rows that serialize as objects (structs or maps)
+    ///
+    /// Columns follow field order, with fields first seen in later rows
+    /// appended. A row without some column leaves that cell empty.
+    pub fn from_rows<T: Serialize>(rows: &[T]) -> Result<Self> {
+        let json = serde_json::to_string(rows).map_err(|e| table_error(e.to_string()))?;
+        let rows: Vec<OrderedRow> = serde_json::from_str(&json).map_err(|e| table_error(e.to_string()))?;
+
+        let mut table = Self::default();
+        for OrderedRow(fields) in &rows {
+            for (name, _) in fields {
+                if !table.columns.contains(name) {
+                    table.columns.push(name.clone());
+                }
+            }
+        }
+        for OrderedRow(fields) in rows {
+            let mut row = vec![Value::Null; table.columns.len()];
+            for (name, value) in fields {
+                let i = table.columns.iter().position(|c| *c == name).expect("column was collected");
+                row[i] = value;
+            }
+            table.rows.push(row);
+        }
+        Ok(table)
+    }
+
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rows.is_empty()
+    }
+
+    /// RFC 4180 CSV with a header line, quoting fields only when needed
+    pub fn to_csv(&self) -> String {
+        let mut out = String::new();
+        let header = self.columns.iter().map(|c| Cow::Borrowed(c.as_str()));
+        push_csv_line(&mut out, header);
+        for row in &self.rows {
+            push_csv_line(&mut out, row.iter().map(cell_text));
+        }
+        out
+    }
+
+    /// JSON array with one object per row, keys in column order
+    pub fn to_json(&self) -> String {
+        if self.rows.is_empty() {
+            return "[]\n".to_string();
+        }
+        let keys: Vec<String> = self.columns.iter().map(|c| Value::from(c.as_str()).to_string()).collect();
+        let lines: Vec<String> = self
+            .rows
+            .iter()
+            .map(|row| {
+                let fields: Vec<String> = keys.iter().zip(row).map(|(k, v)| format!("{}: {}", k, v)).collect();
+                format!("  {{{}}}", fields.join(", "))
+            })
+            .collect();
+        format!("[\n{}\n]\n", lines.join(",\n"))
+    }
+
+    /// GitHub-flavored Markdown table, right-aligning numeric columns
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::new();
+        let header = self.columns.iter().map(|c| escape_md(c));
+        push_md_line(&mut out, header);
+        let align = (0..self.columns.len()).map(|i| {
+            let mut cells = self.rows.iter().map(|row| &row[i]).filter(|v| !v.is_null()).peekable();
+            let numeric = cells.peek().is_some() && cells.all(Value::is_number);
+            Cow::Borrowed(if numeric { "---:" } else { "---" })
+        });
+        push_md_line(&mut out, align);
+        for row in &self.rows {
+            push_md_line(&mut out, row.iter().map(|v| escape_md(&cell_text(v)).into_owned().into()));
+        }
+        out
+    }
+
+    /// All formats at once
+    pub fn render(&self) -> RenderedTable {
+        RenderedTable {
+            csv: self.to_csv(),
+            json: self.to_json(),
+            md: self.to_markdown(),
+        }
+    }
+}
+
+/// Render `rows` as CSV, JSON and Markdown, see [`Table::from_rows`]
+pub fn render_table<T: Serialize>(rows: &[T]) -> Result<RenderedTable> {
+    Ok(Table::from_rows(rows)?.render())
+}
+
+fn table_error(msg: String) -> Error {
+    Error::InvalidInput(format!("table: {}", msg))
+}
+
+/// Cell as plain text: strings as they are, null as empty, the rest as JSON
+fn cell_text(value: &Value) -> Cow<'_, str> {
+    match value {
+        Value::Null => Cow::Borrowed(""),
+        Value::String(s) => Cow::Borrowed(s),
+        other => Cow::Owned(other.to_string()),
+    }
+}
+
+fn push_csv_line<'a>(out: &mut String, fields: impl Iterator<Item = Cow<'a, str>>) {
+    for (i, field) in fields.enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        if field.contains([',', '"', '\n', '\r']) {
+            out.push('"');
+            out.push_str(&field.replace('"', "\"\""));
+            out.push('"');
+        } else {
+            out.push_str(&field);
+        }
+    }
+    out.push('\n');
+}
+
+fn push_md_line<'a>(out: &mut String, cells: impl Iterator<Item = Cow<'a, str>>) {
+    out.push('|');
+    for cell in cells {
+        out.push(' ');
+        out.push_str(&cell);
+        out.push_str(" |");
+    }
+    out.push('\n');
+}
+
+/// Keep a cell on one line and from closing the column early
+fn escape_md(text: &str) -> Cow<'_, str> {
+    if !text.contains(['|', '\n', '\r']) {
+        return Cow::Borrowed(text);
+    }
+    Cow::Owned(text.replace('|', "\\|").replace("\r\n", "<br>").replace(['\n', '\r'], "<br>"))
+}
+
+/// Object fields in the order they were serialized
+///
+/// `serde_json::Map` sorts its keys, which would lose the field order of the
+/// row structs, so rows are read back through this instead.
+struct OrderedRow(Vec<(String, Value)>);
+
+impl<'de> Deserialize<'de> for OrderedRow {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct RowVisitor;
+
+        impl<'de> Visitor<'de> for RowVisitor {
+            type Value = OrderedRow;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a row that serializes as an object")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<OrderedRow, A::Error> {
+                let mut fields = Vec::new();
+                while let Some(field) = map.next_entry()? {
+                    fields.push(field);
+                }
+                Ok(OrderedRow(fields))
+            }
+        }
+
+        deserializer.deserialize_map(RowVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Serialize)]
+    struct Metric {
+        name: String,
+        value: f64,
+        labels: Option<String>,
+    }
+
+    #[test]
+    fn test_render_table() {
+        let rows = vec![
+            Metric { name: "up".into(), value: 1.0, labels: Some("job=\"api\", env=prod".into()) },
+            Metric { name: "errors|total".into(), value: 0.5, labels: None },
+        ];
+        let out = render_table(&rows).unwrap();
+        assert_eq!(
+            out.csv,
+            "name,value,labels\nup,1.0,\"job=\"\"api\"\", env=prod\"\nerrors|total,0.5,\n"
+        );
+        assert_eq!(
+            out.json,
+            "[\n  {\"name\": \"up\", \"value\": 1.0, \"labels\": \"job=\\\"api\\\", env=prod\"},\n  \
+             {\"name\": \"errors|total\", \"value\": 0.5, \"labels\": null}\n]\n"
+        );
+        assert_eq!(
+            out.md,
+            "| name | value | labels |\n| --- | ---: | --- |\n\
+             | up | 1.0 | job=\"api\", env=prod |\n| errors\\|total | 0.5 |  |\n"
+        );
+    }
+
+    #[test]
+    fn test_from_rows_merges_columns() {
+        let rows = vec![json!({"b": 1, "a": "x"}), json!({"c": [1, 2], "a": "line\nbreak"})];
+        let table = Table::from_rows(&rows).unwrap();
+        // serde_json::Value objects come out sorted, unlike structs
+        assert_eq!(table.columns(), ["a", "b", "c"]);
+        assert_eq!(table.to_csv(), "a,b,c\nx,1,\n\"line\nbreak\",,\"[1,2]\"\n");
+        assert!(table.to_markdown().ends_with("| line<br>break |  | [1,2] |\n"));
+
+        assert!(Table::from_rows(&[1, 2]).is_err());
+        assert_eq!(render_table::<Metric>(&[]).unwrap().json, "[]\n");
+    }
+
+    #[test]
+    fn test_builder() {
+        let table = Table::new(["pod", "ready"]).row([json!("web-1"), json!(true)]).row([json!("web-2")]);
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.to_csv(), "pod,ready\nweb-1,true\nweb-2,\n");
+        assert_eq!(table.to_markdown(), "| pod | ready |\n| --- | --- |\n| web-1 | true |\n| web-2 |  |\n");
+    }
+}
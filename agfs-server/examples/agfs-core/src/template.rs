@@ -0,0 +1,273 @@
+//! Small text templates for generated file content
+//!
+//! Plugins render files such as story pages or summaries from data structs.
+//! Keeping the layout in a template instead of a `format!` block lets users
+//! override it through config. The syntax is a Jinja subset:
+//!
+//! - `{{ title }}`, `{{ story.by }}`: value of a field, empty if missing
+//! - `{% if url %}...{% else %}...{% endif %}`: taken unless the value is
+//!   missing, `null`, `false`, `0`, or an empty string, array or object
+//! - `{% for c in comments %}...{% endfor %}`: once per array element
+//!
+//! As with Jinja's `trim_blocks` and `lstrip_blocks`, a `{% %}` tag alone on
+//! its line does not leave a blank line behind.
+//!
+//! ```
+//! use agfs_core::template::Template;
+//! use agfs_core::serde_json::json;
+//!
+//! let t = Template::parse("# {{ title }}\n{% if url %}<{{ url }}>\n{% endif %}").unwrap();
+//! assert_eq!(t.render(&json!({"title": "Hi", "url": ""})).unwrap(), "# Hi\n");
+//! ```
+
+use crate::error::{Error, Result};
+use serde::Serialize;
+use serde_json::Value;
+
+/// Parsed template, reusable across renders
+#[derive(Debug, Clone)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Text(String),
+    Var(String),
+    If(String, Vec<Node>, Vec<Node>),
+    For(String, String, Vec<Node>),
+}
+
+/// `{{ }}` or `{% %}` tag with its trimmed contents
+enum Tag<'a> {
+    Var(&'a str),
+    Block(&'a str),
+}
+
+impl Template {
+    /// Parse `source`, reporting unbalanced or unknown tags
+    pub fn parse(source: &str) -> Result<Self> {
+        let tokens = tokenize(source)?;
+        let mut pos = 0;
+        let (nodes, end) = parse_nodes(&tokens, &mut pos)?;
+        match end {
+            None => Ok(Self { nodes }),
+            Some(tag) => Err(template_error(format!("unexpected {{% {} %}}", tag))),
+        }
+    }
+
+    /// Render with the fields of `data`
+    pub fn render<T: Serialize>(&self, data: &T) -> Result<String> {
+        let value = serde_json::to_value(data).map_err(|e| template_error(e.to_string()))?;
+        let mut scopes = vec![("", &value)];
+        let mut out = String::new();
+        render_nodes(&self.nodes, &mut scopes, &mut out);
+        Ok(out)
+    }
+}
+
+/// Parse `source` and render it with `data` in one go
+pub fn render<T: Serialize>(source: &str, data: &T) -> Result<String> {
+    Template::parse(source)?.render(data)
+}
+
+fn template_error(msg: String) -> Error {
+    Error::InvalidInput(format!("template: {}", msg))
+}
+
+enum Token<'a> {
+    Text(String),
+    Tag(Tag<'a>),
+}
+
+/// Split `source` into text and tags, trimming around lone block tags
+fn tokenize(source: &str) -> Result<Vec<Token<'_>>> {
+    let mut tokens = Vec::new();
+    let mut rest = source;
+    let mut trim_newline = false;
+
+    while !rest.is_empty() {
+        let next = [rest.find("{{"), rest.find("{%")].into_iter().flatten().min();
+        let Some(start) = next else {
+            push_text(&mut tokens, rest, trim_newline);
+            break;
+        };
+        let is_block = rest[start..].starts_with("{%");
+        let close = if is_block { "%}" } else { "}}" };
+        let end = rest[start + 2..]
+            .find(close)
+            .map(|i| start + 2 + i)
+            .ok_or_else(|| template_error(format!("unclosed {}", &rest[start..start + 2])))?;
+
+        let mut text = &rest[..start];
+        if is_block {
+            // Drop indentation before a tag that starts its line
+            let line_start = text.rfind('\n').map_or(0, |i| i + 1);
+            let at_line_start = line_start > 0 || tokens.is_empty();
+            if at_line_start && text[line_start..].chars().all(|c| c == ' ' || c == '\t') {
+                text = &text[..line_start];
+            }
+        }
+        push_text(&mut tokens, text, trim_newline);
+
+        let inner = rest[start + 2..end].trim();
+        tokens.push(Token::Tag(if is_block { Tag::Block(inner) } else { Tag::Var(inner) }));
+        trim_newline = is_block;
+        rest = &rest[end + 2..];
+    }
+    Ok(tokens)
+}
+
+fn push_text(tokens: &mut Vec<Token<'_>>, text: &str, trim_newline: bool) {
+    let text = match trim_newline {
+        true => text.strip_prefix("\r\n").or_else(|| text.strip_prefix('\n')).unwrap_or(text),
+        false => text,
+    };
+    if !text.is_empty() {
+        tokens.push(Token::Text(text.to_string()));
+    }
+}
+
+/// Parse nodes up to a closing tag, which is returned alongside
+fn parse_nodes<'a>(tokens: &[Token<'a>], pos: &mut usize) -> Result<(Vec<Node>, Option<&'a str>)> {
+    let mut nodes = Vec::new();
+    while let Some(token) = tokens.get(*pos) {
+        *pos += 1;
+        let tag = match token {
+            Token::Text(text) => {
+                nodes.push(Node::Text(text.clone()));
+                continue;
+            }
+            Token::Tag(Tag::Var(name)) => {
+                nodes.push(Node::Var(name.to_string()));
+                continue;
+            }
+            Token::Tag(Tag::Block(tag)) => *tag,
+        };
+
+        let words: Vec<&str> = tag.split_whitespace().collect();
+        match words.as_slice() {
+            ["if", cond] => {
+                let (then, end) = parse_nodes(tokens, pos)?;
+                let otherwise = match end {
+                    Some("endif") => Vec::new(),
+                    Some("else") => match parse_nodes(tokens, pos)? {
+                        (otherwise, Some("endif")) => otherwise,
+                        _ => return Err(template_error(format!("{{% if {} %}} without {{% endif %}}", cond))),
+                    },
+                    _ => return Err(template_error(format!("{{% if {} %}} without {{% endif %}}", cond))),
+                };
+                nodes.push(Node::If(cond.to_string(), then, otherwise));
+            }
+            ["for", var, "in", list] => match parse_nodes(tokens, pos)? {
+                (body, Some("endfor")) => nodes.push(Node::For(var.to_string(), list.to_string(), body)),
+                _ => return Err(template_error(format!("{{% for {} in {} %}} without {{% endfor %}}", var, list))),
+            },
+            ["else"] | ["endif"] | ["endfor"] => return Ok((nodes, Some(tag))),
+            _ => return Err(template_error(format!("unknown tag {{% {} %}}", tag))),
+        }
+    }
+    Ok((nodes, None))
+}
+
+/// Look up a dotted name, innermost loop variable first
+fn lookup<'v>(scopes: &[(&str, &'v Value)], name: &str) -> Option<&'v Value> {
+    let mut parts = name.split('.');
+    let first = parts.next()?;
+    let mut value = scopes
+        .iter()
+        .rev()
+        .find_map(|(var, value)| match *var {
+            "" => value.get(first),
+            var if var == first => Some(*value),
+            _ => None,
+        })?;
+    for part in parts {
+        value = match value {
+            Value::Array(items) => items.get(part.parse::<usize>().ok()?)?,
+            _ => value.get(part)?,
+        };
+    }
+    Some(value)
+}
+
+fn truthy(value: Option<&Value>) -> bool {
+    match value {
+        None | Some(Value::Null) => false,
+        Some(Value::Bool(b)) => *b,
+        Some(Value::Number(n)) => n.as_f64() != Some(0.0),
+        Some(Value::String(s)) => !s.is_empty(),
+        Some(Value::Array(a)) => !a.is_empty(),
+        Some(Value::Object(o)) => !o.is_empty(),
+    }
+}
+
+fn render_nodes<'v>(nodes: &'v [Node], scopes: &mut Vec<(&'v str, &'v Value)>, out: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Var(name) => match lookup(scopes, name) {
+                None | Some(Value::Null) => {}
+                Some(Value::String(s)) => out.push_str(s),
+                Some(other) => out.push_str(&other.to_string()),
+            },
+            Node::If(cond, then, otherwise) => {
+                let branch = if truthy(lookup(scopes, cond)) { then } else { otherwise };
+                render_nodes(branch, scopes, out);
+            }
+            Node::For(var, list, body) => {
+                if let Some(Value::Array(items)) = lookup(scopes, list) {
+                    for item in items {
+                        scopes.push((var.as_str(), item));
+                        render_nodes(body, scopes, out);
+                        scopes.pop();
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_variables_and_conditionals() {
+        let t = Template::parse("{{ title }} by {{ author.name }}{% if score %} ({{ score }}){% endif %}").unwrap();
+        let data = json!({"title": "Hello", "author": {"name": "pg"}, "score": 42});
+        assert_eq!(t.render(&data).unwrap(), "Hello by pg (42)");
+        assert_eq!(t.render(&json!({"title": "x", "score": 0})).unwrap(), "x by ");
+
+        let t = Template::parse("{% if url %}link{% else %}none{% endif %}").unwrap();
+        assert_eq!(t.render(&json!({"url": ""})).unwrap(), "none");
+        assert_eq!(t.render(&json!({"url": "https://x"})).unwrap(), "link");
+    }
+
+    #[test]
+    fn test_loops_and_line_trimming() {
+        let source = "\
+# Items
+{% for item in items %}
+  {% if item.done %}
+- [x] {{ item.name }}
+  {% else %}
+- [ ] {{ item.name }}
+  {% endif %}
+{% endfor %}
+Total: {{ items.0.name }}
+";
+        let data = json!({"items": [{"name": "a", "done": true}, {"name": "b", "done": false}]});
+        assert_eq!(render(source, &data).unwrap(), "# Items\n- [x] a\n- [ ] b\nTotal: a\n");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(Template::parse("{% if x %}open").is_err());
+        assert!(Template::parse("{% endfor %}").is_err());
+        assert!(Template::parse("{% while x %}{% endwhile %}").is_err());
+        assert!(Template::parse("{{ unclosed").is_err());
+        assert!(Template::parse("{% for x in %}{% endfor %}").is_err());
+    }
+}
@@ -0,0 +1,141 @@
+//! UTC calendar dates of Unix times
+//!
+//! Plugins stamp times into file names, feeds, HTTP headers and request
+//! signatures without a date library. [`DateTime`] converts between Unix
+//! seconds and the UTC calendar, and the formatters below cover the layouts
+//! those need.
+//!
+//! ```
+//! use agfs_core::time::{self, DateTime};
+//!
+//! assert_eq!(time::rfc3339(1_717_243_200), "2024-06-01T12:00:00Z");
+//! assert_eq!(time::rfc822(1_717_243_200), "Sat, 01 Jun 2024 12:00:00 GMT");
+//! assert_eq!(DateTime::from_unix(1_717_243_200).to_unix(), 1_717_243_200);
+//! ```
+
+const DAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+/// A UTC date and time of day, to the second
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateTime {
+    pub year: i64,
+    /// 1 to 12
+    pub month: u32,
+    /// 1 to 31
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+}
+
+impl DateTime {
+    /// The UTC date and time `secs` after the Unix epoch (before it if negative)
+    pub fn from_unix(secs: i64) -> Self {
+        let days = secs.div_euclid(86400);
+        let time = secs.rem_euclid(86400) as u32;
+
+        // Civil-from-days conversion (Howard Hinnant's algorithm)
+        let z = days + 719468;
+        let era = z.div_euclid(146097);
+        let doe = z.rem_euclid(146097);
+        let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        Self {
+            year,
+            month,
+            day,
+            hour: time / 3600,
+            minute: time / 60 % 60,
+            second: time % 60,
+        }
+    }
+
+    /// Seconds since the Unix epoch
+    pub fn to_unix(&self) -> i64 {
+        // Days-from-civil conversion (Howard Hinnant's algorithm)
+        let (month, day) = (self.month as i64, self.day as i64);
+        let y = if month <= 2 { self.year - 1 } else { self.year };
+        let era = y.div_euclid(400);
+        let yoe = y.rem_euclid(400);
+        let mp = (month + 9) % 12;
+        let doy = (153 * mp + 2) / 5 + day - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        let days = era * 146097 + doe - 719468;
+        days * 86400 + (self.hour * 3600 + self.minute * 60 + self.second) as i64
+    }
+
+    /// Day of the week, 0 for Sunday
+    pub fn weekday(&self) -> u32 {
+        // The epoch was a Thursday
+        (self.to_unix().div_euclid(86400) + 4).rem_euclid(7) as u32
+    }
+
+    /// `YYYY-MM-DD`
+    pub fn date(&self) -> String {
+        format!("{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// `secs` as an RFC 3339 UTC timestamp, `2024-06-01T12:00:00Z`
+pub fn rfc3339(secs: i64) -> String {
+    let t = DateTime::from_unix(secs);
+    format!("{}T{:02}:{:02}:{:02}Z", t.date(), t.hour, t.minute, t.second)
+}
+
+/// `secs` as an RFC 822 date, `Sat, 01 Jun 2024 12:00:00 GMT`, as in HTTP
+/// headers and RSS `pubDate`
+pub fn rfc822(secs: i64) -> String {
+    let t = DateTime::from_unix(secs);
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        DAYS[t.weekday() as usize],
+        t.day,
+        MONTHS[t.month as usize - 1],
+        t.year,
+        t.hour,
+        t.minute,
+        t.second
+    )
+}
+
+/// `secs` as an ISO 8601 basic UTC timestamp, `20240601T120000Z`, as in AWS
+/// request signatures
+pub fn basic(secs: i64) -> String {
+    let t = DateTime::from_unix(secs);
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        t.year, t.month, t.day, t.hour, t.minute, t.second
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        for secs in [0, -1, 951_782_400, 1_709_164_800, 1_717_243_199, -62_135_596_800, 253_402_300_799] {
+            assert_eq!(DateTime::from_unix(secs).to_unix(), secs, "{}", secs);
+        }
+        assert_eq!(
+            DateTime::from_unix(-1),
+            DateTime { year: 1969, month: 12, day: 31, hour: 23, minute: 59, second: 59 }
+        );
+    }
+
+    #[test]
+    fn test_formats() {
+        assert_eq!(rfc3339(0), "1970-01-01T00:00:00Z");
+        assert_eq!(rfc3339(1_709_164_800 + 3_723), "2024-02-29T01:02:03Z");
+        assert_eq!(rfc822(0), "Thu, 01 Jan 1970 00:00:00 GMT");
+        assert_eq!(rfc822(1_709_164_800), "Thu, 29 Feb 2024 00:00:00 GMT");
+        assert_eq!(basic(1_440_938_160), "20150830T123600Z");
+        assert_eq!(DateTime::from_unix(-62_135_596_800).date(), "0001-01-01");
+    }
+}
@@ -0,0 +1,1342 @@
+//! Type definitions for AGFS filesystem operations
+
+use crate::error::{Error, Result};
+use crate::table::Table;
+use serde::{Deserialize, Serialize};
+
+/// File information structure
+///
+/// On the wire the kind is also carried as `IsDir` and the Go `os.FileMode`
+/// type bits of `Mode`, so hosts and plugins that predate `Kind` agree on it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "FileInfoWire", into = "FileInfoWire")]
+pub struct FileInfo {
+    pub name: String,
+    /// Size in bytes, or [`SIZE_UNKNOWN`] for content generated on read
+    pub size: i64,
+    /// Permission bits; the type bits follow `kind`
+    pub mode: u32,
+    pub mod_time: i64,
+    pub kind: FileKind,
+    /// Where a symlink points
+    pub target: Option<String>,
+    pub meta: Option<MetaData>,
+    /// Opaque content version (e.g. an HTTP ETag or content hash); changes
+    /// whenever the content does
+    pub etag: Option<String>,
+    /// Inode number that stays the same for this path across lookups (see
+    /// `InodeMap`)
+    pub ino: Option<u64>,
+    /// MIME type the server's HTTP gateway serves the content as (see
+    /// `mime::detect`)
+    pub content_type: Option<String>,
+    /// Checksum of the content as `<algorithm>:<hex>`, e.g. `crc32c:e3069283`
+    /// (see `verify::VerifyFs`)
+    pub checksum: Option<String>,
+    /// Unix time after which the entry is stale and should be refetched or
+    /// dropped, e.g. a paste or a forecast (see `expiry::ExpiryFs`)
+    pub expires_at: Option<i64>,
+    /// Left out of directory listings unless asked for, e.g. a tombstone or
+    /// sync state (see `hidden::HiddenFs`)
+    pub hidden: bool,
+    /// Byte ranges currently locked and by whom, for contention diagnostics
+    pub locks: Vec<RangeLock>,
+}
+
+/// A locked byte range of a file, as reported by `stat` and `handle_stat`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RangeLock {
+    /// Who holds the lock, e.g. a client or handle ID; free-form
+    pub holder: String,
+    pub offset: u64,
+    /// Bytes covered; 0 means to the end of the file
+    pub length: u64,
+    pub exclusive: bool,
+}
+
+impl RangeLock {
+    /// A write lock on `length` bytes from `offset`
+    pub fn exclusive(holder: impl Into<String>, offset: u64, length: u64) -> Self {
+        Self {
+            holder: holder.into(),
+            offset,
+            length,
+            exclusive: true,
+        }
+    }
+
+    /// A read lock on `length` bytes from `offset`
+    pub fn shared(holder: impl Into<String>, offset: u64, length: u64) -> Self {
+        Self {
+            exclusive: false,
+            ..Self::exclusive(holder, offset, length)
+        }
+    }
+}
+
+/// What a directory entry is
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileKind {
+    #[default]
+    File,
+    Dir,
+    Symlink,
+    Fifo,
+    Socket,
+}
+
+/// `FileInfo::size` of a file whose size is only known once it is read
+///
+/// Hosts read such files until a read returns no data, report them as empty
+/// to `ls -l` and FUSE, and send them without a `Content-Length`.
+pub const SIZE_UNKNOWN: i64 = -1;
+
+/// Directory type bit in `FileInfo::mode` (matches Go `os.ModeDir`)
+pub const MODE_DIR: u32 = 1 << 31;
+/// Symlink type bit in `FileInfo::mode` (matches Go `os.ModeSymlink`)
+pub const MODE_SYMLINK: u32 = 1 << 27;
+/// Named pipe type bit in `FileInfo::mode` (matches Go `os.ModeNamedPipe`)
+pub const MODE_FIFO: u32 = 1 << 25;
+/// Socket type bit in `FileInfo::mode` (matches Go `os.ModeSocket`)
+pub const MODE_SOCKET: u32 = 1 << 24;
+const MODE_TYPE: u32 = MODE_DIR | MODE_SYMLINK | MODE_FIFO | MODE_SOCKET;
+
+impl FileKind {
+    /// Kind named by the type bits of `mode`, or by `is_dir` without any
+    pub fn from_mode(mode: u32, is_dir: bool) -> Self {
+        if is_dir || mode & MODE_DIR != 0 {
+            Self::Dir
+        } else if mode & MODE_SYMLINK != 0 {
+            Self::Symlink
+        } else if mode & MODE_FIFO != 0 {
+            Self::Fifo
+        } else if mode & MODE_SOCKET != 0 {
+            Self::Socket
+        } else {
+            Self::File
+        }
+    }
+
+    /// Go `os.FileMode` type bits of this kind
+    pub fn mode_bits(self) -> u32 {
+        match self {
+            Self::File => 0,
+            Self::Dir => MODE_DIR,
+            Self::Symlink => MODE_SYMLINK,
+            Self::Fifo => MODE_FIFO,
+            Self::Socket => MODE_SOCKET,
+        }
+    }
+}
+
+/// `FileInfo` as serialized, with the field names the Go host uses
+#[derive(Serialize, Deserialize)]
+struct FileInfoWire {
+    #[serde(rename = "Name")]
+    name: String,
+    #[serde(rename = "Size")]
+    size: i64,
+    #[serde(rename = "Mode")]
+    mode: u32,
+    #[serde(rename = "ModTime", serialize_with = "serialize_timestamp", deserialize_with = "deserialize_timestamp")]
+    mod_time: i64,
+    #[serde(rename = "IsDir")]
+    is_dir: bool,
+    #[serde(rename = "Kind", default, skip_serializing_if = "Option::is_none")]
+    kind: Option<FileKind>,
+    #[serde(rename = "Target", default, skip_serializing_if = "Option::is_none")]
+    target: Option<String>,
+    #[serde(rename = "Meta")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    meta: Option<MetaData>,
+    #[serde(rename = "ETag", default, skip_serializing_if = "Option::is_none")]
+    etag: Option<String>,
+    #[serde(rename = "Ino", default, skip_serializing_if = "Option::is_none")]
+    ino: Option<u64>,
+    #[serde(rename = "ContentType", default, skip_serializing_if = "Option::is_none")]
+    content_type: Option<String>,
+    #[serde(rename = "Checksum", default, skip_serializing_if = "Option::is_none")]
+    checksum: Option<String>,
+    #[serde(rename = "ExpiresAt", default, skip_serializing_if = "Option::is_none", with = "rfc3339_expiry")]
+    expires_at: Option<i64>,
+    #[serde(rename = "Hidden", default, skip_serializing_if = "std::ops::Not::not")]
+    hidden: bool,
+    #[serde(rename = "Locks", default, skip_serializing_if = "Vec::is_empty")]
+    locks: Vec<RangeLock>,
+}
+
+impl From<FileInfoWire> for FileInfo {
+    fn from(wire: FileInfoWire) -> Self {
+        Self {
+            name: wire.name,
+            size: wire.size,
+            mode: wire.mode & !MODE_TYPE,
+            mod_time: wire.mod_time,
+            kind: wire.kind.unwrap_or_else(|| FileKind::from_mode(wire.mode, wire.is_dir)),
+            target: wire.target,
+            meta: wire.meta,
+            etag: wire.etag,
+            ino: wire.ino,
+            content_type: wire.content_type,
+            checksum: wire.checksum,
+            expires_at: wire.expires_at,
+            hidden: wire.hidden,
+            locks: wire.locks,
+        }
+    }
+}
+
+impl From<FileInfo> for FileInfoWire {
+    fn from(info: FileInfo) -> Self {
+        Self {
+            mode: info.type_mode(),
+            is_dir: info.is_dir(),
+            name: info.name,
+            size: info.size,
+            mod_time: info.mod_time,
+            kind: Some(info.kind),
+            target: info.target,
+            meta: info.meta,
+            etag: info.etag,
+            ino: info.ino,
+            content_type: info.content_type,
+            checksum: info.checksum,
+            expires_at: info.expires_at,
+            hidden: info.hidden,
+            locks: info.locks,
+        }
+    }
+}
+
+// Serialize Unix timestamp to RFC3339 string
+fn serialize_timestamp<S>(_timestamp: &i64, serializer: S) -> std::result::Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    // Always serialize as zero time for simplicity
+    serializer.serialize_str("0001-01-01T00:00:00Z")
+}
+
+// Deserialize RFC3339 string to Unix timestamp
+fn deserialize_timestamp<'de, D>(deserializer: D) -> std::result::Result<i64, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let _s = String::deserialize(deserializer)?;
+    // Always return 0 for simplicity
+    Ok(0)
+}
+
+impl FileInfo {
+    /// Create a file info for a regular file
+    pub fn file(name: impl Into<String>, size: i64, mode: u32) -> Self {
+        Self {
+            name: name.into(),
+            size,
+            mode,
+            mod_time: 0,
+            kind: FileKind::File,
+            target: None,
+            meta: None,
+            etag: None,
+            ino: None,
+            content_type: None,
+            checksum: None,
+            expires_at: None,
+            hidden: false,
+            locks: Vec::new(),
+        }
+    }
+
+    /// Create a file info for a file whose content is generated when read
+    ///
+    /// Its size is [`SIZE_UNKNOWN`], so `stat` and `readdir` need not render
+    /// the content just to measure it. `read` must then honor its offset
+    /// (see `filesystem::read_range`).
+    pub fn generated(name: impl Into<String>, mode: u32) -> Self {
+        Self::file(name, SIZE_UNKNOWN, mode)
+    }
+
+    /// Create a file info for a directory
+    pub fn dir(name: impl Into<String>, mode: u32) -> Self {
+        Self {
+            name: name.into(),
+            size: 0,
+            mode,
+            mod_time: 0,
+            kind: FileKind::Dir,
+            target: None,
+            meta: None,
+            etag: None,
+            ino: None,
+            content_type: None,
+            checksum: None,
+            expires_at: None,
+            hidden: false,
+            locks: Vec::new(),
+        }
+    }
+
+    /// Create a file info for a symlink to `target`
+    pub fn symlink(name: impl Into<String>, target: impl Into<String>) -> Self {
+        let target = target.into();
+        Self {
+            size: target.len() as i64,
+            kind: FileKind::Symlink,
+            target: Some(target),
+            ..Self::file(name, 0, 0o777)
+        }
+    }
+
+    /// Set metadata
+    pub fn with_meta(mut self, meta: MetaData) -> Self {
+        self.meta = Some(meta);
+        self
+    }
+
+    /// Set the content version used for conditional reads
+    pub fn with_etag(mut self, etag: impl Into<String>) -> Self {
+        self.etag = Some(etag.into());
+        self
+    }
+
+    /// Set the inode number
+    pub fn with_ino(mut self, ino: u64) -> Self {
+        self.ino = Some(ino);
+        self
+    }
+
+    /// Set the MIME type of the content
+    pub fn with_content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.content_type = Some(content_type.into());
+        self
+    }
+
+    /// Set the checksum of the content, as `<algorithm>:<hex>`
+    pub fn with_checksum(mut self, checksum: impl Into<String>) -> Self {
+        self.checksum = Some(checksum.into());
+        self
+    }
+
+    /// Mark the entry stale from Unix time `timestamp` on
+    pub fn with_expiry(mut self, timestamp: i64) -> Self {
+        self.expires_at = Some(timestamp);
+        self
+    }
+
+    /// Whether the entry is stale at Unix time `now`
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at.is_some_and(|at| at <= now)
+    }
+
+    /// Leave the entry out of directory listings
+    pub fn hidden(mut self) -> Self {
+        self.hidden = true;
+        self
+    }
+
+    /// Report a lock held on part of the file
+    pub fn with_lock(mut self, lock: RangeLock) -> Self {
+        self.locks.push(lock);
+        self
+    }
+
+    /// Set modification time (Unix timestamp)
+    pub fn with_mod_time(mut self, timestamp: i64) -> Self {
+        self.mod_time = timestamp;
+        self
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.kind == FileKind::Dir
+    }
+
+    pub fn is_symlink(&self) -> bool {
+        self.kind == FileKind::Symlink
+    }
+
+    /// Whether `size` is the actual size rather than [`SIZE_UNKNOWN`]
+    pub fn size_known(&self) -> bool {
+        self.size >= 0
+    }
+
+    /// `mode` with the Go `os.FileMode` type bits of `kind`
+    pub fn type_mode(&self) -> u32 {
+        (self.mode & !MODE_TYPE) | self.kind.mode_bits()
+    }
+}
+
+/// Metadata structure
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetaData {
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(rename = "Type")]
+    pub type_: String,
+    #[serde(rename = "Content")]
+    pub content: serde_json::Value,
+}
+
+impl MetaData {
+    /// Create new metadata
+    pub fn new(name: impl Into<String>, type_: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            type_: type_.into(),
+            content: serde_json::Value::Object(serde_json::Map::new()),
+        }
+    }
+
+    /// Set content from JSON value
+    pub fn with_content(mut self, content: serde_json::Value) -> Self {
+        self.content = content;
+        self
+    }
+}
+
+/// Configuration parameter definition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigParameter {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub param_type: String,
+    pub required: bool,
+    pub default: String,
+    pub description: String,
+}
+
+impl ConfigParameter {
+    /// Create a new configuration parameter
+    pub fn new(
+        name: impl Into<String>,
+        param_type: impl Into<String>,
+        required: bool,
+        default: impl Into<String>,
+        description: impl Into<String>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            param_type: param_type.into(),
+            required,
+            default: default.into(),
+            description: description.into(),
+        }
+    }
+}
+
+/// Resources a plugin declares it will use, for operators to review
+///
+/// `http_hosts` lists the hosts the plugin contacts through the host HTTP
+/// API: `api.example.com`, `*.example.com` (any subdomain) or
+/// `internal:8443` (one port only). `None` leaves egress unrestricted, as
+/// for plugins that declare nothing.
+///
+/// `mounts` lists the agfs paths the plugin reaches on other mounts through
+/// the host. Unlike `http_hosts`, declaring nothing grants nothing.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Capabilities {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_hosts: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mounts: Option<Vec<MountGrant>>,
+}
+
+/// An agfs path, and everything below it, a plugin may use on other mounts
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MountGrant {
+    pub path: String,
+    /// Allow writes as well as reads
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub write: bool,
+}
+
+impl Capabilities {
+    /// Restrict HTTP to `hosts`
+    pub fn with_http_hosts<I, S>(mut self, hosts: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.http_hosts = Some(hosts.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Grant access to `path` on another mount, read-only unless `write`
+    pub fn with_mount(mut self, path: impl Into<String>, write: bool) -> Self {
+        self.mounts.get_or_insert_with(Vec::new).push(MountGrant {
+            path: path.into(),
+            write,
+        });
+        self
+    }
+
+    /// Whether `path` on another mount is within the declared mounts
+    ///
+    /// The host applies the same check to every call, after resolving `.`
+    /// and `..` in `path`.
+    pub fn allows_mount(&self, path: &str, write: bool) -> bool {
+        let Some(mounts) = &self.mounts else {
+            return false;
+        };
+        let path = normalize_path(path);
+        mounts.iter().any(|grant| {
+            let root = normalize_path(&grant.path);
+            let inside = root == "/"
+                || path == root
+                || path.strip_prefix(root.as_str()).is_some_and(|rest| rest.starts_with('/'));
+            inside && (grant.write || !write)
+        })
+    }
+
+    /// Whether an HTTP request to `url` is within the declared hosts
+    pub fn allows_url(&self, url: &str) -> bool {
+        let Some(hosts) = &self.http_hosts else {
+            return true;
+        };
+        let Some((host, port)) = url_authority(url) else {
+            return false;
+        };
+        hosts.iter().any(|pattern| {
+            let (pattern_host, pattern_port) = split_port(pattern);
+            let host_matches = match pattern_host.strip_prefix("*.") {
+                Some(domain) => host
+                    .strip_suffix(&domain.to_ascii_lowercase())
+                    .is_some_and(|sub| sub.len() > 1 && sub.ends_with('.')),
+                None => host.eq_ignore_ascii_case(pattern_host),
+            };
+            host_matches && pattern_port.is_none_or(|p| Some(p) == port)
+        })
+    }
+}
+
+/// `path` made absolute with `.`, `..` and repeated slashes resolved, as
+/// Go's `path.Clean("/" + path)` does on the host
+fn normalize_path(path: &str) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    format!("/{}", parts.join("/"))
+}
+
+/// Lowercased host and explicit port of an absolute URL
+fn url_authority(url: &str) -> Option<(String, Option<&str>)> {
+    let (_, rest) = url.split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let authority = authority.rsplit_once('@').map_or(authority, |(_, a)| a);
+    let (host, port) = split_port(authority);
+    (!host.is_empty()).then(|| (host.to_ascii_lowercase(), port))
+}
+
+/// Split `host:port`, leaving bracketed IPv6 addresses intact
+fn split_port(authority: &str) -> (&str, Option<&str>) {
+    match authority.rsplit_once(':') {
+        Some((host, port)) if !port.contains(']') && (!host.contains(':') || host.ends_with(']')) => {
+            (host, Some(port))
+        }
+        _ => (authority, None),
+    }
+}
+
+/// Machine-readable layout of a plugin's tree, served at `/.agfs/schema.json`
+///
+/// Lets tools and agents find their way around a mount without parsing the
+/// README. Patterns are absolute paths in which `{name}` and `*` match within
+/// one segment (`/stories/{rank}.md`) and a `**` segment any number of them.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FsSchema {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    #[serde(default)]
+    pub paths: Vec<PathSchema>,
+}
+
+/// One kind of entry in an [`FsSchema`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PathSchema {
+    pub pattern: String,
+    pub is_dir: bool,
+    /// MIME type of the content, for files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+    #[serde(default)]
+    pub writable: bool,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+}
+
+impl FsSchema {
+    pub fn new(description: impl Into<String>) -> Self {
+        Self {
+            description: description.into(),
+            paths: Vec::new(),
+        }
+    }
+
+    /// Add an entry
+    pub fn path(mut self, path: PathSchema) -> Self {
+        self.paths.push(path);
+        self
+    }
+
+    /// First entry whose pattern matches `path`
+    pub fn lookup(&self, path: &str) -> Option<&PathSchema> {
+        self.paths.iter().find(|p| p.matches(path))
+    }
+
+    /// Markdown README listing the layout and config `params`
+    ///
+    /// Generated from the same schema the plugin serves, so the docs shown
+    /// at mount cannot drift from the tree. Build it once and return it from
+    /// [`FileSystem::readme`](crate::FileSystem::readme).
+    pub fn to_readme(&self, title: &str, params: &[ConfigParameter]) -> String {
+        let mut out = format!("# {}\n\n", title);
+        if !self.description.is_empty() {
+            out.push_str(&format!("{}\n\n", self.description));
+        }
+
+        if !self.paths.is_empty() {
+            out.push_str("## Layout\n\nPaths are relative to the mount point.\n\n");
+            let mut table = Table::new(["Path", "Kind", "Access", "Description"]);
+            for path in &self.paths {
+                let kind = match (&path.format, path.is_dir) {
+                    (_, true) => "directory".to_string(),
+                    (Some(format), false) => format!("file ({})", format),
+                    (None, false) => "file".to_string(),
+                };
+                let access = if path.writable { "read-write" } else { "read-only" };
+                let pattern = format!("`{}`", path.pattern);
+                table = table.row([pattern, kind, access.to_string(), path.description.clone()]);
+            }
+            out.push_str(&table.to_markdown());
+            out.push('\n');
+        }
+
+        if !params.is_empty() {
+            out.push_str("## Configuration\n\n");
+            let mut table = Table::new(["Name", "Type", "Required", "Default", "Description"]);
+            for param in params {
+                let required = if param.required { "yes" } else { "no" };
+                let default = if param.default.is_empty() { String::new() } else { format!("`{}`", param.default) };
+                table = table.row([
+                    format!("`{}`", param.name),
+                    param.param_type.clone(),
+                    required.to_string(),
+                    default,
+                    param.description.clone(),
+                ]);
+            }
+            out.push_str(&table.to_markdown());
+        }
+        out.trim_end().to_string() + "\n"
+    }
+}
+
+impl PathSchema {
+    /// Files matching `pattern`
+    pub fn file(pattern: impl Into<String>, description: impl Into<String>) -> Self {
+        Self {
+            pattern: pattern.into(),
+            is_dir: false,
+            format: None,
+            writable: false,
+            description: description.into(),
+        }
+    }
+
+    /// Directories matching `pattern`
+    pub fn dir(pattern: impl Into<String>, description: impl Into<String>) -> Self {
+        Self {
+            is_dir: true,
+            ..Self::file(pattern, description)
+        }
+    }
+
+    /// Set the MIME type of the content
+    pub fn format(mut self, format: impl Into<String>) -> Self {
+        self.format = Some(format.into());
+        self
+    }
+
+    /// Mark as writable (files) or as accepting new entries (directories)
+    pub fn writable(mut self) -> Self {
+        self.writable = true;
+        self
+    }
+
+    /// Whether `path` is described by this entry
+    pub fn matches(&self, path: &str) -> bool {
+        // `{rank}.md` becomes `*.md`
+        let mut glob = String::with_capacity(self.pattern.len());
+        let mut rest = self.pattern.as_str();
+        while let Some(start) = rest.find('{') {
+            let Some(len) = rest[start..].find('}') else {
+                break;
+            };
+            glob.push_str(&rest[..start]);
+            glob.push('*');
+            rest = &rest[start + len + 1..];
+        }
+        glob.push_str(rest);
+        crate::policy::glob_match(&glob, path)
+    }
+}
+
+/// How far [`FileSystem::warmup`](crate::FileSystem::warmup) has got, in
+/// units the plugin chooses (stories fetched, prefixes listed)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WarmupProgress {
+    pub done: u64,
+    pub total: u64,
+}
+
+impl WarmupProgress {
+    pub fn new(done: u64, total: u64) -> Self {
+        Self { done, total }
+    }
+
+    /// Nothing (left) to warm up
+    pub fn complete() -> Self {
+        Self::default()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.done >= self.total
+    }
+}
+
+/// A directory listing and the generation of the directory it was taken at,
+/// see [`FileSystem::readdir_if_changed`](crate::FileSystem::readdir_if_changed)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Listing {
+    pub entries: Vec<FileInfo>,
+    /// 0 if the directory's generation is not tracked
+    pub generation: u64,
+}
+
+/// State of a resumable upload, see `UploadFS`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UploadSession {
+    pub id: String,
+    /// Path the data is committed to
+    pub path: String,
+    /// Bytes staged so far; the offset of the next append
+    pub received: u64,
+}
+
+/// One mutation in a `FileSystem::batch`
+///
+/// In JSON the variant is an `op` field, as in
+/// `{"op": "rename", "old_path": "/a", "new_path": "/b"}`, and write data is
+/// base64, matching how Go encodes `[]byte`. Zero-valued numbers and empty
+/// data may be omitted, as Go's `omitempty` does.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum FsOp {
+    Create {
+        path: String,
+    },
+    Mkdir {
+        path: String,
+        #[serde(default)]
+        perm: u32,
+    },
+    /// Replace the whole file, creating it if needed
+    Write {
+        path: String,
+        #[serde(default, with = "base64_bytes")]
+        data: Vec<u8>,
+    },
+    Remove {
+        path: String,
+    },
+    RemoveAll {
+        path: String,
+    },
+    Rename {
+        old_path: String,
+        new_path: String,
+    },
+    Chmod {
+        path: String,
+        #[serde(default)]
+        mode: u32,
+    },
+}
+
+impl FsOp {
+    /// Every path the operation touches
+    pub fn paths(&self) -> Vec<&str> {
+        match self {
+            FsOp::Rename { old_path, new_path } => vec![old_path, new_path],
+            FsOp::Create { path }
+            | FsOp::Mkdir { path, .. }
+            | FsOp::Write { path, .. }
+            | FsOp::Remove { path }
+            | FsOp::RemoveAll { path }
+            | FsOp::Chmod { path, .. } => vec![path],
+        }
+    }
+
+    /// Every path the operation touches, for rewriting
+    pub fn paths_mut(&mut self) -> Vec<&mut String> {
+        match self {
+            FsOp::Rename { old_path, new_path } => vec![old_path, new_path],
+            FsOp::Create { path }
+            | FsOp::Mkdir { path, .. }
+            | FsOp::Write { path, .. }
+            | FsOp::Remove { path }
+            | FsOp::RemoveAll { path }
+            | FsOp::Chmod { path, .. } => vec![path],
+        }
+    }
+}
+
+/// Standard base64 with padding, for byte fields exchanged with the Go host
+mod base64_bytes {
+    use serde::{de::Error as _, Deserialize, Deserializer, Serializer};
+
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    pub fn serialize<S: Serializer>(data: &[u8], serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+        for chunk in data.chunks(3) {
+            let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+            for i in 0..4 {
+                match i <= chunk.len() {
+                    true => out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char),
+                    false => out.push('='),
+                }
+            }
+        }
+        serializer.serialize_str(&out)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Vec<u8>, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        let text = text.trim_end_matches('=');
+        let mut out = Vec::with_capacity(text.len() * 3 / 4);
+        let (mut buf, mut bits) = (0u32, 0);
+        for c in text.bytes() {
+            let value = ALPHABET
+                .iter()
+                .position(|&a| a == c)
+                .ok_or_else(|| D::Error::custom("invalid base64"))?;
+            buf = (buf << 6) | value as u32;
+            bits += 6;
+            if bits >= 8 {
+                bits -= 8;
+                out.push((buf >> bits) as u8);
+                buf &= (1 << bits) - 1;
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// Expiry times as the RFC 3339 timestamps of Go's `time.Time`, whose zero
+/// value means the entry never expires
+mod rfc3339_expiry {
+    use crate::time::{self, DateTime};
+    use serde::{de::Error as _, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(timestamp: &Option<i64>, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&time::rfc3339(timestamp.unwrap_or_default()))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Option<i64>, D::Error> {
+        let Some(text) = Option::<String>::deserialize(deserializer)? else {
+            return Ok(None);
+        };
+        let secs = parse(&text).ok_or_else(|| D::Error::custom(format!("invalid RFC 3339 time: {}", text)))?;
+        // Go's zero time, year 1
+        Ok((secs > -62135596800).then_some(secs))
+    }
+
+    /// `YYYY-MM-DDTHH:MM:SS[.fraction](Z|±HH:MM)` as Unix seconds
+    fn parse(text: &str) -> Option<i64> {
+        let field = |range: std::ops::Range<usize>| text.get(range)?.parse::<u32>().ok();
+        let year = text.get(0..4)?.parse::<i64>().ok()?;
+        let (month, day) = (field(5..7)?, field(8..10)?);
+        let (hour, minute, second) = (field(11..13)?, field(14..16)?, field(17..19)?);
+        let zone = text.get(19..)?.trim_start_matches(|c: char| c == '.' || c.is_ascii_digit());
+        let offset = match zone {
+            "Z" | "z" => 0,
+            _ => {
+                let sign = match zone.get(..1)? {
+                    "+" => 1,
+                    "-" => -1,
+                    _ => return None,
+                };
+                sign * (zone.get(1..3)?.parse::<i64>().ok()? * 3600 + zone.get(4..6)?.parse::<i64>().ok()? * 60)
+            }
+        };
+
+        let t = DateTime { year, month, day, hour, minute, second };
+        Some(t.to_unix() - offset)
+    }
+}
+
+/// Configuration passed to plugin
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Config {
+    #[serde(flatten)]
+    pub inner: serde_json::Map<String, serde_json::Value>,
+}
+
+impl Config {
+    /// Parse the JSON object the host passes to validate/initialize
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).map_err(|e| Error::InvalidInput(format!("invalid config: {}", e)))
+    }
+
+    /// Get a string value
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.inner.get(key)?.as_str()
+    }
+
+    /// Get an integer value
+    pub fn get_i64(&self, key: &str) -> Option<i64> {
+        self.inner.get(key)?.as_i64()
+    }
+
+    /// Get a boolean value
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        self.inner.get(key)?.as_bool()
+    }
+
+    /// Check if a key exists
+    pub fn contains(&self, key: &str) -> bool {
+        self.inner.contains_key(key)
+    }
+}
+
+impl From<serde_json::Value> for Config {
+    fn from(value: serde_json::Value) -> Self {
+        match value {
+            serde_json::Value::Object(map) => Config { inner: map },
+            _ => Config {
+                inner: serde_json::Map::new(),
+            },
+        }
+    }
+}
+
+/// Get current Unix timestamp
+///
+/// Not available on wasm32, which has no clock without host support.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn current_timestamp() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .expect("System time is before Unix epoch")
+        .as_secs() as i64
+}
+
+/// Write flags for file operations (matches Go filesystem.WriteFlag)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteFlag(pub u32);
+
+impl WriteFlag {
+    /// No special flags (default overwrite)
+    pub const NONE: WriteFlag = WriteFlag(0);
+    /// Append mode - write at end of file
+    pub const APPEND: WriteFlag = WriteFlag(1 << 0);
+    /// Create file if it doesn't exist
+    pub const CREATE: WriteFlag = WriteFlag(1 << 1);
+    /// Fail if file already exists (used with CREATE)
+    pub const EXCLUSIVE: WriteFlag = WriteFlag(1 << 2);
+    /// Truncate file before writing
+    pub const TRUNCATE: WriteFlag = WriteFlag(1 << 3);
+    /// Sync after write
+    pub const SYNC: WriteFlag = WriteFlag(1 << 4);
+    /// Write made by `write_if` after the expected ETag matched; backends
+    /// that can apply the check and the write atomically should do so
+    pub const IF_MATCH: WriteFlag = WriteFlag(1 << 5);
+
+    /// Check if a flag is set
+    pub fn contains(&self, flag: WriteFlag) -> bool {
+        (self.0 & flag.0) != 0
+    }
+
+    /// Combine flags
+    pub fn with(&self, flag: WriteFlag) -> WriteFlag {
+        WriteFlag(self.0 | flag.0)
+    }
+}
+
+impl From<u32> for WriteFlag {
+    fn from(value: u32) -> Self {
+        WriteFlag(value)
+    }
+}
+
+impl From<WriteFlag> for u32 {
+    fn from(value: WriteFlag) -> Self {
+        value.0
+    }
+}
+
+impl std::ops::BitOr for WriteFlag {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self::Output {
+        WriteFlag(self.0 | rhs.0)
+    }
+}
+
+/// Open flags for file handle operations (matches Go filesystem.OpenFlag)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpenFlag(pub u32);
+
+impl OpenFlag {
+    /// Open for reading only
+    pub const O_RDONLY: OpenFlag = OpenFlag(0);
+    /// Open for writing only
+    pub const O_WRONLY: OpenFlag = OpenFlag(1);
+    /// Open for reading and writing
+    pub const O_RDWR: OpenFlag = OpenFlag(2);
+    /// Append mode - writes append to end of file
+    pub const O_APPEND: OpenFlag = OpenFlag(1 << 3);
+    /// Create file if it doesn't exist
+    pub const O_CREATE: OpenFlag = OpenFlag(1 << 4);
+    /// Exclusive - fail if file exists (used with O_CREATE)
+    pub const O_EXCL: OpenFlag = OpenFlag(1 << 5);
+    /// Truncate file to zero length
+    pub const O_TRUNC: OpenFlag = OpenFlag(1 << 6);
+
+    /// Check if a flag is set
+    pub fn contains(&self, flag: OpenFlag) -> bool {
+        (self.0 & flag.0) != 0
+    }
+
+    /// Combine flags
+    pub fn with(&self, flag: OpenFlag) -> OpenFlag {
+        OpenFlag(self.0 | flag.0)
+    }
+
+    /// Get the access mode (O_RDONLY, O_WRONLY, or O_RDWR)
+    pub fn access_mode(&self) -> OpenFlag {
+        OpenFlag(self.0 & 3)
+    }
+
+    /// Check if the handle may be read from
+    pub fn is_readable(&self) -> bool {
+        let mode = self.access_mode();
+        mode == Self::O_RDONLY || mode == Self::O_RDWR
+    }
+
+    /// Check if the handle may be written to
+    pub fn is_writable(&self) -> bool {
+        let mode = self.access_mode();
+        mode == Self::O_WRONLY || mode == Self::O_RDWR
+    }
+}
+
+impl From<u32> for OpenFlag {
+    fn from(value: u32) -> Self {
+        OpenFlag(value)
+    }
+}
+
+impl From<OpenFlag> for u32 {
+    fn from(value: OpenFlag) -> Self {
+        value.0
+    }
+}
+
+impl std::ops::BitOr for OpenFlag {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self::Output {
+        OpenFlag(self.0 | rhs.0)
+    }
+}
+
+/// Access pattern hint passed to `FileSystem::advise` (values match `posix_fadvise`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Advice {
+    /// No particular pattern
+    Normal = 0,
+    /// Random access; read-ahead is wasted
+    Random = 1,
+    /// Sequential access; read ahead aggressively
+    Sequential = 2,
+    /// The range will be read soon
+    WillNeed = 3,
+    /// The range will not be read again soon
+    DontNeed = 4,
+}
+
+impl TryFrom<i32> for Advice {
+    type Error = crate::error::Error;
+
+    fn try_from(value: i32) -> crate::error::Result<Self> {
+        match value {
+            0 => Ok(Advice::Normal),
+            1 => Ok(Advice::Random),
+            2 => Ok(Advice::Sequential),
+            3 => Ok(Advice::WillNeed),
+            4 => Ok(Advice::DontNeed),
+            _ => Err(crate::error::Error::InvalidInput(format!("unknown advice {}", value))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_info_creation() {
+        let info = FileInfo::file("test.txt", 100, 0o644);
+        assert_eq!(info.name, "test.txt");
+        assert_eq!(info.size, 100);
+        assert_eq!(info.mode, 0o644);
+        assert!(!info.is_dir());
+    }
+
+    #[test]
+    fn test_generated_file_info() {
+        let info = FileInfo::generated("story.md", 0o444);
+        assert_eq!(info.size, SIZE_UNKNOWN);
+        assert!(!info.size_known());
+
+        let wire = serde_json::to_value(&info).unwrap();
+        let back: FileInfo = serde_json::from_value(wire).unwrap();
+        assert_eq!(back.size, SIZE_UNKNOWN);
+
+        let content = b"rendered on read";
+        assert_eq!(crate::filesystem::read_range(content, 9, 4096), b"on read");
+        assert!(crate::filesystem::read_range(content, 16, 4096).is_empty());
+    }
+
+    #[test]
+    fn test_directory_info_creation() {
+        let info = FileInfo::dir("testdir", 0o755);
+        assert_eq!(info.name, "testdir");
+        assert_eq!(info.size, 0);
+        assert!(info.is_dir());
+    }
+
+    #[test]
+    fn test_file_info_kind_wire_format() {
+        let link = FileInfo::symlink("latest", "2024/12.md");
+        let json = serde_json::to_value(&link).unwrap();
+        assert_eq!(json["Kind"], "symlink");
+        assert_eq!(json["Target"], "2024/12.md");
+        assert_eq!(json["IsDir"], false);
+        assert_eq!(json["Mode"], MODE_SYMLINK | 0o777);
+
+        let back: FileInfo = serde_json::from_value(json).unwrap();
+        assert!(back.is_symlink());
+        assert_eq!(back.mode, 0o777);
+        assert_eq!(back.target.as_deref(), Some("2024/12.md"));
+
+        // Hosts and plugins that predate `Kind`
+        let old = r#"{"Name":"d","Size":0,"Mode":2147484141,"ModTime":"0001-01-01T00:00:00Z","IsDir":true}"#;
+        let info: FileInfo = serde_json::from_str(old).unwrap();
+        assert_eq!((info.kind, info.mode), (FileKind::Dir, 0o755));
+        let old = r#"{"Name":"p","Size":0,"Mode":33554852,"ModTime":"0001-01-01T00:00:00Z","IsDir":false}"#;
+        assert_eq!(serde_json::from_str::<FileInfo>(old).unwrap().kind, FileKind::Fifo);
+    }
+
+    #[test]
+    fn test_file_info_locks() {
+        let info = FileInfo::file("db", 4096, 0o644)
+            .with_lock(RangeLock::exclusive("h12", 0, 512))
+            .with_lock(RangeLock::shared("client-7", 1024, 0));
+        let json = serde_json::to_value(&info).unwrap();
+        assert_eq!(json["Locks"][0], serde_json::json!({"holder": "h12", "offset": 0, "length": 512, "exclusive": true}));
+        assert_eq!(serde_json::from_value::<FileInfo>(json).unwrap().locks, info.locks);
+
+        let json = serde_json::to_value(FileInfo::file("a", 0, 0o644)).unwrap();
+        assert_eq!(json.get("Locks"), None);
+    }
+
+    #[test]
+    fn test_file_info_expiry() {
+        let info = FileInfo::file("paste", 5, 0o644).with_expiry(1718000000);
+        assert!(!info.is_expired(1717999999));
+        assert!(info.is_expired(1718000000));
+        assert!(!FileInfo::file("a", 0, 0o644).is_expired(i64::MAX));
+
+        let json = serde_json::to_value(&info).unwrap();
+        assert_eq!(json["ExpiresAt"], "2024-06-10T06:13:20Z");
+        assert_eq!(serde_json::from_value::<FileInfo>(json).unwrap().expires_at, Some(1718000000));
+        assert!(serde_json::to_value(FileInfo::file("a", 0, 0o644)).unwrap().get("ExpiresAt").is_none());
+
+        // As the Go host sends it: offsets, fractions, and the zero time for none
+        let host = |at: &str| {
+            let json = serde_json::json!({"Name": "a", "Size": 0, "Mode": 420, "ModTime": "", "IsDir": false, "ExpiresAt": at});
+            serde_json::from_value::<FileInfo>(json).unwrap().expires_at
+        };
+        assert_eq!(host("2024-06-10T08:13:20.5+02:00"), Some(1718000000));
+        assert_eq!(host("0001-01-01T00:00:00Z"), None);
+    }
+
+    #[test]
+    fn test_file_info_with_meta() {
+        let meta = MetaData::new("myplugin", "text").with_content(serde_json::json!({"key": "value"}));
+        let info = FileInfo::file("test.txt", 50, 0o644).with_meta(meta);
+        let meta = info.meta.as_ref().unwrap();
+        assert_eq!(meta.name, "myplugin");
+        assert_eq!(meta.type_, "text");
+        assert_eq!(meta.content["key"], "value");
+    }
+
+    #[test]
+    fn test_fs_op_json() {
+        let op = FsOp::Write {
+            path: "/a".to_string(),
+            data: b"hello".to_vec(),
+        };
+        let json = serde_json::to_value(&op).unwrap();
+        assert_eq!(json, serde_json::json!({"op": "write", "path": "/a", "data": "aGVsbG8="}));
+        assert_eq!(serde_json::from_value::<FsOp>(json).unwrap(), op);
+
+        for data in [&b""[..], b"h", b"he", b"\xff\x00\x10"] {
+            let op = FsOp::Write { path: "/a".to_string(), data: data.to_vec() };
+            assert_eq!(serde_json::from_str::<FsOp>(&serde_json::to_string(&op).unwrap()).unwrap(), op);
+        }
+
+        let op: FsOp = serde_json::from_str(r#"{"op": "write", "path": "/empty"}"#).unwrap();
+        assert_eq!(op, FsOp::Write { path: "/empty".to_string(), data: vec![] });
+        let op: FsOp = serde_json::from_str(r#"{"op": "rename", "old_path": "/a", "new_path": "/b"}"#).unwrap();
+        assert_eq!(op.paths(), ["/a", "/b"]);
+    }
+
+    #[test]
+    fn test_file_info_json_shape() {
+        let json = serde_json::to_value(FileInfo::file("a", 1, 0o644)).unwrap();
+        assert_eq!(json["Name"], "a");
+        assert_eq!(json["IsDir"], false);
+        assert!(json.get("Meta").is_none());
+        assert!(json.get("ETag").is_none());
+
+        let json = serde_json::to_value(FileInfo::file("a", 1, 0o644).with_etag("v1")).unwrap();
+        assert_eq!(json["ETag"], "v1");
+    }
+
+    #[test]
+    fn test_current_timestamp() {
+        let ts = current_timestamp();
+        assert!(ts > 0);
+    }
+
+    #[test]
+    fn test_open_flag_access_mode() {
+        assert!(OpenFlag::O_RDONLY.is_readable());
+        assert!(!OpenFlag::O_RDONLY.is_writable());
+        assert!(!OpenFlag::O_WRONLY.is_readable());
+        let flags = OpenFlag::O_RDWR | OpenFlag::O_CREATE | OpenFlag::O_TRUNC;
+        assert!(flags.is_readable() && flags.is_writable());
+        assert!(flags.contains(OpenFlag::O_TRUNC));
+        assert!(!flags.contains(OpenFlag::O_APPEND));
+    }
+
+    #[test]
+    fn test_config_from_json() {
+        let config = Config::from_json(r#"{"prefix":"/data","limit":10,"debug":true}"#).unwrap();
+        assert_eq!(config.get_str("prefix"), Some("/data"));
+        assert_eq!(config.get_i64("limit"), Some(10));
+        assert_eq!(config.get_bool("debug"), Some(true));
+        assert!(!config.contains("missing"));
+        assert!(Config::from_json("not json").is_err());
+    }
+
+    #[test]
+    fn test_config_parameter_json() {
+        let param = ConfigParameter::new("prefix", "string", false, "/", "Root prefix");
+        let json = serde_json::to_string(&param).unwrap();
+        assert!(json.contains(r#""type":"string""#));
+    }
+
+    #[test]
+    fn test_capabilities_allows_url() {
+        assert!(Capabilities::default().allows_url("https://anywhere.test/x"));
+
+        let caps = Capabilities::default().with_http_hosts(["api.example.com", "*.cdn.test", "internal:8443"]);
+        assert!(caps.allows_url("https://api.example.com/v1?q=1"));
+        assert!(caps.allows_url("https://API.Example.com:443/v1"));
+        assert!(caps.allows_url("https://img.cdn.test/a.png"));
+        assert!(!caps.allows_url("https://cdn.test/a.png"));
+        assert!(!caps.allows_url("https://evilcdn.test/a.png"));
+        assert!(caps.allows_url("http://internal:8443/"));
+        assert!(!caps.allows_url("http://internal/"));
+        assert!(!caps.allows_url("https://api.example.com.evil.test/"));
+        assert!(!caps.allows_url("https://evil.test/@api.example.com"));
+        assert!(caps.allows_url("https://user:pw@api.example.com/"));
+        assert!(!caps.allows_url("not a url"));
+
+        let json = serde_json::to_string(&caps).unwrap();
+        assert_eq!(serde_json::from_str::<Capabilities>(&json).unwrap(), caps);
+        assert_eq!(serde_json::to_string(&Capabilities::default()).unwrap(), "{}");
+    }
+
+    #[test]
+    fn test_capabilities_allows_mount() {
+        assert!(!Capabilities::default().allows_mount("/s3/a", false));
+
+        let caps = Capabilities::default().with_mount("/s3/", false).with_mount("/local/out", true);
+        assert!(caps.allows_mount("/s3", false));
+        assert!(caps.allows_mount("/s3/bucket/key", false));
+        assert!(!caps.allows_mount("/s3/bucket/key", true));
+        assert!(!caps.allows_mount("/s3x/key", false));
+        assert!(!caps.allows_mount("/s3/../etc", false));
+        assert!(caps.allows_mount("/local/out/a.txt", true));
+        assert!(caps.allows_mount("//local/./out/a.txt", true));
+        assert!(!caps.allows_mount("/local", false));
+
+        let json = serde_json::to_string(&caps).unwrap();
+        assert!(json.contains(r#"{"path":"/s3/"}"#));
+        assert_eq!(serde_json::from_str::<Capabilities>(&json).unwrap(), caps);
+    }
+
+    #[test]
+    fn test_fs_schema_lookup() {
+        let schema = FsSchema::new("Stories")
+            .path(PathSchema::dir("/stories", "One file per story"))
+            .path(PathSchema::file("/stories/{rank}.md", "Story text").format("text/markdown"))
+            .path(PathSchema::file("/archive/**", "Old stories"));
+
+        assert!(schema.lookup("/stories").unwrap().is_dir);
+        assert_eq!(schema.lookup("/stories/12.md").unwrap().description, "Story text");
+        assert_eq!(schema.lookup("/archive/2024/01/3.md").unwrap().pattern, "/archive/**");
+        assert!(schema.lookup("/stories/12.md/x").is_none());
+
+        let json = serde_json::to_value(&schema).unwrap();
+        assert_eq!(json["paths"][1]["format"], "text/markdown");
+        assert_eq!(json["paths"][0].get("format"), None);
+    }
+
+    #[test]
+    fn test_fs_schema_to_readme() {
+        let schema = FsSchema::new("Stories")
+            .path(PathSchema::file("/refresh", "Refetch the list").writable())
+            .path(PathSchema::dir("/stories", "One file per story"))
+            .path(PathSchema::file("/stories/{rank}.md", "Story | text").format("text/markdown"));
+        let params = [ConfigParameter::new("max_stories", "int", false, "30", "Stories to fetch")];
+
+        let readme = schema.to_readme("StoryFS", &params);
+        assert!(readme.starts_with("# StoryFS\n\nStories\n\n## Layout\n"));
+        assert!(readme.contains("| `/refresh` | file | read-write | Refetch the list |\n"));
+        assert!(readme.contains("| `/stories` | directory | read-only | One file per story |\n"));
+        assert!(readme.contains("| file (text/markdown) | read-only | Story \\| text |\n"));
+        assert!(readme.ends_with("| `max_stories` | int | no | `30` | Stories to fetch |\n"));
+
+        assert_eq!(FsSchema::default().to_readme("Empty", &[]), "# Empty\n");
+    }
+
+    #[test]
+    fn test_advice_from_i32() {
+        assert_eq!(Advice::try_from(2).unwrap(), Advice::Sequential);
+        assert_eq!(Advice::try_from(Advice::DontNeed as i32).unwrap(), Advice::DontNeed);
+        assert!(Advice::try_from(9).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+//! In-memory vector index for semantic search
+//!
+//! Holds one embedding per key, e.g. per story or note, and finds the keys
+//! closest to a query embedding by cosine similarity. Search is a linear
+//! scan, fast enough for the thousands of entries a plugin keeps in memory.
+
+use crate::error::{Error, Result};
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// A key found by [`VectorIndex::search`] and its cosine similarity to the
+/// query, from -1 to 1
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hit<K> {
+    pub key: K,
+    pub score: f32,
+}
+
+/// Embeddings by key, all of the same dimension
+pub struct VectorIndex<K> {
+    /// Vectors scaled to unit length, so similarity is a dot product
+    vectors: HashMap<K, Vec<f32>>,
+    dimensions: usize,
+}
+
+impl<K: Eq + Hash + Clone> Default for VectorIndex<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Eq + Hash + Clone> VectorIndex<K> {
+    pub fn new() -> Self {
+        Self {
+            vectors: HashMap::new(),
+            dimensions: 0,
+        }
+    }
+
+    /// Set the embedding of `key`, replacing any earlier one
+    ///
+    /// The first vector fixes the dimension of the index; vectors of another
+    /// dimension, or of zero length, fail with `InvalidInput`.
+    pub fn insert(&mut self, key: K, vector: Vec<f32>) -> Result<()> {
+        let vector = self.normalized(vector)?;
+        self.dimensions = vector.len();
+        self.vectors.insert(key, vector);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, key: &K) -> bool {
+        self.vectors.remove(key).is_some()
+    }
+
+    /// Keep only the keys `keep` accepts
+    pub fn retain(&mut self, mut keep: impl FnMut(&K) -> bool) {
+        self.vectors.retain(|key, _| keep(key));
+    }
+
+    pub fn contains(&self, key: &K) -> bool {
+        self.vectors.contains_key(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.vectors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vectors.is_empty()
+    }
+
+    /// Dimension of the vectors, 0 while the index is empty
+    pub fn dimensions(&self) -> usize {
+        match self.vectors.is_empty() {
+            true => 0,
+            false => self.dimensions,
+        }
+    }
+
+    /// The `limit` keys most similar to `query`, best first
+    pub fn search(&self, query: &[f32], limit: usize) -> Result<Vec<Hit<K>>> {
+        if self.vectors.is_empty() || limit == 0 {
+            return Ok(Vec::new());
+        }
+        let query = self.normalized(query.to_vec())?;
+        let mut hits: Vec<Hit<K>> = self
+            .vectors
+            .iter()
+            .map(|(key, vector)| Hit {
+                key: key.clone(),
+                score: vector.iter().zip(&query).map(|(a, b)| a * b).sum(),
+            })
+            .collect();
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+        hits.truncate(limit);
+        Ok(hits)
+    }
+
+    /// `vector` scaled to unit length, checked against the index's dimension
+    fn normalized(&self, mut vector: Vec<f32>) -> Result<Vec<f32>> {
+        if !self.vectors.is_empty() && vector.len() != self.dimensions {
+            return Err(Error::InvalidInput(format!(
+                "vector has {} dimensions, the index {}",
+                vector.len(),
+                self.dimensions
+            )));
+        }
+        let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if norm == 0.0 || !norm.is_finite() {
+            return Err(Error::InvalidInput("vector has no direction".to_string()));
+        }
+        vector.iter_mut().for_each(|x| *x /= norm);
+        Ok(vector)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vector_index() {
+        let mut index = VectorIndex::new();
+        index.insert("rust", vec![1.0, 0.0, 0.0]).unwrap();
+        index.insert("go", vec![0.6, 0.8, 0.0]).unwrap();
+        index.insert("cooking", vec![0.0, 0.0, 5.0]).unwrap();
+        assert_eq!(index.dimensions(), 3);
+        assert!(index.insert("bad", vec![1.0, 0.0]).is_err());
+        assert!(index.insert("bad", vec![0.0; 3]).is_err());
+
+        let hits = index.search(&[2.0, 0.5, 0.0], 2).unwrap();
+        let keys: Vec<&str> = hits.iter().map(|hit| hit.key).collect();
+        assert_eq!(keys, ["rust", "go"]);
+        assert!((index.search(&[0.0, 0.0, 1.0], 1).unwrap()[0].score - 1.0).abs() < 1e-6);
+
+        // Replacing a vector moves its key
+        index.insert("cooking", vec![1.0, 0.1, 0.0]).unwrap();
+        assert_eq!(index.search(&[1.0, 0.1, 0.0], 1).unwrap()[0].key, "cooking");
+        index.retain(|key| *key != "cooking");
+        assert!(index.remove(&"go"));
+        assert_eq!(index.len(), 1);
+    }
+}
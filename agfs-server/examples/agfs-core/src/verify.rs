@@ -0,0 +1,959 @@
+//! End-to-end checksums of file content
+//!
+//! Archival mounts cannot afford to hand back silently corrupted data.
+//! Plugins attach a checksum to the files they list with
+//! [`FileInfo::with_checksum`], e.g. one the backend stores next to each
+//! object, and [`VerifyFs`] checks what the plugin reads against it, failing
+//! the read with `EIO` on a mismatch:
+//!
+//! ```ignore
+//! fn stat(&self, path: &str) -> Result<FileInfo> {
+//!     let object = self.bucket.head(path)?;
+//!     Ok(FileInfo::file(name, object.size, 0o644).with_checksum(format!("crc32c:{}", object.crc32c)))
+//! }
+//!
+//! type Exported = VerifyFs<ArchiveFS>;
+//! export_plugin!(Exported);
+//! ```
+//!
+//! Files the plugin reports no checksum for get one when written whole
+//! through the wrapper, so a corrupted round trip through the backend is
+//! caught too; those checksums are kept in memory only. Only [`CRC32C`]
+//! checksums are verified; files with other algorithms pass unchecked.
+//!
+//! Data nobody reads can rot unnoticed until it is needed, so the wrapper
+//! also scrubs the mount: every `maintain` call verifies the next
+//! `scrub_files` files (16 by default, 0 disables scrubbing), working
+//! through the whole tree in path order and starting over when done. Damaged
+//! files fail [`FileSystem::health`] and are listed in [`SCRUB_REPORT_PATH`]
+//! until they are scrubbed clean or rewritten; writing `scrub` to
+//! `/.agfs/ctl` finishes the current pass at once.
+
+use crate::error::{Error, Result};
+use crate::filesystem::{
+    filter_entries, read_range, reject_batch, split_batch, walk_tree, FileSystem, HandleFS, StreamFS, UploadFS,
+};
+use crate::types::{
+    Advice, Capabilities, Config, ConfigParameter, FileInfo, FsOp, FsSchema, OpenFlag, PathSchema, UploadSession,
+    WarmupProgress, WriteFlag,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+/// Name of the CRC-32C (Castagnoli) algorithm in checksums
+pub const CRC32C: &str = "crc32c";
+
+/// Read-only file with the results of scrubbing
+pub const SCRUB_REPORT_PATH: &str = "/.scrub-report";
+
+/// Config key setting how many files each `maintain` call scrubs
+pub const SCRUB_FILES_CONFIG_KEY: &str = "scrub_files";
+
+/// Files scrubbed per `maintain` call unless configured otherwise
+pub const DEFAULT_SCRUB_FILES: usize = 16;
+
+/// CRC-32C lookup table, reflected polynomial 0x82F63B78
+const CRC32C_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = match crc & 1 {
+                1 => (crc >> 1) ^ 0x82F6_3B78,
+                _ => crc >> 1,
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// CRC-32C of `data`, as used by iSCSI, ext4 and Google Cloud Storage
+pub fn crc32c(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &byte| {
+        CRC32C_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+/// The checksum of `data` in `FileInfo::checksum` form, `crc32c:<hex>`
+pub fn checksum(data: &[u8]) -> String {
+    format!("{}:{:08x}", CRC32C, crc32c(data))
+}
+
+/// Whether `data` matches `expected`; `None` if its algorithm is unknown
+pub fn matches(expected: &str, data: &[u8]) -> Option<bool> {
+    let (_, hex) = expected.split_once(':').filter(|_| verifiable(expected))?;
+    Some(u32::from_str_radix(hex, 16).is_ok_and(|crc| crc == crc32c(data)))
+}
+
+/// Whether [`matches`] knows the algorithm of `checksum`
+pub fn verifiable(checksum: &str) -> bool {
+    checksum
+        .split_once(':')
+        .is_some_and(|(algorithm, _)| algorithm.eq_ignore_ascii_case(CRC32C))
+}
+
+/// Progress and findings of scrubbing
+#[derive(Default)]
+struct Scrub {
+    /// Last path scrubbed in the current pass
+    cursor: String,
+    files: u64,
+    passes: u64,
+    /// Damaged files and what is wrong with them
+    damaged: BTreeMap<String, String>,
+}
+
+/// Filesystem wrapper verifying reads against the checksums of the files
+///
+/// A read of a file with a checksum reads the whole file through the plugin
+/// to check it, then returns the requested range; reads through handles and
+/// streams are passed on unchecked.
+pub struct VerifyFs<F> {
+    inner: F,
+    /// Checksums of files written whole through the wrapper
+    written: Mutex<HashMap<String, String>>,
+    verified: AtomicU64,
+    mismatches: AtomicU64,
+    scrub_files: usize,
+    scrub: Mutex<Scrub>,
+}
+
+impl<F: Default> Default for VerifyFs<F> {
+    fn default() -> Self {
+        Self::new(F::default())
+    }
+}
+
+impl<F> VerifyFs<F> {
+    /// Wrap `inner`, verifying its reads
+    pub fn new(inner: F) -> Self {
+        Self {
+            inner,
+            written: Mutex::new(HashMap::new()),
+            verified: AtomicU64::new(0),
+            mismatches: AtomicU64::new(0),
+            scrub_files: DEFAULT_SCRUB_FILES,
+            scrub: Mutex::new(Scrub::default()),
+        }
+    }
+
+    /// The wrapped filesystem
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    fn written(&self) -> MutexGuard<'_, HashMap<String, String>> {
+        self.written.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn scrub_state(&self) -> MutexGuard<'_, Scrub> {
+        self.scrub.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn record(&self, path: &str, data: &[u8]) {
+        self.written().insert(path.to_string(), checksum(data));
+    }
+
+    /// Forget the checksums and damage of `path` and everything below it
+    fn forget(&self, path: &str) {
+        let path = path.trim_end_matches('/');
+        self.written().retain(|kept, _| !is_within(kept, path));
+        self.scrub_state()
+            .damaged
+            .retain(|damaged, _| !is_within(damaged, path));
+    }
+
+    fn moved(&self, old_path: &str, new_path: &str) {
+        let (old_path, new_path) = (old_path.trim_end_matches('/'), new_path.trim_end_matches('/'));
+        let mut written = self.written();
+        written.retain(|kept, _| !is_within(kept, new_path));
+        let moved: Vec<String> = written
+            .keys()
+            .filter(|kept| is_within(kept, old_path))
+            .cloned()
+            .collect();
+        for kept in moved {
+            if let Some(sum) = written.remove(&kept) {
+                written.insert(format!("{}{}", new_path, &kept[old_path.len()..]), sum);
+            }
+        }
+        drop(written);
+        // A damaged file stays damaged under its new name
+        let mut scrub = self.scrub_state();
+        scrub.damaged.retain(|damaged, _| !is_within(damaged, new_path));
+        let moved: Vec<String> = scrub
+            .damaged
+            .keys()
+            .filter(|damaged| is_within(damaged, old_path))
+            .cloned()
+            .collect();
+        for damaged in moved {
+            if let Some(problem) = scrub.damaged.remove(&damaged) {
+                scrub
+                    .damaged
+                    .insert(format!("{}{}", new_path, &damaged[old_path.len()..]), problem);
+            }
+        }
+    }
+
+    /// The content of [`SCRUB_REPORT_PATH`]
+    fn report(&self) -> String {
+        let scrub = self.scrub_state();
+        let mut report = format!(
+            "files scrubbed: {}\npasses completed: {}\ndamaged files: {}\n",
+            scrub.files,
+            scrub.passes,
+            scrub.damaged.len()
+        );
+        if !scrub.damaged.is_empty() {
+            report.push('\n');
+        }
+        for (path, problem) in &scrub.damaged {
+            let _ = writeln!(report, "{}: {}", path, problem);
+        }
+        report
+    }
+
+    /// Attach the checksum of a file written through the wrapper to `info`
+    /// of `path` if the plugin reported none
+    fn annotate(&self, path: &str, mut info: FileInfo) -> FileInfo {
+        if info.checksum.is_none() && !info.is_dir() {
+            info.checksum = self.written().get(path).cloned();
+        }
+        info
+    }
+
+    /// Check `data`, all of `path`, against `expected`
+    fn check(&self, path: &str, expected: &str, data: &[u8]) -> Result<()> {
+        match matches(expected, data) {
+            None => Ok(()),
+            Some(true) => {
+                self.verified.fetch_add(1, Ordering::Relaxed);
+                Ok(())
+            }
+            Some(false) => {
+                self.mismatches.fetch_add(1, Ordering::Relaxed);
+                Err(Error::Io(format!(
+                    "checksum mismatch on {}: expected {}, read {}",
+                    path,
+                    expected,
+                    checksum(data)
+                )))
+            }
+        }
+    }
+}
+
+/// Whether `path` is `dir` or below it
+fn is_within(path: &str, dir: &str) -> bool {
+    path.strip_prefix(dir)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+fn is_report(path: &str) -> bool {
+    path.trim_end_matches('/') == SCRUB_REPORT_PATH
+}
+
+fn is_root(path: &str) -> bool {
+    path.trim_end_matches('/').is_empty()
+}
+
+fn touches_report(op: &FsOp) -> bool {
+    match op {
+        FsOp::Write { path, .. }
+        | FsOp::Create { path }
+        | FsOp::Mkdir { path, .. }
+        | FsOp::Remove { path }
+        | FsOp::RemoveAll { path }
+        | FsOp::Chmod { path, .. } => is_report(path),
+        FsOp::Rename { old_path, new_path } => is_report(old_path) || is_report(new_path),
+    }
+}
+
+fn report_info() -> FileInfo {
+    FileInfo::generated(&SCRUB_REPORT_PATH[1..], 0o444).with_content_type("text/plain")
+}
+
+fn scrub_files(config: &Config) -> Result<Option<usize>> {
+    match config.get_i64(SCRUB_FILES_CONFIG_KEY) {
+        None => Ok(None),
+        Some(files) if files < 0 => Err(Error::InvalidInput(format!(
+            "invalid {}: {} (expected 0 or more)",
+            SCRUB_FILES_CONFIG_KEY, files
+        ))),
+        Some(files) => Ok(Some(files as usize)),
+    }
+}
+
+impl<F: FileSystem> VerifyFs<F> {
+    /// The checksum `path` should have, from the plugin or an earlier write
+    fn expected(&self, path: &str) -> Option<String> {
+        let info = self.inner.stat(path).ok()?;
+        info.checksum.or_else(|| self.written().get(path).cloned())
+    }
+
+    /// Verify up to `limit` files after the last one scrubbed, returning how
+    /// many were checked
+    ///
+    /// Files without a verifiable checksum are skipped. Reaching the end of
+    /// the tree completes a pass, and the next call starts over.
+    pub fn scrub(&self, limit: usize) -> Result<usize> {
+        let mut files: Vec<(String, String)> = self
+            .inner
+            .walk("/", 0)?
+            .into_iter()
+            .filter(|(_, info)| !info.is_dir())
+            .filter_map(|(path, info)| {
+                let expected = info.checksum.or_else(|| self.written().get(&path).cloned())?;
+                verifiable(&expected).then_some((path, expected))
+            })
+            .collect();
+        files.sort();
+        let cursor = self.scrub_state().cursor.clone();
+        let start = files.partition_point(|(path, _)| *path <= cursor);
+        let batch = &files[start..files.len().min(start.saturating_add(limit))];
+
+        // Read without the lock held
+        let checked: Vec<(&str, Option<String>)> = batch
+            .iter()
+            .map(|(path, expected)| {
+                let problem = match self.inner.read(path, 0, -1) {
+                    Ok(data) if matches(expected, &data) == Some(false) => Some(format!(
+                        "checksum mismatch: expected {}, read {}",
+                        expected,
+                        checksum(&data)
+                    )),
+                    Ok(_) => None,
+                    Err(err) => Some(format!("unreadable: {}", err)),
+                };
+                (path.as_str(), problem)
+            })
+            .collect();
+
+        let mut scrub = self.scrub_state();
+        for (path, problem) in &checked {
+            match problem {
+                Some(problem) => scrub.damaged.insert(path.to_string(), problem.clone()),
+                None => scrub.damaged.remove(*path),
+            };
+        }
+        scrub.files += checked.len() as u64;
+        match start + batch.len() >= files.len() {
+            true => {
+                scrub.passes += 1;
+                scrub.cursor.clear();
+            }
+            false => scrub.cursor = batch.last().map(|(path, _)| path.clone()).unwrap_or_default(),
+        }
+        Ok(checked.len())
+    }
+
+    /// Whether a successful write of `data` at `offset` left the file holding
+    /// exactly `data`
+    fn replaced_whole(&self, path: &str, data: &[u8], offset: i64, flags: WriteFlag) -> bool {
+        offset == 0
+            && !flags.contains(WriteFlag::APPEND)
+            && (flags.contains(WriteFlag::TRUNCATE)
+                || self.inner.stat(path).is_ok_and(|info| info.size == data.len() as i64))
+    }
+}
+
+impl<F: FileSystem> FileSystem for VerifyFs<F> {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn readme(&self) -> &str {
+        self.inner.readme()
+    }
+
+    fn config_params(&self) -> Vec<ConfigParameter> {
+        let mut params = self.inner.config_params();
+        params.push(ConfigParameter::new(
+            SCRUB_FILES_CONFIG_KEY,
+            "int",
+            false,
+            DEFAULT_SCRUB_FILES.to_string(),
+            "Files whose checksums each maintain call verifies (0 disables scrubbing)",
+        ));
+        params
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    fn schema(&self) -> FsSchema {
+        self.inner.schema().path(
+            PathSchema::file(SCRUB_REPORT_PATH, "Files verified by scrubbing and the damaged ones")
+                .format("text/plain"),
+        )
+    }
+
+    fn validate(&self, config: &Config) -> Result<()> {
+        scrub_files(config)?;
+        self.inner.validate(config)
+    }
+
+    fn initialize(&mut self, config: &Config) -> Result<()> {
+        if let Some(files) = scrub_files(config)? {
+            self.scrub_files = files;
+        }
+        self.inner.initialize(config)
+    }
+
+    fn shutdown(&mut self) -> Result<()> {
+        self.inner.shutdown()
+    }
+
+    /// The plugin's stats plus the reads verified and failed, and the
+    /// progress of scrubbing
+    fn stats(&self) -> serde_json::Value {
+        let mut stats = match self.inner.stats() {
+            serde_json::Value::Null => serde_json::json!({}),
+            stats => stats,
+        };
+        if let serde_json::Value::Object(fields) = &mut stats {
+            fields.insert(
+                "checksum_verified".to_string(),
+                self.verified.load(Ordering::Relaxed).into(),
+            );
+            fields.insert(
+                "checksum_mismatches".to_string(),
+                self.mismatches.load(Ordering::Relaxed).into(),
+            );
+            let scrub = self.scrub_state();
+            fields.insert("scrub_files".to_string(), scrub.files.into());
+            fields.insert("scrub_passes".to_string(), scrub.passes.into());
+            fields.insert("scrub_damaged".to_string(), scrub.damaged.len().into());
+        }
+        stats
+    }
+
+    /// Unhealthy while scrubbing has found damaged files
+    fn health(&self) -> Result<()> {
+        self.inner.health()?;
+        match self.scrub_state().damaged.len() {
+            0 => Ok(()),
+            damaged => Err(Error::Io(format!(
+                "{} damaged files, see {}",
+                damaged, SCRUB_REPORT_PATH
+            ))),
+        }
+    }
+
+    /// `scrub` finishes the current scrubbing pass
+    fn ctl(&mut self, command: &str) -> Result<()> {
+        match command.trim() {
+            "scrub" => self.scrub(usize::MAX).map(drop),
+            _ => self.inner.ctl(command),
+        }
+    }
+
+    fn maintain(&mut self) -> Result<()> {
+        self.inner.maintain()?;
+        if self.scrub_files > 0 {
+            self.scrub(self.scrub_files)?;
+        }
+        Ok(())
+    }
+
+    fn warmup(&mut self, budget: Duration) -> Result<WarmupProgress> {
+        self.inner.warmup(budget)
+    }
+
+    fn export_state(&self) -> Result<Vec<u8>> {
+        self.inner.export_state()
+    }
+
+    fn import_state(&mut self, state: &[u8]) -> Result<()> {
+        self.inner.import_state(state)
+    }
+
+    fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+        if is_report(path) {
+            return Ok(read_range(self.report().as_bytes(), offset, size));
+        }
+        let Some(expected) = self.expected(path) else {
+            return self.inner.read(path, offset, size);
+        };
+        let data = self.inner.read(path, 0, -1)?;
+        self.check(path, &expected, &data)?;
+        match offset == 0 && size < 0 {
+            true => Ok(data),
+            false => Ok(read_range(&data, offset, size)),
+        }
+    }
+
+    fn poll_read(&self, path: &str, offset: i64, size: i64, timeout: Duration) -> Result<Vec<u8>> {
+        match is_report(path) || self.expected(path).is_some() {
+            true => self.read(path, offset, size),
+            false => self.inner.poll_read(path, offset, size, timeout),
+        }
+    }
+
+    fn stat(&self, path: &str) -> Result<FileInfo> {
+        match is_report(path) {
+            true => Ok(report_info()),
+            false => Ok(self.annotate(path, self.inner.stat(path)?)),
+        }
+    }
+
+    /// The plugin's entries, the root followed by the scrub report, which
+    /// hides a plugin entry of the same name
+    fn readdir(&self, path: &str) -> Result<Vec<FileInfo>> {
+        let dir = path.trim_end_matches('/');
+        let mut entries: Vec<FileInfo> = self
+            .inner
+            .readdir(path)?
+            .into_iter()
+            .map(|entry| {
+                let path = format!("{}/{}", dir, entry.name);
+                self.annotate(&path, entry)
+            })
+            .collect();
+        if is_root(path) {
+            let report = report_info();
+            entries.retain(|entry| entry.name != report.name);
+            entries.push(report);
+        }
+        Ok(entries)
+    }
+
+    fn readdir_page(&self, path: &str, offset: usize, limit: usize) -> Result<Vec<FileInfo>> {
+        match is_root(path) {
+            true => Ok(self.readdir(path)?.into_iter().skip(offset).take(limit).collect()),
+            false => self.inner.readdir_page(path, offset, limit),
+        }
+    }
+
+    fn readdir_filtered(&self, path: &str, glob: &str, limit: usize) -> Result<Vec<FileInfo>> {
+        match is_root(path) {
+            true => Ok(filter_entries(self.readdir(path)?, glob, limit)),
+            false => self.inner.readdir_filtered(path, glob, limit),
+        }
+    }
+
+    fn walk(&self, path: &str, depth: usize) -> Result<Vec<(String, FileInfo)>> {
+        match is_root(path) {
+            true => walk_tree(self, path, depth),
+            false => self.inner.walk(path, depth),
+        }
+    }
+
+    fn dir_generation(&self, path: &str) -> u64 {
+        self.inner.dir_generation(path)
+    }
+
+    /// Writing a whole file records the checksum of its new content; any
+    /// other write leaves it to the plugin
+    fn write(&mut self, path: &str, data: &[u8], offset: i64, flags: WriteFlag) -> Result<i64> {
+        if is_report(path) {
+            return Err(Error::PermissionDenied);
+        }
+        self.forget(path);
+        let written = self.inner.write(path, data, offset, flags)?;
+        if self.replaced_whole(path, data, offset, flags) {
+            self.record(path, data);
+        }
+        Ok(written)
+    }
+
+    fn create(&mut self, path: &str) -> Result<()> {
+        if is_report(path) {
+            return Err(Error::PermissionDenied);
+        }
+        self.forget(path);
+        self.inner.create(path)
+    }
+
+    fn mkdir(&mut self, path: &str, perm: u32) -> Result<()> {
+        match is_report(path) {
+            true => Err(Error::AlreadyExists),
+            false => self.inner.mkdir(path, perm),
+        }
+    }
+
+    fn remove(&mut self, path: &str) -> Result<()> {
+        if is_report(path) {
+            return Err(Error::PermissionDenied);
+        }
+        self.inner.remove(path)?;
+        self.forget(path);
+        Ok(())
+    }
+
+    fn remove_all(&mut self, path: &str) -> Result<()> {
+        if is_report(path) {
+            return Err(Error::PermissionDenied);
+        }
+        self.inner.remove_all(path)?;
+        self.forget(path);
+        Ok(())
+    }
+
+    fn rename(&mut self, old_path: &str, new_path: &str) -> Result<()> {
+        if is_report(old_path) || is_report(new_path) {
+            return Err(Error::PermissionDenied);
+        }
+        self.inner.rename(old_path, new_path)?;
+        self.moved(old_path, new_path);
+        Ok(())
+    }
+
+    fn chmod(&mut self, path: &str, mode: u32) -> Result<()> {
+        match is_report(path) {
+            true => Err(Error::PermissionDenied),
+            false => self.inner.chmod(path, mode),
+        }
+    }
+
+    fn symlink(&mut self, target: &str, link_path: &str) -> Result<()> {
+        match is_report(link_path) {
+            true => Err(Error::AlreadyExists),
+            false => self.inner.symlink(target, link_path),
+        }
+    }
+
+    fn readlink(&self, path: &str) -> Result<String> {
+        match is_report(path) {
+            true => Err(Error::InvalidInput("not a symlink".to_string())),
+            false => self.inner.readlink(path),
+        }
+    }
+
+    fn get_xattr(&self, path: &str, name: &str) -> Result<Vec<u8>> {
+        match is_report(path) {
+            true => Err(Error::NoAttribute),
+            false => self.inner.get_xattr(path, name),
+        }
+    }
+
+    fn set_xattr(&mut self, path: &str, name: &str, value: &[u8]) -> Result<()> {
+        match is_report(path) {
+            true => Err(Error::PermissionDenied),
+            false => self.inner.set_xattr(path, name, value),
+        }
+    }
+
+    fn list_xattr(&self, path: &str) -> Result<Vec<String>> {
+        match is_report(path) {
+            true => Ok(Vec::new()),
+            false => self.inner.list_xattr(path),
+        }
+    }
+
+    /// The scrub report has no etag of its own and is always rendered
+    fn read_if_changed(&self, path: &str, etag: &str) -> Result<Option<Vec<u8>>> {
+        if is_report(path) {
+            return self.read(path, 0, -1).map(Some);
+        }
+        let data = self.inner.read_if_changed(path, etag)?;
+        if let (Some(data), Some(expected)) = (&data, self.expected(path)) {
+            self.check(path, &expected, data)?;
+        }
+        Ok(data)
+    }
+
+    fn read_many(&self, paths: &[&str]) -> Vec<Result<Vec<u8>>> {
+        let results = split_batch(
+            paths,
+            |path| is_report(path).then(|| self.read(path, 0, -1)),
+            |rest| self.inner.read_many(rest),
+        );
+        paths
+            .iter()
+            .zip(results)
+            .map(|(path, result)| {
+                let data = result?;
+                if is_report(path) {
+                    return Ok(data);
+                }
+                if let Some(expected) = self.expected(path) {
+                    self.check(path, &expected, &data)?;
+                }
+                Ok(data)
+            })
+            .collect()
+    }
+
+    fn write_if(&mut self, path: &str, data: &[u8], expected_etag: &str) -> Result<i64> {
+        if is_report(path) {
+            return Err(Error::PermissionDenied);
+        }
+        let written = self.inner.write_if(path, data, expected_etag)?;
+        self.record(path, data);
+        Ok(written)
+    }
+
+    /// The scrub report is read-only, so a batch touching it is rejected as
+    /// a whole
+    fn batch(&mut self, ops: Vec<FsOp>) -> Vec<Result<()>> {
+        if let Some(i) = ops.iter().position(touches_report) {
+            return reject_batch(ops.len(), i, Error::PermissionDenied);
+        }
+        // Replayed after the batch, as it consumes the operations
+        let effects: Vec<FsOp> = ops
+            .iter()
+            .map(|op| match op {
+                FsOp::Write { path, data } => FsOp::Write {
+                    path: path.clone(),
+                    data: checksum(data).into_bytes(),
+                },
+                op => op.clone(),
+            })
+            .collect();
+        let results = self.inner.batch(ops);
+        for (op, result) in effects.into_iter().zip(&results) {
+            match op {
+                FsOp::Write { path, data } if result.is_ok() => {
+                    let sum = String::from_utf8(data).unwrap_or_default();
+                    self.written().insert(path, sum);
+                }
+                FsOp::Write { path, .. } | FsOp::Create { path } => self.forget(&path),
+                FsOp::Remove { path } | FsOp::RemoveAll { path } if result.is_ok() => self.forget(&path),
+                FsOp::Rename { old_path, new_path } if result.is_ok() => self.moved(&old_path, &new_path),
+                _ => {}
+            }
+        }
+        results
+    }
+
+    fn atomic_batch(&self) -> bool {
+        self.inner.atomic_batch()
+    }
+
+    fn begin_write_session(&mut self) -> Result<()> {
+        self.inner.begin_write_session()
+    }
+
+    fn commit_session(&mut self, message: &str) -> Result<()> {
+        self.inner.commit_session(message)
+    }
+
+    fn abort_session(&mut self) -> Result<()> {
+        self.inner.abort_session()
+    }
+
+    fn advise(&self, path: &str, offset: i64, len: i64, advice: Advice) -> Result<()> {
+        match is_report(path) {
+            true => Ok(()),
+            false => self.inner.advise(path, offset, len, advice),
+        }
+    }
+}
+
+/// Handles are passed on unchecked; opening one for writing drops the
+/// checksum recorded for the file. The scrub report is read by path.
+impl<F: HandleFS> HandleFS for VerifyFs<F> {
+    fn open_handle(&mut self, path: &str, flags: OpenFlag, mode: u32) -> Result<i64> {
+        if is_report(path) {
+            return Err(Error::PermissionDenied);
+        }
+        if flags.is_writable() {
+            self.forget(path);
+        }
+        self.inner.open_handle(path, flags, mode)
+    }
+
+    fn handle_read(&mut self, id: i64, buf: &mut [u8]) -> Result<usize> {
+        self.inner.handle_read(id, buf)
+    }
+
+    fn handle_read_at(&self, id: i64, buf: &mut [u8], offset: i64) -> Result<usize> {
+        self.inner.handle_read_at(id, buf, offset)
+    }
+
+    fn handle_write(&mut self, id: i64, data: &[u8]) -> Result<usize> {
+        self.inner.handle_write(id, data)
+    }
+
+    fn handle_write_at(&self, id: i64, data: &[u8], offset: i64) -> Result<usize> {
+        self.inner.handle_write_at(id, data, offset)
+    }
+
+    fn handle_seek(&mut self, id: i64, offset: i64, whence: i32) -> Result<i64> {
+        self.inner.handle_seek(id, offset, whence)
+    }
+
+    fn handle_sync(&self, id: i64) -> Result<()> {
+        self.inner.handle_sync(id)
+    }
+
+    fn handle_stat(&self, id: i64) -> Result<FileInfo> {
+        self.inner.handle_stat(id)
+    }
+
+    fn handle_info(&self, id: i64) -> Result<(String, OpenFlag)> {
+        self.inner.handle_info(id)
+    }
+
+    fn close_handle(&mut self, id: i64) -> Result<()> {
+        self.inner.close_handle(id)
+    }
+}
+
+/// Streams may write, so opening one drops the checksum recorded for the file
+impl<F: StreamFS> StreamFS for VerifyFs<F> {
+    fn open_stream(&mut self, path: &str) -> Result<i64> {
+        if is_report(path) {
+            return Err(Error::PermissionDenied);
+        }
+        self.forget(path);
+        self.inner.open_stream(path)
+    }
+
+    fn stream_read(&mut self, id: i64, buf: &mut [u8]) -> Result<Option<usize>> {
+        self.inner.stream_read(id, buf)
+    }
+
+    fn stream_write(&mut self, id: i64, data: &[u8]) -> Result<usize> {
+        self.inner.stream_write(id, data)
+    }
+
+    fn close_stream(&mut self, id: i64) -> Result<()> {
+        self.inner.close_stream(id)
+    }
+}
+
+impl<F: UploadFS> UploadFS for VerifyFs<F> {
+    fn begin_upload(&mut self, path: &str) -> Result<UploadSession> {
+        if is_report(path) {
+            return Err(Error::PermissionDenied);
+        }
+        self.forget(path);
+        self.inner.begin_upload(path)
+    }
+
+    fn upload_status(&self, id: &str) -> Result<UploadSession> {
+        self.inner.upload_status(id)
+    }
+
+    fn upload_append(&mut self, id: &str, offset: u64, data: &[u8]) -> Result<UploadSession> {
+        self.inner.upload_append(id, offset, data)
+    }
+
+    fn commit_upload(&mut self, id: &str) -> Result<()> {
+        self.inner.commit_upload(id)
+    }
+
+    fn abort_upload(&mut self, id: &str) -> Result<()> {
+        self.inner.abort_upload(id)
+    }
+
+    fn list_uploads(&self) -> Result<Vec<UploadSession>> {
+        self.inner.list_uploads()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    /// Stores files, flipping a bit of every read of `/rotten`
+    #[derive(Default)]
+    struct RotFS {
+        files: BTreeMap<String, Vec<u8>>,
+    }
+
+    impl FileSystem for RotFS {
+        fn name(&self) -> &str {
+            "rotfs"
+        }
+
+        fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+            let mut data = self.files.get(path).ok_or(Error::NotFound)?.clone();
+            if path == "/rotten" {
+                data[0] ^= 1;
+            }
+            Ok(read_range(&data, offset, size))
+        }
+
+        fn stat(&self, path: &str) -> Result<FileInfo> {
+            let data = self.files.get(path).ok_or(Error::NotFound)?;
+            let info = FileInfo::file(&path[1..], data.len() as i64, 0o644);
+            match path {
+                "/signed" => Ok(info.with_checksum("CRC32C:E3069283")),
+                _ => Ok(info),
+            }
+        }
+
+        fn readdir(&self, _path: &str) -> Result<Vec<FileInfo>> {
+            self.files.keys().map(|path| self.stat(path)).collect()
+        }
+
+        fn write(&mut self, path: &str, data: &[u8], _offset: i64, _flags: WriteFlag) -> Result<i64> {
+            self.files.insert(path.to_string(), data.to_vec());
+            Ok(data.len() as i64)
+        }
+    }
+
+    #[test]
+    fn test_crc32c() {
+        assert_eq!(crc32c(b""), 0);
+        assert_eq!(crc32c(b"123456789"), 0xe306_9283);
+        assert_eq!(checksum(b"123456789"), "crc32c:e3069283");
+        assert_eq!(matches("crc32c:e3069283", b"123456789"), Some(true));
+        assert_eq!(matches("crc32c:e3069283", b"12345678"), Some(false));
+        assert_eq!(matches("sha256:00", b""), None);
+    }
+
+    #[test]
+    fn test_verify_fs() {
+        let mut fs = VerifyFs::<RotFS>::default();
+        fs.write("/signed", b"123456789", 0, WriteFlag::NONE).unwrap();
+        fs.write("/rotten", b"data", 0, WriteFlag::TRUNCATE).unwrap();
+        assert_eq!(fs.read("/signed", 2, 3).unwrap(), b"345");
+        assert_eq!(fs.stat("/rotten").unwrap().checksum.unwrap(), checksum(b"data"));
+        assert!(matches!(fs.read("/rotten", 0, -1), Err(Error::Io(_))));
+        assert_eq!(fs.stats()["checksum_mismatches"], 1);
+
+        // Corruption the plugin itself reports a checksum for
+        fs.inner.files.insert("/signed".to_string(), b"123456780".to_vec());
+        assert_eq!(fs.read("/signed", 0, -1).unwrap_err().code(), 5);
+    }
+
+    #[test]
+    fn test_scrub() {
+        let mut fs = VerifyFs::<RotFS>::default();
+        fs.initialize(&Config::from(serde_json::json!({ SCRUB_FILES_CONFIG_KEY: 2 })))
+            .unwrap();
+        fs.write("/a", b"intact", 0, WriteFlag::TRUNCATE).unwrap();
+        fs.write("/rotten", b"data", 0, WriteFlag::TRUNCATE).unwrap();
+        fs.write("/signed", b"123456789", 0, WriteFlag::TRUNCATE).unwrap();
+        fs.inner.files.insert("/unsigned".to_string(), b"?".to_vec());
+
+        fs.maintain().unwrap();
+        assert!(fs.health().is_err());
+        let report = String::from_utf8(fs.read(SCRUB_REPORT_PATH, 0, -1).unwrap()).unwrap();
+        assert!(report.contains("passes completed: 0\n"));
+        assert!(report.contains("/rotten: checksum mismatch"));
+
+        // The last file with a checksum completes the pass
+        assert_eq!(fs.scrub(usize::MAX).unwrap(), 1);
+        assert_eq!(fs.stats()["scrub_passes"], 1);
+        assert_eq!(fs.stats()["scrub_files"], 3);
+
+        // Rewriting a damaged file clears it until it is scrubbed again
+        fs.write("/rotten", b"data", 0, WriteFlag::TRUNCATE).unwrap();
+        assert!(fs.health().is_ok());
+        assert!(fs
+            .readdir("/")
+            .unwrap()
+            .iter()
+            .any(|entry| entry.name == ".scrub-report"));
+        assert_eq!(
+            fs.write(SCRUB_REPORT_PATH, b"", 0, WriteFlag::NONE),
+            Err(Error::PermissionDenied)
+        );
+    }
+}
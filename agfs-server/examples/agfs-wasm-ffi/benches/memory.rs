@@ -0,0 +1,54 @@
+//! Benchmarks for returning results to the host
+//!
+//! Compares copying a read result into a freshly allocated block (the
+//! `fs_read` path) with draining it through the 64KB shared output buffer
+//! (`fs_read_chunked` + `read_chunk`). Run with `cargo bench`; this measures
+//! the copies natively, without the wasm runtime.
+
+use agfs_core::bench::BenchFs;
+use agfs_wasm_ffi::memory::{Buffer, ChunkedResults};
+use agfs_wasm_ffi::FileSystem;
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use std::hint::black_box;
+
+const READ_SIZES: [usize; 4] = [4 << 10, 64 << 10, 1 << 20, 16 << 20];
+const SHARED_BUFFER_SIZE: usize = 65536;
+
+fn bench_result_transfer(c: &mut Criterion) {
+    let fs = BenchFs::new(*READ_SIZES.last().unwrap(), 0);
+    let mut out = vec![0u8; SHARED_BUFFER_SIZE];
+    let mut chunks = ChunkedResults::new();
+
+    let mut group = c.benchmark_group("read_result");
+    for size in READ_SIZES {
+        let data = fs.read("/file", 0, size as i64).unwrap();
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::new("malloc", size), &data, |b, data| {
+            b.iter_batched(
+                || data.clone(),
+                |data| black_box(Buffer::from_bytes(&data)),
+                BatchSize::LargeInput,
+            );
+        });
+        group.bench_with_input(BenchmarkId::new("shared_buffer", size), &data, |b, data| {
+            b.iter_batched(
+                || data.clone(),
+                |data| {
+                    let (mut len, mut token) = chunks.start(data, &mut out);
+                    let mut total = len as usize;
+                    while token != 0 {
+                        (len, token) = chunks.next(token, &mut out).unwrap();
+                        total += len as usize;
+                    }
+                    assert_eq!(total, size);
+                    black_box(&out);
+                },
+                BatchSize::LargeInput,
+            );
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_result_transfer);
+criterion_main!(benches);
@@ -0,0 +1,25 @@
+//! Archives and scheduled exports on the host clock
+//!
+//! Re-exports [`ArchiveView`] and [`ExportScheduler`] and builds them in WASM,
+//! where a plugin has no clock of its own. Jobs run from `maintain`, so they
+//! need a mount with a `maintain_interval`:
+//!
+//! ```ignore
+//! // In the plugin struct
+//! archive: ArchiveView = archive::view().keep(30),
+//! exports: ExportScheduler = archive::new().job("frontpage", Duration::from_secs(3600)).keep_days(30),
+//! ```
+
+use crate::clock;
+
+pub use agfs_core::archive::{date, is_archive_path, ArchiveView, ExportScheduler, ARCHIVE_DIR};
+
+/// A scheduler reading the time from the host
+pub fn new() -> ExportScheduler {
+    ExportScheduler::with_clock(clock::now)
+}
+
+/// A daily archive reading the time from the host
+pub fn view() -> ArchiveView {
+    ArchiveView::with_clock(clock::now)
+}
@@ -0,0 +1,23 @@
+//! Circuit breakers on the host clock
+//!
+//! Re-exports [`CircuitBreaker`] and builds it in WASM, where a plugin has no
+//! clock of its own: [`new`] reads the time from the host, so the cooldown
+//! runs across operations and instances.
+//!
+//! ```ignore
+//! let breaker = breaker::new(5, Duration::from_secs(30));
+//! let body = breaker.call_or(
+//!     || Ok(Http::get(&url)?.text()?),
+//!     |_| self.cache.get(&url).ok_or(Error::NotFound),
+//! )?;
+//! ```
+
+use crate::clock;
+use std::time::Duration;
+
+pub use agfs_core::breaker::{is_upstream_failure, CircuitBreaker, CircuitState};
+
+/// A breaker opening after `threshold` failures in a row, for `cooldown`
+pub fn new(threshold: u32, cooldown: Duration) -> CircuitBreaker {
+    CircuitBreaker::with_clock(threshold, cooldown, clock::now)
+}
@@ -0,0 +1,99 @@
+//! Build metadata embedded in every plugin
+//!
+//! `export_plugin!` records the SDK version, the plugin crate's version, the
+//! git commit and the build time as JSON, both in an `agfs_build_info`
+//! custom section of the wasm file and behind a `plugin_build_info` export,
+//! so operators can tell exactly which code is mounted:
+//!
+//! ```text
+//! {"sdk_version":"1.4.0","version":"0.3.1","git_hash":"9f2c1e7","build_time":"1760572800"}
+//! ```
+//!
+//! The commit comes from `AGFS_GIT_HASH` and the build time from
+//! `SOURCE_DATE_EPOCH` (seconds), both read at compile time. Neither is
+//! taken from the clock or the checkout, so the same inputs give the same
+//! wasm; each is left out when its variable is unset.
+//!
+//! The JSON is assembled at compile time, so values may not contain `"`,
+//! `\` or control characters; such a value fails the build.
+
+use crate::manifest::SDK_VERSION;
+
+/// Name of the custom section holding the build info
+pub const SECTION: &str = "agfs_build_info";
+
+/// Pieces of the build info JSON, for `export_plugin!`
+#[doc(hidden)]
+pub const fn parts(
+    version: &'static str,
+    git_hash: Option<&'static str>,
+    build_time: Option<&'static str>,
+) -> [&'static str; 11] {
+    let (git_hash_key, git_hash, git_hash_end) = match git_hash {
+        Some(hash) if !hash.is_empty() => (",\"git_hash\":\"", plain(hash), "\""),
+        _ => ("", "", ""),
+    };
+    let (build_time_key, build_time, build_time_end) = match build_time {
+        Some(time) if !time.is_empty() => (",\"build_time\":\"", plain(time), "\""),
+        _ => ("", "", ""),
+    };
+    [
+        "{\"sdk_version\":\"",
+        plain(SDK_VERSION),
+        "\",\"version\":\"",
+        plain(version),
+        "\"",
+        git_hash_key,
+        git_hash,
+        git_hash_end,
+        build_time_key,
+        build_time,
+        build_time_end,
+    ]
+}
+
+/// Length of the JSON made of `parts` and its closing brace
+#[doc(hidden)]
+pub const fn len(parts: &[&str]) -> usize {
+    let mut total = 1;
+    let mut i = 0;
+    while i < parts.len() {
+        total += parts[i].len();
+        i += 1;
+    }
+    total
+}
+
+/// The JSON made of `parts`, as the bytes of a custom section
+#[doc(hidden)]
+pub const fn json<const N: usize>(parts: &[&str]) -> [u8; N] {
+    let mut out = [0u8; N];
+    let mut at = 0;
+    let mut i = 0;
+    while i < parts.len() {
+        let bytes = parts[i].as_bytes();
+        let mut j = 0;
+        while j < bytes.len() {
+            out[at] = bytes[j];
+            at += 1;
+            j += 1;
+        }
+        i += 1;
+    }
+    out[at] = b'}';
+    out
+}
+
+/// `value`, failing the build if it would need escaping in JSON
+const fn plain(value: &'static str) -> &'static str {
+    let bytes = value.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        assert!(
+            bytes[i] != b'"' && bytes[i] != b'\\' && bytes[i] >= 0x20,
+            "build info values may not contain quotes, backslashes or control characters"
+        );
+        i += 1;
+    }
+    value
+}
@@ -0,0 +1,16 @@
+//! Wall-clock time from the host
+//!
+//! WASM plugins have no clock of their own. [`now`] asks the host, for
+//! anything timed across operations: breaker cooldowns, token expiry.
+
+use std::time::Duration;
+
+host_imports! {
+    fn host_now_ms() -> i64;
+}
+
+/// Time since the Unix epoch, as the host sees it
+pub fn now() -> Duration {
+    let ms = unsafe { host_now_ms() };
+    Duration::from_millis(ms.max(0) as u64)
+}
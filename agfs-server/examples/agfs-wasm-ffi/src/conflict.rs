@@ -0,0 +1,20 @@
+//! Conflict tracking on the host clock
+//!
+//! Re-exports [`ConflictTracker`] and builds it in WASM, where a plugin has no
+//! clock of its own to name conflicted copies by:
+//!
+//! ```ignore
+//! fn initialize(&mut self, config: &Config) -> Result<()> {
+//!     self.conflicts = conflict::tracker(ConflictPolicy::from_config(config)?);
+//!     Ok(())
+//! }
+//! ```
+
+use crate::clock;
+
+pub use agfs_core::conflict::{conflicted_copy, ConflictPolicy, ConflictTracker, Target, CONFLICT_POLICY_CONFIG_KEY};
+
+/// A tracker applying `policy`, reading the time from the host
+pub fn tracker(policy: ConflictPolicy) -> ConflictTracker {
+    ConflictTracker::with_clock(policy, clock::now)
+}
@@ -0,0 +1,74 @@
+//! Hashing and message authentication on the host
+//!
+//! The host computes digests and RSA signatures, so plugins signing requests
+//! (`sigv4`, `jwt`) do not compile a crypto library into their module.
+
+use crate::memory::read_packed_response;
+use crate::types::{Error, Result};
+
+host_imports! {
+    fn host_crypto_sha256(data: *const u8, len: u32) -> u64;
+    fn host_crypto_hmac_sha256(key: *const u8, key_len: u32, data: *const u8, data_len: u32) -> u64;
+    fn host_crypto_rsa_sign_sha256(key: *const u8, key_len: u32, data: *const u8, data_len: u32) -> u64;
+    fn host_crypto_rsa_verify_sha256(
+        key: *const u8,
+        key_len: u32,
+        data: *const u8,
+        data_len: u32,
+        sig: *const u8,
+        sig_len: u32,
+    ) -> u32;
+}
+
+/// SHA-256 digest of `data`
+pub fn sha256(data: &[u8]) -> Result<[u8; 32]> {
+    digest("sha256", unsafe { host_crypto_sha256(data.as_ptr(), data.len() as u32) })
+}
+
+/// HMAC-SHA256 of `data` under `key`
+pub fn hmac_sha256(key: &[u8], data: &[u8]) -> Result<[u8; 32]> {
+    let result = unsafe { host_crypto_hmac_sha256(key.as_ptr(), key.len() as u32, data.as_ptr(), data.len() as u32) };
+    digest("hmac-sha256", result)
+}
+
+/// RSASSA-PKCS1-v1_5 signature of the SHA-256 of `data` (RS256), under a
+/// PEM private key (PKCS#1 or PKCS#8)
+pub fn rsa_sign_sha256(private_key_pem: &str, data: &[u8]) -> Result<Vec<u8>> {
+    let key = private_key_pem.as_bytes();
+    let result =
+        unsafe { host_crypto_rsa_sign_sha256(key.as_ptr(), key.len() as u32, data.as_ptr(), data.len() as u32) };
+    unsafe { read_packed_response(result) }
+        .ok_or_else(|| Error::InvalidInput("RSA signing failed; is the key an RSA private key?".to_string()))
+}
+
+/// Whether `signature` is a valid [`rsa_sign_sha256`] signature of `data`
+/// for a PEM public key or certificate
+pub fn rsa_verify_sha256(public_key_pem: &str, data: &[u8], signature: &[u8]) -> Result<bool> {
+    let key = public_key_pem.as_bytes();
+    let result = unsafe {
+        host_crypto_rsa_verify_sha256(
+            key.as_ptr(),
+            key.len() as u32,
+            data.as_ptr(),
+            data.len() as u32,
+            signature.as_ptr(),
+            signature.len() as u32,
+        )
+    };
+    match result {
+        0 => Ok(true),
+        1 => Ok(false),
+        _ => Err(Error::InvalidInput("invalid RSA public key".to_string())),
+    }
+}
+
+/// Lowercase hex encoding of `bytes`
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn digest(name: &str, result: u64) -> Result<[u8; 32]> {
+    unsafe { read_packed_response(result) }
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| Error::Other(format!("host {} failed", name)))
+}
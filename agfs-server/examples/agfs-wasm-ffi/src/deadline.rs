@@ -0,0 +1,40 @@
+//! Per-operation deadlines set by the host
+//!
+//! agfs-server can bound every plugin operation with a timeout. The SDK
+//! checks the time left before each `HostFS` and `Http` call and shortens
+//! HTTP timeouts to fit, so a hung upstream fails the operation with
+//! [`Error::TimedOut`] instead of wedging the mount until the instance is
+//! killed. Plugins doing long work of their own can poll [`check`] between
+//! steps.
+
+use crate::types::{Error, Result};
+use std::time::Duration;
+
+host_imports! {
+    fn host_deadline_ms() -> i64;
+}
+
+/// Time left for the current operation, or `None` if it has no deadline
+pub fn remaining() -> Option<Duration> {
+    let ms = unsafe { host_deadline_ms() };
+    (ms >= 0).then(|| Duration::from_millis(ms as u64))
+}
+
+/// Fail with `TimedOut` once the current operation is out of time
+pub fn check() -> Result<()> {
+    match remaining() {
+        Some(left) if left.is_zero() => Err(Error::TimedOut),
+        _ => Ok(()),
+    }
+}
+
+/// Clamp an HTTP timeout in seconds to the time left, failing if none is
+#[cfg(feature = "http")]
+pub(crate) fn http_timeout(seconds: i32) -> Result<i32> {
+    check()?;
+    Ok(match remaining() {
+        // Round up so a sub-second remainder still gets a request off
+        Some(left) => seconds.min(left.as_millis().div_ceil(1000).min(i32::MAX as u128) as i32),
+        None => seconds,
+    })
+}
@@ -0,0 +1,95 @@
+//! Content-addressed cache of downloaded files
+//!
+//! Articles, PDFs and other assets a plugin fetches on read are often read
+//! again: every `cat` of a story, every reader of the same paper. A
+//! [`DownloadCache`] keeps each downloaded body once in a
+//! [`HostCacheDir`], named by its SHA-256, and maps URLs to those digests,
+//! so a URL is downloaded once and two URLs serving the same bytes share
+//! one copy. The least recently used bodies are evicted at the byte budget.
+//!
+//! ```ignore
+//! let downloads = DownloadCache::open("/var/cache/agfs/hackernews", 256 << 20)?;
+//! let article = downloads.get(&story.url)?;
+//! ```
+
+use crate::crypto::{hex, sha256};
+use crate::host_cache::HostCacheDir;
+use crate::host_http::Http;
+use crate::types::Result;
+
+/// Downloaded bodies in a host cache directory, keyed by content
+pub struct DownloadCache {
+    dir: HostCacheDir,
+}
+
+impl DownloadCache {
+    /// Open (creating if needed) the cache at host path `dir`, holding at
+    /// most `max_bytes` of bodies and URL entries
+    pub fn open(dir: &str, max_bytes: u64) -> Result<Self> {
+        Ok(Self {
+            dir: HostCacheDir::open(dir, max_bytes)?,
+        })
+    }
+
+    /// The body at `url`, downloaded with a GET only if not cached
+    ///
+    /// Non-2xx responses are errors and are not cached. A body that cannot
+    /// be stored, e.g. one larger than the budget, is still returned.
+    pub fn get(&self, url: &str) -> Result<Vec<u8>> {
+        if let Some(body) = self.cached(url)? {
+            return Ok(body);
+        }
+        let response = Http::get(url)?;
+        response.error_for_status()?;
+        let _ = self.put(url, &response.body);
+        Ok(response.body)
+    }
+
+    /// The cached body of `url`, without downloading it
+    pub fn cached(&self, url: &str) -> Result<Option<Vec<u8>>> {
+        let Some(digest) = self.dir.get(&url_key(url)?)? else {
+            return Ok(None);
+        };
+        let digest = String::from_utf8_lossy(&digest).into_owned();
+        match self.dir.get(&blob_key(&digest))? {
+            Some(body) => Ok(Some(body)),
+            // The body was evicted; the URL entry is of no use without it
+            None => {
+                self.dir.remove(&url_key(url)?)?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// Store `body` as the content of `url`, sharing the copy of any other
+    /// URL with the same bytes
+    pub fn put(&self, url: &str, body: &[u8]) -> Result<()> {
+        let digest = hex(&sha256(body)?);
+        let blob = blob_key(&digest);
+        if !self.dir.contains(&blob) {
+            self.dir.put(&blob, body)?;
+        }
+        self.dir.put(&url_key(url)?, digest.as_bytes())
+    }
+
+    /// Forget `url`; its body stays until evicted, as other URLs may share it
+    pub fn remove(&self, url: &str) -> Result<()> {
+        self.dir.remove(&url_key(url)?)
+    }
+
+    /// Bytes currently held
+    pub fn used_bytes(&self) -> u64 {
+        self.dir.used_bytes()
+    }
+}
+
+/// Key of the entry holding the digest of `url`'s body; hashed, as URLs
+/// can be longer than a file name
+fn url_key(url: &str) -> Result<String> {
+    Ok(format!("url-{}", hex(&sha256(url.as_bytes())?)))
+}
+
+/// Key of the body with SHA-256 `digest`
+fn blob_key(digest: &str) -> String {
+    format!("blob-{}", digest)
+}
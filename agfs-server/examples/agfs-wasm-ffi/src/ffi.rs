@@ -4,17 +4,45 @@
 //! C-compatible types and safe Rust types.
 
 use crate::memory::{pack_u64, Buffer, CString};
-use crate::types::{Config, Error, FileInfo, Result, WriteFlag};
+use crate::types::{Capabilities, Config, Error, FileInfo, Result, WriteFlag};
 use crate::FileSystem;
+use agfs_core::redact::redact;
+
+/// Hand the plugin's declared capabilities to the host clients
+///
+/// Called by `export_plugin!`; only the HTTP client uses them.
+#[doc(hidden)]
+pub fn declare_capabilities(capabilities: Capabilities) {
+    #[cfg(feature = "http")]
+    crate::host_http::declare(capabilities);
+    #[cfg(not(feature = "http"))]
+    let _ = capabilities;
+}
+
+/// Hand the plugin config to the host clients
+///
+/// Called by `export_plugin!` when the plugin is initialized.
+#[doc(hidden)]
+pub fn configure_host(config: &Config) {
+    #[cfg(feature = "http")]
+    crate::host_http::configure(config);
+    #[cfg(not(feature = "http"))]
+    let _ = config;
+}
 
 /// Convert a Result to an error pointer (null = success)
 pub fn result_to_error_ptr<T>(result: Result<T>) -> *mut u8 {
     match result {
         Ok(_) => CString::null(),
-        Err(e) => CString::new(&e.to_string()).into_raw(),
+        Err(e) => error_ptr(&e),
     }
 }
 
+/// Hand the message of `err` to the host with secrets scrubbed
+pub fn error_ptr(err: &Error) -> *mut u8 {
+    CString::new(&redact(&err.to_string())).into_raw()
+}
+
 /// Read config from JSON pointer
 pub fn read_config(config_ptr: *const u8) -> Result<Config> {
     if config_ptr.is_null() {
@@ -46,6 +74,37 @@ pub fn fileinfo_vec_to_json_ptr(infos: &[FileInfo]) -> Result<*mut u8> {
     Ok(CString::new(&json).into_raw())
 }
 
+/// Serialize an upload result to JSON
+/// Returns packed u64: low 32 bits = json pointer, high 32 bits = error ptr (0 = success)
+pub fn upload_result_to_packed<T: serde::Serialize>(result: Result<T>) -> u64 {
+    let json = result.and_then(|value| {
+        serde_json::to_string(&value).map_err(|e| Error::Other(format!("JSON serialization failed: {}", e)))
+    });
+    match json {
+        Ok(json) => pack_u64(CString::new(&json).into_raw() as u32, 0),
+        Err(e) => pack_u64(0, error_ptr(&e) as u32),
+    }
+}
+
+/// Serialize a walk result as `[{"Path": ..., "Info": ...}]`
+/// Returns packed u64: low 32 bits = json pointer, high 32 bits = error ptr (0 = success)
+pub fn walk_to_packed(result: Result<Vec<(String, FileInfo)>>) -> u64 {
+    #[derive(serde::Serialize)]
+    #[serde(rename_all = "PascalCase")]
+    struct WalkEntry<'a> {
+        path: &'a str,
+        info: &'a FileInfo,
+    }
+
+    match result {
+        Ok(entries) => upload_result_to_packed(Ok(entries
+            .iter()
+            .map(|(path, info)| WalkEntry { path, info })
+            .collect::<Vec<_>>())),
+        Err(e) => pack_u64(0, error_ptr(&e) as u32),
+    }
+}
+
 /// Handle fs_read FFI call
 pub fn handle_read<FS: FileSystem>(fs: &FS, path_ptr: *const u8, offset: i64, size: i64) -> u64 {
     let path = unsafe { CString::from_ptr(path_ptr) };
@@ -61,6 +120,55 @@ pub fn handle_read<FS: FileSystem>(fs: &FS, path_ptr: *const u8, offset: i64, si
     }
 }
 
+/// Handle fs_read_many FFI call; `paths_ptr` is a JSON array of paths
+///
+/// Each result is framed as a tag byte (0 = data, 1 = error message), a
+/// little-endian u32 length and that many bytes, in path order.
+/// Returns packed u64: low 32 bits = buffer ptr, high 32 bits = buffer length (0 = invalid input)
+///
+/// # Safety
+///
+/// `paths_ptr` must point to a NUL-terminated string in plugin memory.
+pub unsafe fn handle_read_many<FS: FileSystem + ?Sized>(fs: &FS, paths_ptr: *const u8) -> u64 {
+    let paths_json = CString::from_ptr(paths_ptr);
+    let Ok(paths) = serde_json::from_str::<Vec<String>>(&paths_json) else {
+        return 0;
+    };
+    let paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+
+    let mut framed = Vec::new();
+    for result in fs.read_many(&paths) {
+        let (tag, body) = match result {
+            Ok(data) => (0u8, data),
+            Err(e) => (1u8, e.to_string().into_bytes()),
+        };
+        framed.push(tag);
+        framed.extend_from_slice(&(body.len() as u32).to_le_bytes());
+        framed.extend_from_slice(&body);
+    }
+    if framed.is_empty() {
+        return 0;
+    }
+    let len = framed.len() as u32;
+    pack_u64(Buffer::from_bytes(&framed).into_raw() as u32, len)
+}
+
+/// Handle fs_batch FFI call; `ops_ptr` is a JSON array of [`FsOp`](crate::types::FsOp)s
+///
+/// Returns packed u64: low 32 bits = json ptr (one entry per operation,
+/// `null` or `{"code", "message"}`), high 32 bits = error ptr
+///
+/// # Safety
+///
+/// `ops_ptr` must point to a NUL-terminated string in plugin memory.
+pub unsafe fn handle_batch<FS: FileSystem + ?Sized>(fs: &mut FS, ops_ptr: *const u8) -> u64 {
+    let ops_json = CString::from_ptr(ops_ptr);
+    match serde_json::from_str(&ops_json) {
+        Ok(ops) => upload_result_to_packed(Ok(agfs_core::filesystem::batch_results_json(&fs.batch(ops)))),
+        Err(e) => pack_u64(0, error_ptr(&Error::InvalidInput(format!("invalid batch: {}", e))) as u32),
+    }
+}
+
 /// Handle fs_stat FFI call
 pub fn handle_stat<FS: FileSystem>(fs: &FS, path_ptr: *const u8) -> u64 {
     let path = unsafe { CString::from_ptr(path_ptr) };
@@ -69,12 +177,12 @@ pub fn handle_stat<FS: FileSystem>(fs: &FS, path_ptr: *const u8) -> u64 {
         Ok(info) => match fileinfo_to_json_ptr(&info) {
             Ok(json_ptr) => pack_u64(json_ptr as u32, 0),
             Err(e) => {
-                let err_ptr = CString::new(&e.to_string()).into_raw();
+                let err_ptr = error_ptr(&e);
                 pack_u64(0, err_ptr as u32)
             }
         },
         Err(e) => {
-            let err_ptr = CString::new(&e.to_string()).into_raw();
+            let err_ptr = error_ptr(&e);
             pack_u64(0, err_ptr as u32)
         }
     }
@@ -88,12 +196,12 @@ pub fn handle_readdir<FS: FileSystem>(fs: &FS, path_ptr: *const u8) -> u64 {
         Ok(infos) => match fileinfo_vec_to_json_ptr(&infos) {
             Ok(json_ptr) => pack_u64(json_ptr as u32, 0),
             Err(e) => {
-                let err_ptr = CString::new(&e.to_string()).into_raw();
+                let err_ptr = error_ptr(&e);
                 pack_u64(0, err_ptr as u32)
             }
         },
         Err(e) => {
-            let err_ptr = CString::new(&e.to_string()).into_raw();
+            let err_ptr = error_ptr(&e);
             pack_u64(0, err_ptr as u32)
         }
     }
@@ -129,7 +237,7 @@ pub fn handle_write<FS: FileSystem>(
         }
         Err(e) => {
             // Pack 0 (no bytes written) in high bits, error pointer in low bits
-            let err_ptr = CString::new(&e.to_string()).into_raw();
+            let err_ptr = error_ptr(&e);
             pack_u64(0, err_ptr as u32)
         }
     }
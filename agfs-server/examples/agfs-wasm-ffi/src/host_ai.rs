@@ -0,0 +1,107 @@
+//! Text generation by the host
+//!
+//! Small generated features, such as a summary next to each story or a
+//! digest of mail subjects, need a language model but not one of their own.
+//! [`HostAI`] has the host run the prompt on the model in the server's
+//! `external_plugins.wasm` config, which also holds the credentials:
+//!
+//! ```ignore
+//! let options = CompletionOptions::new()
+//!     .system("You summarize articles for busy readers.")
+//!     .max_tokens(400);
+//! let summary = HostAI::complete(&format!("Summarize in 3 paragraphs:\n\n{}", article), &options)?;
+//! ```
+//!
+//! Completions are slow and cost money: cache what they produce (e.g. in a
+//! [`RenderCache`](agfs_core::cache::RenderCache)) rather than asking again
+//! on every read.
+
+use crate::deadline;
+use crate::memory::read_packed_response;
+use crate::types::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::ffi::CString;
+
+host_imports! {
+    fn host_ai_complete(request_ptr: *const u8) -> u64;
+}
+
+/// How to generate a completion; unset fields leave the model's defaults
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct CompletionOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+}
+
+impl CompletionOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Instructions the model follows for the whole prompt
+    pub fn system(mut self, system: &str) -> Self {
+        self.system = Some(system.to_string());
+        self
+    }
+
+    /// Upper bound on the length of the answer; the host allows at most
+    /// 4096 and uses 1024 if unset
+    pub fn max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// Sampling temperature, 0 for the most predictable answer
+    pub fn temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+}
+
+#[derive(Serialize)]
+struct CompleteRequest<'a> {
+    prompt: &'a str,
+    options: &'a CompletionOptions,
+}
+
+/// The host's answer; `code` is a non-zero errno on failure
+#[derive(Deserialize)]
+struct CompleteResponse {
+    #[serde(default)]
+    code: i32,
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    text: String,
+}
+
+/// Completions of the host's language model
+pub struct HostAI;
+
+impl HostAI {
+    /// The model's answer to `prompt`
+    ///
+    /// Fails with `InvalidInput` if the server has no completion model
+    /// configured or the prompt exceeds 256 KiB, and with `Busy` while the
+    /// model's API is rate limiting.
+    pub fn complete(prompt: &str, options: &CompletionOptions) -> Result<String> {
+        deadline::check()?;
+        let request_json = serde_json::to_string(&CompleteRequest { prompt, options })
+            .map_err(|e| Error::Other(format!("failed to serialize request: {}", e)))?;
+        let request_c =
+            CString::new(request_json).map_err(|_| Error::InvalidInput("invalid completion request".to_string()))?;
+
+        let response = unsafe { read_packed_response(host_ai_complete(request_c.as_ptr() as *const u8)) }
+            .ok_or_else(|| Error::Io("completion failed".to_string()))?;
+        let response: CompleteResponse =
+            serde_json::from_slice(&response).map_err(|e| Error::Other(format!("failed to parse response: {}", e)))?;
+        if response.code != 0 {
+            return Err(Error::from_code(response.code, response.message));
+        }
+        Ok(response.text)
+    }
+}
@@ -0,0 +1,278 @@
+//! Size-bounded cache directory on the host filesystem
+//!
+//! Large artifacts such as downloaded PDFs, git packfiles or article bodies
+//! should not be held in wasm memory. [`HostCacheDir`] stores them as files
+//! in a host directory, one file per key, and evicts the least recently used
+//! entries once the directory would exceed its byte budget.
+//!
+//! ```ignore
+//! let cache = HostCacheDir::open("/tmp/agfs-cache/arxiv", 256 << 20)?;
+//! if cache.get(&id)?.is_none() {
+//!     cache.put(&id, &Http::get(&url)?.body)?;
+//! }
+//! ```
+
+use crate::host_fs::HostFS;
+use crate::types::{Error, Result};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+
+/// Prefix of files being written; never a valid encoded key
+const TEMP_PREFIX: &str = ".tmp-";
+
+/// Longest encoded key, leaving room for the temp prefix under NAME_MAX
+const MAX_NAME_LEN: usize = 240;
+
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    size: u64,
+    last_used: u64,
+}
+
+/// Files in a host directory addressed by key, evicted LRU at a byte budget
+///
+/// The index lives in plugin memory and is rebuilt from the directory on
+/// [`open`](Self::open), ordered by modification time, so a cache survives
+/// plugin reloads. All methods take `&self` so a cache can be filled from
+/// `read()`.
+pub struct HostCacheDir {
+    dir: String,
+    max_bytes: u64,
+    entries: RefCell<HashMap<String, Entry>>,
+    used: Cell<u64>,
+    clock: Cell<u64>,
+}
+
+impl HostCacheDir {
+    /// Open (creating if needed) the cache at host path `dir`
+    ///
+    /// Leftovers of interrupted writes are removed, and existing entries
+    /// are evicted if they exceed `max_bytes`.
+    pub fn open(dir: &str, max_bytes: u64) -> Result<Self> {
+        let dir = dir.trim_end_matches('/');
+        if dir.is_empty() {
+            return Err(Error::InvalidInput("cache directory must not be the host root".to_string()));
+        }
+        match HostFS::stat(dir) {
+            Ok(info) if info.is_dir() => {}
+            Ok(_) => return Err(Error::InvalidInput(format!("{} is not a directory", dir))),
+            Err(_) => HostFS::mkdir(dir, 0o755)?,
+        }
+
+        let mut found = Vec::new();
+        for info in HostFS::readdir(dir)? {
+            if info.is_dir() {
+                continue;
+            }
+            match decode_key(&info.name) {
+                Some(key) => found.push((info.mod_time, key, info.size.max(0) as u64)),
+                None if info.name.starts_with(TEMP_PREFIX) => {
+                    let _ = HostFS::remove(&format!("{}/{}", dir, info.name));
+                }
+                None => {}
+            }
+        }
+        found.sort();
+
+        let cache = Self {
+            dir: dir.to_string(),
+            max_bytes,
+            entries: RefCell::new(HashMap::new()),
+            used: Cell::new(0),
+            clock: Cell::new(0),
+        };
+        for (_, key, size) in found {
+            let last_used = cache.tick();
+            cache.entries.borrow_mut().insert(key, Entry { size, last_used });
+            cache.used.set(cache.used.get() + size);
+        }
+        cache.evict_to(max_bytes)?;
+        Ok(cache)
+    }
+
+    /// Store `data` under `key`, replacing any previous value
+    ///
+    /// The value is written to a temp file and renamed into place, so a
+    /// reader never sees a partial file. Fails with `InvalidInput` if the
+    /// value alone exceeds the budget.
+    pub fn put(&self, key: &str, data: &[u8]) -> Result<()> {
+        let size = data.len() as u64;
+        if size > self.max_bytes {
+            return Err(Error::InvalidInput(format!(
+                "cache value of {} bytes exceeds budget of {} bytes",
+                size, self.max_bytes
+            )));
+        }
+        let name = encode_key(key)?;
+        self.remove(key)?;
+        self.evict_to(self.max_bytes - size)?;
+
+        let temp = format!("{}/{}{}", self.dir, TEMP_PREFIX, name);
+        let written = if data.is_empty() {
+            HostFS::create(&temp)
+        } else {
+            HostFS::write(&temp, data).map(|_| ())
+        };
+        if let Err(e) = written.and_then(|_| HostFS::rename(&temp, &self.file_path(&name))) {
+            let _ = HostFS::remove(&temp);
+            return Err(e);
+        }
+
+        let last_used = self.tick();
+        self.entries.borrow_mut().insert(key.to_string(), Entry { size, last_used });
+        self.used.set(self.used.get() + size);
+        Ok(())
+    }
+
+    /// The whole value stored under `key`, if cached
+    pub fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        self.read(key, 0, -1)
+    }
+
+    /// `size` bytes of the value under `key` from `offset` (`-1` reads to the end)
+    pub fn read(&self, key: &str, offset: i64, size: i64) -> Result<Option<Vec<u8>>> {
+        let Some(path) = self.path(key) else {
+            return Ok(None);
+        };
+        match HostFS::read(&path, offset, size) {
+            Ok(data) => Ok(Some(data)),
+            // Deleted behind our back; forget it rather than fail the caller
+            Err(_) if HostFS::stat(&path).is_err() => {
+                self.forget(key);
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Host path of the file holding `key`, for passing to `HostFS` directly
+    ///
+    /// Counts as a use of the entry. The file may be evicted by a later
+    /// [`put`](Self::put).
+    pub fn path(&self, key: &str) -> Option<String> {
+        let name = encode_key(key).ok()?;
+        let last_used = self.tick();
+        let mut entries = self.entries.borrow_mut();
+        entries.get_mut(key)?.last_used = last_used;
+        Some(self.file_path(&name))
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.entries.borrow().contains_key(key)
+    }
+
+    /// Drop `key` from the cache; missing keys are not an error
+    pub fn remove(&self, key: &str) -> Result<()> {
+        if !self.contains(key) {
+            return Ok(());
+        }
+        let name = encode_key(key)?;
+        match HostFS::remove(&self.file_path(&name)) {
+            Err(e) if HostFS::stat(&self.file_path(&name)).is_ok() => Err(e),
+            _ => {
+                self.forget(key);
+                Ok(())
+            }
+        }
+    }
+
+    /// Remove every entry
+    pub fn clear(&self) -> Result<()> {
+        let keys: Vec<String> = self.entries.borrow().keys().cloned().collect();
+        for key in keys {
+            self.remove(&key)?;
+        }
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.borrow().is_empty()
+    }
+
+    /// Bytes currently held
+    pub fn used_bytes(&self) -> u64 {
+        self.used.get()
+    }
+
+    pub fn max_bytes(&self) -> u64 {
+        self.max_bytes
+    }
+
+    /// Evict least recently used entries until at most `budget` bytes remain
+    fn evict_to(&self, budget: u64) -> Result<()> {
+        while self.used.get() > budget {
+            let oldest = self
+                .entries
+                .borrow()
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            match oldest {
+                Some(key) => self.remove(&key)?,
+                None => break,
+            }
+        }
+        Ok(())
+    }
+
+    fn forget(&self, key: &str) {
+        if let Some(entry) = self.entries.borrow_mut().remove(key) {
+            self.used.set(self.used.get().saturating_sub(entry.size));
+        }
+    }
+
+    fn tick(&self) -> u64 {
+        let now = self.clock.get() + 1;
+        self.clock.set(now);
+        now
+    }
+
+    fn file_path(&self, name: &str) -> String {
+        format!("{}/{}", self.dir, name)
+    }
+}
+
+/// File name for `key`: alphanumerics, `-` and `_` kept, everything else `%XX`
+///
+/// Encoding `.` keeps names clear of `.`, `..` and [`TEMP_PREFIX`].
+fn encode_key(key: &str) -> Result<String> {
+    if key.is_empty() {
+        return Err(Error::InvalidInput("cache key must not be empty".to_string()));
+    }
+    let mut name = String::with_capacity(key.len());
+    for b in key.bytes() {
+        if b.is_ascii_alphanumeric() || b == b'-' || b == b'_' {
+            name.push(b as char);
+        } else {
+            name.push_str(&format!("%{:02X}", b));
+        }
+    }
+    if name.len() > MAX_NAME_LEN {
+        return Err(Error::InvalidInput("cache key too long".to_string()));
+    }
+    Ok(name)
+}
+
+/// Inverse of [`encode_key`], `None` for names it could not have produced
+fn decode_key(name: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(name.len());
+    let mut chars = name.bytes();
+    while let Some(b) = chars.next() {
+        match b {
+            b'%' => {
+                let hex = [chars.next()?, chars.next()?];
+                bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+            }
+            b if b.is_ascii_alphanumeric() || b == b'-' || b == b'_' => bytes.push(b),
+            _ => return None,
+        }
+    }
+    if bytes.is_empty() {
+        return None;
+    }
+    String::from_utf8(bytes).ok()
+}
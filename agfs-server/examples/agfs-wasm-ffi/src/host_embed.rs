@@ -0,0 +1,87 @@
+//! Text embeddings computed by the host
+//!
+//! Semantic search needs an embedding model, far too large to bundle into a
+//! plugin. [`HostEmbed`] asks the host instead, which serves every plugin
+//! from the model in the server's `external_plugins.wasm` config, so vectors
+//! from different plugins and reloads stay comparable. Keep them in a
+//! [`VectorIndex`](agfs_core::vector::VectorIndex) to search them:
+//!
+//! ```ignore
+//! let texts: Vec<&str> = stories.iter().map(|story| story.title.as_str()).collect();
+//! for (story, vector) in stories.iter().zip(HostEmbed::embed(&texts)?) {
+//!     self.index.insert(story.id, vector)?;
+//! }
+//! let query = HostEmbed::embed(&["rust compilers"])?.remove(0);
+//! let hits = self.index.search(&query, 10)?;
+//! ```
+
+use crate::deadline;
+use crate::memory::read_packed_response;
+use crate::types::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::ffi::CString;
+
+host_imports! {
+    fn host_embed(request_ptr: *const u8) -> u64;
+}
+
+/// Texts sent to the host per call; the host accepts at most 256
+const BATCH_TEXTS: usize = 64;
+
+#[derive(Serialize)]
+struct EmbedRequest<'a> {
+    texts: &'a [&'a str],
+}
+
+/// The host's answer; `code` is a non-zero errno on failure
+#[derive(Deserialize)]
+struct EmbedResponse {
+    #[serde(default)]
+    code: i32,
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    vectors: Vec<Vec<f32>>,
+}
+
+/// Embeddings of the host's model
+pub struct HostEmbed;
+
+impl HostEmbed {
+    /// One vector per text, in order
+    ///
+    /// Fails with `InvalidInput` if the server has no embedding model
+    /// configured. Many texts are sent in several calls.
+    pub fn embed<S: AsRef<str>>(texts: &[S]) -> Result<Vec<Vec<f32>>> {
+        let texts: Vec<&str> = texts.iter().map(AsRef::as_ref).collect();
+        let mut vectors = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(BATCH_TEXTS) {
+            vectors.extend(Self::call(batch)?);
+        }
+        Ok(vectors)
+    }
+
+    fn call(texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        deadline::check()?;
+        let request_json = serde_json::to_string(&EmbedRequest { texts })
+            .map_err(|e| Error::Other(format!("failed to serialize request: {}", e)))?;
+        let request_c =
+            CString::new(request_json).map_err(|_| Error::InvalidInput("invalid embedding request".to_string()))?;
+
+        let response = unsafe { read_packed_response(host_embed(request_c.as_ptr() as *const u8)) }
+            .ok_or_else(|| Error::Io("embedding failed".to_string()))?;
+        let response: EmbedResponse =
+            serde_json::from_slice(&response).map_err(|e| Error::Other(format!("failed to parse response: {}", e)))?;
+        if response.code != 0 {
+            return Err(Error::from_code(response.code, response.message));
+        }
+        if response.vectors.len() != texts.len() {
+            return Err(Error::Io(format!(
+                "embedding returned {} vectors for {} texts",
+                response.vectors.len(),
+                texts.len()
+            )));
+        }
+        Ok(response.vectors)
+    }
+}
@@ -3,15 +3,19 @@
 //! This module provides access to the host filesystem exposed by agfs-server.
 //! WASM plugins can use this to access files on the host system.
 
+use crate::deadline;
+use crate::memory::guest_ptr;
+use crate::retry::{self, RetryPolicy};
 use crate::types::{Error, FileInfo, Result};
 use std::ffi::CString;
+use std::sync::{Mutex, PoisonError};
 
 // Import host functions from the "env" module
-#[link(wasm_import_module = "env")]
-extern "C" {
+host_imports! {
     fn host_fs_read(path: *const u8, offset: i64, size: i64) -> u64;
     fn host_fs_write(path: *const u8, data: *const u8, len: u32) -> u64;
     fn host_fs_stat(path: *const u8) -> u64;
+    fn host_fs_realpath(path: *const u8) -> u64;
     fn host_fs_readdir(path: *const u8) -> u64;
     fn host_fs_create(path: *const u8) -> u32;
     fn host_fs_mkdir(path: *const u8, perm: u32) -> u32;
@@ -19,14 +23,42 @@ extern "C" {
     fn host_fs_remove_all(path: *const u8) -> u32;
     fn host_fs_rename(old_path: *const u8, new_path: *const u8) -> u32;
     fn host_fs_chmod(path: *const u8, mode: u32) -> u32;
+    fn host_fs_write_at(path: *const u8, data: *const u8, len: u32, offset: i64) -> u64;
+    fn host_fs_truncate(path: *const u8, size: i64) -> u32;
+}
+
+/// Retry policy of the idempotent calls, set by `HostFS::set_retry_policy`
+static RETRY: Mutex<Option<RetryPolicy>> = Mutex::new(None);
+
+fn policy() -> RetryPolicy {
+    RETRY
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .unwrap_or_else(RetryPolicy::never)
 }
 
 /// HostFS provides access to the host filesystem from WASM
 pub struct HostFS;
 
 impl HostFS {
+    /// Retry failed `read`, `write`, `write_at`, `truncate`, `stat` and
+    /// `readdir` calls with `policy`; other calls are not idempotent
+    ///
+    /// Off by default. Host errors carry no code: a failed `read` or `write`
+    /// is an `Io` error whatever the cause and is retried as transient, the
+    /// other calls fail with `Other`, which only a custom
+    /// [`retry_if`](RetryPolicy::retry_if) retries.
+    pub fn set_retry_policy(policy: RetryPolicy) {
+        *RETRY.lock().unwrap_or_else(PoisonError::into_inner) = Some(policy);
+    }
+
     /// Read data from a file on the host filesystem
     pub fn read(path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+        retry::run(&policy(), || Self::read_once(path, offset, size))
+    }
+
+    fn read_once(path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+        deadline::check()?;
         let path_c = CString::new(path).map_err(|_| Error::InvalidInput("invalid path".to_string()))?;
 
         unsafe {
@@ -41,13 +73,18 @@ impl HostFS {
             }
 
             // Read data from memory
-            let slice = std::slice::from_raw_parts(data_ptr as *const u8, data_size as usize);
+            let slice = std::slice::from_raw_parts(guest_ptr(data_ptr), data_size as usize);
             Ok(slice.to_vec())
         }
     }
 
     /// Write data to a file on the host filesystem
     pub fn write(path: &str, data: &[u8]) -> Result<Vec<u8>> {
+        retry::run(&policy(), || Self::write_once(path, data))
+    }
+
+    fn write_once(path: &str, data: &[u8]) -> Result<Vec<u8>> {
+        deadline::check()?;
         let path_c = CString::new(path).map_err(|_| Error::InvalidInput("invalid path".to_string()))?;
 
         unsafe {
@@ -66,13 +103,96 @@ impl HostFS {
             }
 
             // Read response from memory
-            let slice = std::slice::from_raw_parts(response_ptr as *const u8, response_size as usize);
+            let slice = std::slice::from_raw_parts(guest_ptr(response_ptr), response_size as usize);
             Ok(slice.to_vec())
         }
     }
 
+    /// Write data at `offset` without touching the rest of the file (pwrite)
+    ///
+    /// Returns the number of bytes written. The file must already exist.
+    pub fn write_at(path: &str, data: &[u8], offset: i64) -> Result<usize> {
+        retry::run(&policy(), || Self::write_at_once(path, data, offset))
+    }
+
+    fn write_at_once(path: &str, data: &[u8], offset: i64) -> Result<usize> {
+        deadline::check()?;
+        let path_c = CString::new(path).map_err(|_| Error::InvalidInput("invalid path".to_string()))?;
+
+        unsafe {
+            let result = host_fs_write_at(
+                path_c.as_ptr() as *const u8,
+                data.as_ptr(),
+                data.len() as u32,
+                offset,
+            );
+
+            // Unpack: lower 32 bits = bytes written, upper 32 bits = error pointer
+            let written = (result & 0xFFFFFFFF) as u32;
+            let err_ptr = ((result >> 32) & 0xFFFFFFFF) as u32;
+
+            if err_ptr != 0 {
+                let err_str = read_string_from_ptr(err_ptr);
+                return Err(Error::Other(err_str));
+            }
+
+            Ok(written as usize)
+        }
+    }
+
+    /// Truncate or zero-extend a file to `size` bytes
+    pub fn truncate(path: &str, size: i64) -> Result<()> {
+        retry::run(&policy(), || Self::truncate_once(path, size))
+    }
+
+    fn truncate_once(path: &str, size: i64) -> Result<()> {
+        deadline::check()?;
+        let path_c = CString::new(path).map_err(|_| Error::InvalidInput("invalid path".to_string()))?;
+
+        unsafe {
+            let err_ptr = host_fs_truncate(path_c.as_ptr() as *const u8, size);
+            if err_ptr != 0 {
+                let err_str = read_string_from_ptr(err_ptr);
+                return Err(Error::Other(err_str));
+            }
+            Ok(())
+        }
+    }
+
+    /// `path` with every symbolic link resolved, as the host sees it
+    ///
+    /// [`stat`](Self::stat) follows links, so it cannot tell a plugin where
+    /// a path leads; check this before trusting a path below a root.
+    /// Components that do not exist yet are kept as given, and links leading
+    /// outside the host's directory or to nothing fail.
+    pub fn realpath(path: &str) -> Result<String> {
+        deadline::check()?;
+        let path_c = CString::new(path).map_err(|_| Error::InvalidInput("invalid path".to_string()))?;
+
+        unsafe {
+            let result = host_fs_realpath(path_c.as_ptr() as *const u8);
+
+            // Unpack: lower 32 bits = path pointer, upper 32 bits = error pointer
+            let path_ptr = (result & 0xFFFFFFFF) as u32;
+            let err_ptr = ((result >> 32) & 0xFFFFFFFF) as u32;
+
+            if err_ptr != 0 {
+                return Err(Error::Other(read_string_from_ptr(err_ptr)));
+            }
+            if path_ptr == 0 {
+                return Err(Error::Other("failed to resolve path".to_string()));
+            }
+            Ok(read_string_from_ptr(path_ptr))
+        }
+    }
+
     /// Get file information
     pub fn stat(path: &str) -> Result<FileInfo> {
+        retry::run(&policy(), || Self::stat_once(path))
+    }
+
+    fn stat_once(path: &str) -> Result<FileInfo> {
+        deadline::check()?;
         let path_c = CString::new(path).map_err(|_| Error::InvalidInput("invalid path".to_string()))?;
 
         unsafe {
@@ -100,6 +220,11 @@ impl HostFS {
 
     /// Read directory contents
     pub fn readdir(path: &str) -> Result<Vec<FileInfo>> {
+        retry::run(&policy(), || Self::readdir_once(path))
+    }
+
+    fn readdir_once(path: &str) -> Result<Vec<FileInfo>> {
+        deadline::check()?;
         let path_c = CString::new(path).map_err(|_| Error::InvalidInput("invalid path".to_string()))?;
 
         unsafe {
@@ -127,6 +252,7 @@ impl HostFS {
 
     /// Create a new file
     pub fn create(path: &str) -> Result<()> {
+        deadline::check()?;
         let path_c = CString::new(path).map_err(|_| Error::InvalidInput("invalid path".to_string()))?;
 
         unsafe {
@@ -141,6 +267,7 @@ impl HostFS {
 
     /// Create a directory
     pub fn mkdir(path: &str, perm: u32) -> Result<()> {
+        deadline::check()?;
         let path_c = CString::new(path).map_err(|_| Error::InvalidInput("invalid path".to_string()))?;
 
         unsafe {
@@ -155,6 +282,7 @@ impl HostFS {
 
     /// Remove a file or empty directory
     pub fn remove(path: &str) -> Result<()> {
+        deadline::check()?;
         let path_c = CString::new(path).map_err(|_| Error::InvalidInput("invalid path".to_string()))?;
 
         unsafe {
@@ -169,6 +297,7 @@ impl HostFS {
 
     /// Remove a file or directory recursively
     pub fn remove_all(path: &str) -> Result<()> {
+        deadline::check()?;
         let path_c = CString::new(path).map_err(|_| Error::InvalidInput("invalid path".to_string()))?;
 
         unsafe {
@@ -183,6 +312,7 @@ impl HostFS {
 
     /// Rename a file or directory
     pub fn rename(old_path: &str, new_path: &str) -> Result<()> {
+        deadline::check()?;
         let old_path_c = CString::new(old_path).map_err(|_| Error::InvalidInput("invalid path".to_string()))?;
         let new_path_c = CString::new(new_path).map_err(|_| Error::InvalidInput("invalid path".to_string()))?;
 
@@ -201,6 +331,7 @@ impl HostFS {
 
     /// Change file permissions
     pub fn chmod(path: &str, mode: u32) -> Result<()> {
+        deadline::check()?;
         let path_c = CString::new(path).map_err(|_| Error::InvalidInput("invalid path".to_string()))?;
 
         unsafe {
@@ -222,7 +353,7 @@ unsafe fn read_string_from_ptr(ptr: u32) -> String {
 
     // Find the null terminator
     let mut len = 0;
-    let start_ptr = ptr as *const u8;
+    let start_ptr = guest_ptr(ptr);
     while *start_ptr.add(len) != 0 {
         len += 1;
     }
@@ -2,63 +2,88 @@
 //!
 //! This module provides HTTP request capabilities exposed by agfs-server.
 //! WASM plugins can use this to make HTTP requests to external services.
-
-use crate::types::{Error, Result};
+//!
+//! A plugin whose `FileSystem::capabilities()` lists `http_hosts` can only
+//! reach those hosts; requests elsewhere fail before they leave the plugin.
+//!
+//! Requests go through the proxy named by the `http_proxy`, `https_proxy` and
+//! `no_proxy` keys of the plugin's config, if set. Without them the server's
+//! own proxy environment applies.
+//!
+//! Connections are pooled per plugin. The `http_max_conns_per_host`,
+//! `http_keep_alive` and `http2` config keys tune the pool; without them the
+//! server's defaults apply.
+
+use crate::deadline;
+use crate::encoding;
+use crate::memory::{base64_decode, read_packed_response};
+use crate::retry::{self, RetryPolicy};
+use crate::types::{Capabilities, Config, Error, Result};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::ffi::CString;
+use std::sync::{Mutex, PoisonError};
 
-// Simple base64 decoding (standard alphabet)
-fn base64_decode(input: &str) -> Result<Vec<u8>> {
-    const BASE64_TABLE: &[u8; 128] = &[
-        255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
-        255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
-        255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 62, 255, 255, 255, 63,
-        52, 53, 54, 55, 56, 57, 58, 59, 60, 61, 255, 255, 255, 0, 255, 255,
-        255, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14,
-        15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 255, 255, 255, 255, 255,
-        255, 26, 27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40,
-        41, 42, 43, 44, 45, 46, 47, 48, 49, 50, 51, 255, 255, 255, 255, 255,
-    ];
-
-    if input.is_empty() {
-        return Ok(Vec::new());
-    }
-
-    let input = input.trim();
-    let mut output = Vec::with_capacity((input.len() * 3) / 4);
-    let mut buf = 0u32;
-    let mut bits = 0;
-
-    for &b in input.as_bytes() {
-        if b == b'=' {
-            break;
-        }
-        if b >= 128 {
-            return Err(Error::Other("invalid base64 character".to_string()));
-        }
-        let val = BASE64_TABLE[b as usize];
-        if val == 255 {
-            continue; // Skip whitespace/invalid chars
-        }
+/// Capabilities of the exported plugin, recorded by `export_plugin!`
+static DECLARED: Mutex<Option<Capabilities>> = Mutex::new(None);
 
-        buf = (buf << 6) | (val as u32);
-        bits += 6;
+/// Record the plugin's declared capabilities
+///
+/// Called by `export_plugin!` after the plugin is created and initialized.
+#[doc(hidden)]
+pub fn declare(capabilities: Capabilities) {
+    *DECLARED.lock().unwrap_or_else(PoisonError::into_inner) = Some(capabilities);
+}
 
-        if bits >= 8 {
-            bits -= 8;
-            output.push((buf >> bits) as u8);
-            buf &= (1 << bits) - 1;
-        }
+/// Proxy settings from the plugin config, recorded by `export_plugin!`
+static PROXY: Mutex<Option<ProxyOptions>> = Mutex::new(None);
+
+/// Connection pool settings from the plugin config, recorded by `export_plugin!`
+static POOL: Mutex<Option<PoolOptions>> = Mutex::new(None);
+
+/// Record the proxy and connection pool keys of the plugin config
+///
+/// Called by `export_plugin!` when the plugin is initialized.
+#[doc(hidden)]
+pub fn configure(config: &Config) {
+    *PROXY.lock().unwrap_or_else(PoisonError::into_inner) = ProxyOptions::from_config(config);
+    *POOL.lock().unwrap_or_else(PoisonError::into_inner) = PoolOptions::from_config(config);
+}
+
+/// Fill in the configured proxy and pool unless the request chose its own
+fn apply_config(req: &mut HttpRequest) {
+    if req.proxy.is_none() {
+        req.proxy = PROXY.lock().unwrap_or_else(PoisonError::into_inner).clone();
+    }
+    if req.pool.is_none() {
+        req.pool = POOL.lock().unwrap_or_else(PoisonError::into_inner).clone();
     }
+}
 
-    Ok(output)
+/// Refuse requests to hosts outside the declared `http_hosts`
+fn check_allowed(url: &str) -> Result<()> {
+    let declared = DECLARED.lock().unwrap_or_else(PoisonError::into_inner);
+    match declared.as_ref() {
+        Some(caps) if !caps.allows_url(url) => {
+            // Only the authority is echoed back; query strings can hold credentials
+            let authority = url
+                .split_once("://")
+                .and_then(|(_, rest)| rest.split(['/', '?', '#']).next())
+                .map(|a| a.rsplit_once('@').map_or(a, |(_, host)| host))
+                .unwrap_or("");
+            Err(Error::Other(format!(
+                "HTTP request to '{}' not allowed: host is not in the plugin's declared http_hosts",
+                authority
+            )))
+        }
+        _ => Ok(()),
+    }
 }
 
 // Import host function from the "env" module
-#[link(wasm_import_module = "env")]
-extern "C" {
+host_imports! {
     fn host_http_request(request_ptr: *const u8) -> u64;
+    fn host_http_request_batch(requests_ptr: *const u8, concurrency: u32) -> u64;
 }
 
 /// HTTP request to be sent by the host
@@ -73,6 +98,100 @@ pub struct HttpRequest {
     pub body: Vec<u8>,
     #[serde(default = "default_timeout")]
     pub timeout: i32, // timeout in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls: Option<TlsOptions>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<ProxyOptions>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pool: Option<PoolOptions>,
+    /// Share the response with other plugin instances through the host cache
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cache: bool,
+    /// Retries of failed or rate-limited attempts; not sent to the host
+    #[serde(skip)]
+    pub retry: Option<RetryPolicy>,
+}
+
+/// Proxy used to reach the target of a request
+///
+/// Proxy URLs may use the `http`, `https`, `socks5` or `socks5h` scheme.
+/// `no_proxy` is a comma-separated list of hosts and domain suffixes to reach
+/// directly, or `*` for all of them, as in the environment variable.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProxyOptions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http_proxy: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub https_proxy: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_proxy: Option<String>,
+}
+
+impl ProxyOptions {
+    /// Read the `http_proxy`, `https_proxy` and `no_proxy` config keys
+    ///
+    /// Returns `None` if none of them is set to a non-empty string.
+    pub fn from_config(config: &Config) -> Option<Self> {
+        let get = |key| config.get_str(key).filter(|v| !v.is_empty()).map(str::to_string);
+        let options = Self {
+            http_proxy: get("http_proxy"),
+            https_proxy: get("https_proxy"),
+            no_proxy: get("no_proxy"),
+        };
+        (options != Self::default()).then_some(options)
+    }
+}
+
+/// Connection pool of the host transport that sends a request
+///
+/// Requests with the same pool, proxy and TLS settings share connections.
+/// `max_conns_per_host` caps the connections to one host, in use or idle,
+/// and keeps that many open for reuse; `keep_alive: Some(false)` closes each
+/// connection after its request, and `http2: Some(false)` sticks to HTTP/1.1.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PoolOptions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_conns_per_host: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keep_alive: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub http2: Option<bool>,
+}
+
+impl PoolOptions {
+    /// Read the `http_max_conns_per_host`, `http_keep_alive` and `http2`
+    /// config keys
+    ///
+    /// Returns `None` if none of them is set; a non-positive connection limit
+    /// is ignored.
+    pub fn from_config(config: &Config) -> Option<Self> {
+        let options = Self {
+            max_conns_per_host: config
+                .get_i64("http_max_conns_per_host")
+                .and_then(|n| u32::try_from(n).ok())
+                .filter(|&n| n > 0),
+            keep_alive: config.get_bool("http_keep_alive"),
+            http2: config.get_bool("http2"),
+        };
+        (options != Self::default()).then_some(options)
+    }
+}
+
+/// TLS settings for reaching services outside the public PKI
+///
+/// Certificates and keys are PEM-encoded. A custom CA bundle is trusted in
+/// addition to the host's system roots.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct TlsOptions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_cert: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_bundle: Option<String>,
+    /// Accept any server certificate; the host logs a warning for each request
+    #[serde(default)]
+    pub insecure_skip_verify: bool,
 }
 
 fn default_method() -> String {
@@ -92,6 +211,11 @@ impl HttpRequest {
             headers: HashMap::new(),
             body: Vec::new(),
             timeout: 30,
+            tls: None,
+            proxy: None,
+            pool: None,
+            cache: false,
+            retry: None,
         }
     }
 
@@ -103,6 +227,11 @@ impl HttpRequest {
             headers: HashMap::new(),
             body: Vec::new(),
             timeout: 30,
+            tls: None,
+            proxy: None,
+            pool: None,
+            cache: false,
+            retry: None,
         }
     }
 
@@ -114,6 +243,11 @@ impl HttpRequest {
             headers: HashMap::new(),
             body: Vec::new(),
             timeout: 30,
+            tls: None,
+            proxy: None,
+            pool: None,
+            cache: false,
+            retry: None,
         }
     }
 
@@ -125,6 +259,11 @@ impl HttpRequest {
             headers: HashMap::new(),
             body: Vec::new(),
             timeout: 30,
+            tls: None,
+            proxy: None,
+            pool: None,
+            cache: false,
+            retry: None,
         }
     }
 
@@ -167,6 +306,66 @@ impl HttpRequest {
         self.timeout = seconds;
         self
     }
+
+    /// Authenticate with a client certificate and its private key (PEM)
+    pub fn client_cert(mut self, cert_pem: &str, key_pem: &str) -> Self {
+        let tls = self.tls.get_or_insert_with(TlsOptions::default);
+        tls.client_cert = Some(cert_pem.to_string());
+        tls.client_key = Some(key_pem.to_string());
+        self
+    }
+
+    /// Also trust the CA certificates in `bundle_pem`
+    pub fn ca_bundle(mut self, bundle_pem: &str) -> Self {
+        self.tls.get_or_insert_with(TlsOptions::default).ca_bundle = Some(bundle_pem.to_string());
+        self
+    }
+
+    /// Send this request through `proxy_url`, overriding the plugin config
+    pub fn proxy(mut self, proxy_url: &str) -> Self {
+        self.proxy = Some(ProxyOptions {
+            http_proxy: Some(proxy_url.to_string()),
+            https_proxy: Some(proxy_url.to_string()),
+            no_proxy: None,
+        });
+        self
+    }
+
+    /// Send this request with `pool`, overriding the plugin config
+    pub fn pool(mut self, pool: PoolOptions) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
+    /// Retry transport failures and 429/503 responses with `policy`
+    ///
+    /// A `Retry-After` header is waited out when it is longer than the
+    /// backoff. If every attempt is rate-limited the last response is
+    /// returned, as without a policy.
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Serve this GET from the host's shared cache
+    ///
+    /// Responses are shared by URL and headers across every plugin instance
+    /// on the host, kept while `Cache-Control`/`Expires` say they are fresh
+    /// and then revalidated with their `ETag`/`Last-Modified`. Requests with
+    /// a body, credentials or their own conditional headers bypass it.
+    pub fn cached(mut self) -> Self {
+        self.cache = true;
+        self
+    }
+
+    /// Skip server certificate verification
+    ///
+    /// Only for testing against services with throwaway certificates: the
+    /// connection can be intercepted. The host logs every such request.
+    pub fn insecure_skip_verify(mut self) -> Self {
+        self.tls.get_or_insert_with(TlsOptions::default).insecure_skip_verify = true;
+        self
+    }
 }
 
 /// HTTP response from the host (internal, for JSON deserialization)
@@ -179,6 +378,29 @@ struct HttpResponseRaw {
     body: String, // Go encodes []byte as base64 string
     #[serde(default)]
     error: String,
+    #[serde(default)]
+    cached: bool,
+}
+
+impl HttpResponseRaw {
+    /// Decode the base64 body and turn host-reported errors into `Err`
+    fn into_response(self) -> Result<HttpResponse> {
+        // Check for error in response
+        if !self.error.is_empty() {
+            return Err(Error::Io(self.error));
+        }
+
+        // Decode base64 body
+        let body = base64_decode(&self.body)?;
+
+        Ok(HttpResponse {
+            status_code: self.status_code,
+            headers: self.headers,
+            body,
+            error: self.error,
+            cached: self.cached,
+        })
+    }
 }
 
 /// HTTP response from the host
@@ -188,13 +410,23 @@ pub struct HttpResponse {
     pub headers: HashMap<String, String>,
     pub body: Vec<u8>,
     pub error: String,
+    /// The body came from the host's shared cache rather than the network
+    pub cached: bool,
 }
 
 impl HttpResponse {
     /// Get response body as string
+    ///
+    /// The body is converted to UTF-8 from the charset of its
+    /// `Content-Type`, or the one declared in or detected from the body (see
+    /// [`encoding::decode`]); bytes invalid in that charset become U+FFFD.
     pub fn text(&self) -> Result<String> {
-        String::from_utf8(self.body.clone())
-            .map_err(|e| Error::Other(format!("invalid UTF-8 in response body: {}", e)))
+        let declared = self
+            .headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+            .and_then(|(_, value)| encoding::charset_of_content_type(value));
+        Ok(encoding::decode(&self.body, declared).into_owned())
     }
 
     /// Parse response body as JSON
@@ -216,6 +448,41 @@ impl HttpResponse {
             None
         }
     }
+
+    /// `Ok` for a 1xx-3xx response, else the matching `Error`
+    ///
+    /// 404 is `NotFound`, 401/403 `PermissionDenied`, 409 `AlreadyExists`,
+    /// 429/503 `Busy` with the `Retry-After` hint, and so on (see
+    /// `Error::from_http`), so clients see the same errors from every
+    /// network plugin.
+    pub fn error_for_status(&self) -> Result<()> {
+        if let Some(busy) = self.busy_error() {
+            return Err(busy);
+        }
+        match Error::from_http(self.status_code, &self.body) {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    /// `Error::Busy` for a rate-limited (429) or unavailable (503) response,
+    /// carrying its `Retry-After` seconds as the retry hint
+    ///
+    /// Return it instead of sleeping or failing for good, so the host can
+    /// make the client back off.
+    pub fn busy_error(&self) -> Option<Error> {
+        if self.status_code != 429 && self.status_code != 503 {
+            return None;
+        }
+        let retry_after = self
+            .headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("retry-after"))
+            .and_then(|(_, value)| value.trim().parse::<u64>().ok());
+        Some(Error::Busy {
+            retry_after_ms: Some(retry_after.map_or(0, |secs| secs * 1000)),
+        })
+    }
 }
 
 /// Http provides HTTP request capabilities from WASM
@@ -223,7 +490,34 @@ pub struct Http;
 
 impl Http {
     /// Perform an HTTP request
-    pub fn request(req: HttpRequest) -> Result<HttpResponse> {
+    ///
+    /// The timeout is shortened to fit the operation's deadline, and a failure
+    /// after the deadline has passed is reported as `TimedOut`.
+    pub fn request(mut req: HttpRequest) -> Result<HttpResponse> {
+        check_allowed(&req.url)?;
+        apply_config(&mut req);
+        let Some(policy) = req.retry else {
+            return Self::send(&mut req);
+        };
+
+        let mut throttled = None;
+        retry::run(&policy, || {
+            throttled = None;
+            let response = Self::send(&mut req)?;
+            match response.busy_error() {
+                Some(busy) => {
+                    throttled = Some(response);
+                    Err(busy)
+                }
+                None => Ok(response),
+            }
+        })
+        .or_else(|e| throttled.ok_or(e))
+    }
+
+    fn send(req: &mut HttpRequest) -> Result<HttpResponse> {
+        req.timeout = deadline::http_timeout(req.timeout)?;
+
         // Serialize request to JSON
         let request_json = serde_json::to_string(&req)
             .map_err(|e| Error::Other(format!("failed to serialize request: {}", e)))?;
@@ -231,50 +525,113 @@ impl Http {
         let request_c = CString::new(request_json)
             .map_err(|_| Error::InvalidInput("invalid request JSON".to_string()))?;
 
-        unsafe {
+        let response = unsafe {
             let result = host_http_request(request_c.as_ptr() as *const u8);
+            read_packed_response(result).ok_or_else(|| Error::Other("HTTP request failed".to_string()))
+        };
+
+        response
+            .and_then(|response_json| {
+                // Parse response (raw format with base64 body)
+                let response_raw: HttpResponseRaw = serde_json::from_slice(&response_json)
+                    .map_err(|e| Error::Other(format!("failed to parse response: {}", e)))?;
+                response_raw.into_response()
+            })
+            .map_err(|e| deadline::check().err().unwrap_or(e))
+    }
 
-            // Unpack: lower 32 bits = pointer, upper 32 bits = size
-            let response_ptr = (result & 0xFFFFFFFF) as u32;
-            let response_size = ((result >> 32) & 0xFFFFFFFF) as u32;
-
-            if response_ptr == 0 {
-                return Err(Error::Other("HTTP request failed".to_string()));
+    /// Perform several HTTP requests, letting the host run up to `concurrency`
+    /// of them in parallel
+    ///
+    /// Results are returned in the same order as `reqs`. A failure of one
+    /// request does not affect the others.
+    pub fn request_many(reqs: Vec<HttpRequest>, concurrency: u32) -> Vec<Result<HttpResponse>> {
+        // Send the permitted requests and slot the refusals back into place
+        let mut refused = Vec::with_capacity(reqs.len());
+        let mut allowed = Vec::with_capacity(reqs.len());
+        for req in reqs {
+            match check_allowed(&req.url) {
+                Ok(()) => {
+                    refused.push(None);
+                    allowed.push(req);
+                }
+                Err(e) => refused.push(Some(e)),
             }
+        }
 
-            // Read response from memory
-            let slice = std::slice::from_raw_parts(response_ptr as *const u8, response_size as usize);
-            let response_json = String::from_utf8_lossy(slice);
-
-            // Parse response (raw format with base64 body)
-            let response_raw: HttpResponseRaw = serde_json::from_str(&response_json)
-                .map_err(|e| Error::Other(format!("failed to parse response: {}", e)))?;
-
-            // Decode base64 body
-            let body = base64_decode(&response_raw.body)?;
-
-            // Build final response
-            let response = HttpResponse {
-                status_code: response_raw.status_code,
-                headers: response_raw.headers,
-                body,
-                error: response_raw.error.clone(),
-            };
+        let mut sent = Self::send_batch(allowed, concurrency).into_iter();
+        refused
+            .into_iter()
+            .map(|refusal| match refusal {
+                Some(e) => Err(e),
+                None => sent
+                    .next()
+                    .unwrap_or_else(|| Err(Error::Other("missing HTTP batch response".to_string()))),
+            })
+            .collect()
+    }
 
-            // Check for error in response
-            if !response.error.is_empty() {
-                return Err(Error::Other(response.error.clone()));
+    fn send_batch(mut reqs: Vec<HttpRequest>, concurrency: u32) -> Vec<Result<HttpResponse>> {
+        if reqs.is_empty() {
+            return Vec::new();
+        }
+        let count = reqs.len();
+        for req in &mut reqs {
+            apply_config(req);
+            match deadline::http_timeout(req.timeout) {
+                Ok(timeout) => req.timeout = timeout,
+                Err(e) => return (0..count).map(|_| Err(e.clone())).collect(),
             }
+        }
 
-            Ok(response)
+        let batch = serde_json::to_string(&reqs)
+            .map_err(|e| Error::Other(format!("failed to serialize requests: {}", e)))
+            .and_then(|json| {
+                CString::new(json).map_err(|_| Error::InvalidInput("invalid request JSON".to_string()))
+            })
+            .and_then(|requests_c| {
+                let response_json = unsafe {
+                    let result = host_http_request_batch(requests_c.as_ptr() as *const u8, concurrency.max(1));
+                    read_packed_response(result)
+                        .ok_or_else(|| Error::Other("HTTP batch request failed".to_string()))?
+                };
+                serde_json::from_slice::<Vec<HttpResponseRaw>>(&response_json)
+                    .map_err(|e| Error::Other(format!("failed to parse batch response: {}", e)))
+            });
+
+        match batch {
+            Ok(raws) if raws.len() == count => raws.into_iter().map(HttpResponseRaw::into_response).collect(),
+            Ok(raws) => (0..count)
+                .map(|_| {
+                    Err(Error::Other(format!(
+                        "HTTP batch returned {} responses for {} requests",
+                        raws.len(),
+                        count
+                    )))
+                })
+                .collect(),
+            Err(e) => (0..count).map(|_| Err(Error::Other(e.to_string()))).collect(),
         }
     }
 
+    /// Perform GET requests for all `urls` with at most `concurrency` in flight
+    pub fn get_many(urls: &[String], concurrency: u32) -> Vec<Result<HttpResponse>> {
+        Self::request_many(urls.iter().map(|url| HttpRequest::get(url)).collect(), concurrency)
+    }
+
     /// Perform a GET request
     pub fn get(url: &str) -> Result<HttpResponse> {
         Self::request(HttpRequest::get(url))
     }
 
+    /// Perform a GET request through the host's shared cache
+    ///
+    /// Plugin mounts fetching the same URL, such as feeds shared by several
+    /// RSS mounts, download it once; see [`HttpRequest::cached`].
+    pub fn get_cached(url: &str) -> Result<HttpResponse> {
+        Self::request(HttpRequest::get(url).cached())
+    }
+
     /// Perform a POST request with body
     pub fn post(url: &str, body: Vec<u8>) -> Result<HttpResponse> {
         Self::request(HttpRequest::post(url).body(body))
@@ -0,0 +1,166 @@
+//! Image transformation by the host
+//!
+//! Media plugins, such as a photo library or previews of an S3 bucket, want
+//! thumbnails, but image codecs would make up most of a plugin binary.
+//! [`HostImage`] has the host decode, scale and encode instead:
+//!
+//! ```ignore
+//! fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+//!     if let Some(photo) = path.strip_prefix("/thumbnails") {
+//!         let original = HostMounts::read(&format!("{}{}", self.source, photo))?;
+//!         let thumbnail = HostImage::resize(&original, 256, 256, Some(ImageFormat::Jpeg))?;
+//!         return slice(&thumbnail.data, offset, size);
+//!     }
+//!     ...
+//! }
+//! ```
+//!
+//! JPEG, PNG and GIF are understood; a GIF keeps only its first frame.
+//! Transformations cost the host real CPU, so cache thumbnails (e.g. in a
+//! [`HostCacheDir`](crate::HostCacheDir)) rather than resizing on every read.
+
+use crate::deadline;
+use crate::memory::{base64_decode, read_packed_response};
+use crate::types::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::ffi::CString;
+
+host_imports! {
+    fn host_image_transform(request_ptr: *const u8) -> u64;
+}
+
+/// Encodings the host reads and writes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFormat {
+    Jpeg,
+    Png,
+    Gif,
+}
+
+impl ImageFormat {
+    /// The format for a file extension such as `jpg` or `PNG`
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext.to_ascii_lowercase().as_str() {
+            "jpg" | "jpeg" => Some(Self::Jpeg),
+            "png" => Some(Self::Png),
+            "gif" => Some(Self::Gif),
+            _ => None,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Jpeg => "jpg",
+            Self::Png => "png",
+            Self::Gif => "gif",
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            Self::Jpeg => "image/jpeg",
+            Self::Png => "image/png",
+            Self::Gif => "image/gif",
+        }
+    }
+}
+
+/// An encoded image returned by the host
+#[derive(Clone, Debug)]
+pub struct Image {
+    pub data: Vec<u8>,
+    pub format: ImageFormat,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Serialize)]
+struct ImageRequest<'a> {
+    op: &'a str,
+    data: &'a [u8],
+    #[serde(skip_serializing_if = "is_zero")]
+    width: u32,
+    #[serde(skip_serializing_if = "is_zero")]
+    height: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<ImageFormat>,
+}
+
+fn is_zero(n: &u32) -> bool {
+    *n == 0
+}
+
+/// The host's answer; `code` is a non-zero errno on failure
+#[derive(Deserialize)]
+struct ImageResponse {
+    #[serde(default)]
+    code: i32,
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    data: String, // Go encodes []byte as base64 string
+    format: Option<ImageFormat>,
+    #[serde(default)]
+    width: u32,
+    #[serde(default)]
+    height: u32,
+}
+
+/// Image transformations of the host
+pub struct HostImage;
+
+impl HostImage {
+    /// `data` scaled down to fit `width` x `height`, keeping its aspect ratio
+    ///
+    /// A side of 0 is unbounded; images that already fit are only
+    /// re-encoded. `format` picks the output encoding, `None` keeps the
+    /// source's. Fails with `InvalidInput` for data that is not a supported
+    /// image, sides over 4096, or images over 32 MiB or 50 megapixels.
+    pub fn resize(data: &[u8], width: u32, height: u32, format: Option<ImageFormat>) -> Result<Image> {
+        if width == 0 && height == 0 {
+            return Err(Error::InvalidInput("resize needs a width or a height".to_string()));
+        }
+        transform(&ImageRequest {
+            op: "resize",
+            data,
+            width,
+            height,
+            format,
+        })
+    }
+
+    /// `data` re-encoded as `format`; transparency becomes white in JPEG
+    pub fn convert(data: &[u8], format: ImageFormat) -> Result<Image> {
+        transform(&ImageRequest {
+            op: "convert",
+            data,
+            width: 0,
+            height: 0,
+            format: Some(format),
+        })
+    }
+}
+
+fn transform(request: &ImageRequest) -> Result<Image> {
+    deadline::check()?;
+    let request_json =
+        serde_json::to_string(request).map_err(|e| Error::Other(format!("failed to serialize request: {}", e)))?;
+    let request_c = CString::new(request_json).map_err(|_| Error::InvalidInput("invalid image request".to_string()))?;
+
+    let response = unsafe { read_packed_response(host_image_transform(request_c.as_ptr() as *const u8)) }
+        .ok_or_else(|| Error::Io("image transformation failed".to_string()))?;
+    let response: ImageResponse =
+        serde_json::from_slice(&response).map_err(|e| Error::Other(format!("failed to parse response: {}", e)))?;
+    if response.code != 0 {
+        return Err(Error::from_code(response.code, response.message));
+    }
+    Ok(Image {
+        data: base64_decode(&response.data)?,
+        format: response
+            .format
+            .ok_or_else(|| Error::Other("host returned no image format".to_string()))?,
+        width: response.width,
+        height: response.height,
+    })
+}
@@ -0,0 +1,66 @@
+//! What the host offers the plugin
+//!
+//! A server older than the plugin may lack some host imports. It stubs them
+//! so the plugin still loads, but calling a stub fails. Ask
+//! [`HostInfo::capabilities`] before using an optional import and degrade
+//! instead, e.g. turn live updates off without a socket import:
+//!
+//! ```ignore
+//! let host = HostInfo::capabilities();
+//! self.live_updates = host.has("host_socket_connect");
+//! if !host.has("host_embed") {
+//!     self.semantic_search = false;
+//! }
+//! ```
+//!
+//! Servers from before `host_capabilities` existed cannot load plugins that
+//! call it.
+
+use crate::memory::read_packed_response;
+use serde::Deserialize;
+use std::sync::OnceLock;
+
+host_imports! {
+    fn host_capabilities() -> u64;
+}
+
+/// Asked once per instance; the host does not change under a plugin
+static CAPABILITIES: OnceLock<HostCapabilities> = OnceLock::new();
+
+/// The host imports that work on this server
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+pub struct HostCapabilities {
+    /// Names of the usable `env` imports, sorted
+    #[serde(default)]
+    pub imports: Vec<String>,
+}
+
+impl HostCapabilities {
+    /// Whether `import` is provided and backed by the server's config
+    ///
+    /// `host_embed` is left out on a server without an embedding model, for
+    /// example, though calling it would not trap.
+    pub fn has(&self, import: &str) -> bool {
+        self.imports.binary_search_by(|name| name.as_str().cmp(import)).is_ok()
+    }
+}
+
+/// Information about the host running the plugin
+pub struct HostInfo;
+
+impl HostInfo {
+    /// The host imports this server provides
+    ///
+    /// Empty if the host's answer cannot be read, so every optional feature
+    /// is turned off rather than called.
+    pub fn capabilities() -> &'static HostCapabilities {
+        CAPABILITIES.get_or_init(|| {
+            let response = unsafe { read_packed_response(host_capabilities()) };
+            let mut capabilities: HostCapabilities = response
+                .and_then(|json| serde_json::from_slice(&json).ok())
+                .unwrap_or_default();
+            capabilities.imports.sort();
+            capabilities
+        })
+    }
+}
@@ -0,0 +1,183 @@
+//! Crash-recovery journal for plugins that buffer writes
+//!
+//! A plugin that acknowledges writes before flushing them upstream (S3FS,
+//! WebDAV) loses them if it crashes in between. [`WriteJournal`] logs each
+//! write to a host directory before it is acknowledged; the plugin commits
+//! the entry once the data is upstream and replays whatever is left when it
+//! starts again:
+//!
+//! ```ignore
+//! fn initialize(&mut self, config: &Config) -> Result<()> {
+//!     self.journal = WriteJournal::open(config.get_str("journal_dir").unwrap_or(DEFAULT_DIR))?;
+//!     let client = &self.client;
+//!     self.journal.replay(|entry| client.put_range(&entry.path, entry.offset, &entry.data))?;
+//!     Ok(())
+//! }
+//!
+//! fn write(&mut self, path: &str, data: &[u8], offset: i64, flags: WriteFlag) -> Result<i64> {
+//!     let id = self.journal.record(path, offset, data)?;
+//!     self.pending.push((id, path.to_string()));
+//!     self.buffer.write(path, data, offset, flags)
+//! }
+//!
+//! // Called once the buffered data of `path` should go upstream
+//! fn flush(&mut self, path: &str) -> Result<()> {
+//!     self.client.put_file(path, &self.buffer.take(path))?;
+//!     for id in self.pending.drain_for(path) {
+//!         self.journal.commit(id)?;
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use crate::host_fs::HostFS;
+use crate::types::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+
+const ENTRY_SUFFIX: &str = ".rec";
+const TEMP_SUFFIX: &str = ".tmp";
+
+/// One write that was acknowledged but may not have reached the backend
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JournalEntry {
+    /// Sequence number, increasing in the order writes were recorded
+    pub id: u64,
+    pub path: String,
+    /// Offset of the write; `-1` replaces the file, as in `FileSystem::write`
+    pub offset: i64,
+    pub data: Vec<u8>,
+}
+
+/// The first line of an entry file; the data follows it
+#[derive(Serialize, Deserialize)]
+struct EntryHeader {
+    path: String,
+    offset: i64,
+}
+
+/// Intent log of buffered writes in a host directory
+///
+/// Entry `<n>` is the file `<n>.rec`: a JSON header line with the path and
+/// offset, then the data. It is written as `<n>.tmp` and renamed, so a crash
+/// while recording leaves no half entry to replay.
+pub struct WriteJournal {
+    dir: String,
+    next: Cell<u64>,
+}
+
+impl WriteJournal {
+    /// Open (creating if needed) the journal at host path `dir`
+    ///
+    /// Entries left by a previous instance are kept for [`replay`](Self::replay);
+    /// leftovers of interrupted records are removed.
+    pub fn open(dir: &str) -> Result<Self> {
+        let dir = dir.trim_end_matches('/');
+        if dir.is_empty() {
+            return Err(Error::InvalidInput("journal directory must not be the host root".to_string()));
+        }
+        match HostFS::stat(dir) {
+            Ok(info) if info.is_dir() => {}
+            Ok(_) => return Err(Error::InvalidInput(format!("{} is not a directory", dir))),
+            Err(_) => HostFS::mkdir(dir, 0o700)?,
+        }
+
+        let journal = Self {
+            dir: dir.to_string(),
+            next: Cell::new(1),
+        };
+        let mut last = 0;
+        for info in HostFS::readdir(dir)? {
+            if let Some(id) = parse_id(&info.name, TEMP_SUFFIX) {
+                let _ = HostFS::remove(&journal.file(id, TEMP_SUFFIX));
+            } else if let Some(id) = parse_id(&info.name, ENTRY_SUFFIX) {
+                last = last.max(id);
+            }
+        }
+        journal.next.set(last + 1);
+        Ok(journal)
+    }
+
+    /// Log a write before acknowledging it, returning the entry's id
+    pub fn record(&self, path: &str, offset: i64, data: &[u8]) -> Result<u64> {
+        let id = self.next.get();
+        self.next.set(id + 1);
+
+        let header = serde_json::to_string(&EntryHeader {
+            path: path.to_string(),
+            offset,
+        })
+        .map_err(|e| Error::Other(format!("failed to encode journal entry: {}", e)))?;
+        let mut content = Vec::with_capacity(header.len() + 1 + data.len());
+        content.extend_from_slice(header.as_bytes());
+        content.push(b'\n');
+        content.extend_from_slice(data);
+
+        let temp = self.file(id, TEMP_SUFFIX);
+        HostFS::write(&temp, &content)?;
+        if let Err(e) = HostFS::rename(&temp, &self.file(id, ENTRY_SUFFIX)) {
+            let _ = HostFS::remove(&temp);
+            return Err(e);
+        }
+        Ok(id)
+    }
+
+    /// Drop an entry whose write has reached the backend
+    pub fn commit(&self, id: u64) -> Result<()> {
+        HostFS::remove(&self.file(id, ENTRY_SUFFIX)).map_err(|_| Error::NotFound)
+    }
+
+    /// Every entry not yet committed, oldest first
+    pub fn pending(&self) -> Result<Vec<JournalEntry>> {
+        let mut ids: Vec<u64> = HostFS::readdir(&self.dir)?
+            .iter()
+            .filter_map(|info| parse_id(&info.name, ENTRY_SUFFIX))
+            .collect();
+        ids.sort_unstable();
+        ids.into_iter().map(|id| self.entry(id)).collect()
+    }
+
+    /// Apply pending entries in order with `apply`, committing each one
+    ///
+    /// Stops at the first entry `apply` fails on, leaving it and later ones
+    /// for the next replay, and returns the error. Returns the number of
+    /// entries applied otherwise.
+    pub fn replay<F>(&self, mut apply: F) -> Result<usize>
+    where
+        F: FnMut(&JournalEntry) -> Result<()>,
+    {
+        let entries = self.pending()?;
+        for entry in &entries {
+            apply(entry)?;
+            self.commit(entry.id)?;
+        }
+        Ok(entries.len())
+    }
+
+    fn entry(&self, id: u64) -> Result<JournalEntry> {
+        let content = HostFS::read(&self.file(id, ENTRY_SUFFIX), 0, -1)?;
+        let corrupt = |reason: String| Error::Other(format!("corrupt journal entry {}: {}", id, reason));
+        let split = content
+            .iter()
+            .position(|&b| b == b'\n')
+            .ok_or_else(|| corrupt("no header".to_string()))?;
+        let header: EntryHeader = serde_json::from_slice(&content[..split]).map_err(|e| corrupt(e.to_string()))?;
+        Ok(JournalEntry {
+            id,
+            path: header.path,
+            offset: header.offset,
+            data: content[split + 1..].to_vec(),
+        })
+    }
+
+    fn file(&self, id: u64, suffix: &str) -> String {
+        format!("{}/{}{}", self.dir, id, suffix)
+    }
+}
+
+fn parse_id(name: &str, suffix: &str) -> Option<u64> {
+    name.strip_suffix(suffix)
+        .filter(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))?
+        .parse()
+        .ok()
+}
@@ -0,0 +1,184 @@
+//! Access to other agfs mounts from WASM
+//!
+//! [`HostMounts`] lets a composite plugin use paths served by other mounts,
+//! such as an index over several mounts or an encryption layer over any one
+//! of them, without bundling those backends itself. Paths are agfs paths,
+//! e.g. `/s3/bucket/key`.
+//!
+//! The host only serves paths under the `mounts` the plugin declares in its
+//! [`Capabilities`](crate::Capabilities), read-only unless the grant allows
+//! writes; everything else fails with `PermissionDenied`. Capabilities are
+//! read after the plugin is initialized, so they may depend on config.
+//!
+//! A declared grant only applies if the operator allows it in the `mounts`
+//! key of the mount config, a list of paths (read-only) or of
+//! `{path, write}` entries. Grants outside that list are refused:
+//!
+//! ```yaml
+//! config:
+//!   mounts:
+//!     - /s3/photos
+//!     - { path: /local/out, write: true }
+//! ```
+//!
+//! The plugin itself declares what it uses:
+//!
+//! ```ignore
+//! fn capabilities(&self) -> Capabilities {
+//!     Capabilities::default().with_mount(&self.source, true)
+//! }
+//!
+//! fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+//!     let sealed = HostMounts::read(&format!("{}{}", self.source, path))?;
+//!     slice(&self.key.open(&sealed)?, offset, size)
+//! }
+//! ```
+//!
+//! A plugin must not reach its own mount: with a single pooled instance the
+//! call waits forever for the instance making it.
+
+use crate::deadline;
+use crate::memory::{base64_decode, read_packed_response};
+use crate::types::{Error, FileInfo, Result};
+use serde::{Deserialize, Serialize};
+use std::ffi::CString;
+
+host_imports! {
+    fn host_mount_call(request_ptr: *const u8) -> u64;
+}
+
+/// One operation sent to the host
+#[derive(Serialize, Default)]
+struct MountRequest<'a> {
+    op: &'a str,
+    path: &'a str,
+    #[serde(skip_serializing_if = "str::is_empty")]
+    new_path: &'a str,
+    offset: i64,
+    size: i64,
+    #[serde(skip_serializing_if = "<[u8]>::is_empty")]
+    data: &'a [u8],
+    perm: u32,
+}
+
+/// The host's answer; `code` is a non-zero errno on failure
+#[derive(Deserialize)]
+struct MountResponse {
+    #[serde(default)]
+    code: i32,
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    data: String, // Go encodes []byte as base64 string
+    #[serde(default)]
+    written: i64,
+    #[serde(default)]
+    info: Option<FileInfo>,
+    #[serde(default)]
+    entries: Vec<FileInfo>,
+}
+
+/// Files on other mounts, reached through the host
+pub struct HostMounts;
+
+impl HostMounts {
+    /// The whole content of the file at `path`
+    pub fn read(path: &str) -> Result<Vec<u8>> {
+        Self::read_at(path, 0, -1)
+    }
+
+    /// `size` bytes from `offset` (`-1` reads to the end)
+    pub fn read_at(path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+        let resp = call(&MountRequest {
+            op: "read",
+            path,
+            offset,
+            size,
+            ..Default::default()
+        })?;
+        base64_decode(&resp.data)
+    }
+
+    /// Replace the file at `path` with `data`, creating it if needed
+    pub fn write(path: &str, data: &[u8]) -> Result<usize> {
+        Self::write_at(path, data, -1)
+    }
+
+    /// Write `data` at `offset` without truncating; `-1` replaces the file
+    pub fn write_at(path: &str, data: &[u8], offset: i64) -> Result<usize> {
+        let resp = call(&MountRequest {
+            op: "write",
+            path,
+            offset,
+            data,
+            ..Default::default()
+        })?;
+        Ok(resp.written.max(0) as usize)
+    }
+
+    pub fn stat(path: &str) -> Result<FileInfo> {
+        call(&MountRequest {
+            op: "stat",
+            path,
+            ..Default::default()
+        })?
+        .info
+        .ok_or_else(|| Error::Other("host returned no file info".to_string()))
+    }
+
+    pub fn readdir(path: &str) -> Result<Vec<FileInfo>> {
+        Ok(call(&MountRequest {
+            op: "readdir",
+            path,
+            ..Default::default()
+        })?
+        .entries)
+    }
+
+    pub fn mkdir(path: &str, perm: u32) -> Result<()> {
+        call(&MountRequest {
+            op: "mkdir",
+            path,
+            perm,
+            ..Default::default()
+        })
+        .map(|_| ())
+    }
+
+    /// Remove a file or empty directory
+    pub fn remove(path: &str) -> Result<()> {
+        call(&MountRequest {
+            op: "remove",
+            path,
+            ..Default::default()
+        })
+        .map(|_| ())
+    }
+
+    /// Rename within or across mounts; both paths need write access
+    pub fn rename(old_path: &str, new_path: &str) -> Result<()> {
+        call(&MountRequest {
+            op: "rename",
+            path: old_path,
+            new_path,
+            ..Default::default()
+        })
+        .map(|_| ())
+    }
+}
+
+fn call(req: &MountRequest) -> Result<MountResponse> {
+    deadline::check()?;
+    let request_json = serde_json::to_string(req)
+        .map_err(|e| Error::Other(format!("failed to serialize request: {}", e)))?;
+    let request_c = CString::new(request_json).map_err(|_| Error::InvalidInput("invalid path".to_string()))?;
+
+    let response = unsafe { read_packed_response(host_mount_call(request_c.as_ptr() as *const u8)) }
+        .ok_or_else(|| Error::Io(format!("{} {} failed", req.op, req.path)))?;
+    let response: MountResponse = serde_json::from_slice(&response)
+        .map_err(|e| Error::Other(format!("failed to parse response: {}", e)))?;
+    if response.code != 0 {
+        return Err(Error::from_code(response.code, response.message));
+    }
+    Ok(response)
+}
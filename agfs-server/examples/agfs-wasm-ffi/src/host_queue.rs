@@ -0,0 +1,144 @@
+//! Work queues held by the host
+//!
+//! Work a plugin defers, such as comments still to fetch or uploads to
+//! retry, is lost with the instance if it only lives in wasm memory.
+//! [`HostQueue`] keeps it in a queue on the host instead, where it outlives
+//! instances and plugin reloads; a [`persistent`](HostQueue::persistent)
+//! queue is also written to the server's `queue_dir` and survives restarts:
+//!
+//! ```ignore
+//! fn maintain(&mut self) -> Result<()> {
+//!     let backlog = HostQueue::persistent("comments");
+//!     while let Some(id) = backlog.peek()? {
+//!         self.fetch_comments(&String::from_utf8_lossy(&id))?;
+//!         backlog.pop()?;
+//!     }
+//!     Ok(())
+//! }
+//! ```
+//!
+//! Queue names are per plugin: plugins with different names never see each
+//! other's queues, while mounts of the same plugin share them.
+
+use crate::deadline;
+use crate::memory::{base64_decode, read_packed_response};
+use crate::types::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::ffi::CString;
+
+host_imports! {
+    fn host_queue_call(request_ptr: *const u8) -> u64;
+}
+
+/// One operation sent to the host
+#[derive(Serialize)]
+struct QueueRequest<'a> {
+    op: &'a str,
+    queue: &'a str,
+    #[serde(skip_serializing_if = "<[u8]>::is_empty")]
+    data: &'a [u8],
+    persistent: bool,
+}
+
+/// The host's answer; `code` is a non-zero errno on failure
+#[derive(Deserialize)]
+struct QueueResponse {
+    #[serde(default)]
+    code: i32,
+    #[serde(default)]
+    message: String,
+    #[serde(default)]
+    data: String, // Go encodes []byte as base64 string
+    #[serde(default)]
+    found: bool,
+    #[serde(default)]
+    len: usize,
+}
+
+/// A named FIFO queue of byte messages on the host
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostQueue {
+    name: String,
+    persistent: bool,
+}
+
+impl HostQueue {
+    /// The queue `name`, kept in host memory
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            persistent: false,
+        }
+    }
+
+    /// The queue `name`, also kept on disk
+    ///
+    /// Calls fail with `InvalidInput` if the server has no `queue_dir`
+    /// configured. A queue found on disk is persistent whichever
+    /// constructor reaches it.
+    pub fn persistent(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            persistent: true,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Append `message`; fails with `Busy` when the queue is full
+    pub fn push(&self, message: &[u8]) -> Result<()> {
+        self.call("push", message).map(|_| ())
+    }
+
+    /// Remove and return the oldest message, `None` if the queue is empty
+    pub fn pop(&self) -> Result<Option<Vec<u8>>> {
+        self.call("pop", &[])?.message()
+    }
+
+    /// The oldest message, left in the queue
+    pub fn peek(&self) -> Result<Option<Vec<u8>>> {
+        self.call("peek", &[])?.message()
+    }
+
+    /// Number of messages waiting
+    pub fn len(&self) -> Result<usize> {
+        Ok(self.call("len", &[])?.len)
+    }
+
+    pub fn is_empty(&self) -> Result<bool> {
+        Ok(self.len()? == 0)
+    }
+
+    fn call(&self, op: &str, data: &[u8]) -> Result<QueueResponse> {
+        deadline::check()?;
+        let request = QueueRequest {
+            op,
+            queue: &self.name,
+            data,
+            persistent: self.persistent,
+        };
+        let request_json = serde_json::to_string(&request)
+            .map_err(|e| Error::Other(format!("failed to serialize request: {}", e)))?;
+        let request_c = CString::new(request_json).map_err(|_| Error::InvalidInput("invalid queue name".to_string()))?;
+
+        let response = unsafe { read_packed_response(host_queue_call(request_c.as_ptr() as *const u8)) }
+            .ok_or_else(|| Error::Io(format!("queue {} {} failed", self.name, op)))?;
+        let response: QueueResponse = serde_json::from_slice(&response)
+            .map_err(|e| Error::Other(format!("failed to parse response: {}", e)))?;
+        if response.code != 0 {
+            return Err(Error::from_code(response.code, response.message));
+        }
+        Ok(response)
+    }
+}
+
+impl QueueResponse {
+    fn message(self) -> Result<Option<Vec<u8>>> {
+        if !self.found {
+            return Ok(None);
+        }
+        base64_decode(&self.data).map(Some)
+    }
+}
@@ -0,0 +1,227 @@
+//! Stand-ins for host imports in native tests
+//!
+//! Native builds have no agfs-server to import from, so a host import panics
+//! unless a stub serves it. A stub sees the call the way the server's Go
+//! functions do: every argument widened to a `u64`, pointers as addresses
+//! that [`read_str`] and [`read_bytes`] copy from, and one `u64` result.
+//! Pointers a stub returns must come from [`alloc`] or [`alloc_str`], which
+//! play the part of plugin memory the host wrote into.
+//!
+//! Stubs are per thread, and so per test.
+//!
+//! ```ignore
+//! host_stub::set("host_deadline_ms", |_| -1i64 as u64);
+//! host_stub::set("host_fs_truncate", |args| {
+//!     let path = unsafe { host_stub::read_str(args[0]) };
+//!     if path.starts_with("/srv/data/") { 0 } else { host_stub::alloc_str("permission denied") as u64 }
+//! });
+//! ```
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ffi::CStr;
+
+type Stub = Box<dyn Fn(&[u64]) -> u64>;
+
+thread_local! {
+    static STUBS: RefCell<HashMap<&'static str, Stub>> = RefCell::new(HashMap::new());
+    /// Blocks handed out by `alloc`; guest pointer `n` is block `n - 1`
+    static MEMORY: RefCell<Vec<Box<[u8]>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Serve host import `name` with `stub` on this thread, replacing any
+/// earlier stub
+pub fn set(name: &'static str, stub: impl Fn(&[u64]) -> u64 + 'static) {
+    STUBS.with(|stubs| stubs.borrow_mut().insert(name, Box::new(stub)));
+}
+
+/// Stop serving `name` on this thread
+pub fn remove(name: &str) {
+    STUBS.with(|stubs| stubs.borrow_mut().remove(name));
+}
+
+/// Copy `data` into stand-in plugin memory, returning its guest pointer
+pub fn alloc(data: &[u8]) -> u32 {
+    MEMORY.with(|memory| {
+        let mut memory = memory.borrow_mut();
+        memory.push(data.into());
+        memory.len() as u32
+    })
+}
+
+/// Like [`alloc`], NUL-terminated, as the host writes strings
+pub fn alloc_str(s: &str) -> u32 {
+    let mut data = s.as_bytes().to_vec();
+    data.push(0);
+    alloc(&data)
+}
+
+/// Read the NUL-terminated string a pointer argument points to
+///
+/// # Safety
+///
+/// `ptr` must be a pointer argument of the call being served.
+pub unsafe fn read_str(ptr: u64) -> String {
+    if ptr == 0 {
+        return String::new();
+    }
+    CStr::from_ptr(ptr as usize as *const std::ffi::c_char)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Read `len` bytes a pointer argument points to
+///
+/// # Safety
+///
+/// `ptr` must be a pointer argument of the call being served, to at least
+/// `len` bytes.
+pub unsafe fn read_bytes(ptr: u64, len: u64) -> Vec<u8> {
+    if len == 0 {
+        return Vec::new();
+    }
+    std::slice::from_raw_parts(ptr as usize as *const u8, len as usize).to_vec()
+}
+
+/// Address of the block at guest pointer `ptr`
+pub(crate) fn guest_ptr(ptr: u32) -> *const u8 {
+    MEMORY.with(|memory| match memory.borrow().get((ptr as usize).wrapping_sub(1)) {
+        Some(block) => block.as_ptr(),
+        None => panic!("guest pointer {} was not returned by host_stub::alloc", ptr),
+    })
+}
+
+/// Run the stub for `name`; used by the native side of `host_imports!`
+pub(crate) fn call(name: &str, args: &[u64]) -> u64 {
+    STUBS.with(|stubs| match stubs.borrow().get(name) {
+        Some(stub) => stub(args),
+        None => panic!("{} is only available inside agfs-server", name),
+    })
+}
+
+/// Host import argument and result types, as the host sees them
+pub(crate) trait HostValue {
+    fn to_u64(self) -> u64;
+    fn from_u64(value: u64) -> Self;
+}
+
+impl HostValue for u32 {
+    fn to_u64(self) -> u64 {
+        self as u64
+    }
+    fn from_u64(value: u64) -> Self {
+        value as u32
+    }
+}
+
+impl HostValue for i32 {
+    fn to_u64(self) -> u64 {
+        self as u32 as u64
+    }
+    fn from_u64(value: u64) -> Self {
+        value as u32 as i32
+    }
+}
+
+impl HostValue for u64 {
+    fn to_u64(self) -> u64 {
+        self
+    }
+    fn from_u64(value: u64) -> Self {
+        value
+    }
+}
+
+impl HostValue for i64 {
+    fn to_u64(self) -> u64 {
+        self as u64
+    }
+    fn from_u64(value: u64) -> Self {
+        value as i64
+    }
+}
+
+impl HostValue for *const u8 {
+    fn to_u64(self) -> u64 {
+        self as usize as u64
+    }
+    fn from_u64(value: u64) -> Self {
+        value as usize as *const u8
+    }
+}
+
+/// Stubs shared by the SDK's own tests
+#[cfg(test)]
+pub(crate) mod fixtures {
+    use super::*;
+
+    fn packed(data: &[u8]) -> u64 {
+        alloc(data) as u64 | (data.len() as u64) << 32
+    }
+
+    /// Serve the clock at `secs` after the epoch, with no deadline and
+    /// sleeps that return at once
+    pub fn clock(secs: u64) {
+        set("host_now_ms", move |_| secs * 1000);
+        set("host_deadline_ms", |_| -1i64 as u64);
+        set("host_sleep_ms", |_| 0);
+    }
+
+    /// Serve `host_http_request` with `handler`, which answers a request with
+    /// a status code and body
+    #[cfg(all(feature = "http", feature = "hostfs"))]
+    pub fn http(handler: impl Fn(&crate::HttpRequest) -> (i32, String) + 'static) {
+        set("host_http_request", move |args| {
+            let req = serde_json::from_str(&unsafe { read_str(args[0]) }).unwrap();
+            let (status, body) = handler(&req);
+            let response = serde_json::json!({ "status_code": status, "body": base64(body.as_bytes()) });
+            packed(response.to_string().as_bytes())
+        });
+    }
+
+    /// Serve the whole-file host fs imports from an in-memory map, returned
+    /// so tests can look at and change the files
+    #[cfg(all(feature = "http", feature = "hostfs"))]
+    pub fn host_files() -> std::rc::Rc<RefCell<HashMap<String, Vec<u8>>>> {
+        let files: std::rc::Rc<RefCell<HashMap<String, Vec<u8>>>> = Default::default();
+        let f = files.clone();
+        set("host_fs_read", move |args| match f.borrow().get(&unsafe { read_str(args[0]) }) {
+            Some(data) => packed(data),
+            None => 0,
+        });
+        let f = files.clone();
+        set("host_fs_write", move |args| {
+            let (path, data) = unsafe { (read_str(args[0]), read_bytes(args[1], args[2])) };
+            f.borrow_mut().insert(path, data);
+            packed(b"ok")
+        });
+        let f = files.clone();
+        set("host_fs_remove", move |args| match f.borrow_mut().remove(&unsafe { read_str(args[0]) }) {
+            Some(_) => 0,
+            None => alloc_str("no such file") as u64,
+        });
+        files
+    }
+
+    #[cfg(all(feature = "http", feature = "hostfs"))]
+    fn base64(data: &[u8]) -> String {
+        const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut out = String::new();
+        for chunk in data.chunks(3) {
+            let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+            for i in 0..4 {
+                out.push(if i <= chunk.len() { ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char } else { '=' });
+            }
+        }
+        out
+    }
+
+    /// Serve the hashing imports natively
+    pub fn crypto() {
+        set("host_crypto_sha256", |args| packed(&hmac_sha256::Hash::hash(&unsafe { read_bytes(args[0], args[1]) })));
+        set("host_crypto_hmac_sha256", |args| {
+            let (key, data) = unsafe { (read_bytes(args[0], args[1]), read_bytes(args[2], args[3])) };
+            packed(&hmac_sha256::HMAC::mac(data, key))
+        });
+    }
+}
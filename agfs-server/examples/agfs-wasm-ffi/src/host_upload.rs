@@ -0,0 +1,155 @@
+//! Upload sessions staged on the host filesystem
+//!
+//! [`HostUploads`] keeps the data of each [`UploadFS`](crate::UploadFS)
+//! session in a host directory, so a session outlives the plugin instance
+//! that began it. A plugin implements `UploadFS` by delegating to it and
+//! moving the staged file to its backend on commit:
+//!
+//! ```ignore
+//! impl UploadFS for BucketFS {
+//!     fn begin_upload(&mut self, path: &str) -> Result<UploadSession> {
+//!         self.uploads.begin(path)
+//!     }
+//!
+//!     fn commit_upload(&mut self, id: &str) -> Result<()> {
+//!         let session = self.uploads.status(id)?;
+//!         self.client.put_file(&session.path, &self.uploads.data_path(id)?)?;
+//!         self.uploads.discard(id)
+//!     }
+//!     // upload_status, upload_append, abort_upload, list_uploads likewise
+//! }
+//! ```
+
+use crate::host_fs::HostFS;
+use crate::types::{Error, Result, UploadSession};
+use std::cell::Cell;
+
+const DATA_SUFFIX: &str = ".part";
+const META_SUFFIX: &str = ".json";
+
+/// Staging area for upload sessions in a host directory
+///
+/// Each session `u<n>` is a data file `u<n>.part`, whose size is the bytes
+/// received, and `u<n>.json` holding the target path.
+pub struct HostUploads {
+    dir: String,
+    next: Cell<u64>,
+}
+
+impl HostUploads {
+    /// Open (creating if needed) the staging directory at host path `dir`
+    pub fn open(dir: &str) -> Result<Self> {
+        let dir = dir.trim_end_matches('/');
+        if dir.is_empty() {
+            return Err(Error::InvalidInput("upload directory must not be the host root".to_string()));
+        }
+        match HostFS::stat(dir) {
+            Ok(info) if info.is_dir() => {}
+            Ok(_) => return Err(Error::InvalidInput(format!("{} is not a directory", dir))),
+            Err(_) => HostFS::mkdir(dir, 0o700)?,
+        }
+        let uploads = Self {
+            dir: dir.to_string(),
+            next: Cell::new(1),
+        };
+        let last = uploads.ids()?.iter().filter_map(|id| id[1..].parse::<u64>().ok()).max();
+        uploads.next.set(last.map_or(1, |n| n + 1));
+        Ok(uploads)
+    }
+
+    /// Start a session that will write `path`
+    pub fn begin(&self, path: &str) -> Result<UploadSession> {
+        let id = format!("u{}", self.next.get());
+        self.next.set(self.next.get() + 1);
+
+        HostFS::create(&self.file(&id, DATA_SUFFIX))?;
+        let meta = serde_json::json!({ "path": path }).to_string();
+        if let Err(e) = HostFS::write(&self.file(&id, META_SUFFIX), meta.as_bytes()) {
+            let _ = HostFS::remove(&self.file(&id, DATA_SUFFIX));
+            return Err(e);
+        }
+        Ok(UploadSession {
+            id,
+            path: path.to_string(),
+            received: 0,
+        })
+    }
+
+    pub fn status(&self, id: &str) -> Result<UploadSession> {
+        check_id(id)?;
+        let meta = HostFS::read(&self.file(id, META_SUFFIX), 0, -1).map_err(|_| Error::NotFound)?;
+        let meta: serde_json::Value = serde_json::from_slice(&meta)
+            .map_err(|e| Error::Other(format!("corrupt upload session {}: {}", id, e)))?;
+        let path = meta["path"]
+            .as_str()
+            .ok_or_else(|| Error::Other(format!("corrupt upload session {}: no path", id)))?;
+        let received = HostFS::stat(&self.file(id, DATA_SUFFIX)).map_err(|_| Error::NotFound)?.size;
+        Ok(UploadSession {
+            id: id.to_string(),
+            path: path.to_string(),
+            received: received.max(0) as u64,
+        })
+    }
+
+    /// Append `data` at `offset`, which must equal the bytes received so far
+    pub fn append(&self, id: &str, offset: u64, data: &[u8]) -> Result<UploadSession> {
+        let mut session = self.status(id)?;
+        if offset != session.received {
+            return Err(Error::InvalidInput(format!(
+                "upload {} expects offset {}, got {}",
+                id, session.received, offset
+            )));
+        }
+        if !data.is_empty() {
+            HostFS::write_at(&self.file(id, DATA_SUFFIX), data, offset as i64)?;
+        }
+        session.received += data.len() as u64;
+        Ok(session)
+    }
+
+    /// Host path of the staged data, for the plugin to read or move on commit
+    pub fn data_path(&self, id: &str) -> Result<String> {
+        check_id(id)?;
+        let path = self.file(id, DATA_SUFFIX);
+        HostFS::stat(&path).map_err(|_| Error::NotFound)?;
+        Ok(path)
+    }
+
+    /// End a session, removing its staged data
+    pub fn discard(&self, id: &str) -> Result<()> {
+        check_id(id)?;
+        let data = HostFS::remove(&self.file(id, DATA_SUFFIX));
+        let meta = HostFS::remove(&self.file(id, META_SUFFIX));
+        if data.is_err() && meta.is_err() {
+            return Err(Error::NotFound);
+        }
+        Ok(())
+    }
+
+    /// Every open session
+    pub fn list(&self) -> Result<Vec<UploadSession>> {
+        Ok(self.ids()?.iter().filter_map(|id| self.status(id).ok()).collect())
+    }
+
+    fn ids(&self) -> Result<Vec<String>> {
+        let mut ids: Vec<String> = HostFS::readdir(&self.dir)?
+            .into_iter()
+            .filter_map(|info| info.name.strip_suffix(META_SUFFIX).map(str::to_string))
+            .filter(|id| check_id(id).is_ok())
+            .collect();
+        ids.sort();
+        Ok(ids)
+    }
+
+    fn file(&self, id: &str, suffix: &str) -> String {
+        format!("{}/{}{}", self.dir, id, suffix)
+    }
+}
+
+/// Session IDs come from clients; only accept the ones `begin` hands out
+fn check_id(id: &str) -> Result<()> {
+    match id.strip_prefix('u') {
+        Some(n) if !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()) => Ok(()),
+        _ => Err(Error::NotFound),
+    }
+}
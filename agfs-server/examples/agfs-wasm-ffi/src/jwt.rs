@@ -0,0 +1,409 @@
+//! JSON Web Tokens signed with HS256 or RS256
+//!
+//! [`encode`] signs claims, e.g. the short-lived token a GitHub App or a GCP
+//! service account exchanges for an access token; [`decode`] checks a token
+//! (say, one a user wrote into a control file) and returns its claims.
+//! Signatures are computed by the host.
+//!
+//! ```ignore
+//! let now = clock::now().as_secs();
+//! let key = SigningKey::rs256(config.get_str("app_private_key").unwrap_or(""));
+//! let token = jwt::encode(&json!({ "iat": now - 60, "exp": now + 540, "iss": app_id }), &key)?;
+//!
+//! let claims: Claims = jwt::decode(&token, &VerifyingKey::hs256(secret), &Validation::new().issuer("admin"))?;
+//! ```
+
+use crate::clock;
+use crate::crypto::{hmac_sha256, rsa_sign_sha256, rsa_verify_sha256};
+use crate::types::{Error, Result};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Signature algorithm of a token
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Algorithm {
+    HS256,
+    RS256,
+}
+
+#[derive(Clone)]
+enum Secret {
+    Hmac(Vec<u8>),
+    Rsa(String),
+}
+
+impl Secret {
+    fn algorithm(&self) -> Algorithm {
+        match self {
+            Secret::Hmac(_) => Algorithm::HS256,
+            Secret::Rsa(_) => Algorithm::RS256,
+        }
+    }
+}
+
+/// Key that signs tokens: a shared secret or a PEM RSA private key
+#[derive(Clone)]
+pub struct SigningKey {
+    secret: Secret,
+    kid: Option<String>,
+}
+
+impl SigningKey {
+    pub fn hs256(secret: &[u8]) -> Self {
+        Self {
+            secret: Secret::Hmac(secret.to_vec()),
+            kid: None,
+        }
+    }
+
+    /// PKCS#1 or PKCS#8 PEM private key
+    pub fn rs256(private_key_pem: &str) -> Self {
+        Self {
+            secret: Secret::Rsa(private_key_pem.to_string()),
+            kid: None,
+        }
+    }
+
+    /// Name the key in the `kid` header, for servers holding several
+    pub fn kid(mut self, kid: &str) -> Self {
+        self.kid = Some(kid.to_string());
+        self
+    }
+}
+
+/// Key that checks tokens: a shared secret or a PEM RSA public key or
+/// certificate
+#[derive(Clone)]
+pub struct VerifyingKey(Secret);
+
+impl VerifyingKey {
+    pub fn hs256(secret: &[u8]) -> Self {
+        Self(Secret::Hmac(secret.to_vec()))
+    }
+
+    pub fn rs256(public_key_pem: &str) -> Self {
+        Self(Secret::Rsa(public_key_pem.to_string()))
+    }
+}
+
+/// Checks on the registered claims made by [`decode`]
+///
+/// `exp` and `nbf` are always checked when present, and must be integers.
+#[derive(Debug, Clone)]
+pub struct Validation {
+    leeway: Duration,
+    require_exp: bool,
+    issuer: Option<String>,
+    audience: Option<String>,
+}
+
+impl Default for Validation {
+    fn default() -> Self {
+        Self {
+            leeway: Duration::from_secs(60),
+            require_exp: true,
+            issuer: None,
+            audience: None,
+        }
+    }
+}
+
+impl Validation {
+    /// Require `exp`, allowing 60s of clock skew
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Clock skew allowed on `exp` and `nbf`
+    pub fn leeway(mut self, leeway: Duration) -> Self {
+        self.leeway = leeway;
+        self
+    }
+
+    /// Accept tokens without `exp`
+    pub fn allow_no_expiry(mut self) -> Self {
+        self.require_exp = false;
+        self
+    }
+
+    /// Require `iss` to be `issuer`
+    pub fn issuer(mut self, issuer: &str) -> Self {
+        self.issuer = Some(issuer.to_string());
+        self
+    }
+
+    /// Require `aud` to be or contain `audience`
+    pub fn audience(mut self, audience: &str) -> Self {
+        self.audience = Some(audience.to_string());
+        self
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+    alg: Algorithm,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    typ: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kid: Option<String>,
+}
+
+/// Sign `claims` into a compact token
+pub fn encode<T: Serialize>(claims: &T, key: &SigningKey) -> Result<String> {
+    let header = Header {
+        alg: key.secret.algorithm(),
+        typ: Some("JWT".to_string()),
+        kid: key.kid.clone(),
+    };
+    let mut token = format!("{}.{}", base64url_encode(&to_json(&header)?), base64url_encode(&to_json(claims)?));
+    let signature = match &key.secret {
+        Secret::Hmac(secret) => hmac_sha256(secret, token.as_bytes())?.to_vec(),
+        Secret::Rsa(pem) => rsa_sign_sha256(pem, token.as_bytes())?,
+    };
+    token.push('.');
+    token.push_str(&base64url_encode(&signature));
+    Ok(token)
+}
+
+/// Check the signature and claims of `token` and return its claims
+///
+/// A malformed token is `InvalidInput`; a bad signature, a token signed with
+/// another algorithm than `key`'s, or failed `validation` is
+/// `PermissionDenied`.
+pub fn decode<T: DeserializeOwned>(token: &str, key: &VerifyingKey, validation: &Validation) -> Result<T> {
+    let malformed = || Error::InvalidInput("malformed JWT".to_string());
+    let mut parts = token.trim().split('.');
+    let (Some(header), Some(payload), Some(signature), None) = (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(malformed());
+    };
+    let signed = &token.trim()[..header.len() + 1 + payload.len()];
+
+    let header: Header = serde_json::from_slice(&base64url_decode(header)?).map_err(|_| malformed())?;
+    let signature = base64url_decode(signature)?;
+    // The key decides the algorithm, never the token
+    if header.alg != key.0.algorithm() {
+        return Err(Error::PermissionDenied);
+    }
+    let valid = match &key.0 {
+        Secret::Hmac(secret) => constant_time_eq(&hmac_sha256(secret, signed.as_bytes())?, &signature),
+        Secret::Rsa(pem) => rsa_verify_sha256(pem, signed.as_bytes(), &signature)?,
+    };
+    if !valid {
+        return Err(Error::PermissionDenied);
+    }
+
+    let claims: serde_json::Value = serde_json::from_slice(&base64url_decode(payload)?).map_err(|_| malformed())?;
+    check_claims(&claims, validation, clock::now())?;
+    serde_json::from_value(claims).map_err(|e| Error::InvalidInput(format!("unexpected JWT claims: {}", e)))
+}
+
+fn check_claims(claims: &serde_json::Value, validation: &Validation, now: Duration) -> Result<()> {
+    let now = now.as_secs();
+    let leeway = validation.leeway.as_secs();
+    match numeric_date(claims, "exp")? {
+        Some(exp) if exp.saturating_add(leeway) <= now => return Err(Error::PermissionDenied),
+        None if validation.require_exp => return Err(Error::PermissionDenied),
+        _ => {}
+    }
+    if let Some(nbf) = numeric_date(claims, "nbf")? {
+        if nbf > now.saturating_add(leeway) {
+            return Err(Error::PermissionDenied);
+        }
+    }
+    if let Some(issuer) = &validation.issuer {
+        if claims.get("iss").and_then(|v| v.as_str()) != Some(issuer) {
+            return Err(Error::PermissionDenied);
+        }
+    }
+    if let Some(audience) = &validation.audience {
+        let matches = match claims.get("aud") {
+            Some(serde_json::Value::String(aud)) => aud == audience,
+            Some(serde_json::Value::Array(auds)) => auds.iter().any(|aud| aud.as_str() == Some(audience)),
+            _ => false,
+        };
+        if !matches {
+            return Err(Error::PermissionDenied);
+        }
+    }
+    Ok(())
+}
+
+/// The time claim `name` in Unix seconds, if present
+///
+/// A claim that is present but not a whole number of seconds fails with
+/// `PermissionDenied` rather than being skipped, so a token cannot dodge its
+/// expiry by writing it as a string.
+fn numeric_date(claims: &serde_json::Value, name: &str) -> Result<Option<u64>> {
+    claims
+        .get(name)
+        .map(|v| v.as_u64().ok_or(Error::PermissionDenied))
+        .transpose()
+}
+
+fn to_json<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+    serde_json::to_vec(value).map_err(|e| Error::Other(format!("failed to encode JWT: {}", e)))
+}
+
+/// Compare without stopping at the first difference, so the time taken does
+/// not tell how much of a forged signature was right
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+const BASE64URL: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Unpadded base64url, as JWTs use
+fn base64url_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            out.push(BASE64URL[(n >> (18 - 6 * i) & 63) as usize] as char);
+        }
+    }
+    out
+}
+
+fn base64url_decode(text: &str) -> Result<Vec<u8>> {
+    let text = text.trim_end_matches('=');
+    let mut out = Vec::with_capacity(text.len() * 3 / 4);
+    let (mut buf, mut bits) = (0u32, 0);
+    for c in text.bytes() {
+        let value = BASE64URL
+            .iter()
+            .position(|&a| a == c)
+            .ok_or_else(|| Error::InvalidInput("malformed JWT".to_string()))?;
+        buf = (buf << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buf >> bits) as u8);
+            buf &= (1 << bits) - 1;
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host_stub::fixtures;
+    use serde_json::json;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn check(token: &str, key: &VerifyingKey) -> Result<serde_json::Value> {
+        decode(token, key, &Validation::new())
+    }
+
+    fn claims_ok(claims: serde_json::Value, validation: &Validation) -> bool {
+        check_claims(&claims, validation, Duration::from_secs(NOW)).is_ok()
+    }
+
+    #[test]
+    fn test_base64url_round_trip() {
+        assert_eq!(base64url_encode(b"Man"), "TWFu");
+        assert_eq!(base64url_encode(&[0xfb, 0xff]), "-_8");
+        assert_eq!(base64url_decode("-_8").unwrap(), [0xfb, 0xff]);
+        // Padding is tolerated, the standard alphabet's `+` and `/` are not
+        assert_eq!(base64url_decode("TWE=").unwrap(), b"Ma");
+        assert!(base64url_decode("+/8").is_err());
+
+        let data: Vec<u8> = (0..=255).collect();
+        for len in 0..16 {
+            let encoded = base64url_encode(&data[..len]);
+            assert!(!encoded.contains('='));
+            assert_eq!(base64url_decode(&encoded).unwrap(), &data[..len]);
+        }
+        assert_eq!(base64url_decode(&base64url_encode(&data)).unwrap(), data);
+    }
+
+    #[test]
+    fn test_decode_rfc7515_example() {
+        fixtures::crypto();
+        fixtures::clock(1_300_819_300);
+        // RFC 7515 appendix A.1
+        let token = "eyJ0eXAiOiJKV1QiLA0KICJhbGciOiJIUzI1NiJ9\
+                     .eyJpc3MiOiJqb2UiLA0KICJleHAiOjEzMDA4MTkzODAsDQogImh0dHA6Ly9leGFtcGxlLmNvbS9pc19yb290Ijp0cnVlfQ\
+                     .dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+        let key = "AyM1SysPpbyDfgZld3umj1qzKObwVMkoqQ-EstJQLr_T-1qS0gZH75aKtMN3Yj0iPS4hcgUuTwjAzZr1Z9CAow";
+        let key = VerifyingKey::hs256(&base64url_decode(key).unwrap());
+
+        let claims: serde_json::Value = decode(token, &key, &Validation::new().issuer("joe")).unwrap();
+        assert_eq!(claims["http://example.com/is_root"], true);
+
+        let forged = format!("{}A", &token[..token.len() - 1]);
+        assert!(matches!(check(&forged, &key), Err(Error::PermissionDenied)));
+        assert!(matches!(check(token, &VerifyingKey::hs256(b"other")), Err(Error::PermissionDenied)));
+    }
+
+    #[test]
+    fn test_encode_decode_round_trip() {
+        fixtures::crypto();
+        fixtures::clock(NOW);
+        let key = SigningKey::hs256(b"secret").kid("k1");
+        let token = encode(&json!({ "sub": "alice", "exp": NOW + 60 }), &key).unwrap();
+
+        let header = base64url_decode(token.split('.').next().unwrap()).unwrap();
+        let header: serde_json::Value = serde_json::from_slice(&header).unwrap();
+        assert_eq!(header, json!({ "alg": "HS256", "typ": "JWT", "kid": "k1" }));
+        assert_eq!(check(&token, &VerifyingKey::hs256(b"secret")).unwrap()["sub"], "alice");
+        assert!(matches!(check("a.b", &VerifyingKey::hs256(b"secret")), Err(Error::InvalidInput(_))));
+    }
+
+    #[test]
+    fn test_algorithm_is_pinned_by_the_key() {
+        fixtures::crypto();
+        fixtures::clock(NOW);
+        // An HS256 token, e.g. one MACed with the RSA public key as secret,
+        // is refused by an RS256 key before any RSA check is made
+        let token = encode(&json!({ "exp": NOW + 60 }), &SigningKey::hs256(b"-----BEGIN PUBLIC KEY-----")).unwrap();
+        assert!(matches!(
+            check(&token, &VerifyingKey::rs256("-----BEGIN PUBLIC KEY-----")),
+            Err(Error::PermissionDenied)
+        ));
+
+        // And an RS256 header does not make an HS256 key verify with RSA
+        let header = base64url_encode(br#"{"alg":"RS256","typ":"JWT"}"#);
+        let payload = base64url_encode(json!({ "exp": NOW + 60 }).to_string().as_bytes());
+        let token = format!("{}.{}.{}", header, payload, base64url_encode(b"sig"));
+        assert!(matches!(check(&token, &VerifyingKey::hs256(b"secret")), Err(Error::PermissionDenied)));
+    }
+
+    #[test]
+    fn test_exp_and_nbf_with_leeway() {
+        let validation = Validation::new().leeway(Duration::from_secs(30));
+        assert!(claims_ok(json!({ "exp": NOW + 1 }), &validation));
+        assert!(claims_ok(json!({ "exp": NOW - 29 }), &validation));
+        assert!(!claims_ok(json!({ "exp": NOW - 30 }), &validation));
+        assert!(claims_ok(json!({ "exp": NOW + 60, "nbf": NOW + 30 }), &validation));
+        assert!(!claims_ok(json!({ "exp": NOW + 60, "nbf": NOW + 31 }), &validation));
+
+        // exp is required unless allowed to be missing
+        assert!(!claims_ok(json!({}), &validation));
+        assert!(claims_ok(json!({}), &validation.clone().allow_no_expiry()));
+    }
+
+    #[test]
+    fn test_non_integer_times_are_refused() {
+        let validation = Validation::new().allow_no_expiry();
+        for exp in [json!("2099-01-01"), json!(1.5e10), json!(-1), json!(null), json!({})] {
+            assert!(!claims_ok(json!({ "exp": exp }), &validation), "exp {} was accepted", exp);
+            assert!(!claims_ok(json!({ "nbf": exp }), &validation), "nbf {} was accepted", exp);
+        }
+    }
+
+    #[test]
+    fn test_issuer_and_audience() {
+        let validation = Validation::new().issuer("admin").audience("agfs");
+        let exp = NOW + 60;
+        assert!(claims_ok(json!({ "exp": exp, "iss": "admin", "aud": "agfs" }), &validation));
+        assert!(claims_ok(json!({ "exp": exp, "iss": "admin", "aud": ["other", "agfs"] }), &validation));
+        assert!(!claims_ok(json!({ "exp": exp, "iss": "admin", "aud": ["other"] }), &validation));
+        assert!(!claims_ok(json!({ "exp": exp, "iss": "admin", "aud": [] }), &validation));
+        assert!(!claims_ok(json!({ "exp": exp, "iss": "admin" }), &validation));
+        assert!(!claims_ok(json!({ "exp": exp, "iss": "user", "aud": "agfs" }), &validation));
+    }
+}
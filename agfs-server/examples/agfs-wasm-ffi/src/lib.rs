@@ -9,6 +9,25 @@
 //! - **Flexible**: Support for both read-only and read-write filesystems
 //! - **Type-safe**: Strong typing for all filesystem operations
 //!
+//! # Cargo features
+//!
+//! - `http` (default): the host HTTP client and the OAuth2, SigV4 and
+//!   rate-limit helpers built on it
+//! - `hostfs` (default): host filesystem access, and the cache directory,
+//!   write journal and resumable uploads kept on it
+//! - `ai` (default): completions from the host's language model, `HostAI`
+//! - `embed` (default): text embeddings from the host, `HostEmbed`
+//! - `image` (default): thumbnails and other transforms, `HostImage`
+//! - `mounts` (default): paths of other agfs mounts, `HostMounts`
+//! - `queue` (default): work queues kept by the host, `HostQueue`
+//! - `kv`, `sockets`: reserved for the host key-value store and sockets;
+//!   the SDK has no bindings for them yet, so they import nothing
+//!
+//! `OAuth2Client` and `DownloadCache` need both `http` and `hostfs`.
+//!
+//! A plugin built without a feature does not import its host functions, so
+//! the server can tell a plugin without network access from its imports.
+//!
 //! # Example
 //!
 //! ```ignore
@@ -57,29 +76,170 @@
 //! export_plugin!(HelloFS);
 //! ```
 
+/// Declare functions imported from the agfs-server `env` module
+///
+/// On wasm32 these are real imports. Native builds (e.g. `cargo test` of a
+/// plugin crate) get stand-ins that call the stub a test set with
+/// [`host_stub::set`], and panic if there is none, so plugins still link and
+/// can unit-test code paths that reach the host.
+macro_rules! host_imports {
+    ($(fn $name:ident($($arg:ident: $ty:ty),* $(,)?) -> $ret:ty;)*) => {
+        #[cfg(target_arch = "wasm32")]
+        #[link(wasm_import_module = "env")]
+        extern "C" {
+            $(fn $name($($arg: $ty),*) -> $ret;)*
+        }
+
+        $(
+            #[cfg(not(target_arch = "wasm32"))]
+            unsafe fn $name($($arg: $ty),*) -> $ret {
+                use crate::host_stub::HostValue;
+                <$ret>::from_u64(crate::host_stub::call(stringify!($name), &[$($arg.to_u64()),*]))
+            }
+        )*
+    };
+}
+
+pub mod archive;
+pub mod breaker;
+pub mod build_info;
+pub mod clock;
+pub mod conflict;
+pub mod crypto;
+pub mod deadline;
+#[cfg(all(feature = "http", feature = "hostfs"))]
+pub mod downloads;
 pub mod ffi;
 pub mod filesystem;
+pub mod jwt;
 pub mod macros;
+pub mod manifest;
 pub mod memory;
+#[cfg(all(feature = "http", feature = "hostfs"))]
+pub mod oauth2;
+pub mod path;
+#[cfg(feature = "http")]
+pub mod ratelimit;
+pub mod retry;
+#[cfg(feature = "http")]
+pub mod sigv4;
+pub mod stream;
 pub mod types;
+#[cfg(feature = "ai")]
+pub mod host_ai;
+#[cfg(feature = "hostfs")]
+pub mod host_cache;
+#[cfg(feature = "embed")]
+pub mod host_embed;
+#[cfg(feature = "hostfs")]
 pub mod host_fs;
+#[cfg(feature = "image")]
+pub mod host_image;
+pub mod host_info;
+#[cfg(feature = "hostfs")]
+pub mod host_journal;
+#[cfg(feature = "mounts")]
+pub mod host_mounts;
+#[cfg(feature = "queue")]
+pub mod host_queue;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod host_stub;
+#[cfg(feature = "hostfs")]
+pub mod host_upload;
+#[cfg(feature = "http")]
 pub mod host_http;
 
 // Re-export serde_json for use in macros
 pub use serde_json;
 
 // Re-exports for convenience
-pub use filesystem::{FileSystem, HandleFS, ReadOnlyFileSystem};
-pub use types::{Config, ConfigParameter, Error, FileInfo, MetaData, OpenFlag, Result, WriteFlag};
+pub use archive::{ArchiveView, ExportScheduler};
+pub use breaker::{CircuitBreaker, CircuitState};
+pub use agfs_core::buffer::WriteBuffer;
+pub use agfs_core::cache::{self, CachedFs, NegativeCache, RenderCache};
+pub use conflict::{ConflictPolicy, ConflictTracker};
+#[cfg(all(feature = "http", feature = "hostfs"))]
+pub use downloads::DownloadCache;
+pub use agfs_core::control::{self, ControlFs};
+pub use agfs_core::diff::{self, DiffFs};
+pub use agfs_core::expiry::{self, ExpiryFs};
+pub use agfs_core::fifo::{self, FifoFiles, FifoFs, Fifos};
+pub use agfs_core::frontmatter::{self, FrontMatter};
+pub use agfs_core::hidden::{self, HiddenFs};
+pub use agfs_core::encoding;
+pub use agfs_core::html2md;
+pub use agfs_core::inode::InodeMap;
+pub use agfs_core::{eprintln, log};
+pub use agfs_core::namer::{self, UniqueNamer};
+pub use agfs_core::mime;
+pub use agfs_core::normalize::{self, NormalizeFs};
+pub use agfs_core::paginate::{self, Page, Paginator};
+pub use agfs_core::policy::PolicyFs;
+pub use agfs_core::redact;
+#[cfg(feature = "http")]
+pub use ratelimit::RateLimitGuard;
+pub use retry::RetryPolicy;
+pub use agfs_core::table::{self, Table};
+pub use agfs_core::template::{self, Template};
+pub use agfs_core::time;
+pub use agfs_core::vector::{self, VectorIndex};
+pub use agfs_core::verify::{self, VerifyFs};
+pub use filesystem::{FileSystem, HandleFS, ReadOnlyFileSystem, StreamFS, UploadFS};
+pub use types::{
+    Advice, Capabilities, Config, ConfigParameter, Error, FileInfo, FileKind, FsOp, FsSchema, Listing, MetaData,
+    MountGrant, OpenFlag, PathSchema, RangeLock, Result, UploadSession, WarmupProgress, WriteFlag,
+};
+#[cfg(feature = "ai")]
+pub use host_ai::{CompletionOptions, HostAI};
+#[cfg(feature = "hostfs")]
+pub use host_cache::HostCacheDir;
+#[cfg(feature = "embed")]
+pub use host_embed::HostEmbed;
+#[cfg(feature = "hostfs")]
 pub use host_fs::HostFS;
-pub use host_http::{Http, HttpRequest, HttpResponse};
+#[cfg(feature = "image")]
+pub use host_image::{HostImage, Image, ImageFormat};
+pub use host_info::{HostCapabilities, HostInfo};
+#[cfg(feature = "hostfs")]
+pub use host_journal::{JournalEntry, WriteJournal};
+#[cfg(feature = "mounts")]
+pub use host_mounts::HostMounts;
+#[cfg(feature = "queue")]
+pub use host_queue::HostQueue;
+#[cfg(feature = "hostfs")]
+pub use host_upload::HostUploads;
+pub use manifest::Manifest;
+#[cfg(all(feature = "http", feature = "hostfs"))]
+pub use oauth2::OAuth2Client;
+#[cfg(feature = "http")]
+pub use sigv4::{Credentials, SigV4};
+#[cfg(feature = "http")]
+pub use host_http::{Http, HttpRequest, HttpResponse, PoolOptions, ProxyOptions, TlsOptions};
 
 /// Prelude module with common imports
 pub mod prelude {
     pub use crate::export_plugin;
     pub use crate::export_handle_plugin;
-    pub use crate::filesystem::{FileSystem, HandleFS, ReadOnlyFileSystem};
-    pub use crate::types::{Config, ConfigParameter, Error, FileInfo, MetaData, OpenFlag, Result, WriteFlag};
+    pub use crate::filesystem::{FileSystem, HandleFS, ReadOnlyFileSystem, StreamFS, UploadFS};
+    pub use crate::types::{
+        Advice, Capabilities, Config, ConfigParameter, Error, FileInfo, FileKind, FsOp, FsSchema, Listing, MetaData,
+        OpenFlag, PathSchema, RangeLock, Result, UploadSession, WarmupProgress, WriteFlag,
+    };
+    #[cfg(feature = "hostfs")]
+    pub use crate::host_cache::HostCacheDir;
+    #[cfg(feature = "hostfs")]
     pub use crate::host_fs::HostFS;
+    pub use crate::host_info::HostInfo;
+    #[cfg(feature = "hostfs")]
+    pub use crate::host_journal::WriteJournal;
+    #[cfg(feature = "mounts")]
+    pub use crate::host_mounts::HostMounts;
+    #[cfg(feature = "hostfs")]
+    pub use crate::host_upload::HostUploads;
+    #[cfg(feature = "http")]
     pub use crate::host_http::{Http, HttpRequest, HttpResponse};
+    #[cfg(all(feature = "http", feature = "hostfs"))]
+    pub use crate::oauth2::OAuth2Client;
+    pub use crate::breaker::CircuitBreaker;
+    pub use crate::retry::RetryPolicy;
 }
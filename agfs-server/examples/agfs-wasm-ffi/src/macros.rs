@@ -1,10 +1,212 @@
 //! Macros for exporting WASM plugin functions
 
 /// Export a FileSystem implementation as a WASM plugin
+///
+/// Optional features are listed after the type, e.g.
+/// `export_plugin!(T, stream, upload)`:
+///
+/// - `stream` exports the `stream_*` functions of a type implementing `StreamFS`
+/// - `upload` exports the `upload_*` functions of a type implementing `UploadFS`
+///
+/// The plugin is exported wrapped in [`ControlFs`](crate::ControlFs), which
+/// adds the standard `/.agfs/` control files (see [`control`](crate::control)).
 #[macro_export]
 macro_rules! export_plugin {
+    (@stream $plugin_type:ty) => {
+        // Open streams keyed by the address of their plugin -> host ring
+        static mut STREAMS: std::collections::BTreeMap<u32, $crate::stream::Channel> = std::collections::BTreeMap::new();
+
+        /// Open a stream and allocate its ring buffers (capacity 0 = default size)
+        /// Returns: On success, low 32 bits = plugin -> host ring ptr (also the stream token),
+        /// high 32 bits = host -> plugin ring ptr. On error, low 32 bits = 0, high 32 bits = error ptr
+        #[no_mangle]
+        pub extern "C" fn stream_open(path_ptr: *const u8, capacity: u32) -> u64 {
+            use $crate::memory::{CString, pack_u64};
+            use $crate::stream::Channel;
+
+            let path = unsafe { CString::from_ptr(path_ptr) };
+
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                match <__AgfsPlugin as $crate::StreamFS>::open_stream(p, &path) {
+                    Ok(id) => {
+                        let channel = Channel::new(id, capacity as usize);
+                        let out = channel.out_ptr() as u32;
+                        let input = channel.input_ptr() as u32;
+                        (*std::ptr::addr_of_mut!(STREAMS)).insert(out, channel);
+                        pack_u64(out, input)
+                    }
+                    Err(e) => {
+                        let err_ptr = $crate::ffi::error_ptr(&e);
+                        pack_u64(0, err_ptr as u32)
+                    }
+                }
+            }
+        }
+
+        /// Refill the plugin -> host ring of a stream
+        /// Returns packed u64: low 32 bits = bytes waiting in the ring, high 32 bits = error ptr (0 = success)
+        #[no_mangle]
+        pub extern "C" fn stream_read(token: u32) -> u64 {
+            use $crate::memory::{CString, pack_u64};
+
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                let result = match (*std::ptr::addr_of!(STREAMS)).get(&token) {
+                    Some(channel) => channel.fill(|buf| {
+                        <__AgfsPlugin as $crate::StreamFS>::stream_read(p, channel.id, buf)
+                    }),
+                    None => Err($crate::Error::InvalidInput("unknown stream".to_string())),
+                };
+                match result {
+                    Ok(n) => pack_u64(n as u32, 0),
+                    Err(e) => {
+                        let err_ptr = $crate::ffi::error_ptr(&e);
+                        pack_u64(0, err_ptr as u32)
+                    }
+                }
+            }
+        }
+
+        /// Pass the contents of the host -> plugin ring of a stream to the plugin
+        /// Returns packed u64: low 32 bits = bytes consumed, high 32 bits = error ptr (0 = success)
+        #[no_mangle]
+        pub extern "C" fn stream_write(token: u32) -> u64 {
+            use $crate::memory::{CString, pack_u64};
+
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                let result = match (*std::ptr::addr_of!(STREAMS)).get(&token) {
+                    Some(channel) => channel.drain(|data| {
+                        <__AgfsPlugin as $crate::StreamFS>::stream_write(p, channel.id, data)
+                    }),
+                    None => Err($crate::Error::InvalidInput("unknown stream".to_string())),
+                };
+                match result {
+                    Ok(n) => pack_u64(n as u32, 0),
+                    Err(e) => {
+                        let err_ptr = $crate::ffi::error_ptr(&e);
+                        pack_u64(0, err_ptr as u32)
+                    }
+                }
+            }
+        }
+
+        /// Close a stream and free its ring buffers
+        /// Returns: error ptr (null = success)
+        #[no_mangle]
+        pub extern "C" fn stream_close(token: u32) -> *mut u8 {
+            use $crate::ffi::result_to_error_ptr;
+
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                let result = match (*std::ptr::addr_of_mut!(STREAMS)).remove(&token) {
+                    Some(channel) => <__AgfsPlugin as $crate::StreamFS>::close_stream(p, channel.id),
+                    None => Err($crate::Error::InvalidInput("unknown stream".to_string())),
+                };
+                result_to_error_ptr(result)
+            }
+        }
+    };
+    (@upload $plugin_type:ty) => {
+        /// Begin an upload session
+        /// Returns packed u64: low 32 bits = session json ptr, high 32 bits = error ptr (0 = success)
+        #[no_mangle]
+        #[allow(clippy::not_unsafe_ptr_arg_deref)]
+        pub extern "C" fn upload_begin(path_ptr: *const u8) -> u64 {
+            use $crate::memory::CString;
+
+            let path = unsafe { CString::from_ptr(path_ptr) };
+
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                $crate::ffi::upload_result_to_packed(<__AgfsPlugin as $crate::UploadFS>::begin_upload(p, &path))
+            }
+        }
+
+        /// Current state of an upload session
+        /// Returns packed u64: low 32 bits = session json ptr, high 32 bits = error ptr (0 = success)
+        #[no_mangle]
+        #[allow(clippy::not_unsafe_ptr_arg_deref)]
+        pub extern "C" fn upload_status(id_ptr: *const u8) -> u64 {
+            use $crate::memory::CString;
+
+            let id = unsafe { CString::from_ptr(id_ptr) };
+
+            unsafe {
+                let p = PLUGIN.as_ref().expect("Not initialized");
+                $crate::ffi::upload_result_to_packed(<__AgfsPlugin as $crate::UploadFS>::upload_status(p, &id))
+            }
+        }
+
+        /// Append data to an upload session at offset
+        /// Returns packed u64: low 32 bits = session json ptr, high 32 bits = error ptr (0 = success)
+        #[no_mangle]
+        #[allow(clippy::not_unsafe_ptr_arg_deref)]
+        pub extern "C" fn upload_append(id_ptr: *const u8, offset: i64, data_ptr: *const u8, size: usize) -> u64 {
+            use $crate::memory::CString;
+
+            let id = unsafe { CString::from_ptr(id_ptr) };
+            let data = unsafe { std::slice::from_raw_parts(data_ptr, size) };
+
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                $crate::ffi::upload_result_to_packed(
+                    <__AgfsPlugin as $crate::UploadFS>::upload_append(p, &id, offset as u64, data),
+                )
+            }
+        }
+
+        /// Commit an upload session to its path
+        /// Returns: error ptr (null = success)
+        #[no_mangle]
+        #[allow(clippy::not_unsafe_ptr_arg_deref)]
+        pub extern "C" fn upload_commit(id_ptr: *const u8) -> *mut u8 {
+            use $crate::memory::CString;
+            use $crate::ffi::result_to_error_ptr;
+
+            let id = unsafe { CString::from_ptr(id_ptr) };
+
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                result_to_error_ptr(<__AgfsPlugin as $crate::UploadFS>::commit_upload(p, &id))
+            }
+        }
+
+        /// Abort an upload session
+        /// Returns: error ptr (null = success)
+        #[no_mangle]
+        #[allow(clippy::not_unsafe_ptr_arg_deref)]
+        pub extern "C" fn upload_abort(id_ptr: *const u8) -> *mut u8 {
+            use $crate::memory::CString;
+            use $crate::ffi::result_to_error_ptr;
+
+            let id = unsafe { CString::from_ptr(id_ptr) };
+
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                result_to_error_ptr(<__AgfsPlugin as $crate::UploadFS>::abort_upload(p, &id))
+            }
+        }
+
+        /// List open upload sessions
+        /// Returns packed u64: low 32 bits = json array ptr, high 32 bits = error ptr (0 = success)
+        #[no_mangle]
+        pub extern "C" fn upload_list() -> u64 {
+            unsafe {
+                let p = PLUGIN.as_ref().expect("Not initialized");
+                $crate::ffi::upload_result_to_packed(<__AgfsPlugin as $crate::UploadFS>::list_uploads(p))
+            }
+        }
+    };
+    ($plugin_type:ty, $($feature:ident),+ $(,)?) => {
+        $crate::export_plugin!($plugin_type);
+        $($crate::export_plugin!(@$feature $plugin_type);)+
+    };
     ($plugin_type:ty) => {
-        static mut PLUGIN: Option<$plugin_type> = None;
+        // The plugin with the standard `/.agfs/` control files merged in
+        type __AgfsPlugin = $crate::ControlFs<$plugin_type>;
+        static mut PLUGIN: Option<__AgfsPlugin> = None;
 
         // Force type checking
         const _: fn() = || {
@@ -15,7 +217,9 @@ macro_rules! export_plugin {
         #[no_mangle]
         pub extern "C" fn plugin_new() -> usize {
             unsafe {
-                PLUGIN = Some(<$plugin_type>::default());
+                let p = __AgfsPlugin::default();
+                $crate::ffi::declare_capabilities(<__AgfsPlugin as $crate::FileSystem>::capabilities(&p));
+                PLUGIN = Some(p);
             }
             1
         }
@@ -26,7 +230,7 @@ macro_rules! export_plugin {
             use $crate::FileSystem;
             unsafe {
                 let p = PLUGIN.as_ref().expect("Not initialized");
-                CString::new(<$plugin_type as $crate::FileSystem>::name(p)).into_raw()
+                CString::new(<__AgfsPlugin as $crate::FileSystem>::name(p)).into_raw()
             }
         }
 
@@ -36,7 +240,7 @@ macro_rules! export_plugin {
             use $crate::FileSystem;
             unsafe {
                 let p = PLUGIN.as_ref().expect("Not initialized");
-                CString::new(<$plugin_type as $crate::FileSystem>::readme(p)).into_raw()
+                CString::new(&$crate::redact::redact(<__AgfsPlugin as $crate::FileSystem>::readme(p))).into_raw()
             }
         }
 
@@ -46,7 +250,7 @@ macro_rules! export_plugin {
             use $crate::FileSystem;
             unsafe {
                 let p = PLUGIN.as_ref().expect("Not initialized");
-                let params = <$plugin_type as $crate::FileSystem>::config_params(p);
+                let params = <__AgfsPlugin as $crate::FileSystem>::config_params(p);
                 // Serialize to JSON using crate's re-exported serde_json
                 match $crate::serde_json::to_string(&params) {
                     Ok(json) => CString::new(&json).into_raw(),
@@ -55,6 +259,61 @@ macro_rules! export_plugin {
             }
         }
 
+        #[no_mangle]
+        pub extern "C" fn plugin_capabilities() -> *mut u8 {
+            use $crate::memory::CString;
+            unsafe {
+                let p = PLUGIN.as_ref().expect("Not initialized");
+                let caps = <__AgfsPlugin as $crate::FileSystem>::capabilities(p);
+                match $crate::serde_json::to_string(&caps) {
+                    Ok(json) => CString::new(&json).into_raw(),
+                    Err(_) => CString::new("{}").into_raw(),
+                }
+            }
+        }
+
+        const __AGFS_BUILD_INFO_PARTS: [&str; 11] = $crate::build_info::parts(
+            env!("CARGO_PKG_VERSION"),
+            option_env!("AGFS_GIT_HASH"),
+            option_env!("SOURCE_DATE_EPOCH"),
+        );
+
+        /// Build info JSON, readable from the wasm file without running it
+        #[cfg_attr(target_arch = "wasm32", link_section = "agfs_build_info")]
+        #[used]
+        static __AGFS_BUILD_INFO: [u8; $crate::build_info::len(&__AGFS_BUILD_INFO_PARTS)] =
+            $crate::build_info::json(&__AGFS_BUILD_INFO_PARTS);
+
+        /// The build info embedded in the `agfs_build_info` section
+        #[no_mangle]
+        pub extern "C" fn plugin_build_info() -> *mut u8 {
+            use $crate::memory::CString;
+            match ::std::str::from_utf8(&__AGFS_BUILD_INFO) {
+                Ok(json) => CString::new(json).into_raw(),
+                Err(_) => CString::new("{}").into_raw(),
+            }
+        }
+
+        /// Build manifest as JSON; the version is the plugin crate's
+        #[no_mangle]
+        pub extern "C" fn plugin_manifest() -> *mut u8 {
+            use $crate::memory::CString;
+            unsafe {
+                let p = PLUGIN.as_ref().expect("Not initialized");
+                let manifest = $crate::manifest::Manifest::new(
+                    <__AgfsPlugin as $crate::FileSystem>::name(p),
+                    env!("CARGO_PKG_VERSION"),
+                    <__AgfsPlugin as $crate::FileSystem>::capabilities(p),
+                )
+                .build_hash(option_env!("AGFS_BUILD_HASH"))
+                .signature(option_env!("AGFS_MANIFEST_SIGNATURE"));
+                match $crate::serde_json::to_string(&manifest) {
+                    Ok(json) => CString::new(&json).into_raw(),
+                    Err(_) => CString::new("{}").into_raw(),
+                }
+            }
+        }
+
         #[no_mangle]
         pub extern "C" fn plugin_validate(config_ptr: *const u8) -> *mut u8 {
             use $crate::ffi::{read_config, result_to_error_ptr};
@@ -65,7 +324,10 @@ macro_rules! export_plugin {
             };
             unsafe {
                 let p = PLUGIN.as_ref().expect("Not initialized");
-                result_to_error_ptr::<()>(<$plugin_type as $crate::FileSystem>::validate(p, &config))
+                // Register credentials first so a failure quoting them is scrubbed
+                let params = <__AgfsPlugin as $crate::FileSystem>::config_params(p);
+                $crate::redact::add_config_secrets(&config, &params);
+                result_to_error_ptr::<()>(<__AgfsPlugin as $crate::FileSystem>::validate(p, &config))
             }
         }
 
@@ -79,7 +341,14 @@ macro_rules! export_plugin {
             };
             unsafe {
                 let p = PLUGIN.as_mut().expect("Not initialized");
-                result_to_error_ptr::<()>(<$plugin_type as $crate::FileSystem>::initialize(p, &config))
+                let params = <__AgfsPlugin as $crate::FileSystem>::config_params(p);
+                $crate::redact::add_config_secrets(&config, &params);
+                // initialize() may already make requests through the proxy
+                $crate::ffi::configure_host(&config);
+                let result = <__AgfsPlugin as $crate::FileSystem>::initialize(p, &config);
+                // Capabilities may depend on the config just applied
+                $crate::ffi::declare_capabilities(<__AgfsPlugin as $crate::FileSystem>::capabilities(p));
+                result_to_error_ptr::<()>(result)
             }
         }
 
@@ -89,7 +358,7 @@ macro_rules! export_plugin {
             use $crate::FileSystem;
             unsafe {
                 let p = PLUGIN.as_mut().expect("Not initialized");
-                result_to_error_ptr::<()>(<$plugin_type as $crate::FileSystem>::shutdown(p))
+                result_to_error_ptr::<()>(<__AgfsPlugin as $crate::FileSystem>::shutdown(p))
             }
         }
 
@@ -102,7 +371,7 @@ macro_rules! export_plugin {
 
             unsafe {
                 let p = PLUGIN.as_ref().expect("Not initialized");
-                match <$plugin_type as $crate::FileSystem>::read(p, &path, offset, size) {
+                match <__AgfsPlugin as $crate::FileSystem>::read(p, &path, offset, size) {
                     Ok(data) => {
                         let len = data.len() as u32;
                         let buffer = Buffer::from_bytes(&data);
@@ -114,6 +383,17 @@ macro_rules! export_plugin {
             }
         }
 
+        /// Read several whole files; paths_ptr is a JSON array of paths
+        /// Returns packed u64: low 32 bits = framed results ptr, high 32 bits = length
+        #[no_mangle]
+        #[allow(clippy::not_unsafe_ptr_arg_deref)]
+        pub extern "C" fn fs_read_many(paths_ptr: *const u8) -> u64 {
+            unsafe {
+                let p = PLUGIN.as_ref().expect("Not initialized");
+                $crate::ffi::handle_read_many::<__AgfsPlugin>(p, paths_ptr)
+            }
+        }
+
         #[no_mangle]
         pub extern "C" fn fs_stat(path_ptr: *const u8) -> u64 {
             use $crate::memory::{CString, pack_u64};
@@ -124,16 +404,16 @@ macro_rules! export_plugin {
 
             unsafe {
                 let p = PLUGIN.as_ref().expect("Not initialized");
-                match <$plugin_type as $crate::FileSystem>::stat(p, &path) {
+                match <__AgfsPlugin as $crate::FileSystem>::stat(p, &path) {
                     Ok(info) => match fileinfo_to_json_ptr(&info) {
                         Ok(json_ptr) => pack_u64(json_ptr as u32, 0),
                         Err(e) => {
-                            let err_ptr = CString::new(&e.to_string()).into_raw();
+                            let err_ptr = $crate::ffi::error_ptr(&e);
                             pack_u64(0, err_ptr as u32)
                         }
                     },
                     Err(e) => {
-                        let err_ptr = CString::new(&e.to_string()).into_raw();
+                        let err_ptr = $crate::ffi::error_ptr(&e);
                         pack_u64(0, err_ptr as u32)
                     }
                 }
@@ -150,22 +430,54 @@ macro_rules! export_plugin {
 
             unsafe {
                 let p = PLUGIN.as_ref().expect("Not initialized");
-                match <$plugin_type as $crate::FileSystem>::readdir(p, &path) {
+                match <__AgfsPlugin as $crate::FileSystem>::readdir(p, &path) {
                     Ok(infos) => match fileinfo_vec_to_json_ptr(&infos) {
                         Ok(json_ptr) => pack_u64(json_ptr as u32, 0),
                         Err(e) => {
-                            let err_ptr = CString::new(&e.to_string()).into_raw();
+                            let err_ptr = $crate::ffi::error_ptr(&e);
                             pack_u64(0, err_ptr as u32)
                         }
                     },
                     Err(e) => {
-                        let err_ptr = CString::new(&e.to_string()).into_raw();
+                        let err_ptr = $crate::ffi::error_ptr(&e);
                         pack_u64(0, err_ptr as u32)
                     }
                 }
             }
         }
 
+        /// Walk the subtree at path, at most depth levels deep (0 = no limit)
+        /// Returns packed u64: low 32 bits = json ptr ([{"Path", "Info"}]), high 32 bits = error ptr
+        #[no_mangle]
+        #[allow(clippy::not_unsafe_ptr_arg_deref)]
+        pub extern "C" fn fs_walk(path_ptr: *const u8, depth: u32) -> u64 {
+            use $crate::memory::CString;
+
+            let path = unsafe { CString::from_ptr(path_ptr) };
+
+            unsafe {
+                let p = PLUGIN.as_ref().expect("Not initialized");
+                $crate::ffi::walk_to_packed(<__AgfsPlugin as $crate::FileSystem>::walk(p, &path, depth as usize))
+            }
+        }
+
+        /// List path unless its generation is still generation
+        /// Returns packed u64: low 32 bits = json ptr ({"entries", "generation"}, or null if unchanged),
+        /// high 32 bits = error ptr
+        #[no_mangle]
+        #[allow(clippy::not_unsafe_ptr_arg_deref)]
+        pub extern "C" fn fs_readdir_if_changed(path_ptr: *const u8, generation: u64) -> u64 {
+            use $crate::memory::CString;
+
+            let path = unsafe { CString::from_ptr(path_ptr) };
+
+            unsafe {
+                let p = PLUGIN.as_ref().expect("Not initialized");
+                let listing = <__AgfsPlugin as $crate::FileSystem>::readdir_if_changed(p, &path, generation);
+                $crate::ffi::upload_result_to_packed(listing)
+            }
+        }
+
         /// Write to file with offset and flags
         /// Returns packed u64: high 32 bits = bytes written, low 32 bits = error ptr (0 = success)
         #[no_mangle]
@@ -179,19 +491,124 @@ macro_rules! export_plugin {
 
             unsafe {
                 let p = PLUGIN.as_mut().expect("Not initialized");
-                match <$plugin_type as $crate::FileSystem>::write(p, &path, data, offset, WriteFlag::from(flags)) {
+                match <__AgfsPlugin as $crate::FileSystem>::write(p, &path, data, offset, WriteFlag::from(flags)) {
                     Ok(bytes_written) => {
                         // Pack bytes_written in high 32 bits, 0 (success) in low 32 bits
                         pack_u64(bytes_written as u32, 0)
                     }
                     Err(e) => {
-                        let err_ptr = CString::new(&e.to_string()).into_raw();
+                        let err_ptr = $crate::ffi::error_ptr(&e);
                         pack_u64(0, err_ptr as u32)
                     }
                 }
             }
         }
 
+        /// Apply a JSON array of operations with FileSystem::batch
+        /// Returns packed u64: low 32 bits = results json ptr, high 32 bits = error ptr
+        #[no_mangle]
+        #[allow(clippy::not_unsafe_ptr_arg_deref)]
+        pub extern "C" fn fs_batch(ops_ptr: *const u8) -> u64 {
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                $crate::ffi::handle_batch::<__AgfsPlugin>(p, ops_ptr)
+            }
+        }
+
+        /// 1 if fs_batch applies all operations or none
+        #[no_mangle]
+        pub extern "C" fn fs_atomic_batch() -> u32 {
+            unsafe {
+                let p = PLUGIN.as_ref().expect("Not initialized");
+                <__AgfsPlugin as $crate::FileSystem>::atomic_batch(p) as u32
+            }
+        }
+
+        #[no_mangle]
+        pub extern "C" fn fs_maintain() -> *mut u8 {
+            use $crate::ffi::result_to_error_ptr;
+
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                result_to_error_ptr::<()>(<__AgfsPlugin as $crate::FileSystem>::maintain(p))
+            }
+        }
+
+        /// One warm-up slice; returns packed `{"done", "total"}` JSON / error ptr
+        #[no_mangle]
+        pub extern "C" fn fs_warmup(budget_ms: u64) -> u64 {
+            use $crate::ffi::upload_result_to_packed;
+
+            let budget = ::std::time::Duration::from_millis(budget_ms);
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                upload_result_to_packed(<__AgfsPlugin as $crate::FileSystem>::warmup(p, budget))
+            }
+        }
+
+        /// State for the instance replacing this one
+        /// Returns packed u64: low 32 bits = data ptr, high 32 bits = length;
+        /// on failure the data ptr is 0 and the high bits hold the error ptr
+        #[no_mangle]
+        pub extern "C" fn fs_export_state() -> u64 {
+            use $crate::memory::{Buffer, pack_u64};
+
+            unsafe {
+                let p = PLUGIN.as_ref().expect("Not initialized");
+                match <__AgfsPlugin as $crate::FileSystem>::export_state(p) {
+                    Ok(state) if state.is_empty() => 0,
+                    Ok(state) => pack_u64(Buffer::from_bytes(&state).into_raw() as u32, state.len() as u32),
+                    Err(e) => pack_u64(0, $crate::ffi::error_ptr(&e) as u32),
+                }
+            }
+        }
+
+        #[no_mangle]
+        #[allow(clippy::not_unsafe_ptr_arg_deref)]
+        pub extern "C" fn fs_import_state(state_ptr: *const u8, size: usize) -> *mut u8 {
+            use $crate::ffi::result_to_error_ptr;
+
+            let state: &[u8] = if size == 0 { &[] } else { unsafe { std::slice::from_raw_parts(state_ptr, size) } };
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                result_to_error_ptr::<()>(<__AgfsPlugin as $crate::FileSystem>::import_state(p, state))
+            }
+        }
+
+        #[no_mangle]
+        pub extern "C" fn fs_begin_session() -> *mut u8 {
+            use $crate::ffi::result_to_error_ptr;
+
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                result_to_error_ptr::<()>(<__AgfsPlugin as $crate::FileSystem>::begin_write_session(p))
+            }
+        }
+
+        #[no_mangle]
+        #[allow(clippy::not_unsafe_ptr_arg_deref)]
+        pub extern "C" fn fs_commit_session(msg_ptr: *const u8) -> *mut u8 {
+            use $crate::memory::CString;
+            use $crate::ffi::result_to_error_ptr;
+
+            let message = unsafe { CString::from_ptr(msg_ptr) };
+
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                result_to_error_ptr::<()>(<__AgfsPlugin as $crate::FileSystem>::commit_session(p, &message))
+            }
+        }
+
+        #[no_mangle]
+        pub extern "C" fn fs_abort_session() -> *mut u8 {
+            use $crate::ffi::result_to_error_ptr;
+
+            unsafe {
+                let p = PLUGIN.as_mut().expect("Not initialized");
+                result_to_error_ptr::<()>(<__AgfsPlugin as $crate::FileSystem>::abort_session(p))
+            }
+        }
+
         #[no_mangle]
         pub extern "C" fn fs_create(path_ptr: *const u8) -> *mut u8 {
             use $crate::memory::CString;
@@ -202,7 +619,7 @@ macro_rules! export_plugin {
 
             unsafe {
                 let p = PLUGIN.as_mut().expect("Not initialized");
-                result_to_error_ptr::<()>(<$plugin_type as $crate::FileSystem>::create(p, &path))
+                result_to_error_ptr::<()>(<__AgfsPlugin as $crate::FileSystem>::create(p, &path))
             }
         }
 
@@ -216,7 +633,7 @@ macro_rules! export_plugin {
 
             unsafe {
                 let p = PLUGIN.as_mut().expect("Not initialized");
-                result_to_error_ptr::<()>(<$plugin_type as $crate::FileSystem>::mkdir(p, &path, perm))
+                result_to_error_ptr::<()>(<__AgfsPlugin as $crate::FileSystem>::mkdir(p, &path, perm))
             }
         }
 
@@ -230,7 +647,7 @@ macro_rules! export_plugin {
 
             unsafe {
                 let p = PLUGIN.as_mut().expect("Not initialized");
-                result_to_error_ptr::<()>(<$plugin_type as $crate::FileSystem>::remove(p, &path))
+                result_to_error_ptr::<()>(<__AgfsPlugin as $crate::FileSystem>::remove(p, &path))
             }
         }
 
@@ -244,7 +661,7 @@ macro_rules! export_plugin {
 
             unsafe {
                 let p = PLUGIN.as_mut().expect("Not initialized");
-                result_to_error_ptr::<()>(<$plugin_type as $crate::FileSystem>::remove_all(p, &path))
+                result_to_error_ptr::<()>(<__AgfsPlugin as $crate::FileSystem>::remove_all(p, &path))
             }
         }
 
@@ -259,7 +676,7 @@ macro_rules! export_plugin {
 
             unsafe {
                 let p = PLUGIN.as_mut().expect("Not initialized");
-                result_to_error_ptr::<()>(<$plugin_type as $crate::FileSystem>::rename(p, &old_path, &new_path))
+                result_to_error_ptr::<()>(<__AgfsPlugin as $crate::FileSystem>::rename(p, &old_path, &new_path))
             }
         }
 
@@ -273,7 +690,7 @@ macro_rules! export_plugin {
 
             unsafe {
                 let p = PLUGIN.as_mut().expect("Not initialized");
-                result_to_error_ptr::<()>(<$plugin_type as $crate::FileSystem>::chmod(p, &path, mode))
+                result_to_error_ptr::<()>(<__AgfsPlugin as $crate::FileSystem>::chmod(p, &path, mode))
             }
         }
 
@@ -301,7 +718,94 @@ macro_rules! export_plugin {
             SHARED_BUFFER_SIZE as u32
         }
 
+        // Large results are returned through the output buffer in chunks
+        static mut CHUNKS: $crate::memory::ChunkedResults = $crate::memory::ChunkedResults::new();
+
+        /// Read file data into the output buffer
+        /// Returns: On success, low 32 bits = bytes in the output buffer, high 32 bits = continuation
+        /// token (0 if complete; pass it to read_chunk for the rest). On error, low 32 bits = 0,
+        /// high 32 bits = error ptr
+        #[no_mangle]
+        #[allow(clippy::not_unsafe_ptr_arg_deref)]
+        pub extern "C" fn fs_read_chunked(path_ptr: *const u8, offset: i64, size: i64) -> u64 {
+            use $crate::memory::{CString, pack_u64};
+            use $crate::FileSystem;
+
+            let path = unsafe { CString::from_ptr(path_ptr) };
+
+            unsafe {
+                let p = PLUGIN.as_ref().expect("Not initialized");
+                match <__AgfsPlugin as $crate::FileSystem>::read(p, &path, offset, size) {
+                    Ok(data) => {
+                        let chunks = &mut *std::ptr::addr_of_mut!(CHUNKS);
+                        let out = &mut *std::ptr::addr_of_mut!(OUTPUT_BUFFER);
+                        let (len, token) = chunks.start(data, out);
+                        pack_u64(len, token)
+                    }
+                    Err(e) => {
+                        let err_ptr = $crate::ffi::error_ptr(&e);
+                        pack_u64(0, err_ptr as u32)
+                    }
+                }
+            }
+        }
+
+        /// Read file data like fs_read_chunked, waiting up to timeout_ms for data at offset
+        /// Returns: as fs_read_chunked; no bytes and no token if nothing arrived in time
+        #[no_mangle]
+        #[allow(clippy::not_unsafe_ptr_arg_deref)]
+        pub extern "C" fn fs_poll_read(path_ptr: *const u8, offset: i64, size: i64, timeout_ms: u64) -> u64 {
+            use $crate::memory::{CString, pack_u64};
+            use $crate::FileSystem;
+
+            let path = unsafe { CString::from_ptr(path_ptr) };
+            let timeout = std::time::Duration::from_millis(timeout_ms);
+
+            unsafe {
+                let p = PLUGIN.as_ref().expect("Not initialized");
+                match <__AgfsPlugin as $crate::FileSystem>::poll_read(p, &path, offset, size, timeout) {
+                    Ok(data) => {
+                        let chunks = &mut *std::ptr::addr_of_mut!(CHUNKS);
+                        let out = &mut *std::ptr::addr_of_mut!(OUTPUT_BUFFER);
+                        let (len, token) = chunks.start(data, out);
+                        pack_u64(len, token)
+                    }
+                    Err(e) => {
+                        let err_ptr = $crate::ffi::error_ptr(&e);
+                        pack_u64(0, err_ptr as u32)
+                    }
+                }
+            }
+        }
+
+        /// Copy the next chunk of a large result into the output buffer
+        /// Returns: low 32 bits = bytes in the output buffer, high 32 bits = next token (0 when done).
+        /// An unknown token returns 0
+        #[no_mangle]
+        pub extern "C" fn read_chunk(token: u32) -> u64 {
+            use $crate::memory::pack_u64;
+
+            unsafe {
+                let chunks = &mut *std::ptr::addr_of_mut!(CHUNKS);
+                let out = &mut *std::ptr::addr_of_mut!(OUTPUT_BUFFER);
+                match chunks.next(token, out) {
+                    Some((len, next)) => pack_u64(len, next),
+                    None => 0,
+                }
+            }
+        }
+
+        /// Drop the rest of a chunked result the host no longer wants
+        #[no_mangle]
+        pub extern "C" fn cancel_chunks(token: u32) {
+            unsafe {
+                (*std::ptr::addr_of_mut!(CHUNKS)).cancel(token);
+            }
+        }
+
         // Export malloc and free for Go compatibility (fallback for large data)
+        // Only on wasm: natively these would replace libc's allocator in test binaries
+        #[cfg(target_arch = "wasm32")]
         #[no_mangle]
         pub extern "C" fn malloc(size: usize) -> *mut u8 {
             use std::alloc::{alloc, Layout};
@@ -316,6 +820,7 @@ macro_rules! export_plugin {
             }
         }
 
+        #[cfg(target_arch = "wasm32")]
         #[no_mangle]
         pub extern "C" fn free(ptr: *mut u8, size: usize) {
             use std::alloc::{dealloc, Layout};
@@ -334,8 +839,14 @@ macro_rules! export_plugin {
 
 /// Export a HandleFS implementation as a WASM plugin with handle support
 /// This macro exports all FileSystem functions plus HandleFS handle operations
+///
+/// `export_handle_plugin!(T, stream)` also exports the `StreamFS` functions.
 #[macro_export]
 macro_rules! export_handle_plugin {
+    ($plugin_type:ty, stream) => {
+        $crate::export_handle_plugin!($plugin_type);
+        $crate::export_plugin!(@stream $plugin_type);
+    };
     ($plugin_type:ty) => {
         // First export all the basic FileSystem functions
         $crate::export_plugin!($plugin_type);
@@ -353,14 +864,14 @@ macro_rules! export_handle_plugin {
 
             unsafe {
                 let p = PLUGIN.as_mut().expect("Not initialized");
-                match <$plugin_type as $crate::HandleFS>::open_handle(p, &path, $crate::OpenFlag::from(flags), mode) {
+                match <__AgfsPlugin as $crate::HandleFS>::open_handle(p, &path, $crate::OpenFlag::from(flags), mode) {
                     Ok(id) => {
                         // Return handle ID as i64 (cast to u64)
                         id as u64
                     }
                     Err(e) => {
                         // Error: high 32 bits = error ptr, low 32 bits = 0
-                        let err_ptr = CString::new(&e.to_string()).into_raw();
+                        let err_ptr = $crate::ffi::error_ptr(&e);
                         pack_u64(0, err_ptr as u32)
                     }
                 }
@@ -378,10 +889,10 @@ macro_rules! export_handle_plugin {
 
             unsafe {
                 let p = PLUGIN.as_mut().expect("Not initialized");
-                match <$plugin_type as $crate::HandleFS>::handle_read(p, id, buf) {
+                match <__AgfsPlugin as $crate::HandleFS>::handle_read(p, id, buf) {
                     Ok(n) => pack_u64(n as u32, 0),
                     Err(e) => {
-                        let err_ptr = CString::new(&e.to_string()).into_raw();
+                        let err_ptr = $crate::ffi::error_ptr(&e);
                         pack_u64(0, err_ptr as u32)
                     }
                 }
@@ -399,10 +910,10 @@ macro_rules! export_handle_plugin {
 
             unsafe {
                 let p = PLUGIN.as_ref().expect("Not initialized");
-                match <$plugin_type as $crate::HandleFS>::handle_read_at(p, id, buf, offset) {
+                match <__AgfsPlugin as $crate::HandleFS>::handle_read_at(p, id, buf, offset) {
                     Ok(n) => pack_u64(n as u32, 0),
                     Err(e) => {
-                        let err_ptr = CString::new(&e.to_string()).into_raw();
+                        let err_ptr = $crate::ffi::error_ptr(&e);
                         pack_u64(0, err_ptr as u32)
                     }
                 }
@@ -419,10 +930,10 @@ macro_rules! export_handle_plugin {
 
             unsafe {
                 let p = PLUGIN.as_mut().expect("Not initialized");
-                match <$plugin_type as $crate::HandleFS>::handle_write(p, id, data) {
+                match <__AgfsPlugin as $crate::HandleFS>::handle_write(p, id, data) {
                     Ok(n) => pack_u64(n as u32, 0),
                     Err(e) => {
-                        let err_ptr = CString::new(&e.to_string()).into_raw();
+                        let err_ptr = $crate::ffi::error_ptr(&e);
                         pack_u64(0, err_ptr as u32)
                     }
                 }
@@ -439,10 +950,10 @@ macro_rules! export_handle_plugin {
 
             unsafe {
                 let p = PLUGIN.as_ref().expect("Not initialized");
-                match <$plugin_type as $crate::HandleFS>::handle_write_at(p, id, data, offset) {
+                match <__AgfsPlugin as $crate::HandleFS>::handle_write_at(p, id, data, offset) {
                     Ok(n) => pack_u64(n as u32, 0),
                     Err(e) => {
-                        let err_ptr = CString::new(&e.to_string()).into_raw();
+                        let err_ptr = $crate::ffi::error_ptr(&e);
                         pack_u64(0, err_ptr as u32)
                     }
                 }
@@ -459,10 +970,10 @@ macro_rules! export_handle_plugin {
 
             unsafe {
                 let p = PLUGIN.as_mut().expect("Not initialized");
-                match <$plugin_type as $crate::HandleFS>::handle_seek(p, id, offset, whence) {
+                match <__AgfsPlugin as $crate::HandleFS>::handle_seek(p, id, offset, whence) {
                     Ok(pos) => pack_u64(pos as u32, 0),
                     Err(e) => {
-                        let err_ptr = CString::new(&e.to_string()).into_raw();
+                        let err_ptr = $crate::ffi::error_ptr(&e);
                         pack_u64(0, err_ptr as u32)
                     }
                 }
@@ -479,7 +990,7 @@ macro_rules! export_handle_plugin {
 
             unsafe {
                 let p = PLUGIN.as_ref().expect("Not initialized");
-                result_to_error_ptr::<()>(<$plugin_type as $crate::HandleFS>::handle_sync(p, id))
+                result_to_error_ptr::<()>(<__AgfsPlugin as $crate::HandleFS>::handle_sync(p, id))
             }
         }
 
@@ -493,16 +1004,16 @@ macro_rules! export_handle_plugin {
 
             unsafe {
                 let p = PLUGIN.as_ref().expect("Not initialized");
-                match <$plugin_type as $crate::HandleFS>::handle_stat(p, id) {
+                match <__AgfsPlugin as $crate::HandleFS>::handle_stat(p, id) {
                     Ok(info) => match fileinfo_to_json_ptr(&info) {
                         Ok(json_ptr) => pack_u64(json_ptr as u32, 0),
                         Err(e) => {
-                            let err_ptr = CString::new(&e.to_string()).into_raw();
+                            let err_ptr = $crate::ffi::error_ptr(&e);
                             pack_u64(0, err_ptr as u32)
                         }
                     },
                     Err(e) => {
-                        let err_ptr = CString::new(&e.to_string()).into_raw();
+                        let err_ptr = $crate::ffi::error_ptr(&e);
                         pack_u64(0, err_ptr as u32)
                     }
                 }
@@ -518,7 +1029,7 @@ macro_rules! export_handle_plugin {
 
             unsafe {
                 let p = PLUGIN.as_ref().expect("Not initialized");
-                match <$plugin_type as $crate::HandleFS>::handle_info(p, id) {
+                match <__AgfsPlugin as $crate::HandleFS>::handle_info(p, id) {
                     Ok((path, flags)) => {
                         // Return JSON with path and flags
                         let json = $crate::serde_json::json!({
@@ -530,7 +1041,7 @@ macro_rules! export_handle_plugin {
                         pack_u64(json_ptr as u32, 0)
                     }
                     Err(e) => {
-                        let err_ptr = CString::new(&e.to_string()).into_raw();
+                        let err_ptr = $crate::ffi::error_ptr(&e);
                         pack_u64(0, err_ptr as u32)
                     }
                 }
@@ -547,7 +1058,7 @@ macro_rules! export_handle_plugin {
 
             unsafe {
                 let p = PLUGIN.as_mut().expect("Not initialized");
-                result_to_error_ptr::<()>(<$plugin_type as $crate::HandleFS>::close_handle(p, id))
+                result_to_error_ptr::<()>(<__AgfsPlugin as $crate::HandleFS>::close_handle(p, id))
             }
         }
     };
@@ -0,0 +1,57 @@
+//! Build manifest exported as `plugin_manifest`
+//!
+//! The manifest tells operators what a plugin is before they mount it: its
+//! name and version, the SDK it was built with, a build hash and the
+//! capabilities it declares. `agfs-manifest show plugin.wasm` prints it.
+//!
+//! `export_plugin!` fills in the build hash and signature from the
+//! `AGFS_BUILD_HASH` and `AGFS_MANIFEST_SIGNATURE` environment variables at
+//! compile time. To sign a plugin, build it once with `AGFS_BUILD_HASH` set,
+//! run `agfs-manifest sign -key plugin.key plugin.wasm`, then rebuild with
+//! the printed value in `AGFS_MANIFEST_SIGNATURE`. The signature is Ed25519
+//! over the manifest JSON without its `signature` field, keys sorted.
+
+use crate::types::Capabilities;
+use serde::Serialize;
+
+/// Version of this SDK, recorded in every manifest
+pub const SDK_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Identity and declared capabilities of a plugin build
+#[derive(Debug, Clone, Serialize)]
+pub struct Manifest {
+    pub name: String,
+    pub version: String,
+    pub sdk_version: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_hash: Option<String>,
+    pub capabilities: Capabilities,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub signature: Option<String>,
+}
+
+impl Manifest {
+    /// Manifest for `name` at `version`, built with this SDK
+    pub fn new(name: &str, version: &str, capabilities: Capabilities) -> Self {
+        Self {
+            name: name.to_string(),
+            version: version.to_string(),
+            sdk_version: SDK_VERSION.to_string(),
+            build_hash: None,
+            capabilities,
+            signature: None,
+        }
+    }
+
+    /// Record the build hash (empty values are ignored)
+    pub fn build_hash(mut self, hash: Option<&str>) -> Self {
+        self.build_hash = hash.filter(|h| !h.is_empty()).map(str::to_string);
+        self
+    }
+
+    /// Record the signature produced by `agfs-manifest sign`
+    pub fn signature(mut self, signature: Option<&str>) -> Self {
+        self.signature = signature.filter(|s| !s.is_empty()).map(str::to_string);
+        self
+    }
+}
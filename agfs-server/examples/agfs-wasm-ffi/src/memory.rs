@@ -3,7 +3,10 @@
 //! This module provides safe wrappers around raw pointer operations
 //! needed for WASM<->Go communication.
 
+#[cfg(any(feature = "http", feature = "image", feature = "mounts", feature = "queue"))]
+use crate::types::{Error, Result};
 use std::alloc::{alloc, dealloc, Layout};
+use std::collections::BTreeMap;
 use std::ptr;
 
 /// A string allocated in WASM memory that can be passed to Go
@@ -170,3 +173,142 @@ impl Drop for Buffer {
 pub fn pack_u64(low: u32, high: u32) -> u64 {
     ((high as u64) << 32) | (low as u64)
 }
+
+/// Results too large for the shared output buffer, drained a chunk at a time
+///
+/// Instead of copying a large result into a malloc'd block (so that the plugin
+/// briefly holds it twice), the export copies the first chunk into the output
+/// buffer and keeps the rest here under a continuation token. The host calls
+/// `read_chunk(token)` until the returned token is 0.
+pub struct ChunkedResults {
+    next_token: u32,
+    pending: BTreeMap<u32, (Vec<u8>, usize)>,
+}
+
+impl ChunkedResults {
+    /// Create an empty store
+    pub const fn new() -> Self {
+        Self {
+            next_token: 0,
+            pending: BTreeMap::new(),
+        }
+    }
+
+    /// Copy the first chunk of `data` into `out`
+    ///
+    /// Returns the number of bytes copied and the token for the rest, or 0 if
+    /// `data` fit entirely.
+    pub fn start(&mut self, data: Vec<u8>, out: &mut [u8]) -> (u32, u32) {
+        if data.len() <= out.len() {
+            out[..data.len()].copy_from_slice(&data);
+            return (data.len() as u32, 0);
+        }
+
+        // Token 0 means "done", so skip it when wrapping
+        self.next_token = self.next_token.wrapping_add(1).max(1);
+        let token = self.next_token;
+        self.pending.insert(token, (data, 0));
+        let len = self.next(token, out).map_or(0, |(len, _)| len);
+        (len, token)
+    }
+
+    /// Copy the next chunk for `token` into `out`
+    ///
+    /// Returns the number of bytes copied and the token to pass next time (0
+    /// after the last chunk), or None for an unknown token.
+    pub fn next(&mut self, token: u32, out: &mut [u8]) -> Option<(u32, u32)> {
+        let (data, pos) = self.pending.get_mut(&token)?;
+        let n = (data.len() - *pos).min(out.len());
+        out[..n].copy_from_slice(&data[*pos..*pos + n]);
+        *pos += n;
+        if *pos < data.len() {
+            return Some((n as u32, token));
+        }
+        self.pending.remove(&token);
+        Some((n as u32, 0))
+    }
+
+    /// Drop the rest of an abandoned result
+    pub fn cancel(&mut self, token: u32) {
+        self.pending.remove(&token);
+    }
+}
+
+impl Default for ChunkedResults {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Address of plugin memory at a pointer the host returned
+///
+/// The identity on wasm32; native builds look it up among the blocks
+/// [`host_stub::alloc`](crate::host_stub::alloc) handed out.
+pub(crate) fn guest_ptr(ptr: u32) -> *const u8 {
+    #[cfg(target_arch = "wasm32")]
+    return ptr as *const u8;
+    #[cfg(not(target_arch = "wasm32"))]
+    return crate::host_stub::guest_ptr(ptr);
+}
+
+/// Copy a host response out of WASM memory
+/// Packed format: lower 32 bits = pointer, upper 32 bits = size
+pub(crate) unsafe fn read_packed_response(result: u64) -> Option<Vec<u8>> {
+    let response_ptr = (result & 0xFFFFFFFF) as u32;
+    let response_size = ((result >> 32) & 0xFFFFFFFF) as u32;
+
+    if response_ptr == 0 {
+        return None;
+    }
+
+    let slice = std::slice::from_raw_parts(guest_ptr(response_ptr), response_size as usize);
+    Some(slice.to_vec())
+}
+
+// Simple base64 decoding (standard alphabet)
+#[cfg(any(feature = "http", feature = "image", feature = "mounts", feature = "queue"))]
+pub(crate) fn base64_decode(input: &str) -> Result<Vec<u8>> {
+    const BASE64_TABLE: &[u8; 128] = &[
+        255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+        255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255,
+        255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 255, 62, 255, 255, 255, 63,
+        52, 53, 54, 55, 56, 57, 58, 59, 60, 61, 255, 255, 255, 0, 255, 255,
+        255, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14,
+        15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 255, 255, 255, 255, 255,
+        255, 26, 27, 28, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40,
+        41, 42, 43, 44, 45, 46, 47, 48, 49, 50, 51, 255, 255, 255, 255, 255,
+    ];
+
+    if input.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let input = input.trim();
+    let mut output = Vec::with_capacity((input.len() * 3) / 4);
+    let mut buf = 0u32;
+    let mut bits = 0;
+
+    for &b in input.as_bytes() {
+        if b == b'=' {
+            break;
+        }
+        if b >= 128 {
+            return Err(Error::Other("invalid base64 character".to_string()));
+        }
+        let val = BASE64_TABLE[b as usize];
+        if val == 255 {
+            continue; // Skip whitespace/invalid chars
+        }
+
+        buf = (buf << 6) | (val as u32);
+        bits += 6;
+
+        if bits >= 8 {
+            bits -= 8;
+            output.push((buf >> bits) as u8);
+            buf &= (1 << bits) - 1;
+        }
+    }
+
+    Ok(output)
+}
@@ -0,0 +1,514 @@
+//! OAuth2 tokens for plugins calling authorized APIs
+//!
+//! [`OAuth2Client`] obtains an access token (client credentials, a refresh
+//! token, or the device flow), refreshes it shortly before it expires and can
+//! keep it in a host file so a new instance does not have to authorize again:
+//!
+//! ```ignore
+//! fn initialize(&mut self, config: &Config) -> Result<()> {
+//!     self.oauth = OAuth2Client::new("https://oauth2.googleapis.com/token", config.get_str("client_id").unwrap_or(""))
+//!         .client_secret(config.get_str("client_secret").unwrap_or(""))
+//!         .scopes(&["https://www.googleapis.com/auth/drive.readonly"])
+//!         .store("/var/lib/agfs/gdrivefs/token.json")?;
+//!     Ok(())
+//! }
+//!
+//! fn list(&self, folder: &str) -> Result<Vec<FileInfo>> {
+//!     let response = Http::request(self.oauth.authorize(HttpRequest::get(&self.files_url(folder)))?)?;
+//!     // ...
+//! }
+//! ```
+//!
+//! Without a stored or refresh token and without a client secret, a user has
+//! to grant access through the device flow: [`OAuth2Client::start_device_flow`]
+//! returns the code to show them (e.g. in a control file) and
+//! [`OAuth2Client::poll_device_flow`] completes it once they have.
+//!
+//! Tokens are registered with [`redact`](crate::redact), so they never show
+//! up in error messages.
+
+use crate::clock;
+use crate::host_fs::HostFS;
+use crate::host_http::{Http, HttpRequest, HttpResponse};
+use crate::redact;
+use crate::types::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::time::Duration;
+
+/// Refresh tokens this long before they expire, so a request started with
+/// the token does not reach the API after it
+const EXPIRY_MARGIN: Duration = Duration::from_secs(60);
+
+const DEVICE_CODE_GRANT: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// An access token and what is needed to renew it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Token {
+    pub access_token: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    /// Unix time in seconds; `None` if the server did not say
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+}
+
+impl Token {
+    fn is_fresh(&self, now: Duration) -> bool {
+        self.expires_at.is_none_or(|at| now + EXPIRY_MARGIN < Duration::from_secs(at))
+    }
+}
+
+/// A pending device authorization: show `user_code` and `verification_uri`
+/// to the user, then poll
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceAuthorization {
+    pub device_code: String,
+    pub user_code: String,
+    #[serde(alias = "verification_url")]
+    pub verification_uri: String,
+    /// Seconds the code stays valid
+    pub expires_in: u64,
+    /// Seconds to wait between polls
+    #[serde(default = "default_interval")]
+    pub interval: u64,
+}
+
+fn default_interval() -> u64 {
+    5
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    refresh_token: Option<String>,
+    #[serde(default)]
+    expires_in: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: String,
+    #[serde(default)]
+    error_description: Option<String>,
+}
+
+/// Access tokens for one client of one authorization server
+pub struct OAuth2Client {
+    token_url: String,
+    client_id: String,
+    client_secret: Option<String>,
+    scopes: Vec<String>,
+    store: Option<String>,
+    token: RefCell<Option<Token>>,
+}
+
+impl OAuth2Client {
+    pub fn new(token_url: &str, client_id: &str) -> Self {
+        Self {
+            token_url: token_url.to_string(),
+            client_id: client_id.to_string(),
+            client_secret: None,
+            scopes: Vec::new(),
+            store: None,
+            token: RefCell::new(None),
+        }
+    }
+
+    /// Authenticate as a confidential client; enables the client credentials
+    /// grant. An empty secret is ignored.
+    pub fn client_secret(mut self, secret: &str) -> Self {
+        if !secret.is_empty() {
+            redact::add_secret(secret);
+            self.client_secret = Some(secret.to_string());
+        }
+        self
+    }
+
+    /// Scopes to request
+    pub fn scopes(mut self, scopes: &[&str]) -> Self {
+        self.scopes = scopes.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    /// Keep tokens in the host file `path`, loading the one stored there
+    pub fn store(mut self, path: &str) -> Result<Self> {
+        self.store = Some(path.to_string());
+        if let Ok(data) = HostFS::read(path, 0, -1) {
+            let token: Token = serde_json::from_slice(&data)
+                .map_err(|e| Error::Other(format!("corrupt token file {}: {}", path, e)))?;
+            register(&token);
+            *self.token.borrow_mut() = Some(token);
+        }
+        Ok(self)
+    }
+
+    /// Use a token obtained elsewhere, e.g. a refresh token from the mount
+    /// config
+    pub fn set_token(&self, token: Token) -> Result<()> {
+        register(&token);
+        self.save(&token)?;
+        *self.token.borrow_mut() = Some(token);
+        Ok(())
+    }
+
+    /// Forget the token, also in the store
+    pub fn clear(&self) -> Result<()> {
+        *self.token.borrow_mut() = None;
+        if let Some(path) = &self.store {
+            // Nothing stored yet is fine
+            let _ = HostFS::remove(path);
+        }
+        Ok(())
+    }
+
+    /// Whether a token is held; it may still need refreshing
+    pub fn is_authorized(&self) -> bool {
+        self.token.borrow().is_some()
+    }
+
+    /// A valid access token, renewing the held one if it is about to expire
+    ///
+    /// Renews with the refresh token, else with the client credentials.
+    /// Fails with `PermissionDenied` if neither is available; the user then
+    /// has to go through the device flow.
+    pub fn access_token(&self) -> Result<String> {
+        let held = self.token.borrow().clone();
+        if let Some(token) = &held {
+            if token.is_fresh(clock::now()) {
+                return Ok(token.access_token.clone());
+            }
+        }
+
+        let refresh_token = held.and_then(|t| t.refresh_token);
+        let token = match (refresh_token, &self.client_secret) {
+            (Some(refresh_token), _) => self.refresh(&refresh_token)?,
+            (None, Some(_)) => self.client_credentials()?,
+            (None, None) => return Err(Error::PermissionDenied),
+        };
+        Ok(token.access_token)
+    }
+
+    /// `req` with an `Authorization: Bearer` header
+    pub fn authorize(&self, req: HttpRequest) -> Result<HttpRequest> {
+        Ok(req.header("Authorization", &format!("Bearer {}", self.access_token()?)))
+    }
+
+    /// Obtain a token with the client credentials grant
+    pub fn client_credentials(&self) -> Result<Token> {
+        let mut form = vec![("grant_type", "client_credentials")];
+        let scope = self.scopes.join(" ");
+        if !scope.is_empty() {
+            form.push(("scope", &scope));
+        }
+        self.grant(form, None)
+    }
+
+    /// Renew the token with `refresh_token`
+    ///
+    /// Servers that do not rotate refresh tokens omit it from the response;
+    /// the old one is kept then.
+    pub fn refresh(&self, refresh_token: &str) -> Result<Token> {
+        self.grant(
+            vec![("grant_type", "refresh_token"), ("refresh_token", refresh_token)],
+            Some(refresh_token),
+        )
+    }
+
+    /// Start the device flow at the server's device authorization endpoint
+    pub fn start_device_flow(&self, device_url: &str) -> Result<DeviceAuthorization> {
+        let scope = self.scopes.join(" ");
+        let mut form = vec![("client_id", self.client_id.as_str())];
+        if !scope.is_empty() {
+            form.push(("scope", &scope));
+        }
+        let response = Http::request(form_request(device_url, &form))?;
+        check_response(&response)?;
+        response.json()
+    }
+
+    /// Ask whether the user has granted `auth` yet
+    ///
+    /// Returns `Ok(true)` once the token is held, `Ok(false)` while the user
+    /// has not acted (wait [`DeviceAuthorization::interval`] seconds before
+    /// asking again), and `PermissionDenied` if they refused or the code
+    /// expired.
+    pub fn poll_device_flow(&self, auth: &DeviceAuthorization) -> Result<bool> {
+        match self.grant(vec![("grant_type", DEVICE_CODE_GRANT), ("device_code", &auth.device_code)], None) {
+            Ok(_) => Ok(true),
+            Err(Error::Busy { .. }) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Post a grant to the token endpoint and hold the token it returns,
+    /// with `refresh_token` if it comes without one
+    fn grant<'a>(&'a self, mut form: Vec<(&'a str, &'a str)>, refresh_token: Option<&str>) -> Result<Token> {
+        form.push(("client_id", &self.client_id));
+        if let Some(secret) = &self.client_secret {
+            form.push(("client_secret", secret));
+        }
+        let response = Http::request(form_request(&self.token_url, &form))?;
+        check_response(&response)?;
+
+        let issued: TokenResponse = response.json()?;
+        let token = Token {
+            access_token: issued.access_token,
+            refresh_token: issued.refresh_token.or(refresh_token.map(str::to_string)),
+            expires_at: issued.expires_in.map(|secs| clock::now().as_secs() + secs),
+        };
+        self.set_token(token.clone())?;
+        Ok(token)
+    }
+
+    fn save(&self, token: &Token) -> Result<()> {
+        let Some(path) = &self.store else {
+            return Ok(());
+        };
+        let data = serde_json::to_vec(token).map_err(|e| Error::Other(format!("failed to encode token: {}", e)))?;
+        HostFS::write(path, &data).map(|_| ())
+    }
+}
+
+fn register(token: &Token) {
+    redact::add_secret(&token.access_token);
+    if let Some(refresh_token) = &token.refresh_token {
+        redact::add_secret(refresh_token);
+    }
+}
+
+fn form_request(url: &str, form: &[(&str, &str)]) -> HttpRequest {
+    let body = form
+        .iter()
+        .map(|(key, value)| format!("{}={}", form_encode(key), form_encode(value)))
+        .collect::<Vec<_>>()
+        .join("&");
+    HttpRequest::post(url)
+        .header("Content-Type", "application/x-www-form-urlencoded")
+        .header("Accept", "application/json")
+        .body_str(&body)
+}
+
+/// `application/x-www-form-urlencoded` encoding of `value`
+fn form_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for b in value.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'*' => encoded.push(b as char),
+            b' ' => encoded.push('+'),
+            _ => encoded.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    encoded
+}
+
+/// Map an unsuccessful token endpoint response to an error
+///
+/// A pending device authorization (`authorization_pending`, `slow_down`) is
+/// `Busy`, a refused grant `PermissionDenied`.
+fn check_response(response: &HttpResponse) -> Result<()> {
+    if response.is_success() {
+        return Ok(());
+    }
+    if let Some(busy) = response.busy_error() {
+        return Err(busy);
+    }
+    match response.json::<ErrorResponse>() {
+        Ok(err) => match err.error.as_str() {
+            "authorization_pending" | "slow_down" => Err(Error::Busy { retry_after_ms: None }),
+            "invalid_grant" | "invalid_client" | "unauthorized_client" | "access_denied" | "expired_token" => {
+                Err(Error::PermissionDenied)
+            }
+            _ => Err(Error::Io(format!(
+                "token endpoint rejected the request: {}{}",
+                err.error,
+                err.error_description.map(|d| format!(" ({})", d)).unwrap_or_default()
+            ))),
+        },
+        Err(_) => Err(Error::Io(format!("token endpoint returned status {}", response.status_code))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::host_stub::fixtures;
+    use std::rc::Rc;
+
+    const NOW: u64 = 1_700_000_000;
+    const TOKEN_URL: &str = "https://auth.example.com/token";
+
+    /// Serve the token endpoint with `responses` in turn, recording the form
+    /// bodies posted to it
+    fn token_endpoint(responses: Vec<(i32, &'static str)>) -> Rc<RefCell<Vec<String>>> {
+        fixtures::clock(NOW);
+        let requests = Rc::new(RefCell::new(Vec::new()));
+        let posted = requests.clone();
+        let responses = RefCell::new(responses.into_iter());
+        fixtures::http(move |req| {
+            assert_eq!(req.method, "POST");
+            assert_eq!(req.headers["Content-Type"], "application/x-www-form-urlencoded");
+            posted.borrow_mut().push(format!("{} {}", req.url, String::from_utf8_lossy(&req.body)));
+            let (status, body) = responses.borrow_mut().next().expect("unexpected token request");
+            (status, body.to_string())
+        });
+        requests
+    }
+
+    fn expiring_token(expires_at: u64) -> Token {
+        Token {
+            access_token: "old-access".to_string(),
+            refresh_token: Some("refresh-1".to_string()),
+            expires_at: Some(expires_at),
+        }
+    }
+
+    #[test]
+    fn test_fresh_token_is_not_refreshed() {
+        let requests = token_endpoint(vec![]);
+        let client = OAuth2Client::new(TOKEN_URL, "client");
+        client.set_token(expiring_token(NOW + 3600)).unwrap();
+        assert_eq!(client.access_token().unwrap(), "old-access");
+        let req = client.authorize(HttpRequest::get("https://api.example.com/")).unwrap();
+        assert_eq!(req.headers["Authorization"], "Bearer old-access");
+        assert!(requests.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_refresh_before_expiry() {
+        let requests = token_endpoint(vec![(200, r#"{"access_token":"new-access","expires_in":3600}"#)]);
+        let client = OAuth2Client::new(TOKEN_URL, "client");
+        // Within the expiry margin, so renewed before use
+        client.set_token(expiring_token(NOW + 30)).unwrap();
+
+        assert_eq!(client.access_token().unwrap(), "new-access");
+        assert_eq!(
+            requests.borrow().as_slice(),
+            [format!("{} grant_type=refresh_token&refresh_token=refresh-1&client_id=client", TOKEN_URL)]
+        );
+        // The server did not rotate the refresh token, so the old one is kept
+        assert_eq!(
+            client.token.borrow().clone().unwrap(),
+            Token {
+                access_token: "new-access".to_string(),
+                refresh_token: Some("refresh-1".to_string()),
+                expires_at: Some(NOW + 3600),
+            }
+        );
+        assert_eq!(client.access_token().unwrap(), "new-access");
+        assert_eq!(requests.borrow().len(), 1);
+    }
+
+    #[test]
+    fn test_refresh_rotates_and_stores_token() {
+        let files = fixtures::host_files();
+        let stored = serde_json::to_vec(&expiring_token(NOW - 10)).unwrap();
+        files.borrow_mut().insert("/var/lib/token.json".to_string(), stored);
+        token_endpoint(vec![(
+            200,
+            r#"{"access_token":"new-access","refresh_token":"refresh-2","expires_in":60}"#,
+        )]);
+
+        let client = OAuth2Client::new(TOKEN_URL, "client")
+            .client_secret("s3cret")
+            .store("/var/lib/token.json")
+            .unwrap();
+        assert!(client.is_authorized());
+        assert_eq!(client.access_token().unwrap(), "new-access");
+
+        let saved: Token = serde_json::from_slice(&files.borrow()["/var/lib/token.json"]).unwrap();
+        assert_eq!(saved.refresh_token.as_deref(), Some("refresh-2"));
+        assert_eq!(saved.expires_at, Some(NOW + 60));
+
+        client.clear().unwrap();
+        assert!(!client.is_authorized());
+        assert!(files.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_refused_refresh() {
+        token_endpoint(vec![
+            (400, r#"{"error":"invalid_grant"}"#),
+            (400, r#"{"error":"server_error","error_description":"try later"}"#),
+        ]);
+        let client = OAuth2Client::new(TOKEN_URL, "client");
+        client.set_token(expiring_token(NOW)).unwrap();
+        assert!(matches!(client.access_token(), Err(Error::PermissionDenied)));
+        match client.access_token() {
+            Err(Error::Io(msg)) => assert!(msg.contains("server_error (try later)"), "{}", msg),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_client_credentials() {
+        let requests = token_endpoint(vec![(200, r#"{"access_token":"app-access"}"#)]);
+        // Without a refresh token or a secret the user has to authorize
+        assert!(matches!(OAuth2Client::new(TOKEN_URL, "client").access_token(), Err(Error::PermissionDenied)));
+
+        let client = OAuth2Client::new(TOKEN_URL, "client").client_secret("s3cret").scopes(&["read", "write:all"]);
+        assert_eq!(client.access_token().unwrap(), "app-access");
+        assert_eq!(
+            requests.borrow().as_slice(),
+            [format!(
+                "{} grant_type=client_credentials&scope=read+write%3Aall&client_id=client&client_secret=s3cret",
+                TOKEN_URL
+            )]
+        );
+        // No expiry given, so the token is used until refused
+        assert_eq!(client.token.borrow().as_ref().unwrap().expires_at, None);
+    }
+
+    #[test]
+    fn test_device_flow() {
+        let requests = token_endpoint(vec![
+            (
+                200,
+                r#"{"device_code":"dev-1","user_code":"WDJB-MJHT",
+                    "verification_url":"https://example.com/device","expires_in":900}"#,
+            ),
+            (400, r#"{"error":"authorization_pending"}"#),
+            (400, r#"{"error":"slow_down"}"#),
+            (200, r#"{"access_token":"user-access","refresh_token":"user-refresh","expires_in":3600}"#),
+        ]);
+        let client = OAuth2Client::new(TOKEN_URL, "client").scopes(&["drive"]);
+
+        let auth = client.start_device_flow("https://auth.example.com/device").unwrap();
+        assert_eq!(auth.user_code, "WDJB-MJHT");
+        assert_eq!(auth.verification_uri, "https://example.com/device");
+        assert_eq!(auth.interval, 5);
+
+        assert!(!client.poll_device_flow(&auth).unwrap());
+        assert!(!client.poll_device_flow(&auth).unwrap());
+        assert!(!client.is_authorized());
+        assert!(client.poll_device_flow(&auth).unwrap());
+        assert_eq!(client.access_token().unwrap(), "user-access");
+
+        let requests = requests.borrow();
+        assert_eq!(requests[0], "https://auth.example.com/device client_id=client&scope=drive");
+        assert_eq!(
+            requests[3],
+            format!(
+                "{} grant_type={}&device_code=dev-1&client_id=client",
+                TOKEN_URL, "urn%3Aietf%3Aparams%3Aoauth%3Agrant-type%3Adevice_code"
+            )
+        );
+    }
+
+    #[test]
+    fn test_device_flow_refused() {
+        token_endpoint(vec![(400, r#"{"error":"access_denied"}"#), (400, r#"{"error":"expired_token"}"#)]);
+        let client = OAuth2Client::new(TOKEN_URL, "client");
+        let auth = DeviceAuthorization {
+            device_code: "dev-1".to_string(),
+            user_code: "WDJB-MJHT".to_string(),
+            verification_uri: "https://example.com/device".to_string(),
+            expires_in: 900,
+            interval: 5,
+        };
+        assert!(matches!(client.poll_device_flow(&auth), Err(Error::PermissionDenied)));
+        assert!(matches!(client.poll_device_flow(&auth), Err(Error::PermissionDenied)));
+        assert!(!client.is_authorized());
+    }
+}
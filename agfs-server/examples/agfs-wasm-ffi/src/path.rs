@@ -0,0 +1,87 @@
+//! Path utilities for plugins that map their paths onto another tree
+//!
+//! Plugins that proxy to the host filesystem (or any prefix-addressed
+//! backend) must never let a user path escape the configured root. These
+//! helpers normalize paths lexically and enforce containment.
+
+use crate::types::{Error, Result};
+
+/// Percent-encoded forms of `.`, `/` and `\` that some clients or gateways
+/// decode late; rejected outright rather than guessing how they will be read
+const ENCODED_SEPARATORS: [&str; 3] = ["%2e", "%2f", "%5c"];
+
+/// Lexically normalize an absolute path
+///
+/// Collapses repeated slashes, removes `.` components and resolves `..`.
+/// Returns `PermissionDenied` if `..` would climb above `/`, and
+/// `InvalidInput` for NUL bytes, backslashes or percent-encoded separators.
+///
+/// ```ignore
+/// assert_eq!(clean("/a//b/./c/../d")?, "/a/b/d");
+/// ```
+pub fn clean(path: &str) -> Result<String> {
+    if path.contains('\0') {
+        return Err(Error::InvalidInput("path contains NUL byte".to_string()));
+    }
+    if path.contains('\\') {
+        return Err(Error::InvalidInput("path contains backslash".to_string()));
+    }
+    let lower = path.to_ascii_lowercase();
+    if ENCODED_SEPARATORS.iter().any(|enc| lower.contains(enc)) {
+        return Err(Error::InvalidInput("path contains encoded separator".to_string()));
+    }
+
+    let mut parts: Vec<&str> = Vec::new();
+    for component in path.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                if parts.pop().is_none() {
+                    return Err(Error::PermissionDenied);
+                }
+            }
+            c => parts.push(c),
+        }
+    }
+
+    Ok(format!("/{}", parts.join("/")))
+}
+
+/// Join `path` onto `root`, guaranteeing the result stays inside `root`
+///
+/// `path` is treated as relative to `root` even if it starts with `/`.
+pub fn join_within(root: &str, path: &str) -> Result<String> {
+    let cleaned = clean(path)?;
+    let root = root.trim_end_matches('/');
+    if cleaned == "/" {
+        return Ok(if root.is_empty() { "/".to_string() } else { root.to_string() });
+    }
+    Ok(format!("{}{}", root, cleaned))
+}
+
+/// Like [`join_within`], but also follows symlinks to check where the path
+/// really leads
+///
+/// `realpath` resolves every symlink in a path, as
+/// [`HostFS::realpath`](crate::HostFS::realpath) does. The joined path is
+/// accepted only if it resolves to `root` (resolved the same way) or below
+/// it, and the resolved path is returned so later calls do not go through
+/// the links again. Anything that fails to resolve is refused with
+/// `PermissionDenied`, like a path leading outside.
+pub fn resolve_within<F>(root: &str, path: &str, realpath: F) -> Result<String>
+where
+    F: Fn(&str) -> Result<String>,
+{
+    let full = join_within(root, path)?;
+    let root = realpath(root).map_err(|_| Error::PermissionDenied)?;
+    let resolved = realpath(&full).map_err(|_| Error::PermissionDenied)?;
+
+    let root = root.trim_end_matches('/');
+    let inside = root.is_empty()
+        || resolved == root
+        || resolved.strip_prefix(root).is_some_and(|rest| rest.starts_with('/'));
+    if !inside {
+        return Err(Error::PermissionDenied);
+    }
+    Ok(resolved)
+}
@@ -0,0 +1,35 @@
+//! Rate-limit guards on the host clock
+//!
+//! Re-exports [`RateLimitGuard`] and builds it in WASM, where a plugin has no
+//! clock of its own, and sends requests through it:
+//!
+//! ```ignore
+//! // In the plugin struct, shared by all operations
+//! limits: RateLimitGuard = ratelimit::new(),
+//!
+//! let response = ratelimit::request(&self.limits, HttpRequest::get(&url))?;
+//! ```
+
+use crate::clock;
+use crate::host_http::{Http, HttpRequest, HttpResponse};
+use crate::types::Result;
+
+pub use agfs_core::ratelimit::{RateLimitGuard, DEFAULT_BACKOFF};
+
+/// A guard reading the time from the host
+pub fn new() -> RateLimitGuard {
+    RateLimitGuard::with_clock(clock::now)
+}
+
+/// Send `req` unless `guard` holds calls back, and learn from the response
+///
+/// Fails with `Busy` while the limit is exhausted and when the response
+/// itself was throttled; any other response is returned as it is.
+pub fn request(guard: &RateLimitGuard, req: HttpRequest) -> Result<HttpResponse> {
+    guard.check()?;
+    let response = Http::request(req)?;
+    match guard.observe(response.status_code, &response.headers) {
+        Some(busy) => Err(busy),
+        None => Ok(response),
+    }
+}
@@ -0,0 +1,41 @@
+//! Retrying host calls with backoff
+//!
+//! Re-exports [`RetryPolicy`] and runs it in WASM, where a plugin cannot
+//! block its thread: [`run`] waits on the host between attempts and gives up
+//! early rather than wait past the operation's deadline.
+//!
+//! ```ignore
+//! let policy = RetryPolicy::new().max_attempts(5);
+//! let page = retry::run(&policy, || self.client.fetch_page(cursor))?;
+//!
+//! // Requests and HostFS calls take a policy of their own
+//! let response = Http::request(HttpRequest::get(&url).retry(policy))?;
+//! HostFS::set_retry_policy(policy);
+//! ```
+
+use crate::deadline;
+use crate::types::{Error, Result};
+use std::time::Duration;
+
+pub use agfs_core::retry::{is_transient, RetryPolicy, DEFAULT_MAX_ATTEMPTS};
+
+host_imports! {
+    fn host_sleep_ms(ms: i64) -> i32;
+}
+
+/// Run `op` with `policy`, waiting on the host between attempts
+pub fn run<T>(policy: &RetryPolicy, op: impl FnMut() -> Result<T>) -> Result<T> {
+    policy.run_with(sleep, op)
+}
+
+/// Wait `delay` on the host, failing if it would pass the deadline
+fn sleep(delay: Duration) -> Result<()> {
+    if deadline::remaining().is_some_and(|left| left <= delay) {
+        return Err(Error::TimedOut);
+    }
+    let ms = delay.as_millis().min(i64::MAX as u128) as i64;
+    if unsafe { host_sleep_ms(ms) } != 0 {
+        return Err(Error::Cancelled);
+    }
+    Ok(())
+}
@@ -0,0 +1,82 @@
+//! Ring-buffer channels behind the `stream_*` exports
+//!
+//! Each open stream owns two [`RingBuffer`]s in linear memory: `out` carries
+//! plugin data to the host and `input` carries host data to the plugin. The
+//! host reads and writes the rings directly and only calls `stream_read` or
+//! `stream_write` when `out` runs dry or `input` fills up, so a busy stream
+//! costs one FFI call per ring-full rather than one per chunk.
+
+use crate::types::{Error, Result};
+use agfs_core::ring::RingBuffer;
+
+/// Default ring size when the host asks for 0
+pub const DEFAULT_STREAM_CAPACITY: usize = 64 * 1024;
+
+/// The rings of one open stream
+pub struct Channel {
+    /// Stream ID returned by `StreamFS::open_stream`
+    pub id: i64,
+    out: RingBuffer,
+    input: RingBuffer,
+}
+
+impl Channel {
+    /// Create the rings for stream `id`
+    pub fn new(id: i64, capacity: usize) -> Self {
+        let capacity = if capacity == 0 { DEFAULT_STREAM_CAPACITY } else { capacity };
+        Self {
+            id,
+            out: RingBuffer::new(capacity),
+            input: RingBuffer::new(capacity),
+        }
+    }
+
+    /// Address of the plugin -> host ring, which also identifies the stream
+    pub fn out_ptr(&self) -> *mut u8 {
+        self.out.as_ptr()
+    }
+
+    /// Address of the host -> plugin ring
+    pub fn input_ptr(&self) -> *mut u8 {
+        self.input.as_ptr()
+    }
+
+    /// Move whatever `read` has available into the outgoing ring
+    ///
+    /// `read` follows `StreamFS::stream_read`; the ring is closed when it
+    /// reports the end of the stream. Returns the bytes waiting for the host.
+    pub fn fill<F>(&self, mut read: F) -> Result<usize>
+    where
+        F: FnMut(&mut [u8]) -> Result<Option<usize>>,
+    {
+        let mut buf = vec![0u8; self.out.free()];
+        while !buf.is_empty() && !self.out.is_closed() {
+            match read(&mut buf)? {
+                Some(0) => break,
+                Some(n) => {
+                    self.out.push(&buf[..n.min(buf.len())]);
+                    buf.truncate(self.out.free());
+                }
+                None => self.out.close(),
+            }
+        }
+        Ok(self.out.len())
+    }
+
+    /// Pass everything the host wrote to `write`, returning the byte count
+    pub fn drain<F>(&self, mut write: F) -> Result<usize>
+    where
+        F: FnMut(&[u8]) -> Result<usize>,
+    {
+        let mut buf = vec![0u8; self.input.len()];
+        let n = self.input.pop(&mut buf);
+        let mut done = 0;
+        while done < n {
+            match write(&buf[done..n])? {
+                0 => return Err(Error::Io("short write".to_string())),
+                written => done += written,
+            }
+        }
+        Ok(n)
+    }
+}
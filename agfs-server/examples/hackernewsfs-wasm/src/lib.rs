@@ -4,18 +4,110 @@
 //! - cat /hackernews/refresh - Refreshes the story list
 //! - ls /hackernews/frontpage/ - Lists all stories
 //! - cat /hackernews/frontpage/1.md - Read a specific story
+//! - ls /hackernews/frontpage/page-2/ - Lists stories 31-60 (fetched on first access)
+//! - cat /hackernews/frontpage.xml - RSS feed of the front page stories
+//! - cat /hackernews/frontpage/1.summary.md - Generated summary of the article and top comments
+//! - ls /hackernews/archive/2024-06-01/frontpage/ - The front page as of the last refresh that day
 
 use agfs_wasm_ffi::prelude::*;
+use agfs_wasm_ffi::filesystem::read_range;
+use agfs_wasm_ffi::{
+    archive, cache, clock, eprintln, html2md, time, ArchiveView, DownloadCache, FrontMatter, CompletionOptions, HostAI, HostEmbed, RenderCache, Table, Template, VectorIndex,
+};
 use indoc::formatdoc;
 use serde::{Deserialize, Serialize};
-use std::cell::RefCell;
+use std::cell::{Cell, Ref, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
 const HN_API_BASE: &str = "https://hacker-news.firebaseio.com/v0";
+
+/// Age of the last refresh past which the next one refetches every story:
+/// `/v0/updates.json` only lists the last few minutes of changes
+const UPDATES_MAX_AGE_MS: u64 = 5 * 60 * 1000;
+/// Number of stories per front page (page 1 is `/frontpage/`, the rest `/frontpage/page-N/`)
 const MAX_STORIES: usize = 30;
+const DEFAULT_FETCH_CONCURRENCY: u32 = 8;
+/// Days of front page copies kept under `/archive`
+const DEFAULT_ARCHIVE_DAYS: usize = 30;
+/// Budget of the article download cache
+const DEFAULT_DOWNLOAD_CACHE_MB: u64 = 256;
+const RSS_CONTENT_TYPE: &str = "application/rss+xml; charset=utf-8";
+/// Stories listed for each semantic search
+const SEARCH_RESULTS: usize = 10;
+/// Searches whose results are kept until the next refresh
+const MAX_CACHED_SEARCHES: usize = 64;
+/// Characters of a story embedded for semantic search
+const EMBED_TEXT_CHARS: usize = 2000;
+/// Comments that go into a story summary, in HN's ranking
+const SUMMARY_COMMENTS: usize = 5;
+/// Characters of the linked article that go into a story summary
+const SUMMARY_ARTICLE_CHARS: usize = 24_000;
+/// Characters of each comment that go into a story summary
+const SUMMARY_COMMENT_CHARS: usize = 2_000;
+const SUMMARY_SYSTEM_PROMPT: &str = "You write concise, neutral summaries of Hacker News stories in Markdown.";
+
+/// Layout of `/frontpage/N.md`, overridable with the `story_template` config key
+const STORY_TEMPLATE: &str = "\
+# {{ title }}
+
+**Story #{{ rank }}**
+
+- **Author**: {{ by }}
+- **Score**: {{ score }}
+- **Comments**: {{ comments }}
+- **ID**: {{ id }}
+{% if url %}
+- **URL**: {{ url }}
+{% endif %}
+- **Time**: {{ time }}
+{% if dead %}
+
+> Flagged dead on Hacker News: hidden from the site unless showdead is on.
+{% endif %}
+{% if stale %}
+
+> Could not be refreshed ({{ stale }}); this is the copy from an earlier refresh.
+{% endif %}
+{% if text %}
+
+## Content
+
+{{ text }}
+{% endif %}
+{% if poll %}
+
+## Poll
+
+{{ poll }}
+{% endif %}
+{% if article %}
+
+## Article Content
+
+{{ article }}
+{% endif %}
+
+---
+View on HN: https://news.ycombinator.com/item?id={{ id }}
+";
+
+/// `eprintln!` when the `debug` config flag is set; the output also lands in
+/// `/.agfs/log`
+macro_rules! debug {
+    ($fs:expr, $($arg:tt)*) => {
+        if $fs.debug {
+            eprintln!($($arg)*);
+        }
+    };
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 struct HNItem {
     id: u64,
+    /// "story", "comment", "job", "poll" or "pollopt"
+    #[serde(rename = "type", default)]
+    type_: String,
     #[serde(default)]
     title: String,
     #[serde(default)]
@@ -30,14 +122,35 @@ struct HNItem {
     descendants: i64,
     #[serde(default)]
     time: i64,
+    /// Ids of the direct replies, in HN's ranking
+    #[serde(default)]
+    kids: Vec<u64>,
+    /// Ids of the options of a poll, in order
+    #[serde(default)]
+    parts: Vec<u64>,
+    /// Removed by its author or a moderator; every other field but `id`,
+    /// `type` and `time` is then missing
+    #[serde(default)]
+    deleted: bool,
+    /// Killed by flags or moderators; the content is still there
+    #[serde(default)]
+    dead: bool,
     #[serde(skip)]
     url_content: RefCell<Option<String>>,
+    /// Options of a poll with their votes, fetched on first read
+    #[serde(skip)]
+    poll_options: RefCell<Option<Vec<HNItem>>>,
+    /// Why the last refresh failed to fetch this item, if it did; the item
+    /// is then the copy from an earlier refresh
+    #[serde(skip)]
+    stale: Option<String>,
 }
 
 impl Default for HNItem {
     fn default() -> Self {
         Self {
             id: 0,
+            type_: String::new(),
             title: String::new(),
             by: String::new(),
             score: 0,
@@ -45,38 +158,136 @@ impl Default for HNItem {
             text: String::new(),
             descendants: 0,
             time: 0,
+            kids: Vec::new(),
+            parts: Vec::new(),
+            deleted: false,
+            dead: false,
             url_content: RefCell::new(None),
+            poll_options: RefCell::new(None),
+            stale: None,
         }
     }
 }
 
-#[derive(Default)]
+/// Fields available to the story template
+#[derive(Serialize)]
+struct StoryView<'a> {
+    rank: usize,
+    id: u64,
+    title: &'a str,
+    by: &'a str,
+    score: i64,
+    comments: i64,
+    url: &'a str,
+    time: i64,
+    /// Story text converted from HN's HTML
+    text: String,
+    article: Option<String>,
+    /// Results table of a poll
+    poll: Option<String>,
+    dead: bool,
+    /// Why the story could not be refreshed
+    stale: Option<&'a str>,
+}
+
+/// Response of `/v0/updates.json`: ids of items and profiles changed recently
+#[derive(Debug, Default, Deserialize)]
+struct HNUpdates {
+    #[serde(default)]
+    items: Vec<u64>,
+}
+
 pub struct HackerNewsFS {
     stories: RefCell<Vec<HNItem>>,
+    /// All top story ids from the last refresh (up to 500)
+    story_ids: RefCell<Vec<u64>>,
+    /// Lazily fetched stories of pages 2 and beyond, keyed by page number
+    pages: RefCell<HashMap<usize, Vec<HNItem>>>,
+    /// Number of items actually fetched from the API during the last refresh
+    last_fetched: Cell<usize>,
+    /// When the last refresh started, in ms, 0 before the first
+    refreshed_ms: Cell<u64>,
+    /// Per-item failures of the last refresh, exposed as /errors.log
+    errors: RefCell<Vec<String>>,
+    /// Generation of every listing: the time of the last refresh in ms, as
+    /// listings only change when stories are refreshed
+    generation: Cell<u64>,
+    /// Maximum number of item requests in flight at once
+    fetch_concurrency: u32,
+    story_template: Template,
+    /// Whether story and summary files start with YAML front matter
+    front_matter: bool,
+    /// Rendered stories by id, re-rendered when the story changes
+    rendered: RenderCache<u64>,
+    /// Daily copies of the front page, `None` with `archive_days` set to 0
+    archive: Option<ArchiveView>,
+    /// Whether front page stories are embedded for /semantic-search
+    semantic_search: bool,
+    /// Embeddings of the front page stories by id
+    index: RefCell<VectorIndex<u64>>,
+    /// Story ids found by recent searches, best first, by query
+    searches: RefCell<HashMap<String, Vec<u64>>>,
+    /// Whether stories get a generated `N.summary.md` next to them
+    summaries: bool,
+    /// Generated summaries by story id, regenerated when the link changes
+    summarized: RenderCache<u64>,
+    /// Downloaded articles on the host, shared by every mount using the
+    /// same `download_cache_dir`
+    downloads: Option<DownloadCache>,
+    /// Whether requests and responses are logged in detail
+    debug: bool,
+    /// Generated from the schema so it always matches the tree
+    readme: String,
+}
+
+impl Default for HackerNewsFS {
+    fn default() -> Self {
+        let mut fs = Self {
+            stories: RefCell::new(Vec::new()),
+            story_ids: RefCell::new(Vec::new()),
+            pages: RefCell::new(HashMap::new()),
+            last_fetched: Cell::new(0),
+            refreshed_ms: Cell::new(0),
+            errors: RefCell::new(Vec::new()),
+            generation: Cell::new(0),
+            fetch_concurrency: DEFAULT_FETCH_CONCURRENCY,
+            story_template: Template::parse(STORY_TEMPLATE).expect("built-in story template is valid"),
+            front_matter: true,
+            rendered: RenderCache::new(),
+            archive: Some(archive::view().keep(DEFAULT_ARCHIVE_DAYS)),
+            semantic_search: false,
+            index: RefCell::new(VectorIndex::new()),
+            searches: RefCell::new(HashMap::new()),
+            summaries: false,
+            summarized: RenderCache::new(),
+            downloads: None,
+            debug: false,
+            readme: String::new(),
+        };
+        fs.readme = fs.schema().to_readme("HackerNewsFS", &fs.config_params());
+        fs
+    }
 }
 
 impl HackerNewsFS {
     fn fetch_top_stories(&self) -> Result<()> {
+        let started = clock::now();
+
         // Fetch top story IDs
-        eprintln!("Fetching from: {}/topstories.json", HN_API_BASE);
+        debug!(self, "Fetching from: {}/topstories.json", HN_API_BASE);
         let response = Http::get(&format!("{}/topstories.json", HN_API_BASE))?;
 
-        eprintln!("Response status: {}", response.status_code);
-        eprintln!("Response headers: {:?}", response.headers);
-        eprintln!("Response body length: {}", response.body.len());
+        debug!(self, "Response status: {}", response.status_code);
+        debug!(self, "Response headers: {:?}", response.headers);
+        debug!(self, "Response body length: {}", response.body.len());
 
-        if !response.is_success() {
-            return Err(Error::Other(format!("Failed to fetch top stories: HTTP {}", response.status_code)));
-        }
+        response.error_for_status()?;
 
-        // Debug: print first 200 bytes of response
-        let preview = if response.body.len() > 200 {
-            String::from_utf8_lossy(&response.body[..200])
-        } else {
-            String::from_utf8_lossy(&response.body)
-        };
-        eprintln!("Response preview: '{}'", preview);
-        eprintln!("Response first 20 bytes (hex): {:02x?}", &response.body[..response.body.len().min(20)]);
+        if self.debug {
+            let preview = String::from_utf8_lossy(&response.body[..response.body.len().min(200)]);
+            eprintln!("Response preview: '{}'", preview);
+            eprintln!("Response first 20 bytes (hex): {:02x?}", &response.body[..response.body.len().min(20)]);
+        }
 
         if response.body.is_empty() {
             return Err(Error::Other("Response body is empty".to_string()));
@@ -85,111 +296,671 @@ impl HackerNewsFS {
         let story_ids: Vec<u64> = response.json()
             .map_err(|e| Error::Other(format!("Failed to parse story IDs: {}", e)))?;
 
-        // Fetch first MAX_STORIES items
-        let mut stories = Vec::new();
-        for (i, &id) in story_ids.iter().take(MAX_STORIES).enumerate() {
-            match self.fetch_story(id) {
-                Ok(story) => {
-                    stories.push(story);
-                }
+        // Only refetch items that are new to the front page or reported as
+        // changed by the updates endpoint; everything else is served from cache
+        let mut cached: HashMap<u64, HNItem> = self.stories.borrow_mut()
+            .drain(..)
+            .map(|story| (story.id, story))
+            .collect();
+
+        let age_ms = (started.as_millis() as u64).saturating_sub(self.refreshed_ms.get());
+        let changed = if cached.is_empty() {
+            HashSet::new()
+        } else if age_ms > UPDATES_MAX_AGE_MS {
+            debug!(self, "Last refresh was {} s ago, refetching all stories", age_ms / 1000);
+            cached.clear();
+            HashSet::new()
+        } else {
+            match self.fetch_updates() {
+                Ok(changed) => changed,
                 Err(e) => {
-                    eprintln!("Failed to fetch story {}: {:?}", id, e);
-                    // Continue with other stories
+                    eprintln!("Failed to fetch updates, refetching all stories: {:?}", e);
+                    cached.clear();
+                    HashSet::new()
                 }
             }
+        };
 
-            // Show progress
-            if (i + 1) % 10 == 0 {
-                eprintln!("Fetched {}/{} stories...", i + 1, MAX_STORIES);
+        let top_ids: Vec<u64> = story_ids.iter().copied().take(MAX_STORIES).collect();
+        let to_fetch: Vec<u64> = top_ids.iter()
+            .copied()
+            .filter(|id| cached.get(id).is_none_or(|story| story.stale.is_some()) || changed.contains(id))
+            .collect();
+
+        let mut fetched_items = self.fetch_stories(&to_fetch);
+        let mut errors = Vec::new();
+        let mut stories = Vec::new();
+        let mut reused = 0;
+        for id in top_ids {
+            let previous = cached.remove(&id);
+            match fetched_items.remove(&id) {
+                None => {
+                    if let Some(story) = previous {
+                        stories.push(story);
+                        reused += 1;
+                    }
+                }
+                Some(Ok(story)) if story.deleted => {
+                    // Nothing left to show; not an error either
+                    debug!(self, "Skipping deleted story {}", id);
+                }
+                Some(Ok(story)) => {
+                    // Keep already fetched article content if the link is unchanged
+                    if let Some(previous) = previous {
+                        if previous.url == story.url {
+                            *story.url_content.borrow_mut() = previous.url_content.take();
+                        }
+                    }
+                    stories.push(story);
+                }
+                Some(Err(e)) => {
+                    // Keep the previous copy so later stories keep their rank
+                    match previous {
+                        Some(mut story) => {
+                            errors.push(format!("story {}: {} (serving the previous copy)", id, e));
+                            story.stale = Some(e.to_string());
+                            stories.push(story);
+                        }
+                        None => errors.push(format!("story {}: {}", id, e)),
+                    }
+                }
             }
         }
 
+        eprintln!(
+            "hackernewsfs: refresh stories={} fetched={} reused={} errors={} elapsed_ms={}",
+            stories.len(),
+            to_fetch.len(),
+            reused,
+            errors.len(),
+            clock::now().saturating_sub(started).as_millis()
+        );
         *self.stories.borrow_mut() = stories;
+        *self.story_ids.borrow_mut() = story_ids;
+        // Later pages are refetched on their next access
+        self.pages.borrow_mut().clear();
+        *self.errors.borrow_mut() = errors;
+        self.last_fetched.set(to_fetch.len());
+        self.refreshed_ms.set(started.as_millis() as u64);
+        let now = clock::now().as_millis() as u64;
+        self.generation.set(now.max(self.generation.get() + 1));
+        self.archive_frontpage();
+        self.index_frontpage();
         Ok(())
     }
 
-    fn fetch_story(&self, id: u64) -> Result<HNItem> {
-        let url = format!("{}/item/{}.json", HN_API_BASE, id);
-        let response = Http::get(&url)?;
-
-        if !response.is_success() {
-            return Err(Error::Other(format!("HTTP {}", response.status_code)));
+    /// Embed the front page stories not yet in the search index, dropping
+    /// those that left the front page
+    fn index_frontpage(&self) {
+        if !self.semantic_search {
+            return;
+        }
+        self.searches.borrow_mut().clear();
+        let stories = self.stories.borrow();
+        let mut index = self.index.borrow_mut();
+        index.retain(|id| stories.iter().any(|story| story.id == *id));
+        let missing: Vec<&HNItem> = stories.iter().filter(|story| !index.contains(&story.id)).collect();
+        if missing.is_empty() {
+            return;
         }
 
-        response.json()
-            .map_err(|e| Error::Other(format!("Failed to parse story: {}", e)))
+        let texts: Vec<String> = missing.iter().map(|story| embed_text(story)).collect();
+        match HostEmbed::embed(&texts) {
+            Ok(vectors) => {
+                for (story, vector) in missing.iter().zip(vectors) {
+                    if let Err(e) = index.insert(story.id, vector) {
+                        eprintln!("Failed to index story {}: {:?}", story.id, e);
+                    }
+                }
+            }
+            Err(e) => eprintln!("Failed to embed stories for semantic search: {:?}", e),
+        }
     }
 
-    fn fetch_url_content(&self, url: &str) -> Result<String> {
-        let jina_url = format!("https://r.jina.ai/{}", url);
-        eprintln!("Fetching content from: {}", jina_url);
+    /// Ids of the front page stories closest to `query`, best first
+    fn search(&self, query: &str) -> Result<Vec<u64>> {
+        if !self.semantic_search {
+            return Err(Error::NotFound);
+        }
+        if let Some(ids) = self.searches.borrow().get(query) {
+            return Ok(ids.clone());
+        }
 
-        let response = Http::get(&jina_url)?;
+        let vector = HostEmbed::embed(&[query])?
+            .pop()
+            .ok_or_else(|| Error::Io("no embedding for the query".to_string()))?;
+        let ids: Vec<u64> = self.index.borrow()
+            .search(&vector, SEARCH_RESULTS)?
+            .into_iter()
+            .map(|hit| hit.key)
+            .collect();
 
-        if !response.is_success() {
-            return Err(Error::Other(format!("HTTP {}", response.status_code)));
+        let mut searches = self.searches.borrow_mut();
+        if searches.len() >= MAX_CACHED_SEARCHES {
+            searches.clear();
         }
+        searches.insert(query.to_string(), ids.clone());
+        Ok(ids)
+    }
 
-        String::from_utf8(response.body)
-            .map_err(|e| Error::Other(format!("Failed to parse URL content: {}", e)))
+    /// The `n`th result of `query` (1-based) as its front page index and story
+    fn search_result(&self, query: &str, n: usize) -> Result<(usize, Ref<'_, HNItem>)> {
+        let ids = self.search(query)?;
+        let id = *n.checked_sub(1).and_then(|i| ids.get(i)).ok_or(Error::NotFound)?;
+        let stories = self.stories.borrow();
+        let index = stories.iter().position(|story| story.id == id).ok_or(Error::NotFound)?;
+        Ok((index, Ref::map(stories, |stories| &stories[index])))
     }
 
-    fn story_to_markdown(&self, index: usize, story: &HNItem) -> String {
-        let url_line = if !story.url.is_empty() {
-            format!("- **URL**: {}\n", story.url)
-        } else {
-            String::new()
+    /// Copy the front page stories and feed into today's archive directory
+    fn archive_frontpage(&self) {
+        let Some(archive) = &self.archive else {
+            return;
         };
+        let stories: Result<Vec<(String, Arc<[u8]>)>> = self.stories.borrow()
+            .iter()
+            .enumerate()
+            .map(|(index, story)| Ok((format!("{}.md", index + 1), self.story_content(index, story)?)))
+            .collect();
+        let result = stories
+            .and_then(|stories| archive.record_dir("frontpage", stories))
+            .and_then(|()| archive.record_file("frontpage.xml", self.frontpage_rss().into_bytes()));
+        if let Err(e) = result {
+            eprintln!("Failed to archive the front page: {:?}", e);
+        }
+    }
 
-        let content_section = if !story.text.is_empty() {
-            formatdoc! {"
+    fn archive(&self) -> Result<&ArchiveView> {
+        self.archive.as_ref().ok_or(Error::NotFound)
+    }
 
-                ## Content
+    /// Number of front pages available from the last refresh
+    fn page_count(&self) -> usize {
+        self.story_ids.borrow().len().div_ceil(MAX_STORIES).max(1)
+    }
 
-                {}
-            ", story.text}
-        } else {
-            String::new()
-        };
+    /// Stories of the given page, fetching the page on first access
+    fn page_stories(&self, page: usize) -> Result<Ref<'_, Vec<HNItem>>> {
+        if page == 1 {
+            return Ok(self.stories.borrow());
+        }
+        if page > self.page_count() {
+            return Err(Error::NotFound);
+        }
 
-        let url_content_section = if let Some(ref content) = *story.url_content.borrow() {
-            formatdoc! {"
+        if !self.pages.borrow().contains_key(&page) {
+            let ids: Vec<u64> = self.story_ids.borrow()
+                .iter()
+                .copied()
+                .skip((page - 1) * MAX_STORIES)
+                .take(MAX_STORIES)
+                .collect();
 
-                ## Article Content
+            let mut fetched = self.fetch_stories(&ids);
+            let mut stories = Vec::new();
+            for id in ids {
+                match fetched.remove(&id) {
+                    Some(Ok(story)) if story.deleted => {}
+                    Some(Ok(story)) => stories.push(story),
+                    Some(Err(e)) => self.errors.borrow_mut().push(format!("story {}: {}", id, e)),
+                    None => {}
+                }
+            }
+            self.pages.borrow_mut().insert(page, stories);
+        }
 
-                {}
-            ", content}
-        } else {
-            String::new()
+        Ok(Ref::map(self.pages.borrow(), |pages| &pages[&page]))
+    }
+
+    /// Look up a story by page and overall rank (1-based, continuing across pages)
+    fn story_at(&self, page: usize, rank: usize) -> Result<Ref<'_, HNItem>> {
+        let first = (page - 1) * MAX_STORIES + 1;
+        if rank < first {
+            return Err(Error::NotFound);
+        }
+
+        let stories = self.page_stories(page)?;
+        if rank - first >= stories.len() {
+            return Err(Error::NotFound);
+        }
+        Ok(Ref::map(stories, |stories| &stories[rank - first]))
+    }
+
+    /// File entries for the stories of a page, named by their overall rank
+    fn page_entries(&self, page: usize) -> Result<Vec<FileInfo>> {
+        let first = (page - 1) * MAX_STORIES;
+        let stories = self.page_stories(page)?;
+
+        // Stories are rendered on read, so listing a page renders nothing
+        let mut entries = Vec::new();
+        for (rank, story) in (first + 1..).zip(stories.iter()) {
+            entries.push(story_info(rank, story));
+            if self.summaries {
+                entries.push(FileInfo::generated(format!("{}.summary.md", rank), 0o444));
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Fetch the linked article of `story` unless it already was
+    fn load_article(&self, story: &HNItem) {
+        if story.url.is_empty() || story.url_content.borrow().is_some() {
+            return;
+        }
+        match self.fetch_url_content(&story.url) {
+            Ok(content) => {
+                *story.url_content.borrow_mut() = Some(content);
+            }
+            Err(e) => {
+                eprintln!("Failed to fetch URL content for {}: {:?}", story.url, e);
+                // Continue without URL content
+            }
+        }
+    }
+
+    /// Fetch the options of `story` if it is a poll and they were not yet
+    fn load_poll(&self, story: &HNItem) {
+        if story.type_ != "poll" || story.parts.is_empty() || story.poll_options.borrow().is_some() {
+            return;
+        }
+        let mut fetched = self.fetch_stories(&story.parts);
+        let mut options = Vec::new();
+        for id in &story.parts {
+            match fetched.remove(id) {
+                Some(Ok(option)) if option.deleted => {}
+                Some(Ok(option)) => options.push(option),
+                Some(Err(e)) => eprintln!("Failed to fetch poll option {} of {}: {:?}", id, story.id, e),
+                None => {}
+            }
+        }
+        *story.poll_options.borrow_mut() = Some(options);
+    }
+
+    /// The summary of `story`, generated on first read and kept until its
+    /// link changes
+    fn story_summary(&self, rank: usize, story: &HNItem) -> Result<Arc<[u8]>> {
+        let version = cache::fingerprint(&(&story.title, &story.url));
+        self.summarized.render(story.id, version, || {
+            self.load_article(story);
+            let summary = HostAI::complete(
+                &self.summary_prompt(story),
+                &CompletionOptions::new()
+                    .system(SUMMARY_SYSTEM_PROMPT)
+                    .max_tokens(800)
+                    .temperature(0.2),
+            )?;
+            let markdown = formatdoc! {"
+                # Summary: {title}
+
+                {summary}
+
+                ---
+                Generated from the linked article and top comments of story #{rank}.
+                View on HN: https://news.ycombinator.com/item?id={id}
+                ",
+                title = story.title,
+                summary = summary.trim(),
+                rank = rank,
+                id = story.id,
+            };
+            let meta = FrontMatter::new()
+                .field("title", format!("Summary: {}", story.title))
+                .field("id", story.id)
+                .field("rank", rank)
+                .field("summary_of", format!("https://news.ycombinator.com/item?id={}", story.id));
+            Ok::<_, Error>(self.with_front_matter(meta, &markdown))
+        })
+    }
+
+    /// Prompt asking for a 3-paragraph summary of the article and the top
+    /// comments of `story`
+    fn summary_prompt(&self, story: &HNItem) -> String {
+        let mut prompt = format!(
+            "Summarize this Hacker News story in exactly three paragraphs: the linked article, \
+             the discussion in the top comments, and what readers found notable or disputed.\n\n\
+             Title: {}\n",
+            story.title
+        );
+        if !story.url.is_empty() {
+            prompt.push_str(&format!("URL: {}\n", story.url));
+        }
+        if !story.text.is_empty() {
+            prompt.push_str(&format!("\nText:\n{}\n", html2md::to_markdown(&story.text)));
+        }
+        if let Some(article) = story.url_content.borrow().as_deref() {
+            prompt.push_str(&format!("\nArticle:\n{}\n", truncate_chars(article, SUMMARY_ARTICLE_CHARS)));
+        }
+
+        let ids: Vec<u64> = story.kids.iter().copied().take(SUMMARY_COMMENTS).collect();
+        let mut comments = self.fetch_stories(&ids);
+        let comments: Vec<HNItem> = ids.iter()
+            .filter_map(|id| comments.remove(id)?.ok())
+            .filter(|comment| !comment.deleted && !comment.dead && !comment.text.is_empty())
+            .collect();
+        if !comments.is_empty() {
+            prompt.push_str("\nTop comments:\n");
+            for comment in comments {
+                let text = html2md::to_markdown(&comment.text);
+                prompt.push_str(&format!("\n{} wrote:\n{}\n", comment.by, truncate_chars(&text, SUMMARY_COMMENT_CHARS)));
+            }
+        }
+        prompt
+    }
+
+    /// Fetch the given items, running up to `fetch_concurrency` requests in parallel
+    fn fetch_stories(&self, ids: &[u64]) -> HashMap<u64, Result<HNItem>> {
+        let urls: Vec<String> = ids.iter()
+            .map(|id| format!("{}/item/{}.json", HN_API_BASE, id))
+            .collect();
+
+        let responses = Http::get_many(&urls, self.fetch_concurrency);
+        ids.iter()
+            .copied()
+            .zip(responses)
+            .map(|(id, response)| (id, response.and_then(Self::parse_story)))
+            .collect()
+    }
+
+    /// Fetch the set of item ids changed since the last poll
+    fn fetch_updates(&self) -> Result<HashSet<u64>> {
+        let response = Http::get(&format!("{}/updates.json", HN_API_BASE))?;
+
+        response.error_for_status()?;
+
+        let updates: HNUpdates = response.json()
+            .map_err(|e| Error::Other(format!("Failed to parse updates: {}", e)))?;
+
+        Ok(updates.items.into_iter().collect())
+    }
+
+    fn parse_story(response: HttpResponse) -> Result<HNItem> {
+        response.error_for_status()?;
+
+        response.json()
+            .map_err(|e| Error::Other(format!("Failed to parse story: {}", e)))
+    }
+
+    /// Render the failures of the last refresh, one per line
+    fn errors_log(&self) -> String {
+        self.errors.borrow()
+            .iter()
+            .map(|line| format!("{}\n", line))
+            .collect()
+    }
+
+    fn fetch_url_content(&self, url: &str) -> Result<String> {
+        let jina_url = format!("https://r.jina.ai/{}", url);
+        debug!(self, "Fetching content from: {}", jina_url);
+
+        let body = match &self.downloads {
+            Some(downloads) => downloads.get(&jina_url)?,
+            None => {
+                let response = Http::get_cached(&jina_url)?;
+                response.error_for_status()?;
+                response.body
+            }
         };
 
+        String::from_utf8(body)
+            .map_err(|e| Error::Other(format!("Failed to parse URL content: {}", e)))
+    }
+
+    /// Render the front page stories as an RSS 2.0 feed
+    fn frontpage_rss(&self) -> String {
+        let items: String = self.stories.borrow()
+            .iter()
+            .map(|story| {
+                let discussion = format!("https://news.ycombinator.com/item?id={}", story.id);
+                let link = if story.url.is_empty() { &discussion } else { &story.url };
+                formatdoc! {"
+                    <item>
+                      <title>{}</title>
+                      <link>{}</link>
+                      <guid isPermaLink=\"true\">{}</guid>
+                      <comments>{}</comments>
+                      <author>{}</author>
+                      <pubDate>{}</pubDate>
+                    </item>
+                ",
+                    xml_escape(&story.title),
+                    xml_escape(link),
+                    xml_escape(&discussion),
+                    xml_escape(&discussion),
+                    xml_escape(&story.by),
+                    rfc822_date(story.time)
+                }
+            })
+            .collect();
+
         formatdoc! {"
-            # {}
-
-            **Story #{}**
-
-            - **Author**: {}
-            - **Score**: {}
-            - **Comments**: {}
-            - **ID**: {}
-            {}- **Time**: {}
-            {}{}
-            ---
-            View on HN: https://news.ycombinator.com/item?id={}
-        ",
-            story.title,
-            index + 1,
-            story.by,
+            <?xml version=\"1.0\" encoding=\"UTF-8\"?>
+            <rss version=\"2.0\">
+            <channel>
+            <title>Hacker News: Front Page</title>
+            <link>https://news.ycombinator.com/</link>
+            <description>Top stories from Hacker News</description>
+            {}</channel>
+            </rss>
+        ", items}
+    }
+
+    fn rss_info(&self) -> FileInfo {
+        let meta = MetaData::new("hackernewsfs", "rss")
+            .with_content(serde_json::json!({ "content_type": RSS_CONTENT_TYPE }));
+        FileInfo::generated("frontpage.xml", 0o444)
+            .with_content_type(RSS_CONTENT_TYPE)
+            .with_meta(meta)
+    }
+
+    /// The story rendered as Markdown, rendering it only when it changed
+    /// since it was last read
+    fn story_content(&self, index: usize, story: &HNItem) -> Result<Arc<[u8]>> {
+        let version = cache::fingerprint(&(
+            index,
+            &story.title,
+            &story.by,
             story.score,
             story.descendants,
-            story.id,
-            url_line,
+            &story.url,
             story.time,
-            content_section,
-            url_content_section,
-            story.id
+            &story.text,
+            story.dead,
+            story.url_content.borrow().is_some(),
+            story.poll_options.borrow().as_ref().map(|options| options.iter().map(|o| o.score).collect::<Vec<_>>()),
+            &story.stale,
+        ));
+        self.rendered.render(story.id, version, || self.story_to_markdown(index, story))
+    }
+
+    fn story_to_markdown(&self, index: usize, story: &HNItem) -> Result<String> {
+        let markdown = self.story_template.render(&StoryView {
+            rank: index + 1,
+            id: story.id,
+            title: &story.title,
+            by: &story.by,
+            score: story.score,
+            comments: story.descendants,
+            url: &story.url,
+            time: story.time,
+            text: html2md::to_markdown(&story.text),
+            article: story.url_content.borrow().clone(),
+            poll: story.poll_options.borrow().as_deref().and_then(poll_results),
+            dead: story.dead,
+            stale: story.stale.as_deref(),
+        })?;
+        Ok(self.with_front_matter(story_front_matter(index + 1, story), &markdown))
+    }
+
+    fn with_front_matter(&self, meta: FrontMatter, markdown: &str) -> String {
+        match self.front_matter {
+            true => meta.render(markdown),
+            false => markdown.to_string(),
+        }
+    }
+}
+
+/// Metadata of a story file, for static site and note tools
+fn story_front_matter(rank: usize, story: &HNItem) -> FrontMatter {
+    let mut meta = FrontMatter::new()
+        .field("title", story.title.as_str())
+        .field("id", story.id)
+        .field("rank", rank)
+        .field("author", story.by.as_str())
+        .field("score", story.score)
+        .field("comments", story.descendants);
+    if !story.url.is_empty() {
+        meta = meta.field("url", story.url.as_str());
+    }
+    meta = meta.field("date", iso_date(story.time))
+        .field("hn_url", format!("https://news.ycombinator.com/item?id={}", story.id));
+    if story.dead {
+        meta = meta.field("dead", true);
+    }
+    if story.stale.is_some() {
+        meta = meta.field("stale", true);
+    }
+    meta
+}
+
+/// File entry of a story, with `stale` metadata when the last refresh kept
+/// an earlier copy of it
+fn story_info(rank: usize, story: &HNItem) -> FileInfo {
+    let info = FileInfo::generated(format!("{}.md", rank), 0o644);
+    match &story.stale {
+        Some(reason) => info.with_meta(
+            MetaData::new("hackernewsfs", "story")
+                .with_content(serde_json::json!({ "stale": true, "error": reason })),
+        ),
+        None => info,
+    }
+}
+
+/// Markdown table of the votes of each poll option and their share of the
+/// total, or `None` without options
+fn poll_results(options: &[HNItem]) -> Option<String> {
+    if options.is_empty() {
+        return None;
+    }
+    let total: i64 = options.iter().map(|option| option.score.max(0)).sum();
+    let mut table = Table::new(["Option", "Votes", "Share"]);
+    for option in options {
+        let share = match total {
+            0 => 0.0,
+            total => option.score.max(0) as f64 * 100.0 / total as f64,
+        };
+        table = table.row([
+            serde_json::json!(html2md::to_markdown(&option.text).trim()),
+            serde_json::json!(option.score),
+            serde_json::json!(format!("{:.1}%", share)),
+        ]);
+    }
+    Some(table.to_markdown())
+}
+
+/// What is embedded of a story: its title and the start of its text
+fn embed_text(story: &HNItem) -> String {
+    let text = format!("{}\n\n{}", story.title, html2md::to_markdown(&story.text));
+    truncate_chars(&text, EMBED_TEXT_CHARS).to_string()
+}
+
+/// The first `max` characters of `text`
+fn truncate_chars(text: &str, max: usize) -> &str {
+    match text.char_indices().nth(max) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    }
+}
+
+/// Escape text for inclusion in XML element content
+fn xml_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
         }
     }
+    out
+}
+
+/// Format a Unix timestamp as an ISO 8601 UTC date and time
+fn iso_date(timestamp: i64) -> String {
+    time::rfc3339(timestamp.max(0))
+}
+
+/// Format a Unix timestamp as an RFC 822 date (as required by RSS `pubDate`)
+fn rfc822_date(timestamp: i64) -> String {
+    time::rfc822(timestamp)
+}
+
+/// Split a path to a story summary into its page number and story rank
+///
+/// `/frontpage/3.summary.md` summarizes story 3 on page 1.
+fn parse_summary_path(path: &str) -> Option<(usize, usize)> {
+    let story = format!("{}.md", path.strip_suffix(".summary.md")?);
+    match parse_frontpage_path(&story)? {
+        (page, Some(rank)) => Some((page, rank)),
+        _ => None,
+    }
+}
+
+/// Split a path under `/frontpage` into its page number and optional story rank
+///
+/// `/frontpage/3.md` is story 3 on page 1, `/frontpage/page-2/31.md` is story 31 on page 2.
+fn parse_frontpage_path(path: &str) -> Option<(usize, Option<usize>)> {
+    let rest = path.strip_prefix("/frontpage")?;
+    let rest = match rest {
+        "" => return Some((1, None)),
+        r => r.strip_prefix('/')?,
+    };
+
+    let (page, file) = match rest.strip_prefix("page-") {
+        Some(r) => {
+            let (page, file) = match r.split_once('/') {
+                Some((page, file)) => (page, Some(file)),
+                None => (r, None),
+            };
+            let page: usize = page.parse().ok()?;
+            if page < 2 {
+                return None;
+            }
+            (page, file)
+        }
+        None => (1, Some(rest)),
+    };
+
+    match file {
+        None => Some((page, None)),
+        Some(file) => {
+            let rank: usize = file.strip_suffix(".md")?.parse().ok()?;
+            Some((page, Some(rank)))
+        }
+    }
+}
+
+/// Split a path under `/semantic-search` into its query and optional result number
+///
+/// `/semantic-search/rust compilers/2.md` is the second result for "rust compilers".
+fn parse_search_path(path: &str) -> Option<(Option<&str>, Option<usize>)> {
+    let rest = match path.strip_prefix("/semantic-search")? {
+        "" => return Some((None, None)),
+        r => r.strip_prefix('/')?,
+    };
+    let (query, file) = match rest.split_once('/') {
+        Some((query, file)) => (query, Some(file)),
+        None => (rest, None),
+    };
+    if query.trim().is_empty() {
+        return None;
+    }
+    match file {
+        None => Some((Some(query), None)),
+        Some(file) => Some((Some(query), Some(file.strip_suffix(".md")?.parse().ok()?))),
+    }
 }
 
 impl FileSystem for HackerNewsFS {
@@ -198,78 +969,189 @@ impl FileSystem for HackerNewsFS {
     }
 
     fn readme(&self) -> &str {
-        "HackerNewsFS - Access Hacker News stories as files\n\
-         \n\
-         Usage:\n\
-         - cat /hackernews/refresh - Refresh story list from HN\n\
-         - ls /hackernews/frontpage/ - List all stories\n\
-         - cat /hackernews/frontpage/1.md - Read story #1\n\
-         - cat /hackernews/frontpage/2.md - Read story #2\n\
-         etc.\n"
+        &self.readme
     }
 
     fn config_params(&self) -> Vec<ConfigParameter> {
         vec![
             ConfigParameter::new(
-                "max_stories",
+                "fetch_concurrency",
+                "int",
+                false,
+                "8",
+                "Maximum number of story requests in flight at once"
+            ),
+            ConfigParameter::new(
+                "story_template",
+                "string",
+                false,
+                "",
+                "Template for story files ({{ title }}, {{ url }}, {% if text %}...{% endif %}, ...)"
+            ),
+            ConfigParameter::new(
+                "front_matter",
+                "bool",
+                false,
+                "true",
+                "Start story and summary files with YAML front matter (title, author, score, url, date, ...)"
+            ),
+            ConfigParameter::new(
+                "archive_days",
                 "int",
                 false,
                 "30",
-                "Maximum number of stories to fetch"
+                "Days of front page copies kept under /archive (0 disables the archive)"
+            ),
+            ConfigParameter::new(
+                "semantic_search",
+                "bool",
+                false,
+                "false",
+                "Embed stories through the server's embedding model for /semantic-search (opt-in)"
+            ),
+            ConfigParameter::new(
+                "summaries",
+                "bool",
+                false,
+                "false",
+                "List N.summary.md next to each story, generated by the server's completion model (opt-in)"
+            ),
+            ConfigParameter::new(
+                "download_cache_dir",
+                "string",
+                false,
+                "",
+                "Host directory caching downloaded articles across reads and restarts (off if empty)"
+            ),
+            ConfigParameter::new(
+                "download_cache_mb",
+                "int",
+                false,
+                "256",
+                "Megabytes of articles kept in download_cache_dir before the least recently read are evicted"
+            ),
+            ConfigParameter::new(
+                "debug",
+                "bool",
+                false,
+                "false",
+                "Log every request and response in detail (see /.agfs/log)"
             ),
         ]
     }
 
-    fn initialize(&mut self, _config: &Config) -> Result<()> {
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::default().with_http_hosts(["hacker-news.firebaseio.com", "r.jina.ai"])
+    }
+
+    fn schema(&self) -> FsSchema {
+        FsSchema::new("Hacker News front page stories as Markdown files")
+            .path(PathSchema::file("/refresh", "Read or write to refetch the story list").writable())
+            .path(PathSchema::file("/errors.log", "Stories that failed during the last refresh, and why").format("text/plain"))
+            .path(PathSchema::file("/frontpage.xml", "RSS feed of the front page").format(RSS_CONTENT_TYPE))
+            .path(PathSchema::dir("/frontpage", "Stories #1-#30, plus one page-N directory per further page"))
+            .path(PathSchema::file("/frontpage/{rank}.md", "Story with its linked article").format("text/markdown"))
+            .path(PathSchema::file("/frontpage/{rank}.summary.md", "Summary of the article and top comments").format("text/markdown"))
+            .path(PathSchema::dir("/frontpage/page-{n}", "Stories of page N, fetched on first access"))
+            .path(PathSchema::file("/frontpage/page-{n}/{rank}.md", "Story by overall rank").format("text/markdown"))
+            .path(PathSchema::dir("/archive", "One directory per day, holding the front page of its last refresh"))
+            .path(PathSchema::file("/archive/{date}/frontpage.xml", "RSS feed of that day").format(RSS_CONTENT_TYPE))
+            .path(PathSchema::file("/archive/{date}/frontpage/{rank}.md", "Story of that day").format("text/markdown"))
+            .path(PathSchema::dir("/semantic-search", "Empty; open a directory named after a query inside it"))
+            .path(PathSchema::dir("/semantic-search/{query}", "The front page stories closest in meaning to the query"))
+            .path(PathSchema::file("/semantic-search/{query}/{n}.md", "Nth closest story").format("text/markdown"))
+    }
+
+    fn initialize(&mut self, config: &Config) -> Result<()> {
+        if let Some(concurrency) = config.get_i64("fetch_concurrency") {
+            if concurrency < 1 {
+                return Err(Error::InvalidInput("fetch_concurrency must be at least 1".to_string()));
+            }
+            self.fetch_concurrency = concurrency as u32;
+        }
+        if let Some(source) = config.get_str("story_template") {
+            self.story_template = Template::parse(source)?;
+        }
+        if let Some(enabled) = config.get_bool("front_matter") {
+            self.front_matter = enabled;
+        }
+        if let Some(days) = config.get_i64("archive_days") {
+            self.archive = match days {
+                ..0 => return Err(Error::InvalidInput("archive_days must not be negative".to_string())),
+                0 => None,
+                days => Some(archive::view().keep(days as usize)),
+            };
+        }
+        if let Some(enabled) = config.get_bool("semantic_search") {
+            self.semantic_search = enabled;
+        }
+        if let Some(enabled) = config.get_bool("summaries") {
+            self.summaries = enabled;
+        }
+
+        if let Some(dir) = config.get_str("download_cache_dir").filter(|dir| !dir.is_empty()) {
+            let mb = match config.get_i64("download_cache_mb") {
+                Some(mb) if mb < 1 => {
+                    return Err(Error::InvalidInput("download_cache_mb must be at least 1".to_string()))
+                }
+                Some(mb) => mb as u64,
+                None => DEFAULT_DOWNLOAD_CACHE_MB,
+            };
+            self.downloads = Some(DownloadCache::open(dir, mb << 20)?);
+        }
+
+        if let Some(enabled) = config.get_bool("debug") {
+            self.debug = enabled;
+        }
+
         // Fetch stories on initialization
-        eprintln!("HackerNewsFS: Fetching initial stories...");
-        self.fetch_top_stories()?;
-        eprintln!("HackerNewsFS: Loaded {} stories", self.stories.borrow().len());
-        Ok(())
+        debug!(self, "HackerNewsFS: Fetching initial stories...");
+        self.fetch_top_stories()
     }
 
-    fn read(&self, path: &str, _offset: i64, _size: i64) -> Result<Vec<u8>> {
+    fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
         match path {
             "/refresh" => {
                 // Trigger refresh
                 self.fetch_top_stories()?;
-                let msg = format!("Refreshed {} stories from Hacker News\n", self.stories.borrow().len());
+                let msg = format!(
+                    "Refreshed {} stories from Hacker News ({} fetched)\n",
+                    self.stories.borrow().len(),
+                    self.last_fetched.get()
+                );
                 Ok(msg.into_bytes())
             }
-            p if p.starts_with("/frontpage/") && p.ends_with(".md") => {
-                // Extract story number from filename
-                let filename = p.strip_prefix("/frontpage/")
-                    .unwrap()
-                    .strip_suffix(".md")
-                    .unwrap();
-
-                let index: usize = filename.parse()
-                    .map_err(|_| Error::NotFound)?;
-
-                if index == 0 || index > self.stories.borrow().len() {
-                    return Err(Error::NotFound);
+            "/errors.log" => Ok(self.errors_log().into_bytes()),
+            "/frontpage.xml" => Ok(read_range(self.frontpage_rss().as_bytes(), offset, size)),
+            p if archive::is_archive_path(p) => self.archive()?.read(p, offset, size),
+            p if p.starts_with("/semantic-search") => match parse_search_path(p) {
+                Some((Some(query), Some(n))) => {
+                    let (index, story) = self.search_result(query, n)?;
+                    Ok(read_range(&self.story_content(index, &story)?, offset, size))
                 }
+                Some(_) => Err(Error::IsDirectory),
+                None => Err(Error::NotFound),
+            },
+            p if p.ends_with(".summary.md") && self.summaries => {
+                let (page, rank) = parse_summary_path(p).ok_or(Error::NotFound)?;
+                let story = self.story_at(page, rank)?;
+                Ok(read_range(&self.story_summary(rank, &story)?, offset, size))
+            }
+            p => {
+                let (page, rank) = match parse_frontpage_path(p) {
+                    Some((page, Some(rank))) => (page, rank),
+                    Some((_, None)) => return Err(Error::IsDirectory),
+                    None => return Err(Error::NotFound),
+                };
+                let story = self.story_at(page, rank)?;
 
-                let stories = self.stories.borrow();
-                let story = &stories[index - 1];
-
-                // Lazy load URL content if not already fetched
-                if !story.url.is_empty() && story.url_content.borrow().is_none() {
-                    match self.fetch_url_content(&story.url) {
-                        Ok(content) => {
-                            *story.url_content.borrow_mut() = Some(content);
-                        }
-                        Err(e) => {
-                            eprintln!("Failed to fetch URL content for {}: {:?}", story.url, e);
-                            // Continue without URL content
-                        }
-                    }
-                }
+                // Lazy load URL content and poll results if not already fetched
+                self.load_article(&story);
+                self.load_poll(&story);
 
-                let content = self.story_to_markdown(index - 1, story);
-                Ok(content.into_bytes())
+                let content = self.story_content(rank - 1, &story)?;
+                Ok(read_range(&content, offset, size))
             }
-            _ => Err(Error::NotFound),
         }
     }
 
@@ -279,57 +1161,87 @@ impl FileSystem for HackerNewsFS {
             "/refresh" => {
                 Ok(FileInfo::file("refresh", 0, 0o644))
             }
-            "/frontpage" => {
-                Ok(FileInfo::dir("frontpage", 0o755))
+            "/errors.log" => {
+                Ok(FileInfo::file("errors.log", self.errors_log().len() as i64, 0o444))
             }
-            p if p.starts_with("/frontpage/") && p.ends_with(".md") => {
-                let filename = p.strip_prefix("/frontpage/")
-                    .unwrap()
-                    .strip_suffix(".md")
-                    .unwrap();
-
-                let index: usize = filename.parse()
-                    .map_err(|_| Error::NotFound)?;
-
-                if index == 0 || index > self.stories.borrow().len() {
-                    return Err(Error::NotFound);
+            "/frontpage.xml" => Ok(self.rss_info()),
+            p if archive::is_archive_path(p) => self.archive()?.stat(p),
+            p if p.starts_with("/semantic-search") && self.semantic_search => match parse_search_path(p) {
+                Some((None, _)) => Ok(FileInfo::dir("semantic-search", 0o755)),
+                // Every query has a directory; it is searched when listed
+                Some((Some(query), None)) => Ok(FileInfo::dir(query, 0o755)),
+                Some((Some(query), Some(n))) => {
+                    self.search_result(query, n)?;
+                    Ok(FileInfo::generated(format!("{}.md", n), 0o644))
                 }
-
-                let stories = self.stories.borrow();
-                let story = &stories[index - 1];
-                let content = self.story_to_markdown(index - 1, story);
-                let name = format!("{}.md", index);
-
-                Ok(FileInfo::file(&name, content.len() as i64, 0o644))
-            }
-            _ => Err(Error::NotFound),
+                None => Err(Error::NotFound),
+            },
+            p if p.ends_with(".summary.md") && self.summaries => match parse_summary_path(p) {
+                Some((page, rank)) => {
+                    // Generated on read; stat only checks the story exists
+                    self.story_at(page, rank)?;
+                    Ok(FileInfo::generated(format!("{}.summary.md", rank), 0o444))
+                }
+                None => Err(Error::NotFound),
+            },
+            p => match parse_frontpage_path(p) {
+                Some((1, None)) => Ok(FileInfo::dir("frontpage", 0o755)),
+                Some((page, None)) if page <= self.page_count() => {
+                    Ok(FileInfo::dir(format!("page-{}", page), 0o755))
+                }
+                Some((page, Some(rank))) => {
+                    // Rendered on read; stat only checks the story exists
+                    let story = self.story_at(page, rank)?;
+                    Ok(story_info(rank, &story))
+                }
+                _ => Err(Error::NotFound),
+            },
         }
     }
 
     fn readdir(&self, path: &str) -> Result<Vec<FileInfo>> {
         match path {
             "/" => {
-                Ok(vec![
+                let mut entries = vec![
                     FileInfo::file("refresh", 0, 0o644),
+                    FileInfo::file("errors.log", self.errors_log().len() as i64, 0o444),
                     FileInfo::dir("frontpage", 0o755),
-                ])
-            }
-            "/frontpage" => {
-                let stories = self.stories.borrow();
-                let mut entries = Vec::new();
-
-                for (i, story) in stories.iter().enumerate() {
-                    let name = format!("{}.md", i + 1);
-                    let content = self.story_to_markdown(i, story);
-                    entries.push(FileInfo::file(&name, content.len() as i64, 0o644));
+                    self.rss_info(),
+                ];
+                entries.extend(self.archive.as_ref().map(ArchiveView::root_entry));
+                if self.semantic_search {
+                    entries.push(FileInfo::dir("semantic-search", 0o755));
                 }
-
                 Ok(entries)
             }
-            _ => Err(Error::NotFound),
+            p if archive::is_archive_path(p) => self.archive()?.readdir(p),
+            p if p.starts_with("/semantic-search") && self.semantic_search => match parse_search_path(p) {
+                Some((None, _)) => Ok(Vec::new()),
+                Some((Some(query), None)) => {
+                    let ids = self.search(query)?;
+                    Ok((1..=ids.len()).map(|n| FileInfo::generated(format!("{}.md", n), 0o644)).collect())
+                }
+                Some((Some(_), Some(_))) => Err(Error::NotDirectory),
+                None => Err(Error::NotFound),
+            },
+            p => match parse_frontpage_path(p) {
+                Some((1, None)) => {
+                    let mut entries = self.page_entries(1)?;
+                    for page in 2..=self.page_count() {
+                        entries.push(FileInfo::dir(format!("page-{}", page), 0o755));
+                    }
+                    Ok(entries)
+                }
+                Some((page, None)) => self.page_entries(page),
+                _ => Err(Error::NotFound),
+            },
         }
     }
 
+    fn dir_generation(&self, _path: &str) -> u64 {
+        self.generation.get()
+    }
+
     fn write(&mut self, path: &str, _data: &[u8], _offset: i64, _flags: WriteFlag) -> Result<i64> {
         if path == "/refresh" {
             // Allow writing to refresh to trigger update
@@ -367,3 +1279,86 @@ impl FileSystem for HackerNewsFS {
 }
 
 export_plugin!(HackerNewsFS);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn poll_option(text: &str, score: i64) -> HNItem {
+        serde_json::from_value(serde_json::json!({ "id": 1, "type": "pollopt", "text": text, "score": score })).unwrap()
+    }
+
+    #[test]
+    fn test_parse_frontpage_path() {
+        assert_eq!(parse_frontpage_path("/frontpage"), Some((1, None)));
+        assert_eq!(parse_frontpage_path("/frontpage/3.md"), Some((1, Some(3))));
+        assert_eq!(parse_frontpage_path("/frontpage/page-2"), Some((2, None)));
+        assert_eq!(parse_frontpage_path("/frontpage/page-2/31.md"), Some((2, Some(31))));
+        assert_eq!(parse_frontpage_path("/frontpage/"), None);
+        assert_eq!(parse_frontpage_path("/frontpage/page-2/"), None);
+        for path in ["/frontpage/page-1", "/frontpage/page-0/1.md", "/frontpage/page-x", "/frontpage/page--2"] {
+            assert_eq!(parse_frontpage_path(path), None, "{}", path);
+        }
+        let others = ["/frontpages", "/frontpage/3", "/frontpage/three.md", "/frontpage.xml", "/archive/frontpage"];
+        for path in others {
+            assert_eq!(parse_frontpage_path(path), None, "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_parse_search_path() {
+        assert_eq!(parse_search_path("/semantic-search"), Some((None, None)));
+        assert_eq!(parse_search_path("/semantic-search/rust compilers"), Some((Some("rust compilers"), None)));
+        assert_eq!(parse_search_path("/semantic-search/rust compilers/2.md"), Some((Some("rust compilers"), Some(2))));
+        assert_eq!(parse_search_path("/semantic-search/rust/"), None);
+        for path in ["/semantic-search/", "/semantic-search/  ", "/semantic-search//1.md", "/semantic-search/ /1.md"] {
+            assert_eq!(parse_search_path(path), None, "{:?}", path);
+        }
+        for path in ["/semantic-searches", "/semantic-search/rust/two.md", "/semantic-search/rust/2"] {
+            assert_eq!(parse_search_path(path), None, "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_parse_summary_path() {
+        assert_eq!(parse_summary_path("/frontpage/3.summary.md"), Some((1, 3)));
+        assert_eq!(parse_summary_path("/frontpage/page-2/31.summary.md"), Some((2, 31)));
+        let others = [
+            "/frontpage/3.md",
+            "/frontpage/.summary.md",
+            "/frontpage/page-2.summary.md",
+            "/frontpage/page-1/3.summary.md",
+        ];
+        for path in others {
+            assert_eq!(parse_summary_path(path), None, "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_poll_results() {
+        assert_eq!(poll_results(&[]), None);
+
+        let options = [poll_option("Yes", 3), poll_option("<i>No</i>", 1), poll_option("Maybe", 0)];
+        let table = poll_results(&options).unwrap();
+        assert!(table.contains("| Yes | 3 | 75.0% |"), "{}", table);
+        assert!(table.contains("| *No* | 1 | 25.0% |"), "{}", table);
+        assert!(table.contains("| Maybe | 0 | 0.0% |"), "{}", table);
+
+        // No votes yet, and scores below zero count as none
+        let table = poll_results(&[poll_option("Yes", 0), poll_option("No", -2)]).unwrap();
+        assert!(table.contains("| Yes | 0 | 0.0% |"), "{}", table);
+        assert!(table.contains("| No | -2 | 0.0% |"), "{}", table);
+    }
+
+    #[test]
+    fn test_truncate_chars() {
+        assert_eq!(truncate_chars("hello", 3), "hel");
+        assert_eq!(truncate_chars("hello", 5), "hello");
+        assert_eq!(truncate_chars("hello", 10), "hello");
+        assert_eq!(truncate_chars("", 3), "");
+        assert_eq!(truncate_chars("héllo", 2), "hé");
+        assert_eq!(truncate_chars("日本語テキスト", 3), "日本語");
+        assert_eq!(truncate_chars("a🦀b", 2), "a🦀");
+        assert_eq!(truncate_chars("🦀", 0), "");
+    }
+}
@@ -27,6 +27,25 @@ typedef struct {
     int Count;
 } FileInfoArray;
 
+// Error out-parameter matching Go's FSErrorC
+// Code is 0 on success, otherwise a Linux errno value
+typedef struct {
+    int32_t Code;
+    const char* Message;
+} FSError;
+
+#define FS_ENOENT 2
+#define FS_EROFS  30
+
+// Report an error through the out-parameter. This plugin does not export
+// PluginFreeString, so the host never frees Message and static strings are fine.
+static void set_error(FSError* err, int32_t code, const char* message) {
+    if (err != NULL) {
+        err->Code = code;
+        err->Message = message;
+    }
+}
+
 // Plugin lifecycle functions
 void* PluginNew() {
     HelloFSPlugin* plugin = (HelloFSPlugin*)malloc(sizeof(HelloFSPlugin));
@@ -77,7 +96,8 @@ const char* PluginGetReadme(void* plugin) {
 }
 
 // File system functions
-const char* FSRead(void* plugin, const char* path, int64_t offset, int64_t size, int* out_len) {
+const char* FSRead(void* plugin, const char* path, int64_t offset, int64_t size, int64_t* out_len, FSError* err) {
+    set_error(err, 0, NULL);
     if (strcmp(path, "/hello") == 0) {
         const char* content = "Hello from C dynamic library!\n";
         int content_len = strlen(content);
@@ -102,10 +122,12 @@ const char* FSRead(void* plugin, const char* path, int64_t offset, int64_t size,
     }
 
     *out_len = -1;
-    return "file not found";
+    set_error(err, FS_ENOENT, "file not found");
+    return NULL;
 }
 
-FileInfoC* FSStat(void* plugin, const char* path) {
+FileInfoC* FSStat(void* plugin, const char* path, FSError* err) {
+    set_error(err, 0, NULL);
     FileInfoC* info = (FileInfoC*)malloc(sizeof(FileInfoC));
     time_t now = time(NULL);
 
@@ -133,10 +155,12 @@ FileInfoC* FSStat(void* plugin, const char* path) {
     }
 
     free(info);
+    set_error(err, FS_ENOENT, "file not found");
     return NULL;
 }
 
-FileInfoArray* FSReadDir(void* plugin, const char* path, int* out_count) {
+FileInfoArray* FSReadDir(void* plugin, const char* path, int* out_count, FSError* err) {
+    set_error(err, 0, NULL);
     if (strcmp(path, "/") == 0) {
         FileInfoArray* result = (FileInfoArray*)malloc(sizeof(FileInfoArray));
         result->Count = 1;
@@ -159,6 +183,7 @@ FileInfoArray* FSReadDir(void* plugin, const char* path, int* out_count) {
     }
 
     *out_count = -1;
+    set_error(err, FS_ENOENT, "file not found");
     return NULL;
 }
 
@@ -188,10 +213,11 @@ const char* FSRemoveAll(void* plugin, const char* path) {
 #define WRITE_FLAG_SYNC      (1 << 4)
 
 // FSWrite with offset and flags
-// Returns: positive = bytes written, negative = error
-int64_t FSWrite(void* plugin, const char* path, const char* data, int data_len, int64_t offset, uint32_t flags) {
+// Returns: bytes written, or -1 with err describing the failure
+int64_t FSWrite(void* plugin, const char* path, const char* data, int data_len, int64_t offset, uint32_t flags, FSError* err) {
     (void)plugin; (void)path; (void)data; (void)data_len; (void)offset; (void)flags;
-    // Read-only filesystem - return -1 for error
+    // Read-only filesystem
+    set_error(err, FS_EROFS, "operation not supported: read-only filesystem");
     return -1;
 }
 
@@ -0,0 +1,94 @@
+//! Benchmarks for the native FFI entry points
+//!
+//! Run with `cargo bench`. Data comes from the synthetic `BenchFs`, so the
+//! numbers are the cost of the binding layer itself.
+
+use agfs_core::bench::BenchFs;
+use agfs_ffi::ffi::{self, FSErrorC, FileInfoC, PluginWrapper};
+use agfs_ffi::FileSystem;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::ffi::{c_int, c_void, CString};
+use std::hint::black_box;
+use std::ptr;
+
+const READ_SIZES: [usize; 4] = [4 << 10, 64 << 10, 1 << 20, 16 << 20];
+const DIR_SIZES: [usize; 3] = [10, 1_000, 100_000];
+
+/// Plugin pointer as the host sees it, freed on drop
+struct Plugin(*mut c_void);
+
+impl Plugin {
+    fn new(fs: BenchFs) -> Self {
+        let wrapper = PluginWrapper::<BenchFs>::new();
+        *wrapper.write() = fs;
+        Self(Box::into_raw(Box::new(wrapper)) as *mut c_void)
+    }
+}
+
+impl Drop for Plugin {
+    fn drop(&mut self) {
+        unsafe { drop(Box::from_raw(self.0 as *mut PluginWrapper<BenchFs>)) };
+    }
+}
+
+fn no_error() -> FSErrorC {
+    FSErrorC {
+        code: 0,
+        message: ptr::null(),
+    }
+}
+
+fn bench_read(c: &mut Criterion) {
+    let plugin = Plugin::new(BenchFs::new(*READ_SIZES.last().unwrap(), 0));
+    let path = CString::new("/file").unwrap();
+    let mut group = c.benchmark_group("fs_read");
+    for size in READ_SIZES {
+        group.throughput(Throughput::Bytes(size as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(size), &size, |b, &size| {
+            b.iter(|| {
+                let (mut len, mut err) = (0i64, no_error());
+                let data = ffi::fs_read::<BenchFs>(plugin.0, path.as_ptr(), 0, size as i64, &mut len, &mut err);
+                assert_eq!(len, size as i64);
+                unsafe { ffi::free_byte_buffer(black_box(data)) };
+            });
+        });
+    }
+    group.finish();
+}
+
+fn bench_readdir(c: &mut Criterion) {
+    let path = CString::new("/dir").unwrap();
+    let mut group = c.benchmark_group("fs_readdir");
+    for entries in DIR_SIZES {
+        let plugin = Plugin::new(BenchFs::new(0, entries));
+        group.throughput(Throughput::Elements(entries as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(entries), &entries, |b, &entries| {
+            b.iter(|| {
+                let (mut count, mut err): (c_int, _) = (0, no_error());
+                let array = ffi::fs_readdir::<BenchFs>(plugin.0, path.as_ptr(), &mut count, &mut err);
+                assert_eq!(count as usize, entries);
+                unsafe { ffi::free_file_info_array(black_box(array)) };
+            });
+        });
+    }
+    group.finish();
+}
+
+/// `FileInfoC` structs (native ABI) against the JSON the WASM ABI uses
+fn bench_file_info_encoding(c: &mut Criterion) {
+    let mut group = c.benchmark_group("file_info_encoding");
+    for entries in DIR_SIZES {
+        let infos = BenchFs::new(0, entries).readdir("/dir").unwrap();
+        group.throughput(Throughput::Elements(entries as u64));
+        group.bench_with_input(BenchmarkId::new("binary", entries), &infos, |b, infos| {
+            b.iter(|| black_box(infos.iter().map(FileInfoC::from).collect::<Vec<_>>()));
+        });
+        group.bench_with_input(BenchmarkId::new("json", entries), &infos, |b, infos| {
+            b.iter(|| black_box(serde_json::to_vec(infos).unwrap()));
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_read, bench_readdir, bench_file_info_encoding);
+criterion_main!(benches);
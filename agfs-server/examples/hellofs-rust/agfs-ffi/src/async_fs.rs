@@ -0,0 +1,247 @@
+//! Submit/poll support for long-running reads and writes
+//!
+//! A slow operation (e.g. a network fetch) executed inside `FileSystem::read`
+//! holds the plugin lock for its whole duration and blocks every writer. An
+//! [`AsyncFS`] plugin instead hands back a [`Job`]: the lock is held only while
+//! the job is prepared, and the job itself runs on a worker thread. The host
+//! submits the operation with `FSSubmitRead`/`FSSubmitWrite`, collects the
+//! result with `FSPoll` and abandons it with `FSCancel`.
+
+use crate::error::{Error, Result};
+use agfs_core::cancel::CancellationToken;
+use agfs_core::control::{is_control_path, ControlFs};
+use crate::filesystem::FileSystem;
+use crate::types::WriteFlag;
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+/// Work that runs on a worker thread without holding the plugin lock
+///
+/// The token is cancelled when the host abandons the operation (e.g. the user
+/// interrupts `cat`); long jobs should call `check()` on it between chunks.
+pub type Job<T> = Box<dyn FnOnce(&CancellationToken) -> Result<T> + Send + 'static>;
+
+/// Optional trait for filesystems with long-running reads or writes
+///
+/// Export with `export_plugin!(MyFS, async)` (or
+/// `export_handle_plugin!(MyFS, async)`). Both methods are called under the
+/// shared lock and should only capture what the job needs, typically `Arc`
+/// clones of the plugin state. Returning `Ok(None)` runs the operation
+/// synchronously through `FileSystem::read`/`FileSystem::write` instead.
+pub trait AsyncFS: FileSystem {
+    /// Prepare a read to run on a worker thread
+    fn read_job(&self, _path: &str, _offset: i64, _size: i64) -> Result<Option<Job<Vec<u8>>>> {
+        Ok(None)
+    }
+
+    /// Prepare a write to run on a worker thread
+    fn write_job(
+        &self,
+        _path: &str,
+        _data: Vec<u8>,
+        _offset: i64,
+        _flags: WriteFlag,
+    ) -> Result<Option<Job<i64>>> {
+        Ok(None)
+    }
+}
+
+/// Control files are answered synchronously; other paths get the plugin's jobs
+impl<F: AsyncFS> AsyncFS for ControlFs<F> {
+    fn read_job(&self, path: &str, offset: i64, size: i64) -> Result<Option<Job<Vec<u8>>>> {
+        match is_control_path(path) {
+            true => Ok(None),
+            false => self.inner().read_job(path, offset, size),
+        }
+    }
+
+    fn write_job(&self, path: &str, data: Vec<u8>, offset: i64, flags: WriteFlag) -> Result<Option<Job<i64>>> {
+        match is_control_path(path) {
+            true => Ok(None),
+            false => self.inner().write_job(path, data, offset, flags),
+        }
+    }
+}
+
+/// Result of a finished operation
+#[derive(Debug)]
+pub enum Completion {
+    /// Data returned by a read
+    Read(Vec<u8>),
+    /// Bytes written by a write
+    Write(i64),
+}
+
+/// State of an operation as seen by `poll`
+#[derive(Debug)]
+pub enum PollResult {
+    /// No operation with this ID (never submitted or already collected)
+    Unknown,
+    /// Still running after the timeout elapsed
+    Pending,
+    /// Finished; the operation has been removed from the table
+    Done(Result<Completion>),
+}
+
+type Slot = Arc<(Mutex<Option<Result<Completion>>>, Condvar)>;
+
+/// Table of submitted operations, keyed by the IDs handed to the host
+#[derive(Default)]
+pub struct PendingOps {
+    next_id: AtomicI64,
+    ops: Mutex<HashMap<i64, (Slot, CancellationToken)>>,
+}
+
+impl PendingOps {
+    fn insert(&self, slot: Slot, cancel: CancellationToken) -> i64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        self.ops
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(id, (slot, cancel));
+        id
+    }
+
+    /// Record an operation that already finished and return its ID
+    pub fn completed(&self, result: Result<Completion>) -> i64 {
+        let slot = Arc::new((Mutex::new(Some(result)), Condvar::new()));
+        self.insert(slot, CancellationToken::new())
+    }
+
+    /// Run `job` on a new worker thread and return its ID
+    ///
+    /// A panicking job completes with an error instead of leaving the poller
+    /// waiting forever.
+    pub fn spawn<F>(&self, job: F) -> i64
+    where
+        F: FnOnce(&CancellationToken) -> Result<Completion> + Send + 'static,
+    {
+        let slot: Slot = Arc::new((Mutex::new(None), Condvar::new()));
+        let cancel = CancellationToken::new();
+        let id = self.insert(slot.clone(), cancel.clone());
+
+        thread::spawn(move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(|| job(&cancel)))
+                .unwrap_or_else(|_| Err(Error::Other("operation panicked".to_string())));
+            let (done, cond) = &*slot;
+            *done.lock().unwrap_or_else(PoisonError::into_inner) = Some(result);
+            cond.notify_all();
+        });
+
+        id
+    }
+
+    /// Cancel operation `id` and forget it, returning false if it is unknown
+    ///
+    /// The job sees its token cancelled and whatever it still returns is
+    /// discarded, so the host never polls a cancelled operation.
+    pub fn cancel(&self, id: i64) -> bool {
+        let op = self.ops.lock().unwrap_or_else(PoisonError::into_inner).remove(&id);
+        match op {
+            Some((_, cancel)) => {
+                cancel.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Wait up to `timeout` (`None` waits until done) for operation `id`
+    pub fn poll(&self, id: i64, timeout: Option<Duration>) -> PollResult {
+        let slot = match self.ops.lock().unwrap_or_else(PoisonError::into_inner).get(&id) {
+            Some((slot, _)) => slot.clone(),
+            None => return PollResult::Unknown,
+        };
+
+        let (done, cond) = &*slot;
+        let mut result = done.lock().unwrap_or_else(PoisonError::into_inner);
+        while result.is_none() {
+            result = match timeout {
+                None => cond.wait(result).unwrap_or_else(PoisonError::into_inner),
+                Some(timeout) => {
+                    let (guard, wait) = cond
+                        .wait_timeout(result, timeout)
+                        .unwrap_or_else(PoisonError::into_inner);
+                    if wait.timed_out() && guard.is_none() {
+                        return PollResult::Pending;
+                    }
+                    guard
+                }
+            };
+        }
+
+        // Only one poller may collect the result
+        match result.take() {
+            Some(result) => {
+                self.ops
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .remove(&id);
+                PollResult::Done(result)
+            }
+            None => PollResult::Unknown,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_spawned_job_completes() {
+        let ops = PendingOps::default();
+        let (tx, rx) = mpsc::channel::<()>();
+        let id = ops.spawn(move |_| {
+            rx.recv().unwrap();
+            Ok(Completion::Write(3))
+        });
+
+        assert!(matches!(ops.poll(id, Some(Duration::from_millis(10))), PollResult::Pending));
+        tx.send(()).unwrap();
+        assert!(matches!(ops.poll(id, None), PollResult::Done(Ok(Completion::Write(3)))));
+        assert!(matches!(ops.poll(id, None), PollResult::Unknown));
+    }
+
+    #[test]
+    fn test_panicking_job_reports_error() {
+        let ops = PendingOps::default();
+        let id = ops.spawn(|_| panic!("boom"));
+        assert!(matches!(ops.poll(id, None), PollResult::Done(Err(Error::Other(_)))));
+    }
+
+    #[test]
+    fn test_cancelled_job_sees_token() {
+        let ops = PendingOps::default();
+        let (started_tx, started) = mpsc::channel::<()>();
+        let (stopped_tx, stopped) = mpsc::channel::<bool>();
+        let id = ops.spawn(move |cancel| {
+            started_tx.send(()).unwrap();
+            while cancel.check().is_ok() {
+                thread::sleep(Duration::from_millis(1));
+            }
+            stopped_tx.send(true).unwrap();
+            Err(Error::Cancelled)
+        });
+
+        started.recv().unwrap();
+        assert!(ops.cancel(id));
+        assert!(stopped.recv_timeout(Duration::from_secs(5)).unwrap());
+        assert!(matches!(ops.poll(id, Some(Duration::ZERO)), PollResult::Unknown));
+        assert!(!ops.cancel(id));
+    }
+
+    #[test]
+    fn test_completed_ids_are_unique() {
+        let ops = PendingOps::default();
+        let a = ops.completed(Ok(Completion::Read(vec![1])));
+        let b = ops.completed(Err(Error::NotFound));
+        assert_ne!(a, b);
+        assert!(matches!(ops.poll(b, Some(Duration::ZERO)), PollResult::Done(Err(Error::NotFound))));
+    }
+}
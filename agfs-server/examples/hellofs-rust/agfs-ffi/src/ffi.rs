@@ -1,13 +1,108 @@
 //! FFI boundary layer
 //!
 //! This module handles all C interop safely. All unsafe code is contained here.
+//!
+//! # Ownership
+//!
+//! Every pointer handed to the host is allocated by Rust and must be returned
+//! to the matching free export once the host has copied what it needs:
+//!
+//! | Returned by                                  | Release with          |
+//! |----------------------------------------------|-----------------------|
+//! | error strings (any `*const c_char` result)   | `PluginFreeString`    |
+//! | `FSErrorC::message` out-parameters           | `PluginFreeString`    |
+//! | `PluginGetConfigParams`                      | `PluginFreeString`    |
+//! | `FSReadlink`                                 | `PluginFreeString`    |
+//! | `FSRead` data (when `out_len >= 0`)          | `PluginFreeBuffer`    |
+//! | `FSReadIfChanged` data (when `out_len >= 0`) | `PluginFreeBuffer`    |
+//! | `FSPoll` read data (when `out_len >= 0`)     | `PluginFreeBuffer`    |
+//! | `FSGetXattr`, `FSListXattr`                  | `PluginFreeBuffer`    |
+//! | `FSStat`                                     | `FSFreeFileInfo`      |
+//! | `FSReadDir`, `FSReadDirPage`                 | `FSFreeFileInfoArray` |
+//! | `FSReadDirFiltered`                          | `FSFreeFileInfoArray` |
+//!
+//! `HandleStat` hands out a `FileInfoC` through its out-parameter, which is
+//! released with `FSFreeFileInfo` as well.
+//!
+//! `PluginName` and `PluginGetReadme` point into the plugin instance itself;
+//! they stay valid until `PluginFree` and must not be freed separately.
+
+// The `plugin_*`/`fs_*`/`handle_*` helpers are the bodies of the generated C
+// exports; the pointers they take come straight from the host, which owns the
+// contract, so they are deliberately not marked `unsafe` themselves.
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
 
-use crate::filesystem::FileSystem;
-use crate::types::{FileInfo, WriteFlag};
+use crate::async_fs::{AsyncFS, Completion, PendingOps, PollResult};
+use crate::error::Error;
+use crate::filesystem::{batch_results_json, FileSystem, HandleFS};
+use crate::types::{Advice, Config, FileInfo, FsOp, OpenFlag, WriteFlag};
+use agfs_core::redact::{add_config_secrets, redact};
 use std::ffi::{CStr, CString};
 use std::os::raw::{c_char, c_int, c_void};
 use std::ptr;
-use std::sync::Mutex;
+use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::Duration;
+
+/// Native C ABI version reported by `PluginABIVersion`
+///
+/// Bumped when an existing export changes signature or ownership rules; new
+/// optional exports are announced through `PluginFeatures` instead.
+///
+/// Version 2 appended `etag` to `FileInfoC`.
+pub const ABI_VERSION: u32 = 2;
+
+/// `PluginFeatures` bit: `PluginFreeString`/`PluginFreeBuffer`/`FSFreeFileInfo*`
+pub const FEATURE_FREE: u64 = 1 << 0;
+/// `PluginFeatures` bit: `PluginGetConfigParams`
+pub const FEATURE_CONFIG_PARAMS: u64 = 1 << 1;
+/// `PluginFeatures` bit: the `Handle*` entry points
+pub const FEATURE_HANDLES: u64 = 1 << 2;
+/// `PluginFeatures` bit: `PluginSetLogCallback`
+pub const FEATURE_LOG: u64 = 1 << 3;
+/// `PluginFeatures` bit: `FSSubmitRead`/`FSSubmitWrite`/`FSPoll`
+pub const FEATURE_ASYNC: u64 = 1 << 4;
+/// `PluginFeatures` bit: `FSReadDirPage`
+pub const FEATURE_READDIR_PAGE: u64 = 1 << 5;
+/// `PluginFeatures` bit: `FSGetXattr`/`FSSetXattr`/`FSListXattr`
+pub const FEATURE_XATTR: u64 = 1 << 6;
+/// `PluginFeatures` bit: `FSSymlink`/`FSReadlink`
+pub const FEATURE_SYMLINK: u64 = 1 << 7;
+/// `PluginFeatures` bit: `FSAdvise`
+pub const FEATURE_ADVISE: u64 = 1 << 8;
+/// `PluginFeatures` bit: `FSReadIfChanged`
+pub const FEATURE_READ_IF_CHANGED: u64 = 1 << 9;
+/// `PluginFeatures` bit: `FSCancel`
+pub const FEATURE_CANCEL: u64 = 1 << 10;
+/// `PluginFeatures` bit: `FSWriteIf`
+pub const FEATURE_WRITE_IF: u64 = 1 << 11;
+/// `PluginFeatures` bit: `FSReadDirFiltered`
+pub const FEATURE_READDIR_FILTERED: u64 = 1 << 12;
+/// `PluginFeatures` bit: `FSReadMany`
+pub const FEATURE_READ_MANY: u64 = 1 << 13;
+/// `PluginFeatures` bit: `FSBatch`/`FSAtomicBatch`
+pub const FEATURE_BATCH: u64 = 1 << 14;
+/// `PluginFeatures` bit: `FSBeginSession`/`FSCommitSession`/`FSAbortSession`
+pub const FEATURE_SESSIONS: u64 = 1 << 15;
+/// `PluginFeatures` bit: `FSMaintain`
+pub const FEATURE_MAINTAIN: u64 = 1 << 16;
+/// `PluginFeatures` bit: `FSWarmup`
+pub const FEATURE_WARMUP: u64 = 1 << 17;
+/// `PluginFeatures` bit: `FSExportState`/`FSImportState`
+pub const FEATURE_STATE: u64 = 1 << 18;
+
+/// `FSReadIfChanged` length: the content still matches the given ETag
+pub const READ_NOT_MODIFIED: i64 = -2;
+
+/// `FSPoll` status: the operation is still running
+pub const POLL_PENDING: c_int = 0;
+/// `FSPoll` status: the operation finished and its result was written out
+pub const POLL_DONE: c_int = 1;
+/// `FSPoll` status: no such operation
+pub const POLL_UNKNOWN: c_int = -1;
+
+/// Features every `export_plugin!` plugin provides; the others are reported
+/// only when the plugin lists them in the macro
+pub const BASE_FEATURES: u64 = FEATURE_FREE | FEATURE_CONFIG_PARAMS | FEATURE_LOG;
 
 /// C-compatible FileInfo structure
 #[repr(C)]
@@ -20,6 +115,7 @@ pub struct FileInfoC {
     meta_name: *const c_char,
     meta_type: *const c_char,
     meta_content: *const c_char,
+    etag: *const c_char,
 }
 
 /// C-compatible array of FileInfo structures
@@ -30,25 +126,38 @@ pub struct FileInfoArray {
 }
 
 /// Convert FileInfo to C representation
+///
+/// Files without metadata report empty name/type and `{}` content; a missing
+/// (or unrepresentable) ETag is null.
 impl From<&FileInfo> for FileInfoC {
     fn from(info: &FileInfo) -> Self {
+        let (meta_name, meta_type, meta_content) = match &info.meta {
+            Some(meta) => (meta.name.as_str(), meta.type_.as_str(), meta.content.to_string()),
+            None => ("", "", "{}".to_string()),
+        };
+
         FileInfoC {
             name: CString::new(info.name.as_str())
                 .expect("name contains null byte")
                 .into_raw(),
             size: info.size,
-            mode: info.mode,
+            mode: info.type_mode(),
             mod_time: info.mod_time,
-            is_dir: if info.is_dir { 1 } else { 0 },
-            meta_name: CString::new(info.metadata.name.as_str())
+            is_dir: if info.is_dir() { 1 } else { 0 },
+            meta_name: CString::new(meta_name)
                 .expect("meta_name contains null byte")
                 .into_raw(),
-            meta_type: CString::new(info.metadata.file_type.as_str())
+            meta_type: CString::new(meta_type)
                 .expect("meta_type contains null byte")
                 .into_raw(),
-            meta_content: CString::new(info.metadata.content.as_str())
+            meta_content: CString::new(meta_content)
                 .expect("meta_content contains null byte")
                 .into_raw(),
+            etag: info
+                .etag
+                .as_deref()
+                .and_then(|etag| CString::new(etag).ok())
+                .map_or(ptr::null(), |etag| etag.into_raw()),
         }
     }
 }
@@ -69,31 +178,115 @@ impl Drop for FileInfoC {
             if !self.meta_content.is_null() {
                 let _ = CString::from_raw(self.meta_content as *mut c_char);
             }
+            if !self.etag.is_null() {
+                let _ = CString::from_raw(self.etag as *mut c_char);
+            }
+        }
+    }
+}
+
+/// C-compatible error report written by the `FS*` exports that return data
+/// (`FSRead`, `FSStat`, `FSReadDir`, `FSReadlink`, `FSGetXattr`, ...) and `FSWrite`
+///
+/// `code` is 0 on success, otherwise a Linux errno value (see
+/// [`Error::code`]) and `message` holds a description.
+#[repr(C)]
+pub struct FSErrorC {
+    pub code: i32,
+    pub message: *const c_char,
+}
+
+/// One file's result written by `FSReadMany`
+///
+/// On success `data` holds `len` bytes (free with `PluginFreeBuffer`) and
+/// `err.code` is 0; on failure `data` is null, `len` is -1 and `err` says why.
+#[repr(C)]
+pub struct ReadResultC {
+    pub data: *const c_char,
+    pub len: i64,
+    pub err: FSErrorC,
+}
+
+/// Report `err` through the host's error out-parameter (if it supplied one)
+unsafe fn set_error(out_err: *mut FSErrorC, err: &Error) {
+    if !out_err.is_null() {
+        *out_err = FSErrorC {
+            code: err.code(),
+            message: error_to_c_string(&err.to_string()),
+        };
+    }
+}
+
+/// Mark the host's error out-parameter as success
+unsafe fn clear_error(out_err: *mut FSErrorC) {
+    if !out_err.is_null() {
+        *out_err = FSErrorC {
+            code: 0,
+            message: ptr::null(),
+        };
+    }
+}
+
+/// Parse a path argument, reporting `InvalidPath` on failure
+unsafe fn path_arg<'a>(path: *const c_char, out_err: *mut FSErrorC) -> Option<&'a str> {
+    match c_str_to_str(path) {
+        Ok(s) => Some(s),
+        Err(_) => {
+            set_error(out_err, &Error::InvalidPath);
+            None
         }
     }
 }
 
 /// Wrapper to make FileSystem thread-safe
+///
+/// Operations that take `&self` on the trait (reads, stat, readdir,
+/// positional handle I/O) share a read lock and run concurrently; anything
+/// taking `&mut self` holds the write lock exclusively. Submitted
+/// [`AsyncFS`] jobs run outside the lock and are tracked in `ops`.
 pub struct PluginWrapper<T: FileSystem> {
-    pub fs: Mutex<T>,
+    pub fs: RwLock<T>,
     pub name: CString,
     pub readme: CString,
+    pub ops: PendingOps,
 }
 
-impl<T: FileSystem> PluginWrapper<T> {
+impl<T: FileSystem + Default> PluginWrapper<T> {
     pub fn new() -> Self {
         let fs = T::default();
         let name = CString::new(fs.name()).expect("plugin name contains null byte");
-        let readme = CString::new(fs.readme()).expect("readme contains null byte");
+        let readme = CString::new(redact(fs.readme()).into_owned()).expect("readme contains null byte");
 
         Self {
-            fs: Mutex::new(fs),
+            fs: RwLock::new(fs),
             name,
             readme,
+            ops: PendingOps::default(),
         }
     }
 }
 
+impl<T: FileSystem> PluginWrapper<T> {
+    /// Shared access for `&self` operations
+    ///
+    /// A panic in another operation must not wedge the plugin (or unwind
+    /// across the C boundary), so a poisoned lock is recovered.
+    pub fn read(&self) -> RwLockReadGuard<'_, T> {
+        self.fs.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Exclusive access for `&mut self` operations
+    pub fn write(&self) -> RwLockWriteGuard<'_, T> {
+        self.fs.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T: FileSystem + Default> Default for PluginWrapper<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Helper to safely convert C string to Rust str
 unsafe fn c_str_to_str<'a>(ptr: *const c_char) -> Result<&'a str, &'static str> {
     if ptr.is_null() {
@@ -102,13 +295,81 @@ unsafe fn c_str_to_str<'a>(ptr: *const c_char) -> Result<&'a str, &'static str>
     CStr::from_ptr(ptr).to_str().map_err(|_| "invalid UTF-8")
 }
 
-/// Helper to create error C string
+/// Helper to create error C string, with secrets scrubbed
 fn error_to_c_string(msg: &str) -> *const c_char {
-    CString::new(msg)
+    CString::new(redact(msg).into_owned())
         .expect("error message contains null byte")
         .into_raw()
 }
 
+/// Size of the length header in front of byte buffers handed to the host
+const LEN_PREFIX: usize = std::mem::size_of::<u64>();
+
+/// Hand `data` to the host as a length-prefixed byte buffer
+///
+/// The allocation is laid out as `[len: u64 LE][data]` and the returned pointer
+/// addresses `data`, so the host can copy `len` bytes straight from it. Unlike a
+/// C string the contents may hold NUL bytes or arbitrary binary data.
+pub fn into_byte_buffer(data: Vec<u8>) -> *const c_char {
+    let mut buf = Vec::with_capacity(LEN_PREFIX + data.len());
+    buf.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    buf.extend_from_slice(&data);
+    let ptr = Box::into_raw(buf.into_boxed_slice()) as *mut u8;
+    unsafe { ptr.add(LEN_PREFIX) as *const c_char }
+}
+
+/// Length stored in the header of a buffer from [`into_byte_buffer`]
+///
+/// # Safety
+///
+/// `ptr` must have been returned by [`into_byte_buffer`] and not yet freed.
+pub unsafe fn byte_buffer_len(ptr: *const c_char) -> usize {
+    let header = (ptr as *const u8).sub(LEN_PREFIX);
+    let mut len = [0u8; LEN_PREFIX];
+    std::ptr::copy_nonoverlapping(header, len.as_mut_ptr(), LEN_PREFIX);
+    u64::from_le_bytes(len) as usize
+}
+
+/// Release a buffer from [`into_byte_buffer`]
+///
+/// # Safety
+///
+/// `ptr` must have been returned by [`into_byte_buffer`] and not yet freed.
+pub unsafe fn free_byte_buffer(ptr: *const c_char) {
+    if ptr.is_null() {
+        return;
+    }
+    let len = byte_buffer_len(ptr);
+    let start = (ptr as *mut u8).sub(LEN_PREFIX);
+    let slice = std::ptr::slice_from_raw_parts_mut(start, LEN_PREFIX + len);
+    drop(Box::from_raw(slice));
+}
+
+/// Store `value` through an out-parameter if the host supplied one
+unsafe fn set_out<V>(out: *mut V, value: V) {
+    if !out.is_null() {
+        *out = value;
+    }
+}
+
+/// View a host buffer as a mutable slice (a zero length allows a null pointer)
+unsafe fn host_buf_mut<'a>(buf: *mut u8, len: i64) -> Result<&'a mut [u8], &'static str> {
+    match len {
+        0 => Ok(&mut []),
+        n if n < 0 || buf.is_null() => Err("invalid buffer"),
+        n => Ok(std::slice::from_raw_parts_mut(buf, n as usize)),
+    }
+}
+
+/// View a host buffer as a slice (a zero length allows a null pointer)
+unsafe fn host_buf<'a>(data: *const u8, len: i64) -> Result<&'a [u8], &'static str> {
+    match len {
+        0 => Ok(&[]),
+        n if n < 0 || data.is_null() => Err("invalid buffer"),
+        n => Ok(std::slice::from_raw_parts(data, n as usize)),
+    }
+}
+
 /// Success indicator (NULL in C API)
 fn success() -> *const c_char {
     ptr::null()
@@ -130,17 +391,38 @@ pub fn plugin_validate<T: FileSystem>(
             Err(e) => return error_to_c_string(e),
         }
     };
+    let config = match Config::from_json(config) {
+        Ok(c) => c,
+        Err(e) => return error_to_c_string(&e.to_string()),
+    };
 
     unsafe {
         let wrapper = &*(plugin as *const PluginWrapper<T>);
-        let fs = wrapper.fs.lock().unwrap();
-        match fs.validate(config) {
+        let fs = wrapper.read();
+        add_config_secrets(&config, &fs.config_params());
+        match fs.validate(&config) {
             Ok(_) => success(),
             Err(e) => error_to_c_string(&e.to_string()),
         }
     }
 }
 
+/// Serialize the plugin's config parameters as a JSON array
+///
+/// The returned string must be released with `PluginFreeString`.
+pub fn plugin_get_config_params<T: FileSystem>(plugin: *mut c_void) -> *const c_char {
+    if plugin.is_null() {
+        return ptr::null();
+    }
+
+    unsafe {
+        let wrapper = &*(plugin as *const PluginWrapper<T>);
+        let fs = wrapper.read();
+        let json = serde_json::to_string(&fs.config_params()).unwrap_or_else(|_| "[]".to_string());
+        CString::new(json).expect("config params contain null byte").into_raw()
+    }
+}
+
 pub fn plugin_initialize<T: FileSystem>(
     plugin: *mut c_void,
     config_json: *const c_char,
@@ -155,11 +437,16 @@ pub fn plugin_initialize<T: FileSystem>(
             Err(e) => return error_to_c_string(e),
         }
     };
+    let config = match Config::from_json(config) {
+        Ok(c) => c,
+        Err(e) => return error_to_c_string(&e.to_string()),
+    };
 
     unsafe {
         let wrapper = &*(plugin as *const PluginWrapper<T>);
-        let mut fs = wrapper.fs.lock().unwrap();
-        match fs.initialize(config) {
+        let mut fs = wrapper.write();
+        add_config_secrets(&config, &fs.config_params());
+        match fs.initialize(&config) {
             Ok(_) => success(),
             Err(e) => error_to_c_string(&e.to_string()),
         }
@@ -173,7 +460,7 @@ pub fn plugin_shutdown<T: FileSystem>(plugin: *mut c_void) -> *const c_char {
 
     unsafe {
         let wrapper = &*(plugin as *const PluginWrapper<T>);
-        let mut fs = wrapper.fs.lock().unwrap();
+        let mut fs = wrapper.write();
         match fs.shutdown() {
             Ok(_) => success(),
             Err(e) => error_to_c_string(&e.to_string()),
@@ -186,61 +473,177 @@ pub fn fs_read<T: FileSystem>(
     path: *const c_char,
     offset: i64,
     size: i64,
-    out_len: *mut c_int,
+    out_len: *mut i64,
+    out_err: *mut FSErrorC,
 ) -> *const c_char {
-    if plugin.is_null() {
-        unsafe {
-            *out_len = -1;
+    unsafe {
+        set_out(out_len, -1);
+        if plugin.is_null() {
+            set_error(out_err, &Error::Other("plugin is null".to_string()));
+            return ptr::null();
         }
-        return error_to_c_string("plugin is null");
-    }
+        let Some(path_str) = path_arg(path, out_err) else {
+            return ptr::null();
+        };
 
-    let path_str = unsafe {
-        match c_str_to_str(path) {
-            Ok(s) => s,
+        let wrapper = &*(plugin as *const PluginWrapper<T>);
+        let fs = wrapper.read();
+        match fs.read(path_str, offset, size) {
+            Ok(content) => {
+                clear_error(out_err);
+                set_out(out_len, content.len() as i64);
+                into_byte_buffer(content)
+            }
             Err(e) => {
-                *out_len = -1;
-                return error_to_c_string(e);
+                set_error(out_err, &e);
+                ptr::null()
             }
         }
-    };
+    }
+}
 
+/// Read a whole file unless it still matches `etag`
+///
+/// Sets `out_len` to `READ_NOT_MODIFIED` (and returns null) when unchanged.
+pub fn fs_read_if_changed<T: FileSystem>(
+    plugin: *mut c_void,
+    path: *const c_char,
+    etag: *const c_char,
+    out_len: *mut i64,
+    out_err: *mut FSErrorC,
+) -> *const c_char {
     unsafe {
+        set_out(out_len, -1);
+        if plugin.is_null() {
+            set_error(out_err, &Error::Other("plugin is null".to_string()));
+            return ptr::null();
+        }
+        let Some(path_str) = path_arg(path, out_err) else {
+            return ptr::null();
+        };
+        let Ok(etag_str) = c_str_to_str(etag) else {
+            set_error(out_err, &Error::InvalidInput("invalid etag".to_string()));
+            return ptr::null();
+        };
+
         let wrapper = &*(plugin as *const PluginWrapper<T>);
-        let fs = wrapper.fs.lock().unwrap();
-        match fs.read(path_str, offset, size) {
-            Ok(content) => {
-                *out_len = content.len() as c_int;
-                CString::new(content)
-                    .expect("content contains null byte")
-                    .into_raw()
+        let fs = wrapper.read();
+        match fs.read_if_changed(path_str, etag_str) {
+            Ok(Some(content)) => {
+                clear_error(out_err);
+                set_out(out_len, content.len() as i64);
+                into_byte_buffer(content)
+            }
+            Ok(None) => {
+                clear_error(out_err);
+                set_out(out_len, READ_NOT_MODIFIED);
+                ptr::null()
             }
             Err(e) => {
-                *out_len = -1;
-                error_to_c_string(&e.to_string())
+                set_error(out_err, &e);
+                ptr::null()
             }
         }
     }
 }
 
-pub fn fs_stat<T: FileSystem>(plugin: *mut c_void, path: *const c_char) -> *mut FileInfoC {
-    if plugin.is_null() {
-        return ptr::null_mut();
-    }
+/// Read several whole files in one call
+///
+/// `paths` holds `paths_len` bytes of NUL-terminated paths back to back. The
+/// host passes room for `max_results` entries in `out_results`, and one is
+/// written per path in order. Returns the number of paths, or -1 with
+/// `out_err` set when the batch itself is invalid.
+pub fn fs_read_many<T: FileSystem>(
+    plugin: *mut c_void,
+    paths: *const c_char,
+    paths_len: i64,
+    out_results: *mut ReadResultC,
+    max_results: c_int,
+    out_err: *mut FSErrorC,
+) -> c_int {
+    unsafe {
+        if plugin.is_null() {
+            set_error(out_err, &Error::Other("plugin is null".to_string()));
+            return -1;
+        }
+        let buf = match host_buf(paths as *const u8, paths_len) {
+            Ok(b) => b,
+            Err(e) => {
+                set_error(out_err, &Error::Other(e.to_string()));
+                return -1;
+            }
+        };
+        let path_strs: Vec<&str> = match buf.strip_suffix(&[0]) {
+            None if buf.is_empty() => Vec::new(),
+            None => {
+                set_error(out_err, &Error::InvalidInput("paths must be NUL-terminated".to_string()));
+                return -1;
+            }
+            Some(body) => match body.split(|&b| b == 0).map(std::str::from_utf8).collect() {
+                Ok(strs) => strs,
+                Err(_) => {
+                    set_error(out_err, &Error::InvalidPath);
+                    return -1;
+                }
+            },
+        };
+        if path_strs.len() > max_results.max(0) as usize || (!path_strs.is_empty() && out_results.is_null()) {
+            set_error(out_err, &Error::InvalidInput("too many paths for result buffer".to_string()));
+            return -1;
+        }
 
-    let path_str = unsafe {
-        match c_str_to_str(path) {
-            Ok(s) => s,
-            Err(_) => return ptr::null_mut(),
+        let wrapper = &*(plugin as *const PluginWrapper<T>);
+        let fs = wrapper.read();
+        let mut results = fs.read_many(&path_strs);
+        results.resize_with(path_strs.len(), || Err(Error::Other("missing read result".to_string())));
+        for (i, result) in results.into_iter().enumerate() {
+            let entry = match result {
+                Ok(content) => ReadResultC {
+                    len: content.len() as i64,
+                    data: into_byte_buffer(content),
+                    err: FSErrorC { code: 0, message: ptr::null() },
+                },
+                Err(e) => ReadResultC {
+                    data: ptr::null(),
+                    len: -1,
+                    err: FSErrorC {
+                        code: e.code(),
+                        message: error_to_c_string(&e.to_string()),
+                    },
+                },
+            };
+            out_results.add(i).write(entry);
         }
-    };
+        clear_error(out_err);
+        path_strs.len() as c_int
+    }
+}
 
+pub fn fs_stat<T: FileSystem>(
+    plugin: *mut c_void,
+    path: *const c_char,
+    out_err: *mut FSErrorC,
+) -> *mut FileInfoC {
     unsafe {
+        if plugin.is_null() {
+            set_error(out_err, &Error::Other("plugin is null".to_string()));
+            return ptr::null_mut();
+        }
+        let Some(path_str) = path_arg(path, out_err) else {
+            return ptr::null_mut();
+        };
+
         let wrapper = &*(plugin as *const PluginWrapper<T>);
-        let fs = wrapper.fs.lock().unwrap();
+        let fs = wrapper.read();
         match fs.stat(path_str) {
-            Ok(info) => Box::into_raw(Box::new(FileInfoC::from(&info))),
-            Err(_) => ptr::null_mut(),
+            Ok(info) => {
+                clear_error(out_err);
+                Box::into_raw(Box::new(FileInfoC::from(&info)))
+            }
+            Err(e) => {
+                set_error(out_err, &e);
+                ptr::null_mut()
+            }
         }
     }
 }
@@ -249,48 +652,115 @@ pub fn fs_readdir<T: FileSystem>(
     plugin: *mut c_void,
     path: *const c_char,
     out_count: *mut c_int,
+    out_err: *mut FSErrorC,
 ) -> *mut FileInfoArray {
-    if plugin.is_null() {
-        unsafe {
-            *out_count = -1;
+    unsafe {
+        set_out(out_count, -1);
+        if plugin.is_null() {
+            set_error(out_err, &Error::Other("plugin is null".to_string()));
+            return ptr::null_mut();
         }
-        return ptr::null_mut();
+        let Some(path_str) = path_arg(path, out_err) else {
+            return ptr::null_mut();
+        };
+
+        let wrapper = &*(plugin as *const PluginWrapper<T>);
+        let fs = wrapper.read();
+        file_info_array(fs.readdir(path_str), out_count, out_err)
     }
+}
 
-    let path_str = unsafe {
-        match c_str_to_str(path) {
-            Ok(s) => s,
-            Err(_) => {
-                *out_count = -1;
-                return ptr::null_mut();
-            }
+/// List one page of a directory: up to `limit` entries starting at `offset`
+///
+/// A page shorter than `limit` is the last one.
+pub fn fs_readdir_page<T: FileSystem>(
+    plugin: *mut c_void,
+    path: *const c_char,
+    offset: i64,
+    limit: i64,
+    out_count: *mut c_int,
+    out_err: *mut FSErrorC,
+) -> *mut FileInfoArray {
+    unsafe {
+        set_out(out_count, -1);
+        if plugin.is_null() {
+            set_error(out_err, &Error::Other("plugin is null".to_string()));
+            return ptr::null_mut();
         }
-    };
+        let Some(path_str) = path_arg(path, out_err) else {
+            return ptr::null_mut();
+        };
+        let (Ok(offset), Ok(limit)) = (usize::try_from(offset), usize::try_from(limit)) else {
+            set_error(out_err, &Error::InvalidInput("negative offset or limit".to_string()));
+            return ptr::null_mut();
+        };
+
+        let wrapper = &*(plugin as *const PluginWrapper<T>);
+        let fs = wrapper.read();
+        file_info_array(fs.readdir_page(path_str, offset, limit), out_count, out_err)
+    }
+}
 
+/// List the entries of `path` matching `glob`, at most `limit` (0 = all)
+pub fn fs_readdir_filtered<T: FileSystem>(
+    plugin: *mut c_void,
+    path: *const c_char,
+    glob: *const c_char,
+    limit: i64,
+    out_count: *mut c_int,
+    out_err: *mut FSErrorC,
+) -> *mut FileInfoArray {
     unsafe {
+        set_out(out_count, -1);
+        if plugin.is_null() {
+            set_error(out_err, &Error::Other("plugin is null".to_string()));
+            return ptr::null_mut();
+        }
+        let Some(path_str) = path_arg(path, out_err) else {
+            return ptr::null_mut();
+        };
+        let Ok(glob_str) = c_str_to_str(glob) else {
+            set_error(out_err, &Error::InvalidInput("invalid glob".to_string()));
+            return ptr::null_mut();
+        };
+        let Ok(limit) = usize::try_from(limit) else {
+            set_error(out_err, &Error::InvalidInput("negative limit".to_string()));
+            return ptr::null_mut();
+        };
+
         let wrapper = &*(plugin as *const PluginWrapper<T>);
-        let fs = wrapper.fs.lock().unwrap();
-        match fs.readdir(path_str) {
-            Ok(files) => {
-                let count = files.len();
-                let items: Vec<FileInfoC> = files.iter().map(FileInfoC::from).collect();
+        let fs = wrapper.read();
+        file_info_array(fs.readdir_filtered(path_str, glob_str, limit), out_count, out_err)
+    }
+}
 
-                let mut items_vec = items.into_boxed_slice();
-                let items_ptr = items_vec.as_mut_ptr();
-                std::mem::forget(items_vec);
+/// Convert a readdir result into an `FSReadDir`-style return value
+unsafe fn file_info_array(
+    result: crate::Result<Vec<FileInfo>>,
+    out_count: *mut c_int,
+    out_err: *mut FSErrorC,
+) -> *mut FileInfoArray {
+    match result {
+        Ok(files) => {
+            let count = files.len();
+            let items: Vec<FileInfoC> = files.iter().map(FileInfoC::from).collect();
 
-                let array = Box::new(FileInfoArray {
-                    items: items_ptr,
-                    count: count as c_int,
-                });
+            let mut items_vec = items.into_boxed_slice();
+            let items_ptr = items_vec.as_mut_ptr();
+            std::mem::forget(items_vec);
 
-                *out_count = count as c_int;
-                Box::into_raw(array)
-            }
-            Err(_) => {
-                *out_count = -1;
-                ptr::null_mut()
-            }
+            let array = Box::new(FileInfoArray {
+                items: items_ptr,
+                count: count as c_int,
+            });
+
+            clear_error(out_err);
+            set_out(out_count, count as c_int);
+            Box::into_raw(array)
+        }
+        Err(e) => {
+            set_error(out_err, &e);
+            ptr::null_mut()
         }
     }
 }
@@ -309,7 +779,7 @@ pub fn fs_create<T: FileSystem>(plugin: *mut c_void, path: *const c_char) -> *co
 
     unsafe {
         let wrapper = &*(plugin as *const PluginWrapper<T>);
-        let fs = wrapper.fs.lock().unwrap();
+        let mut fs = wrapper.write();
         match fs.create(path_str) {
             Ok(_) => success(),
             Err(e) => error_to_c_string(&e.to_string()),
@@ -335,7 +805,7 @@ pub fn fs_mkdir<T: FileSystem>(
 
     unsafe {
         let wrapper = &*(plugin as *const PluginWrapper<T>);
-        let fs = wrapper.fs.lock().unwrap();
+        let mut fs = wrapper.write();
         match fs.mkdir(path_str, mode) {
             Ok(_) => success(),
             Err(e) => error_to_c_string(&e.to_string()),
@@ -357,7 +827,7 @@ pub fn fs_remove<T: FileSystem>(plugin: *mut c_void, path: *const c_char) -> *co
 
     unsafe {
         let wrapper = &*(plugin as *const PluginWrapper<T>);
-        let fs = wrapper.fs.lock().unwrap();
+        let mut fs = wrapper.write();
         match fs.remove(path_str) {
             Ok(_) => success(),
             Err(e) => error_to_c_string(&e.to_string()),
@@ -379,7 +849,7 @@ pub fn fs_remove_all<T: FileSystem>(plugin: *mut c_void, path: *const c_char) ->
 
     unsafe {
         let wrapper = &*(plugin as *const PluginWrapper<T>);
-        let fs = wrapper.fs.lock().unwrap();
+        let mut fs = wrapper.write();
         match fs.remove_all(path_str) {
             Ok(_) => success(),
             Err(e) => error_to_c_string(&e.to_string()),
@@ -388,7 +858,7 @@ pub fn fs_remove_all<T: FileSystem>(plugin: *mut c_void, path: *const c_char) ->
 }
 
 /// Write to file with offset and flags
-/// Returns packed i64: positive = bytes written, negative = error (use last 32 bits as error pointer)
+/// Returns bytes written, or -1 with `out_err` describing the failure
 pub fn fs_write<T: FileSystem>(
     plugin: *mut c_void,
     path: *const c_char,
@@ -396,31 +866,245 @@ pub fn fs_write<T: FileSystem>(
     data_len: c_int,
     offset: i64,
     flags: u32,
+    out_err: *mut FSErrorC,
 ) -> i64 {
+    unsafe {
+        if plugin.is_null() {
+            set_error(out_err, &Error::Other("plugin is null".to_string()));
+            return -1;
+        }
+        let Some(path_str) = path_arg(path, out_err) else {
+            return -1;
+        };
+
+        let data_slice = match host_buf(data as *const u8, data_len as i64) {
+            Ok(d) => d,
+            Err(e) => {
+                set_error(out_err, &Error::Other(e.to_string()));
+                return -1;
+            }
+        };
+
+        let wrapper = &*(plugin as *const PluginWrapper<T>);
+        let mut fs = wrapper.write();
+        match fs.write(path_str, data_slice, offset, WriteFlag::from(flags)) {
+            Ok(bytes_written) => {
+                clear_error(out_err);
+                bytes_written
+            }
+            Err(e) => {
+                set_error(out_err, &e);
+                -1
+            }
+        }
+    }
+}
+
+/// Apply a JSON array of [`FsOp`]s with `FileSystem::batch`
+///
+/// Returns a JSON array with one entry per operation, `null` on success or
+/// `{"code", "message"}` (free with `PluginFreeString`), or null with
+/// `out_err` set when `ops_json` is not a valid batch.
+pub fn fs_batch<T: FileSystem>(plugin: *mut c_void, ops_json: *const c_char, out_err: *mut FSErrorC) -> *const c_char {
+    unsafe {
+        if plugin.is_null() {
+            set_error(out_err, &Error::Other("plugin is null".to_string()));
+            return ptr::null();
+        }
+        let ops = match c_str_to_str(ops_json).map(serde_json::from_str::<Vec<FsOp>>) {
+            Ok(Ok(ops)) => ops,
+            Ok(Err(e)) => {
+                set_error(out_err, &Error::InvalidInput(format!("invalid batch: {}", e)));
+                return ptr::null();
+            }
+            Err(e) => {
+                set_error(out_err, &Error::InvalidInput(e.to_string()));
+                return ptr::null();
+            }
+        };
+
+        let wrapper = &*(plugin as *const PluginWrapper<T>);
+        let mut fs = wrapper.write();
+        let json = batch_results_json(&fs.batch(ops)).to_string();
+        clear_error(out_err);
+        CString::new(json).expect("batch results contain null byte").into_raw()
+    }
+}
+
+/// 1 if `fs_batch` applies all operations or none, else 0
+pub fn fs_atomic_batch<T: FileSystem>(plugin: *mut c_void) -> c_int {
     if plugin.is_null() {
+        return 0;
+    }
+    unsafe {
+        let wrapper = &*(plugin as *const PluginWrapper<T>);
+        wrapper.read().atomic_batch() as c_int
+    }
+}
+
+/// Start a write session; returns 0, or -1 with `out_err` set
+pub fn fs_begin_session<T: FileSystem>(plugin: *mut c_void, out_err: *mut FSErrorC) -> c_int {
+    call_mut::<T>(plugin, out_err, |fs| fs.begin_write_session())
+}
+
+/// Commit the open write session as one change described by `message`
+pub fn fs_commit_session<T: FileSystem>(plugin: *mut c_void, message: *const c_char, out_err: *mut FSErrorC) -> c_int {
+    let Ok(message) = (unsafe { c_str_to_str(message) }) else {
+        unsafe { set_error(out_err, &Error::InvalidInput("invalid commit message".to_string())) };
         return -1;
+    };
+    call_mut::<T>(plugin, out_err, |fs| fs.commit_session(message))
+}
+
+/// Discard the open write session
+pub fn fs_abort_session<T: FileSystem>(plugin: *mut c_void, out_err: *mut FSErrorC) -> c_int {
+    call_mut::<T>(plugin, out_err, |fs| fs.abort_session())
+}
+
+/// Run the plugin's periodic upkeep; returns 0, or -1 with `out_err` set
+pub fn fs_maintain<T: FileSystem>(plugin: *mut c_void, out_err: *mut FSErrorC) -> c_int {
+    call_mut::<T>(plugin, out_err, |fs| fs.maintain())
+}
+
+/// Run one warm-up slice of at most `budget_ms`, storing the progress it
+/// reports; returns 0, or -1 with `out_err` set
+pub fn fs_warmup<T: FileSystem>(
+    plugin: *mut c_void,
+    budget_ms: i64,
+    out_done: *mut u64,
+    out_total: *mut u64,
+    out_err: *mut FSErrorC,
+) -> c_int {
+    let budget = Duration::from_millis(budget_ms.max(0) as u64);
+    call_mut::<T>(plugin, out_err, |fs| {
+        let progress = fs.warmup(budget)?;
+        unsafe {
+            if !out_done.is_null() {
+                *out_done = progress.done;
+            }
+            if !out_total.is_null() {
+                *out_total = progress.total;
+            }
+        }
+        Ok(())
+    })
+}
+
+/// State for the instance replacing this one, as a byte buffer of
+/// `out_len` bytes (free with [`free_byte_buffer`]); null with `out_len` -1
+/// on failure
+pub fn fs_export_state<T: FileSystem>(
+    plugin: *mut c_void,
+    out_len: *mut i64,
+    out_err: *mut FSErrorC,
+) -> *const c_char {
+    unsafe {
+        set_out(out_len, -1);
+        if plugin.is_null() {
+            set_error(out_err, &Error::Other("plugin is null".to_string()));
+            return ptr::null();
+        }
+        let wrapper = &*(plugin as *const PluginWrapper<T>);
+        let fs = wrapper.read();
+        match fs.export_state() {
+            Ok(state) => {
+                clear_error(out_err);
+                set_out(out_len, state.len() as i64);
+                into_byte_buffer(state)
+            }
+            Err(e) => {
+                set_error(out_err, &e);
+                ptr::null()
+            }
+        }
     }
+}
 
-    let path_str = unsafe {
-        match c_str_to_str(path) {
-            Ok(s) => s,
-            Err(_) => return -1,
+/// Take over `state_len` bytes of state from the instance being replaced;
+/// returns 0, or -1 with `out_err` set
+pub fn fs_import_state<T: FileSystem>(
+    plugin: *mut c_void,
+    state: *const u8,
+    state_len: i64,
+    out_err: *mut FSErrorC,
+) -> c_int {
+    let state = match unsafe { host_buf(state, state_len) } {
+        Ok(state) => state,
+        Err(e) => {
+            unsafe { set_error(out_err, &Error::InvalidInput(e.to_string())) };
+            return -1;
         }
     };
+    call_mut::<T>(plugin, out_err, |fs| fs.import_state(state))
+}
 
-    let data_slice = unsafe {
-        if data.is_null() || data_len < 0 {
+/// Run `step` on the plugin under the write lock, reporting failure through `out_err`
+fn call_mut<T: FileSystem>(
+    plugin: *mut c_void,
+    out_err: *mut FSErrorC,
+    step: impl FnOnce(&mut T) -> crate::Result<()>,
+) -> c_int {
+    unsafe {
+        if plugin.is_null() {
+            set_error(out_err, &Error::Other("plugin is null".to_string()));
             return -1;
         }
-        std::slice::from_raw_parts(data as *const u8, data_len as usize)
-    };
+        let wrapper = &*(plugin as *const PluginWrapper<T>);
+        match step(&mut wrapper.write()) {
+            Ok(()) => {
+                clear_error(out_err);
+                0
+            }
+            Err(e) => {
+                set_error(out_err, &e);
+                -1
+            }
+        }
+    }
+}
 
+/// Replace a file only if it still matches `etag` (empty: must not exist)
+///
+/// A mismatch is reported as `ESTALE` through `out_err`.
+pub fn fs_write_if<T: FileSystem>(
+    plugin: *mut c_void,
+    path: *const c_char,
+    data: *const c_char,
+    data_len: c_int,
+    etag: *const c_char,
+    out_err: *mut FSErrorC,
+) -> i64 {
     unsafe {
+        if plugin.is_null() {
+            set_error(out_err, &Error::Other("plugin is null".to_string()));
+            return -1;
+        }
+        let Some(path_str) = path_arg(path, out_err) else {
+            return -1;
+        };
+        let Ok(etag_str) = c_str_to_str(etag) else {
+            set_error(out_err, &Error::InvalidInput("invalid etag".to_string()));
+            return -1;
+        };
+        let data_slice = match host_buf(data as *const u8, data_len as i64) {
+            Ok(d) => d,
+            Err(e) => {
+                set_error(out_err, &Error::Other(e.to_string()));
+                return -1;
+            }
+        };
+
         let wrapper = &*(plugin as *const PluginWrapper<T>);
-        let fs = wrapper.fs.lock().unwrap();
-        match fs.write(path_str, data_slice, offset, WriteFlag::from(flags)) {
-            Ok(bytes_written) => bytes_written,
-            Err(_) => -1,
+        let mut fs = wrapper.write();
+        match fs.write_if(path_str, data_slice, etag_str) {
+            Ok(bytes_written) => {
+                clear_error(out_err);
+                bytes_written
+            }
+            Err(e) => {
+                set_error(out_err, &e);
+                -1
+            }
         }
     }
 }
@@ -450,7 +1134,7 @@ pub fn fs_rename<T: FileSystem>(
 
     unsafe {
         let wrapper = &*(plugin as *const PluginWrapper<T>);
-        let fs = wrapper.fs.lock().unwrap();
+        let mut fs = wrapper.write();
         match fs.rename(old_path_str, new_path_str) {
             Ok(_) => success(),
             Err(e) => error_to_c_string(&e.to_string()),
@@ -476,10 +1160,1325 @@ pub fn fs_chmod<T: FileSystem>(
 
     unsafe {
         let wrapper = &*(plugin as *const PluginWrapper<T>);
-        let fs = wrapper.fs.lock().unwrap();
+        let mut fs = wrapper.write();
         match fs.chmod(path_str, mode) {
             Ok(_) => success(),
             Err(e) => error_to_c_string(&e.to_string()),
         }
     }
 }
+
+pub fn fs_symlink<T: FileSystem>(
+    plugin: *mut c_void,
+    target: *const c_char,
+    link_path: *const c_char,
+) -> *const c_char {
+    if plugin.is_null() {
+        return error_to_c_string("plugin is null");
+    }
+
+    let target_str = unsafe {
+        match c_str_to_str(target) {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(e),
+        }
+    };
+
+    let link_path_str = unsafe {
+        match c_str_to_str(link_path) {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(e),
+        }
+    };
+
+    unsafe {
+        let wrapper = &*(plugin as *const PluginWrapper<T>);
+        let mut fs = wrapper.write();
+        match fs.symlink(target_str, link_path_str) {
+            Ok(_) => success(),
+            Err(e) => error_to_c_string(&e.to_string()),
+        }
+    }
+}
+
+/// Read a symlink target; the returned string is released with `PluginFreeString`
+pub fn fs_readlink<T: FileSystem>(
+    plugin: *mut c_void,
+    path: *const c_char,
+    out_err: *mut FSErrorC,
+) -> *const c_char {
+    unsafe {
+        if plugin.is_null() {
+            set_error(out_err, &Error::Other("plugin is null".to_string()));
+            return ptr::null();
+        }
+        let Some(path_str) = path_arg(path, out_err) else {
+            return ptr::null();
+        };
+
+        let wrapper = &*(plugin as *const PluginWrapper<T>);
+        let fs = wrapper.read();
+        let target = fs.readlink(path_str).and_then(|target| {
+            CString::new(target).map_err(|_| Error::InvalidInput("link target contains NUL byte".to_string()))
+        });
+        match target {
+            Ok(target) => {
+                clear_error(out_err);
+                target.into_raw()
+            }
+            Err(e) => {
+                set_error(out_err, &e);
+                ptr::null()
+            }
+        }
+    }
+}
+
+/// Get an extended attribute value as a byte buffer (like `fs_read`)
+pub fn fs_get_xattr<T: FileSystem>(
+    plugin: *mut c_void,
+    path: *const c_char,
+    name: *const c_char,
+    out_len: *mut i64,
+    out_err: *mut FSErrorC,
+) -> *const c_char {
+    unsafe {
+        set_out(out_len, -1);
+        if plugin.is_null() {
+            set_error(out_err, &Error::Other("plugin is null".to_string()));
+            return ptr::null();
+        }
+        let Some(path_str) = path_arg(path, out_err) else {
+            return ptr::null();
+        };
+        let Ok(name_str) = c_str_to_str(name) else {
+            set_error(out_err, &Error::InvalidInput("invalid attribute name".to_string()));
+            return ptr::null();
+        };
+
+        let wrapper = &*(plugin as *const PluginWrapper<T>);
+        let fs = wrapper.read();
+        match fs.get_xattr(path_str, name_str) {
+            Ok(value) => {
+                clear_error(out_err);
+                set_out(out_len, value.len() as i64);
+                into_byte_buffer(value)
+            }
+            Err(e) => {
+                set_error(out_err, &e);
+                ptr::null()
+            }
+        }
+    }
+}
+
+pub fn fs_set_xattr<T: FileSystem>(
+    plugin: *mut c_void,
+    path: *const c_char,
+    name: *const c_char,
+    value: *const c_char,
+    value_len: c_int,
+) -> *const c_char {
+    if plugin.is_null() {
+        return error_to_c_string("plugin is null");
+    }
+
+    let path_str = unsafe {
+        match c_str_to_str(path) {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(e),
+        }
+    };
+
+    let name_str = unsafe {
+        match c_str_to_str(name) {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(e),
+        }
+    };
+
+    let value_slice = if value.is_null() || value_len <= 0 {
+        &[][..]
+    } else {
+        unsafe { std::slice::from_raw_parts(value as *const u8, value_len as usize) }
+    };
+
+    unsafe {
+        let wrapper = &*(plugin as *const PluginWrapper<T>);
+        let mut fs = wrapper.write();
+        match fs.set_xattr(path_str, name_str, value_slice) {
+            Ok(_) => success(),
+            Err(e) => error_to_c_string(&e.to_string()),
+        }
+    }
+}
+
+/// List extended attribute names as a NUL-separated byte buffer, the layout
+/// `listxattr(2)` uses
+pub fn fs_list_xattr<T: FileSystem>(
+    plugin: *mut c_void,
+    path: *const c_char,
+    out_len: *mut i64,
+    out_err: *mut FSErrorC,
+) -> *const c_char {
+    unsafe {
+        set_out(out_len, -1);
+        if plugin.is_null() {
+            set_error(out_err, &Error::Other("plugin is null".to_string()));
+            return ptr::null();
+        }
+        let Some(path_str) = path_arg(path, out_err) else {
+            return ptr::null();
+        };
+
+        let wrapper = &*(plugin as *const PluginWrapper<T>);
+        let fs = wrapper.read();
+        match fs.list_xattr(path_str) {
+            Ok(names) => {
+                let mut list = Vec::new();
+                for name in names {
+                    list.extend_from_slice(name.as_bytes());
+                    list.push(0);
+                }
+                clear_error(out_err);
+                set_out(out_len, list.len() as i64);
+                into_byte_buffer(list)
+            }
+            Err(e) => {
+                set_error(out_err, &e);
+                ptr::null()
+            }
+        }
+    }
+}
+
+/// Pass an access pattern hint (`posix_fadvise` values) to the filesystem
+pub fn fs_advise<T: FileSystem>(
+    plugin: *mut c_void,
+    path: *const c_char,
+    offset: i64,
+    len: i64,
+    advice: c_int,
+) -> *const c_char {
+    if plugin.is_null() {
+        return error_to_c_string("plugin is null");
+    }
+
+    let path_str = unsafe {
+        match c_str_to_str(path) {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(e),
+        }
+    };
+
+    let advice = match Advice::try_from(advice) {
+        Ok(advice) => advice,
+        Err(e) => return error_to_c_string(&e.to_string()),
+    };
+
+    unsafe {
+        let wrapper = &*(plugin as *const PluginWrapper<T>);
+        let fs = wrapper.read();
+        match fs.advise(path_str, offset, len, advice) {
+            Ok(_) => success(),
+            Err(e) => error_to_c_string(&e.to_string()),
+        }
+    }
+}
+
+// Helper functions used by the async exports (`export_plugin!(T, async)`)
+
+/// Start a read and return its operation ID (-1 on error)
+pub fn fs_submit_read<T: AsyncFS>(
+    plugin: *mut c_void,
+    path: *const c_char,
+    offset: i64,
+    size: i64,
+    out_err: *mut FSErrorC,
+) -> i64 {
+    unsafe {
+        if plugin.is_null() {
+            set_error(out_err, &Error::Other("plugin is null".to_string()));
+            return -1;
+        }
+        let Some(path_str) = path_arg(path, out_err) else {
+            return -1;
+        };
+
+        let wrapper = &*(plugin as *const PluginWrapper<T>);
+        let fs = wrapper.read();
+        let job = match fs.read_job(path_str, offset, size) {
+            Ok(Some(job)) => job,
+            Ok(None) => {
+                clear_error(out_err);
+                return wrapper.ops.completed(fs.read(path_str, offset, size).map(Completion::Read));
+            }
+            Err(e) => {
+                set_error(out_err, &e);
+                return -1;
+            }
+        };
+        drop(fs);
+
+        clear_error(out_err);
+        wrapper.ops.spawn(move |cancel| job(cancel).map(Completion::Read))
+    }
+}
+
+/// Start a write and return its operation ID (-1 on error)
+///
+/// `data` is copied before this returns.
+pub fn fs_submit_write<T: AsyncFS>(
+    plugin: *mut c_void,
+    path: *const c_char,
+    data: *const c_char,
+    data_len: c_int,
+    offset: i64,
+    flags: u32,
+    out_err: *mut FSErrorC,
+) -> i64 {
+    unsafe {
+        if plugin.is_null() {
+            set_error(out_err, &Error::Other("plugin is null".to_string()));
+            return -1;
+        }
+        let Some(path_str) = path_arg(path, out_err) else {
+            return -1;
+        };
+
+        let data_slice = match host_buf(data as *const u8, data_len as i64) {
+            Ok(d) => d,
+            Err(e) => {
+                set_error(out_err, &Error::Other(e.to_string()));
+                return -1;
+            }
+        };
+        let flags = WriteFlag::from(flags);
+
+        let wrapper = &*(plugin as *const PluginWrapper<T>);
+        let fs = wrapper.read();
+        let job = match fs.write_job(path_str, data_slice.to_vec(), offset, flags) {
+            Ok(Some(job)) => job,
+            Ok(None) => {
+                drop(fs);
+                let result = wrapper.write().write(path_str, data_slice, offset, flags);
+                clear_error(out_err);
+                return wrapper.ops.completed(result.map(Completion::Write));
+            }
+            Err(e) => {
+                set_error(out_err, &e);
+                return -1;
+            }
+        };
+        drop(fs);
+
+        clear_error(out_err);
+        wrapper.ops.spawn(move |cancel| job(cancel).map(Completion::Write))
+    }
+}
+
+/// Collect the result of a submitted operation
+///
+/// Waits up to `timeout_ms` (negative waits until done) and returns
+/// `POLL_PENDING`, `POLL_DONE` or `POLL_UNKNOWN`. On `POLL_DONE` a read stores
+/// its data in `out_data` (free with `PluginFreeBuffer`) and its length in
+/// `out_len`; a write stores the bytes written in `out_len`. A failed
+/// operation sets `out_len` to -1 and fills `out_err`.
+pub fn fs_poll<T: AsyncFS>(
+    plugin: *mut c_void,
+    op: i64,
+    timeout_ms: i64,
+    out_data: *mut *const c_char,
+    out_len: *mut i64,
+    out_err: *mut FSErrorC,
+) -> c_int {
+    unsafe {
+        set_out(out_data, ptr::null());
+        set_out(out_len, -1);
+        if plugin.is_null() {
+            set_error(out_err, &Error::Other("plugin is null".to_string()));
+            return POLL_UNKNOWN;
+        }
+
+        let wrapper = &*(plugin as *const PluginWrapper<T>);
+        let timeout = u64::try_from(timeout_ms).ok().map(Duration::from_millis);
+        match wrapper.ops.poll(op, timeout) {
+            PollResult::Unknown => {
+                set_error(out_err, &Error::InvalidInput(format!("unknown operation {}", op)));
+                POLL_UNKNOWN
+            }
+            PollResult::Pending => {
+                clear_error(out_err);
+                POLL_PENDING
+            }
+            PollResult::Done(Ok(Completion::Read(data))) => {
+                clear_error(out_err);
+                set_out(out_len, data.len() as i64);
+                set_out(out_data, into_byte_buffer(data));
+                POLL_DONE
+            }
+            PollResult::Done(Ok(Completion::Write(n))) => {
+                clear_error(out_err);
+                set_out(out_len, n);
+                POLL_DONE
+            }
+            PollResult::Done(Err(e)) => {
+                set_error(out_err, &e);
+                POLL_DONE
+            }
+        }
+    }
+}
+
+/// Abandon a submitted operation
+///
+/// Returns 1 if `op` was pending (its job's token is now cancelled and its
+/// result will be discarded) or 0 if it is unknown. Cancelled operations must
+/// not be polled.
+pub fn fs_cancel<T: AsyncFS>(plugin: *mut c_void, op: i64) -> c_int {
+    if plugin.is_null() {
+        return 0;
+    }
+    let wrapper = unsafe { &*(plugin as *const PluginWrapper<T>) };
+    wrapper.ops.cancel(op) as c_int
+}
+
+// Helper functions used by the export_handle_plugin! macro
+
+pub fn handle_open<T: HandleFS>(
+    plugin: *mut c_void,
+    path: *const c_char,
+    flags: u32,
+    mode: u32,
+    out_id: *mut i64,
+) -> *const c_char {
+    if plugin.is_null() {
+        return error_to_c_string("plugin is null");
+    }
+
+    let path_str = unsafe {
+        match c_str_to_str(path) {
+            Ok(s) => s,
+            Err(e) => return error_to_c_string(e),
+        }
+    };
+
+    unsafe {
+        let wrapper = &*(plugin as *const PluginWrapper<T>);
+        let mut fs = wrapper.write();
+        match fs.open_handle(path_str, OpenFlag::from(flags), mode) {
+            Ok(id) => {
+                set_out(out_id, id);
+                success()
+            }
+            Err(e) => error_to_c_string(&e.to_string()),
+        }
+    }
+}
+
+pub fn handle_read<T: HandleFS>(
+    plugin: *mut c_void,
+    id: i64,
+    buf: *mut u8,
+    buf_len: i64,
+    out_n: *mut i64,
+) -> *const c_char {
+    if plugin.is_null() {
+        return error_to_c_string("plugin is null");
+    }
+
+    unsafe {
+        let buf = match host_buf_mut(buf, buf_len) {
+            Ok(b) => b,
+            Err(e) => return error_to_c_string(e),
+        };
+        let wrapper = &*(plugin as *const PluginWrapper<T>);
+        let mut fs = wrapper.write();
+        match fs.handle_read(id, buf) {
+            Ok(n) => {
+                set_out(out_n, n as i64);
+                success()
+            }
+            Err(e) => error_to_c_string(&e.to_string()),
+        }
+    }
+}
+
+pub fn handle_read_at<T: HandleFS>(
+    plugin: *mut c_void,
+    id: i64,
+    buf: *mut u8,
+    buf_len: i64,
+    offset: i64,
+    out_n: *mut i64,
+) -> *const c_char {
+    if plugin.is_null() {
+        return error_to_c_string("plugin is null");
+    }
+
+    unsafe {
+        let buf = match host_buf_mut(buf, buf_len) {
+            Ok(b) => b,
+            Err(e) => return error_to_c_string(e),
+        };
+        let wrapper = &*(plugin as *const PluginWrapper<T>);
+        let fs = wrapper.read();
+        match fs.handle_read_at(id, buf, offset) {
+            Ok(n) => {
+                set_out(out_n, n as i64);
+                success()
+            }
+            Err(e) => error_to_c_string(&e.to_string()),
+        }
+    }
+}
+
+pub fn handle_write<T: HandleFS>(
+    plugin: *mut c_void,
+    id: i64,
+    data: *const u8,
+    data_len: i64,
+    out_n: *mut i64,
+) -> *const c_char {
+    if plugin.is_null() {
+        return error_to_c_string("plugin is null");
+    }
+
+    unsafe {
+        let data = match host_buf(data, data_len) {
+            Ok(d) => d,
+            Err(e) => return error_to_c_string(e),
+        };
+        let wrapper = &*(plugin as *const PluginWrapper<T>);
+        let mut fs = wrapper.write();
+        match fs.handle_write(id, data) {
+            Ok(n) => {
+                set_out(out_n, n as i64);
+                success()
+            }
+            Err(e) => error_to_c_string(&e.to_string()),
+        }
+    }
+}
+
+pub fn handle_write_at<T: HandleFS>(
+    plugin: *mut c_void,
+    id: i64,
+    data: *const u8,
+    data_len: i64,
+    offset: i64,
+    out_n: *mut i64,
+) -> *const c_char {
+    if plugin.is_null() {
+        return error_to_c_string("plugin is null");
+    }
+
+    unsafe {
+        let data = match host_buf(data, data_len) {
+            Ok(d) => d,
+            Err(e) => return error_to_c_string(e),
+        };
+        let wrapper = &*(plugin as *const PluginWrapper<T>);
+        let fs = wrapper.read();
+        match fs.handle_write_at(id, data, offset) {
+            Ok(n) => {
+                set_out(out_n, n as i64);
+                success()
+            }
+            Err(e) => error_to_c_string(&e.to_string()),
+        }
+    }
+}
+
+pub fn handle_seek<T: HandleFS>(
+    plugin: *mut c_void,
+    id: i64,
+    offset: i64,
+    whence: i32,
+    out_pos: *mut i64,
+) -> *const c_char {
+    if plugin.is_null() {
+        return error_to_c_string("plugin is null");
+    }
+
+    unsafe {
+        let wrapper = &*(plugin as *const PluginWrapper<T>);
+        let mut fs = wrapper.write();
+        match fs.handle_seek(id, offset, whence) {
+            Ok(pos) => {
+                set_out(out_pos, pos);
+                success()
+            }
+            Err(e) => error_to_c_string(&e.to_string()),
+        }
+    }
+}
+
+pub fn handle_sync<T: HandleFS>(plugin: *mut c_void, id: i64) -> *const c_char {
+    if plugin.is_null() {
+        return error_to_c_string("plugin is null");
+    }
+
+    unsafe {
+        let wrapper = &*(plugin as *const PluginWrapper<T>);
+        let fs = wrapper.read();
+        match fs.handle_sync(id) {
+            Ok(_) => success(),
+            Err(e) => error_to_c_string(&e.to_string()),
+        }
+    }
+}
+
+pub fn handle_stat<T: HandleFS>(
+    plugin: *mut c_void,
+    id: i64,
+    out_info: *mut *mut FileInfoC,
+) -> *const c_char {
+    if plugin.is_null() {
+        return error_to_c_string("plugin is null");
+    }
+
+    unsafe {
+        let wrapper = &*(plugin as *const PluginWrapper<T>);
+        let fs = wrapper.read();
+        match fs.handle_stat(id) {
+            Ok(info) => {
+                set_out(out_info, Box::into_raw(Box::new(FileInfoC::from(&info))));
+                success()
+            }
+            Err(e) => error_to_c_string(&e.to_string()),
+        }
+    }
+}
+
+pub fn handle_close<T: HandleFS>(plugin: *mut c_void, id: i64) -> *const c_char {
+    if plugin.is_null() {
+        return error_to_c_string("plugin is null");
+    }
+
+    unsafe {
+        let wrapper = &*(plugin as *const PluginWrapper<T>);
+        let mut fs = wrapper.write();
+        match fs.close_handle(id) {
+            Ok(_) => success(),
+            Err(e) => error_to_c_string(&e.to_string()),
+        }
+    }
+}
+
+/// Release an error string returned by any export
+///
+/// # Safety
+///
+/// `ptr` must be null or a string returned by this SDK and not yet freed.
+pub unsafe fn free_string(ptr: *const c_char) {
+    if !ptr.is_null() {
+        drop(CString::from_raw(ptr as *mut c_char));
+    }
+}
+
+/// Release a `FileInfoC` returned by `fs_stat`, including its strings
+///
+/// # Safety
+///
+/// `info` must be null or a pointer returned by `fs_stat` and not yet freed.
+pub unsafe fn free_file_info(info: *mut FileInfoC) {
+    if !info.is_null() {
+        drop(Box::from_raw(info));
+    }
+}
+
+/// Release a `FileInfoArray` returned by `fs_readdir` and every entry in it
+///
+/// # Safety
+///
+/// `array` must be null or a pointer returned by `fs_readdir` and not yet freed.
+pub unsafe fn free_file_info_array(array: *mut FileInfoArray) {
+    if array.is_null() {
+        return;
+    }
+    let array = Box::from_raw(array);
+    if !array.items.is_null() {
+        let items = ptr::slice_from_raw_parts_mut(array.items, array.count as usize);
+        drop(Box::from_raw(items));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_byte_buffer_roundtrip() {
+        let data = vec![b'a', 0x00, 0xff, 0x00, b'z'];
+        let ptr = into_byte_buffer(data.clone());
+        unsafe {
+            assert_eq!(byte_buffer_len(ptr), data.len());
+            let slice = std::slice::from_raw_parts(ptr as *const u8, data.len());
+            assert_eq!(slice, data.as_slice());
+            free_byte_buffer(ptr);
+        }
+    }
+
+    #[test]
+    fn test_empty_byte_buffer() {
+        let ptr = into_byte_buffer(Vec::new());
+        assert!(!ptr.is_null());
+        unsafe {
+            assert_eq!(byte_buffer_len(ptr), 0);
+            free_byte_buffer(ptr);
+        }
+    }
+
+    #[test]
+    fn test_free_file_info_array() {
+        let files = [FileInfo::file("a", 1, 0o644), FileInfo::dir("b", 0o755)];
+        let items: Vec<FileInfoC> = files.iter().map(FileInfoC::from).collect();
+        let count = items.len() as c_int;
+        let items = Box::into_raw(items.into_boxed_slice()) as *mut FileInfoC;
+        let array = Box::into_raw(Box::new(FileInfoArray { items, count }));
+        unsafe {
+            let second = &*(*array).items.add(1);
+            assert_eq!(CStr::from_ptr(second.name).to_str().unwrap(), "b");
+            free_file_info_array(array);
+            free_file_info_array(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn test_free_string_and_file_info() {
+        unsafe {
+            free_string(error_to_c_string("boom"));
+            free_string(ptr::null());
+            free_file_info(Box::into_raw(Box::new(FileInfoC::from(&FileInfo::file("x", 0, 0o644)))));
+            free_file_info(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn test_file_info_etag_and_read_if_changed() {
+        let plain = FileInfoC::from(&FileInfo::file("x", 0, 0o644));
+        assert!(plain.etag.is_null());
+        let tagged = FileInfoC::from(&FileInfo::file("x", 0, 0o644).with_etag("abc"));
+        unsafe { assert_eq!(CStr::from_ptr(tagged.etag).to_str().unwrap(), "abc") };
+
+        let plugin = Box::into_raw(Box::new(PluginWrapper::<ListFS>::new())) as *mut c_void;
+        let path = CString::new("/").unwrap();
+        let mut err = FSErrorC { code: -1, message: ptr::null() };
+        let mut len = 0i64;
+
+        let current = CString::new("v1").unwrap();
+        let data = fs_read_if_changed::<ListFS>(plugin, path.as_ptr(), current.as_ptr(), &mut len, &mut err);
+        assert!(data.is_null());
+        assert_eq!(len, READ_NOT_MODIFIED);
+        assert_eq!(err.code, 0);
+
+        // A stale ETag falls through to read, which ListFS does not implement
+        let stale = CString::new("v0").unwrap();
+        let data = fs_read_if_changed::<ListFS>(plugin, path.as_ptr(), stale.as_ptr(), &mut len, &mut err);
+        assert!(data.is_null());
+        assert_eq!(len, -1);
+        assert_eq!(err.code, Error::ReadOnly.code());
+
+        unsafe {
+            free_string(err.message);
+            drop(Box::from_raw(plugin as *mut PluginWrapper<ListFS>));
+        }
+    }
+
+    #[test]
+    fn test_write_if_reports_stale() {
+        let plugin = Box::into_raw(Box::new(PluginWrapper::<ListFS>::new())) as *mut c_void;
+        let path = CString::new("/").unwrap();
+        let data = b"new".as_ptr() as *const c_char;
+        let mut err = FSErrorC { code: -1, message: ptr::null() };
+
+        let stale = CString::new("v0").unwrap();
+        let n = fs_write_if::<ListFS>(plugin, path.as_ptr(), data, 3, stale.as_ptr(), &mut err);
+        assert_eq!(n, -1);
+        assert_eq!(err.code, Error::Stale.code());
+
+        unsafe {
+            free_string(err.message);
+            drop(Box::from_raw(plugin as *mut PluginWrapper<ListFS>));
+        }
+    }
+
+    /// Single-file filesystem with one cursor per handle
+    #[derive(Default)]
+    struct CursorFS {
+        data: Mutex<Vec<u8>>,
+        handles: std::collections::HashMap<i64, i64>,
+    }
+
+    impl FileSystem for CursorFS {
+        fn name(&self) -> &str {
+            "cursor-fs"
+        }
+
+        fn read(&self, _path: &str, _offset: i64, _size: i64) -> crate::Result<Vec<u8>> {
+            Ok(self.data.lock().unwrap().clone())
+        }
+
+        fn stat(&self, _path: &str) -> crate::Result<FileInfo> {
+            Ok(FileInfo::file("f", self.data.lock().unwrap().len() as i64, 0o644))
+        }
+
+        fn readdir(&self, _path: &str) -> crate::Result<Vec<FileInfo>> {
+            Ok(vec![])
+        }
+    }
+
+    impl HandleFS for CursorFS {
+        fn open_handle(&mut self, _path: &str, _flags: OpenFlag, _mode: u32) -> crate::Result<i64> {
+            let id = self.handles.len() as i64 + 1;
+            self.handles.insert(id, 0);
+            Ok(id)
+        }
+
+        fn handle_read(&mut self, id: i64, buf: &mut [u8]) -> crate::Result<usize> {
+            let pos = *self.handles.get(&id).ok_or(crate::Error::NotFound)?;
+            let n = self.handle_read_at(id, buf, pos)?;
+            self.handles.insert(id, pos + n as i64);
+            Ok(n)
+        }
+
+        fn handle_read_at(&self, _id: i64, buf: &mut [u8], offset: i64) -> crate::Result<usize> {
+            let data = self.data.lock().unwrap();
+            let start = (offset as usize).min(data.len());
+            let n = buf.len().min(data.len() - start);
+            buf[..n].copy_from_slice(&data[start..start + n]);
+            Ok(n)
+        }
+
+        fn handle_write(&mut self, id: i64, data: &[u8]) -> crate::Result<usize> {
+            let pos = *self.handles.get(&id).ok_or(crate::Error::NotFound)?;
+            let n = self.handle_write_at(id, data, pos)?;
+            self.handles.insert(id, pos + n as i64);
+            Ok(n)
+        }
+
+        fn handle_write_at(&self, _id: i64, data: &[u8], offset: i64) -> crate::Result<usize> {
+            let mut file = self.data.lock().unwrap();
+            let end = offset as usize + data.len();
+            if file.len() < end {
+                file.resize(end, 0);
+            }
+            file[offset as usize..end].copy_from_slice(data);
+            Ok(data.len())
+        }
+
+        fn handle_seek(&mut self, id: i64, offset: i64, _whence: i32) -> crate::Result<i64> {
+            let pos = self.handles.get_mut(&id).ok_or(crate::Error::NotFound)?;
+            *pos = offset;
+            Ok(offset)
+        }
+
+        fn handle_stat(&self, _id: i64) -> crate::Result<FileInfo> {
+            self.stat("/f")
+        }
+
+        fn handle_info(&self, id: i64) -> crate::Result<(String, OpenFlag)> {
+            self.handles.get(&id).map(|_| ("/f".to_string(), OpenFlag::O_RDWR)).ok_or(crate::Error::NotFound)
+        }
+
+        fn close_handle(&mut self, id: i64) -> crate::Result<()> {
+            self.handles.remove(&id).map(|_| ()).ok_or(crate::Error::NotFound)
+        }
+    }
+
+    #[test]
+    fn test_handle_exports_roundtrip() {
+        let plugin = Box::into_raw(Box::new(PluginWrapper::<CursorFS>::new())) as *mut c_void;
+        let path = CString::new("/f").unwrap();
+        let mut id = 0i64;
+        let mut n = 0i64;
+
+        assert!(handle_open::<CursorFS>(plugin, path.as_ptr(), 2, 0o644, &mut id).is_null());
+        assert_eq!(id, 1);
+
+        let data = b"ab\0cd";
+        assert!(handle_write::<CursorFS>(plugin, id, data.as_ptr(), 5, &mut n).is_null());
+        assert_eq!(n, 5);
+
+        let mut pos = -1i64;
+        assert!(handle_seek::<CursorFS>(plugin, id, 1, 0, &mut pos).is_null());
+        assert_eq!(pos, 1);
+
+        let mut buf = [0u8; 8];
+        assert!(handle_read::<CursorFS>(plugin, id, buf.as_mut_ptr(), 8, &mut n).is_null());
+        assert_eq!(&buf[..n as usize], b"b\0cd");
+
+        assert!(handle_write_at::<CursorFS>(plugin, id, b"Z".as_ptr(), 1, 0, &mut n).is_null());
+        assert!(handle_read_at::<CursorFS>(plugin, id, buf.as_mut_ptr(), 2, 0, &mut n).is_null());
+        assert_eq!(&buf[..2], b"Zb");
+
+        let mut info: *mut FileInfoC = ptr::null_mut();
+        assert!(handle_stat::<CursorFS>(plugin, id, &mut info).is_null());
+        unsafe {
+            assert_eq!((*info).size, 5);
+            free_file_info(info);
+        }
+
+        assert!(handle_close::<CursorFS>(plugin, id).is_null());
+        let err = handle_close::<CursorFS>(plugin, id);
+        unsafe {
+            assert_eq!(CStr::from_ptr(err).to_str().unwrap(), "file not found");
+            free_string(err);
+            drop(Box::from_raw(plugin as *mut PluginWrapper<CursorFS>));
+        }
+    }
+
+    #[test]
+    fn test_structured_errors() {
+        let plugin = Box::into_raw(Box::new(PluginWrapper::<CursorFS>::new())) as *mut c_void;
+        let path = CString::new("/f").unwrap();
+        let mut err = FSErrorC { code: -1, message: ptr::null() };
+
+        // Success clears the report
+        let info = fs_stat::<CursorFS>(plugin, path.as_ptr(), &mut err);
+        assert!(!info.is_null());
+        assert_eq!(err.code, 0);
+        assert!(err.message.is_null());
+
+        // CursorFS has no write(), so the default ReadOnly error surfaces as EROFS
+        let n = fs_write::<CursorFS>(plugin, path.as_ptr(), b"x".as_ptr() as *const c_char, 1, 0, 0, &mut err);
+        assert_eq!(n, -1);
+        assert_eq!(err.code, Error::ReadOnly.code());
+
+        // A null path is reported as InvalidPath with a fresh message
+        unsafe { free_string(err.message) };
+        let mut len = 0i64;
+        let data = fs_read::<CursorFS>(plugin, ptr::null(), 0, -1, &mut len, &mut err);
+        assert!(data.is_null());
+        assert_eq!(len, -1);
+        assert_eq!(err.code, Error::InvalidPath.code());
+
+        unsafe {
+            assert_eq!(CStr::from_ptr(err.message).to_str().unwrap(), "invalid path");
+            free_string(err.message);
+            free_file_info(info);
+            drop(Box::from_raw(plugin as *mut PluginWrapper<CursorFS>));
+        }
+    }
+
+    #[test]
+    fn test_read_ops_share_lock() {
+        let plugin = Box::into_raw(Box::new(PluginWrapper::<CursorFS>::new())) as *mut c_void;
+        let path = CString::new("/f").unwrap();
+        let mut err = FSErrorC { code: -1, message: ptr::null() };
+
+        unsafe {
+            // A reader holding the lock must not block stat from another caller
+            let wrapper = &*(plugin as *const PluginWrapper<CursorFS>);
+            let _guard = wrapper.read();
+            let info = fs_stat::<CursorFS>(plugin, path.as_ptr(), &mut err);
+            assert!(!info.is_null());
+            assert_eq!(err.code, 0);
+            free_file_info(info);
+        }
+
+        unsafe {
+            drop(Box::from_raw(plugin as *mut PluginWrapper<CursorFS>));
+        }
+    }
+
+    /// Filesystem with a fixed five-entry root directory
+    #[derive(Default)]
+    struct ListFS;
+
+    impl FileSystem for ListFS {
+        fn name(&self) -> &str {
+            "list-fs"
+        }
+
+        fn stat(&self, _path: &str) -> crate::Result<FileInfo> {
+            Ok(FileInfo::dir("", 0o755).with_etag("v1"))
+        }
+
+        fn readdir(&self, _path: &str) -> crate::Result<Vec<FileInfo>> {
+            Ok((0..5).map(|i| FileInfo::file(format!("f{}", i), 0, 0o644)).collect())
+        }
+    }
+
+    #[test]
+    fn test_readdir_page() {
+        let plugin = Box::into_raw(Box::new(PluginWrapper::<ListFS>::new())) as *mut c_void;
+        let path = CString::new("/").unwrap();
+        let mut err = FSErrorC { code: -1, message: ptr::null() };
+        let mut count: c_int = 0;
+
+        let mut names = Vec::new();
+        let mut offset = 0;
+        loop {
+            let array = fs_readdir_page::<ListFS>(plugin, path.as_ptr(), offset, 2, &mut count, &mut err);
+            assert!(!array.is_null());
+            assert_eq!(err.code, 0);
+            unsafe {
+                for i in 0..count as usize {
+                    let item = &*(*array).items.add(i);
+                    names.push(CStr::from_ptr(item.name).to_str().unwrap().to_string());
+                }
+                free_file_info_array(array);
+            }
+            offset += count as i64;
+            if count < 2 {
+                break;
+            }
+        }
+        assert_eq!(names, ["f0", "f1", "f2", "f3", "f4"]);
+
+        let array = fs_readdir_page::<ListFS>(plugin, path.as_ptr(), -1, 2, &mut count, &mut err);
+        assert!(array.is_null());
+        assert_eq!(count, -1);
+        assert_eq!(err.code, Error::InvalidInput(String::new()).code());
+
+        unsafe {
+            free_string(err.message);
+            drop(Box::from_raw(plugin as *mut PluginWrapper<ListFS>));
+        }
+    }
+
+    #[test]
+    fn test_readdir_filtered() {
+        let plugin = Box::into_raw(Box::new(PluginWrapper::<ListFS>::new())) as *mut c_void;
+        let path = CString::new("/").unwrap();
+        let glob = CString::new("f[0-9]").unwrap();
+        let mut err = FSErrorC { code: -1, message: ptr::null() };
+        let mut count: c_int = 0;
+
+        // Brackets are literal, so nothing matches
+        let array = fs_readdir_filtered::<ListFS>(plugin, path.as_ptr(), glob.as_ptr(), 0, &mut count, &mut err);
+        assert_eq!(count, 0);
+        unsafe { free_file_info_array(array) };
+
+        let glob = CString::new("f?").unwrap();
+        let array = fs_readdir_filtered::<ListFS>(plugin, path.as_ptr(), glob.as_ptr(), 3, &mut count, &mut err);
+        assert_eq!(count, 3);
+        assert_eq!(err.code, 0);
+
+        unsafe {
+            free_file_info_array(array);
+            drop(Box::from_raw(plugin as *mut PluginWrapper<ListFS>));
+        }
+    }
+
+    #[test]
+    fn test_batch() {
+        let plugin = Box::into_raw(Box::new(PluginWrapper::<ListFS>::new())) as *mut c_void;
+        let mut err = FSErrorC { code: -1, message: ptr::null() };
+
+        let bad = CString::new(r#"[{"op": "explode"}]"#).unwrap();
+        assert!(fs_batch::<ListFS>(plugin, bad.as_ptr(), &mut err).is_null());
+        assert_eq!(err.code, Error::InvalidInput(String::new()).code());
+        unsafe { free_string(err.message) };
+
+        // ListFS is read-only: the first op fails and the second is skipped
+        let ops = CString::new(r#"[{"op": "create", "path": "/a"}, {"op": "remove", "path": "/b"}]"#).unwrap();
+        let json = fs_batch::<ListFS>(plugin, ops.as_ptr(), &mut err);
+        assert_eq!(err.code, 0);
+        unsafe {
+            let results: serde_json::Value = serde_json::from_str(CStr::from_ptr(json).to_str().unwrap()).unwrap();
+            assert_eq!(results[0]["code"], Error::ReadOnly.code());
+            assert_eq!(results[1]["code"], Error::Cancelled.code());
+            free_string(json);
+        }
+        assert_eq!(fs_atomic_batch::<ListFS>(plugin), 0);
+
+        unsafe { drop(Box::from_raw(plugin as *mut PluginWrapper<ListFS>)) };
+    }
+
+    #[test]
+    fn test_default_upkeep_and_write_session() {
+        let plugin = Box::into_raw(Box::new(PluginWrapper::<ListFS>::new())) as *mut c_void;
+        let mut err = FSErrorC { code: -1, message: ptr::null() };
+        let message = CString::new("update docs").unwrap();
+
+        assert_eq!(fs_maintain::<ListFS>(plugin, &mut err), 0);
+        assert_eq!(err.code, 0);
+        let (mut done, mut total) = (7, 7);
+        assert_eq!(fs_warmup::<ListFS>(plugin, 100, &mut done, &mut total, &mut err), 0);
+        assert_eq!((done, total), (0, 0));
+        let mut len = 0;
+        let state = fs_export_state::<ListFS>(plugin, &mut len, &mut err);
+        assert_eq!(len, 0);
+        unsafe { free_byte_buffer(state) };
+        let old_state = b"cache";
+        assert_eq!(fs_import_state::<ListFS>(plugin, old_state.as_ptr(), 5, &mut err), 0);
+        assert_eq!(fs_import_state::<ListFS>(plugin, ptr::null(), 5, &mut err), -1);
+        unsafe { free_string(err.message) };
+        assert_eq!(fs_begin_session::<ListFS>(plugin, &mut err), -1);
+        assert_eq!(err.code, Error::ReadOnly.code());
+        unsafe { free_string(err.message) };
+        assert_eq!(fs_commit_session::<ListFS>(plugin, message.as_ptr(), &mut err), -1);
+        unsafe { free_string(err.message) };
+        assert_eq!(fs_commit_session::<ListFS>(plugin, ptr::null(), &mut err), -1);
+        assert_eq!(err.code, Error::InvalidInput(String::new()).code());
+
+        unsafe {
+            free_string(err.message);
+            drop(Box::from_raw(plugin as *mut PluginWrapper<ListFS>));
+        }
+    }
+
+    /// Files `/f0`..`/f4` whose content is their name
+    #[derive(Default)]
+    struct NamedFS;
+
+    impl FileSystem for NamedFS {
+        fn name(&self) -> &str {
+            "named-fs"
+        }
+
+        fn read(&self, path: &str, _offset: i64, _size: i64) -> crate::Result<Vec<u8>> {
+            self.stat(path).map(|info| info.name.into_bytes())
+        }
+
+        fn stat(&self, path: &str) -> crate::Result<FileInfo> {
+            match path.strip_prefix('/') {
+                Some(name @ ("f0" | "f1" | "f2" | "f3" | "f4")) => Ok(FileInfo::file(name, 2, 0o644)),
+                _ => Err(Error::NotFound),
+            }
+        }
+
+        fn readdir(&self, _path: &str) -> crate::Result<Vec<FileInfo>> {
+            Ok(vec![])
+        }
+    }
+
+    #[test]
+    fn test_read_many() {
+        let plugin = Box::into_raw(Box::new(PluginWrapper::<NamedFS>::new())) as *mut c_void;
+        let paths = b"/f1\0/nope\0/f3\0";
+        let (paths_ptr, paths_len) = (paths.as_ptr() as *const c_char, paths.len() as i64);
+        let mut err = FSErrorC { code: -1, message: ptr::null() };
+        let mut results: Vec<ReadResultC> = Vec::with_capacity(3);
+
+        let n = fs_read_many::<NamedFS>(plugin, paths_ptr, paths_len, results.as_mut_ptr(), 2, &mut err);
+        assert_eq!(n, -1);
+        assert_eq!(err.code, Error::InvalidInput(String::new()).code());
+        unsafe { free_string(err.message) };
+
+        let n = fs_read_many::<NamedFS>(plugin, paths_ptr, paths_len, results.as_mut_ptr(), 3, &mut err);
+        assert_eq!(n, 3);
+        assert_eq!(err.code, 0);
+        unsafe {
+            results.set_len(3);
+            assert_eq!(std::slice::from_raw_parts(results[0].data as *const u8, results[0].len as usize), b"f1");
+            assert_eq!(results[1].len, -1);
+            assert_eq!(results[1].err.code, Error::NotFound.code());
+            assert_eq!(results[2].len, 2);
+            free_byte_buffer(results[0].data);
+            free_string(results[1].err.message);
+            free_byte_buffer(results[2].data);
+            drop(Box::from_raw(plugin as *mut PluginWrapper<NamedFS>));
+        }
+    }
+
+    /// Filesystem storing symlinks and xattrs in maps
+    #[derive(Default)]
+    struct AttrFS {
+        links: std::collections::BTreeMap<String, String>,
+        xattrs: std::collections::BTreeMap<String, Vec<u8>>,
+    }
+
+    impl FileSystem for AttrFS {
+        fn name(&self) -> &str {
+            "attr-fs"
+        }
+
+        fn stat(&self, _path: &str) -> crate::Result<FileInfo> {
+            Ok(FileInfo::file("f", 0, 0o644))
+        }
+
+        fn readdir(&self, _path: &str) -> crate::Result<Vec<FileInfo>> {
+            Ok(vec![])
+        }
+
+        fn symlink(&mut self, target: &str, link_path: &str) -> crate::Result<()> {
+            self.links.insert(link_path.to_string(), target.to_string());
+            Ok(())
+        }
+
+        fn readlink(&self, path: &str) -> crate::Result<String> {
+            self.links.get(path).cloned().ok_or(Error::NotFound)
+        }
+
+        fn get_xattr(&self, _path: &str, name: &str) -> crate::Result<Vec<u8>> {
+            self.xattrs.get(name).cloned().ok_or(Error::NoAttribute)
+        }
+
+        fn set_xattr(&mut self, _path: &str, name: &str, value: &[u8]) -> crate::Result<()> {
+            self.xattrs.insert(name.to_string(), value.to_vec());
+            Ok(())
+        }
+
+        fn list_xattr(&self, _path: &str) -> crate::Result<Vec<String>> {
+            Ok(self.xattrs.keys().cloned().collect())
+        }
+    }
+
+    #[test]
+    fn test_symlink_and_xattr_exports() {
+        let plugin = Box::into_raw(Box::new(PluginWrapper::<AttrFS>::new())) as *mut c_void;
+        let path = CString::new("/f").unwrap();
+        let link = CString::new("/l").unwrap();
+        let mut err = FSErrorC { code: -1, message: ptr::null() };
+        let mut len = 0i64;
+
+        unsafe {
+            assert!(fs_symlink::<AttrFS>(plugin, path.as_ptr(), link.as_ptr()).is_null());
+            let target = fs_readlink::<AttrFS>(plugin, link.as_ptr(), &mut err);
+            assert_eq!(CStr::from_ptr(target).to_str().unwrap(), "/f");
+            free_string(target);
+
+            for (name, value) in [("user.b", &b"2"[..]), ("user.a", &b"\x001"[..])] {
+                let name = CString::new(name).unwrap();
+                let n = value.len() as c_int;
+                let res = fs_set_xattr::<AttrFS>(plugin, path.as_ptr(), name.as_ptr(), value.as_ptr() as *const c_char, n);
+                assert!(res.is_null());
+            }
+
+            let name = CString::new("user.a").unwrap();
+            let value = fs_get_xattr::<AttrFS>(plugin, path.as_ptr(), name.as_ptr(), &mut len, &mut err);
+            assert_eq!(std::slice::from_raw_parts(value as *const u8, len as usize), b"\x001");
+            free_byte_buffer(value);
+
+            let list = fs_list_xattr::<AttrFS>(plugin, path.as_ptr(), &mut len, &mut err);
+            assert_eq!(std::slice::from_raw_parts(list as *const u8, len as usize), b"user.a\0user.b\0");
+            free_byte_buffer(list);
+
+            let missing = CString::new("user.c").unwrap();
+            let value = fs_get_xattr::<AttrFS>(plugin, path.as_ptr(), missing.as_ptr(), &mut len, &mut err);
+            assert!(value.is_null());
+            assert_eq!(len, -1);
+            assert_eq!(err.code, Error::NoAttribute.code());
+            free_string(err.message);
+
+            drop(Box::from_raw(plugin as *mut PluginWrapper<AttrFS>));
+        }
+    }
+
+    /// Filesystem whose reads block until released, to show jobs run unlocked
+    #[derive(Default)]
+    struct SlowFS {
+        gate: Mutex<Option<std::sync::mpsc::Receiver<()>>>,
+        written: Vec<u8>,
+    }
+
+    impl FileSystem for SlowFS {
+        fn name(&self) -> &str {
+            "slow-fs"
+        }
+
+        fn stat(&self, _path: &str) -> crate::Result<FileInfo> {
+            Ok(FileInfo::file("f", self.written.len() as i64, 0o644))
+        }
+
+        fn readdir(&self, _path: &str) -> crate::Result<Vec<FileInfo>> {
+            Ok(vec![])
+        }
+
+        fn write(&mut self, _path: &str, data: &[u8], _offset: i64, _flags: WriteFlag) -> crate::Result<i64> {
+            self.written.extend_from_slice(data);
+            Ok(data.len() as i64)
+        }
+    }
+
+    impl AsyncFS for SlowFS {
+        fn read_job(&self, _path: &str, _offset: i64, _size: i64) -> crate::Result<Option<crate::Job<Vec<u8>>>> {
+            let gate = self.gate.lock().unwrap().take();
+            Ok(Some(Box::new(move |_| {
+                if let Some(gate) = gate {
+                    gate.recv().unwrap();
+                }
+                Ok(b"slow".to_vec())
+            })))
+        }
+    }
+
+    #[test]
+    fn test_async_read_runs_without_lock() {
+        let plugin = Box::into_raw(Box::new(PluginWrapper::<SlowFS>::new())) as *mut c_void;
+        let path = CString::new("/f").unwrap();
+        let mut err = FSErrorC { code: -1, message: ptr::null() };
+        let mut data: *const c_char = ptr::null();
+        let mut len = 0i64;
+
+        let (release, gate) = std::sync::mpsc::channel();
+        unsafe {
+            let wrapper = &*(plugin as *const PluginWrapper<SlowFS>);
+            *wrapper.read().gate.lock().unwrap() = Some(gate);
+        }
+
+        let op = fs_submit_read::<SlowFS>(plugin, path.as_ptr(), 0, -1, &mut err);
+        assert!(op > 0);
+
+        // A write needs the exclusive lock; it must not wait for the pending read
+        let n = fs_submit_write::<SlowFS>(plugin, path.as_ptr(), b"xy".as_ptr() as *const c_char, 2, 0, 0, &mut err);
+        assert_eq!(fs_poll::<SlowFS>(plugin, n, -1, &mut data, &mut len, &mut err), POLL_DONE);
+        assert_eq!(len, 2);
+
+        assert_eq!(fs_poll::<SlowFS>(plugin, op, 0, &mut data, &mut len, &mut err), POLL_PENDING);
+        release.send(()).unwrap();
+        assert_eq!(fs_poll::<SlowFS>(plugin, op, -1, &mut data, &mut len, &mut err), POLL_DONE);
+        assert_eq!(len, 4);
+        assert_eq!(err.code, 0);
+
+        unsafe {
+            assert_eq!(std::slice::from_raw_parts(data as *const u8, len as usize), b"slow");
+            free_byte_buffer(data);
+        }
+
+        // Results are collected once
+        assert_eq!(fs_poll::<SlowFS>(plugin, op, 0, &mut data, &mut len, &mut err), POLL_UNKNOWN);
+        assert_eq!(err.code, Error::InvalidInput(String::new()).code());
+
+        unsafe {
+            free_string(err.message);
+            drop(Box::from_raw(plugin as *mut PluginWrapper<SlowFS>));
+        }
+    }
+
+    /// A plugin exported without listing any optional feature
+    mod plain {
+        use crate::{FileInfo, ReadOnlyFileSystem, Result};
+
+        #[derive(Default)]
+        pub struct Plain;
+
+        impl ReadOnlyFileSystem for Plain {
+            fn name(&self) -> &str {
+                "plain"
+            }
+
+            fn read(&self, _path: &str, _offset: i64, _size: i64) -> Result<Vec<u8>> {
+                Ok(Vec::new())
+            }
+
+            fn stat(&self, _path: &str) -> Result<FileInfo> {
+                Ok(FileInfo::dir("/", 0o755))
+            }
+
+            fn readdir(&self, _path: &str) -> Result<Vec<FileInfo>> {
+                Ok(Vec::new())
+            }
+        }
+
+        crate::export_plugin!(Plain);
+    }
+
+    #[test]
+    fn test_features_are_opt_in() {
+        let features = plain::PluginFeatures();
+        assert_eq!(features, BASE_FEATURES);
+        for feature in [FEATURE_XATTR, FEATURE_SYMLINK, FEATURE_SESSIONS, FEATURE_STATE, FEATURE_WARMUP, FEATURE_ASYNC] {
+            assert_eq!(features & feature, 0, "{:#x}", feature);
+        }
+
+        let listed = crate::export_plugin!(@feature xattr) | crate::export_plugin!(@feature sessions);
+        assert_eq!(listed, FEATURE_XATTR | FEATURE_SESSIONS);
+        assert_eq!(crate::export_plugin!(@feature async), FEATURE_ASYNC | FEATURE_CANCEL);
+    }
+}
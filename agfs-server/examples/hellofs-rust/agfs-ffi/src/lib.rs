@@ -9,6 +9,7 @@
 //! - Automatic FFI bindings generation
 //! - Memory-safe FFI boundary layer
 //! - Comprehensive error handling
+//! - Logging through the host via the `log` macros
 //! - Built-in testing support
 //!
 //! ## Example
@@ -24,8 +25,8 @@
 //!         "my-fs"
 //!     }
 //!
-//!     fn read(&self, _path: &str, _offset: i64, _size: i64) -> Result<String> {
-//!         Ok("Hello, World!".to_string())
+//!     fn read(&self, _path: &str, _offset: i64, _size: i64) -> Result<Vec<u8>> {
+//!         Ok(b"Hello, World!".to_vec())
 //!     }
 //!
 //!     fn stat(&self, _path: &str) -> Result<FileInfo> {
@@ -41,23 +42,71 @@
 //! // export_plugin!(MyFS);
 //! ```
 
+pub mod async_fs;
 pub mod error;
 pub mod ffi;
 pub mod filesystem;
+pub mod logging;
+pub mod prefetch;
 pub mod types;
 
+// Re-export log so plugins can log through the host without a direct dependency
+pub use log;
+// Re-export serde_json for building file metadata
+pub use serde_json;
+
 /// Prelude module for convenient imports
 pub mod prelude {
-    pub use crate::error::{FileSystemError, Result};
-    pub use crate::filesystem::FileSystem;
-    pub use crate::types::{FileInfo, FileMetadata, WriteFlag};
+    pub use crate::async_fs::{AsyncFS, Job};
+    pub use agfs_core::cancel::CancellationToken;
+    pub use crate::error::{Error, FileSystemError, Result};
+    pub use crate::filesystem::{FileSystem, HandleFS, ReadOnlyFileSystem};
+    pub use crate::types::{
+        Advice, Config, ConfigParameter, FileInfo, FileKind, FsOp, FsSchema, Listing, MetaData, OpenFlag, PathSchema,
+        RangeLock, WarmupProgress, WriteFlag,
+    };
+    pub use crate::export_handle_plugin;
     pub use crate::export_plugin;
 }
 
 // Re-export main types
-pub use error::{FileSystemError, Result};
-pub use filesystem::FileSystem;
-pub use types::{FileInfo, FileMetadata, WriteFlag};
+pub use agfs_core::breaker::{self, CircuitBreaker, CircuitState};
+pub use agfs_core::buffer::WriteBuffer;
+pub use agfs_core::archive::{self, ArchiveView, ExportScheduler};
+pub use agfs_core::cache::{self, CachedFs, NegativeCache, RenderCache};
+pub use agfs_core::encoding;
+pub use agfs_core::html2md;
+pub use agfs_core::inode::InodeMap;
+pub use agfs_core::namer::{self, UniqueNamer};
+pub use agfs_core::mime;
+pub use agfs_core::normalize::{self, NormalizeFs};
+pub use agfs_core::paginate::{self, Page, Paginator};
+pub use agfs_core::cancel::CancellationToken;
+pub use agfs_core::conflict::{self, ConflictPolicy, ConflictTracker};
+pub use agfs_core::control::{self, ControlFs};
+pub use agfs_core::diff::{self, DiffFs};
+pub use agfs_core::expiry::{self, ExpiryFs};
+pub use agfs_core::fifo::{self, FifoFiles, FifoFs, Fifos};
+pub use agfs_core::frontmatter::{self, FrontMatter};
+pub use agfs_core::hidden::{self, HiddenFs};
+pub use agfs_core::policy::PolicyFs;
+pub use agfs_core::ratelimit::{self, RateLimitGuard};
+pub use agfs_core::eprintln;
+pub use agfs_core::redact;
+pub use agfs_core::retry::{self, RetryPolicy};
+pub use agfs_core::table::{self, Table};
+pub use agfs_core::template::{self, Template};
+pub use agfs_core::time;
+pub use agfs_core::vector::{self, VectorIndex};
+pub use agfs_core::verify::{self, VerifyFs};
+pub use async_fs::{AsyncFS, Job};
+pub use error::{Error, FileSystemError, Result};
+pub use filesystem::{FileHandle, FileSystem, HandleFS, ReadOnlyFileSystem};
+pub use prefetch::Prefetcher;
+pub use types::{
+    Advice, Config, ConfigParameter, FileInfo, FileKind, FsOp, FsSchema, Listing, MetaData, OpenFlag, PathSchema,
+    RangeLock, WarmupProgress, WriteFlag, MODE_SYMLINK,
+};
 
 /// Macro to export a FileSystem implementation as a C-compatible plugin
 ///
@@ -74,14 +123,53 @@ pub use types::{FileInfo, FileMetadata, WriteFlag};
 ///
 /// export_plugin!(MyFS);
 /// ```
+///
+/// The generated `PluginABIVersion` and `PluginFeatures` exports let the host
+/// check which optional entry points exist before resolving them.
+///
+/// Optional capabilities the plugin actually implements are listed after the
+/// type, e.g. `export_plugin!(MyFS, xattr, symlink, sessions)`, and reported
+/// to the host in `PluginFeatures`; the host does not call the others. The
+/// names are `readdir_page`, `readdir_filtered`, `xattr`, `symlink`,
+/// `advise`, `read_if_changed`, `write_if`, `read_many`, `batch`,
+/// `sessions`, `maintain`, `warmup` and `state`.
+///
+/// Plugins implementing [`AsyncFS`] also list `async` to export
+/// `FSSubmitRead`, `FSSubmitWrite`, `FSPoll` and `FSCancel`.
+///
+/// The plugin is exported wrapped in [`ControlFs`], which adds the standard
+/// `/.agfs/` control files (see [`control`]).
 #[macro_export]
 macro_rules! export_plugin {
-    ($fs_type:ty) => {
+    (@features $fs_type:ty, $features:expr) => {
         use $crate::ffi::PluginWrapper;
-        use std::ffi::CString;
         use std::os::raw::{c_char, c_int, c_void};
         use std::ptr;
 
+        // The host calls exports from several threads
+        const _: fn() = || {
+            fn assert_impl<T: $crate::FileSystem + Default + Send + Sync>() {}
+            assert_impl::<$fs_type>();
+        };
+
+        /// Native C ABI version this plugin was built against
+        #[no_mangle]
+        pub extern "C" fn PluginABIVersion() -> u32 {
+            $crate::ffi::ABI_VERSION
+        }
+
+        /// Bitmask of `FEATURE_*` flags for the optional exports present
+        #[no_mangle]
+        pub extern "C" fn PluginFeatures() -> u64 {
+            $features
+        }
+
+        /// Route `log` records to the host (NULL stops forwarding)
+        #[no_mangle]
+        pub extern "C" fn PluginSetLogCallback(callback: Option<$crate::logging::LogCallback>) {
+            $crate::logging::set_callback(callback)
+        }
+
         #[no_mangle]
         pub extern "C" fn PluginNew() -> *mut c_void {
             let wrapper = Box::new(PluginWrapper::<$fs_type>::new());
@@ -108,6 +196,34 @@ macro_rules! export_plugin {
             }
         }
 
+        /// Free an error string returned by any export
+        #[no_mangle]
+        #[allow(clippy::not_unsafe_ptr_arg_deref)]
+        pub extern "C" fn PluginFreeString(s: *const c_char) {
+            unsafe { $crate::ffi::free_string(s) }
+        }
+
+        /// Free the data buffer returned by a successful `FSRead`
+        #[no_mangle]
+        #[allow(clippy::not_unsafe_ptr_arg_deref)]
+        pub extern "C" fn PluginFreeBuffer(buf: *const c_char) {
+            unsafe { $crate::ffi::free_byte_buffer(buf) }
+        }
+
+        /// Free a `FileInfoC` returned by `FSStat`
+        #[no_mangle]
+        #[allow(clippy::not_unsafe_ptr_arg_deref)]
+        pub extern "C" fn FSFreeFileInfo(info: *mut $crate::ffi::FileInfoC) {
+            unsafe { $crate::ffi::free_file_info(info) }
+        }
+
+        /// Free a `FileInfoArray` returned by `FSReadDir`, `FSReadDirPage` or `FSReadDirFiltered`
+        #[no_mangle]
+        #[allow(clippy::not_unsafe_ptr_arg_deref)]
+        pub extern "C" fn FSFreeFileInfoArray(array: *mut $crate::ffi::FileInfoArray) {
+            unsafe { $crate::ffi::free_file_info_array(array) }
+        }
+
         #[no_mangle]
         pub extern "C" fn PluginValidate(
             plugin: *mut c_void,
@@ -116,6 +232,12 @@ macro_rules! export_plugin {
             $crate::ffi::plugin_validate::<$fs_type>(plugin, config_json)
         }
 
+        /// Returns the config parameters as a JSON array (free with `PluginFreeString`)
+        #[no_mangle]
+        pub extern "C" fn PluginGetConfigParams(plugin: *mut c_void) -> *const c_char {
+            $crate::ffi::plugin_get_config_params::<$fs_type>(plugin)
+        }
+
         #[no_mangle]
         pub extern "C" fn PluginInitialize(
             plugin: *mut c_void,
@@ -140,23 +262,29 @@ macro_rules! export_plugin {
             }
         }
 
+        /// Read file contents into a buffer of `*out_len` bytes, which may hold
+        /// NUL bytes (release with `PluginFreeBuffer`)
+        /// On error `out_len` is -1, the return value is NULL and `out_err`
+        /// describes the failure
         #[no_mangle]
         pub extern "C" fn FSRead(
             plugin: *mut c_void,
             path: *const c_char,
             offset: i64,
             size: i64,
-            out_len: *mut c_int,
+            out_len: *mut i64,
+            out_err: *mut $crate::ffi::FSErrorC,
         ) -> *const c_char {
-            $crate::ffi::fs_read::<$fs_type>(plugin, path, offset, size, out_len)
+            $crate::ffi::fs_read::<$fs_type>(plugin, path, offset, size, out_len, out_err)
         }
 
         #[no_mangle]
         pub extern "C" fn FSStat(
             plugin: *mut c_void,
             path: *const c_char,
+            out_err: *mut $crate::ffi::FSErrorC,
         ) -> *mut $crate::ffi::FileInfoC {
-            $crate::ffi::fs_stat::<$fs_type>(plugin, path)
+            $crate::ffi::fs_stat::<$fs_type>(plugin, path, out_err)
         }
 
         #[no_mangle]
@@ -164,8 +292,33 @@ macro_rules! export_plugin {
             plugin: *mut c_void,
             path: *const c_char,
             out_count: *mut c_int,
+            out_err: *mut $crate::ffi::FSErrorC,
+        ) -> *mut $crate::ffi::FileInfoArray {
+            $crate::ffi::fs_readdir::<$fs_type>(plugin, path, out_count, out_err)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn FSReadDirPage(
+            plugin: *mut c_void,
+            path: *const c_char,
+            offset: i64,
+            limit: i64,
+            out_count: *mut c_int,
+            out_err: *mut $crate::ffi::FSErrorC,
+        ) -> *mut $crate::ffi::FileInfoArray {
+            $crate::ffi::fs_readdir_page::<$fs_type>(plugin, path, offset, limit, out_count, out_err)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn FSReadDirFiltered(
+            plugin: *mut c_void,
+            path: *const c_char,
+            glob: *const c_char,
+            limit: i64,
+            out_count: *mut c_int,
+            out_err: *mut $crate::ffi::FSErrorC,
         ) -> *mut $crate::ffi::FileInfoArray {
-            $crate::ffi::fs_readdir::<$fs_type>(plugin, path, out_count)
+            $crate::ffi::fs_readdir_filtered::<$fs_type>(plugin, path, glob, limit, out_count, out_err)
         }
 
         #[no_mangle]
@@ -196,7 +349,7 @@ macro_rules! export_plugin {
         }
 
         /// Write to file with offset and flags
-        /// Returns i64: positive = bytes written, negative = error
+        /// Returns bytes written, or -1 with `out_err` describing the failure
         #[no_mangle]
         pub extern "C" fn FSWrite(
             plugin: *mut c_void,
@@ -205,8 +358,9 @@ macro_rules! export_plugin {
             data_len: c_int,
             offset: i64,
             flags: u32,
+            out_err: *mut $crate::ffi::FSErrorC,
         ) -> i64 {
-            $crate::ffi::fs_write::<$fs_type>(plugin, path, data, data_len, offset, flags)
+            $crate::ffi::fs_write::<$fs_type>(plugin, path, data, data_len, offset, flags, out_err)
         }
 
         #[no_mangle]
@@ -226,5 +380,363 @@ macro_rules! export_plugin {
         ) -> *const c_char {
             $crate::ffi::fs_chmod::<$fs_type>(plugin, path, mode)
         }
+
+        #[no_mangle]
+        pub extern "C" fn FSSymlink(
+            plugin: *mut c_void,
+            target: *const c_char,
+            link_path: *const c_char,
+        ) -> *const c_char {
+            $crate::ffi::fs_symlink::<$fs_type>(plugin, target, link_path)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn FSReadlink(
+            plugin: *mut c_void,
+            path: *const c_char,
+            out_err: *mut $crate::ffi::FSErrorC,
+        ) -> *const c_char {
+            $crate::ffi::fs_readlink::<$fs_type>(plugin, path, out_err)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn FSGetXattr(
+            plugin: *mut c_void,
+            path: *const c_char,
+            name: *const c_char,
+            out_len: *mut i64,
+            out_err: *mut $crate::ffi::FSErrorC,
+        ) -> *const c_char {
+            $crate::ffi::fs_get_xattr::<$fs_type>(plugin, path, name, out_len, out_err)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn FSSetXattr(
+            plugin: *mut c_void,
+            path: *const c_char,
+            name: *const c_char,
+            value: *const c_char,
+            value_len: c_int,
+        ) -> *const c_char {
+            $crate::ffi::fs_set_xattr::<$fs_type>(plugin, path, name, value, value_len)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn FSListXattr(
+            plugin: *mut c_void,
+            path: *const c_char,
+            out_len: *mut i64,
+            out_err: *mut $crate::ffi::FSErrorC,
+        ) -> *const c_char {
+            $crate::ffi::fs_list_xattr::<$fs_type>(plugin, path, out_len, out_err)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn FSAdvise(
+            plugin: *mut c_void,
+            path: *const c_char,
+            offset: i64,
+            len: i64,
+            advice: c_int,
+        ) -> *const c_char {
+            $crate::ffi::fs_advise::<$fs_type>(plugin, path, offset, len, advice)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn FSReadIfChanged(
+            plugin: *mut c_void,
+            path: *const c_char,
+            etag: *const c_char,
+            out_len: *mut i64,
+            out_err: *mut $crate::ffi::FSErrorC,
+        ) -> *const c_char {
+            $crate::ffi::fs_read_if_changed::<$fs_type>(plugin, path, etag, out_len, out_err)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn FSReadMany(
+            plugin: *mut c_void,
+            paths: *const c_char,
+            paths_len: i64,
+            out_results: *mut $crate::ffi::ReadResultC,
+            max_results: c_int,
+            out_err: *mut $crate::ffi::FSErrorC,
+        ) -> c_int {
+            $crate::ffi::fs_read_many::<$fs_type>(plugin, paths, paths_len, out_results, max_results, out_err)
+        }
+
+        /// Returns a JSON array of per-operation results (free with `PluginFreeString`)
+        #[no_mangle]
+        pub extern "C" fn FSBatch(
+            plugin: *mut c_void,
+            ops_json: *const c_char,
+            out_err: *mut $crate::ffi::FSErrorC,
+        ) -> *const c_char {
+            $crate::ffi::fs_batch::<$fs_type>(plugin, ops_json, out_err)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn FSAtomicBatch(plugin: *mut c_void) -> c_int {
+            $crate::ffi::fs_atomic_batch::<$fs_type>(plugin)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn FSBeginSession(plugin: *mut c_void, out_err: *mut $crate::ffi::FSErrorC) -> c_int {
+            $crate::ffi::fs_begin_session::<$fs_type>(plugin, out_err)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn FSCommitSession(
+            plugin: *mut c_void,
+            message: *const c_char,
+            out_err: *mut $crate::ffi::FSErrorC,
+        ) -> c_int {
+            $crate::ffi::fs_commit_session::<$fs_type>(plugin, message, out_err)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn FSAbortSession(plugin: *mut c_void, out_err: *mut $crate::ffi::FSErrorC) -> c_int {
+            $crate::ffi::fs_abort_session::<$fs_type>(plugin, out_err)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn FSMaintain(plugin: *mut c_void, out_err: *mut $crate::ffi::FSErrorC) -> c_int {
+            $crate::ffi::fs_maintain::<$fs_type>(plugin, out_err)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn FSWarmup(
+            plugin: *mut c_void,
+            budget_ms: i64,
+            out_done: *mut u64,
+            out_total: *mut u64,
+            out_err: *mut $crate::ffi::FSErrorC,
+        ) -> c_int {
+            $crate::ffi::fs_warmup::<$fs_type>(plugin, budget_ms, out_done, out_total, out_err)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn FSExportState(
+            plugin: *mut c_void,
+            out_len: *mut i64,
+            out_err: *mut $crate::ffi::FSErrorC,
+        ) -> *const c_char {
+            $crate::ffi::fs_export_state::<$fs_type>(plugin, out_len, out_err)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn FSImportState(
+            plugin: *mut c_void,
+            state: *const u8,
+            state_len: i64,
+            out_err: *mut $crate::ffi::FSErrorC,
+        ) -> c_int {
+            $crate::ffi::fs_import_state::<$fs_type>(plugin, state, state_len, out_err)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn FSWriteIf(
+            plugin: *mut c_void,
+            path: *const c_char,
+            data: *const c_char,
+            data_len: c_int,
+            etag: *const c_char,
+            out_err: *mut $crate::ffi::FSErrorC,
+        ) -> i64 {
+            $crate::ffi::fs_write_if::<$fs_type>(plugin, path, data, data_len, etag, out_err)
+        }
+    };
+    (@async $fs_type:ty) => {
+        #[no_mangle]
+        pub extern "C" fn FSSubmitRead(
+            plugin: *mut c_void,
+            path: *const c_char,
+            offset: i64,
+            size: i64,
+            out_err: *mut $crate::ffi::FSErrorC,
+        ) -> i64 {
+            $crate::ffi::fs_submit_read::<$fs_type>(plugin, path, offset, size, out_err)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn FSSubmitWrite(
+            plugin: *mut c_void,
+            path: *const c_char,
+            data: *const c_char,
+            data_len: c_int,
+            offset: i64,
+            flags: u32,
+            out_err: *mut $crate::ffi::FSErrorC,
+        ) -> i64 {
+            $crate::ffi::fs_submit_write::<$fs_type>(plugin, path, data, data_len, offset, flags, out_err)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn FSPoll(
+            plugin: *mut c_void,
+            op: i64,
+            timeout_ms: i64,
+            out_data: *mut *const c_char,
+            out_len: *mut i64,
+            out_err: *mut $crate::ffi::FSErrorC,
+        ) -> c_int {
+            $crate::ffi::fs_poll::<$fs_type>(plugin, op, timeout_ms, out_data, out_len, out_err)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn FSCancel(plugin: *mut c_void, op: i64) -> c_int {
+            $crate::ffi::fs_cancel::<$fs_type>(plugin, op)
+        }
+    };
+    (@feature async) => { $crate::ffi::FEATURE_ASYNC | $crate::ffi::FEATURE_CANCEL };
+    (@feature readdir_page) => { $crate::ffi::FEATURE_READDIR_PAGE };
+    (@feature readdir_filtered) => { $crate::ffi::FEATURE_READDIR_FILTERED };
+    (@feature xattr) => { $crate::ffi::FEATURE_XATTR };
+    (@feature symlink) => { $crate::ffi::FEATURE_SYMLINK };
+    (@feature advise) => { $crate::ffi::FEATURE_ADVISE };
+    (@feature read_if_changed) => { $crate::ffi::FEATURE_READ_IF_CHANGED };
+    (@feature write_if) => { $crate::ffi::FEATURE_WRITE_IF };
+    (@feature read_many) => { $crate::ffi::FEATURE_READ_MANY };
+    (@feature batch) => { $crate::ffi::FEATURE_BATCH };
+    (@feature sessions) => { $crate::ffi::FEATURE_SESSIONS };
+    (@feature maintain) => { $crate::ffi::FEATURE_MAINTAIN };
+    (@feature warmup) => { $crate::ffi::FEATURE_WARMUP };
+    (@feature state) => { $crate::ffi::FEATURE_STATE };
+    // Exports beyond the common ones that a feature needs
+    (@extra async $fs_type:ty) => {
+        $crate::export_plugin!(@async $fs_type);
+    };
+    (@extra $feature:ident $fs_type:ty) => {};
+    ($fs_type:ty $(, $feature:ident)* $(,)?) => {
+        $crate::export_plugin!(
+            @features $crate::ControlFs<$fs_type>,
+            $crate::ffi::BASE_FEATURES $(| $crate::export_plugin!(@feature $feature))*
+        );
+        $($crate::export_plugin!(@extra $feature $crate::ControlFs<$fs_type>);)*
+    };
+}
+
+/// Macro to export a HandleFS implementation as a C-compatible plugin
+///
+/// Generates everything `export_plugin!` does plus the `Handle*` entry points
+/// for stateful opens. Every `Handle*` export returns NULL on success or an
+/// error string (free with `PluginFreeString`); results are written through
+/// the trailing out-parameters.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use agfs_ffi::prelude::*;
+///
+/// #[derive(Default)]
+/// struct MyFS;
+/// impl FileSystem for MyFS { /* ... */ }
+/// impl HandleFS for MyFS { /* ... */ }
+///
+/// export_handle_plugin!(MyFS);
+/// ```
+///
+/// Optional capabilities are listed after the type as for `export_plugin!`,
+/// e.g. `export_handle_plugin!(MyFS, async, xattr)`.
+#[macro_export]
+macro_rules! export_handle_plugin {
+    (@handles $fs_type:ty) => {
+
+        #[no_mangle]
+        pub extern "C" fn HandleOpen(
+            plugin: *mut c_void,
+            path: *const c_char,
+            flags: u32,
+            mode: u32,
+            out_id: *mut i64,
+        ) -> *const c_char {
+            $crate::ffi::handle_open::<$fs_type>(plugin, path, flags, mode, out_id)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn HandleRead(
+            plugin: *mut c_void,
+            id: i64,
+            buf: *mut u8,
+            buf_len: i64,
+            out_n: *mut i64,
+        ) -> *const c_char {
+            $crate::ffi::handle_read::<$fs_type>(plugin, id, buf, buf_len, out_n)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn HandleReadAt(
+            plugin: *mut c_void,
+            id: i64,
+            buf: *mut u8,
+            buf_len: i64,
+            offset: i64,
+            out_n: *mut i64,
+        ) -> *const c_char {
+            $crate::ffi::handle_read_at::<$fs_type>(plugin, id, buf, buf_len, offset, out_n)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn HandleWrite(
+            plugin: *mut c_void,
+            id: i64,
+            data: *const u8,
+            data_len: i64,
+            out_n: *mut i64,
+        ) -> *const c_char {
+            $crate::ffi::handle_write::<$fs_type>(plugin, id, data, data_len, out_n)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn HandleWriteAt(
+            plugin: *mut c_void,
+            id: i64,
+            data: *const u8,
+            data_len: i64,
+            offset: i64,
+            out_n: *mut i64,
+        ) -> *const c_char {
+            $crate::ffi::handle_write_at::<$fs_type>(plugin, id, data, data_len, offset, out_n)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn HandleSeek(
+            plugin: *mut c_void,
+            id: i64,
+            offset: i64,
+            whence: i32,
+            out_pos: *mut i64,
+        ) -> *const c_char {
+            $crate::ffi::handle_seek::<$fs_type>(plugin, id, offset, whence, out_pos)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn HandleSync(plugin: *mut c_void, id: i64) -> *const c_char {
+            $crate::ffi::handle_sync::<$fs_type>(plugin, id)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn HandleStat(
+            plugin: *mut c_void,
+            id: i64,
+            out_info: *mut *mut $crate::ffi::FileInfoC,
+        ) -> *const c_char {
+            $crate::ffi::handle_stat::<$fs_type>(plugin, id, out_info)
+        }
+
+        #[no_mangle]
+        pub extern "C" fn HandleClose(plugin: *mut c_void, id: i64) -> *const c_char {
+            $crate::ffi::handle_close::<$fs_type>(plugin, id)
+        }
+    };
+    ($fs_type:ty $(, $feature:ident)* $(,)?) => {
+        $crate::export_plugin!(
+            @features $crate::ControlFs<$fs_type>,
+            $crate::ffi::BASE_FEATURES
+                | $crate::ffi::FEATURE_HANDLES
+                $(| $crate::export_plugin!(@feature $feature))*
+        );
+        $($crate::export_plugin!(@extra $feature $crate::ControlFs<$fs_type>);)*
+        $crate::export_handle_plugin!(@handles $crate::ControlFs<$fs_type>);
     };
 }
@@ -0,0 +1,90 @@
+//! Host logging for native plugins
+//!
+//! Plugins log with the standard `log` macros (re-exported as
+//! `agfs_ffi::log`). Once the host registers a callback through
+//! `PluginSetLogCallback`, records are forwarded to it so they end up in the
+//! server's log instead of interleaving with its output on stderr. Messages
+//! pass through [`redact`] on the way out.
+
+use agfs_core::redact::redact;
+use std::ffi::CString;
+use std::os::raw::{c_char, c_int};
+use std::sync::{PoisonError, RwLock};
+
+/// Host log sink
+///
+/// `level` follows `log::Level` (1 = error, 2 = warn, 3 = info, 4 = debug,
+/// 5 = trace). `message` is only valid for the duration of the call.
+pub type LogCallback = extern "C" fn(level: c_int, message: *const c_char);
+
+static CALLBACK: RwLock<Option<LogCallback>> = RwLock::new(None);
+
+static LOGGER: HostLogger = HostLogger;
+
+struct HostLogger;
+
+impl ::log::Log for HostLogger {
+    fn enabled(&self, _metadata: &::log::Metadata) -> bool {
+        callback().is_some()
+    }
+
+    fn log(&self, record: &::log::Record) {
+        if let Some(callback) = callback() {
+            let message = redact(&record.args().to_string()).replace('\0', "\\0");
+            let message = CString::new(message).expect("NUL bytes were escaped");
+            callback(record.level() as c_int, message.as_ptr());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+fn callback() -> Option<LogCallback> {
+    *CALLBACK.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Register the host callback (`None` stops forwarding)
+///
+/// The first registration installs the SDK logger. If the plugin already set
+/// its own `log` implementation, that one is kept and the callback goes unused.
+pub fn set_callback(callback: Option<LogCallback>) {
+    *CALLBACK.write().unwrap_or_else(PoisonError::into_inner) = callback;
+    if callback.is_some() && ::log::set_logger(&LOGGER).is_ok() {
+        ::log::set_max_level(::log::LevelFilter::Trace);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+    use std::sync::Mutex;
+
+    static RECORDS: Mutex<Vec<(c_int, String)>> = Mutex::new(Vec::new());
+
+    extern "C" fn record(level: c_int, message: *const c_char) {
+        let message = unsafe { CStr::from_ptr(message) }.to_string_lossy().into_owned();
+        RECORDS.lock().unwrap().push((level, message));
+    }
+
+    #[test]
+    fn test_forwards_to_host_callback() {
+        ::log::info!("before callback");
+        set_callback(Some(record));
+        ::log::warn!("mounted {}", "/data");
+        ::log::error!("bad\0byte");
+        ::log::info!("auth header Bearer abc123");
+        set_callback(None);
+        ::log::info!("after callback");
+
+        let records = RECORDS.lock().unwrap();
+        assert_eq!(
+            *records,
+            vec![
+                (2, "mounted /data".to_string()),
+                (1, "bad\\0byte".to_string()),
+                (3, "auth header Bearer [REDACTED]".to_string()),
+            ]
+        );
+    }
+}
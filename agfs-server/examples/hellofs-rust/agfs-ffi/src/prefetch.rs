@@ -0,0 +1,299 @@
+//! Read-ahead for handles over slow storage
+//!
+//! Sequential `cat` of a remote file pays one round trip per host read. A
+//! [`Prefetcher`] watches the offsets a handle is read at and, once the
+//! pattern is sequential, fetches the following chunks on worker threads so
+//! later reads are served from memory.
+
+use crate::error::Result;
+use crate::types::Advice;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread;
+
+/// Sequential reads in a row before read-ahead starts
+const SEQUENTIAL_THRESHOLD: u32 = 2;
+
+type Chunk = Arc<(Mutex<Option<Result<Vec<u8>>>>, Condvar)>;
+
+/// Read pattern and fetched chunks of one handle
+struct Stream {
+    next: i64,
+    run: u32,
+    advice: Advice,
+    chunks: BTreeMap<i64, Chunk>,
+}
+
+impl Default for Stream {
+    fn default() -> Self {
+        Self {
+            next: 0,
+            run: 0,
+            advice: Advice::Normal,
+            chunks: BTreeMap::new(),
+        }
+    }
+}
+
+/// Detects sequential reads per handle and fetches ahead in the background
+///
+/// Route `handle_read_at` (and `handle_read`, passing the handle's cursor)
+/// through [`Prefetcher::read_at`] with a closure that fetches a byte range
+/// from the backend, and call [`Prefetcher::forget`] from `close_handle`:
+///
+/// ```ignore
+/// fn handle_read_at(&self, id: i64, buf: &mut [u8], offset: i64) -> Result<usize> {
+///     let (client, path) = (self.client.clone(), self.path_of(id)?);
+///     self.prefetch.read_at(id, buf, offset, move |off, len| client.get_range(&path, off, len))
+/// }
+/// ```
+///
+/// The fetch closure returns fewer bytes than asked only at end of file.
+pub struct Prefetcher {
+    chunk_size: usize,
+    depth: usize,
+    streams: Mutex<HashMap<i64, Stream>>,
+}
+
+fn wait(chunk: &Chunk) -> MutexGuard<'_, Option<Result<Vec<u8>>>> {
+    let (done, cond) = &**chunk;
+    let mut result = done.lock().unwrap_or_else(PoisonError::into_inner);
+    while result.is_none() {
+        result = cond.wait(result).unwrap_or_else(PoisonError::into_inner);
+    }
+    result
+}
+
+impl Prefetcher {
+    /// Fetch up to `depth` chunks of `chunk_size` bytes ahead of the reader
+    pub fn new(chunk_size: usize, depth: usize) -> Self {
+        Self {
+            chunk_size: chunk_size.max(1),
+            depth,
+            streams: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn streams(&self) -> MutexGuard<'_, HashMap<i64, Stream>> {
+        self.streams.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Override pattern detection for handle `id`
+    ///
+    /// `Sequential` and `WillNeed` read ahead immediately, `Random` and
+    /// `DontNeed` disable read-ahead and drop fetched chunks, `Normal`
+    /// restores detection.
+    pub fn advise(&self, id: i64, advice: Advice) {
+        let mut streams = self.streams();
+        let stream = streams.entry(id).or_default();
+        stream.advice = advice;
+        if matches!(advice, Advice::Random | Advice::DontNeed) {
+            stream.chunks.clear();
+        }
+    }
+
+    /// Drop all state for handle `id`
+    pub fn forget(&self, id: i64) {
+        self.streams().remove(&id);
+    }
+
+    /// Fetched (or in-flight) chunk covering `pos`
+    fn buffered(&self, id: i64, pos: i64) -> Option<(i64, Chunk)> {
+        let streams = self.streams();
+        let (&start, chunk) = streams.get(&id)?.chunks.range(..=pos).next_back()?;
+        (pos < start + self.chunk_size as i64).then(|| (start, chunk.clone()))
+    }
+
+    /// Read into `buf` at `offset`, from fetched chunks where possible
+    ///
+    /// Whatever the chunks do not cover is fetched synchronously with `fetch`.
+    pub fn read_at<F>(&self, id: i64, buf: &mut [u8], offset: i64, fetch: F) -> Result<usize>
+    where
+        F: Fn(i64, usize) -> Result<Vec<u8>> + Clone + Send + 'static,
+    {
+        let mut filled = 0;
+        let mut eof = false;
+        while filled < buf.len() {
+            let pos = offset + filled as i64;
+            let Some((start, chunk)) = self.buffered(id, pos) else {
+                break;
+            };
+            let result = wait(&chunk);
+            let Some(Ok(data)) = result.as_ref() else {
+                // Failed read-ahead; retry the range synchronously
+                drop(result);
+                if let Some(stream) = self.streams().get_mut(&id) {
+                    stream.chunks.remove(&start);
+                }
+                break;
+            };
+
+            let within = (pos - start) as usize;
+            let n = data.len().saturating_sub(within).min(buf.len() - filled);
+            buf[filled..filled + n].copy_from_slice(&data[within..within + n]);
+            filled += n;
+            if data.len() < self.chunk_size && within + n == data.len() {
+                eof = true;
+                break;
+            }
+        }
+
+        if filled < buf.len() && !eof {
+            match fetch(offset + filled as i64, buf.len() - filled) {
+                Ok(data) => {
+                    let n = data.len().min(buf.len() - filled);
+                    buf[filled..filled + n].copy_from_slice(&data[..n]);
+                    filled += n;
+                }
+                Err(e) if filled == 0 => return Err(e),
+                Err(_) => {}
+            }
+        }
+
+        self.record(id, offset, filled, fetch);
+        Ok(filled)
+    }
+
+    /// Update the read pattern of `id` and start read-ahead if it is sequential
+    fn record<F>(&self, id: i64, offset: i64, n: usize, fetch: F)
+    where
+        F: Fn(i64, usize) -> Result<Vec<u8>> + Clone + Send + 'static,
+    {
+        let chunk_size = self.chunk_size as i64;
+        let mut streams = self.streams();
+        let stream = streams.entry(id).or_default();
+
+        if offset == stream.next {
+            stream.run += 1;
+        } else {
+            stream.run = 1;
+            stream.chunks.clear();
+        }
+        stream.next = offset + n as i64;
+
+        let next = stream.next;
+        stream.chunks.retain(|&start, _| start + chunk_size > next);
+
+        let enabled = match stream.advice {
+            Advice::Sequential | Advice::WillNeed => true,
+            Advice::Random | Advice::DontNeed => false,
+            Advice::Normal => stream.run >= SEQUENTIAL_THRESHOLD,
+        };
+        if n == 0 || !enabled {
+            return;
+        }
+
+        let mut start = stream
+            .chunks
+            .last_key_value()
+            .map_or(next, |(&last, _)| last + chunk_size);
+        while stream.chunks.len() < self.depth {
+            let chunk: Chunk = Arc::new((Mutex::new(None), Condvar::new()));
+            stream.chunks.insert(start, chunk.clone());
+
+            let fetch = fetch.clone();
+            let len = self.chunk_size;
+            thread::spawn(move || {
+                let result = fetch(start, len);
+                let (done, cond) = &*chunk;
+                *done.lock().unwrap_or_else(PoisonError::into_inner) = Some(result);
+                cond.notify_all();
+            });
+            start += chunk_size;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+
+    type Calls = Arc<Mutex<Vec<(i64, usize)>>>;
+
+    /// Fetcher over `0..100` that records the ranges it was asked for
+    fn source() -> (Calls, impl Fn(i64, usize) -> Result<Vec<u8>> + Clone + Send + 'static) {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let log = calls.clone();
+        let fetch = move |offset: i64, len: usize| {
+            log.lock().unwrap().push((offset, len));
+            let data: Vec<u8> = (0..100).collect();
+            let start = (offset as usize).min(data.len());
+            let end = (start + len).min(data.len());
+            Ok(data[start..end].to_vec())
+        };
+        (calls, fetch)
+    }
+
+    #[test]
+    fn test_sequential_reads_prefetch() {
+        let prefetcher = Prefetcher::new(16, 2);
+        let (calls, fetch) = source();
+
+        let mut out = Vec::new();
+        let mut buf = [0u8; 6];
+        loop {
+            let n = prefetcher.read_at(1, &mut buf, out.len() as i64, fetch.clone()).unwrap();
+            if n == 0 {
+                break;
+            }
+            out.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(out, (0..100).collect::<Vec<u8>>());
+
+        // Most of the file came from chunk-sized read-ahead
+        let calls = calls.lock().unwrap();
+        assert!(calls.iter().filter(|&&(_, len)| len == 16).count() >= 4);
+        assert!(calls.iter().filter(|&&(_, len)| len != 16).count() < 17);
+    }
+
+    #[test]
+    fn test_random_reads_do_not_prefetch() {
+        let prefetcher = Prefetcher::new(16, 2);
+        let (calls, fetch) = source();
+
+        let mut buf = [0u8; 4];
+        for offset in [50, 10, 90, 30] {
+            assert_eq!(prefetcher.read_at(1, &mut buf, offset, fetch.clone()).unwrap(), 4);
+            assert_eq!(buf[0] as i64, offset);
+        }
+        assert!(calls.lock().unwrap().iter().all(|&(_, len)| len == 4));
+    }
+
+    #[test]
+    fn test_advice_overrides_detection() {
+        let prefetcher = Prefetcher::new(16, 1);
+        let (calls, fetch) = source();
+        let mut buf = [0u8; 4];
+
+        prefetcher.advise(1, Advice::Sequential);
+        prefetcher.read_at(1, &mut buf, 40, fetch.clone()).unwrap();
+        prefetcher.read_at(1, &mut buf, 44, fetch.clone()).unwrap();
+        assert_eq!(buf, [44, 45, 46, 47]);
+        assert!(calls.lock().unwrap().contains(&(44, 16)));
+
+        prefetcher.advise(2, Advice::Random);
+        for offset in [0, 4, 8] {
+            prefetcher.read_at(2, &mut buf, offset, fetch.clone()).unwrap();
+        }
+        assert!(!calls.lock().unwrap().iter().any(|&(offset, len)| offset < 40 && len == 16));
+    }
+
+    #[test]
+    fn test_failed_prefetch_falls_back() {
+        let prefetcher = Prefetcher::new(8, 1);
+        let fetch = |offset: i64, len: usize| {
+            if len == 8 {
+                Err(Error::Io("timeout".to_string()))
+            } else {
+                Ok(vec![offset as u8; len])
+            }
+        };
+
+        let mut buf = [0u8; 2];
+        for offset in [0, 2, 4] {
+            assert_eq!(prefetcher.read_at(1, &mut buf, offset, fetch).unwrap(), 2);
+            assert_eq!(buf, [offset as u8; 2]);
+        }
+    }
+}
@@ -4,6 +4,7 @@
 //! to create a filesystem plugin with minimal boilerplate.
 
 use agfs_ffi::prelude::*;
+use agfs_ffi::serde_json::json;
 
 /// HelloFS - A simple read-only filesystem with a single file
 #[derive(Default)]
@@ -26,15 +27,15 @@ A simple read-only filesystem plugin written in Rust using the AGFS Plugin SDK.
 "#
     }
 
-    fn read(&self, path: &str, offset: i64, size: i64) -> Result<String> {
+    fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
         match path {
             "/hello" => {
-                let content = Self::hello_content();
+                let content = Self::hello_content().as_bytes();
                 let content_len = content.len() as i64;
 
                 // Handle offset beyond file size
                 if offset >= content_len {
-                    return Ok(String::new());
+                    return Ok(Vec::new());
                 }
 
                 // Calculate actual read length
@@ -47,21 +48,21 @@ A simple read-only filesystem plugin written in Rust using the AGFS Plugin SDK.
 
                 let start = offset as usize;
                 let end = (offset + read_len) as usize;
-                Ok(content[start..end].to_string())
+                Ok(content[start..end].to_vec())
             }
-            _ => Err(FileSystemError::NotFound),
+            _ => Err(Error::NotFound),
         }
     }
 
     fn stat(&self, path: &str) -> Result<FileInfo> {
         match path {
-            "/" => Ok(FileInfo::directory("", 0o755).with_metadata(Self::dir_metadata())),
+            "/" => Ok(FileInfo::dir("", 0o755).with_meta(Self::dir_metadata())),
             "/hello" => {
                 let content = Self::hello_content();
                 Ok(FileInfo::file("hello", content.len() as i64, 0o644)
-                    .with_metadata(Self::file_metadata()))
+                    .with_meta(Self::file_metadata()))
             }
-            _ => Err(FileSystemError::NotFound),
+            _ => Err(Error::NotFound),
         }
     }
 
@@ -74,9 +75,9 @@ A simple read-only filesystem plugin written in Rust using the AGFS Plugin SDK.
                     content.len() as i64,
                     0o644,
                 )
-                .with_metadata(Self::file_metadata())])
+                .with_meta(Self::file_metadata())])
             }
-            _ => Err(FileSystemError::NotFound),
+            _ => Err(Error::NotFound),
         }
     }
 }
@@ -88,25 +89,13 @@ impl HelloFS {
     }
 
     /// Get file metadata
-    fn file_metadata() -> FileMetadata {
-        FileMetadata::new("hellofs-rust", "text", r#"{"language":"rust"}"#)
+    fn file_metadata() -> MetaData {
+        MetaData::new("hellofs-rust", "text").with_content(json!({"language": "rust"}))
     }
 
     /// Get directory metadata
-    fn dir_metadata() -> FileMetadata {
-        FileMetadata::new("hellofs-rust", "directory", r#"{"language":"rust"}"#)
-    }
-}
-
-// Helper method to add metadata to FileInfo
-trait WithMetadata {
-    fn with_metadata(self, metadata: FileMetadata) -> Self;
-}
-
-impl WithMetadata for FileInfo {
-    fn with_metadata(mut self, metadata: FileMetadata) -> Self {
-        self.metadata = metadata;
-        self
+    fn dir_metadata() -> MetaData {
+        MetaData::new("hellofs-rust", "directory").with_content(json!({"language": "rust"}))
     }
 }
 
@@ -119,44 +108,44 @@ mod tests {
 
     #[test]
     fn test_read_hello_file() {
-        let fs = HelloFS::default();
+        let fs = HelloFS;
         let result = fs.read("/hello", 0, 100);
         assert!(result.is_ok());
         let content = result.unwrap();
-        assert_eq!(content, "Hello from Rust dynamic library!\n");
+        assert_eq!(content, b"Hello from Rust dynamic library!\n");
     }
 
     #[test]
     fn test_read_nonexistent_file() {
-        let fs = HelloFS::default();
+        let fs = HelloFS;
         let result = fs.read("/nonexistent", 0, 100);
         assert!(result.is_err());
-        assert!(matches!(result.unwrap_err(), FileSystemError::NotFound));
+        assert!(matches!(result.unwrap_err(), Error::NotFound));
     }
 
     #[test]
     fn test_stat_root() {
-        let fs = HelloFS::default();
+        let fs = HelloFS;
         let result = fs.stat("/");
         assert!(result.is_ok());
         let info = result.unwrap();
-        assert!(info.is_dir);
+        assert!(info.is_dir());
     }
 
     #[test]
     fn test_stat_hello() {
-        let fs = HelloFS::default();
+        let fs = HelloFS;
         let result = fs.stat("/hello");
         assert!(result.is_ok());
         let info = result.unwrap();
         assert_eq!(info.name, "hello");
-        assert!(!info.is_dir);
+        assert!(!info.is_dir());
         assert_eq!(info.size, 33);  // "Hello from Rust dynamic library!\n"
     }
 
     #[test]
     fn test_readdir_root() {
-        let fs = HelloFS::default();
+        let fs = HelloFS;
         let result = fs.readdir("/");
         assert!(result.is_ok());
         let files = result.unwrap();
@@ -166,29 +155,29 @@ mod tests {
 
     #[test]
     fn test_read_with_offset() {
-        let fs = HelloFS::default();
+        let fs = HelloFS;
         let result = fs.read("/hello", 6, 100);
         assert!(result.is_ok());
-        assert_eq!(result.unwrap(), "from Rust dynamic library!\n");
+        assert_eq!(result.unwrap(), b"from Rust dynamic library!\n");
     }
 
     #[test]
     fn test_write_fails() {
-        let fs = HelloFS::default();
-        let result = fs.write("/hello", b"new content");
+        let mut fs = HelloFS;
+        let result = fs.write("/hello", b"new content", 0, WriteFlag::NONE);
         assert!(result.is_err());
-        assert!(matches!(result.unwrap_err(), FileSystemError::ReadOnly));
+        assert!(matches!(result.unwrap_err(), Error::ReadOnly));
     }
 
     #[test]
     fn test_plugin_name() {
-        let fs = HelloFS::default();
+        let fs = HelloFS;
         assert_eq!(fs.name(), "hellofs-rust");
     }
 
     #[test]
     fn test_plugin_readme() {
-        let fs = HelloFS::default();
+        let fs = HelloFS;
         assert!(fs.readme().contains("HelloFS Rust Plugin"));
     }
 }
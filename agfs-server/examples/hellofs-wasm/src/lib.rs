@@ -3,8 +3,14 @@
 //! Returns a single file with "Hello World" content
 //! Also demonstrates accessing the host filesystem
 //! Now with HandleFS support for FUSE-like stateful operations
+//! and a writable in-memory `/tmp` area showing full write semantics
 
+mod memfs;
+
+use agfs_wasm_ffi::path::resolve_within;
 use agfs_wasm_ffi::prelude::*;
+use memfs::MemTree;
+use std::cell::RefCell;
 use std::collections::HashMap;
 
 /// Internal file handle state
@@ -16,6 +22,41 @@ struct HandleState {
     content: Option<Vec<u8>>,
     /// For host files, store the host path
     host_path: Option<String>,
+    /// For files in the in-memory /tmp area
+    tmp_path: Option<String>,
+}
+
+/// How a `FileSystem::write` to a host file is carried out
+#[derive(Debug, PartialEq)]
+struct WritePlan {
+    /// File is missing and must be created first
+    create: bool,
+    /// Existing content must be dropped first
+    truncate: bool,
+    /// Absolute offset for the pwrite
+    offset: i64,
+}
+
+impl WritePlan {
+    /// Resolve `WriteFlag` semantics against the current size (`None` if missing)
+    fn new(existing_size: Option<i64>, offset: i64, flags: WriteFlag) -> Result<Self> {
+        let create = match existing_size {
+            Some(_) if flags.contains(WriteFlag::CREATE) && flags.contains(WriteFlag::EXCLUSIVE) => {
+                return Err(Error::AlreadyExists);
+            }
+            Some(_) => false,
+            None if flags.contains(WriteFlag::CREATE) => true,
+            None => return Err(Error::NotFound),
+        };
+        let truncate = !create && flags.contains(WriteFlag::TRUNCATE);
+        let size = if create || truncate { 0 } else { existing_size.unwrap_or(0) };
+        let offset = if flags.contains(WriteFlag::APPEND) || offset < 0 {
+            size
+        } else {
+            offset
+        };
+        Ok(Self { create, truncate, offset })
+    }
 }
 
 /// Counter for generating unique handle IDs
@@ -32,6 +73,8 @@ fn generate_handle_id() -> i64 {
 pub struct HelloFS {
     host_prefix: String,
     handles: HashMap<i64, HandleState>,
+    /// Writable in-memory tree under /tmp (RefCell because pwrite takes &self)
+    tmp: RefCell<MemTree>,
 }
 
 impl FileSystem for HelloFS {
@@ -42,6 +85,7 @@ impl FileSystem for HelloFS {
     fn readme(&self) -> &str {
         "HelloFS WASM - Demonstrates host filesystem access\n\
          - /hello.txt - Returns 'Hello World'\n\
+         - /tmp/* - Writable in-memory area (create, write, mkdir, rename, remove)\n\
          - /host/* - Proxies to host filesystem (if configured)"
     }
 
@@ -56,10 +100,10 @@ impl FileSystem for HelloFS {
     fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
         match path {
             "/hello.txt" => Ok(b"Hello World\n".to_vec()),
+            p if memfs::contains(p) => self.tmp.borrow().read(p, offset, size),
             p if p.starts_with("/host/") && !self.host_prefix.is_empty() => {
                 // Proxy to host filesystem
-                let host_path = p.strip_prefix("/host").unwrap();
-                let full_path = format!("{}{}", self.host_prefix, host_path);
+                let full_path = self.host_path(p)?;
                 HostFS::read(&full_path, offset, size)
                     .map_err(|e| Error::Other(format!("host fs: {}", e)))
             }
@@ -71,25 +115,14 @@ impl FileSystem for HelloFS {
         match path {
             "/" => Ok(FileInfo::dir("", 0o755)),
             "/hello.txt" => Ok(FileInfo::file("hello.txt", 12, 0o644)),
+            p if memfs::contains(p) => self.tmp.borrow().stat(p),
             "/host" if !self.host_prefix.is_empty() => {
                 Ok(FileInfo::dir("host", 0o755))
             }
             p if p.starts_with("/host/") && !self.host_prefix.is_empty() => {
                 // Proxy to host filesystem
-                let host_path = p.strip_prefix("/host").unwrap();
-                let full_path = format!("{}{}", self.host_prefix, host_path);
-                let host_info = HostFS::stat(&full_path)
-                    .map_err(|e| Error::Other(format!("host fs: {}", e)))?;
-
-                // Convert and return
-                Ok(FileInfo {
-                    name: host_info.name,
-                    size: host_info.size,
-                    mode: host_info.mode,
-                    mod_time: host_info.mod_time,
-                    is_dir: host_info.is_dir,
-                    meta: host_info.meta,
-                })
+                let full_path = self.host_path(p)?;
+                HostFS::stat(&full_path).map_err(|e| Error::Other(format!("host fs: {}", e)))
             }
             _ => Err(Error::NotFound),
         }
@@ -98,71 +131,62 @@ impl FileSystem for HelloFS {
     fn readdir(&self, path: &str) -> Result<Vec<FileInfo>> {
         match path {
             "/" => {
-                let mut entries = vec![FileInfo::file("hello.txt", 12, 0o644)];
+                let mut entries = vec![
+                    FileInfo::file("hello.txt", 12, 0o644),
+                    FileInfo::dir("tmp", 0o755),
+                ];
                 if !self.host_prefix.is_empty() {
                     entries.push(FileInfo::dir("host", 0o755));
                 }
                 Ok(entries)
             }
+            p if memfs::contains(p) => self.tmp.borrow().readdir(p),
             "/host" if !self.host_prefix.is_empty() => {
                 // Read from host filesystem root
-                let host_infos = HostFS::readdir(&self.host_prefix)
-                    .map_err(|e| Error::Other(format!("host fs: {}", e)))?;
-
-                Ok(host_infos
-                    .into_iter()
-                    .map(|info| FileInfo {
-                        name: info.name,
-                        size: info.size,
-                        mode: info.mode,
-                        mod_time: info.mod_time,
-                        is_dir: info.is_dir,
-                        meta: info.meta,
-                    })
-                    .collect())
+                HostFS::readdir(&self.host_prefix).map_err(|e| Error::Other(format!("host fs: {}", e)))
             }
             p if p.starts_with("/host/") && !self.host_prefix.is_empty() => {
                 // Proxy to host filesystem
-                let host_path = p.strip_prefix("/host").unwrap();
-                let full_path = format!("{}{}", self.host_prefix, host_path);
-                let host_infos = HostFS::readdir(&full_path)
-                    .map_err(|e| Error::Other(format!("host fs: {}", e)))?;
-
-                Ok(host_infos
-                    .into_iter()
-                    .map(|info| FileInfo {
-                        name: info.name,
-                        size: info.size,
-                        mode: info.mode,
-                        mod_time: info.mod_time,
-                        is_dir: info.is_dir,
-                        meta: info.meta,
-                    })
-                    .collect())
+                let full_path = self.host_path(p)?;
+                HostFS::readdir(&full_path).map_err(|e| Error::Other(format!("host fs: {}", e)))
             }
             _ => Err(Error::NotFound),
         }
     }
 
-    fn write(&mut self, path: &str, data: &[u8], _offset: i64, _flags: WriteFlag) -> Result<i64> {
-        if path.starts_with("/host/") && !self.host_prefix.is_empty() {
+    fn write(&mut self, path: &str, data: &[u8], offset: i64, flags: WriteFlag) -> Result<i64> {
+        if memfs::contains(path) {
+            self.tmp.get_mut().write(path, data, offset, flags)
+        } else if path.starts_with("/host/") && !self.host_prefix.is_empty() {
             // Proxy to host filesystem
-            // Note: HostFS doesn't support offset/flags yet, ignoring them
-            let host_path = path.strip_prefix("/host").unwrap();
-            let full_path = format!("{}{}", self.host_prefix, host_path);
-            HostFS::write(&full_path, data)
-                .map_err(|e| Error::Other(format!("host fs: {}", e)))?;
-            Ok(data.len() as i64)
+            let full_path = self.host_path(path)?;
+            let existing = HostFS::stat(&full_path).ok();
+            if existing.as_ref().is_some_and(|info| info.is_dir()) {
+                return Err(Error::IsDirectory);
+            }
+            let plan = WritePlan::new(existing.map(|info| info.size), offset, flags)?;
+            if plan.create {
+                HostFS::create(&full_path)
+                    .map_err(|e| Error::Other(format!("host fs: {}", e)))?;
+            }
+            if plan.truncate {
+                HostFS::truncate(&full_path, 0)
+                    .map_err(|e| Error::Other(format!("host fs: {}", e)))?;
+            }
+            HostFS::write_at(&full_path, data, plan.offset)
+                .map(|n| n as i64)
+                .map_err(|e| Error::Other(format!("host fs: {}", e)))
         } else {
             Err(Error::PermissionDenied)
         }
     }
 
     fn create(&mut self, path: &str) -> Result<()> {
-        if path.starts_with("/host/") && !self.host_prefix.is_empty() {
+        if memfs::contains(path) {
+            self.tmp.get_mut().create(path)
+        } else if path.starts_with("/host/") && !self.host_prefix.is_empty() {
             // Proxy to host filesystem
-            let host_path = path.strip_prefix("/host").unwrap();
-            let full_path = format!("{}{}", self.host_prefix, host_path);
+            let full_path = self.host_path(path)?;
             HostFS::create(&full_path)
                 .map_err(|e| Error::Other(format!("host fs: {}", e)))
         } else {
@@ -171,10 +195,11 @@ impl FileSystem for HelloFS {
     }
 
     fn mkdir(&mut self, path: &str, perm: u32) -> Result<()> {
-        if path.starts_with("/host/") && !self.host_prefix.is_empty() {
+        if memfs::contains(path) {
+            self.tmp.get_mut().mkdir(path, perm)
+        } else if path.starts_with("/host/") && !self.host_prefix.is_empty() {
             // Proxy to host filesystem
-            let host_path = path.strip_prefix("/host").unwrap();
-            let full_path = format!("{}{}", self.host_prefix, host_path);
+            let full_path = self.host_path(path)?;
             HostFS::mkdir(&full_path, perm)
                 .map_err(|e| Error::Other(format!("host fs: {}", e)))
         } else {
@@ -183,10 +208,11 @@ impl FileSystem for HelloFS {
     }
 
     fn remove(&mut self, path: &str) -> Result<()> {
-        if path.starts_with("/host/") && !self.host_prefix.is_empty() {
+        if memfs::contains(path) {
+            self.tmp.get_mut().remove(path)
+        } else if path.starts_with("/host/") && !self.host_prefix.is_empty() {
             // Proxy to host filesystem
-            let host_path = path.strip_prefix("/host").unwrap();
-            let full_path = format!("{}{}", self.host_prefix, host_path);
+            let full_path = self.host_path(path)?;
             HostFS::remove(&full_path)
                 .map_err(|e| Error::Other(format!("host fs: {}", e)))
         } else {
@@ -195,10 +221,11 @@ impl FileSystem for HelloFS {
     }
 
     fn remove_all(&mut self, path: &str) -> Result<()> {
-        if path.starts_with("/host/") && !self.host_prefix.is_empty() {
+        if memfs::contains(path) {
+            self.tmp.get_mut().remove_all(path)
+        } else if path.starts_with("/host/") && !self.host_prefix.is_empty() {
             // Proxy to host filesystem
-            let host_path = path.strip_prefix("/host").unwrap();
-            let full_path = format!("{}{}", self.host_prefix, host_path);
+            let full_path = self.host_path(path)?;
             HostFS::remove_all(&full_path)
                 .map_err(|e| Error::Other(format!("host fs: {}", e)))
         } else {
@@ -207,12 +234,12 @@ impl FileSystem for HelloFS {
     }
 
     fn rename(&mut self, old_path: &str, new_path: &str) -> Result<()> {
-        if old_path.starts_with("/host/") && new_path.starts_with("/host/") && !self.host_prefix.is_empty() {
+        if memfs::contains(old_path) && memfs::contains(new_path) {
+            self.tmp.get_mut().rename(old_path, new_path)
+        } else if old_path.starts_with("/host/") && new_path.starts_with("/host/") && !self.host_prefix.is_empty() {
             // Proxy to host filesystem (both paths must be in host)
-            let host_old_path = old_path.strip_prefix("/host").unwrap();
-            let host_new_path = new_path.strip_prefix("/host").unwrap();
-            let full_old_path = format!("{}{}", self.host_prefix, host_old_path);
-            let full_new_path = format!("{}{}", self.host_prefix, host_new_path);
+            let full_old_path = self.host_path(old_path)?;
+            let full_new_path = self.host_path(new_path)?;
             HostFS::rename(&full_old_path, &full_new_path)
                 .map_err(|e| Error::Other(format!("host fs: {}", e)))
         } else {
@@ -220,8 +247,12 @@ impl FileSystem for HelloFS {
         }
     }
 
-    fn chmod(&mut self, _path: &str, _mode: u32) -> Result<()> {
-        Ok(())
+    fn chmod(&mut self, path: &str, mode: u32) -> Result<()> {
+        if memfs::contains(path) {
+            self.tmp.get_mut().chmod(path, mode)
+        } else {
+            Ok(())
+        }
     }
 }
 
@@ -239,17 +270,36 @@ impl HandleFS for HelloFS {
             return Err(Error::AlreadyExists);
         }
 
-        // Determine content and host_path
-        let (content, host_path) = match path {
+        // Determine content, host_path and tmp_path
+        let (content, host_path, tmp_path) = match path {
             "/hello.txt" => {
                 // Built-in file - load content
-                (Some(b"Hello World\n".to_vec()), None)
+                (Some(b"Hello World\n".to_vec()), None, None)
+            }
+            p if memfs::contains(p) => {
+                let tmp = self.tmp.get_mut();
+                if !exists {
+                    tmp.create(p)?;
+                } else if tmp.stat(p)?.is_dir() {
+                    return Err(Error::IsDirectory);
+                }
+                if flags.contains(OpenFlag::O_TRUNC) && flags.is_writable() {
+                    tmp.truncate(p, 0)?;
+                }
+                (None, None, Some(p.to_string()))
             }
             p if p.starts_with("/host/") && !self.host_prefix.is_empty() => {
                 // Host file
-                let hp = p.strip_prefix("/host").unwrap();
-                let full_path = format!("{}{}", self.host_prefix, hp);
-                (None, Some(full_path))
+                let full_path = self.host_path(p)?;
+                if !exists {
+                    HostFS::create(&full_path)
+                        .map_err(|e| Error::Other(format!("host fs: {}", e)))?;
+                }
+                if flags.contains(OpenFlag::O_TRUNC) && flags.is_writable() {
+                    HostFS::truncate(&full_path, 0)
+                        .map_err(|e| Error::Other(format!("host fs: {}", e)))?;
+                }
+                (None, Some(full_path), None)
             }
             _ => return Err(Error::NotFound),
         };
@@ -261,6 +311,7 @@ impl HandleFS for HelloFS {
             pos: 0,
             content,
             host_path,
+            tmp_path,
         };
 
         self.handles.insert(id, state);
@@ -304,6 +355,13 @@ impl HandleFS for HelloFS {
             return Ok(n);
         }
 
+        // For in-memory /tmp files
+        if let Some(ref tmp_path) = state.tmp_path {
+            let data = self.tmp.borrow().read(tmp_path, offset, buf.len() as i64)?;
+            buf[..data.len()].copy_from_slice(&data);
+            return Ok(data.len());
+        }
+
         // For host files
         if let Some(ref host_path) = state.host_path {
             let data = HostFS::read(host_path, offset, buf.len() as i64)
@@ -327,6 +385,8 @@ impl HandleFS for HelloFS {
         let pos = if state.flags.contains(OpenFlag::O_APPEND) {
             if let Some(ref content) = state.content {
                 content.len() as i64
+            } else if let Some(ref tmp_path) = state.tmp_path {
+                self.tmp.borrow().size(tmp_path)?
             } else if let Some(ref host_path) = state.host_path {
                 let info = HostFS::stat(host_path)
                     .map_err(|e| Error::Other(format!("host fs: {}", e)))?;
@@ -348,7 +408,7 @@ impl HandleFS for HelloFS {
         Ok(n)
     }
 
-    fn handle_write_at(&self, id: i64, data: &[u8], _offset: i64) -> Result<usize> {
+    fn handle_write_at(&self, id: i64, data: &[u8], offset: i64) -> Result<usize> {
         let state = self.handles.get(&id).ok_or(Error::NotFound)?;
 
         if !state.flags.is_writable() {
@@ -360,12 +420,15 @@ impl HandleFS for HelloFS {
             return Err(Error::PermissionDenied);
         }
 
+        // For in-memory /tmp files
+        if let Some(ref tmp_path) = state.tmp_path {
+            return self.tmp.borrow_mut().write_at(tmp_path, data, offset);
+        }
+
         // For host files
         if let Some(ref host_path) = state.host_path {
-            // Note: Host FS write doesn't support offset well
-            HostFS::write(host_path, data)
-                .map_err(|e| Error::Other(format!("host fs: {}", e)))?;
-            return Ok(data.len());
+            return HostFS::write_at(host_path, data, offset)
+                .map_err(|e| Error::Other(format!("host fs: {}", e)));
         }
 
         Err(Error::PermissionDenied)
@@ -376,6 +439,8 @@ impl HandleFS for HelloFS {
 
         let size = if let Some(ref content) = state.content {
             content.len() as i64
+        } else if let Some(ref tmp_path) = state.tmp_path {
+            self.tmp.borrow().size(tmp_path)?
         } else if let Some(ref host_path) = state.host_path {
             let info = HostFS::stat(host_path)
                 .map_err(|e| Error::Other(format!("host fs: {}", e)))?;
@@ -411,6 +476,10 @@ impl HandleFS for HelloFS {
             return Ok(FileInfo::file("hello.txt", content.len() as i64, 0o644));
         }
 
+        if let Some(ref tmp_path) = state.tmp_path {
+            return self.tmp.borrow().stat(tmp_path);
+        }
+
         if let Some(ref host_path) = state.host_path {
             let info = HostFS::stat(host_path)
                 .map_err(|e| Error::Other(format!("host fs: {}", e)))?;
@@ -433,6 +502,17 @@ impl HandleFS for HelloFS {
 
 // Helper methods for internal use
 impl HelloFS {
+    /// Map a `/host/...` path onto the host filesystem, confined to
+    /// `host_prefix` even through symlinks
+    fn host_path(&self, path: &str) -> Result<String> {
+        self.host_path_with(path, HostFS::realpath)
+    }
+
+    fn host_path_with<F: Fn(&str) -> Result<String>>(&self, path: &str, realpath: F) -> Result<String> {
+        let rel = path.strip_prefix("/host").unwrap_or(path);
+        resolve_within(&self.host_prefix, rel, realpath)
+    }
+
     fn handle_read_at_internal(&self, id: i64, buf: &mut [u8], offset: i64) -> Result<usize> {
         self.handle_read_at(id, buf, offset)
     }
@@ -444,3 +524,266 @@ impl HelloFS {
 
 // Export with HandleFS support
 export_handle_plugin!(HelloFS);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agfs_wasm_ffi::host_stub;
+    use std::rc::Rc;
+
+    fn hellofs() -> HelloFS {
+        HelloFS {
+            host_prefix: "/srv/data".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn no_symlinks(path: &str) -> Result<String> {
+        Ok(path.to_string())
+    }
+
+    #[test]
+    fn test_host_path_maps_into_prefix() {
+        let fs = hellofs();
+        assert_eq!(fs.host_path_with("/host/a/b.txt", no_symlinks).unwrap(), "/srv/data/a/b.txt");
+        assert_eq!(fs.host_path_with("/host/a/./b//c", no_symlinks).unwrap(), "/srv/data/a/b/c");
+        assert_eq!(fs.host_path_with("/host/a/../b", no_symlinks).unwrap(), "/srv/data/b");
+        assert_eq!(fs.host_path_with("/host/", no_symlinks).unwrap(), "/srv/data");
+    }
+
+    #[test]
+    fn test_host_path_rejects_traversal() {
+        let fs = hellofs();
+        for path in ["/host/..", "/host/../etc/passwd", "/host/a/../../etc", "/host/a/b/../../../x"] {
+            assert!(
+                matches!(fs.host_path_with(path, no_symlinks), Err(Error::PermissionDenied)),
+                "{} escaped the prefix",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn test_host_path_rejects_encoded_separators() {
+        let fs = hellofs();
+        for path in ["/host/..%2fetc", "/host/%2E%2E/etc", "/host/a%5c..%5c..", "/host/a\\..\\b"] {
+            assert!(
+                matches!(fs.host_path_with(path, no_symlinks), Err(Error::InvalidInput(_))),
+                "{} was accepted",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn test_host_path_follows_symlinks() {
+        let fs = hellofs();
+        // /srv/data/out -> /etc, /srv/data/docs -> /srv/data/shared/docs
+        let links = [("/srv/data/out", "/etc"), ("/srv/data/docs", "/srv/data/shared/docs")];
+        let realpath = |p: &str| {
+            for (link, target) in links {
+                match p.strip_prefix(link) {
+                    Some(rest) if rest.is_empty() || rest.starts_with('/') => return Ok(format!("{}{}", target, rest)),
+                    _ => {}
+                }
+            }
+            Ok(p.to_string())
+        };
+        assert!(matches!(fs.host_path_with("/host/out", realpath), Err(Error::PermissionDenied)));
+        assert!(matches!(fs.host_path_with("/host/out/passwd", realpath), Err(Error::PermissionDenied)));
+        assert_eq!(fs.host_path_with("/host/docs/a.md", realpath).unwrap(), "/srv/data/shared/docs/a.md");
+        assert_eq!(fs.host_path_with("/host/outside", realpath).unwrap(), "/srv/data/outside");
+        // Links the host refuses to resolve are refused too
+        let failing = |_: &str| Err(Error::Other("dangling symbolic link".to_string()));
+        assert!(matches!(fs.host_path_with("/host/x", failing), Err(Error::PermissionDenied)));
+    }
+
+    #[test]
+    fn test_write_plan_honors_flags() {
+        let plan = |size, offset, flags| WritePlan::new(size, offset, flags);
+
+        // pwrite into an existing file keeps the offset
+        assert_eq!(
+            plan(Some(10), 4, WriteFlag::NONE).unwrap(),
+            WritePlan { create: false, truncate: false, offset: 4 }
+        );
+        // append (or a negative offset) writes at the current end
+        assert_eq!(plan(Some(10), 2, WriteFlag::APPEND).unwrap().offset, 10);
+        assert_eq!(plan(Some(10), -1, WriteFlag::NONE).unwrap().offset, 10);
+        // truncate empties the file first, so appends land at 0
+        assert_eq!(
+            plan(Some(10), -1, WriteFlag::TRUNCATE | WriteFlag::APPEND).unwrap(),
+            WritePlan { create: false, truncate: true, offset: 0 }
+        );
+        // missing files need CREATE, existing ones fail EXCLUSIVE
+        assert!(matches!(plan(None, 0, WriteFlag::NONE), Err(Error::NotFound)));
+        assert_eq!(
+            plan(None, 0, WriteFlag::CREATE | WriteFlag::TRUNCATE).unwrap(),
+            WritePlan { create: true, truncate: false, offset: 0 }
+        );
+        assert!(matches!(
+            plan(Some(0), 0, WriteFlag::CREATE | WriteFlag::EXCLUSIVE),
+            Err(Error::AlreadyExists)
+        ));
+    }
+
+    fn read_all(fs: &HelloFS, id: i64) -> Vec<u8> {
+        let mut buf = vec![0u8; 64];
+        let n = fs.handle_read_at(id, &mut buf, 0).unwrap();
+        buf.truncate(n);
+        buf
+    }
+
+    #[test]
+    fn test_interleaved_pread_pwrite() {
+        let mut fs = hellofs();
+        let id = fs
+            .open_handle("/tmp/data", OpenFlag::O_RDWR | OpenFlag::O_CREATE, 0o644)
+            .unwrap();
+
+        assert_eq!(fs.handle_write_at(id, b"hello world", 0).unwrap(), 11);
+        assert_eq!(fs.handle_write_at(id, b"WORLD", 6).unwrap(), 5);
+
+        let mut buf = [0u8; 5];
+        assert_eq!(fs.handle_read_at(id, &mut buf, 0).unwrap(), 5);
+        assert_eq!(&buf, b"hello");
+
+        // Writing past the end zero-fills the gap and leaves earlier bytes alone
+        fs.handle_write_at(id, b"!", 13).unwrap();
+        assert_eq!(read_all(&fs, id), b"hello WORLD\0\0!");
+
+        // Positional I/O does not move the sequential cursor
+        let mut buf = [0u8; 3];
+        fs.handle_read(id, &mut buf).unwrap();
+        assert_eq!(&buf, b"hel");
+        fs.handle_write(id, b"P").unwrap();
+        assert_eq!(read_all(&fs, id), b"helPo WORLD\0\0!");
+    }
+
+    #[test]
+    fn test_open_trunc_and_append() {
+        let mut fs = hellofs();
+        fs.write("/tmp/log", b"old contents", -1, WriteFlag::CREATE).unwrap();
+
+        let id = fs
+            .open_handle("/tmp/log", OpenFlag::O_WRONLY | OpenFlag::O_APPEND, 0)
+            .unwrap();
+        fs.handle_write(id, b"+1").unwrap();
+        // O_APPEND ignores the cursor, even after a seek back to the start
+        fs.handle_seek(id, 0, 0).unwrap();
+        fs.handle_write(id, b"+2").unwrap();
+        assert_eq!(fs.read("/tmp/log", 0, -1).unwrap(), b"old contents+1+2");
+
+        let id = fs
+            .open_handle("/tmp/log", OpenFlag::O_RDWR | OpenFlag::O_TRUNC, 0)
+            .unwrap();
+        assert_eq!(fs.stat("/tmp/log").unwrap().size, 0);
+        fs.handle_write(id, b"new").unwrap();
+        assert_eq!(read_all(&fs, id), b"new");
+    }
+
+    /// Serve the host fs imports from an in-memory map of host files
+    fn stub_host_fs() -> Rc<RefCell<HashMap<String, Vec<u8>>>> {
+        let files: Rc<RefCell<HashMap<String, Vec<u8>>>> = Rc::default();
+        let path = |args: &[u64]| unsafe { host_stub::read_str(args[0]) };
+        let fail = |msg: &str| host_stub::alloc_str(msg) as u64;
+
+        host_stub::set("host_deadline_ms", |_| -1i64 as u64);
+        host_stub::set("host_fs_realpath", move |args| host_stub::alloc_str(&path(args)) as u64);
+        let f = files.clone();
+        host_stub::set("host_fs_stat", move |args| match f.borrow().get(&path(args)) {
+            Some(data) => {
+                let info = FileInfo::file("file", data.len() as i64, 0o644);
+                host_stub::alloc_str(&agfs_wasm_ffi::serde_json::to_string(&info).unwrap()) as u64
+            }
+            None => fail("no such file") << 32,
+        });
+        let f = files.clone();
+        host_stub::set("host_fs_create", move |args| {
+            f.borrow_mut().insert(path(args), Vec::new());
+            0
+        });
+        let f = files.clone();
+        host_stub::set("host_fs_read", move |args| {
+            let files = f.borrow();
+            let Some(data) = files.get(&path(args)) else { return 0 };
+            let start = (args[1] as usize).min(data.len());
+            let end = (start + args[2] as usize).min(data.len());
+            host_stub::alloc(&data[start..end]) as u64 | ((end - start) as u64) << 32
+        });
+        let f = files.clone();
+        host_stub::set("host_fs_write_at", move |args| {
+            let mut files = f.borrow_mut();
+            let Some(file) = files.get_mut(&path(args)) else { return fail("no such file") << 32 };
+            let data = unsafe { host_stub::read_bytes(args[1], args[2]) };
+            let offset = args[3] as usize;
+            if file.len() < offset + data.len() {
+                file.resize(offset + data.len(), 0);
+            }
+            file[offset..offset + data.len()].copy_from_slice(&data);
+            data.len() as u64
+        });
+        let f = files.clone();
+        host_stub::set("host_fs_truncate", move |args| match f.borrow_mut().get_mut(&path(args)) {
+            Some(file) => {
+                file.resize(args[1] as usize, 0);
+                0
+            }
+            None => fail("no such file"),
+        });
+        files
+    }
+
+    #[test]
+    fn test_host_write_honors_offsets_and_flags() {
+        let files = stub_host_fs();
+        let mut fs = hellofs();
+        let contents = |p: &str| files.borrow()[p].clone();
+
+        assert!(matches!(fs.write("/host/notes", b"x", 0, WriteFlag::NONE), Err(Error::NotFound)));
+        fs.write("/host/notes", b"hello world", 0, WriteFlag::CREATE).unwrap();
+        fs.write("/host/notes", b"WORLD", 6, WriteFlag::NONE).unwrap();
+        assert_eq!(contents("/srv/data/notes"), b"hello WORLD");
+
+        fs.write("/host/notes", b"!", 0, WriteFlag::APPEND).unwrap();
+        assert_eq!(contents("/srv/data/notes"), b"hello WORLD!");
+
+        fs.write("/host/notes", b"new", -1, WriteFlag::TRUNCATE).unwrap();
+        assert_eq!(contents("/srv/data/notes"), b"new");
+        assert_eq!(fs.read("/host/notes", 0, 64).unwrap(), b"new");
+
+        // Host errors reach the caller with their message
+        host_stub::set("host_fs_truncate", |_| host_stub::alloc_str("read-only file system") as u64);
+        let err = fs.write("/host/notes", b"x", 0, WriteFlag::TRUNCATE).unwrap_err();
+        assert!(err.to_string().contains("read-only file system"), "{}", err);
+        assert_eq!(contents("/srv/data/notes"), b"new");
+    }
+
+    #[test]
+    fn test_host_handles_pwrite_trunc_and_append() {
+        let files = stub_host_fs();
+        let mut fs = hellofs();
+
+        let id = fs
+            .open_handle("/host/data", OpenFlag::O_RDWR | OpenFlag::O_CREATE, 0o644)
+            .unwrap();
+        assert_eq!(fs.handle_write_at(id, b"hello world", 0).unwrap(), 11);
+        assert_eq!(fs.handle_write_at(id, b"WORLD", 6).unwrap(), 5);
+        fs.handle_write_at(id, b"!", 13).unwrap();
+        assert_eq!(read_all(&fs, id), b"hello WORLD\0\0!");
+
+        let id = fs
+            .open_handle("/host/data", OpenFlag::O_WRONLY | OpenFlag::O_APPEND, 0)
+            .unwrap();
+        fs.handle_seek(id, 0, 0).unwrap();
+        fs.handle_write(id, b"+1").unwrap();
+        assert_eq!(files.borrow()["/srv/data/data"], b"hello WORLD\0\0!+1");
+
+        let id = fs
+            .open_handle("/host/data", OpenFlag::O_RDWR | OpenFlag::O_TRUNC, 0)
+            .unwrap();
+        assert_eq!(fs.stat("/host/data").unwrap().size, 0);
+        fs.handle_write(id, b"new").unwrap();
+        assert_eq!(read_all(&fs, id), b"new");
+    }
+}
@@ -0,0 +1,282 @@
+//! In-memory file tree backing the writable `/tmp` area of HelloFS
+//!
+//! Paths are absolute plugin paths (e.g. `/tmp/notes/a.txt`). The tree is
+//! rooted at `/tmp`, which always exists.
+
+use agfs_wasm_ffi::prelude::*;
+use std::collections::BTreeMap;
+
+pub const ROOT: &str = "/tmp";
+
+enum Node {
+    Dir { mode: u32 },
+    File { data: Vec<u8>, mode: u32 },
+}
+
+pub struct MemTree {
+    nodes: BTreeMap<String, Node>,
+}
+
+impl Default for MemTree {
+    fn default() -> Self {
+        let mut nodes = BTreeMap::new();
+        nodes.insert(ROOT.to_string(), Node::Dir { mode: 0o755 });
+        Self { nodes }
+    }
+}
+
+/// Returns true if `path` is `/tmp` or lies below it
+pub fn contains(path: &str) -> bool {
+    path == ROOT || path.starts_with("/tmp/")
+}
+
+fn normalize(path: &str) -> &str {
+    path.trim_end_matches('/')
+}
+
+fn parent(path: &str) -> &str {
+    match path.rfind('/') {
+        Some(0) | None => "/",
+        Some(i) => &path[..i],
+    }
+}
+
+fn base_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// Returns true if `key` is a direct child of `dir` (not a grandchild)
+fn is_child(dir: &str, key: &str) -> bool {
+    key.len() > dir.len() + 1
+        && key.starts_with(dir)
+        && key.as_bytes()[dir.len()] == b'/'
+        && !key[dir.len() + 1..].contains('/')
+}
+
+/// Returns true if `key` lies anywhere below `dir`
+fn is_descendant(dir: &str, key: &str) -> bool {
+    key.len() > dir.len() && key.starts_with(dir) && key.as_bytes()[dir.len()] == b'/'
+}
+
+impl MemTree {
+    fn info(path: &str, node: &Node) -> FileInfo {
+        match node {
+            Node::Dir { mode } => FileInfo::dir(base_name(path), *mode),
+            Node::File { data, mode } => FileInfo::file(base_name(path), data.len() as i64, *mode),
+        }
+    }
+
+    fn file_mut(&mut self, path: &str) -> Result<&mut Vec<u8>> {
+        match self.nodes.get_mut(normalize(path)) {
+            Some(Node::File { data, .. }) => Ok(data),
+            Some(Node::Dir { .. }) => Err(Error::IsDirectory),
+            None => Err(Error::NotFound),
+        }
+    }
+
+    /// Check that the parent of `path` exists and is a directory
+    fn check_parent(&self, path: &str) -> Result<()> {
+        match self.nodes.get(parent(path)) {
+            Some(Node::Dir { .. }) => Ok(()),
+            Some(Node::File { .. }) => Err(Error::NotDirectory),
+            None => Err(Error::NotFound),
+        }
+    }
+
+    pub fn stat(&self, path: &str) -> Result<FileInfo> {
+        let path = normalize(path);
+        self.nodes
+            .get(path)
+            .map(|node| Self::info(path, node))
+            .ok_or(Error::NotFound)
+    }
+
+    pub fn size(&self, path: &str) -> Result<i64> {
+        self.stat(path).map(|info| info.size)
+    }
+
+    pub fn readdir(&self, path: &str) -> Result<Vec<FileInfo>> {
+        let path = normalize(path);
+        match self.nodes.get(path) {
+            Some(Node::Dir { .. }) => {}
+            Some(Node::File { .. }) => return Err(Error::NotDirectory),
+            None => return Err(Error::NotFound),
+        }
+
+        Ok(self
+            .nodes
+            .iter()
+            .filter(|(key, _)| is_child(path, key))
+            .map(|(key, node)| Self::info(key, node))
+            .collect())
+    }
+
+    /// Read `size` bytes at `offset` (`size < 0` reads to the end)
+    pub fn read(&self, path: &str, offset: i64, size: i64) -> Result<Vec<u8>> {
+        let data = match self.nodes.get(normalize(path)) {
+            Some(Node::File { data, .. }) => data,
+            Some(Node::Dir { .. }) => return Err(Error::IsDirectory),
+            None => return Err(Error::NotFound),
+        };
+
+        let start = offset.clamp(0, data.len() as i64) as usize;
+        let end = if size < 0 {
+            data.len()
+        } else {
+            (start + size as usize).min(data.len())
+        };
+        Ok(data[start..end].to_vec())
+    }
+
+    /// Write following `FileSystem::write` semantics
+    ///
+    /// CREATE creates a missing file, EXCLUSIVE fails if it already exists,
+    /// TRUNCATE empties it first and APPEND (or `offset < 0`) writes at the end.
+    pub fn write(&mut self, path: &str, data: &[u8], offset: i64, flags: WriteFlag) -> Result<i64> {
+        let path = normalize(path);
+        match self.nodes.get(path) {
+            Some(Node::Dir { .. }) => return Err(Error::IsDirectory),
+            Some(Node::File { .. }) => {
+                if flags.contains(WriteFlag::CREATE) && flags.contains(WriteFlag::EXCLUSIVE) {
+                    return Err(Error::AlreadyExists);
+                }
+            }
+            None => {
+                if !flags.contains(WriteFlag::CREATE) {
+                    return Err(Error::NotFound);
+                }
+                self.create(path)?;
+            }
+        }
+
+        if flags.contains(WriteFlag::TRUNCATE) {
+            self.truncate(path, 0)?;
+        }
+
+        let offset = if flags.contains(WriteFlag::APPEND) || offset < 0 {
+            self.size(path)?
+        } else {
+            offset
+        };
+        self.write_at(path, data, offset).map(|n| n as i64)
+    }
+
+    /// Write at `offset`, zero-filling any gap past the current end (pwrite)
+    pub fn write_at(&mut self, path: &str, data: &[u8], offset: i64) -> Result<usize> {
+        if offset < 0 {
+            return Err(Error::InvalidInput("negative offset".to_string()));
+        }
+        let file = self.file_mut(path)?;
+        let start = offset as usize;
+        let end = start + data.len();
+        if file.len() < end {
+            file.resize(end, 0);
+        }
+        file[start..end].copy_from_slice(data);
+        Ok(data.len())
+    }
+
+    pub fn truncate(&mut self, path: &str, size: i64) -> Result<()> {
+        if size < 0 {
+            return Err(Error::InvalidInput("negative size".to_string()));
+        }
+        self.file_mut(path)?.resize(size as usize, 0);
+        Ok(())
+    }
+
+    pub fn create(&mut self, path: &str) -> Result<()> {
+        let path = normalize(path);
+        if self.nodes.contains_key(path) {
+            return Err(Error::AlreadyExists);
+        }
+        self.check_parent(path)?;
+        self.nodes.insert(path.to_string(), Node::File { data: Vec::new(), mode: 0o644 });
+        Ok(())
+    }
+
+    pub fn mkdir(&mut self, path: &str, perm: u32) -> Result<()> {
+        let path = normalize(path);
+        if self.nodes.contains_key(path) {
+            return Err(Error::AlreadyExists);
+        }
+        self.check_parent(path)?;
+        self.nodes.insert(path.to_string(), Node::Dir { mode: perm });
+        Ok(())
+    }
+
+    pub fn remove(&mut self, path: &str) -> Result<()> {
+        let path = normalize(path);
+        if path == ROOT {
+            return Err(Error::PermissionDenied);
+        }
+        if !self.nodes.contains_key(path) {
+            return Err(Error::NotFound);
+        }
+        if self.nodes.keys().any(|key| is_descendant(path, key)) {
+            return Err(Error::Other("directory not empty".to_string()));
+        }
+        self.nodes.remove(path);
+        Ok(())
+    }
+
+    pub fn remove_all(&mut self, path: &str) -> Result<()> {
+        let path = normalize(path);
+        if path == ROOT {
+            return Err(Error::PermissionDenied);
+        }
+        if self.nodes.remove(path).is_none() {
+            return Err(Error::NotFound);
+        }
+        self.nodes.retain(|key, _| !is_descendant(path, key));
+        Ok(())
+    }
+
+    /// Move a file or a whole directory subtree, replacing an existing file target
+    pub fn rename(&mut self, old_path: &str, new_path: &str) -> Result<()> {
+        let old_path = normalize(old_path);
+        let new_path = normalize(new_path);
+        if old_path == ROOT || new_path == ROOT {
+            return Err(Error::PermissionDenied);
+        }
+        if old_path == new_path {
+            return self.stat(old_path).map(|_| ());
+        }
+        if is_descendant(old_path, new_path) {
+            return Err(Error::InvalidInput("cannot move a directory into itself".to_string()));
+        }
+        let moving_dir = match self.nodes.get(old_path) {
+            Some(Node::Dir { .. }) => true,
+            Some(Node::File { .. }) => false,
+            None => return Err(Error::NotFound),
+        };
+        self.check_parent(new_path)?;
+        match self.nodes.get(new_path) {
+            Some(Node::Dir { .. }) => return Err(Error::IsDirectory),
+            Some(Node::File { .. }) if moving_dir => return Err(Error::NotDirectory),
+            _ => {}
+        }
+
+        let moved: Vec<String> = self
+            .nodes
+            .keys()
+            .filter(|key| key.as_str() == old_path || is_descendant(old_path, key))
+            .cloned()
+            .collect();
+        for key in moved {
+            let node = self.nodes.remove(&key).expect("key collected above");
+            let new_key = format!("{}{}", new_path, &key[old_path.len()..]);
+            self.nodes.insert(new_key, node);
+        }
+        Ok(())
+    }
+
+    pub fn chmod(&mut self, path: &str, new_mode: u32) -> Result<()> {
+        match self.nodes.get_mut(normalize(path)) {
+            Some(Node::Dir { mode }) | Some(Node::File { mode, .. }) => {
+                *mode = new_mode;
+                Ok(())
+            }
+            None => Err(Error::NotFound),
+        }
+    }
+}
@@ -36,7 +36,14 @@ type WASMPluginConfig struct {
 	InstanceMaxLifetime  int `yaml:"instance_max_lifetime"`   // Maximum instance lifetime in seconds (0 = unlimited)
 	InstanceMaxRequests  int `yaml:"instance_max_requests"`   // Maximum requests per instance (0 = unlimited)
 	HealthCheckInterval  int `yaml:"health_check_interval"`   // Health check interval in seconds (0 = disabled)
+	OperationTimeout     int `yaml:"operation_timeout"`       // Deadline for each plugin operation in seconds (0 = none)
 	EnablePoolStatistics bool `yaml:"enable_pool_statistics"` // Enable pool statistics collection
+	QueueDir             string `yaml:"queue_dir"`            // Directory persisting plugins' host queues (empty = in memory only)
+	EmbeddingAPIKey      string `yaml:"embedding_api_key"`    // OpenAI API key for plugins' host_embed calls (empty = embedding unavailable)
+	EmbeddingModel       string `yaml:"embedding_model"`      // Embedding model (default: text-embedding-3-small)
+	CompletionURL        string `yaml:"completion_url"`       // OpenAI-compatible API for plugins' host_ai_complete calls (default: https://api.openai.com/v1)
+	CompletionAPIKey     string `yaml:"completion_api_key"`   // API key of the completion API (empty = text generation unavailable)
+	CompletionModel      string `yaml:"completion_model"`     // Completion model (default: gpt-4o-mini)
 }
 
 // PluginConfig can be either a single plugin or an array of plugin instances
@@ -111,6 +118,18 @@ func (c *Config) GetWASMConfig() WASMPluginConfig {
 	if cfg.HealthCheckInterval < 0 {
 		cfg.HealthCheckInterval = 0 // Default: disabled
 	}
+	if cfg.OperationTimeout < 0 {
+		cfg.OperationTimeout = 0 // Default: no deadline
+	}
+	if cfg.EmbeddingModel == "" {
+		cfg.EmbeddingModel = "text-embedding-3-small"
+	}
+	if cfg.CompletionURL == "" {
+		cfg.CompletionURL = "https://api.openai.com/v1"
+	}
+	if cfg.CompletionModel == "" {
+		cfg.CompletionModel = "gpt-4o-mini"
+	}
 
 	return cfg
 }
@@ -294,7 +294,16 @@ func (h *BaseFileHandle) Seek(offset int64, whence int) (int64, error) {
 	case 1: // SEEK_CUR
 		newPos = h.position + offset
 	case 2: // SEEK_END
-		newPos = stat.Size + offset
+		size := stat.Size
+		if size == SizeUnknown {
+			// Generated content: its length is only known by reading it
+			data, err := h.fs.Read(h.path, 0, -1)
+			if err != nil && err != io.EOF {
+				return 0, err
+			}
+			size = int64(len(data))
+		}
+		newPos = size + offset
 	default:
 		return 0, fmt.Errorf("invalid whence: %d", whence)
 	}
@@ -3,6 +3,7 @@ package filesystem
 import (
 	"errors"
 	"fmt"
+	"time"
 )
 
 // Standard error types for filesystem operations
@@ -26,6 +27,17 @@ var (
 
 	// ErrNotSupported indicates the operation is not supported by this filesystem
 	ErrNotSupported = errors.New("operation not supported")
+
+	// ErrBusy indicates the resource is held by another writer; retrying later may succeed
+	ErrBusy = errors.New("resource busy")
+
+	// ErrTryAgain indicates the backend asked for backoff, e.g. because an
+	// upstream rate limit was hit; the request can be retried after a while
+	ErrTryAgain = errors.New("try again later")
+
+	// ErrStale indicates the content changed since the caller last read it
+	// (a conditional write's ETag no longer matches)
+	ErrStale = errors.New("stale content")
 )
 
 // NotFoundError represents a file or directory not found error with context
@@ -131,6 +143,28 @@ func (e *NotSupportedError) Is(target error) bool {
 	return target == ErrNotSupported
 }
 
+// BackoffError is ErrTryAgain with the backend's hint of when to retry
+type BackoffError struct {
+	Path       string
+	Op         string
+	RetryAfter time.Duration // Zero if the backend gave no hint
+}
+
+func (e *BackoffError) Error() string {
+	msg := "backend busy"
+	if e.RetryAfter > 0 {
+		msg = fmt.Sprintf("backend busy, retry after %s", e.RetryAfter)
+	}
+	if e.Op != "" {
+		return fmt.Sprintf("%s: %s: %s", e.Op, e.Path, msg)
+	}
+	return msg
+}
+
+func (e *BackoffError) Is(target error) bool {
+	return target == ErrTryAgain
+}
+
 // Helper functions to create common errors
 
 // NewNotFoundError creates a new NotFoundError
@@ -162,3 +196,8 @@ func NewNotDirectoryError(path string) error {
 func NewNotSupportedError(op, path string) error {
 	return &NotSupportedError{Op: op, Path: path}
 }
+
+// NewBackoffError creates a new BackoffError
+func NewBackoffError(op, path string, retryAfter time.Duration) error {
+	return &BackoffError{Op: op, Path: path, RetryAfter: retryAfter}
+}
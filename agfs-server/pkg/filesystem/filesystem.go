@@ -1,7 +1,9 @@
 package filesystem
 
 import (
+	"context"
 	"io"
+	"path"
 	"time"
 )
 
@@ -26,6 +28,9 @@ const (
 
 	// WriteFlagSync syncs the file after writing (fsync)
 	WriteFlagSync WriteFlag = 1 << 4
+
+	// WriteFlagIfMatch marks a write made by WriteIf after the expected ETag matched
+	WriteFlagIfMatch WriteFlag = 1 << 5
 )
 
 // OpenFlag defines file open flags (similar to os.O_* flags)
@@ -48,14 +53,32 @@ type MetaData struct {
 	Content map[string]string // Additional extensible metadata
 }
 
+// SizeUnknown is the FileInfo.Size of a file whose content is generated on
+// read. Such files are read until a read returns no data.
+const SizeUnknown int64 = -1
+
 // FileInfo represents file metadata similar to os.FileInfo
 type FileInfo struct {
-	Name    string
-	Size    int64
-	Mode    uint32
-	ModTime time.Time
-	IsDir   bool
-	Meta    MetaData // Structured metadata for additional information
+	Name        string
+	Size        int64 // Bytes, or SizeUnknown
+	Mode        uint32
+	ModTime     time.Time
+	IsDir       bool
+	Meta        MetaData    // Structured metadata for additional information
+	ETag        string      // Opaque content version (e.g. HTTP ETag or content hash); empty if unknown
+	ContentType string      // MIME type served by the HTTP gateway; empty for application/octet-stream
+	Checksum    string      // Content checksum as <algorithm>:<hex>, e.g. crc32c:e3069283; empty if unknown
+	ExpiresAt   time.Time   // Time from which the entry is stale and should be refetched or dropped; zero if never
+	Hidden      bool        // Left out of directory listings unless asked for, e.g. a tombstone or sync state
+	Locks       []RangeLock // Locked byte ranges and their holders, for contention diagnostics
+}
+
+// RangeLock is a locked byte range of a file
+type RangeLock struct {
+	Holder    string `json:"holder"` // Who holds the lock, e.g. a client or handle ID
+	Offset    uint64 `json:"offset"`
+	Length    uint64 `json:"length"` // 0 means to the end of the file
+	Exclusive bool   `json:"exclusive"`
 }
 
 // FileSystem defines the interface for a POSIX-like file system
@@ -127,6 +150,26 @@ type Streamer interface {
 	OpenStream(path string) (StreamReader, error)
 }
 
+// UploadSession is the state of a resumable upload
+type UploadSession struct {
+	ID       string `json:"id"`
+	Path     string `json:"path"`
+	Received uint64 `json:"received"` // bytes staged so far; the offset of the next append
+}
+
+// Uploader is implemented by file systems that support resumable uploads
+// Data is staged outside the plugin until committed, so a session survives
+// dropped connections and plugin restarts and resumes at Received
+type Uploader interface {
+	BeginUpload(path string) (*UploadSession, error)
+	UploadStatus(id string) (*UploadSession, error)
+	// UploadAppend fails unless offset equals the bytes received so far
+	UploadAppend(id string, offset uint64, data []byte) (*UploadSession, error)
+	CommitUpload(id string) error
+	AbortUpload(id string) error
+	ListUploads() ([]UploadSession, error)
+}
+
 // Toucher is implemented by file systems that support efficient touch operations
 // Touch updates the modification time without reading/writing the entire file content
 type Toucher interface {
@@ -147,3 +190,260 @@ type Symlinker interface {
 	// Returns the target path and error if the operation fails
 	Readlink(linkPath string) (string, error)
 }
+
+// RealPather is implemented by file systems whose paths may traverse
+// symbolic links, so callers confining access to a subtree can check where
+// a path really leads
+type RealPather interface {
+	// RealPath returns path with every symbolic link resolved. Components
+	// that do not exist yet are kept as given; links leading outside the
+	// file system, or to nothing, fail with a PermissionDeniedError.
+	RealPath(path string) (string, error)
+}
+
+// Xattrer is implemented by file systems that support extended attributes
+type Xattrer interface {
+	// GetXattr returns the value of extended attribute name on path
+	GetXattr(path, name string) ([]byte, error)
+
+	// SetXattr creates or replaces extended attribute name on path
+	SetXattr(path, name string, value []byte) error
+
+	// ListXattr returns the names of the extended attributes set on path
+	ListXattr(path string) ([]string, error)
+}
+
+// Advice is an access pattern hint; values match posix_fadvise
+type Advice int32
+
+const (
+	AdviceNormal Advice = iota
+	AdviceRandom
+	AdviceSequential
+	AdviceWillNeed
+	AdviceDontNeed
+)
+
+// Adviser is implemented by file systems that accept access pattern hints
+// Hints let slow backends start or stop read-ahead; they never change results
+type Adviser interface {
+	// Advise hints how length bytes of path starting at offset will be read
+	// length <= 0 means up to the end of the file
+	Advise(path string, offset, length int64, advice Advice) error
+}
+
+// ConditionalReader is implemented by file systems that can skip reads of
+// unchanged content, identified by FileInfo.ETag
+type ConditionalReader interface {
+	// ReadIfChanged reads the whole file unless its ETag still equals etag
+	// Returns changed == false (and no data) when the content is unchanged
+	ReadIfChanged(path, etag string) (data []byte, changed bool, err error)
+}
+
+// ReadResult is the outcome of reading one file in a batch
+type ReadResult struct {
+	Data []byte
+	Err  error
+}
+
+// BatchReader is implemented by file systems that can read many whole files
+// in one call, for clients dominated by per-call latency
+type BatchReader interface {
+	// ReadMany returns one result per path, in order; a failed path fails
+	// only its own result
+	ReadMany(paths []string) []ReadResult
+}
+
+// FsOp is one mutation in a Batcher batch. Op is "create", "mkdir", "write"
+// (replacing the whole file), "remove", "remove_all", "rename" or "chmod".
+type FsOp struct {
+	Op      string `json:"op"`
+	Path    string `json:"path,omitempty"`
+	Data    []byte `json:"data,omitempty"`
+	Perm    uint32 `json:"perm,omitempty"`
+	Mode    uint32 `json:"mode,omitempty"`
+	OldPath string `json:"old_path,omitempty"`
+	NewPath string `json:"new_path,omitempty"`
+}
+
+// Target is the path an operation is reported against
+func (op FsOp) Target() string {
+	if op.Op == "rename" {
+		return op.OldPath
+	}
+	return op.Path
+}
+
+// Batcher is implemented by file systems that can apply several mutations in
+// one call, such as a rename, write and remove committed together
+type Batcher interface {
+	// Batch applies ops in order and returns one error (nil on success) per
+	// op. Ops after a failure are not applied and report context.Canceled.
+	Batch(ops []FsOp) ([]error, error)
+	// AtomicBatch reports whether a failed Batch leaves every op unapplied,
+	// as when the backend runs it in one transaction
+	AtomicBatch() bool
+}
+
+// ApplyOps applies ops one at a time, stopping at the first failure; the ops
+// after it report context.Canceled. It is the Batch of file systems without
+// native batches.
+func ApplyOps(fs FileSystem, ops []FsOp) []error {
+	errs := make([]error, len(ops))
+	for i, op := range ops {
+		if i > 0 && errs[i-1] != nil {
+			errs[i] = context.Canceled
+			continue
+		}
+		switch op.Op {
+		case "create":
+			errs[i] = fs.Create(op.Path)
+		case "mkdir":
+			errs[i] = fs.Mkdir(op.Path, op.Perm)
+		case "write":
+			_, errs[i] = fs.Write(op.Path, op.Data, 0, WriteFlagCreate|WriteFlagTruncate)
+		case "remove":
+			errs[i] = fs.Remove(op.Path)
+		case "remove_all":
+			errs[i] = fs.RemoveAll(op.Path)
+		case "rename":
+			errs[i] = fs.Rename(op.OldPath, op.NewPath)
+		case "chmod":
+			errs[i] = fs.Chmod(op.Path, op.Mode)
+		default:
+			errs[i] = NewInvalidArgumentError("op", op.Op, "unknown batch operation")
+		}
+	}
+	return errs
+}
+
+// Maintainer is implemented by file systems with periodic upkeep, such as
+// compaction, cache eviction or index rebuilds
+type Maintainer interface {
+	// Maintain runs one round of upkeep. MountableFS calls it on the
+	// schedule set by the mount's maintain_interval, never concurrently
+	// with itself.
+	Maintain() error
+}
+
+// WarmupProgress reports how far a Warmer has got, in units it chooses
+type WarmupProgress struct {
+	Done  uint64 `json:"done"`
+	Total uint64 `json:"total"`
+}
+
+// Complete reports whether nothing is left to warm up
+func (p WarmupProgress) Complete() bool {
+	return p.Done >= p.Total
+}
+
+// Warmer is implemented by file systems that pre-populate caches after
+// mount, such as front-page stories or bucket prefix listings
+type Warmer interface {
+	// Warmup does at most budget of warm-up work. MountableFS calls it in
+	// the background until it reports completion, fails, or the mount's
+	// warmup_budget is spent.
+	Warmup(budget time.Duration) (WarmupProgress, error)
+}
+
+// StateTransferer is implemented by file systems that can hand caches,
+// handle metadata or bookmarks to a newer build of themselves
+type StateTransferer interface {
+	// ExportState serializes whatever the replacement instance should
+	// keep; nil means nothing to transfer
+	ExportState() ([]byte, error)
+
+	// ImportState loads state exported by the instance being replaced,
+	// before it serves any request
+	ImportState(state []byte) error
+}
+
+// WriteSessioner is implemented by file systems that can group writes into
+// one upstream change, such as a single git commit or pull request
+type WriteSessioner interface {
+	// BeginWriteSession starts staging writes; it fails with ErrBusy if a
+	// session is already open
+	BeginWriteSession() error
+	// CommitSession publishes the staged writes as one change described
+	// by message
+	CommitSession(message string) error
+	// AbortSession discards the staged writes
+	AbortSession() error
+}
+
+// FilteredDirReader is implemented by file systems that can list only the
+// directory entries matching a glob, e.g. from an index or a backend query
+type FilteredDirReader interface {
+	// ReadDirFiltered returns the entries of path whose names match glob
+	// (* and ? within a name; empty matches all), at most limit (0 = all)
+	ReadDirFiltered(path, glob string, limit int) ([]FileInfo, error)
+}
+
+// WalkEntry is one file or directory found by a Walker
+type WalkEntry struct {
+	Path string   `json:"Path"`
+	Info FileInfo `json:"Info"`
+}
+
+// Walker is implemented by file systems that can list a whole subtree in one
+// call, sparing clients a ReadDir and Stat round trip per directory
+type Walker interface {
+	// Walk returns every entry below path in depth-first preorder, descending
+	// at most depth levels (0 = no limit); path itself is not included
+	Walk(path string, depth int) ([]WalkEntry, error)
+}
+
+// Listing is a directory listing and the generation of the directory it was
+// taken at; Generation is 0 when the file system does not track it
+type Listing struct {
+	Entries    []FileInfo `json:"entries"`
+	Generation uint64     `json:"generation"`
+}
+
+// ChangedDirReader is implemented by file systems that version their
+// directory listings, so a caller holding a listing can check it is still
+// current without having the whole listing sent again
+type ChangedDirReader interface {
+	// ReadDirIfChanged returns the listing of path, or nil if the directory
+	// is still at generation
+	ReadDirIfChanged(path string, generation uint64) (*Listing, error)
+}
+
+// FilterEntries applies a FilteredDirReader glob and limit to a full listing
+func FilterEntries(infos []FileInfo, glob string, limit int) []FileInfo {
+	matching := []FileInfo{}
+	for _, info := range infos {
+		if limit > 0 && len(matching) == limit {
+			break
+		}
+		if ok, _ := path.Match(glob, info.Name); glob == "" || ok {
+			matching = append(matching, info)
+		}
+	}
+	return matching
+}
+
+// ConditionalWriter is implemented by file systems that can replace a file
+// only if it is unchanged, identified by FileInfo.ETag
+type ConditionalWriter interface {
+	// WriteIf replaces the file's contents if its ETag equals expectedETag
+	// (empty: the file must not exist); returns ErrStale on mismatch and
+	// ErrBusy if another writer holds the file
+	WriteIf(path string, data []byte, expectedETag string) (int64, error)
+}
+
+// ContextReader is implemented by file systems whose reads can be abandoned
+// part way, e.g. when the client interrupts a long `cat`
+type ContextReader interface {
+	// ReadContext is Read, cancelled in the backend once ctx is done
+	// Returns ctx.Err() for a cancelled read
+	ReadContext(ctx context.Context, path string, offset int64, size int64) ([]byte, error)
+}
+
+// PollReader is implemented by file systems that can wait for data to be
+// appended to a file, so a client following it (tail -f) need not poll
+type PollReader interface {
+	// PollRead is Read, waiting up to timeout for data at offset when there
+	// is none yet; it returns no data if none arrived in time
+	PollRead(ctx context.Context, path string, offset int64, size int64, timeout time.Duration) ([]byte, error)
+}
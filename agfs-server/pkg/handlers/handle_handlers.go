@@ -125,8 +125,7 @@ func (h *Handler) OpenHandle(w http.ResponseWriter, r *http.Request) {
 
 	handle, err := handleFS.OpenHandle(path, flags, mode)
 	if err != nil {
-		status := mapErrorToStatus(err)
-		writeError(w, status, err.Error())
+		writeFSError(w, err)
 		return
 	}
 
@@ -158,8 +157,7 @@ func (h *Handler) GetHandle(w http.ResponseWriter, r *http.Request, handleIDStr
 
 	handle, err := handleFS.GetHandle(handleID)
 	if err != nil {
-		status := mapErrorToStatus(err)
-		writeError(w, status, err.Error())
+		writeFSError(w, err)
 		return
 	}
 
@@ -191,8 +189,7 @@ func (h *Handler) CloseHandle(w http.ResponseWriter, r *http.Request, handleIDSt
 	}
 
 	if err := handleFS.CloseHandle(handleID); err != nil {
-		status := mapErrorToStatus(err)
-		writeError(w, status, err.Error())
+		writeFSError(w, err)
 		return
 	}
 
@@ -215,8 +212,7 @@ func (h *Handler) HandleRead(w http.ResponseWriter, r *http.Request, handleIDStr
 
 	handle, err := handleFS.GetHandle(handleID)
 	if err != nil {
-		status := mapErrorToStatus(err)
-		writeError(w, status, err.Error())
+		writeFSError(w, err)
 		return
 	}
 
@@ -293,8 +289,7 @@ func (h *Handler) HandleWrite(w http.ResponseWriter, r *http.Request, handleIDSt
 
 	handle, err := handleFS.GetHandle(handleID)
 	if err != nil {
-		status := mapErrorToStatus(err)
-		writeError(w, status, err.Error())
+		writeFSError(w, err)
 		return
 	}
 
@@ -354,8 +349,7 @@ func (h *Handler) HandleSeek(w http.ResponseWriter, r *http.Request, handleIDStr
 
 	handle, err := handleFS.GetHandle(handleID)
 	if err != nil {
-		status := mapErrorToStatus(err)
-		writeError(w, status, err.Error())
+		writeFSError(w, err)
 		return
 	}
 
@@ -409,8 +403,7 @@ func (h *Handler) HandleSync(w http.ResponseWriter, r *http.Request, handleIDStr
 
 	handle, err := handleFS.GetHandle(handleID)
 	if err != nil {
-		status := mapErrorToStatus(err)
-		writeError(w, status, err.Error())
+		writeFSError(w, err)
 		return
 	}
 
@@ -438,25 +431,28 @@ func (h *Handler) HandleStat(w http.ResponseWriter, r *http.Request, handleIDStr
 
 	handle, err := handleFS.GetHandle(handleID)
 	if err != nil {
-		status := mapErrorToStatus(err)
-		writeError(w, status, err.Error())
+		writeFSError(w, err)
 		return
 	}
 
 	info, err := handle.Stat()
 	if err != nil {
-		status := mapErrorToStatus(err)
-		writeError(w, status, err.Error())
+		writeFSError(w, err)
 		return
 	}
 
 	response := FileInfoResponse{
-		Name:    info.Name,
-		Size:    info.Size,
-		Mode:    info.Mode,
-		ModTime: info.ModTime.Format(time.RFC3339Nano),
-		IsDir:   info.IsDir,
-		Meta:    info.Meta,
+		Name:        info.Name,
+		Size:        info.Size,
+		Mode:        info.Mode,
+		ModTime:     info.ModTime.Format(time.RFC3339Nano),
+		IsDir:       info.IsDir,
+		Meta:        info.Meta,
+		ContentType: info.ContentType,
+		Checksum:    info.Checksum,
+		ExpiresAt:   formatExpiry(info.ExpiresAt),
+		Hidden:      info.Hidden,
+		Locks:       info.Locks,
 	}
 
 	writeJSON(w, http.StatusOK, response)
@@ -479,8 +475,7 @@ func (h *Handler) HandleStream(w http.ResponseWriter, r *http.Request, handleIDS
 
 	handle, err := handleFS.GetHandle(handleID)
 	if err != nil {
-		status := mapErrorToStatus(err)
-		writeError(w, status, err.Error())
+		writeFSError(w, err)
 		return
 	}
 
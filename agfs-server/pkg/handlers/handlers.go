@@ -9,6 +9,7 @@ import (
 	"errors"
 	"fmt"
 	"io"
+	"mime"
 	"net/http"
 	"os"
 	"path/filepath"
@@ -62,12 +63,26 @@ type SuccessResponse struct {
 
 // FileInfoResponse represents file info response
 type FileInfoResponse struct {
-	Name    string              `json:"name"`
-	Size    int64               `json:"size"`
-	Mode    uint32              `json:"mode"`
-	ModTime string              `json:"modTime"`
-	IsDir   bool                `json:"isDir"`
-	Meta    filesystem.MetaData `json:"meta,omitempty"` // Structured metadata
+	Name        string                 `json:"name"`
+	Size        int64                  `json:"size"`
+	Mode        uint32                 `json:"mode"`
+	ModTime     string                 `json:"modTime"`
+	IsDir       bool                   `json:"isDir"`
+	Meta        filesystem.MetaData    `json:"meta,omitempty"` // Structured metadata
+	ContentType string                 `json:"contentType,omitempty"`
+	Checksum    string                 `json:"checksum,omitempty"`
+	ExpiresAt   string                 `json:"expiresAt,omitempty"`
+	Hidden      bool                   `json:"hidden,omitempty"`
+	Locks       []filesystem.RangeLock `json:"locks,omitempty"`
+}
+
+// formatExpiry renders a FileInfo.ExpiresAt, empty for entries that never
+// expire
+func formatExpiry(t time.Time) string {
+	if t.IsZero() {
+		return ""
+	}
+	return t.Format(time.RFC3339)
 }
 
 // ListResponse represents directory listing response
@@ -140,9 +155,29 @@ func mapErrorToStatus(err error) int {
 	if errors.Is(err, filesystem.ErrNotSupported) {
 		return http.StatusNotImplemented
 	}
+	if errors.Is(err, filesystem.ErrStale) {
+		return http.StatusPreconditionFailed
+	}
+	if errors.Is(err, filesystem.ErrBusy) {
+		return http.StatusLocked
+	}
+	if errors.Is(err, filesystem.ErrTryAgain) {
+		return http.StatusServiceUnavailable
+	}
 	return http.StatusInternalServerError
 }
 
+// writeFSError writes a filesystem error with the status mapErrorToStatus
+// picks, adding Retry-After when the backend asked clients to back off
+func writeFSError(w http.ResponseWriter, err error) {
+	var backoff *filesystem.BackoffError
+	if errors.As(err, &backoff) && backoff.RetryAfter > 0 {
+		seconds := (backoff.RetryAfter + time.Second - 1) / time.Second
+		w.Header().Set("Retry-After", strconv.FormatInt(int64(seconds), 10))
+	}
+	writeError(w, mapErrorToStatus(err), err.Error())
+}
+
 // CreateFile handles POST /files?path=<path>
 func (h *Handler) CreateFile(w http.ResponseWriter, r *http.Request) {
 	path := r.URL.Query().Get("path")
@@ -152,8 +187,7 @@ func (h *Handler) CreateFile(w http.ResponseWriter, r *http.Request) {
 	}
 
 	if err := h.fs.Create(path); err != nil {
-		status := mapErrorToStatus(err)
-		writeError(w, status, err.Error())
+		writeFSError(w, err)
 		return
 	}
 
@@ -180,15 +214,17 @@ func (h *Handler) CreateDirectory(w http.ResponseWriter, r *http.Request) {
 	}
 
 	if err := h.fs.Mkdir(path, mode); err != nil {
-		status := mapErrorToStatus(err)
-		writeError(w, status, err.Error())
+		writeFSError(w, err)
 		return
 	}
 
 	writeJSON(w, http.StatusCreated, SuccessResponse{Message: "directory created"})
 }
 
-// ReadFile handles GET /files?path=<path>&offset=<offset>&size=<size>&stream=<true|false>
+// maxReadWait caps the wait parameter of ReadFile
+const maxReadWait = time.Minute
+
+// ReadFile handles GET /files?path=<path>&offset=<offset>&size=<size>&stream=<true|false>&wait=<duration>
 func (h *Handler) ReadFile(w http.ResponseWriter, r *http.Request) {
 	path := r.URL.Query().Get("path")
 	if path == "" {
@@ -225,7 +261,28 @@ func (h *Handler) ReadFile(w http.ResponseWriter, r *http.Request) {
 		}
 	}
 
-	data, err := h.fs.Read(path, offset, size)
+	// Following a growing file: hold the request until data arrives at
+	// offset, or the wait is over
+	wait := time.Duration(0)
+	if waitStr := r.URL.Query().Get("wait"); waitStr != "" {
+		parsedWait, err := time.ParseDuration(waitStr)
+		if err != nil || parsedWait < 0 {
+			writeError(w, http.StatusBadRequest, "invalid wait parameter")
+			return
+		}
+		wait = min(parsedWait, maxReadWait)
+	}
+
+	var data []byte
+	var err error
+	if pr, ok := h.fs.(filesystem.PollReader); ok && wait > 0 {
+		data, err = pr.PollRead(r.Context(), path, offset, size, wait)
+	} else if cr, ok := h.fs.(filesystem.ContextReader); ok {
+		// Lets the plugin stop the read if the client goes away
+		data, err = cr.ReadContext(r.Context(), path, offset, size)
+	} else {
+		data, err = h.fs.Read(path, offset, size)
+	}
 	if err != nil {
 		// Check if it's EOF (reached end of file)
 		if err == io.EOF {
@@ -239,12 +296,11 @@ func (h *Handler) ReadFile(w http.ResponseWriter, r *http.Request) {
 			return
 		}
 		// Map error to appropriate HTTP status code
-		status := mapErrorToStatus(err)
-		writeError(w, status, err.Error())
+		writeFSError(w, err)
 		return
 	}
 
-	w.Header().Set("Content-Type", "application/octet-stream")
+	w.Header().Set("Content-Type", h.contentType(path))
 	w.WriteHeader(http.StatusOK)
 	w.Write(data)
 
@@ -254,6 +310,19 @@ func (h *Handler) ReadFile(w http.ResponseWriter, r *http.Request) {
 	}
 }
 
+// contentType returns the MIME type of path's extension. Only paths with no
+// known extension cost a plugin Stat, for the type the plugin reports;
+// otherwise it is application/octet-stream.
+func (h *Handler) contentType(path string) string {
+	if t := mime.TypeByExtension(filepath.Ext(path)); t != "" {
+		return t
+	}
+	if info, err := h.fs.Stat(path); err == nil && info.ContentType != "" {
+		return info.ContentType
+	}
+	return "application/octet-stream"
+}
+
 // WriteFile handles PUT /files?path=<path>
 func (h *Handler) WriteFile(w http.ResponseWriter, r *http.Request) {
 	path := r.URL.Query().Get("path")
@@ -279,8 +348,7 @@ func (h *Handler) WriteFile(w http.ResponseWriter, r *http.Request) {
 	bytesWritten, err := h.fs.Write(path, data, -1, filesystem.WriteFlagCreate|filesystem.WriteFlagTruncate)
 	if err != nil {
 		log.Errorf("[handler] WriteFile failed: path=%s, err=%v", path, err)
-		status := mapErrorToStatus(err)
-		writeError(w, status, err.Error())
+		writeFSError(w, err)
 		return
 	}
 
@@ -307,8 +375,7 @@ func (h *Handler) Delete(w http.ResponseWriter, r *http.Request) {
 	}
 
 	if err != nil {
-		status := mapErrorToStatus(err)
-		writeError(w, status, err.Error())
+		writeFSError(w, err)
 		return
 	}
 
@@ -325,20 +392,24 @@ func (h *Handler) ListDirectory(w http.ResponseWriter, r *http.Request) {
 	files, err := h.fs.ReadDir(path)
 	if err != nil {
 		// Map error to appropriate HTTP status code
-		status := mapErrorToStatus(err)
-		writeError(w, status, err.Error())
+		writeFSError(w, err)
 		return
 	}
 
 	var response ListResponse
 	for _, f := range files {
 		response.Files = append(response.Files, FileInfoResponse{
-			Name:    f.Name,
-			Size:    f.Size,
-			Mode:    f.Mode,
-			ModTime: f.ModTime.Format(time.RFC3339Nano),
-			IsDir:   f.IsDir,
-			Meta:    f.Meta,
+			Name:        f.Name,
+			Size:        f.Size,
+			Mode:        f.Mode,
+			ModTime:     f.ModTime.Format(time.RFC3339Nano),
+			IsDir:       f.IsDir,
+			Meta:        f.Meta,
+			ContentType: f.ContentType,
+			Checksum:    f.Checksum,
+			ExpiresAt:   formatExpiry(f.ExpiresAt),
+			Hidden:      f.Hidden,
+			Locks:       f.Locks,
 		})
 	}
 
@@ -362,17 +433,22 @@ func (h *Handler) Stat(w http.ResponseWriter, r *http.Request) {
 		} else {
 			log.Errorf("Stat error for path %s: %v (from %s)", path, err, r.RemoteAddr)
 		}
-		writeError(w, status, err.Error())
+		writeFSError(w, err)
 		return
 	}
 
 	response := FileInfoResponse{
-		Name:    info.Name,
-		Size:    info.Size,
-		Mode:    info.Mode,
-		ModTime: info.ModTime.Format(time.RFC3339Nano),
-		IsDir:   info.IsDir,
-		Meta:    info.Meta,
+		Name:        info.Name,
+		Size:        info.Size,
+		Mode:        info.Mode,
+		ModTime:     info.ModTime.Format(time.RFC3339Nano),
+		IsDir:       info.IsDir,
+		Meta:        info.Meta,
+		ContentType: info.ContentType,
+		Checksum:    info.Checksum,
+		ExpiresAt:   formatExpiry(info.ExpiresAt),
+		Hidden:      info.Hidden,
+		Locks:       info.Locks,
 	}
 
 	writeJSON(w, http.StatusOK, response)
@@ -398,8 +474,7 @@ func (h *Handler) Rename(w http.ResponseWriter, r *http.Request) {
 	}
 
 	if err := h.fs.Rename(path, req.NewPath); err != nil {
-		status := mapErrorToStatus(err)
-		writeError(w, status, err.Error())
+		writeFSError(w, err)
 		return
 	}
 
@@ -421,8 +496,7 @@ func (h *Handler) Chmod(w http.ResponseWriter, r *http.Request) {
 	}
 
 	if err := h.fs.Chmod(path, req.Mode); err != nil {
-		status := mapErrorToStatus(err)
-		writeError(w, status, err.Error())
+		writeFSError(w, err)
 		return
 	}
 
@@ -592,8 +666,7 @@ func (h *Handler) Touch(w http.ResponseWriter, r *http.Request) {
 		// Use efficient touch implementation
 		err := toucher.Touch(path)
 		if err != nil {
-			status := mapErrorToStatus(err)
-			writeError(w, status, err.Error())
+			writeFSError(w, err)
 			return
 		}
 		writeJSON(w, http.StatusOK, SuccessResponse{Message: "touched"})
@@ -608,14 +681,12 @@ func (h *Handler) Touch(w http.ResponseWriter, r *http.Request) {
 		if !info.IsDir {
 			data, readErr := h.fs.Read(path, 0, -1)
 			if readErr != nil {
-				status := mapErrorToStatus(readErr)
-				writeError(w, status, readErr.Error())
+				writeFSError(w, readErr)
 				return
 			}
 			_, writeErr := h.fs.Write(path, data, -1, filesystem.WriteFlagTruncate)
 			if writeErr != nil {
-				status := mapErrorToStatus(writeErr)
-				writeError(w, status, writeErr.Error())
+				writeFSError(w, writeErr)
 				return
 			}
 		} else {
@@ -627,8 +698,7 @@ func (h *Handler) Touch(w http.ResponseWriter, r *http.Request) {
 		// File doesn't exist - create with empty content
 		_, err := h.fs.Write(path, []byte{}, -1, filesystem.WriteFlagCreate)
 		if err != nil {
-			status := mapErrorToStatus(err)
-			writeError(w, status, err.Error())
+			writeFSError(w, err)
 			return
 		}
 	}
@@ -663,8 +733,7 @@ func (h *Handler) Symlink(w http.ResponseWriter, r *http.Request) {
 	}
 
 	if err := symlinker.Symlink(req.Target, linkPath); err != nil {
-		status := mapErrorToStatus(err)
-		writeError(w, status, err.Error())
+		writeFSError(w, err)
 		return
 	}
 
@@ -688,8 +757,7 @@ func (h *Handler) Readlink(w http.ResponseWriter, r *http.Request) {
 
 	target, err := symlinker.Readlink(linkPath)
 	if err != nil {
-		status := mapErrorToStatus(err)
-		writeError(w, status, err.Error())
+		writeFSError(w, err)
 		return
 	}
 
@@ -729,8 +797,7 @@ func (h *Handler) Truncate(w http.ResponseWriter, r *http.Request) {
 	}
 
 	if err := truncater.Truncate(path, size); err != nil {
-		status := mapErrorToStatus(err)
-		writeError(w, status, err.Error())
+		writeFSError(w, err)
 		return
 	}
 
@@ -796,8 +863,7 @@ func (h *Handler) SetupRoutes(mux *http.ServeMux) {
 			}
 			bytesWritten, err := h.fs.Write(path, data, -1, filesystem.WriteFlagCreate|filesystem.WriteFlagTruncate)
 			if err != nil {
-				status := mapErrorToStatus(err)
-				writeError(w, status, err.Error())
+				writeFSError(w, err)
 				return
 			}
 			writeJSON(w, http.StatusOK, SuccessResponse{Message: fmt.Sprintf("Written %d bytes", bytesWritten)})
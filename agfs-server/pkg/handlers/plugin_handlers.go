@@ -129,6 +129,38 @@ func (ph *PluginHandler) Mount(w http.ResponseWriter, r *http.Request) {
 	writeJSON(w, http.StatusOK, SuccessResponse{Message: "plugin mounted"})
 }
 
+// UpgradeRequest represents a request to replace the plugin at a mount
+type UpgradeRequest struct {
+	FSType string `json:"fstype"`
+	Path   string `json:"path"`
+}
+
+// Upgrade handles POST /upgrade
+func (ph *PluginHandler) Upgrade(w http.ResponseWriter, r *http.Request) {
+	var req UpgradeRequest
+	if err := json.NewDecoder(r.Body).Decode(&req); err != nil {
+		writeError(w, http.StatusBadRequest, "invalid request body")
+		return
+	}
+
+	if req.FSType == "" {
+		writeError(w, http.StatusBadRequest, "fstype is required")
+		return
+	}
+
+	if req.Path == "" {
+		writeError(w, http.StatusBadRequest, "path is required")
+		return
+	}
+
+	if err := ph.mfs.UpgradeMount(req.Path, req.FSType); err != nil {
+		writeError(w, http.StatusInternalServerError, err.Error())
+		return
+	}
+
+	writeJSON(w, http.StatusOK, SuccessResponse{Message: "plugin upgraded"})
+}
+
 
 // LoadPluginRequest represents a request to load an external plugin
 type LoadPluginRequest struct {
@@ -450,6 +482,14 @@ func (ph *PluginHandler) SetupRoutes(mux *http.ServeMux) {
 		ph.Unmount(w, r)
 	})
 
+	mux.HandleFunc("/api/v1/upgrade", func(w http.ResponseWriter, r *http.Request) {
+		if r.Method != http.MethodPost {
+			writeError(w, http.StatusMethodNotAllowed, "method not allowed")
+			return
+		}
+		ph.Upgrade(w, r)
+	})
+
 	// External plugin management endpoints
 	mux.HandleFunc("/api/v1/plugins", func(w http.ResponseWriter, r *http.Request) {
 		if r.Method != http.MethodGet {
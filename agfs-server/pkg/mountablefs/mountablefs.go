@@ -1,6 +1,7 @@
 package mountablefs
 
 import (
+	"context"
 	"fmt"
 	"io"
 	"path/filepath"
@@ -12,6 +13,7 @@ import (
 	"github.com/c4pt0r/agfs/agfs-server/pkg/filesystem"
 	"github.com/c4pt0r/agfs/agfs-server/pkg/plugin"
 	"github.com/c4pt0r/agfs/agfs-server/pkg/plugin/api"
+	pluginconfig "github.com/c4pt0r/agfs/agfs-server/pkg/plugin/config"
 	"github.com/c4pt0r/agfs/agfs-server/pkg/plugin/loader"
 	iradix "github.com/hashicorp/go-immutable-radix"
 	log "github.com/sirupsen/logrus"
@@ -23,11 +25,117 @@ const (
 	MetaValueMountPoint = "mount-point"
 )
 
+// Mount config keys consumed by MountableFS and not passed to the plugin.
+// Both take a duration such as "10m", or seconds.
+const (
+	// MaintainIntervalKey sets how often the plugin's filesystem.Maintainer
+	// runs; unset or 0 means never
+	MaintainIntervalKey = "maintain_interval"
+	// WarmupBudgetKey caps the time spent in the plugin's filesystem.Warmer
+	// after mount; 0 disables warm-up
+	WarmupBudgetKey = "warmup_budget"
+)
+
+// DefaultWarmupBudget is the warm-up time of a mount without warmup_budget
+const DefaultWarmupBudget = 5 * time.Minute
+
+// warmupSlice is the budget of each Warmup call; requests are served in between
+const warmupSlice = time.Second
+
+// pollInterval is how often PollRead reads again a file whose plugin
+// returned no data without waiting for it
+const pollInterval = 250 * time.Millisecond
+
 // MountPoint represents a mounted service plugin
 type MountPoint struct {
 	Path   string
 	Plugin plugin.ServicePlugin
 	Config map[string]interface{} // Plugin configuration
+
+	stop       chan struct{}  // Closed on unmount to end background work; nil without any
+	background sync.WaitGroup // Maintenance and warm-up goroutines
+}
+
+// goBackground runs fn until it returns, which it should do once stop closes
+func (mp *MountPoint) goBackground(fn func(stop <-chan struct{})) {
+	if mp.stop == nil {
+		mp.stop = make(chan struct{})
+	}
+	mp.background.Add(1)
+	go func(stop <-chan struct{}) {
+		defer mp.background.Done()
+		fn(stop)
+	}(mp.stop)
+}
+
+// stopBackground ends background work, waiting out a running Maintain or
+// Warmup call
+func (mp *MountPoint) stopBackground() {
+	if mp.stop != nil {
+		close(mp.stop)
+		mp.background.Wait()
+	}
+}
+
+// startMaintenance calls the plugin's Maintain every interval until unmount
+func (mp *MountPoint) startMaintenance(interval time.Duration) {
+	maintainer, ok := mp.Plugin.GetFileSystem().(filesystem.Maintainer)
+	if !ok {
+		log.Warnf("%s: plugin has no maintenance hook, ignoring %s", mp.Path, MaintainIntervalKey)
+		return
+	}
+
+	mp.goBackground(func(stop <-chan struct{}) {
+		ticker := time.NewTicker(interval)
+		defer ticker.Stop()
+		for {
+			select {
+			case <-stop:
+				return
+			case <-ticker.C:
+				start := time.Now()
+				if err := maintainer.Maintain(); err != nil {
+					log.Warnf("%s: maintenance failed: %v", mp.Path, err)
+				} else {
+					log.Debugf("%s: maintenance took %v", mp.Path, time.Since(start))
+				}
+			}
+		}
+	})
+}
+
+// startWarmup calls the plugin's Warmup in slices until it completes, fails,
+// spends budget or the mount goes away
+func (mp *MountPoint) startWarmup(budget time.Duration) {
+	warmer, ok := mp.Plugin.GetFileSystem().(filesystem.Warmer)
+	if !ok {
+		return
+	}
+
+	mp.goBackground(func(stop <-chan struct{}) {
+		start := time.Now()
+		for {
+			slice := min(warmupSlice, budget-time.Since(start))
+			if slice <= 0 {
+				log.Infof("%s: warm-up stopped after its %v budget", mp.Path, budget)
+				return
+			}
+			progress, err := warmer.Warmup(slice)
+			if err != nil {
+				log.Warnf("%s: warm-up failed: %v", mp.Path, err)
+				return
+			}
+			if progress.Complete() {
+				log.Infof("%s: warm-up done in %v", mp.Path, time.Since(start).Round(time.Millisecond))
+				return
+			}
+			select {
+			case <-stop:
+				return
+			default:
+			}
+		}
+	})
 }
 
 // PluginFactory is a function that creates a new plugin instance
@@ -185,31 +293,33 @@ func (mfs *MountableFS) Mount(path string, plugin plugin.ServicePlugin) error {
 	return nil
 }
 
-// MountPlugin dynamically mounts a plugin at the specified path
-func (mfs *MountableFS) MountPlugin(fstype string, path string, config map[string]interface{}) error {
-	mfs.mu.Lock()
-	defer mfs.mu.Unlock()
-
-	// Normalize path
-	path = filesystem.NormalizePath(path)
-
-	// Load current tree
-	tree := mfs.mountTree.Load().(*iradix.Tree)
+// mountSchedule is the background work configured for a mount
+type mountSchedule struct {
+	maintainInterval time.Duration
+	warmupBudget     time.Duration
+}
 
-	// Check if path is already mounted
-	if _, exists := tree.Get([]byte(path)); exists {
-		return filesystem.NewAlreadyExistsError("mount", path)
+func (mp *MountPoint) startBackground(schedule *mountSchedule) {
+	if schedule.maintainInterval > 0 {
+		mp.startMaintenance(schedule.maintainInterval)
 	}
+	if schedule.warmupBudget > 0 {
+		mp.startWarmup(schedule.warmupBudget)
+	}
+}
 
-	// Get plugin factory
+// newPlugin creates an instance of fstype; the caller must hold mfs.mu
+func (mfs *MountableFS) newPlugin(fstype string) (plugin.ServicePlugin, error) {
 	factory, ok := mfs.pluginFactories[fstype]
 	if !ok {
-		return fmt.Errorf("unknown filesystem type: %s", fstype)
+		return nil, fmt.Errorf("unknown filesystem type: %s", fstype)
 	}
+	return factory(), nil
+}
 
-	// Create plugin instance
-	pluginInstance := factory()
-
+// initMountPlugin initializes pluginInstance for a mount at path and
+// returns the background work the mount config asks for
+func (mfs *MountableFS) initMountPlugin(pluginInstance plugin.ServicePlugin, fstype string, path string, config map[string]interface{}) (*mountSchedule, error) {
 	// Special handling for plugins that need rootFS reference
 	type rootFSSetter interface {
 		SetRootFS(filesystem.FileSystem)
@@ -235,22 +345,64 @@ func (mfs *MountableFS) MountPlugin(fstype string, path string, config map[strin
 	}
 	configWithPath["mount_path"] = path
 
+	maintainInterval, err := pluginconfig.GetDurationConfig(configWithPath, MaintainIntervalKey, 0)
+	if err != nil {
+		return nil, err
+	}
+	warmupBudget, err := pluginconfig.GetDurationConfig(configWithPath, WarmupBudgetKey, DefaultWarmupBudget)
+	if err != nil {
+		return nil, err
+	}
+	delete(configWithPath, MaintainIntervalKey)
+	delete(configWithPath, WarmupBudgetKey)
+
 	// Validate plugin configuration
 	if err := pluginInstance.Validate(configWithPath); err != nil {
-		return fmt.Errorf("failed to validate plugin: %v", err)
+		return nil, fmt.Errorf("failed to validate plugin: %v", err)
 	}
 
 	// Initialize plugin with config
 	if err := pluginInstance.Initialize(configWithPath); err != nil {
-		return fmt.Errorf("failed to initialize plugin: %v", err)
+		return nil, fmt.Errorf("failed to initialize plugin: %v", err)
 	}
 
-	// Create new tree with added mount
-	newTree, _, _ := tree.Insert([]byte(path), &MountPoint{
+	return &mountSchedule{maintainInterval, warmupBudget}, nil
+}
+
+// MountPlugin dynamically mounts a plugin at the specified path
+func (mfs *MountableFS) MountPlugin(fstype string, path string, config map[string]interface{}) error {
+	mfs.mu.Lock()
+	defer mfs.mu.Unlock()
+
+	// Normalize path
+	path = filesystem.NormalizePath(path)
+
+	// Load current tree
+	tree := mfs.mountTree.Load().(*iradix.Tree)
+
+	// Check if path is already mounted
+	if _, exists := tree.Get([]byte(path)); exists {
+		return filesystem.NewAlreadyExistsError("mount", path)
+	}
+
+	pluginInstance, err := mfs.newPlugin(fstype)
+	if err != nil {
+		return err
+	}
+	schedule, err := mfs.initMountPlugin(pluginInstance, fstype, path, config)
+	if err != nil {
+		return err
+	}
+
+	mount := &MountPoint{
 		Path:   path,
 		Plugin: pluginInstance,
 		Config: config,
-	})
+	}
+	mount.startBackground(schedule)
+
+	// Create new tree with added mount
+	newTree, _, _ := tree.Insert([]byte(path), mount)
 
 	// Atomically update tree
 	mfs.mountTree.Store(newTree)
@@ -275,6 +427,8 @@ func (mfs *MountableFS) Unmount(path string) error {
 	}
 	mount := val.(*MountPoint)
 
+	mount.stopBackground()
+
 	// Shutdown the plugin
 	if err := mount.Plugin.Shutdown(); err != nil {
 		return fmt.Errorf("failed to shutdown plugin: %v", err)
@@ -290,6 +444,86 @@ func (mfs *MountableFS) Unmount(path string) error {
 	return nil
 }
 
+// UpgradeMount replaces the plugin at path with a new instance of fstype,
+// keeping the mount's config. If the old instance is a StateTransferer its
+// state is handed to the new one before the switch, so caches, handle
+// metadata and bookmarks survive; if the transfer fails the old instance
+// keeps serving. fstype must produce a new instance, e.g. a plugin that was
+// just loaded again from an updated module.
+func (mfs *MountableFS) UpgradeMount(path string, fstype string) error {
+	mfs.mu.Lock()
+	defer mfs.mu.Unlock()
+
+	path = filesystem.NormalizePath(path)
+
+	tree := mfs.mountTree.Load().(*iradix.Tree)
+	val, exists := tree.Get([]byte(path))
+	if !exists {
+		return fmt.Errorf("no mount at path: %s", path)
+	}
+	old := val.(*MountPoint)
+
+	pluginInstance, err := mfs.newPlugin(fstype)
+	if err != nil {
+		return err
+	}
+	if pluginInstance == old.Plugin {
+		return fmt.Errorf("upgrade of %s: %s is the plugin already mounted there", path, fstype)
+	}
+	schedule, err := mfs.initMountPlugin(pluginInstance, fstype, path, old.Config)
+	if err != nil {
+		return err
+	}
+
+	if err := transferState(old.Plugin.GetFileSystem(), pluginInstance.GetFileSystem()); err != nil {
+		if shutdownErr := pluginInstance.Shutdown(); shutdownErr != nil {
+			log.Warnf("%s: failed to shut down new plugin after failed upgrade: %v", path, shutdownErr)
+		}
+		return fmt.Errorf("upgrade of %s: %w", path, err)
+	}
+
+	mount := &MountPoint{
+		Path:   path,
+		Plugin: pluginInstance,
+		Config: old.Config,
+	}
+	mount.startBackground(schedule)
+
+	newTree, _, _ := tree.Insert([]byte(path), mount)
+	mfs.mountTree.Store(newTree)
+
+	old.stopBackground()
+	if err := old.Plugin.Shutdown(); err != nil {
+		log.Warnf("%s: failed to shut down replaced plugin: %v", path, err)
+	}
+
+	log.Infof("upgraded %s to %s", path, fstype)
+	return nil
+}
+
+// transferState moves the state of from into to when both support it
+func transferState(from, to filesystem.FileSystem) error {
+	exporter, ok := from.(filesystem.StateTransferer)
+	if !ok {
+		return nil
+	}
+	importer, ok := to.(filesystem.StateTransferer)
+	if !ok {
+		return nil
+	}
+	state, err := exporter.ExportState()
+	if err != nil {
+		return fmt.Errorf("failed to export state: %w", err)
+	}
+	if len(state) == 0 {
+		return nil
+	}
+	if err := importer.ImportState(state); err != nil {
+		return fmt.Errorf("failed to import state: %w", err)
+	}
+	return nil
+}
+
 // LoadExternalPluginWithType loads a plugin with an explicitly specified type
 func (mfs *MountableFS) LoadExternalPluginWithType(libraryPath string, pluginType loader.PluginType) (plugin.ServicePlugin, error) {
 	// For WASM plugins, pass MountableFS as host filesystem to allow access to all agfs paths
@@ -548,6 +782,65 @@ func (mfs *MountableFS) Read(path string, offset int64, size int64) ([]byte, err
 	return nil, filesystem.NewNotFoundError("read", path)
 }
 
+// ReadContext implements filesystem.ContextReader for mounts that support it
+func (mfs *MountableFS) ReadContext(ctx context.Context, path string, offset int64, size int64) ([]byte, error) {
+	resolved, err := mfs.resolvePath(path)
+	if err != nil {
+		return nil, err
+	}
+
+	mount, relPath, found := mfs.findMount(resolved)
+	if !found {
+		return nil, filesystem.NewNotFoundError("read", path)
+	}
+	if cr, ok := mount.Plugin.GetFileSystem().(filesystem.ContextReader); ok {
+		return cr.ReadContext(ctx, relPath, offset, size)
+	}
+	return mount.Plugin.GetFileSystem().Read(relPath, offset, size)
+}
+
+// PollRead implements filesystem.PollReader for every mount. Plugins that
+// can wait for data are given the whole timeout; the others are read again
+// every pollInterval until data arrives, the timeout passes or ctx is done.
+func (mfs *MountableFS) PollRead(ctx context.Context, path string, offset int64, size int64, timeout time.Duration) ([]byte, error) {
+	resolved, err := mfs.resolvePath(path)
+	if err != nil {
+		return nil, err
+	}
+
+	mount, relPath, found := mfs.findMount(resolved)
+	if !found {
+		return nil, filesystem.NewNotFoundError("read", path)
+	}
+	fs := mount.Plugin.GetFileSystem()
+	deadline := time.Now().Add(timeout)
+	for {
+		remaining := time.Until(deadline)
+		if remaining < 0 {
+			remaining = 0
+		}
+		var data []byte
+		if pr, ok := fs.(filesystem.PollReader); ok {
+			data, err = pr.PollRead(ctx, relPath, offset, size, remaining)
+		} else {
+			data, err = fs.Read(relPath, offset, size)
+		}
+		if len(data) > 0 || (err != nil && err != io.EOF) || remaining == 0 {
+			return data, err
+		}
+
+		wait := pollInterval
+		if remaining < wait {
+			wait = remaining
+		}
+		select {
+		case <-ctx.Done():
+			return nil, ctx.Err()
+		case <-time.After(wait):
+		}
+	}
+}
+
 func (mfs *MountableFS) Write(path string, data []byte, offset int64, flags filesystem.WriteFlag) (int64, error) {
 	// Resolve symlinks in all path components
 	resolved, err := mfs.resolvePath(path)
@@ -1311,6 +1604,29 @@ func (mfs *MountableFS) Readlink(linkPath string) (string, error) {
 	return target, nil
 }
 
+// RealPath implements filesystem.RealPather: virtual symlinks are resolved
+// here, and links inside a mount by its file system if it supports them
+func (mfs *MountableFS) RealPath(path string) (string, error) {
+	resolved, err := mfs.resolvePath(path)
+	if err != nil {
+		return "", err
+	}
+	mount, relPath, found := mfs.findMount(resolved)
+	if !found {
+		return "", filesystem.NewNotFoundError("realpath", path)
+	}
+
+	realPather, ok := mount.Plugin.GetFileSystem().(filesystem.RealPather)
+	if !ok {
+		return resolved, nil
+	}
+	resolvedPath, err := realPather.RealPath(relPath)
+	if err != nil {
+		return "", err
+	}
+	return filesystem.NormalizePath(filepath.Join(mount.Path, resolvedPath)), nil
+}
+
 // CustomGrepResult represents a custom grep search result
 type CustomGrepResult struct {
 	File     string                 `json:"file"`     // File path
@@ -1363,3 +1679,12 @@ var _ filesystem.FileHandle = (*globalFileHandle)(nil)
 
 // Ensure MountableFS implements Truncater interface
 var _ filesystem.Truncater = (*MountableFS)(nil)
+
+// Ensure MountableFS implements RealPather interface
+var _ filesystem.RealPather = (*MountableFS)(nil)
+
+// Ensure MountableFS implements ContextReader interface
+var _ filesystem.ContextReader = (*MountableFS)(nil)
+
+// Ensure MountableFS implements PollReader interface
+var _ filesystem.PollReader = (*MountableFS)(nil)
@@ -1,9 +1,12 @@
 package api
 
 import (
+	"context"
 	"encoding/json"
+	"errors"
 	"fmt"
 	"io"
+	"strings"
 	"time"
 	"unsafe"
 
@@ -41,6 +44,7 @@ func NewExternalPlugin(libHandle uintptr, vtable *PluginVTable) (*ExternalPlugin
 	ep.fileSystem = &ExternalFileSystem{
 		pluginPtr: pluginPtr,
 		vtable:    vtable,
+		handles:   make(map[int64]*ExternalFileHandle),
 	}
 
 	return ep, nil
@@ -65,7 +69,7 @@ func (ep *ExternalPlugin) Validate(config map[string]interface{}) error {
 
 	configCStr := CString(string(configJSON))
 	errPtr := ep.vtable.PluginValidate(ep.pluginPtr, configCStr)
-	return GoError(errPtr)
+	return ep.vtable.takeError(errPtr)
 }
 
 func (ep *ExternalPlugin) Initialize(config map[string]interface{}) error {
@@ -81,7 +85,7 @@ func (ep *ExternalPlugin) Initialize(config map[string]interface{}) error {
 
 	configCStr := CString(string(configJSON))
 	errPtr := ep.vtable.PluginInitialize(ep.pluginPtr, configCStr)
-	return GoError(errPtr)
+	return ep.vtable.takeError(errPtr)
 }
 
 func (ep *ExternalPlugin) GetFileSystem() filesystem.FileSystem {
@@ -98,9 +102,24 @@ func (ep *ExternalPlugin) GetReadme() string {
 }
 
 func (ep *ExternalPlugin) GetConfigParams() []plugin.ConfigParameter {
-	// External plugins (native .so/.dylib/.dll) don't expose config params via C API yet
-	// Return empty list for now
-	return []plugin.ConfigParameter{}
+	params := []plugin.ConfigParameter{}
+	if ep.vtable.PluginGetConfigParams == nil {
+		return params
+	}
+
+	jsonPtr := ep.vtable.PluginGetConfigParams(ep.pluginPtr)
+	if jsonPtr == nil {
+		return params
+	}
+	jsonStr := GoString(jsonPtr)
+	if ep.vtable.PluginFreeString != nil {
+		ep.vtable.PluginFreeString(jsonPtr)
+	}
+
+	if err := json.Unmarshal([]byte(jsonStr), &params); err != nil {
+		return []plugin.ConfigParameter{}
+	}
+	return params
 }
 
 func (ep *ExternalPlugin) Shutdown() error {
@@ -109,7 +128,7 @@ func (ep *ExternalPlugin) Shutdown() error {
 	}
 
 	errPtr := ep.vtable.PluginShutdown(ep.pluginPtr)
-	err := GoError(errPtr)
+	err := ep.vtable.takeError(errPtr)
 
 	// Free the plugin instance
 	if ep.vtable.PluginFree != nil {
@@ -128,7 +147,7 @@ func (efs *ExternalFileSystem) Create(path string) error {
 
 	pathCStr := CString(path)
 	errPtr := efs.vtable.FSCreate(efs.pluginPtr, pathCStr)
-	return GoError(errPtr)
+	return efs.vtable.takeError(errPtr)
 }
 
 func (efs *ExternalFileSystem) Mkdir(path string, perm uint32) error {
@@ -138,7 +157,7 @@ func (efs *ExternalFileSystem) Mkdir(path string, perm uint32) error {
 
 	pathCStr := CString(path)
 	errPtr := efs.vtable.FSMkdir(efs.pluginPtr, pathCStr, perm)
-	return GoError(errPtr)
+	return efs.vtable.takeError(errPtr)
 }
 
 func (efs *ExternalFileSystem) Remove(path string) error {
@@ -148,7 +167,7 @@ func (efs *ExternalFileSystem) Remove(path string) error {
 
 	pathCStr := CString(path)
 	errPtr := efs.vtable.FSRemove(efs.pluginPtr, pathCStr)
-	return GoError(errPtr)
+	return efs.vtable.takeError(errPtr)
 }
 
 func (efs *ExternalFileSystem) RemoveAll(path string) error {
@@ -158,37 +177,120 @@ func (efs *ExternalFileSystem) RemoveAll(path string) error {
 
 	pathCStr := CString(path)
 	errPtr := efs.vtable.FSRemoveAll(efs.pluginPtr, pathCStr)
-	return GoError(errPtr)
+	return efs.vtable.takeError(errPtr)
 }
 
 func (efs *ExternalFileSystem) Read(path string, offset int64, size int64) ([]byte, error) {
+	if efs.vtable.FSSubmitRead != nil && efs.vtable.FSPoll != nil {
+		return efs.readAsync(path, offset, size)
+	}
 	if efs.vtable.FSRead == nil {
 		return nil, fmt.Errorf("not implemented")
 	}
 
 	pathCStr := CString(path)
-	var dataLen int
-	dataPtr := efs.vtable.FSRead(efs.pluginPtr, pathCStr, offset, size, &dataLen)
+	var dataLen int64
+	var cErr FSErrorC
+	dataPtr := efs.vtable.FSRead(efs.pluginPtr, pathCStr, offset, size, &dataLen, &cErr)
+
+	if dataLen < 0 {
+		return nil, efs.vtable.takeFSError("read", path, &cErr)
+	}
+	return efs.vtable.takeBuffer(dataPtr, dataLen), nil
+}
+
+// readAsync submits a read and waits for it with FSPoll, so the plugin runs
+// it on a worker thread instead of under its lock
+func (efs *ExternalFileSystem) readAsync(path string, offset int64, size int64) ([]byte, error) {
+	pathCStr := CString(path)
+	var cErr FSErrorC
+	op := efs.vtable.FSSubmitRead(efs.pluginPtr, pathCStr, offset, size, &cErr)
+	if op < 0 {
+		return nil, efs.vtable.takeFSError("read", path, &cErr)
+	}
 
-	if dataPtr == nil {
-		if dataLen < 0 {
-			return nil, fmt.Errorf("read failed")
+	dataPtr, dataLen, err := efs.wait(context.Background(), "read", path, op)
+	if err != nil {
+		return nil, err
+	}
+	return efs.vtable.takeBuffer(dataPtr, dataLen), nil
+}
+
+// cancelPollInterval is how often a cancellable wait checks its context
+const cancelPollInterval = 50 * time.Millisecond
+
+// ReadContext implements filesystem.ContextReader
+// Once ctx is done the read is cancelled in the plugin and ctx.Err() returned
+func (efs *ExternalFileSystem) ReadContext(ctx context.Context, path string, offset int64, size int64) ([]byte, error) {
+	if efs.vtable.FSSubmitRead == nil || efs.vtable.FSPoll == nil || efs.vtable.FSCancel == nil {
+		return efs.Read(path, offset, size)
+	}
+
+	pathCStr := CString(path)
+	var cErr FSErrorC
+	op := efs.vtable.FSSubmitRead(efs.pluginPtr, pathCStr, offset, size, &cErr)
+	if op < 0 {
+		return nil, efs.vtable.takeFSError("read", path, &cErr)
+	}
+
+	dataPtr, dataLen, err := efs.wait(ctx, "read", path, op)
+	if err != nil {
+		return nil, err
+	}
+	return efs.vtable.takeBuffer(dataPtr, dataLen), nil
+}
+
+// wait blocks in FSPoll until operation op finishes and returns its data and
+// size. If ctx is done first the operation is cancelled in the plugin.
+func (efs *ExternalFileSystem) wait(ctx context.Context, opName, path string, op int64) (*byte, int64, error) {
+	timeoutMs := int64(-1)
+	if ctx.Done() != nil && efs.vtable.FSCancel != nil {
+		timeoutMs = cancelPollInterval.Milliseconds()
+	}
+
+	var dataPtr *byte
+	var size int64
+	var cErr FSErrorC
+	for {
+		state := efs.vtable.FSPoll(efs.pluginPtr, op, timeoutMs, &dataPtr, &size, &cErr)
+		if state == PollDone {
+			break
+		}
+		if state != PollPending {
+			return nil, 0, efs.vtable.takeFSError(opName, path, &cErr)
+		}
+		if err := ctx.Err(); err != nil {
+			efs.vtable.FSCancel(efs.pluginPtr, op)
+			return nil, 0, err
 		}
-		return []byte{}, nil
 	}
+	if size < 0 {
+		return nil, 0, efs.vtable.takeFSError(opName, path, &cErr)
+	}
+	return dataPtr, size, nil
+}
 
-	// Copy data from C to Go
-	data := make([]byte, dataLen)
-	for i := 0; i < dataLen; i++ {
-		ptr := unsafe.Pointer(uintptr(unsafe.Pointer(dataPtr)) + uintptr(i))
-		data[i] = *(*byte)(ptr)
+// takeBuffer copies a plugin-allocated buffer into Go memory and releases it
+func (vt *PluginVTable) takeBuffer(dataPtr *byte, dataLen int64) []byte {
+	if dataPtr != nil && vt.PluginFreeBuffer != nil {
+		defer vt.PluginFreeBuffer(dataPtr)
+	}
+
+	if dataPtr == nil || dataLen == 0 {
+		return []byte{}
 	}
 
-	return data, nil
+	// Copy data from C to Go; the buffer is binary-safe, so use dataLen rather
+	// than scanning for a terminator
+	data := make([]byte, dataLen)
+	copy(data, unsafe.Slice(dataPtr, dataLen))
+
+	return data
 }
 
 func (efs *ExternalFileSystem) Write(path string, data []byte, offset int64, flags filesystem.WriteFlag) (int64, error) {
-	if efs.vtable.FSWrite == nil {
+	async := efs.vtable.FSSubmitWrite != nil && efs.vtable.FSPoll != nil
+	if !async && efs.vtable.FSWrite == nil {
 		return 0, fmt.Errorf("not implemented")
 	}
 
@@ -198,40 +300,309 @@ func (efs *ExternalFileSystem) Write(path string, data []byte, offset int64, fla
 		dataCStr = &data[0]
 	}
 
-	// Call C plugin with new signature: (plugin, path, data, len, offset, flags) -> int64
-	bytesWritten := efs.vtable.FSWrite(efs.pluginPtr, pathCStr, dataCStr, len(data), offset, uint32(flags))
+	var cErr FSErrorC
+	if async {
+		// The plugin copies data before FSSubmitWrite returns
+		op := efs.vtable.FSSubmitWrite(efs.pluginPtr, pathCStr, dataCStr, int32(len(data)), offset, uint32(flags), &cErr)
+		if op < 0 {
+			return 0, efs.vtable.takeFSError("write", path, &cErr)
+		}
+		_, bytesWritten, err := efs.wait(context.Background(), "write", path, op)
+		return bytesWritten, err
+	}
+
+	bytesWritten := efs.vtable.FSWrite(efs.pluginPtr, pathCStr, dataCStr, int32(len(data)), offset, uint32(flags), &cErr)
 	if bytesWritten < 0 {
-		return 0, fmt.Errorf("write failed")
+		return 0, efs.vtable.takeFSError("write", path, &cErr)
 	}
 
 	return bytesWritten, nil
 }
 
+func (efs *ExternalFileSystem) ReadIfChanged(path, etag string) ([]byte, bool, error) {
+	if efs.vtable.FSReadIfChanged == nil {
+		info, err := efs.Stat(path)
+		if err != nil {
+			return nil, false, err
+		}
+		if info.ETag != "" && info.ETag == etag {
+			return nil, false, nil
+		}
+		data, err := efs.Read(path, 0, -1)
+		return data, err == nil, err
+	}
+
+	var size int64
+	var cErr FSErrorC
+	dataPtr := efs.vtable.FSReadIfChanged(efs.pluginPtr, CString(path), CString(etag), &size, &cErr)
+	switch {
+	case size == ReadNotModified:
+		return nil, false, nil
+	case size < 0:
+		return nil, false, efs.vtable.takeFSError("read", path, &cErr)
+	}
+	return efs.vtable.takeBuffer(dataPtr, size), true, nil
+}
+
+func (efs *ExternalFileSystem) ReadMany(paths []string) []filesystem.ReadResult {
+	results := make([]filesystem.ReadResult, len(paths))
+	if efs.vtable.FSReadMany == nil || len(paths) == 0 {
+		for i, path := range paths {
+			results[i].Data, results[i].Err = efs.Read(path, 0, -1)
+		}
+		return results
+	}
+
+	var buf []byte
+	for _, path := range paths {
+		buf = append(append(buf, path...), 0)
+	}
+	cResults := make([]ReadResultC, len(paths))
+	var cErr FSErrorC
+	n := efs.vtable.FSReadMany(efs.pluginPtr, &buf[0], int64(len(buf)), &cResults[0], int32(len(paths)), &cErr)
+	if n < 0 {
+		err := efs.vtable.takeFSError("read", paths[0], &cErr)
+		for i := range results {
+			results[i].Err = err
+		}
+		return results
+	}
+
+	for i := range results {
+		cResult := &cResults[i]
+		switch {
+		case i >= int(n):
+			results[i].Err = fmt.Errorf("read: %s: no result from plugin", paths[i])
+		case cResult.Len < 0:
+			results[i].Err = efs.vtable.takeFSError("read", paths[i], &cResult.Err)
+		default:
+			results[i].Data = efs.vtable.takeBuffer(cResult.Data, cResult.Len)
+		}
+	}
+	return results
+}
+
+func (efs *ExternalFileSystem) Batch(ops []filesystem.FsOp) ([]error, error) {
+	if efs.vtable.FSBatch == nil {
+		return filesystem.ApplyOps(efs, ops), nil
+	}
+
+	opsJSON, err := json.Marshal(ops)
+	if err != nil {
+		return nil, fmt.Errorf("batch: %w", err)
+	}
+	var cErr FSErrorC
+	resultsPtr := efs.vtable.FSBatch(efs.pluginPtr, CString(string(opsJSON)), &cErr)
+	if resultsPtr == nil {
+		return nil, efs.vtable.takeFSError("batch", "", &cErr)
+	}
+	resultsJSON := GoString(resultsPtr)
+	if efs.vtable.PluginFreeString != nil {
+		efs.vtable.PluginFreeString(resultsPtr)
+	}
+	return batchErrors(ops, resultsJSON)
+}
+
+func (efs *ExternalFileSystem) AtomicBatch() bool {
+	return efs.vtable.FSAtomicBatch != nil && efs.vtable.FSAtomicBatch(efs.pluginPtr) == 1
+}
+
+// Maintain is a no-op for plugins without FSMaintain
+func (efs *ExternalFileSystem) Maintain() error {
+	if efs.vtable.FSMaintain == nil {
+		return nil
+	}
+	var cErr FSErrorC
+	if efs.vtable.FSMaintain(efs.pluginPtr, &cErr) != 0 {
+		return efs.vtable.takeFSError("maintain", "", &cErr)
+	}
+	return nil
+}
+
+// Warmup reports plugins without FSWarmup as already warm
+func (efs *ExternalFileSystem) Warmup(budget time.Duration) (filesystem.WarmupProgress, error) {
+	var progress filesystem.WarmupProgress
+	if efs.vtable.FSWarmup == nil {
+		return progress, nil
+	}
+	var cErr FSErrorC
+	if efs.vtable.FSWarmup(efs.pluginPtr, budget.Milliseconds(), &progress.Done, &progress.Total, &cErr) != 0 {
+		return progress, efs.vtable.takeFSError("warmup", "", &cErr)
+	}
+	return progress, nil
+}
+
+// ExportState has nothing to hand over for plugins without FSExportState
+func (efs *ExternalFileSystem) ExportState() ([]byte, error) {
+	if efs.vtable.FSExportState == nil {
+		return nil, nil
+	}
+	var stateLen int64
+	var cErr FSErrorC
+	statePtr := efs.vtable.FSExportState(efs.pluginPtr, &stateLen, &cErr)
+	if stateLen < 0 {
+		return nil, efs.vtable.takeFSError("export_state", "", &cErr)
+	}
+	return efs.vtable.takeBuffer(statePtr, stateLen), nil
+}
+
+// ImportState drops the state for plugins without FSImportState, which
+// then start cold
+func (efs *ExternalFileSystem) ImportState(state []byte) error {
+	if efs.vtable.FSImportState == nil {
+		return nil
+	}
+	var cErr FSErrorC
+	if efs.vtable.FSImportState(efs.pluginPtr, bufPtr(state), int64(len(state)), &cErr) != 0 {
+		return efs.vtable.takeFSError("import_state", "", &cErr)
+	}
+	return nil
+}
+
+func (efs *ExternalFileSystem) BeginWriteSession() error {
+	if efs.vtable.FSBeginSession == nil {
+		return filesystem.NewNotSupportedError("begin_session", "")
+	}
+	var cErr FSErrorC
+	if efs.vtable.FSBeginSession(efs.pluginPtr, &cErr) != 0 {
+		return efs.vtable.takeFSError("begin_session", "", &cErr)
+	}
+	return nil
+}
+
+func (efs *ExternalFileSystem) CommitSession(message string) error {
+	if efs.vtable.FSCommitSession == nil {
+		return filesystem.NewNotSupportedError("commit_session", "")
+	}
+	var cErr FSErrorC
+	if efs.vtable.FSCommitSession(efs.pluginPtr, CString(message), &cErr) != 0 {
+		return efs.vtable.takeFSError("commit_session", "", &cErr)
+	}
+	return nil
+}
+
+func (efs *ExternalFileSystem) AbortSession() error {
+	if efs.vtable.FSAbortSession == nil {
+		return filesystem.NewNotSupportedError("abort_session", "")
+	}
+	var cErr FSErrorC
+	if efs.vtable.FSAbortSession(efs.pluginPtr, &cErr) != 0 {
+		return efs.vtable.takeFSError("abort_session", "", &cErr)
+	}
+	return nil
+}
+
+func (efs *ExternalFileSystem) WriteIf(path string, data []byte, expectedETag string) (int64, error) {
+	if efs.vtable.FSWriteIf == nil {
+		info, err := efs.Stat(path)
+		switch {
+		case expectedETag == "" && err == nil:
+			return 0, fmt.Errorf("write: %s: %w", path, filesystem.ErrStale)
+		case expectedETag == "" && errors.Is(err, filesystem.ErrNotFound):
+			return efs.Write(path, data, 0, filesystem.WriteFlagCreate|filesystem.WriteFlagExclusive|filesystem.WriteFlagIfMatch)
+		case err != nil:
+			return 0, err
+		case info.ETag == "" || info.ETag != expectedETag:
+			return 0, fmt.Errorf("write: %s: %w", path, filesystem.ErrStale)
+		}
+		return efs.Write(path, data, 0, filesystem.WriteFlagTruncate|filesystem.WriteFlagIfMatch)
+	}
+
+	var dataPtr *byte
+	if len(data) > 0 {
+		dataPtr = &data[0]
+	}
+	var cErr FSErrorC
+	bytesWritten := efs.vtable.FSWriteIf(efs.pluginPtr, CString(path), dataPtr, int32(len(data)), CString(expectedETag), &cErr)
+	if bytesWritten < 0 {
+		return 0, efs.vtable.takeFSError("write", path, &cErr)
+	}
+	return bytesWritten, nil
+}
+
 func (efs *ExternalFileSystem) ReadDir(path string) ([]filesystem.FileInfo, error) {
+	if efs.vtable.FSReadDirPage != nil {
+		return efs.readDirPaged(path)
+	}
 	if efs.vtable.FSReadDir == nil {
 		return nil, fmt.Errorf("not implemented")
 	}
 
 	pathCStr := CString(path)
-	var count int
-	arrPtr := efs.vtable.FSReadDir(efs.pluginPtr, pathCStr, &count)
+	var count int32
+	var cErr FSErrorC
+	arrPtr := efs.vtable.FSReadDir(efs.pluginPtr, pathCStr, &count, &cErr)
+	if count < 0 {
+		return nil, efs.vtable.takeFSError("readdir", path, &cErr)
+	}
+
+	return efs.vtable.takeFileInfoArray(arrPtr, count), nil
+}
+
+// readDirPageSize is the number of entries requested per FSReadDirPage call
+const readDirPageSize = 1024
+
+// readDirPaged lists a directory in readDirPageSize chunks so the plugin never
+// has to allocate one array for the whole directory
+func (efs *ExternalFileSystem) readDirPaged(path string) ([]filesystem.FileInfo, error) {
+	pathCStr := CString(path)
+	infos := []filesystem.FileInfo{}
+	for {
+		var count int32
+		var cErr FSErrorC
+		arrPtr := efs.vtable.FSReadDirPage(efs.pluginPtr, pathCStr, int64(len(infos)), readDirPageSize, &count, &cErr)
+		if count < 0 {
+			return nil, efs.vtable.takeFSError("readdir", path, &cErr)
+		}
+
+		infos = append(infos, efs.vtable.takeFileInfoArray(arrPtr, count)...)
+		if count < readDirPageSize {
+			return infos, nil
+		}
+	}
+}
+
+func (efs *ExternalFileSystem) ReadDirFiltered(path, glob string, limit int) ([]filesystem.FileInfo, error) {
+	if efs.vtable.FSReadDirFiltered == nil {
+		infos, err := efs.ReadDir(path)
+		if err != nil {
+			return nil, err
+		}
+		return filesystem.FilterEntries(infos, glob, limit), nil
+	}
+
+	var count int32
+	var cErr FSErrorC
+	arrPtr := efs.vtable.FSReadDirFiltered(efs.pluginPtr, CString(path), CString(glob), int64(limit), &count, &cErr)
+	if count < 0 {
+		return nil, efs.vtable.takeFSError("readdir", path, &cErr)
+	}
+	return efs.vtable.takeFileInfoArray(arrPtr, count), nil
+}
+
+// takeFileInfoArray converts a plugin-allocated FileInfoArray to Go and
+// releases it
+func (vt *PluginVTable) takeFileInfoArray(arrPtr *FileInfoArray, count int32) []filesystem.FileInfo {
+	if arrPtr != nil && vt.FSFreeFileInfoArray != nil {
+		defer vt.FSFreeFileInfoArray(arrPtr)
+	}
 
 	if arrPtr == nil || count == 0 {
-		return []filesystem.FileInfo{}, nil
+		return []filesystem.FileInfo{}
 	}
 
-	// Convert C array to Go slice
+	// Convert C array to Go slice; the stride depends on the plugin's layout
 	infos := make([]filesystem.FileInfo, count)
-	for i := 0; i < count; i++ {
-		cInfoPtr := unsafe.Pointer(uintptr(unsafe.Pointer(arrPtr.Items)) + uintptr(i)*unsafe.Sizeof(FileInfoC{}))
+	for i := 0; i < int(count); i++ {
+		cInfoPtr := unsafe.Pointer(uintptr(unsafe.Pointer(arrPtr.Items)) + uintptr(i)*vt.fileInfoCSize())
 		cInfo := (*FileInfoC)(cInfoPtr)
-		goInfo := FileInfoCToGo(cInfo)
+		goInfo := vt.fileInfoToGo(cInfo)
 		if goInfo != nil {
 			infos[i] = *goInfo
 		}
 	}
 
-	return infos, nil
+	return infos
 }
 
 func (efs *ExternalFileSystem) Stat(path string) (*filesystem.FileInfo, error) {
@@ -240,13 +611,17 @@ func (efs *ExternalFileSystem) Stat(path string) (*filesystem.FileInfo, error) {
 	}
 
 	pathCStr := CString(path)
-	cInfo := efs.vtable.FSStat(efs.pluginPtr, pathCStr)
+	var cErr FSErrorC
+	cInfo := efs.vtable.FSStat(efs.pluginPtr, pathCStr, &cErr)
 
 	if cInfo == nil {
-		return nil, fmt.Errorf("stat failed")
+		return nil, efs.vtable.takeFSError("stat", path, &cErr)
+	}
+	if efs.vtable.FSFreeFileInfo != nil {
+		defer efs.vtable.FSFreeFileInfo(cInfo)
 	}
 
-	return FileInfoCToGo(cInfo), nil
+	return efs.vtable.fileInfoToGo(cInfo), nil
 }
 
 func (efs *ExternalFileSystem) Rename(oldPath, newPath string) error {
@@ -257,7 +632,7 @@ func (efs *ExternalFileSystem) Rename(oldPath, newPath string) error {
 	oldPathCStr := CString(oldPath)
 	newPathCStr := CString(newPath)
 	errPtr := efs.vtable.FSRename(efs.pluginPtr, oldPathCStr, newPathCStr)
-	return GoError(errPtr)
+	return efs.vtable.takeError(errPtr)
 }
 
 func (efs *ExternalFileSystem) Chmod(path string, mode uint32) error {
@@ -267,7 +642,89 @@ func (efs *ExternalFileSystem) Chmod(path string, mode uint32) error {
 
 	pathCStr := CString(path)
 	errPtr := efs.vtable.FSChmod(efs.pluginPtr, pathCStr, mode)
-	return GoError(errPtr)
+	return efs.vtable.takeError(errPtr)
+}
+
+func (efs *ExternalFileSystem) Symlink(targetPath, linkPath string) error {
+	if efs.vtable.FSSymlink == nil {
+		return filesystem.NewNotSupportedError("symlink", linkPath)
+	}
+
+	errPtr := efs.vtable.FSSymlink(efs.pluginPtr, CString(targetPath), CString(linkPath))
+	return efs.vtable.takeError(errPtr)
+}
+
+func (efs *ExternalFileSystem) Readlink(linkPath string) (string, error) {
+	if efs.vtable.FSReadlink == nil {
+		return "", filesystem.NewNotSupportedError("readlink", linkPath)
+	}
+
+	var cErr FSErrorC
+	targetPtr := efs.vtable.FSReadlink(efs.pluginPtr, CString(linkPath), &cErr)
+	if targetPtr == nil {
+		return "", efs.vtable.takeFSError("readlink", linkPath, &cErr)
+	}
+
+	target := GoString(targetPtr)
+	if efs.vtable.PluginFreeString != nil {
+		efs.vtable.PluginFreeString(targetPtr)
+	}
+	return target, nil
+}
+
+func (efs *ExternalFileSystem) GetXattr(path, name string) ([]byte, error) {
+	if efs.vtable.FSGetXattr == nil {
+		return nil, filesystem.NewNotSupportedError("getxattr", path)
+	}
+
+	var size int64
+	var cErr FSErrorC
+	dataPtr := efs.vtable.FSGetXattr(efs.pluginPtr, CString(path), CString(name), &size, &cErr)
+	if size < 0 {
+		return nil, efs.vtable.takeFSError("getxattr", path, &cErr)
+	}
+	return efs.vtable.takeBuffer(dataPtr, size), nil
+}
+
+func (efs *ExternalFileSystem) SetXattr(path, name string, value []byte) error {
+	if efs.vtable.FSSetXattr == nil {
+		return filesystem.NewNotSupportedError("setxattr", path)
+	}
+
+	errPtr := efs.vtable.FSSetXattr(efs.pluginPtr, CString(path), CString(name), bufPtr(value), int32(len(value)))
+	return efs.vtable.takeError(errPtr)
+}
+
+func (efs *ExternalFileSystem) ListXattr(path string) ([]string, error) {
+	if efs.vtable.FSListXattr == nil {
+		return nil, filesystem.NewNotSupportedError("listxattr", path)
+	}
+
+	var size int64
+	var cErr FSErrorC
+	dataPtr := efs.vtable.FSListXattr(efs.pluginPtr, CString(path), &size, &cErr)
+	if size < 0 {
+		return nil, efs.vtable.takeFSError("listxattr", path, &cErr)
+	}
+
+	// Names are NUL-terminated and concatenated, as with listxattr(2)
+	names := []string{}
+	for _, name := range strings.Split(string(efs.vtable.takeBuffer(dataPtr, size)), "\x00") {
+		if name != "" {
+			names = append(names, name)
+		}
+	}
+	return names, nil
+}
+
+func (efs *ExternalFileSystem) Advise(path string, offset, length int64, advice filesystem.Advice) error {
+	if efs.vtable.FSAdvise == nil {
+		// Hints are optional; plugins without FSAdvise simply ignore them
+		return nil
+	}
+
+	errPtr := efs.vtable.FSAdvise(efs.pluginPtr, CString(path), offset, length, int32(advice))
+	return efs.vtable.takeError(errPtr)
 }
 
 func (efs *ExternalFileSystem) Open(path string) (io.ReadCloser, error) {
@@ -283,6 +740,191 @@ func (efs *ExternalFileSystem) OpenWrite(path string) (io.WriteCloser, error) {
 	return &writeCloser{fs: efs, path: path}, nil
 }
 
+// HandleFS interface implementation for native plugins
+
+// SupportsHandleFS checks if the plugin exports the handle functions
+func (efs *ExternalFileSystem) SupportsHandleFS() bool {
+	return efs.vtable.HandleOpen != nil
+}
+
+// OpenHandle opens a file and returns a handle backed by the plugin
+func (efs *ExternalFileSystem) OpenHandle(path string, flags filesystem.OpenFlag, mode uint32) (filesystem.FileHandle, error) {
+	if efs.vtable.HandleOpen == nil {
+		return nil, filesystem.NewNotSupportedError("openhandle", path)
+	}
+
+	pathCStr := CString(path)
+	var id int64
+	if err := efs.vtable.takeError(efs.vtable.HandleOpen(efs.pluginPtr, pathCStr, uint32(flags), mode, &id)); err != nil {
+		return nil, err
+	}
+	if id == 0 {
+		return nil, fmt.Errorf("HandleOpen returned zero id")
+	}
+
+	handle := &ExternalFileHandle{id: id, path: path, flags: flags, efs: efs}
+	efs.handlesMu.Lock()
+	efs.handles[id] = handle
+	efs.handlesMu.Unlock()
+	return handle, nil
+}
+
+// GetHandle retrieves an open handle by the ID the plugin assigned
+func (efs *ExternalFileSystem) GetHandle(id int64) (filesystem.FileHandle, error) {
+	efs.handlesMu.Lock()
+	defer efs.handlesMu.Unlock()
+
+	handle, ok := efs.handles[id]
+	if !ok {
+		return nil, filesystem.ErrNotFound
+	}
+	return handle, nil
+}
+
+// CloseHandle closes a handle by ID
+func (efs *ExternalFileSystem) CloseHandle(id int64) error {
+	handle, err := efs.GetHandle(id)
+	if err != nil {
+		return err
+	}
+	return handle.Close()
+}
+
+// ExternalFileHandle is a file handle owned by a native plugin
+type ExternalFileHandle struct {
+	id     int64
+	path   string
+	flags  filesystem.OpenFlag
+	efs    *ExternalFileSystem
+	closed bool
+}
+
+func (h *ExternalFileHandle) ID() int64 {
+	return h.id
+}
+
+func (h *ExternalFileHandle) Path() string {
+	return h.path
+}
+
+func (h *ExternalFileHandle) Flags() filesystem.OpenFlag {
+	return h.flags
+}
+
+// bufPtr returns a pointer to the first byte of buf, or nil if it is empty
+func bufPtr(buf []byte) *byte {
+	if len(buf) == 0 {
+		return nil
+	}
+	return &buf[0]
+}
+
+func (h *ExternalFileHandle) Read(buf []byte) (int, error) {
+	if h.closed {
+		return 0, fmt.Errorf("handle is closed")
+	}
+	var n int64
+	vt := h.efs.vtable
+	if err := vt.takeError(vt.HandleRead(h.efs.pluginPtr, h.id, bufPtr(buf), int64(len(buf)), &n)); err != nil {
+		return 0, err
+	}
+	return int(n), nil
+}
+
+func (h *ExternalFileHandle) ReadAt(buf []byte, offset int64) (int, error) {
+	if h.closed {
+		return 0, fmt.Errorf("handle is closed")
+	}
+	var n int64
+	vt := h.efs.vtable
+	if err := vt.takeError(vt.HandleReadAt(h.efs.pluginPtr, h.id, bufPtr(buf), int64(len(buf)), offset, &n)); err != nil {
+		return 0, err
+	}
+	return int(n), nil
+}
+
+func (h *ExternalFileHandle) Write(data []byte) (int, error) {
+	if h.closed {
+		return 0, fmt.Errorf("handle is closed")
+	}
+	var n int64
+	vt := h.efs.vtable
+	if err := vt.takeError(vt.HandleWrite(h.efs.pluginPtr, h.id, bufPtr(data), int64(len(data)), &n)); err != nil {
+		return 0, err
+	}
+	return int(n), nil
+}
+
+func (h *ExternalFileHandle) WriteAt(data []byte, offset int64) (int, error) {
+	if h.closed {
+		return 0, fmt.Errorf("handle is closed")
+	}
+	var n int64
+	vt := h.efs.vtable
+	if err := vt.takeError(vt.HandleWriteAt(h.efs.pluginPtr, h.id, bufPtr(data), int64(len(data)), offset, &n)); err != nil {
+		return 0, err
+	}
+	return int(n), nil
+}
+
+func (h *ExternalFileHandle) Seek(offset int64, whence int) (int64, error) {
+	if h.closed {
+		return 0, fmt.Errorf("handle is closed")
+	}
+	var pos int64
+	vt := h.efs.vtable
+	if err := vt.takeError(vt.HandleSeek(h.efs.pluginPtr, h.id, offset, int32(whence), &pos)); err != nil {
+		return 0, err
+	}
+	return pos, nil
+}
+
+func (h *ExternalFileHandle) Sync() error {
+	if h.closed {
+		return fmt.Errorf("handle is closed")
+	}
+	vt := h.efs.vtable
+	if vt.HandleSync == nil {
+		return nil
+	}
+	return vt.takeError(vt.HandleSync(h.efs.pluginPtr, h.id))
+}
+
+func (h *ExternalFileHandle) Stat() (*filesystem.FileInfo, error) {
+	if h.closed {
+		return nil, fmt.Errorf("handle is closed")
+	}
+	vt := h.efs.vtable
+	if vt.HandleStat == nil {
+		return h.efs.Stat(h.path)
+	}
+	var cInfo *FileInfoC
+	if err := vt.takeError(vt.HandleStat(h.efs.pluginPtr, h.id, &cInfo)); err != nil {
+		return nil, err
+	}
+	if cInfo == nil {
+		return nil, fmt.Errorf("stat failed")
+	}
+	if vt.FSFreeFileInfo != nil {
+		defer vt.FSFreeFileInfo(cInfo)
+	}
+	return vt.fileInfoToGo(cInfo), nil
+}
+
+func (h *ExternalFileHandle) Close() error {
+	if h.closed {
+		return nil
+	}
+	h.closed = true
+
+	h.efs.handlesMu.Lock()
+	delete(h.efs.handles, h.id)
+	h.efs.handlesMu.Unlock()
+
+	vt := h.efs.vtable
+	return vt.takeError(vt.HandleClose(h.efs.pluginPtr, h.id))
+}
+
 // Helper types
 
 type bytesReaderAt struct {
@@ -316,7 +958,28 @@ func (wc *writeCloser) Close() error {
 	return err
 }
 
+// fileInfoCSize is the size of one FileInfoC as laid out by the plugin;
+// version 1 plugins predate the ETag field
+func (vt *PluginVTable) fileInfoCSize() uintptr {
+	if vt.ABIVersion < 2 {
+		return unsafe.Offsetof(FileInfoC{}.ETag)
+	}
+	return unsafe.Sizeof(FileInfoC{})
+}
+
+// fileInfoToGo converts a FileInfoC, reading fields only the plugin's ABI
+// version provides
+func (vt *PluginVTable) fileInfoToGo(c *FileInfoC) *filesystem.FileInfo {
+	info := FileInfoCToGo(c)
+	if info != nil && vt.ABIVersion >= 2 {
+		info.ETag = GoString(c.ETag)
+	}
+	return info
+}
+
 // FileInfoCToGo with proper time handling
+//
+// Only reads the version 1 fields; see PluginVTable.fileInfoToGo.
 func FileInfoCToGo(c *FileInfoC) *filesystem.FileInfo {
 	if c == nil {
 		return nil
@@ -351,3 +1014,10 @@ var _ plugin.ServicePlugin = (*ExternalPlugin)(nil)
 
 // Ensure ExternalFileSystem implements filesystem.FileSystem
 var _ filesystem.FileSystem = (*ExternalFileSystem)(nil)
+var _ filesystem.ContextReader = (*ExternalFileSystem)(nil)
+var _ filesystem.BatchReader = (*ExternalFileSystem)(nil)
+var _ filesystem.Batcher = (*ExternalFileSystem)(nil)
+var _ filesystem.WriteSessioner = (*ExternalFileSystem)(nil)
+var _ filesystem.Maintainer = (*ExternalFileSystem)(nil)
+var _ filesystem.Warmer = (*ExternalFileSystem)(nil)
+var _ filesystem.StateTransferer = (*ExternalFileSystem)(nil)
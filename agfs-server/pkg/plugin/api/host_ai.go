@@ -0,0 +1,194 @@
+package api
+
+import (
+	"bytes"
+	"context"
+	"encoding/json"
+	"fmt"
+	"io"
+	"net/http"
+	"strings"
+	"time"
+
+	"github.com/c4pt0r/agfs/agfs-server/pkg/filesystem"
+	log "github.com/sirupsen/logrus"
+	wazeroapi "github.com/tetratelabs/wazero/api"
+)
+
+// Text generation for WASM plugins
+// Small features like story summaries or mail digests ask the host to run
+// a prompt instead of bundling a model; the server decides which model
+// answers, and holds its credentials.
+
+const (
+	// maxPromptBytes bounds the prompt of one host_ai_complete call
+	maxPromptBytes = 256 << 10
+	// defaultCompletionTokens is the answer length when a plugin sets none
+	defaultCompletionTokens = 1024
+	// maxCompletionTokens bounds the answer length a plugin may ask for
+	maxCompletionTokens = 4096
+)
+
+// CompletionOptions tune one completion; zero values leave the model's defaults
+type CompletionOptions struct {
+	System      string   `json:"system,omitempty"`      // System prompt
+	MaxTokens   int      `json:"max_tokens,omitempty"`  // Answer length limit
+	Temperature *float64 `json:"temperature,omitempty"` // Sampling temperature
+}
+
+// Completer answers a prompt with generated text
+type Completer interface {
+	Complete(ctx context.Context, prompt string, opts CompletionOptions) (string, error)
+}
+
+// OpenAICompleter is a Completer using an OpenAI-compatible chat
+// completions API
+type OpenAICompleter struct {
+	baseURL string
+	apiKey  string
+	model   string
+	client  *http.Client
+}
+
+// NewOpenAICompleter creates a completer for model at baseURL (e.g.
+// https://api.openai.com/v1)
+func NewOpenAICompleter(baseURL, apiKey, model string) *OpenAICompleter {
+	return &OpenAICompleter{
+		baseURL: strings.TrimSuffix(baseURL, "/"),
+		apiKey:  apiKey,
+		model:   model,
+		client:  &http.Client{Timeout: 120 * time.Second},
+	}
+}
+
+type chatMessage struct {
+	Role    string `json:"role"`
+	Content string `json:"content"`
+}
+
+type chatRequest struct {
+	Model       string        `json:"model"`
+	Messages    []chatMessage `json:"messages"`
+	MaxTokens   int           `json:"max_tokens,omitempty"`
+	Temperature *float64      `json:"temperature,omitempty"`
+}
+
+type chatResponse struct {
+	Choices []struct {
+		Message chatMessage `json:"message"`
+	} `json:"choices"`
+}
+
+// Complete sends prompt as a user message after the system prompt, if any.
+// Rate limiting by the API fails with ErrBusy.
+func (c *OpenAICompleter) Complete(ctx context.Context, prompt string, opts CompletionOptions) (string, error) {
+	var messages []chatMessage
+	if opts.System != "" {
+		messages = append(messages, chatMessage{Role: "system", Content: opts.System})
+	}
+	messages = append(messages, chatMessage{Role: "user", Content: prompt})
+	body, err := json.Marshal(chatRequest{
+		Model:       c.model,
+		Messages:    messages,
+		MaxTokens:   opts.MaxTokens,
+		Temperature: opts.Temperature,
+	})
+	if err != nil {
+		return "", fmt.Errorf("failed to marshal request: %w", err)
+	}
+
+	req, err := http.NewRequestWithContext(ctx, http.MethodPost, c.baseURL+"/chat/completions", bytes.NewReader(body))
+	if err != nil {
+		return "", fmt.Errorf("failed to create request: %w", err)
+	}
+	req.Header.Set("Content-Type", "application/json")
+	req.Header.Set("Authorization", "Bearer "+c.apiKey)
+
+	resp, err := c.client.Do(req)
+	if err != nil {
+		return "", fmt.Errorf("failed to send request: %w", err)
+	}
+	defer resp.Body.Close()
+
+	switch {
+	case resp.StatusCode == http.StatusTooManyRequests:
+		return "", fmt.Errorf("completion API rate limited: %w", filesystem.ErrBusy)
+	case resp.StatusCode != http.StatusOK:
+		msg, _ := io.ReadAll(io.LimitReader(resp.Body, 4096))
+		return "", fmt.Errorf("completion API error (status %d): %s", resp.StatusCode, msg)
+	}
+
+	var chat chatResponse
+	if err := json.NewDecoder(resp.Body).Decode(&chat); err != nil {
+		return "", fmt.Errorf("failed to decode response: %w", err)
+	}
+	if len(chat.Choices) == 0 {
+		return "", fmt.Errorf("no completion returned from API")
+	}
+	return chat.Choices[0].Message.Content, nil
+}
+
+// hostAIRequest is what a plugin asks for through host_ai_complete
+type hostAIRequest struct {
+	Prompt  string            `json:"prompt"`
+	Options CompletionOptions `json:"options"`
+}
+
+// hostAIResponse carries the text generated for a hostAIRequest, or a
+// non-zero errno Code and Message
+type hostAIResponse struct {
+	Code    int32  `json:"code,omitempty"`
+	Message string `json:"message,omitempty"`
+	Text    string `json:"text"`
+}
+
+// HostAIComplete serves host_ai_complete: the answer to a prompt of up to
+// maxPromptBytes. Fails with EINVAL when the server has no completer
+// configured. Returns the JSON response packed as pointer | size << 32.
+func HostAIComplete(ctx context.Context, mod wazeroapi.Module, params []uint64, completer Completer) []uint64 {
+	var resp hostAIResponse
+	var req hostAIRequest
+	if reqJSON, ok := readStringFromMemory(mod, uint32(params[0])); !ok {
+		resp = aiErrorResponse(filesystem.NewInvalidArgumentError("request", nil, "failed to read request from memory"))
+	} else if err := json.Unmarshal([]byte(reqJSON), &req); err != nil {
+		resp = aiErrorResponse(filesystem.NewInvalidArgumentError("request", nil, err.Error()))
+	} else {
+		log.Debugf("host_ai_complete: %d prompt bytes", len(req.Prompt))
+		resp = serveAIRequest(ctx, completer, &req)
+	}
+
+	respJSON, err := json.Marshal(resp)
+	if err != nil {
+		log.Errorf("host_ai_complete: failed to marshal response: %v", err)
+		return []uint64{0}
+	}
+	respPtr, _, err := writeBytesToMemory(mod, respJSON)
+	if err != nil {
+		log.Errorf("host_ai_complete: failed to write response to memory: %v", err)
+		return []uint64{0}
+	}
+	return []uint64{uint64(respPtr) | uint64(len(respJSON))<<32}
+}
+
+func serveAIRequest(ctx context.Context, completer Completer, req *hostAIRequest) hostAIResponse {
+	if completer == nil {
+		return aiErrorResponse(filesystem.NewInvalidArgumentError("complete", nil, "the server has no wasm completion model configured"))
+	}
+	if len(req.Prompt) > maxPromptBytes {
+		return aiErrorResponse(filesystem.NewInvalidArgumentError("prompt", len(req.Prompt), fmt.Sprintf("at most %d bytes", maxPromptBytes)))
+	}
+	if req.Options.MaxTokens <= 0 {
+		req.Options.MaxTokens = defaultCompletionTokens
+	}
+	req.Options.MaxTokens = min(req.Options.MaxTokens, maxCompletionTokens)
+
+	text, err := completer.Complete(ctx, req.Prompt, req.Options)
+	if err != nil {
+		return aiErrorResponse(fmt.Errorf("completion failed: %w", err))
+	}
+	return hostAIResponse{Text: text}
+}
+
+func aiErrorResponse(err error) hostAIResponse {
+	return hostAIResponse{Code: fsErrno(err), Message: err.Error()}
+}
@@ -0,0 +1,162 @@
+package api
+
+import (
+	"context"
+	"crypto"
+	"crypto/hmac"
+	"crypto/rsa"
+	"crypto/sha256"
+	"crypto/x509"
+	"encoding/pem"
+	"fmt"
+
+	log "github.com/sirupsen/logrus"
+	wazeroapi "github.com/tetratelabs/wazero/api"
+)
+
+// Host function implementations for cryptography
+// WASM plugins call these instead of compiling crypto libraries into every
+// module. Digests and signatures are written to WASM memory and returned packed (lower 32
+// bits = pointer, upper 32 bits = size); 0 means failure.
+
+// HostCryptoSHA256 implements host_crypto_sha256(data_ptr, data_len)
+func HostCryptoSHA256(ctx context.Context, mod wazeroapi.Module, params []uint64) []uint64 {
+	data, ok := mod.Memory().Read(uint32(params[0]), uint32(params[1]))
+	if !ok {
+		log.Errorf("host_crypto_sha256: failed to read data from memory")
+		return []uint64{0}
+	}
+	sum := sha256.Sum256(data)
+	return packDigest(mod, "host_crypto_sha256", sum[:])
+}
+
+// HostCryptoHMACSHA256 implements
+// host_crypto_hmac_sha256(key_ptr, key_len, data_ptr, data_len)
+func HostCryptoHMACSHA256(ctx context.Context, mod wazeroapi.Module, params []uint64) []uint64 {
+	key, ok := mod.Memory().Read(uint32(params[0]), uint32(params[1]))
+	if !ok {
+		log.Errorf("host_crypto_hmac_sha256: failed to read key from memory")
+		return []uint64{0}
+	}
+	data, ok := mod.Memory().Read(uint32(params[2]), uint32(params[3]))
+	if !ok {
+		log.Errorf("host_crypto_hmac_sha256: failed to read data from memory")
+		return []uint64{0}
+	}
+	mac := hmac.New(sha256.New, key)
+	mac.Write(data)
+	return packDigest(mod, "host_crypto_hmac_sha256", mac.Sum(nil))
+}
+
+// HostCryptoRSASignSHA256 implements
+// host_crypto_rsa_sign_sha256(key_ptr, key_len, data_ptr, data_len): an
+// RSASSA-PKCS1-v1_5 signature of the SHA-256 of data under a PEM private key
+func HostCryptoRSASignSHA256(ctx context.Context, mod wazeroapi.Module, params []uint64) []uint64 {
+	keyPEM, ok := mod.Memory().Read(uint32(params[0]), uint32(params[1]))
+	if !ok {
+		log.Errorf("host_crypto_rsa_sign_sha256: failed to read key from memory")
+		return []uint64{0}
+	}
+	data, ok := mod.Memory().Read(uint32(params[2]), uint32(params[3]))
+	if !ok {
+		log.Errorf("host_crypto_rsa_sign_sha256: failed to read data from memory")
+		return []uint64{0}
+	}
+	key, err := parseRSAPrivateKey(keyPEM)
+	if err != nil {
+		log.Errorf("host_crypto_rsa_sign_sha256: %v", err)
+		return []uint64{0}
+	}
+	sum := sha256.Sum256(data)
+	signature, err := rsa.SignPKCS1v15(nil, key, crypto.SHA256, sum[:])
+	if err != nil {
+		log.Errorf("host_crypto_rsa_sign_sha256: %v", err)
+		return []uint64{0}
+	}
+	return packDigest(mod, "host_crypto_rsa_sign_sha256", signature)
+}
+
+// HostCryptoRSAVerifySHA256 implements
+// host_crypto_rsa_verify_sha256(key_ptr, key_len, data_ptr, data_len, sig_ptr, sig_len)
+// for a PEM public key or certificate. Returns 0 if the signature is
+// valid, 1 if it is not, and 2 if the arguments cannot be read.
+func HostCryptoRSAVerifySHA256(ctx context.Context, mod wazeroapi.Module, params []uint64) []uint64 {
+	keyPEM, okKey := mod.Memory().Read(uint32(params[0]), uint32(params[1]))
+	data, okData := mod.Memory().Read(uint32(params[2]), uint32(params[3]))
+	signature, okSig := mod.Memory().Read(uint32(params[4]), uint32(params[5]))
+	if !okKey || !okData || !okSig {
+		log.Errorf("host_crypto_rsa_verify_sha256: failed to read arguments from memory")
+		return []uint64{2}
+	}
+	key, err := parseRSAPublicKey(keyPEM)
+	if err != nil {
+		log.Errorf("host_crypto_rsa_verify_sha256: %v", err)
+		return []uint64{2}
+	}
+	sum := sha256.Sum256(data)
+	if rsa.VerifyPKCS1v15(key, crypto.SHA256, sum[:], signature) != nil {
+		return []uint64{1}
+	}
+	return []uint64{0}
+}
+
+// parseRSAPrivateKey accepts PKCS#1 ("RSA PRIVATE KEY") and PKCS#8
+// ("PRIVATE KEY") PEM blocks
+func parseRSAPrivateKey(keyPEM []byte) (*rsa.PrivateKey, error) {
+	block, _ := pem.Decode(keyPEM)
+	if block == nil {
+		return nil, fmt.Errorf("no PEM block in private key")
+	}
+	if key, err := x509.ParsePKCS1PrivateKey(block.Bytes); err == nil {
+		return key, nil
+	}
+	parsed, err := x509.ParsePKCS8PrivateKey(block.Bytes)
+	if err != nil {
+		return nil, fmt.Errorf("failed to parse private key: %w", err)
+	}
+	key, ok := parsed.(*rsa.PrivateKey)
+	if !ok {
+		return nil, fmt.Errorf("private key is not an RSA key")
+	}
+	return key, nil
+}
+
+// parseRSAPublicKey accepts PKIX ("PUBLIC KEY"), PKCS#1 ("RSA PUBLIC KEY")
+// and certificate PEM blocks
+func parseRSAPublicKey(keyPEM []byte) (*rsa.PublicKey, error) {
+	block, _ := pem.Decode(keyPEM)
+	if block == nil {
+		return nil, fmt.Errorf("no PEM block in public key")
+	}
+	var parsed any
+	switch block.Type {
+	case "CERTIFICATE":
+		cert, err := x509.ParseCertificate(block.Bytes)
+		if err != nil {
+			return nil, fmt.Errorf("failed to parse certificate: %w", err)
+		}
+		parsed = cert.PublicKey
+	case "RSA PUBLIC KEY":
+		return x509.ParsePKCS1PublicKey(block.Bytes)
+	default:
+		key, err := x509.ParsePKIXPublicKey(block.Bytes)
+		if err != nil {
+			return nil, fmt.Errorf("failed to parse public key: %w", err)
+		}
+		parsed = key
+	}
+	key, ok := parsed.(*rsa.PublicKey)
+	if !ok {
+		return nil, fmt.Errorf("public key is not an RSA key")
+	}
+	return key, nil
+}
+
+func packDigest(mod wazeroapi.Module, name string, digest []byte) []uint64 {
+	ptr, _, err := writeBytesToMemory(mod, digest)
+	if err != nil {
+		log.Errorf("%s: failed to write digest to memory: %v", name, err)
+		return []uint64{0}
+	}
+	return []uint64{uint64(ptr) | (uint64(len(digest)) << 32)}
+}
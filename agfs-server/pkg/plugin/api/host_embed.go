@@ -0,0 +1,90 @@
+package api
+
+import (
+	"context"
+	"encoding/json"
+	"fmt"
+
+	"github.com/c4pt0r/agfs/agfs-server/pkg/filesystem"
+	log "github.com/sirupsen/logrus"
+	wazeroapi "github.com/tetratelabs/wazero/api"
+)
+
+// Text embeddings for WASM plugins
+// Plugins offering semantic search ask the host to embed their texts
+// instead of bundling a model; the server decides which model serves them,
+// so every plugin's vectors are comparable with each other.
+
+// maxEmbedTexts bounds the texts of one host_embed call
+const maxEmbedTexts = 256
+
+// Embedder turns texts into embedding vectors, one per text and in order
+type Embedder interface {
+	GenerateBatchEmbeddings(texts []string) ([][]float32, error)
+}
+
+// hostEmbedRequest is what a plugin asks for through host_embed
+type hostEmbedRequest struct {
+	Texts []string `json:"texts"`
+}
+
+// hostEmbedResponse carries the vectors of a hostEmbedRequest, or a
+// non-zero errno Code and Message
+type hostEmbedResponse struct {
+	Code    int32       `json:"code,omitempty"`
+	Message string      `json:"message,omitempty"`
+	Vectors [][]float32 `json:"vectors,omitempty"`
+}
+
+// HostEmbed serves host_embed: the embeddings of up to maxEmbedTexts texts.
+// Fails with EINVAL when the server has no embedder configured. Returns the
+// JSON response packed as pointer | size << 32.
+func HostEmbed(ctx context.Context, mod wazeroapi.Module, params []uint64, embedder Embedder) []uint64 {
+	var resp hostEmbedResponse
+	var req hostEmbedRequest
+	if reqJSON, ok := readStringFromMemory(mod, uint32(params[0])); !ok {
+		resp = embedErrorResponse(filesystem.NewInvalidArgumentError("request", nil, "failed to read request from memory"))
+	} else if err := json.Unmarshal([]byte(reqJSON), &req); err != nil {
+		resp = embedErrorResponse(filesystem.NewInvalidArgumentError("request", nil, err.Error()))
+	} else {
+		log.Debugf("host_embed: %d texts", len(req.Texts))
+		resp = serveEmbedRequest(embedder, &req)
+	}
+
+	respJSON, err := json.Marshal(resp)
+	if err != nil {
+		log.Errorf("host_embed: failed to marshal response: %v", err)
+		return []uint64{0}
+	}
+	respPtr, _, err := writeBytesToMemory(mod, respJSON)
+	if err != nil {
+		log.Errorf("host_embed: failed to write response to memory: %v", err)
+		return []uint64{0}
+	}
+	return []uint64{uint64(respPtr) | uint64(len(respJSON))<<32}
+}
+
+func serveEmbedRequest(embedder Embedder, req *hostEmbedRequest) hostEmbedResponse {
+	if embedder == nil {
+		return embedErrorResponse(filesystem.NewInvalidArgumentError("embed", nil, "the server has no wasm embedding model configured"))
+	}
+	if len(req.Texts) > maxEmbedTexts {
+		return embedErrorResponse(filesystem.NewInvalidArgumentError("texts", len(req.Texts), fmt.Sprintf("at most %d texts per call", maxEmbedTexts)))
+	}
+	if len(req.Texts) == 0 {
+		return hostEmbedResponse{Vectors: [][]float32{}}
+	}
+
+	vectors, err := embedder.GenerateBatchEmbeddings(req.Texts)
+	if err != nil {
+		return embedErrorResponse(fmt.Errorf("embedding failed: %w", err))
+	}
+	if len(vectors) != len(req.Texts) {
+		return embedErrorResponse(fmt.Errorf("embedding failed: %d vectors for %d texts", len(vectors), len(req.Texts)))
+	}
+	return hostEmbedResponse{Vectors: vectors}
+}
+
+func embedErrorResponse(err error) hostEmbedResponse {
+	return hostEmbedResponse{Code: fsErrno(err), Message: err.Error()}
+}
@@ -131,6 +131,52 @@ func HostFSStat(ctx context.Context, mod wazeroapi.Module, params []uint64, fs f
 	return []uint64{uint64(jsonPtr)}
 }
 
+// HostFSRealPath serves host_fs_realpath: path with its symbolic links
+// resolved, so a plugin confining users to a directory can check where a
+// path leads. Packs the string pointer in the lower 32 bits and an error
+// pointer in the upper 32 bits.
+func HostFSRealPath(ctx context.Context, mod wazeroapi.Module, params []uint64, fs filesystem.FileSystem) []uint64 {
+	pathPtr := uint32(params[0])
+
+	path, ok := readStringFromMemory(mod, pathPtr)
+	if !ok {
+		log.Errorf("host_fs_realpath: failed to read path from memory")
+		return []uint64{0}
+	}
+
+	log.Debugf("host_fs_realpath: path=%s", path)
+
+	fail := func(msg string) []uint64 {
+		errPtr, _, err := writeStringToMemory(mod, msg)
+		if err != nil {
+			return []uint64{0}
+		}
+		return []uint64{uint64(errPtr) << 32}
+	}
+
+	if fs == nil {
+		log.Errorf("host_fs_realpath: no host filesystem provided")
+		return fail("no host filesystem provided")
+	}
+
+	// Without symbolic links a path is its own real path
+	realPath := filesystem.NormalizePath(path)
+	if realPather, ok := fs.(filesystem.RealPather); ok {
+		var err error
+		if realPath, err = realPather.RealPath(path); err != nil {
+			log.Errorf("host_fs_realpath: error resolving path: %v", err)
+			return fail(err.Error())
+		}
+	}
+
+	realPtr, _, err := writeStringToMemory(mod, realPath)
+	if err != nil {
+		log.Errorf("host_fs_realpath: failed to write path to memory: %v", err)
+		return []uint64{0}
+	}
+	return []uint64{uint64(realPtr)}
+}
+
 func HostFSReadDir(ctx context.Context, mod wazeroapi.Module, params []uint64, fs filesystem.FileSystem) []uint64 {
 	pathPtr := uint32(params[0])
 
@@ -338,3 +384,81 @@ func HostFSChmod(ctx context.Context, mod wazeroapi.Module, params []uint64, fs
 
 	return []uint64{0}
 }
+
+func HostFSWriteAt(ctx context.Context, mod wazeroapi.Module, params []uint64, fs filesystem.FileSystem) []uint64 {
+	pathPtr := uint32(params[0])
+	dataPtr := uint32(params[1])
+	dataLen := uint32(params[2])
+	offset := int64(params[3])
+
+	// Result packing: lower 32 bits = bytes written, upper 32 bits = error pointer
+	fail := func(msg string) []uint64 {
+		errPtr, _, _ := writeStringToMemory(mod, msg)
+		return []uint64{uint64(errPtr) << 32}
+	}
+
+	path, ok := readStringFromMemory(mod, pathPtr)
+	if !ok {
+		return fail("failed to read path from memory")
+	}
+
+	data, ok := mod.Memory().Read(dataPtr, dataLen)
+	if !ok {
+		return fail("failed to read data from memory")
+	}
+
+	log.Debugf("host_fs_write_at: path=%s, dataLen=%d, offset=%d", path, dataLen, offset)
+
+	if fs == nil {
+		log.Errorf("host_fs_write_at: no host filesystem provided")
+		return fail("no host filesystem provided")
+	}
+
+	var written int64
+	var err error
+	if w, ok := fs.(filesystem.RandomWriter); ok {
+		written, err = w.WriteAt(path, data, offset)
+	} else {
+		written, err = fs.Write(path, data, offset, filesystem.WriteFlagNone)
+	}
+	if err != nil {
+		log.Errorf("host_fs_write_at: error writing file: %v", err)
+		return fail(err.Error())
+	}
+
+	return []uint64{uint64(uint32(written))}
+}
+
+func HostFSTruncate(ctx context.Context, mod wazeroapi.Module, params []uint64, fs filesystem.FileSystem) []uint64 {
+	pathPtr := uint32(params[0])
+	size := int64(params[1])
+
+	path, ok := readStringFromMemory(mod, pathPtr)
+	if !ok {
+		log.Errorf("host_fs_truncate: failed to read path from memory")
+		errPtr, _, _ := writeStringToMemory(mod, "failed to read path from memory")
+		return []uint64{uint64(errPtr)}
+	}
+
+	log.Debugf("host_fs_truncate: path=%s, size=%d", path, size)
+
+	if fs == nil {
+		log.Errorf("host_fs_truncate: no host filesystem provided")
+		errPtr, _, _ := writeStringToMemory(mod, "no host filesystem provided")
+		return []uint64{uint64(errPtr)}
+	}
+
+	t, ok := fs.(filesystem.Truncater)
+	if !ok {
+		errPtr, _, _ := writeStringToMemory(mod, "host filesystem does not support truncate")
+		return []uint64{uint64(errPtr)}
+	}
+
+	if err := t.Truncate(path, size); err != nil {
+		log.Errorf("host_fs_truncate: error truncating file: %v", err)
+		errPtr, _, _ := writeStringToMemory(mod, err.Error())
+		return []uint64{uint64(errPtr)}
+	}
+
+	return []uint64{0}
+}
@@ -2,10 +2,16 @@ package api
 
 import (
 	"context"
+	"crypto/tls"
+	"crypto/x509"
 	"encoding/json"
+	"errors"
+	"fmt"
 	"io"
 	"net/http"
+	"net/url"
 	"strings"
+	"sync"
 	"time"
 
 	log "github.com/sirupsen/logrus"
@@ -19,6 +25,195 @@ type HTTPRequest struct {
 	Headers map[string]string `json:"headers"`
 	Body    []byte            `json:"body"`
 	Timeout int               `json:"timeout"` // timeout in seconds
+	TLS     *HTTPTLSOptions   `json:"tls,omitempty"`
+	Proxy   *HTTPProxyOptions `json:"proxy,omitempty"`
+	Pool    *HTTPPoolOptions  `json:"pool,omitempty"`
+	Cache   bool              `json:"cache,omitempty"` // share the response through the host cache
+}
+
+// HTTPTLSOptions carries per-request TLS settings from WASM (PEM-encoded)
+type HTTPTLSOptions struct {
+	ClientCert         string `json:"client_cert,omitempty"`
+	ClientKey          string `json:"client_key,omitempty"`
+	CABundle           string `json:"ca_bundle,omitempty"`
+	InsecureSkipVerify bool   `json:"insecure_skip_verify,omitempty"`
+}
+
+// tlsConfig builds the client TLS configuration; a CA bundle is added to the
+// system roots rather than replacing them
+func (o *HTTPTLSOptions) tlsConfig() (*tls.Config, error) {
+	cfg := &tls.Config{InsecureSkipVerify: o.InsecureSkipVerify}
+
+	if o.CABundle != "" {
+		pool, err := x509.SystemCertPool()
+		if err != nil {
+			pool = x509.NewCertPool()
+		}
+		if !pool.AppendCertsFromPEM([]byte(o.CABundle)) {
+			return nil, errors.New("ca_bundle contains no PEM certificates")
+		}
+		cfg.RootCAs = pool
+	}
+
+	if o.ClientCert != "" || o.ClientKey != "" {
+		cert, err := tls.X509KeyPair([]byte(o.ClientCert), []byte(o.ClientKey))
+		if err != nil {
+			return nil, fmt.Errorf("client certificate: %w", err)
+		}
+		cfg.Certificates = []tls.Certificate{cert}
+	}
+
+	return cfg, nil
+}
+
+// HTTPProxyOptions carries the proxy settings of a plugin, taken from its
+// http_proxy, https_proxy and no_proxy config keys
+type HTTPProxyOptions struct {
+	HTTPProxy  string `json:"http_proxy,omitempty"`
+	HTTPSProxy string `json:"https_proxy,omitempty"`
+	NoProxy    string `json:"no_proxy,omitempty"`
+}
+
+// proxyFunc returns the proxy selector for an http.Transport. https requests
+// fall back to the http proxy; no_proxy entries match a host and its
+// subdomains, and "*" bypasses the proxy entirely.
+func (o *HTTPProxyOptions) proxyFunc() (func(*http.Request) (*url.URL, error), error) {
+	parse := func(key, raw string) (*url.URL, error) {
+		if raw == "" {
+			return nil, nil
+		}
+		if !strings.Contains(raw, "://") {
+			raw = "http://" + raw
+		}
+		u, err := url.Parse(raw)
+		if err != nil {
+			return nil, fmt.Errorf("%s: %w", key, err)
+		}
+		switch u.Scheme {
+		case "http", "https", "socks5", "socks5h":
+			return u, nil
+		default:
+			return nil, fmt.Errorf("%s: unsupported proxy scheme %q", key, u.Scheme)
+		}
+	}
+
+	httpProxy, err := parse("http_proxy", o.HTTPProxy)
+	if err != nil {
+		return nil, err
+	}
+	httpsProxy, err := parse("https_proxy", o.HTTPSProxy)
+	if err != nil {
+		return nil, err
+	}
+	if httpsProxy == nil {
+		httpsProxy = httpProxy
+	}
+
+	var noProxy []string
+	for _, entry := range strings.Split(o.NoProxy, ",") {
+		entry = strings.ToLower(strings.TrimPrefix(strings.TrimSpace(entry), "."))
+		if entry != "" {
+			noProxy = append(noProxy, entry)
+		}
+	}
+
+	return func(r *http.Request) (*url.URL, error) {
+		host := strings.ToLower(r.URL.Hostname())
+		for _, entry := range noProxy {
+			if entry == "*" || host == entry || strings.HasSuffix(host, "."+entry) {
+				return nil, nil
+			}
+		}
+		if r.URL.Scheme == "https" {
+			return httpsProxy, nil
+		}
+		return httpProxy, nil
+	}, nil
+}
+
+// HTTPPoolOptions carries the connection pool settings of a plugin, taken from
+// its http_max_conns_per_host, http_keep_alive and http2 config keys
+type HTTPPoolOptions struct {
+	MaxConnsPerHost int   `json:"max_conns_per_host,omitempty"`
+	KeepAlive       *bool `json:"keep_alive,omitempty"`
+	HTTP2           *bool `json:"http2,omitempty"`
+}
+
+// apply sets the pool options on a transport; a connection limit also lets
+// that many connections per host stay idle for reuse
+func (o *HTTPPoolOptions) apply(t *http.Transport) {
+	if o.MaxConnsPerHost > 0 {
+		t.MaxConnsPerHost = o.MaxConnsPerHost
+		t.MaxIdleConnsPerHost = o.MaxConnsPerHost
+	}
+	if o.KeepAlive != nil {
+		t.DisableKeepAlives = !*o.KeepAlive
+	}
+	if o.HTTP2 != nil {
+		protocols := new(http.Protocols)
+		protocols.SetHTTP1(true)
+		protocols.SetHTTP2(*o.HTTP2)
+		t.Protocols = protocols
+	}
+}
+
+// maxHTTPTransports bounds the transports kept for reuse; past it they are
+// all dropped and rebuilt on demand
+const maxHTTPTransports = 256
+
+// httpTransports holds one transport per distinct TLS, proxy and pool
+// setting, so requests of a plugin reuse their connections instead of
+// dialing for each request
+var (
+	httpTransportsMu sync.Mutex
+	httpTransports   = make(map[string]*http.Transport)
+)
+
+// transportFor returns the shared transport for the options of req, or nil
+// if it sets none and the default transport applies
+func transportFor(req *HTTPRequest) (*http.Transport, error) {
+	if req.TLS == nil && req.Proxy == nil && req.Pool == nil {
+		return nil, nil
+	}
+	keyJSON, err := json.Marshal([]any{req.TLS, req.Proxy, req.Pool})
+	if err != nil {
+		return nil, err
+	}
+	key := string(keyJSON)
+
+	httpTransportsMu.Lock()
+	defer httpTransportsMu.Unlock()
+	if transport, ok := httpTransports[key]; ok {
+		return transport, nil
+	}
+
+	transport := http.DefaultTransport.(*http.Transport).Clone()
+	if req.TLS != nil {
+		tlsConfig, err := req.TLS.tlsConfig()
+		if err != nil {
+			return nil, fmt.Errorf("invalid TLS options: %w", err)
+		}
+		transport.TLSClientConfig = tlsConfig
+	}
+	if req.Proxy != nil {
+		proxy, err := req.Proxy.proxyFunc()
+		if err != nil {
+			return nil, fmt.Errorf("invalid proxy options: %w", err)
+		}
+		transport.Proxy = proxy
+	}
+	if req.Pool != nil {
+		req.Pool.apply(transport)
+	}
+
+	if len(httpTransports) >= maxHTTPTransports {
+		for _, old := range httpTransports {
+			old.CloseIdleConnections()
+		}
+		clear(httpTransports)
+	}
+	httpTransports[key] = transport
+	return transport, nil
 }
 
 // HTTPResponse represents an HTTP response to WASM
@@ -27,6 +222,7 @@ type HTTPResponse struct {
 	Headers    map[string]string `json:"headers"`
 	Body       []byte            `json:"body"`
 	Error      string            `json:"error,omitempty"`
+	Cached     bool              `json:"cached,omitempty"` // body served from the host cache
 }
 
 // HostHTTPRequest performs an HTTP request from the host
@@ -44,8 +240,6 @@ func HostHTTPRequest(ctx context.Context, mod wazeroapi.Module, params []uint64)
 		return []uint64{0}
 	}
 
-	log.Debugf("host_http_request: requestJSON=%s", requestJSON)
-
 	// Parse request
 	var req HTTPRequest
 	if err := json.Unmarshal([]byte(requestJSON), &req); err != nil {
@@ -61,6 +255,8 @@ func HostHTTPRequest(ctx context.Context, mod wazeroapi.Module, params []uint64)
 		req.Method = "GET"
 	}
 
+	log.Debugf("host_http_request: %s %s", req.Method, req.URL)
+
 	// Create HTTP client with timeout
 	timeout := time.Duration(req.Timeout) * time.Second
 	if timeout == 0 {
@@ -70,6 +266,21 @@ func HostHTTPRequest(ctx context.Context, mod wazeroapi.Module, params []uint64)
 		Timeout: timeout,
 	}
 
+	transport, err := transportFor(&req)
+	if err != nil {
+		log.Errorf("host_http_request: %v", err)
+		resp := HTTPResponse{
+			Error: err.Error(),
+		}
+		return packHTTPResponse(mod, &resp)
+	}
+	if transport != nil {
+		if req.TLS != nil && req.TLS.InsecureSkipVerify {
+			log.Warnf("host_http_request: TLS certificate verification disabled for %s %s", req.Method, req.URL)
+		}
+		client.Transport = transport
+	}
+
 	// Create HTTP request
 	var bodyReader io.Reader
 	if len(req.Body) > 0 {
@@ -90,6 +301,30 @@ func HostHTTPRequest(ctx context.Context, mod wazeroapi.Module, params []uint64)
 		httpReq.Header.Set(key, value)
 	}
 
+	// Answer cacheable GETs from the shared cache while fresh, and revalidate
+	// them once stale
+	cacheKey, cacheable := "", false
+	var cached httpCacheEntry
+	var hit bool
+	if req.Cache {
+		cacheKey, cacheable = httpCacheKey(&req)
+	}
+	if cacheable {
+		cached, hit = sharedHTTPCache.get(cacheKey)
+		if hit && time.Now().Before(cached.freshUntil) {
+			log.Debugf("host_http_request: cache hit for %s", req.URL)
+			resp := cached.resp
+			resp.Cached = true
+			return packHTTPResponse(mod, &resp)
+		}
+		if hit && cached.etag != "" {
+			httpReq.Header.Set("If-None-Match", cached.etag)
+		}
+		if hit && cached.lastModified != "" {
+			httpReq.Header.Set("If-Modified-Since", cached.lastModified)
+		}
+	}
+
 	// Perform request
 	httpResp, err := client.Do(httpReq)
 	if err != nil {
@@ -101,6 +336,14 @@ func HostHTTPRequest(ctx context.Context, mod wazeroapi.Module, params []uint64)
 	}
 	defer httpResp.Body.Close()
 
+	if hit && httpResp.StatusCode == http.StatusNotModified {
+		log.Debugf("host_http_request: cache revalidated for %s", req.URL)
+		sharedHTTPCache.revalidated(cacheKey, httpResp.Header, time.Now())
+		resp := cached.resp
+		resp.Cached = true
+		return packHTTPResponse(mod, &resp)
+	}
+
 	// Read response body
 	respBody, err := io.ReadAll(httpResp.Body)
 	if err != nil {
@@ -126,6 +369,9 @@ func HostHTTPRequest(ctx context.Context, mod wazeroapi.Module, params []uint64)
 		Headers:    respHeaders,
 		Body:       respBody,
 	}
+	if cacheable {
+		sharedHTTPCache.store(cacheKey, resp, httpResp.Header, time.Now())
+	}
 
 	log.Debugf("host_http_request: status=%d, bodyLen=%d", resp.StatusCode, len(resp.Body))
 	return packHTTPResponse(mod, &resp)
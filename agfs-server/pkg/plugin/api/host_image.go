@@ -0,0 +1,210 @@
+package api
+
+import (
+	"bytes"
+	"context"
+	"encoding/json"
+	"fmt"
+	"image"
+	"image/color"
+	"image/draw"
+	"image/gif"
+	"image/jpeg"
+	"image/png"
+
+	"github.com/c4pt0r/agfs/agfs-server/pkg/filesystem"
+	log "github.com/sirupsen/logrus"
+	wazeroapi "github.com/tetratelabs/wazero/api"
+)
+
+// Image transformation for WASM plugins
+// Media plugins (photo libraries, S3 previews) serve thumbnails through the
+// host's codecs instead of compiling image libraries into every module.
+// JPEG, PNG and GIF are supported; a GIF is reduced to its first frame.
+
+const (
+	// maxImageBytes bounds the encoded image of one host_image_transform call
+	maxImageBytes = 32 << 20
+
+	// maxImagePixels bounds the decoded size, so a small file cannot expand
+	// into gigabytes of pixels
+	maxImagePixels = 50_000_000
+
+	// maxImageSide bounds each side of a resized image
+	maxImageSide = 4096
+
+	// defaultJPEGQuality is used when a plugin sets no quality
+	defaultJPEGQuality = 85
+)
+
+// hostImageRequest is what a plugin asks for through host_image_transform.
+// Op "resize" fits the image into Width x Height keeping its aspect ratio
+// (0 leaves a side unbounded) and never enlarges it; "convert" only
+// re-encodes. Format is "jpeg", "png" or "gif", empty for the source's.
+type hostImageRequest struct {
+	Op      string `json:"op"`
+	Data    []byte `json:"data"`
+	Width   int    `json:"width,omitempty"`
+	Height  int    `json:"height,omitempty"`
+	Format  string `json:"format,omitempty"`
+	Quality int    `json:"quality,omitempty"`
+}
+
+// hostImageResponse carries the transformed image and its dimensions, or a
+// non-zero errno Code and Message
+type hostImageResponse struct {
+	Code    int32  `json:"code,omitempty"`
+	Message string `json:"message,omitempty"`
+	Data    []byte `json:"data,omitempty"`
+	Format  string `json:"format,omitempty"`
+	Width   int    `json:"width,omitempty"`
+	Height  int    `json:"height,omitempty"`
+}
+
+// HostImageTransform serves host_image_transform: a resized or re-encoded
+// copy of an image of up to maxImageBytes. Undecodable images and unknown
+// ops or formats fail with EINVAL. Returns the JSON response packed as
+// pointer | size << 32.
+func HostImageTransform(ctx context.Context, mod wazeroapi.Module, params []uint64) []uint64 {
+	var resp hostImageResponse
+	var req hostImageRequest
+	if reqJSON, ok := readStringFromMemory(mod, uint32(params[0])); !ok {
+		resp = imageErrorResponse(filesystem.NewInvalidArgumentError("request", nil, "failed to read request from memory"))
+	} else if err := json.Unmarshal([]byte(reqJSON), &req); err != nil {
+		resp = imageErrorResponse(filesystem.NewInvalidArgumentError("request", nil, err.Error()))
+	} else {
+		log.Debugf("host_image_transform: %s of %d bytes", req.Op, len(req.Data))
+		resp = serveImageRequest(&req)
+	}
+
+	respJSON, err := json.Marshal(resp)
+	if err != nil {
+		log.Errorf("host_image_transform: failed to marshal response: %v", err)
+		return []uint64{0}
+	}
+	respPtr, _, err := writeBytesToMemory(mod, respJSON)
+	if err != nil {
+		log.Errorf("host_image_transform: failed to write response to memory: %v", err)
+		return []uint64{0}
+	}
+	return []uint64{uint64(respPtr) | uint64(len(respJSON))<<32}
+}
+
+func serveImageRequest(req *hostImageRequest) hostImageResponse {
+	if len(req.Data) > maxImageBytes {
+		return imageErrorResponse(filesystem.NewInvalidArgumentError("data", len(req.Data), fmt.Sprintf("at most %d bytes", maxImageBytes)))
+	}
+	switch req.Op {
+	case "resize":
+		if req.Width < 0 || req.Height < 0 || req.Width > maxImageSide || req.Height > maxImageSide || req.Width+req.Height == 0 {
+			return imageErrorResponse(filesystem.NewInvalidArgumentError("size", fmt.Sprintf("%dx%d", req.Width, req.Height), fmt.Sprintf("sides up to %d, at least one set", maxImageSide)))
+		}
+	case "convert":
+	default:
+		return imageErrorResponse(filesystem.NewInvalidArgumentError("op", req.Op, "expected resize or convert"))
+	}
+
+	cfg, source, err := image.DecodeConfig(bytes.NewReader(req.Data))
+	if err != nil {
+		return imageErrorResponse(filesystem.NewInvalidArgumentError("data", nil, fmt.Sprintf("not a supported image: %v", err)))
+	}
+	if cfg.Width*cfg.Height > maxImagePixels {
+		return imageErrorResponse(filesystem.NewInvalidArgumentError("data", fmt.Sprintf("%dx%d", cfg.Width, cfg.Height), fmt.Sprintf("at most %d pixels", maxImagePixels)))
+	}
+	format := req.Format
+	if format == "" {
+		format = source
+	}
+	if format != "jpeg" && format != "png" && format != "gif" {
+		return imageErrorResponse(filesystem.NewInvalidArgumentError("format", format, "expected jpeg, png or gif"))
+	}
+
+	img, _, err := image.Decode(bytes.NewReader(req.Data))
+	if err != nil {
+		return imageErrorResponse(filesystem.NewInvalidArgumentError("data", nil, fmt.Sprintf("failed to decode image: %v", err)))
+	}
+	if req.Op == "resize" {
+		img = resizeImage(img, req.Width, req.Height)
+	}
+
+	var out bytes.Buffer
+	switch format {
+	case "jpeg":
+		quality := req.Quality
+		if quality <= 0 || quality > 100 {
+			quality = defaultJPEGQuality
+		}
+		err = jpeg.Encode(&out, flattenImage(img), &jpeg.Options{Quality: quality})
+	case "png":
+		err = png.Encode(&out, img)
+	case "gif":
+		err = gif.Encode(&out, img, nil)
+	}
+	if err != nil {
+		return imageErrorResponse(fmt.Errorf("failed to encode %s: %w", format, err))
+	}
+	bounds := img.Bounds()
+	return hostImageResponse{Data: out.Bytes(), Format: format, Width: bounds.Dx(), Height: bounds.Dy()}
+}
+
+// resizeImage scales img down to fit width x height (0 = unbounded),
+// averaging the source pixels under each target pixel. Images that already
+// fit are returned unchanged.
+func resizeImage(img image.Image, width, height int) image.Image {
+	bounds := img.Bounds()
+	sw, sh := bounds.Dx(), bounds.Dy()
+	scale := 1.0
+	if width > 0 && sw > width {
+		scale = float64(width) / float64(sw)
+	}
+	if height > 0 && float64(sh)*scale > float64(height) {
+		scale = float64(height) / float64(sh)
+	}
+	if scale >= 1 {
+		return img
+	}
+	dw := max(1, int(float64(sw)*scale+0.5))
+	dh := max(1, int(float64(sh)*scale+0.5))
+
+	src := image.NewRGBA(image.Rect(0, 0, sw, sh))
+	draw.Draw(src, src.Bounds(), img, bounds.Min, draw.Src)
+	dst := image.NewRGBA(image.Rect(0, 0, dw, dh))
+	for dy := 0; dy < dh; dy++ {
+		y0, y1 := dy*sh/dh, max((dy+1)*sh/dh, dy*sh/dh+1)
+		for dx := 0; dx < dw; dx++ {
+			x0, x1 := dx*sw/dw, max((dx+1)*sw/dw, dx*sw/dw+1)
+			var sum [4]int
+			for y := y0; y < y1; y++ {
+				row := src.Pix[y*src.Stride+x0*4 : y*src.Stride+x1*4]
+				for i := 0; i < len(row); i += 4 {
+					sum[0] += int(row[i])
+					sum[1] += int(row[i+1])
+					sum[2] += int(row[i+2])
+					sum[3] += int(row[i+3])
+				}
+			}
+			n := (y1 - y0) * (x1 - x0)
+			i := dy*dst.Stride + dx*4
+			for c := 0; c < 4; c++ {
+				dst.Pix[i+c] = uint8(sum[c] / n)
+			}
+		}
+	}
+	return dst
+}
+
+// flattenImage composes img over white, since JPEG has no transparency
+func flattenImage(img image.Image) image.Image {
+	if _, ok := img.(*image.YCbCr); ok {
+		return img
+	}
+	bounds := img.Bounds()
+	flat := image.NewRGBA(bounds)
+	draw.Draw(flat, bounds, image.NewUniform(color.White), image.Point{}, draw.Src)
+	draw.Draw(flat, bounds, img, bounds.Min, draw.Over)
+	return flat
+}
+
+func imageErrorResponse(err error) hostImageResponse {
+	return hostImageResponse{Code: fsErrno(err), Message: err.Error()}
+}
@@ -0,0 +1,37 @@
+package api
+
+import (
+	"context"
+	"encoding/json"
+
+	log "github.com/sirupsen/logrus"
+	wazeroapi "github.com/tetratelabs/wazero/api"
+)
+
+// Host capabilities for WASM plugins
+// A plugin built for a newer server may import functions this one lacks.
+// The loader stubs those so the plugin still loads, and host_capabilities
+// tells the plugin which imports actually work, so it can turn features off
+// instead of failing when it calls them.
+
+// hostCapabilitiesResponse lists the usable "env" imports, sorted
+type hostCapabilitiesResponse struct {
+	Imports []string `json:"imports"`
+}
+
+// HostCapabilities serves host_capabilities: the imports the plugin can use,
+// leaving out stubs and functions whose backend the server was started
+// without. Returns the JSON response packed as pointer | size << 32.
+func HostCapabilities(ctx context.Context, mod wazeroapi.Module, imports []string) []uint64 {
+	respJSON, err := json.Marshal(hostCapabilitiesResponse{Imports: imports})
+	if err != nil {
+		log.Errorf("host_capabilities: failed to marshal response: %v", err)
+		return []uint64{0}
+	}
+	respPtr, _, err := writeBytesToMemory(mod, respJSON)
+	if err != nil {
+		log.Errorf("host_capabilities: failed to write response to memory: %v", err)
+		return []uint64{0}
+	}
+	return []uint64{uint64(respPtr) | uint64(len(respJSON))<<32}
+}
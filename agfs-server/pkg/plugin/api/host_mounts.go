@@ -0,0 +1,265 @@
+package api
+
+import (
+	"context"
+	"encoding/json"
+	"errors"
+	"fmt"
+	"io"
+	"path"
+	"strings"
+	"sync"
+
+	"github.com/c4pt0r/agfs/agfs-server/pkg/filesystem"
+	log "github.com/sirupsen/logrus"
+	wazeroapi "github.com/tetratelabs/wazero/api"
+)
+
+// MountGrant is one entry of the "mounts" capability a WASM plugin declares:
+// an agfs path, and everything below it, the plugin may use through
+// host_mount_call; read-only unless Write is set
+type MountGrant struct {
+	Path  string `json:"path"`
+	Write bool   `json:"write,omitempty"`
+}
+
+// MountAccess holds the mounts a WASM plugin may use. The plugin declares
+// grants, and only those the operator allows in the "mounts" key of its
+// mount config are kept; every host_mount_call is checked against them, so a
+// plugin declaring none, or mounted without an allowlist, is refused.
+type MountAccess struct {
+	mu       sync.RWMutex
+	allowed  []MountGrant
+	declared []MountGrant
+	grants   []MountGrant
+}
+
+// SetAllowed sets the operator's allowlist from the "mounts" value of the
+// mount config: a list of paths, read-only, or of {path, write} maps
+func (a *MountAccess) SetAllowed(value interface{}) error {
+	var allowed []MountGrant
+	if value != nil {
+		items, ok := value.([]interface{})
+		if !ok {
+			return fmt.Errorf("mounts must be a list of paths or {path, write} entries")
+		}
+		for _, item := range items {
+			grant, err := parseAllowedMount(item)
+			if err != nil {
+				return err
+			}
+			allowed = append(allowed, grant)
+		}
+	}
+
+	a.mu.Lock()
+	defer a.mu.Unlock()
+	a.allowed = allowed
+	a.grants, _ = allowedGrants(a.allowed, a.declared)
+	return nil
+}
+
+func parseAllowedMount(item interface{}) (MountGrant, error) {
+	switch v := item.(type) {
+	case string:
+		return MountGrant{Path: cleanMountPath(v)}, nil
+	case map[string]interface{}:
+		p, ok := v["path"].(string)
+		if !ok {
+			return MountGrant{}, fmt.Errorf("mounts entry without a path: %v", v)
+		}
+		write, _ := v["write"].(bool)
+		return MountGrant{Path: cleanMountPath(p), Write: write}, nil
+	default:
+		return MountGrant{}, fmt.Errorf("invalid mounts entry: %v", item)
+	}
+}
+
+// Refresh replaces the grants with those reported by the module's
+// plugin_capabilities export, e.g. after the plugin read its config
+func (a *MountAccess) Refresh(ctx context.Context, module wazeroapi.Module) error {
+	capsFunc := module.ExportedFunction("plugin_capabilities")
+	if capsFunc == nil {
+		return a.SetCapabilities("{}")
+	}
+	results, err := capsFunc.Call(ctx)
+	if err != nil || len(results) == 0 {
+		return fmt.Errorf("plugin_capabilities failed: %v", err)
+	}
+	capsJSON, ok := readStringFromMemory(module, uint32(results[0]))
+	if !ok {
+		return fmt.Errorf("failed to read plugin capabilities")
+	}
+	return a.SetCapabilities(capsJSON)
+}
+
+// SetCapabilities replaces the grants with the "mounts" of a capabilities
+// JSON document that the allowlist covers. Grants outside it are refused
+// and reported in the error; the others still apply.
+func (a *MountAccess) SetCapabilities(capsJSON string) error {
+	var caps struct {
+		Mounts []MountGrant `json:"mounts"`
+	}
+	if err := json.Unmarshal([]byte(capsJSON), &caps); err != nil {
+		return fmt.Errorf("invalid plugin capabilities: %w", err)
+	}
+	for i := range caps.Mounts {
+		caps.Mounts[i].Path = cleanMountPath(caps.Mounts[i].Path)
+	}
+
+	a.mu.Lock()
+	defer a.mu.Unlock()
+	a.declared = caps.Mounts
+	var refused []string
+	a.grants, refused = allowedGrants(a.allowed, a.declared)
+	if len(refused) > 0 {
+		return fmt.Errorf("mounts not allowed by the mount config, refused: %s", strings.Join(refused, ", "))
+	}
+	return nil
+}
+
+// allowedGrants splits declared into the grants an allowed entry covers,
+// with write access only where it allows writes, and the refused ones
+func allowedGrants(allowed, declared []MountGrant) (granted []MountGrant, refused []string) {
+	for _, grant := range declared {
+		ok := false
+		for _, allow := range allowed {
+			if mountCovers(allow.Path, grant.Path) && (allow.Write || !grant.Write) {
+				ok = true
+				break
+			}
+		}
+		switch {
+		case ok:
+			granted = append(granted, grant)
+		case grant.Write:
+			refused = append(refused, grant.Path+" (write)")
+		default:
+			refused = append(refused, grant.Path)
+		}
+	}
+	return granted, refused
+}
+
+// mountCovers reports whether clean path p is root or below it
+func mountCovers(root, p string) bool {
+	return root == "/" || p == root || strings.HasPrefix(p, root+"/")
+}
+
+// Check returns the cleaned form of p if a grant covers it, with write
+// access if write is set
+func (a *MountAccess) Check(op, p string, write bool) (string, error) {
+	clean := cleanMountPath(p)
+
+	a.mu.RLock()
+	defer a.mu.RUnlock()
+	for _, grant := range a.grants {
+		if mountCovers(grant.Path, clean) && (grant.Write || !write) {
+			return clean, nil
+		}
+	}
+	return "", filesystem.NewPermissionDeniedError(op, clean, "not in the plugin's allowed mounts")
+}
+
+func cleanMountPath(p string) string {
+	return path.Clean("/" + p)
+}
+
+// hostMountRequest is one operation a plugin asks for through host_mount_call
+type hostMountRequest struct {
+	Op      string `json:"op"`
+	Path    string `json:"path"`
+	NewPath string `json:"new_path"`
+	Offset  int64  `json:"offset"`
+	Size    int64  `json:"size"`
+	Data    []byte `json:"data"`
+	Perm    uint32 `json:"perm"`
+}
+
+// hostMountResponse carries the result of a hostMountRequest, or a non-zero
+// errno Code and Message
+type hostMountResponse struct {
+	Code    int32                 `json:"code,omitempty"`
+	Message string                `json:"message,omitempty"`
+	Data    []byte                `json:"data,omitempty"`
+	Written int64                 `json:"written,omitempty"`
+	Info    *filesystem.FileInfo  `json:"info,omitempty"`
+	Entries []filesystem.FileInfo `json:"entries,omitempty"`
+}
+
+// HostMountCall serves host_mount_call: a read, write, stat, readdir, mkdir,
+// remove or rename on fs (the whole agfs tree) for a path the plugin was
+// granted. Returns the JSON response packed as pointer | size << 32.
+func HostMountCall(ctx context.Context, mod wazeroapi.Module, params []uint64, fs filesystem.FileSystem, access *MountAccess) []uint64 {
+	var resp hostMountResponse
+	var req hostMountRequest
+	if reqJSON, ok := readStringFromMemory(mod, uint32(params[0])); !ok {
+		resp = mountErrorResponse(filesystem.NewInvalidArgumentError("request", nil, "failed to read request from memory"))
+	} else if err := json.Unmarshal([]byte(reqJSON), &req); err != nil {
+		resp = mountErrorResponse(filesystem.NewInvalidArgumentError("request", nil, err.Error()))
+	} else {
+		log.Debugf("host_mount_call: op=%s, path=%s", req.Op, req.Path)
+		resp = serveMountRequest(fs, access, &req)
+	}
+
+	respJSON, err := json.Marshal(resp)
+	if err != nil {
+		log.Errorf("host_mount_call: failed to marshal response: %v", err)
+		return []uint64{0}
+	}
+	respPtr, _, err := writeBytesToMemory(mod, respJSON)
+	if err != nil {
+		log.Errorf("host_mount_call: failed to write response to memory: %v", err)
+		return []uint64{0}
+	}
+	return []uint64{uint64(respPtr) | uint64(len(respJSON))<<32}
+}
+
+func serveMountRequest(fs filesystem.FileSystem, access *MountAccess, req *hostMountRequest) (resp hostMountResponse) {
+	if fs == nil || access == nil {
+		return mountErrorResponse(fmt.Errorf("no host filesystem provided"))
+	}
+
+	write := req.Op != "read" && req.Op != "stat" && req.Op != "readdir"
+	p, err := access.Check(req.Op, req.Path, write)
+	if err != nil {
+		return mountErrorResponse(err)
+	}
+
+	switch req.Op {
+	case "read":
+		resp.Data, err = fs.Read(p, req.Offset, req.Size)
+		if errors.Is(err, io.EOF) {
+			err = nil
+		}
+	case "write":
+		if req.Offset < 0 {
+			resp.Written, err = fs.Write(p, req.Data, -1, filesystem.WriteFlagCreate|filesystem.WriteFlagTruncate)
+		} else {
+			resp.Written, err = fs.Write(p, req.Data, req.Offset, filesystem.WriteFlagNone)
+		}
+	case "stat":
+		resp.Info, err = fs.Stat(p)
+	case "readdir":
+		resp.Entries, err = fs.ReadDir(p)
+	case "mkdir":
+		err = fs.Mkdir(p, req.Perm)
+	case "remove":
+		err = fs.Remove(p)
+	case "rename":
+		var newPath string
+		if newPath, err = access.Check(req.Op, req.NewPath, true); err == nil {
+			err = fs.Rename(p, newPath)
+		}
+	default:
+		err = filesystem.NewInvalidArgumentError("op", req.Op, "unknown mount operation")
+	}
+	if err != nil {
+		return mountErrorResponse(err)
+	}
+	return resp
+}
+
+func mountErrorResponse(err error) hostMountResponse {
+	return hostMountResponse{Code: fsErrno(err), Message: err.Error()}
+}
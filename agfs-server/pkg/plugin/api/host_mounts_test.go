@@ -0,0 +1,91 @@
+package api
+
+import "testing"
+
+func TestMountAccessRefusesSelfDeclaredRoot(t *testing.T) {
+	access := &MountAccess{}
+	if err := access.SetAllowed([]interface{}{"/s3/photos"}); err != nil {
+		t.Fatalf("SetAllowed failed: %v", err)
+	}
+
+	err := access.SetCapabilities(`{"mounts":[{"path":"/","write":true}]}`)
+	if err == nil {
+		t.Fatal("expected a self-declared / grant to be refused")
+	}
+	for _, p := range []string{"/", "/s3/photos/beach.png", "/local/secrets"} {
+		if _, err := access.Check("read", p, false); err == nil {
+			t.Errorf("read of %s allowed by a refused grant", p)
+		}
+		if _, err := access.Check("write", p, true); err == nil {
+			t.Errorf("write of %s allowed by a refused grant", p)
+		}
+	}
+}
+
+func TestMountAccessRefusesWithoutAllowlist(t *testing.T) {
+	access := &MountAccess{}
+	if err := access.SetAllowed(nil); err != nil {
+		t.Fatalf("SetAllowed failed: %v", err)
+	}
+	if err := access.SetCapabilities(`{"mounts":[{"path":"/s3"}]}`); err == nil {
+		t.Fatal("expected grants to be refused without an allowlist")
+	}
+	if _, err := access.Check("read", "/s3/key", false); err == nil {
+		t.Error("read allowed without an allowlist")
+	}
+}
+
+func TestMountAccessKeepsAllowedGrants(t *testing.T) {
+	access := &MountAccess{}
+	allowed := []interface{}{
+		"/s3",
+		map[string]interface{}{"path": "/local/out", "write": true},
+	}
+	if err := access.SetAllowed(allowed); err != nil {
+		t.Fatalf("SetAllowed failed: %v", err)
+	}
+
+	// The write grant on /s3 is refused, the others apply
+	err := access.SetCapabilities(`{"mounts":[{"path":"/s3/photos"},{"path":"/s3/docs","write":true},{"path":"/local/out/","write":true}]}`)
+	if err == nil {
+		t.Fatal("expected the write grant on read-only /s3 to be refused")
+	}
+
+	if p, err := access.Check("read", "/s3/photos/../photos/a.png", false); err != nil || p != "/s3/photos/a.png" {
+		t.Errorf("read of /s3/photos/a.png: got %q, %v", p, err)
+	}
+	if _, err := access.Check("write", "/s3/photos/a.png", true); err == nil {
+		t.Error("write allowed under a read-only grant")
+	}
+	if _, err := access.Check("read", "/s3/docs/a.txt", false); err == nil {
+		t.Error("read allowed under a refused grant")
+	}
+	if _, err := access.Check("write", "/local/out/report.txt", true); err != nil {
+		t.Errorf("write of /local/out/report.txt: %v", err)
+	}
+	if _, err := access.Check("read", "/local/outside", false); err == nil {
+		t.Error("read allowed for a sibling of a granted path")
+	}
+}
+
+func TestMountAccessAllowlistChangesApplyToDeclaredGrants(t *testing.T) {
+	access := &MountAccess{}
+	if err := access.SetCapabilities(`{"mounts":[{"path":"/s3"}]}`); err == nil {
+		t.Fatal("expected grants to be refused before the allowlist is set")
+	}
+	if err := access.SetAllowed([]interface{}{"/s3"}); err != nil {
+		t.Fatalf("SetAllowed failed: %v", err)
+	}
+	if _, err := access.Check("read", "/s3/key", false); err != nil {
+		t.Errorf("read of /s3/key after allowing it: %v", err)
+	}
+}
+
+func TestMountAccessRejectsInvalidAllowlist(t *testing.T) {
+	access := &MountAccess{}
+	for _, value := range []interface{}{"/s3", []interface{}{42}, []interface{}{map[string]interface{}{"write": true}}} {
+		if err := access.SetAllowed(value); err == nil {
+			t.Errorf("SetAllowed(%v) succeeded", value)
+		}
+	}
+}
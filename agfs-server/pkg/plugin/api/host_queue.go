@@ -0,0 +1,228 @@
+package api
+
+import (
+	"context"
+	"encoding/json"
+	"fmt"
+	"net/url"
+	"os"
+	"path/filepath"
+	"sync"
+
+	"github.com/c4pt0r/agfs/agfs-server/pkg/filesystem"
+	log "github.com/sirupsen/logrus"
+	wazeroapi "github.com/tetratelabs/wazero/api"
+)
+
+// Host message queues for WASM plugins
+// Plugins keep internal work (a backlog of comments to fetch, uploads to
+// retry) in queues the host holds, so it outlives instances and reloads;
+// persistent queues are also written to disk and survive restarts. A plugin
+// only reaches the queues of its own namespace, its name.
+
+// maxQueueItems bounds each queue; pushes beyond it fail with ErrBusy
+const maxQueueItems = 100000
+
+// QueueStore holds the host queues of all WASM plugins, by namespace and
+// queue name. With a directory set, persistent queues are kept in
+// <dir>/<namespace>/<queue>.json.
+type QueueStore struct {
+	mu     sync.Mutex
+	dir    string
+	queues map[string]*hostQueue
+}
+
+type hostQueue struct {
+	file       string
+	items      [][]byte
+	persistent bool
+}
+
+// NewQueueStore creates a store persisting to dir; with an empty dir queues
+// live in memory only and requests for persistent ones fail
+func NewQueueStore(dir string) *QueueStore {
+	return &QueueStore{dir: dir, queues: make(map[string]*hostQueue)}
+}
+
+// Access returns a view of the store for one plugin, which has no
+// namespace until SetNamespace is called
+func (s *QueueStore) Access() *QueueAccess {
+	return &QueueAccess{store: s}
+}
+
+// queue returns the queue name of namespace, loading it from disk the first
+// time it is used. The caller holds s.mu.
+func (s *QueueStore) queue(namespace, name string) (*hostQueue, error) {
+	key := namespace + "/" + name
+	if q, ok := s.queues[key]; ok {
+		return q, nil
+	}
+	q := &hostQueue{}
+	if s.dir != "" {
+		q.file = filepath.Join(s.dir, url.PathEscape(namespace), url.PathEscape(name)+".json")
+		data, err := os.ReadFile(q.file)
+		switch {
+		case err == nil:
+			if err := json.Unmarshal(data, &q.items); err != nil {
+				return nil, fmt.Errorf("corrupt queue file %s: %w", q.file, err)
+			}
+			q.persistent = true
+		case !os.IsNotExist(err):
+			return nil, err
+		}
+	}
+	s.queues[key] = q
+	return q, nil
+}
+
+// save writes items to the queue's file, replacing it atomically
+func (q *hostQueue) save(items [][]byte) error {
+	if err := os.MkdirAll(filepath.Dir(q.file), 0o700); err != nil {
+		return err
+	}
+	data, err := json.Marshal(items)
+	if err != nil {
+		return err
+	}
+	temp := q.file + ".tmp"
+	if err := os.WriteFile(temp, data, 0o600); err != nil {
+		return err
+	}
+	return os.Rename(temp, q.file)
+}
+
+// update replaces the queue's items, writing them to disk first if the
+// queue is persistent
+func (q *hostQueue) update(items [][]byte) error {
+	if q.persistent {
+		if err := q.save(items); err != nil {
+			return fmt.Errorf("failed to persist queue: %w", err)
+		}
+	}
+	q.items = items
+	return nil
+}
+
+// QueueAccess is one plugin's view of a QueueStore
+type QueueAccess struct {
+	store     *QueueStore
+	mu        sync.RWMutex
+	namespace string
+}
+
+// SetNamespace names the plugin's namespace, once its name is known
+func (a *QueueAccess) SetNamespace(namespace string) {
+	a.mu.Lock()
+	a.namespace = namespace
+	a.mu.Unlock()
+}
+
+// hostQueueRequest is one operation a plugin asks for through host_queue_call
+type hostQueueRequest struct {
+	Op         string `json:"op"`
+	Queue      string `json:"queue"`
+	Data       []byte `json:"data"`
+	Persistent bool   `json:"persistent"`
+}
+
+// hostQueueResponse carries the result of a hostQueueRequest, or a non-zero
+// errno Code and Message. Found is false when pop or peek found the queue
+// empty.
+type hostQueueResponse struct {
+	Code    int32  `json:"code,omitempty"`
+	Message string `json:"message,omitempty"`
+	Data    []byte `json:"data,omitempty"`
+	Found   bool   `json:"found,omitempty"`
+	Len     int    `json:"len"`
+}
+
+// HostQueueCall serves host_queue_call: a push, pop, peek or len on a queue
+// of the plugin's namespace. Returns the JSON response packed as
+// pointer | size << 32.
+func HostQueueCall(ctx context.Context, mod wazeroapi.Module, params []uint64, access *QueueAccess) []uint64 {
+	var resp hostQueueResponse
+	var req hostQueueRequest
+	if reqJSON, ok := readStringFromMemory(mod, uint32(params[0])); !ok {
+		resp = queueErrorResponse(filesystem.NewInvalidArgumentError("request", nil, "failed to read request from memory"))
+	} else if err := json.Unmarshal([]byte(reqJSON), &req); err != nil {
+		resp = queueErrorResponse(filesystem.NewInvalidArgumentError("request", nil, err.Error()))
+	} else {
+		log.Debugf("host_queue_call: op=%s, queue=%s", req.Op, req.Queue)
+		resp = serveQueueRequest(access, &req)
+	}
+
+	respJSON, err := json.Marshal(resp)
+	if err != nil {
+		log.Errorf("host_queue_call: failed to marshal response: %v", err)
+		return []uint64{0}
+	}
+	respPtr, _, err := writeBytesToMemory(mod, respJSON)
+	if err != nil {
+		log.Errorf("host_queue_call: failed to write response to memory: %v", err)
+		return []uint64{0}
+	}
+	return []uint64{uint64(respPtr) | uint64(len(respJSON))<<32}
+}
+
+func serveQueueRequest(access *QueueAccess, req *hostQueueRequest) (resp hostQueueResponse) {
+	if access == nil {
+		return queueErrorResponse(filesystem.NewNotSupportedError("queue", req.Queue))
+	}
+	access.mu.RLock()
+	namespace := access.namespace
+	access.mu.RUnlock()
+	if namespace == "" {
+		return queueErrorResponse(filesystem.NewPermissionDeniedError("queue", req.Queue, "plugin has no queue namespace yet"))
+	}
+	if req.Queue == "" {
+		return queueErrorResponse(filesystem.NewInvalidArgumentError("queue", req.Queue, "queue name must not be empty"))
+	}
+
+	store := access.store
+	store.mu.Lock()
+	defer store.mu.Unlock()
+
+	q, err := store.queue(namespace, req.Queue)
+	if err != nil {
+		return queueErrorResponse(err)
+	}
+	if req.Persistent && !q.persistent {
+		if store.dir == "" {
+			return queueErrorResponse(filesystem.NewInvalidArgumentError("persistent", true, "the server has no wasm queue_dir configured"))
+		}
+		if err := q.save(q.items); err != nil {
+			return queueErrorResponse(fmt.Errorf("failed to persist queue: %w", err))
+		}
+		q.persistent = true
+	}
+
+	switch req.Op {
+	case "push":
+		if len(q.items) >= maxQueueItems {
+			err = fmt.Errorf("queue %s holds %d items: %w", req.Queue, len(q.items), filesystem.ErrBusy)
+		} else {
+			err = q.update(append(q.items[:len(q.items):len(q.items)], req.Data))
+		}
+	case "pop":
+		if len(q.items) > 0 {
+			resp.Data, resp.Found = q.items[0], true
+			err = q.update(q.items[1:])
+		}
+	case "peek":
+		if len(q.items) > 0 {
+			resp.Data, resp.Found = q.items[0], true
+		}
+	case "len":
+	default:
+		err = filesystem.NewInvalidArgumentError("op", req.Op, "unknown queue operation")
+	}
+	if err != nil {
+		return queueErrorResponse(err)
+	}
+	resp.Len = len(q.items)
+	return resp
+}
+
+func queueErrorResponse(err error) hostQueueResponse {
+	return hostQueueResponse{Code: fsErrno(err), Message: err.Error()}
+}
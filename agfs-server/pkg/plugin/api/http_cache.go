@@ -0,0 +1,199 @@
+package api
+
+import (
+	"container/list"
+	"net/http"
+	"sort"
+	"strconv"
+	"strings"
+	"sync"
+	"time"
+)
+
+// Shared cache of HTTP response bodies for WASM plugins
+// Mounts of the same plugin, or of different ones, often fetch the same
+// URLs: five RSS mounts with overlapping feeds. GETs a plugin marks as
+// cacheable are answered from one host-wide cache keyed by URL and request
+// headers, and stale bodies are revalidated with If-None-Match and
+// If-Modified-Since instead of being downloaded again.
+
+const (
+	// maxHTTPCacheBytes bounds the bodies held by the shared cache; the least
+	// recently used are evicted first
+	maxHTTPCacheBytes = 64 << 20
+
+	// maxHTTPCacheEntryBytes bounds a single cached body
+	maxHTTPCacheEntryBytes = 8 << 20
+
+	// maxHTTPCacheHeuristic bounds how long a response without explicit
+	// freshness is served without revalidation
+	maxHTTPCacheHeuristic = 5 * time.Minute
+)
+
+// sharedHTTPCache is used by every plugin instance
+var sharedHTTPCache = newHTTPCache(maxHTTPCacheBytes)
+
+type httpCacheEntry struct {
+	key          string
+	resp         HTTPResponse
+	etag         string
+	lastModified string
+	freshUntil   time.Time
+}
+
+// httpCache is an LRU of successful GET responses, bounded by body size
+type httpCache struct {
+	mu       sync.Mutex
+	entries  map[string]*list.Element
+	lru      *list.List
+	bytes    int
+	maxBytes int
+}
+
+func newHTTPCache(maxBytes int) *httpCache {
+	return &httpCache{entries: make(map[string]*list.Element), lru: list.New(), maxBytes: maxBytes}
+}
+
+// httpCacheKey returns the cache key of req, or false if its response must
+// not be shared: anything but a body-less GET, requests carrying
+// credentials, and conditional requests whose 304 the plugin expects itself
+func httpCacheKey(req *HTTPRequest) (string, bool) {
+	if req.Method != http.MethodGet || len(req.Body) > 0 {
+		return "", false
+	}
+	if req.TLS != nil && req.TLS.ClientCert != "" {
+		return "", false
+	}
+	headers := make([]string, 0, len(req.Headers))
+	for name, value := range req.Headers {
+		name = strings.ToLower(name)
+		switch name {
+		case "authorization", "cookie", "if-none-match", "if-modified-since":
+			return "", false
+		}
+		headers = append(headers, name+": "+value)
+	}
+	sort.Strings(headers)
+	return req.URL + "\n" + strings.Join(headers, "\n"), true
+}
+
+// get returns a copy of the entry for key, marking it recently used
+func (c *httpCache) get(key string) (httpCacheEntry, bool) {
+	c.mu.Lock()
+	defer c.mu.Unlock()
+	elem, ok := c.entries[key]
+	if !ok {
+		return httpCacheEntry{}, false
+	}
+	c.lru.MoveToFront(elem)
+	return *elem.Value.(*httpCacheEntry), true
+}
+
+// store caches a 200 response unless its Cache-Control forbids a shared
+// cache to keep it, or its body is too large
+func (c *httpCache) store(key string, resp HTTPResponse, header http.Header, now time.Time) {
+	freshUntil, ok := httpFreshness(header, now)
+	if !ok || resp.StatusCode != http.StatusOK || len(resp.Body) > maxHTTPCacheEntryBytes {
+		c.remove(key)
+		return
+	}
+	entry := &httpCacheEntry{
+		key:          key,
+		resp:         resp,
+		etag:         header.Get("ETag"),
+		lastModified: header.Get("Last-Modified"),
+		freshUntil:   freshUntil,
+	}
+
+	c.mu.Lock()
+	defer c.mu.Unlock()
+	if elem, ok := c.entries[key]; ok {
+		c.bytes -= len(elem.Value.(*httpCacheEntry).resp.Body)
+		c.lru.Remove(elem)
+	}
+	c.entries[key] = c.lru.PushFront(entry)
+	c.bytes += len(resp.Body)
+	for c.bytes > c.maxBytes {
+		oldest := c.lru.Back()
+		evicted := c.lru.Remove(oldest).(*httpCacheEntry)
+		delete(c.entries, evicted.key)
+		c.bytes -= len(evicted.resp.Body)
+	}
+}
+
+// revalidated extends the freshness of the entry for key after a 304 Not
+// Modified, taking the validators the server sent along
+func (c *httpCache) revalidated(key string, header http.Header, now time.Time) {
+	freshUntil, ok := httpFreshness(header, now)
+	if !ok {
+		c.remove(key)
+		return
+	}
+	c.mu.Lock()
+	defer c.mu.Unlock()
+	elem, found := c.entries[key]
+	if !found {
+		return
+	}
+	entry := elem.Value.(*httpCacheEntry)
+	entry.freshUntil = freshUntil
+	if etag := header.Get("ETag"); etag != "" {
+		entry.etag = etag
+	}
+	if lastModified := header.Get("Last-Modified"); lastModified != "" {
+		entry.lastModified = lastModified
+	}
+}
+
+func (c *httpCache) remove(key string) {
+	c.mu.Lock()
+	defer c.mu.Unlock()
+	if elem, ok := c.entries[key]; ok {
+		c.bytes -= len(elem.Value.(*httpCacheEntry).resp.Body)
+		c.lru.Remove(elem)
+		delete(c.entries, key)
+	}
+}
+
+// httpFreshness returns until when a response with header may be served
+// without revalidation, or false if a shared cache must not store it.
+// max-age and Expires are honoured; without them a response is fresh for a
+// tenth of its age since Last-Modified, up to maxHTTPCacheHeuristic.
+func httpFreshness(header http.Header, now time.Time) (time.Time, bool) {
+	noCache := false
+	maxAge, sMaxAge := -1, -1
+	for _, directive := range strings.Split(strings.ToLower(header.Get("Cache-Control")), ",") {
+		name, value, _ := strings.Cut(strings.TrimSpace(directive), "=")
+		secs, err := strconv.Atoi(strings.Trim(value, `"`))
+		switch name {
+		case "no-store", "private":
+			return time.Time{}, false
+		case "no-cache":
+			noCache = true
+		case "s-maxage":
+			if err == nil {
+				sMaxAge = secs
+			}
+		case "max-age":
+			if err == nil {
+				maxAge = secs
+			}
+		}
+	}
+	switch {
+	case noCache:
+		return now, true
+	case sMaxAge >= 0:
+		// Meant for shared caches, so it wins over max-age
+		return now.Add(time.Duration(sMaxAge) * time.Second), true
+	case maxAge >= 0:
+		return now.Add(time.Duration(maxAge) * time.Second), true
+	}
+	if expires, err := http.ParseTime(header.Get("Expires")); err == nil {
+		return expires, true
+	}
+	if lastModified, err := http.ParseTime(header.Get("Last-Modified")); err == nil && lastModified.Before(now) {
+		return now.Add(min(now.Sub(lastModified)/10, maxHTTPCacheHeuristic)), true
+	}
+	return now, true
+}
@@ -1,8 +1,18 @@
 package api
 
 import (
+	"context"
+	"encoding/json"
+	"errors"
 	"fmt"
+	"regexp"
+	"strconv"
+	"strings"
+	"sync"
+	"time"
 	"unsafe"
+
+	"github.com/c4pt0r/agfs/agfs-server/pkg/filesystem"
 )
 
 // ExternalPlugin represents a dynamically loaded plugin from a shared library
@@ -18,25 +28,261 @@ type ExternalPlugin struct {
 // PluginVTable contains function pointers to the plugin's C-compatible API
 type PluginVTable struct {
 	// Plugin lifecycle functions
-	PluginNew        func() unsafe.Pointer
-	PluginFree       func(unsafe.Pointer)
-	PluginName       func(unsafe.Pointer) *byte
-	PluginValidate   func(unsafe.Pointer, *byte) *byte // Returns error string or nil
-	PluginInitialize func(unsafe.Pointer, *byte) *byte // Returns error string or nil
-	PluginShutdown   func(unsafe.Pointer) *byte        // Returns error string or nil
-	PluginGetReadme  func(unsafe.Pointer) *byte
+	PluginNew             func() unsafe.Pointer
+	PluginFree            func(unsafe.Pointer)
+	PluginName            func(unsafe.Pointer) *byte
+	PluginValidate        func(unsafe.Pointer, *byte) *byte // Returns error string or nil
+	PluginInitialize      func(unsafe.Pointer, *byte) *byte // Returns error string or nil
+	PluginShutdown        func(unsafe.Pointer) *byte        // Returns error string or nil
+	PluginGetReadme       func(unsafe.Pointer) *byte
+	PluginGetConfigParams func(unsafe.Pointer) *byte // Returns JSON array (release with PluginFreeString)
+
+	// ABI negotiation (optional; plugins without them predate versioning and
+	// have every optional symbol probed)
+	PluginABIVersion func() uint32
+	PluginFeatures   func() uint64
+
+	// ABIVersion is the plugin's reported ABI version (1 for plugins that
+	// predate PluginABIVersion); it selects the FileInfoC layout
+	ABIVersion uint32
+
+	// Host logging (optional): registers a func(level int32, msg *char)
+	// callback created with purego.NewCallback
+	PluginSetLogCallback func(uintptr)
 
 	// FileSystem operation functions
 	FSCreate    func(unsafe.Pointer, *byte) *byte
 	FSMkdir     func(unsafe.Pointer, *byte, uint32) *byte
 	FSRemove    func(unsafe.Pointer, *byte) *byte
 	FSRemoveAll func(unsafe.Pointer, *byte) *byte
-	FSRead      func(unsafe.Pointer, *byte, int64, int64, *int) *byte              // Returns data, sets size
-	FSWrite     func(unsafe.Pointer, *byte, *byte, int, int64, uint32) int64       // NEW: (plugin, path, data, len, offset, flags) -> bytes_written (-1 = error)
-	FSReadDir   func(unsafe.Pointer, *byte, *int) *FileInfoArray                   // Returns array, sets count
-	FSStat      func(unsafe.Pointer, *byte) *FileInfoC
+	FSRead      func(unsafe.Pointer, *byte, int64, int64, *int64, *FSErrorC) *byte       // (plugin, path, offset, size, len, err) -> data of *len bytes, or nil with *len -1 and err set
+	FSWrite     func(unsafe.Pointer, *byte, *byte, int32, int64, uint32, *FSErrorC) int64 // (plugin, path, data, len, offset, flags, err) -> bytes_written (-1 = error)
+	FSReadDir   func(unsafe.Pointer, *byte, *int32, *FSErrorC) *FileInfoArray            // Returns array, sets count
+	FSStat      func(unsafe.Pointer, *byte, *FSErrorC) *FileInfoC
 	FSRename    func(unsafe.Pointer, *byte, *byte) *byte
 	FSChmod     func(unsafe.Pointer, *byte, uint32) *byte
+
+	// Paged readdir (optional): (plugin, path, offset, limit, count, err);
+	// a page with fewer than limit entries is the last one
+	FSReadDirPage func(unsafe.Pointer, *byte, int64, int64, *int32, *FSErrorC) *FileInfoArray
+
+	// Filtered readdir (optional): (plugin, path, glob, limit, count, err);
+	// limit 0 returns every matching entry
+	FSReadDirFiltered func(unsafe.Pointer, *byte, *byte, int64, *int32, *FSErrorC) *FileInfoArray
+
+	// Symlink and xattr functions (optional). FSReadlink returns a string
+	// (release with PluginFreeString); FSGetXattr and FSListXattr return
+	// buffers like FSRead, the list being NUL-separated names.
+	FSSymlink   func(unsafe.Pointer, *byte, *byte) *byte                    // (plugin, target, link) -> error
+	FSReadlink  func(unsafe.Pointer, *byte, *FSErrorC) *byte                // (plugin, path, err) -> target
+	FSGetXattr  func(unsafe.Pointer, *byte, *byte, *int64, *FSErrorC) *byte // (plugin, path, name, size, err) -> value
+	FSSetXattr  func(unsafe.Pointer, *byte, *byte, *byte, int32) *byte      // (plugin, path, name, value, len) -> error
+	FSListXattr func(unsafe.Pointer, *byte, *int64, *FSErrorC) *byte        // (plugin, path, size, err) -> names
+
+	// Access pattern hint (optional): (plugin, path, offset, len, advice) -> error
+	FSAdvise func(unsafe.Pointer, *byte, int64, int64, int32) *byte
+
+	// Conditional read (optional): (plugin, path, etag, size, err) -> data;
+	// size is ReadNotModified when the content still matches etag
+	FSReadIfChanged func(unsafe.Pointer, *byte, *byte, *int64, *FSErrorC) *byte
+
+	// Conditional write (optional): (plugin, path, data, len, etag, err) -> bytes written
+	FSWriteIf func(unsafe.Pointer, *byte, *byte, int32, *byte, *FSErrorC) int64
+
+	// Batched read (optional): (plugin, paths, pathsLen, results, maxResults, err)
+	// -> count; paths are NUL-terminated back to back and one ReadResultC is
+	// written per path, or -1 is returned with err set
+	FSReadMany func(unsafe.Pointer, *byte, int64, *ReadResultC, int32, *FSErrorC) int32
+
+	// Batches (optional): FSBatch takes a JSON array of filesystem.FsOp and
+	// returns a JSON array with null or {"code", "message"} per op (release
+	// with PluginFreeString), or nil with err set; FSAtomicBatch returns 1 if
+	// a batch applies all ops or none
+	FSBatch       func(unsafe.Pointer, *byte, *FSErrorC) *byte
+	FSAtomicBatch func(unsafe.Pointer) int32
+
+	// Write sessions (optional): (plugin[, message], err) -> 0, or -1 with
+	// err set
+	FSBeginSession  func(unsafe.Pointer, *FSErrorC) int32
+	FSCommitSession func(unsafe.Pointer, *byte, *FSErrorC) int32
+	FSAbortSession  func(unsafe.Pointer, *FSErrorC) int32
+
+	// Periodic upkeep (optional): (plugin, err) -> 0, or -1 with err set
+	FSMaintain func(unsafe.Pointer, *FSErrorC) int32
+
+	// Warm-up (optional): (plugin, budget ms, done, total, err) -> 0, or -1
+	// with err set
+	FSWarmup func(unsafe.Pointer, int64, *uint64, *uint64, *FSErrorC) int32
+
+	// State transfer (optional): FSExportState returns a buffer (free with
+	// PluginFreeBuffer) and sets len, or sets len to -1 and err;
+	// FSImportState returns 0, or -1 with err set
+	FSExportState func(unsafe.Pointer, *int64, *FSErrorC) *byte
+	FSImportState func(unsafe.Pointer, *byte, int64, *FSErrorC) int32
+
+	// Deallocation functions for memory returned by the plugin (optional;
+	// older plugins leak instead)
+	PluginFreeString    func(*byte)
+	PluginFreeBuffer    func(*byte)
+	FSFreeFileInfo      func(*FileInfoC)
+	FSFreeFileInfoArray func(*FileInfoArray)
+
+	// Handle functions (optional; exported by plugins built with
+	// export_handle_plugin!). Each returns an error string or nil and writes
+	// its result through the trailing out-parameter.
+	HandleOpen    func(unsafe.Pointer, *byte, uint32, uint32, *int64) *byte
+	HandleRead    func(unsafe.Pointer, int64, *byte, int64, *int64) *byte
+	HandleReadAt  func(unsafe.Pointer, int64, *byte, int64, int64, *int64) *byte
+	HandleWrite   func(unsafe.Pointer, int64, *byte, int64, *int64) *byte
+	HandleWriteAt func(unsafe.Pointer, int64, *byte, int64, int64, *int64) *byte
+	HandleSeek    func(unsafe.Pointer, int64, int64, int32, *int64) *byte
+	HandleSync    func(unsafe.Pointer, int64) *byte
+	HandleStat    func(unsafe.Pointer, int64, **FileInfoC) *byte
+	HandleClose   func(unsafe.Pointer, int64) *byte
+
+	// Async functions (optional; exported with export_plugin!(T, async)).
+	// Submit returns an operation ID (-1 = error); FSPoll waits up to
+	// timeoutMs (-1 = forever) and returns PollPending, PollDone or
+	// PollUnknown. On PollDone, size is the read length or bytes written, or
+	// -1 with the error filled in. FSCancel abandons an operation, which must
+	// not be polled afterwards.
+	FSSubmitRead  func(unsafe.Pointer, *byte, int64, int64, *FSErrorC) int64                // (plugin, path, offset, size, err) -> op
+	FSSubmitWrite func(unsafe.Pointer, *byte, *byte, int32, int64, uint32, *FSErrorC) int64 // (plugin, path, data, len, offset, flags, err) -> op
+	FSPoll        func(unsafe.Pointer, int64, int64, **byte, *int64, *FSErrorC) int32       // (plugin, op, timeoutMs, data, size, err) -> state
+	FSCancel      func(unsafe.Pointer, int64) int32                                         // (plugin, op) -> 1 if cancelled, 0 if unknown
+}
+
+// takeFSError converts an FSErrorC report into a typed filesystem error and
+// releases its message
+func (vt *PluginVTable) takeFSError(op, path string, e *FSErrorC) error {
+	msg := GoString(e.Message)
+	if e.Message != nil && vt.PluginFreeString != nil {
+		vt.PluginFreeString(e.Message)
+	}
+	return fsError(op, path, e.Code, msg)
+}
+
+// fsError converts an errno-style code reported by a plugin into a typed
+// filesystem error
+func fsError(op, path string, code int32, msg string) error {
+	if msg == "" {
+		msg = op + " failed"
+	}
+
+	switch code {
+	case errnoENOENT:
+		return filesystem.NewNotFoundError(op, path)
+	case errnoEACCES:
+		return filesystem.NewPermissionDeniedError(op, path, "")
+	case errnoEROFS:
+		return filesystem.NewNotSupportedError(op, path)
+	case errnoEEXIST:
+		return filesystem.NewAlreadyExistsError("file", path)
+	case errnoENOTDIR:
+		return filesystem.NewNotDirectoryError(path)
+	case errnoEINVAL:
+		return filesystem.NewInvalidArgumentError("path", path, msg)
+	case errnoEAGAIN:
+		return filesystem.NewBackoffError(op, path, retryAfter(msg))
+	case errnoEBUSY:
+		return fmt.Errorf("%s: %s: %w", op, path, filesystem.ErrBusy)
+	case errnoESTALE:
+		return fmt.Errorf("%s: %s: %w", op, path, filesystem.ErrStale)
+	case errnoECANCELED:
+		return fmt.Errorf("%s: %s: %w", op, path, context.Canceled)
+	default:
+		return fmt.Errorf("%s: %s: %s", op, path, msg)
+	}
+}
+
+// The Rust SDK formats a plugin's Error::Busy { retry_after_ms } as
+// backoffMessagePrefix followed by the hint, e.g. "backend busy, retry after
+// 1500ms" (see Display in agfs-core's error.rs); keep the two in sync
+const (
+	retryAfterMarker     = "retry after "
+	backoffMessagePrefix = "backend busy, " + retryAfterMarker
+)
+
+// retryAfterPattern finds the hint in the message of a backoff error
+var retryAfterPattern = regexp.MustCompile(regexp.QuoteMeta(retryAfterMarker) + `(\d+)ms`)
+
+// isBackoffMessage reports whether a WASM plugin's error, which carries no
+// code, is an Error::Busy with a retry hint
+func isBackoffMessage(msg string) bool {
+	return strings.HasPrefix(msg, backoffMessagePrefix)
+}
+
+// retryAfter is the retry hint in a plugin's backoff error message, or zero
+func retryAfter(msg string) time.Duration {
+	m := retryAfterPattern.FindStringSubmatch(msg)
+	if m == nil {
+		return 0
+	}
+	ms, err := strconv.ParseInt(m[1], 10, 64)
+	if err != nil {
+		return 0
+	}
+	return time.Duration(ms) * time.Millisecond
+}
+
+// fsErrno is the inverse of fsError: the errno reported to a plugin for a
+// Go filesystem error
+func fsErrno(err error) int32 {
+	switch {
+	case errors.Is(err, filesystem.ErrNotFound):
+		return errnoENOENT
+	case errors.Is(err, filesystem.ErrPermissionDenied):
+		return errnoEACCES
+	case errors.Is(err, filesystem.ErrNotSupported):
+		return errnoEROFS
+	case errors.Is(err, filesystem.ErrAlreadyExists):
+		return errnoEEXIST
+	case errors.Is(err, filesystem.ErrNotDirectory):
+		return errnoENOTDIR
+	case errors.Is(err, filesystem.ErrInvalidArgument):
+		return errnoEINVAL
+	case errors.Is(err, filesystem.ErrTryAgain):
+		return errnoEAGAIN
+	case errors.Is(err, filesystem.ErrBusy):
+		return errnoEBUSY
+	case errors.Is(err, filesystem.ErrStale):
+		return errnoESTALE
+	case errors.Is(err, context.Canceled):
+		return errnoECANCELED
+	default:
+		return errnoEIO
+	}
+}
+
+// batchErrors decodes the per-op results of FSBatch or fs_batch: null for
+// success, otherwise {"code", "message"}
+func batchErrors(ops []filesystem.FsOp, resultsJSON string) ([]error, error) {
+	var results []*struct {
+		Code    int32  `json:"code"`
+		Message string `json:"message"`
+	}
+	if err := json.Unmarshal([]byte(resultsJSON), &results); err != nil {
+		return nil, fmt.Errorf("batch: invalid result: %w", err)
+	}
+	if len(results) != len(ops) {
+		return nil, fmt.Errorf("batch: %d results for %d ops", len(results), len(ops))
+	}
+	errs := make([]error, len(ops))
+	for i, result := range results {
+		if result != nil {
+			errs[i] = fsError(ops[i].Op, ops[i].Target(), result.Code, result.Message)
+		}
+	}
+	return errs, nil
+}
+
+// takeError converts a plugin error string to a Go error and releases it
+func (vt *PluginVTable) takeError(errStr *byte) error {
+	err := GoError(errStr)
+	if errStr != nil && vt.PluginFreeString != nil {
+		vt.PluginFreeString(errStr)
+	}
+	return err
 }
 
 // FileInfoC is the C-compatible representation of filesystem.FileInfo
@@ -50,18 +296,95 @@ type FileInfoC struct {
 	MetaName    *byte
 	MetaType    *byte
 	MetaContent *byte // JSON-encoded map[string]string
+	// Content version, nil if unknown (ABI version 2+)
+	ETag *byte
+}
+
+// FSErrorC is the error out-parameter filled in by FSRead, FSStat, FSReadDir
+// and FSWrite. Code is 0 on success, otherwise a Linux errno value.
+type FSErrorC struct {
+	Code    int32
+	Message *byte // Error description (release with PluginFreeString)
 }
 
+// ReadResultC is one file's result written by FSReadMany: Data holds Len
+// bytes (release with PluginFreeBuffer), or is nil with Len -1 and Err set
+type ReadResultC struct {
+	Data *byte
+	Len  int64
+	Err  FSErrorC
+}
+
+// Error codes reported in FSErrorC.Code
+const (
+	errnoEIO       = 5
+	errnoENOENT    = 2
+	errnoEAGAIN    = 11
+	errnoEACCES    = 13
+	errnoEBUSY     = 16
+	errnoEEXIST    = 17
+	errnoENOTDIR   = 20
+	errnoEISDIR    = 21
+	errnoEINVAL    = 22
+	errnoEROFS     = 30
+	errnoENOTEMPTY = 39
+	errnoESTALE    = 116
+	errnoECANCELED = 125
+)
+
+// NativeABIVersion is the newest native plugin C ABI this host understands
+//
+// Version 2 appended ETag to FileInfoC; version 1 plugins are still read with
+// the shorter layout. Plugins that predate PluginABIVersion altogether, whose
+// FSRead took an int length and no FSErrorC, must be rebuilt.
+const NativeABIVersion = 2
+
+// Feature bits reported by a plugin's PluginFeatures export
+const (
+	FeatureFree          uint64 = 1 << 0  // PluginFreeString, PluginFreeBuffer, FSFreeFileInfo*
+	FeatureConfigParams  uint64 = 1 << 1  // PluginGetConfigParams
+	FeatureHandles       uint64 = 1 << 2  // Handle*
+	FeatureLog           uint64 = 1 << 3  // PluginSetLogCallback
+	FeatureAsync         uint64 = 1 << 4  // FSSubmitRead, FSSubmitWrite, FSPoll
+	FeatureReadDirPage   uint64 = 1 << 5  // FSReadDirPage
+	FeatureXattr         uint64 = 1 << 6  // FSGetXattr, FSSetXattr, FSListXattr
+	FeatureSymlink       uint64 = 1 << 7  // FSSymlink, FSReadlink
+	FeatureAdvise        uint64 = 1 << 8  // FSAdvise
+	FeatureReadIfChanged uint64 = 1 << 9  // FSReadIfChanged
+	FeatureCancel        uint64 = 1 << 10 // FSCancel
+	FeatureWriteIf       uint64 = 1 << 11 // FSWriteIf
+	FeatureReadDirFilter uint64 = 1 << 12 // FSReadDirFiltered
+	FeatureReadMany      uint64 = 1 << 13 // FSReadMany
+	FeatureBatch         uint64 = 1 << 14 // FSBatch, FSAtomicBatch
+	FeatureSessions      uint64 = 1 << 15 // FSBeginSession, FSCommitSession, FSAbortSession
+	FeatureMaintain      uint64 = 1 << 16 // FSMaintain
+	FeatureWarmup        uint64 = 1 << 17 // FSWarmup
+	FeatureState         uint64 = 1 << 18 // FSExportState, FSImportState
+)
+
+// Operation states returned by FSPoll
+const (
+	PollUnknown int32 = -1
+	PollPending int32 = 0
+	PollDone    int32 = 1
+)
+
+// ReadNotModified is the FSReadIfChanged size reported for unchanged content
+const ReadNotModified int64 = -2
+
 // FileInfoArray is used for returning multiple FileInfo from C
 type FileInfoArray struct {
 	Items *FileInfoC
-	Count int
+	Count int32
 }
 
 // ExternalFileSystem implements filesystem.FileSystem by delegating to C functions
 type ExternalFileSystem struct {
 	pluginPtr unsafe.Pointer
 	vtable    *PluginVTable
+
+	handlesMu sync.Mutex
+	handles   map[int64]*ExternalFileHandle
 }
 
 // Helper functions to convert between Go and C types
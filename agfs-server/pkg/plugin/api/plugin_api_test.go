@@ -0,0 +1,43 @@
+package api
+
+import (
+	"errors"
+	"testing"
+	"time"
+
+	"github.com/c4pt0r/agfs/agfs-server/pkg/filesystem"
+)
+
+// rustBackoffMessage is what the Rust SDK's Error::Busy { retry_after_ms:
+// Some(1500) } displays as (asserted in agfs-core's error.rs tests)
+const rustBackoffMessage = "backend busy, retry after 1500ms"
+
+func TestBackoffMessageFromRustSDK(t *testing.T) {
+	if !isBackoffMessage(rustBackoffMessage) {
+		t.Fatalf("%q not recognized as a backoff message", rustBackoffMessage)
+	}
+	if got := retryAfter(rustBackoffMessage); got != 1500*time.Millisecond {
+		t.Errorf("retryAfter(%q) = %v, want 1.5s", rustBackoffMessage, got)
+	}
+
+	for _, msg := range []string{"resource busy", "file not found", "retry after 1500ms"} {
+		if isBackoffMessage(msg) {
+			t.Errorf("%q recognized as a backoff message", msg)
+		}
+	}
+}
+
+func TestFSErrorCarriesRetryHint(t *testing.T) {
+	err := fsError("read", "/stories", errnoEAGAIN, rustBackoffMessage)
+	var backoff *filesystem.BackoffError
+	if !errors.As(err, &backoff) {
+		t.Fatalf("fsError(EAGAIN) = %v, want a BackoffError", err)
+	}
+	if backoff.RetryAfter != 1500*time.Millisecond {
+		t.Errorf("RetryAfter = %v, want 1.5s", backoff.RetryAfter)
+	}
+
+	if got := retryAfter("rate limited"); got != 0 {
+		t.Errorf("retryAfter without a hint = %v, want 0", got)
+	}
+}
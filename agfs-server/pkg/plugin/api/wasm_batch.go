@@ -0,0 +1,82 @@
+package api
+
+import (
+	"encoding/json"
+	"fmt"
+
+	"github.com/c4pt0r/agfs/agfs-server/pkg/filesystem"
+)
+
+var (
+	_ filesystem.Batcher = (*WASMFileSystem)(nil)
+	_ filesystem.Batcher = (*PooledWASMFileSystem)(nil)
+)
+
+// Batch implements filesystem.Batcher via the plugin's fs_batch export,
+// falling back to filesystem.ApplyOps for plugins without it
+func (wfs *WASMFileSystem) Batch(ops []filesystem.FsOp) ([]error, error) {
+	batchFunc := wfs.module.ExportedFunction("fs_batch")
+	if batchFunc == nil {
+		return filesystem.ApplyOps(wfs, ops), nil
+	}
+
+	opsJSON, err := json.Marshal(ops)
+	if err != nil {
+		return nil, fmt.Errorf("batch: %w", err)
+	}
+	var resultsJSON string
+	err = wfs.withString(string(opsJSON), func(ptr uint32) error {
+		results, err := batchFunc.Call(wfs.ctx, uint64(ptr))
+		if err != nil {
+			return fmt.Errorf("fs_batch failed: %w", err)
+		}
+		if len(results) < 1 {
+			return fmt.Errorf("fs_batch returned invalid results")
+		}
+
+		// Unpack u64: low 32 bits = json ptr, high 32 bits = error ptr
+		jsonPtr := uint32(results[0] & 0xFFFFFFFF)
+		if errPtr := uint32(results[0] >> 32); errPtr != 0 || jsonPtr == 0 {
+			return wfs.takeError(errPtr, "batch failed")
+		}
+		var ok bool
+		resultsJSON, ok = readStringFromMemory(wfs.module, jsonPtr)
+		freeWASMMemory(wfs.module, jsonPtr, 0)
+		if !ok {
+			return fmt.Errorf("failed to read batch result")
+		}
+		return nil
+	})
+	if err != nil {
+		return nil, err
+	}
+	return batchErrors(ops, resultsJSON)
+}
+
+// AtomicBatch implements filesystem.Batcher
+func (wfs *WASMFileSystem) AtomicBatch() bool {
+	atomicFunc := wfs.module.ExportedFunction("fs_atomic_batch")
+	if atomicFunc == nil {
+		return false
+	}
+	results, err := atomicFunc.Call(wfs.ctx)
+	return err == nil && len(results) > 0 && results[0] == 1
+}
+
+// Batch implements filesystem.Batcher
+func (pfs *PooledWASMFileSystem) Batch(ops []filesystem.FsOp) (errs []error, err error) {
+	err = pfs.pool.Execute(func(instance *WASMModuleInstance) error {
+		errs, err = instance.fileSystem.Batch(ops)
+		return err
+	})
+	return errs, err
+}
+
+// AtomicBatch implements filesystem.Batcher
+func (pfs *PooledWASMFileSystem) AtomicBatch() (atomic bool) {
+	_ = pfs.pool.Execute(func(instance *WASMModuleInstance) error {
+		atomic = instance.fileSystem.AtomicBatch()
+		return nil
+	})
+	return atomic
+}
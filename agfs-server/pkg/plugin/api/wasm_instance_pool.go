@@ -19,6 +19,10 @@ type PoolConfig struct {
 	InstanceMaxRequests int64         // Maximum requests per instance (0 = unlimited)
 	HealthCheckInterval time.Duration // Health check interval (0 = disabled)
 	AcquireTimeout      time.Duration // Timeout for acquiring instance (0 = unlimited, default 30s)
+	OperationTimeout    time.Duration // Deadline for each plugin operation, including its host calls (0 = none)
+	QueueDir            string        // Directory persisting host queues (empty = in memory only)
+	Embedder            Embedder      // Serves host_embed (nil = plugins cannot embed texts)
+	Completer           Completer     // Serves host_ai_complete (nil = plugins cannot generate text)
 	EnableStatistics    bool          // Enable statistics collection
 }
 
@@ -451,6 +455,7 @@ func (p *WASMInstancePool) Execute(fn func(*WASMModuleInstance) error) error {
 		return err
 	}
 	defer p.Release(instance)
+	defer p.beginOperation(instance)()
 
 	return fn(instance)
 }
@@ -462,6 +467,53 @@ func (p *WASMInstancePool) ExecuteFS(fn func(filesystem.FileSystem) error) error
 		return err
 	}
 	defer p.Release(instance)
+	defer p.beginOperation(instance)()
 
 	return fn(instance.fileSystem)
 }
+
+// beginOperation bounds the plugin calls made on instance, and the host calls
+// they make, by OperationTimeout. Instances are used by one caller at a time,
+// so the deadline rides on the instance's context until the returned function
+// ends the operation.
+func (p *WASMInstancePool) beginOperation(instance *WASMModuleInstance) func() {
+	if p.config.OperationTimeout <= 0 {
+		return func() {}
+	}
+	ctx, cancel := context.WithTimeout(p.ctx, p.config.OperationTimeout)
+	instance.fileSystem.ctx = ctx
+	return func() {
+		cancel()
+		instance.fileSystem.ctx = p.ctx
+	}
+}
+
+// HostDeadline implements host_deadline_ms: the milliseconds left before the
+// current operation's deadline, 0 once it has passed, or -1 without one
+func HostDeadline(ctx context.Context) int64 {
+	deadline, ok := ctx.Deadline()
+	if !ok {
+		return -1
+	}
+	return max(time.Until(deadline).Milliseconds(), 0)
+}
+
+// HostSleep implements host_sleep_ms, which plugins use to back off between
+// retries: it waits ms milliseconds and returns 0, or -1 if the operation
+// ends first
+func HostSleep(ctx context.Context, ms int64) int32 {
+	timer := time.NewTimer(time.Duration(ms) * time.Millisecond)
+	defer timer.Stop()
+	select {
+	case <-timer.C:
+		return 0
+	case <-ctx.Done():
+		return -1
+	}
+}
+
+// HostNow implements host_now_ms: milliseconds since the Unix epoch, for
+// plugins that time things across operations (circuit breakers, caches)
+func HostNow(ctx context.Context) int64 {
+	return time.Now().UnixMilli()
+}
@@ -0,0 +1,26 @@
+package api
+
+import (
+	"github.com/c4pt0r/agfs/agfs-server/pkg/filesystem"
+)
+
+var (
+	_ filesystem.Maintainer = (*WASMFileSystem)(nil)
+	_ filesystem.Maintainer = (*PooledWASMFileSystem)(nil)
+)
+
+// Maintain implements filesystem.Maintainer via fs_maintain; plugins built
+// without it have nothing to do
+func (wfs *WASMFileSystem) Maintain() error {
+	if wfs.module.ExportedFunction("fs_maintain") == nil {
+		return nil
+	}
+	return wfs.callErrorExport("fs_maintain", "maintain failed")
+}
+
+// Maintain implements filesystem.Maintainer on one pooled instance
+func (pfs *PooledWASMFileSystem) Maintain() error {
+	return pfs.pool.Execute(func(instance *WASMModuleInstance) error {
+		return instance.fileSystem.Maintain()
+	})
+}
@@ -19,6 +19,7 @@ type WASMPlugin struct {
 	name         string
 	instancePool *WASMInstancePool
 	fileSystem   *PooledWASMFileSystem
+	mounts       *MountAccess // Grants for host_mount_call, set on Initialize
 }
 
 // PooledWASMFileSystem implements filesystem.FileSystem using an instance pool
@@ -116,12 +117,24 @@ func (wp *WASMPlugin) Validate(config map[string]interface{}) error {
 	})
 }
 
+// SetMountAccess makes Initialize set mounts from the "mounts" allowlist of
+// the config and the capabilities the plugin declares once configured
+func (wp *WASMPlugin) SetMountAccess(mounts *MountAccess) {
+	wp.mounts = mounts
+}
+
 // Initialize initializes the plugin with configuration
 func (wp *WASMPlugin) Initialize(config map[string]interface{}) error {
+	if wp.mounts != nil {
+		if err := wp.mounts.SetAllowed(config["mounts"]); err != nil {
+			return fmt.Errorf("invalid mounts config: %w", err)
+		}
+	}
 	return wp.instancePool.Execute(func(instance *WASMModuleInstance) error {
 		initFunc := instance.module.ExportedFunction("plugin_initialize")
 		if initFunc == nil {
 			// If initialize function is not exported, assume initialization succeeds
+			wp.refreshMounts(instance)
 			return nil
 		}
 
@@ -155,10 +168,22 @@ func (wp *WASMPlugin) Initialize(config map[string]interface{}) error {
 			return fmt.Errorf("initialization failed")
 		}
 
+		wp.refreshMounts(instance)
 		return nil
 	})
 }
 
+// refreshMounts reads the mounts the configured plugin declares; refused
+// grants are logged, not fatal, so the rest of the plugin still works
+func (wp *WASMPlugin) refreshMounts(instance *WASMModuleInstance) {
+	if wp.mounts == nil {
+		return
+	}
+	if err := wp.mounts.Refresh(wp.instancePool.ctx, instance.module); err != nil {
+		log.Warnf("WASM plugin %s: %v", wp.name, err)
+	}
+}
+
 // GetFileSystem returns the file system implementation
 func (wp *WASMPlugin) GetFileSystem() filesystem.FileSystem {
 	return wp.fileSystem
@@ -362,7 +387,9 @@ func (pfs *PooledWASMFileSystem) OpenHandle(path string, flags filesystem.OpenFl
 	}
 
 	// Call OpenHandle on the WASM instance
+	endOperation := pfs.pool.beginOperation(instance)
 	handle, err := instance.fileSystem.OpenHandle(path, flags, mode)
+	endOperation()
 	if err != nil {
 		// Release the instance back to pool on error
 		pfs.pool.Release(instance)
@@ -451,6 +478,7 @@ func (h *PooledWASMFileHandle) Read(buf []byte) (int, error) {
 	if h.closed {
 		return 0, fmt.Errorf("handle is closed")
 	}
+	defer h.pfs.pool.beginOperation(h.instance)()
 	return h.inner.Read(buf)
 }
 
@@ -460,6 +488,7 @@ func (h *PooledWASMFileHandle) ReadAt(buf []byte, offset int64) (int, error) {
 	if h.closed {
 		return 0, fmt.Errorf("handle is closed")
 	}
+	defer h.pfs.pool.beginOperation(h.instance)()
 	return h.inner.ReadAt(buf, offset)
 }
 
@@ -469,6 +498,7 @@ func (h *PooledWASMFileHandle) Write(data []byte) (int, error) {
 	if h.closed {
 		return 0, fmt.Errorf("handle is closed")
 	}
+	defer h.pfs.pool.beginOperation(h.instance)()
 	return h.inner.Write(data)
 }
 
@@ -478,6 +508,7 @@ func (h *PooledWASMFileHandle) WriteAt(data []byte, offset int64) (int, error) {
 	if h.closed {
 		return 0, fmt.Errorf("handle is closed")
 	}
+	defer h.pfs.pool.beginOperation(h.instance)()
 	return h.inner.WriteAt(data, offset)
 }
 
@@ -487,6 +518,7 @@ func (h *PooledWASMFileHandle) Seek(offset int64, whence int) (int64, error) {
 	if h.closed {
 		return 0, fmt.Errorf("handle is closed")
 	}
+	defer h.pfs.pool.beginOperation(h.instance)()
 	return h.inner.Seek(offset, whence)
 }
 
@@ -496,6 +528,7 @@ func (h *PooledWASMFileHandle) Sync() error {
 	if h.closed {
 		return fmt.Errorf("handle is closed")
 	}
+	defer h.pfs.pool.beginOperation(h.instance)()
 	return h.inner.Sync()
 }
 
@@ -505,6 +538,7 @@ func (h *PooledWASMFileHandle) Stat() (*filesystem.FileInfo, error) {
 	if h.closed {
 		return nil, fmt.Errorf("handle is closed")
 	}
+	defer h.pfs.pool.beginOperation(h.instance)()
 	return h.inner.Stat()
 }
 
@@ -517,7 +551,9 @@ func (h *PooledWASMFileHandle) Close() error {
 	h.closed = true
 
 	// Close the inner handle
+	endOperation := h.pfs.pool.beginOperation(h.instance)
 	err := h.inner.Close()
+	endOperation()
 
 	// Remove from tracking (if not already removed by CloseHandle)
 	h.pfs.handleMu.Lock()
@@ -661,6 +697,12 @@ func (wfs *WASMFileSystem) Read(path string, offset int64, size int64) ([]byte,
 		defer wfs.mu.Unlock()
 	}
 
+	if wfs.sharedBuffer != nil && wfs.sharedBuffer.Enabled {
+		if chunkedFunc := wfs.module.ExportedFunction("fs_read_chunked"); chunkedFunc != nil {
+			return wfs.readChunked(chunkedFunc, path, offset, size)
+		}
+	}
+
 	readFunc := wfs.module.ExportedFunction("fs_read")
 	if readFunc == nil {
 		return nil, fmt.Errorf("fs_read not implemented")
@@ -702,6 +744,78 @@ func (wfs *WASMFileSystem) Read(path string, offset int64, size int64) ([]byte,
 	return data, nil
 }
 
+// readChunked reads through the plugin's output buffer. Results larger than
+// the buffer come back with a continuation token and are drained with
+// read_chunk, so the plugin never copies the whole result into linear memory.
+// extra arguments are passed after size, for exports such as fs_poll_read.
+func (wfs *WASMFileSystem) readChunked(chunkedFunc wazeroapi.Function, path string, offset int64, size int64, extra ...uint64) ([]byte, error) {
+	pathPtr, pathPtrSize, err := writeStringToMemoryWithBuffer(wfs.module, path, wfs.sharedBuffer)
+	if err != nil {
+		return nil, err
+	}
+	defer freeWASMMemoryWithBuffer(wfs.module, pathPtr, pathPtrSize, wfs.sharedBuffer)
+
+	name := chunkedFunc.Definition().Name()
+	params := append([]uint64{uint64(pathPtr), uint64(offset), uint64(size)}, extra...)
+	results, err := chunkedFunc.Call(wfs.ctx, params...)
+	if err != nil {
+		return nil, fmt.Errorf("%s failed: %w", name, err)
+	}
+	if len(results) < 1 {
+		return nil, fmt.Errorf("%s returned invalid results", name)
+	}
+
+	// Unpack u64: lower 32 bits = bytes in output buffer, upper 32 bits = token or error ptr
+	n := uint32(results[0] & 0xFFFFFFFF)
+	token := uint32(results[0] >> 32)
+	if n == 0 && token != 0 {
+		errPtr := token
+		if errMsg, ok := readStringFromMemory(wfs.module, errPtr); ok {
+			freeWASMMemory(wfs.module, errPtr, 0)
+			return nil, fmt.Errorf("%s", errMsg)
+		}
+		freeWASMMemory(wfs.module, errPtr, 0)
+		return nil, fmt.Errorf("read failed")
+	}
+
+	var data []byte
+	for {
+		chunk, ok := wfs.module.Memory().Read(wfs.sharedBuffer.OutputBufferPtr, n)
+		if !ok {
+			if token != 0 {
+				if cancelFunc := wfs.module.ExportedFunction("cancel_chunks"); cancelFunc != nil {
+					cancelFunc.Call(wfs.ctx, uint64(token))
+				}
+			}
+			return nil, fmt.Errorf("failed to read data from shared buffer")
+		}
+		// Memory().Read returns a view; the next chunk overwrites it
+		data = append(data, chunk...)
+		if token == 0 {
+			break
+		}
+
+		chunkFunc := wfs.module.ExportedFunction("read_chunk")
+		if chunkFunc == nil {
+			return nil, fmt.Errorf("read_chunk not implemented")
+		}
+		results, err := chunkFunc.Call(wfs.ctx, uint64(token))
+		if err != nil {
+			return nil, fmt.Errorf("read_chunk failed: %w", err)
+		}
+		if len(results) < 1 || results[0] == 0 {
+			return nil, fmt.Errorf("read_chunk returned invalid results")
+		}
+		n = uint32(results[0] & 0xFFFFFFFF)
+		token = uint32(results[0] >> 32)
+	}
+
+	if data == nil {
+		data = []byte{}
+	}
+	return data, nil
+}
+
 func (wfs *WASMFileSystem) Write(path string, data []byte, offset int64, flags filesystem.WriteFlag) (int64, error) {
 	writeFunc := wfs.module.ExportedFunction("fs_write")
 	if writeFunc == nil {
@@ -0,0 +1,38 @@
+package api
+
+import (
+	"context"
+	"time"
+
+	"github.com/c4pt0r/agfs/agfs-server/pkg/filesystem"
+)
+
+var (
+	_ filesystem.PollReader = (*WASMFileSystem)(nil)
+	_ filesystem.PollReader = (*PooledWASMFileSystem)(nil)
+)
+
+// PollRead implements filesystem.PollReader via the plugin's fs_poll_read
+// export; plugins built without it are read once without waiting. The
+// instance is held for as long as the plugin waits.
+func (wfs *WASMFileSystem) PollRead(ctx context.Context, path string, offset int64, size int64, timeout time.Duration) ([]byte, error) {
+	pollFunc := wfs.module.ExportedFunction("fs_poll_read")
+	if pollFunc == nil || wfs.sharedBuffer == nil || !wfs.sharedBuffer.Enabled {
+		return wfs.Read(path, offset, size)
+	}
+
+	if wfs.mu != nil {
+		wfs.mu.Lock()
+		defer wfs.mu.Unlock()
+	}
+	return wfs.readChunked(pollFunc, path, offset, size, uint64(timeout.Milliseconds()))
+}
+
+// PollRead implements filesystem.PollReader on one pooled instance
+func (pfs *PooledWASMFileSystem) PollRead(ctx context.Context, path string, offset int64, size int64, timeout time.Duration) (data []byte, err error) {
+	err = pfs.pool.Execute(func(instance *WASMModuleInstance) error {
+		data, err = instance.fileSystem.PollRead(ctx, path, offset, size, timeout)
+		return err
+	})
+	return data, err
+}
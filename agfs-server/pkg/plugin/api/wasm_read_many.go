@@ -0,0 +1,99 @@
+package api
+
+import (
+	"encoding/binary"
+	"encoding/json"
+	"fmt"
+
+	"github.com/c4pt0r/agfs/agfs-server/pkg/filesystem"
+)
+
+var (
+	_ filesystem.BatchReader = (*WASMFileSystem)(nil)
+	_ filesystem.BatchReader = (*PooledWASMFileSystem)(nil)
+)
+
+// ReadMany implements filesystem.BatchReader via the plugin's fs_read_many
+// export, falling back to one Read per path for plugins without it
+func (wfs *WASMFileSystem) ReadMany(paths []string) []filesystem.ReadResult {
+	results := make([]filesystem.ReadResult, len(paths))
+	readManyFunc := wfs.module.ExportedFunction("fs_read_many")
+	if readManyFunc == nil || len(paths) == 0 {
+		for i, path := range paths {
+			results[i].Data, results[i].Err = wfs.Read(path, 0, -1)
+		}
+		return results
+	}
+
+	fail := func(err error) []filesystem.ReadResult {
+		for i := range results {
+			results[i].Err = err
+		}
+		return results
+	}
+
+	pathsJSON, err := json.Marshal(paths)
+	if err != nil {
+		return fail(err)
+	}
+	pathsPtr, pathsPtrSize, err := writeStringToMemory(wfs.module, string(pathsJSON))
+	if err != nil {
+		return fail(err)
+	}
+	defer freeWASMMemory(wfs.module, pathsPtr, pathsPtrSize)
+
+	callResults, err := readManyFunc.Call(wfs.ctx, uint64(pathsPtr))
+	if err != nil {
+		return fail(fmt.Errorf("fs_read_many failed: %w", err))
+	}
+	if len(callResults) < 1 || callResults[0] == 0 {
+		return fail(fmt.Errorf("fs_read_many returned invalid results"))
+	}
+
+	// Unpack u64: lower 32 bits = buffer pointer, upper 32 bits = buffer length
+	bufPtr := uint32(callResults[0] & 0xFFFFFFFF)
+	bufLen := uint32(callResults[0] >> 32)
+	buf, ok := wfs.module.Memory().Read(bufPtr, bufLen)
+	if !ok {
+		freeWASMMemory(wfs.module, bufPtr, bufLen)
+		return fail(fmt.Errorf("failed to read fs_read_many result"))
+	}
+	// Copy out of linear memory before freeing it
+	buf = append([]byte(nil), buf...)
+	freeWASMMemory(wfs.module, bufPtr, bufLen)
+
+	// Each result: tag byte (0 = data, 1 = error message), u32 LE length, bytes
+	for i := range results {
+		if len(buf) < 5 {
+			results[i].Err = fmt.Errorf("read: %s: no result from plugin", paths[i])
+			continue
+		}
+		tag, size := buf[0], binary.LittleEndian.Uint32(buf[1:5])
+		if uint64(len(buf)-5) < uint64(size) {
+			return fail(fmt.Errorf("fs_read_many returned a truncated result"))
+		}
+		body := buf[5 : 5+size]
+		buf = buf[5+size:]
+		if tag == 0 {
+			results[i].Data = body
+		} else {
+			results[i].Err = fmt.Errorf("%s", body)
+		}
+	}
+	return results
+}
+
+// ReadMany implements filesystem.BatchReader
+func (pfs *PooledWASMFileSystem) ReadMany(paths []string) (results []filesystem.ReadResult) {
+	err := pfs.pool.Execute(func(instance *WASMModuleInstance) error {
+		results = instance.fileSystem.ReadMany(paths)
+		return nil
+	})
+	if err != nil {
+		results = make([]filesystem.ReadResult, len(paths))
+		for i := range results {
+			results[i].Err = err
+		}
+	}
+	return results
+}
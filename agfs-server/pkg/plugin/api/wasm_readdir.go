@@ -0,0 +1,64 @@
+package api
+
+import (
+	"encoding/json"
+	"fmt"
+
+	"github.com/c4pt0r/agfs/agfs-server/pkg/filesystem"
+)
+
+var (
+	_ filesystem.ChangedDirReader = (*WASMFileSystem)(nil)
+	_ filesystem.ChangedDirReader = (*PooledWASMFileSystem)(nil)
+)
+
+// ReadDirIfChanged implements filesystem.ChangedDirReader via the plugin's
+// fs_readdir_if_changed export
+func (wfs *WASMFileSystem) ReadDirIfChanged(path string, generation uint64) (*filesystem.Listing, error) {
+	readdirFunc := wfs.module.ExportedFunction("fs_readdir_if_changed")
+	if readdirFunc == nil {
+		return nil, fmt.Errorf("fs_readdir_if_changed not implemented")
+	}
+
+	pathPtr, pathPtrSize, err := writeStringToMemory(wfs.module, path)
+	if err != nil {
+		return nil, err
+	}
+	defer freeWASMMemory(wfs.module, pathPtr, pathPtrSize)
+
+	results, err := readdirFunc.Call(wfs.ctx, uint64(pathPtr), generation)
+	if err != nil {
+		return nil, fmt.Errorf("fs_readdir_if_changed failed: %w", err)
+	}
+	if len(results) < 1 {
+		return nil, fmt.Errorf("fs_readdir_if_changed returned invalid results")
+	}
+
+	// Unpack u64: lower 32 bits = json pointer, upper 32 bits = error pointer
+	jsonPtr := uint32(results[0] & 0xFFFFFFFF)
+	if errPtr := uint32(results[0] >> 32); errPtr != 0 || jsonPtr == 0 {
+		return nil, wfs.takeError(errPtr, "readdir failed")
+	}
+
+	jsonStr, ok := readStringFromMemory(wfs.module, jsonPtr)
+	freeWASMMemory(wfs.module, jsonPtr, 0)
+	if !ok {
+		return nil, fmt.Errorf("failed to read readdir result")
+	}
+
+	// null when the directory is unchanged
+	var listing *filesystem.Listing
+	if err := json.Unmarshal([]byte(jsonStr), &listing); err != nil {
+		return nil, fmt.Errorf("failed to unmarshal readdir result: %w", err)
+	}
+	return listing, nil
+}
+
+// ReadDirIfChanged implements filesystem.ChangedDirReader
+func (pfs *PooledWASMFileSystem) ReadDirIfChanged(path string, generation uint64) (listing *filesystem.Listing, err error) {
+	err = pfs.pool.Execute(func(instance *WASMModuleInstance) error {
+		listing, err = instance.fileSystem.ReadDirIfChanged(path, generation)
+		return err
+	})
+	return listing, err
+}
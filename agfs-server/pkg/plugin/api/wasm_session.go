@@ -0,0 +1,96 @@
+package api
+
+import (
+	"fmt"
+
+	"github.com/c4pt0r/agfs/agfs-server/pkg/filesystem"
+)
+
+var (
+	_ filesystem.WriteSessioner = (*WASMFileSystem)(nil)
+	_ filesystem.WriteSessioner = (*PooledWASMFileSystem)(nil)
+)
+
+// BeginWriteSession implements filesystem.WriteSessioner via fs_begin_session
+func (wfs *WASMFileSystem) BeginWriteSession() error {
+	return wfs.callErrorExport("fs_begin_session", "begin session failed")
+}
+
+// CommitSession implements filesystem.WriteSessioner via fs_commit_session
+func (wfs *WASMFileSystem) CommitSession(message string) error {
+	commitFunc := wfs.module.ExportedFunction("fs_commit_session")
+	if commitFunc == nil {
+		return filesystem.NewNotSupportedError("commit_session", "")
+	}
+	return wfs.withString(message, func(ptr uint32) error {
+		results, err := commitFunc.Call(wfs.ctx, uint64(ptr))
+		if err != nil {
+			return fmt.Errorf("fs_commit_session failed: %w", err)
+		}
+		if len(results) > 0 && results[0] != 0 {
+			return wfs.takeError(uint32(results[0]), "commit session failed")
+		}
+		return nil
+	})
+}
+
+// AbortSession implements filesystem.WriteSessioner via fs_abort_session
+func (wfs *WASMFileSystem) AbortSession() error {
+	return wfs.callErrorExport("fs_abort_session", "abort session failed")
+}
+
+// callErrorExport calls an argument-less export returning an error ptr
+func (wfs *WASMFileSystem) callErrorExport(name, fallback string) error {
+	fn := wfs.module.ExportedFunction(name)
+	if fn == nil {
+		return filesystem.NewNotSupportedError(name, "")
+	}
+	results, err := fn.Call(wfs.ctx)
+	if err != nil {
+		return fmt.Errorf("%s failed: %w", name, err)
+	}
+	if len(results) > 0 && results[0] != 0 {
+		return wfs.takeError(uint32(results[0]), fallback)
+	}
+	return nil
+}
+
+// A session is staged inside one WASM instance, so the pool must not hand
+// later writes to a different one
+func (pfs *PooledWASMFileSystem) checkSingleInstance() error {
+	if pfs.pool.config.MaxInstances != 1 {
+		return fmt.Errorf("write sessions require max_instances = 1, plugin %s has %d",
+			pfs.pool.pluginName, pfs.pool.config.MaxInstances)
+	}
+	return nil
+}
+
+// BeginWriteSession implements filesystem.WriteSessioner
+func (pfs *PooledWASMFileSystem) BeginWriteSession() error {
+	if err := pfs.checkSingleInstance(); err != nil {
+		return err
+	}
+	return pfs.pool.Execute(func(instance *WASMModuleInstance) error {
+		return instance.fileSystem.BeginWriteSession()
+	})
+}
+
+// CommitSession implements filesystem.WriteSessioner
+func (pfs *PooledWASMFileSystem) CommitSession(message string) error {
+	if err := pfs.checkSingleInstance(); err != nil {
+		return err
+	}
+	return pfs.pool.Execute(func(instance *WASMModuleInstance) error {
+		return instance.fileSystem.CommitSession(message)
+	})
+}
+
+// AbortSession implements filesystem.WriteSessioner
+func (pfs *PooledWASMFileSystem) AbortSession() error {
+	if err := pfs.checkSingleInstance(); err != nil {
+		return err
+	}
+	return pfs.pool.Execute(func(instance *WASMModuleInstance) error {
+		return instance.fileSystem.AbortSession()
+	})
+}
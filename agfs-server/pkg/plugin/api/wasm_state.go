@@ -0,0 +1,98 @@
+package api
+
+import (
+	"fmt"
+
+	"github.com/c4pt0r/agfs/agfs-server/pkg/filesystem"
+)
+
+var (
+	_ filesystem.StateTransferer = (*WASMFileSystem)(nil)
+	_ filesystem.StateTransferer = (*PooledWASMFileSystem)(nil)
+)
+
+// ExportState implements filesystem.StateTransferer via fs_export_state;
+// plugins built without it have nothing to hand over
+func (wfs *WASMFileSystem) ExportState() ([]byte, error) {
+	exportFunc := wfs.module.ExportedFunction("fs_export_state")
+	if exportFunc == nil {
+		return nil, nil
+	}
+
+	results, err := exportFunc.Call(wfs.ctx)
+	if err != nil {
+		return nil, fmt.Errorf("fs_export_state failed: %w", err)
+	}
+	if len(results) < 1 {
+		return nil, fmt.Errorf("fs_export_state returned invalid results")
+	}
+
+	// Unpack u64: low 32 bits = data ptr, high 32 bits = length, or the
+	// error ptr when the data ptr is 0
+	dataPtr := uint32(results[0] & 0xFFFFFFFF)
+	high := uint32(results[0] >> 32)
+	if dataPtr == 0 {
+		if high != 0 {
+			return nil, wfs.takeError(high, "export state failed")
+		}
+		return nil, nil
+	}
+
+	data, ok := wfs.module.Memory().Read(dataPtr, high)
+	if !ok {
+		freeWASMMemory(wfs.module, dataPtr, 0)
+		return nil, fmt.Errorf("failed to read state from memory")
+	}
+	// Memory().Read returns a view; copy before freeing
+	state := append([]byte(nil), data...)
+	freeWASMMemory(wfs.module, dataPtr, 0)
+	return state, nil
+}
+
+// ImportState implements filesystem.StateTransferer via fs_import_state;
+// plugins built without it start cold
+func (wfs *WASMFileSystem) ImportState(state []byte) error {
+	importFunc := wfs.module.ExportedFunction("fs_import_state")
+	if importFunc == nil {
+		return nil
+	}
+
+	statePtr, statePtrSize, err := writeBytesToMemoryWithBuffer(wfs.module, state, wfs.sharedBuffer)
+	if err != nil {
+		return err
+	}
+	defer freeWASMMemoryWithBuffer(wfs.module, statePtr, statePtrSize, wfs.sharedBuffer)
+
+	results, err := importFunc.Call(wfs.ctx, uint64(statePtr), uint64(len(state)))
+	if err != nil {
+		return fmt.Errorf("fs_import_state failed: %w", err)
+	}
+	if len(results) > 0 && results[0] != 0 {
+		return wfs.takeError(uint32(results[0]), "import state failed")
+	}
+	return nil
+}
+
+// ExportState implements filesystem.StateTransferer. Each instance keeps
+// its own caches, so only a single-instance pool has one state to hand over;
+// larger pools start cold.
+func (pfs *PooledWASMFileSystem) ExportState() (state []byte, err error) {
+	if pfs.pool.config.MaxInstances != 1 {
+		return nil, nil
+	}
+	err = pfs.pool.Execute(func(instance *WASMModuleInstance) error {
+		state, err = instance.fileSystem.ExportState()
+		return err
+	})
+	return state, err
+}
+
+// ImportState implements filesystem.StateTransferer; see ExportState
+func (pfs *PooledWASMFileSystem) ImportState(state []byte) error {
+	if len(state) == 0 || pfs.pool.config.MaxInstances != 1 {
+		return nil
+	}
+	return pfs.pool.Execute(func(instance *WASMModuleInstance) error {
+		return instance.fileSystem.ImportState(state)
+	})
+}
@@ -0,0 +1,308 @@
+package api
+
+import (
+	"fmt"
+	"io"
+	"sync"
+	"time"
+
+	"github.com/c4pt0r/agfs/agfs-server/pkg/filesystem"
+)
+
+// Ring buffer layout shared with the plugin SDK (agfs-core ring.rs)
+const (
+	ringCapacityOffset = 0
+	ringHeadOffset     = 4
+	ringTailOffset     = 8
+	ringFlagsOffset    = 12
+	ringHeaderSize     = 16
+	ringClosed         = 1
+)
+
+// streamPollInterval is how long ReadChunk waits before asking an idle
+// plugin stream for more data
+const streamPollInterval = 10 * time.Millisecond
+
+// WASMStream is a stream opened on a WASM plugin that implements StreamFS
+//
+// Data moves through two ring buffers in the plugin's linear memory. The host
+// drains the outgoing ring and fills the incoming one directly, calling into
+// the plugin only when the former is empty or the latter is full.
+type WASMStream struct {
+	wfs     *WASMFileSystem
+	out     uint32 // plugin -> host ring; also identifies the stream
+	in      uint32 // host -> plugin ring
+	release func() // returns a pooled instance (can be nil)
+	closed  bool
+	mu      sync.Mutex
+}
+
+var _ filesystem.StreamReader = (*WASMStream)(nil)
+
+// OpenStream implements filesystem.Streamer
+func (wfs *WASMFileSystem) OpenStream(path string) (filesystem.StreamReader, error) {
+	return wfs.openStream(path)
+}
+
+func (wfs *WASMFileSystem) openStream(path string) (*WASMStream, error) {
+	if wfs.mu != nil {
+		wfs.mu.Lock()
+		defer wfs.mu.Unlock()
+	}
+
+	openFunc := wfs.module.ExportedFunction("stream_open")
+	if openFunc == nil {
+		return nil, fmt.Errorf("streaming not supported by this plugin")
+	}
+
+	pathPtr, pathPtrSize, err := writeStringToMemoryWithBuffer(wfs.module, path, wfs.sharedBuffer)
+	if err != nil {
+		return nil, err
+	}
+	defer freeWASMMemoryWithBuffer(wfs.module, pathPtr, pathPtrSize, wfs.sharedBuffer)
+
+	// Capacity 0 lets the plugin pick its default ring size
+	results, err := openFunc.Call(wfs.ctx, uint64(pathPtr), 0)
+	if err != nil {
+		return nil, fmt.Errorf("stream_open failed: %w", err)
+	}
+	if len(results) < 1 {
+		return nil, fmt.Errorf("stream_open returned invalid results")
+	}
+
+	// Unpack u64: low 32 bits = outgoing ring ptr (0 on error), high 32 bits = incoming ring ptr or error ptr
+	out := uint32(results[0] & 0xFFFFFFFF)
+	high := uint32(results[0] >> 32)
+	if out == 0 {
+		return nil, wfs.takeError(high, "open stream failed")
+	}
+
+	return &WASMStream{wfs: wfs, out: out, in: high}, nil
+}
+
+// takeError converts an error string returned by the plugin into an error and frees it
+func (wfs *WASMFileSystem) takeError(errPtr uint32, fallback string) error {
+	if errPtr == 0 {
+		return fmt.Errorf("%s", fallback)
+	}
+	errMsg, ok := readStringFromMemory(wfs.module, errPtr)
+	freeWASMMemory(wfs.module, errPtr, 0)
+	if ok && errMsg != "" {
+		if isBackoffMessage(errMsg) {
+			return filesystem.NewBackoffError("", "", retryAfter(errMsg))
+		}
+		return fmt.Errorf("%s", errMsg)
+	}
+	return fmt.Errorf("%s", fallback)
+}
+
+// OpenStream implements filesystem.Streamer
+// The stream keeps its WASM instance until it is closed, like a file handle
+func (pfs *PooledWASMFileSystem) OpenStream(path string) (filesystem.StreamReader, error) {
+	instance, err := pfs.pool.Acquire()
+	if err != nil {
+		return nil, fmt.Errorf("failed to acquire WASM instance: %w", err)
+	}
+
+	endOperation := pfs.pool.beginOperation(instance)
+	stream, err := instance.fileSystem.openStream(path)
+	endOperation()
+	if err != nil {
+		pfs.pool.Release(instance)
+		return nil, err
+	}
+	stream.release = func() { pfs.pool.Release(instance) }
+	return stream, nil
+}
+
+// call invokes a stream export and returns the byte count it reports
+func (s *WASMStream) call(name string) (uint32, error) {
+	wfs := s.wfs
+	if wfs.mu != nil {
+		wfs.mu.Lock()
+		defer wfs.mu.Unlock()
+	}
+
+	fn := wfs.module.ExportedFunction(name)
+	if fn == nil {
+		return 0, fmt.Errorf("%s not implemented", name)
+	}
+	results, err := fn.Call(wfs.ctx, uint64(s.out))
+	if err != nil {
+		return 0, fmt.Errorf("%s failed: %w", name, err)
+	}
+	if len(results) < 1 {
+		return 0, fmt.Errorf("%s returned invalid results", name)
+	}
+
+	// Unpack u64: low 32 bits = byte count, high 32 bits = error ptr
+	if errPtr := uint32(results[0] >> 32); errPtr != 0 {
+		return 0, wfs.takeError(errPtr, name+" failed")
+	}
+	return uint32(results[0] & 0xFFFFFFFF), nil
+}
+
+// ringHeader reads the capacity, head, tail and flags of the ring at ptr
+func (s *WASMStream) ringHeader(ptr uint32) (capacity, head, tail, flags uint32, err error) {
+	mem := s.wfs.module.Memory()
+	var ok [4]bool
+	capacity, ok[0] = mem.ReadUint32Le(ptr + ringCapacityOffset)
+	head, ok[1] = mem.ReadUint32Le(ptr + ringHeadOffset)
+	tail, ok[2] = mem.ReadUint32Le(ptr + ringTailOffset)
+	flags, ok[3] = mem.ReadUint32Le(ptr + ringFlagsOffset)
+	if !ok[0] || !ok[1] || !ok[2] || !ok[3] || capacity == 0 {
+		return 0, 0, 0, 0, fmt.Errorf("invalid stream ring at %#x", ptr)
+	}
+	return capacity, head, tail, flags, nil
+}
+
+// takeOut copies everything waiting in the outgoing ring and marks it consumed
+func (s *WASMStream) takeOut() ([]byte, bool, error) {
+	capacity, head, tail, flags, err := s.ringHeader(s.out)
+	if err != nil {
+		return nil, false, err
+	}
+
+	mem := s.wfs.module.Memory()
+	n := tail - head
+	data := make([]byte, 0, n)
+	for n > 0 {
+		start := head % capacity
+		chunk := min(n, capacity-start)
+		view, ok := mem.Read(s.out+ringHeaderSize+start, chunk)
+		if !ok {
+			return nil, false, fmt.Errorf("failed to read stream data from memory")
+		}
+		data = append(data, view...)
+		head += chunk
+		n -= chunk
+	}
+	if !mem.WriteUint32Le(s.out+ringHeadOffset, head) {
+		return nil, false, fmt.Errorf("failed to update stream ring")
+	}
+	return data, flags&ringClosed != 0, nil
+}
+
+// putIn copies as much of data as fits into the incoming ring
+func (s *WASMStream) putIn(data []byte) (int, error) {
+	capacity, head, tail, _, err := s.ringHeader(s.in)
+	if err != nil {
+		return 0, err
+	}
+
+	mem := s.wfs.module.Memory()
+	n := min(uint32(len(data)), capacity-(tail-head))
+	written := uint32(0)
+	for written < n {
+		start := tail % capacity
+		chunk := min(n-written, capacity-start)
+		if !mem.Write(s.in+ringHeaderSize+start, data[written:written+chunk]) {
+			return 0, fmt.Errorf("failed to write stream data to memory")
+		}
+		tail += chunk
+		written += chunk
+	}
+	if !mem.WriteUint32Le(s.in+ringTailOffset, tail) {
+		return 0, fmt.Errorf("failed to update stream ring")
+	}
+	return int(n), nil
+}
+
+// ReadChunk implements filesystem.StreamReader
+func (s *WASMStream) ReadChunk(timeout time.Duration) ([]byte, bool, error) {
+	s.mu.Lock()
+	defer s.mu.Unlock()
+	if s.closed {
+		return nil, true, io.EOF
+	}
+
+	deadline := time.Now().Add(timeout)
+	for {
+		data, ended, err := s.takeOut()
+		if err != nil {
+			return nil, false, err
+		}
+		if len(data) > 0 {
+			return data, false, nil
+		}
+		if ended {
+			return nil, true, io.EOF
+		}
+
+		// Ring is empty: let the plugin refill it
+		waiting, err := s.call("stream_read")
+		if err != nil {
+			return nil, false, err
+		}
+		if waiting > 0 {
+			continue
+		}
+		if _, _, _, flags, err := s.ringHeader(s.out); err == nil && flags&ringClosed != 0 {
+			continue
+		}
+
+		if !time.Now().Before(deadline) {
+			return nil, false, fmt.Errorf("read timeout")
+		}
+		time.Sleep(min(streamPollInterval, time.Until(deadline)))
+	}
+}
+
+// Write sends data to the plugin through the incoming ring
+func (s *WASMStream) Write(data []byte) (int, error) {
+	s.mu.Lock()
+	defer s.mu.Unlock()
+	if s.closed {
+		return 0, fmt.Errorf("stream is closed")
+	}
+
+	written := 0
+	for written < len(data) {
+		n, err := s.putIn(data[written:])
+		if err != nil {
+			return written, err
+		}
+		written += n
+
+		// Hand the ring to the plugin once it is full or everything is in
+		if _, err := s.call("stream_write"); err != nil {
+			return written, err
+		}
+	}
+	return written, nil
+}
+
+// Close implements filesystem.StreamReader
+func (s *WASMStream) Close() error {
+	s.mu.Lock()
+	defer s.mu.Unlock()
+	if s.closed {
+		return fmt.Errorf("stream is already closed")
+	}
+	s.closed = true
+
+	err := func() error {
+		wfs := s.wfs
+		if wfs.mu != nil {
+			wfs.mu.Lock()
+			defer wfs.mu.Unlock()
+		}
+		closeFunc := wfs.module.ExportedFunction("stream_close")
+		if closeFunc == nil {
+			return fmt.Errorf("stream_close not implemented")
+		}
+		results, err := closeFunc.Call(wfs.ctx, uint64(s.out))
+		if err != nil {
+			return fmt.Errorf("stream_close failed: %w", err)
+		}
+		if len(results) > 0 && results[0] != 0 {
+			return wfs.takeError(uint32(results[0]), "close stream failed")
+		}
+		return nil
+	}()
+
+	if s.release != nil {
+		s.release()
+	}
+	return err
+}
@@ -0,0 +1,200 @@
+package api
+
+import (
+	"encoding/json"
+	"fmt"
+
+	"github.com/c4pt0r/agfs/agfs-server/pkg/filesystem"
+)
+
+var (
+	_ filesystem.Uploader = (*WASMFileSystem)(nil)
+	_ filesystem.Uploader = (*PooledWASMFileSystem)(nil)
+)
+
+// callUploadJSON invokes an upload export returning a packed JSON result and decodes it into out
+func (wfs *WASMFileSystem) callUploadJSON(name string, out interface{}, args ...uint64) error {
+	fn := wfs.module.ExportedFunction(name)
+	if fn == nil {
+		return fmt.Errorf("resumable uploads not supported by this plugin")
+	}
+	results, err := fn.Call(wfs.ctx, args...)
+	if err != nil {
+		return fmt.Errorf("%s failed: %w", name, err)
+	}
+	if len(results) < 1 {
+		return fmt.Errorf("%s returned invalid results", name)
+	}
+
+	// Unpack u64: low 32 bits = json ptr, high 32 bits = error ptr
+	jsonPtr := uint32(results[0] & 0xFFFFFFFF)
+	if errPtr := uint32(results[0] >> 32); errPtr != 0 || jsonPtr == 0 {
+		return wfs.takeError(errPtr, name+" failed")
+	}
+	jsonStr, ok := readStringFromMemory(wfs.module, jsonPtr)
+	freeWASMMemory(wfs.module, jsonPtr, 0)
+	if !ok {
+		return fmt.Errorf("%s: failed to read result", name)
+	}
+	if err := json.Unmarshal([]byte(jsonStr), out); err != nil {
+		return fmt.Errorf("%s: invalid result: %w", name, err)
+	}
+	return nil
+}
+
+// callUploadError invokes an upload export returning an error pointer
+func (wfs *WASMFileSystem) callUploadError(name string, args ...uint64) error {
+	fn := wfs.module.ExportedFunction(name)
+	if fn == nil {
+		return fmt.Errorf("resumable uploads not supported by this plugin")
+	}
+	results, err := fn.Call(wfs.ctx, args...)
+	if err != nil {
+		return fmt.Errorf("%s failed: %w", name, err)
+	}
+	if len(results) > 0 && results[0] != 0 {
+		return wfs.takeError(uint32(results[0]), name+" failed")
+	}
+	return nil
+}
+
+// withString copies s into plugin memory for the duration of fn
+func (wfs *WASMFileSystem) withString(s string, fn func(ptr uint32) error) error {
+	ptr, size, err := writeStringToMemory(wfs.module, s)
+	if err != nil {
+		return err
+	}
+	defer freeWASMMemory(wfs.module, ptr, size)
+	return fn(ptr)
+}
+
+func (wfs *WASMFileSystem) lockUploads() func() {
+	if wfs.mu == nil {
+		return func() {}
+	}
+	wfs.mu.Lock()
+	return wfs.mu.Unlock
+}
+
+// BeginUpload implements filesystem.Uploader
+func (wfs *WASMFileSystem) BeginUpload(path string) (*filesystem.UploadSession, error) {
+	defer wfs.lockUploads()()
+	var session filesystem.UploadSession
+	err := wfs.withString(path, func(ptr uint32) error {
+		return wfs.callUploadJSON("upload_begin", &session, uint64(ptr))
+	})
+	if err != nil {
+		return nil, err
+	}
+	return &session, nil
+}
+
+// UploadStatus implements filesystem.Uploader
+func (wfs *WASMFileSystem) UploadStatus(id string) (*filesystem.UploadSession, error) {
+	defer wfs.lockUploads()()
+	var session filesystem.UploadSession
+	err := wfs.withString(id, func(ptr uint32) error {
+		return wfs.callUploadJSON("upload_status", &session, uint64(ptr))
+	})
+	if err != nil {
+		return nil, err
+	}
+	return &session, nil
+}
+
+// UploadAppend implements filesystem.Uploader
+func (wfs *WASMFileSystem) UploadAppend(id string, offset uint64, data []byte) (*filesystem.UploadSession, error) {
+	defer wfs.lockUploads()()
+	var session filesystem.UploadSession
+	err := wfs.withString(id, func(idPtr uint32) error {
+		dataPtr, dataSize, err := writeBytesToMemory(wfs.module, data)
+		if err != nil {
+			return err
+		}
+		defer freeWASMMemory(wfs.module, dataPtr, dataSize)
+		return wfs.callUploadJSON("upload_append", &session, uint64(idPtr), offset, uint64(dataPtr), uint64(len(data)))
+	})
+	if err != nil {
+		return nil, err
+	}
+	return &session, nil
+}
+
+// CommitUpload implements filesystem.Uploader
+func (wfs *WASMFileSystem) CommitUpload(id string) error {
+	defer wfs.lockUploads()()
+	return wfs.withString(id, func(ptr uint32) error {
+		return wfs.callUploadError("upload_commit", uint64(ptr))
+	})
+}
+
+// AbortUpload implements filesystem.Uploader
+func (wfs *WASMFileSystem) AbortUpload(id string) error {
+	defer wfs.lockUploads()()
+	return wfs.withString(id, func(ptr uint32) error {
+		return wfs.callUploadError("upload_abort", uint64(ptr))
+	})
+}
+
+// ListUploads implements filesystem.Uploader
+func (wfs *WASMFileSystem) ListUploads() ([]filesystem.UploadSession, error) {
+	defer wfs.lockUploads()()
+	var sessions []filesystem.UploadSession
+	if err := wfs.callUploadJSON("upload_list", &sessions); err != nil {
+		return nil, err
+	}
+	return sessions, nil
+}
+
+// Sessions live in host storage rather than instance memory, so any pooled
+// instance can serve any session
+
+// BeginUpload implements filesystem.Uploader
+func (pfs *PooledWASMFileSystem) BeginUpload(path string) (session *filesystem.UploadSession, err error) {
+	err = pfs.pool.Execute(func(instance *WASMModuleInstance) error {
+		session, err = instance.fileSystem.BeginUpload(path)
+		return err
+	})
+	return session, err
+}
+
+// UploadStatus implements filesystem.Uploader
+func (pfs *PooledWASMFileSystem) UploadStatus(id string) (session *filesystem.UploadSession, err error) {
+	err = pfs.pool.Execute(func(instance *WASMModuleInstance) error {
+		session, err = instance.fileSystem.UploadStatus(id)
+		return err
+	})
+	return session, err
+}
+
+// UploadAppend implements filesystem.Uploader
+func (pfs *PooledWASMFileSystem) UploadAppend(id string, offset uint64, data []byte) (session *filesystem.UploadSession, err error) {
+	err = pfs.pool.Execute(func(instance *WASMModuleInstance) error {
+		session, err = instance.fileSystem.UploadAppend(id, offset, data)
+		return err
+	})
+	return session, err
+}
+
+// CommitUpload implements filesystem.Uploader
+func (pfs *PooledWASMFileSystem) CommitUpload(id string) error {
+	return pfs.pool.Execute(func(instance *WASMModuleInstance) error {
+		return instance.fileSystem.CommitUpload(id)
+	})
+}
+
+// AbortUpload implements filesystem.Uploader
+func (pfs *PooledWASMFileSystem) AbortUpload(id string) error {
+	return pfs.pool.Execute(func(instance *WASMModuleInstance) error {
+		return instance.fileSystem.AbortUpload(id)
+	})
+}
+
+// ListUploads implements filesystem.Uploader
+func (pfs *PooledWASMFileSystem) ListUploads() (sessions []filesystem.UploadSession, err error) {
+	err = pfs.pool.Execute(func(instance *WASMModuleInstance) error {
+		sessions, err = instance.fileSystem.ListUploads()
+		return err
+	})
+	return sessions, err
+}
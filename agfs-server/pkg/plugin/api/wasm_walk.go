@@ -0,0 +1,65 @@
+package api
+
+import (
+	"encoding/json"
+	"fmt"
+
+	"github.com/c4pt0r/agfs/agfs-server/pkg/filesystem"
+)
+
+var (
+	_ filesystem.Walker = (*WASMFileSystem)(nil)
+	_ filesystem.Walker = (*PooledWASMFileSystem)(nil)
+)
+
+// Walk implements filesystem.Walker via the plugin's fs_walk export
+func (wfs *WASMFileSystem) Walk(path string, depth int) ([]filesystem.WalkEntry, error) {
+	walkFunc := wfs.module.ExportedFunction("fs_walk")
+	if walkFunc == nil {
+		return nil, fmt.Errorf("fs_walk not implemented")
+	}
+	if depth < 0 {
+		return nil, fmt.Errorf("invalid walk depth %d", depth)
+	}
+
+	pathPtr, pathPtrSize, err := writeStringToMemory(wfs.module, path)
+	if err != nil {
+		return nil, err
+	}
+	defer freeWASMMemory(wfs.module, pathPtr, pathPtrSize)
+
+	results, err := walkFunc.Call(wfs.ctx, uint64(pathPtr), uint64(uint32(depth)))
+	if err != nil {
+		return nil, fmt.Errorf("fs_walk failed: %w", err)
+	}
+	if len(results) < 1 {
+		return nil, fmt.Errorf("fs_walk returned invalid results")
+	}
+
+	// Unpack u64: lower 32 bits = json pointer, upper 32 bits = error pointer
+	jsonPtr := uint32(results[0] & 0xFFFFFFFF)
+	if errPtr := uint32(results[0] >> 32); errPtr != 0 || jsonPtr == 0 {
+		return nil, wfs.takeError(errPtr, "walk failed")
+	}
+
+	jsonStr, ok := readStringFromMemory(wfs.module, jsonPtr)
+	freeWASMMemory(wfs.module, jsonPtr, 0)
+	if !ok {
+		return nil, fmt.Errorf("failed to read walk result")
+	}
+
+	var entries []filesystem.WalkEntry
+	if err := json.Unmarshal([]byte(jsonStr), &entries); err != nil {
+		return nil, fmt.Errorf("failed to unmarshal walk result: %w", err)
+	}
+	return entries, nil
+}
+
+// Walk implements filesystem.Walker
+func (pfs *PooledWASMFileSystem) Walk(path string, depth int) (entries []filesystem.WalkEntry, err error) {
+	err = pfs.pool.Execute(func(instance *WASMModuleInstance) error {
+		entries, err = instance.fileSystem.Walk(path, depth)
+		return err
+	})
+	return entries, err
+}
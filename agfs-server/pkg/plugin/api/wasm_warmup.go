@@ -0,0 +1,56 @@
+package api
+
+import (
+	"encoding/json"
+	"fmt"
+	"time"
+
+	"github.com/c4pt0r/agfs/agfs-server/pkg/filesystem"
+)
+
+var (
+	_ filesystem.Warmer = (*WASMFileSystem)(nil)
+	_ filesystem.Warmer = (*PooledWASMFileSystem)(nil)
+)
+
+// Warmup implements filesystem.Warmer via fs_warmup; plugins built without
+// it are already warm
+func (wfs *WASMFileSystem) Warmup(budget time.Duration) (filesystem.WarmupProgress, error) {
+	var progress filesystem.WarmupProgress
+	warmupFunc := wfs.module.ExportedFunction("fs_warmup")
+	if warmupFunc == nil {
+		return progress, nil
+	}
+
+	results, err := warmupFunc.Call(wfs.ctx, uint64(budget.Milliseconds()))
+	if err != nil {
+		return progress, fmt.Errorf("fs_warmup failed: %w", err)
+	}
+	if len(results) < 1 {
+		return progress, fmt.Errorf("fs_warmup returned invalid results")
+	}
+
+	// Unpack u64: low 32 bits = json ptr, high 32 bits = error ptr
+	jsonPtr := uint32(results[0] & 0xFFFFFFFF)
+	if errPtr := uint32(results[0] >> 32); errPtr != 0 || jsonPtr == 0 {
+		return progress, wfs.takeError(errPtr, "warmup failed")
+	}
+	progressJSON, ok := readStringFromMemory(wfs.module, jsonPtr)
+	freeWASMMemory(wfs.module, jsonPtr, 0)
+	if !ok {
+		return progress, fmt.Errorf("failed to read warmup progress")
+	}
+	if err := json.Unmarshal([]byte(progressJSON), &progress); err != nil {
+		return progress, fmt.Errorf("failed to parse warmup progress: %w", err)
+	}
+	return progress, nil
+}
+
+// Warmup implements filesystem.Warmer on one pooled instance
+func (pfs *PooledWASMFileSystem) Warmup(budget time.Duration) (progress filesystem.WarmupProgress, err error) {
+	err = pfs.pool.Execute(func(instance *WASMModuleInstance) error {
+		progress, err = instance.fileSystem.Warmup(budget)
+		return err
+	})
+	return progress, err
+}
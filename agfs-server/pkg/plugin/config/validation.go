@@ -4,6 +4,7 @@ import (
 	"fmt"
 	"strconv"
 	"strings"
+	"time"
 )
 
 // GetStringConfig retrieves a string value from config with a default fallback
@@ -188,6 +189,37 @@ func GetSizeConfig(config map[string]interface{}, key string, defaultBytes int64
 	}
 }
 
+// GetDurationConfig retrieves a duration from config with a default fallback
+// Supports duration strings (e.g., "10m", "1h30m") and numbers of seconds
+func GetDurationConfig(config map[string]interface{}, key string, defaultValue time.Duration) (time.Duration, error) {
+	val, exists := config[key]
+	if !exists {
+		return defaultValue, nil
+	}
+
+	var d time.Duration
+	switch v := val.(type) {
+	case string:
+		parsed, err := time.ParseDuration(strings.TrimSpace(v))
+		if err != nil {
+			return 0, fmt.Errorf("%s: invalid duration %q (e.g., '30s', '10m')", key, v)
+		}
+		d = parsed
+	case int:
+		d = time.Duration(v) * time.Second
+	case int64:
+		d = time.Duration(v) * time.Second
+	case float64:
+		d = time.Duration(v * float64(time.Second))
+	default:
+		return 0, fmt.Errorf("%s must be a duration string (e.g., '10m') or number of seconds", key)
+	}
+	if d < 0 {
+		return 0, fmt.Errorf("%s must not be negative", key)
+	}
+	return d, nil
+}
+
 // GetPortConfig retrieves a port value from config with a default fallback
 // Supports string, int, and float64 types
 func GetPortConfig(config map[string]interface{}, key, defaultPort string) string {
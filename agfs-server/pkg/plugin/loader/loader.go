@@ -59,7 +59,7 @@ type PluginLoader struct {
 func NewPluginLoader(poolConfig api.PoolConfig) *PluginLoader {
 	return &PluginLoader{
 		loadedPlugins: make(map[string]*LoadedPlugin),
-		wasmLoader:    NewWASMPluginLoader(),
+		wasmLoader:    NewWASMPluginLoader(poolConfig.QueueDir),
 		poolConfig:    poolConfig,
 	}
 }
@@ -223,6 +223,11 @@ func (pl *PluginLoader) loadNativePlugin(libraryPath string) (plugin.ServicePlug
 		return nil, fmt.Errorf("failed to load plugin vtable: %w", err)
 	}
 
+	// Route plugin logging into the server log before the plugin is created
+	if vtable.PluginSetLogCallback != nil {
+		vtable.PluginSetLogCallback(logCallback(libraryPath))
+	}
+
 	// Create external plugin wrapper
 	externalPlugin, err := api.NewExternalPlugin(libHandle, vtable)
 	if err != nil {
@@ -399,6 +404,24 @@ func loadPluginVTable(libHandle uintptr) (*api.PluginVTable, error) {
 		return nil, fmt.Errorf("missing required function PluginNew: %w", err)
 	}
 
+	// ABI negotiation
+	loadFunc(libHandle, "PluginABIVersion", &vtable.PluginABIVersion)
+	loadFunc(libHandle, "PluginFeatures", &vtable.PluginFeatures)
+	vtable.ABIVersion = 1
+	if vtable.PluginABIVersion != nil {
+		vtable.ABIVersion = vtable.PluginABIVersion()
+		if vtable.ABIVersion > api.NativeABIVersion {
+			return nil, fmt.Errorf("plugin ABI version %d is newer than supported version %d", vtable.ABIVersion, api.NativeABIVersion)
+		}
+	}
+
+	// Plugins exporting PluginFeatures only get the optional groups they
+	// advertise; older plugins have every symbol probed
+	features := ^uint64(0)
+	if vtable.PluginFeatures != nil {
+		features = vtable.PluginFeatures()
+	}
+
 	// Optional lifecycle functions
 	loadFunc(libHandle, "PluginFree", &vtable.PluginFree)
 	loadFunc(libHandle, "PluginName", &vtable.PluginName)
@@ -406,6 +429,12 @@ func loadPluginVTable(libHandle uintptr) (*api.PluginVTable, error) {
 	loadFunc(libHandle, "PluginInitialize", &vtable.PluginInitialize)
 	loadFunc(libHandle, "PluginShutdown", &vtable.PluginShutdown)
 	loadFunc(libHandle, "PluginGetReadme", &vtable.PluginGetReadme)
+	if features&api.FeatureConfigParams != 0 {
+		loadFunc(libHandle, "PluginGetConfigParams", &vtable.PluginGetConfigParams)
+	}
+	if features&api.FeatureLog != 0 {
+		loadFunc(libHandle, "PluginSetLogCallback", &vtable.PluginSetLogCallback)
+	}
 
 	// Optional filesystem functions
 	loadFunc(libHandle, "FSCreate", &vtable.FSCreate)
@@ -418,10 +447,119 @@ func loadPluginVTable(libHandle uintptr) (*api.PluginVTable, error) {
 	loadFunc(libHandle, "FSStat", &vtable.FSStat)
 	loadFunc(libHandle, "FSRename", &vtable.FSRename)
 	loadFunc(libHandle, "FSChmod", &vtable.FSChmod)
+	if features&api.FeatureReadDirPage != 0 {
+		loadFunc(libHandle, "FSReadDirPage", &vtable.FSReadDirPage)
+	}
+	if features&api.FeatureReadDirFilter != 0 {
+		loadFunc(libHandle, "FSReadDirFiltered", &vtable.FSReadDirFiltered)
+	}
+	if features&api.FeatureSymlink != 0 {
+		loadFunc(libHandle, "FSSymlink", &vtable.FSSymlink)
+		loadFunc(libHandle, "FSReadlink", &vtable.FSReadlink)
+	}
+	if features&api.FeatureXattr != 0 {
+		loadFunc(libHandle, "FSGetXattr", &vtable.FSGetXattr)
+		loadFunc(libHandle, "FSSetXattr", &vtable.FSSetXattr)
+		loadFunc(libHandle, "FSListXattr", &vtable.FSListXattr)
+	}
+	if features&api.FeatureAdvise != 0 {
+		loadFunc(libHandle, "FSAdvise", &vtable.FSAdvise)
+	}
+	if features&api.FeatureReadIfChanged != 0 {
+		loadFunc(libHandle, "FSReadIfChanged", &vtable.FSReadIfChanged)
+	}
+	if features&api.FeatureWriteIf != 0 {
+		loadFunc(libHandle, "FSWriteIf", &vtable.FSWriteIf)
+	}
+	if features&api.FeatureReadMany != 0 {
+		loadFunc(libHandle, "FSReadMany", &vtable.FSReadMany)
+	}
+	if features&api.FeatureBatch != 0 {
+		loadFunc(libHandle, "FSBatch", &vtable.FSBatch)
+		loadFunc(libHandle, "FSAtomicBatch", &vtable.FSAtomicBatch)
+	}
+	if features&api.FeatureSessions != 0 {
+		loadFunc(libHandle, "FSBeginSession", &vtable.FSBeginSession)
+		loadFunc(libHandle, "FSCommitSession", &vtable.FSCommitSession)
+		loadFunc(libHandle, "FSAbortSession", &vtable.FSAbortSession)
+	}
+	if features&api.FeatureMaintain != 0 {
+		loadFunc(libHandle, "FSMaintain", &vtable.FSMaintain)
+	}
+	if features&api.FeatureWarmup != 0 {
+		loadFunc(libHandle, "FSWarmup", &vtable.FSWarmup)
+	}
+	if features&api.FeatureState != 0 {
+		loadFunc(libHandle, "FSExportState", &vtable.FSExportState)
+		loadFunc(libHandle, "FSImportState", &vtable.FSImportState)
+	}
+
+	// Optional deallocation functions
+	if features&api.FeatureFree != 0 {
+		loadFunc(libHandle, "PluginFreeString", &vtable.PluginFreeString)
+		loadFunc(libHandle, "PluginFreeBuffer", &vtable.PluginFreeBuffer)
+		loadFunc(libHandle, "FSFreeFileInfo", &vtable.FSFreeFileInfo)
+		loadFunc(libHandle, "FSFreeFileInfoArray", &vtable.FSFreeFileInfoArray)
+	}
+
+	// Optional handle functions
+	if features&api.FeatureHandles != 0 {
+		loadFunc(libHandle, "HandleOpen", &vtable.HandleOpen)
+		loadFunc(libHandle, "HandleRead", &vtable.HandleRead)
+		loadFunc(libHandle, "HandleReadAt", &vtable.HandleReadAt)
+		loadFunc(libHandle, "HandleWrite", &vtable.HandleWrite)
+		loadFunc(libHandle, "HandleWriteAt", &vtable.HandleWriteAt)
+		loadFunc(libHandle, "HandleSeek", &vtable.HandleSeek)
+		loadFunc(libHandle, "HandleSync", &vtable.HandleSync)
+		loadFunc(libHandle, "HandleStat", &vtable.HandleStat)
+		loadFunc(libHandle, "HandleClose", &vtable.HandleClose)
+	}
+
+	// Optional async functions
+	if features&api.FeatureAsync != 0 {
+		loadFunc(libHandle, "FSSubmitRead", &vtable.FSSubmitRead)
+		loadFunc(libHandle, "FSSubmitWrite", &vtable.FSSubmitWrite)
+		loadFunc(libHandle, "FSPoll", &vtable.FSPoll)
+	}
+	if features&api.FeatureCancel != 0 {
+		loadFunc(libHandle, "FSCancel", &vtable.FSCancel)
+	}
 
 	return vtable, nil
 }
 
+// logCallbacks caches one C callback per library; purego can only create a
+// limited number of callbacks, so reloads must reuse them
+var logCallbacks sync.Map
+
+// logCallback returns the C function pointer handed to PluginSetLogCallback.
+// Records are forwarded to logrus tagged with the library they came from.
+func logCallback(libraryPath string) uintptr {
+	if cb, ok := logCallbacks.Load(libraryPath); ok {
+		return cb.(uintptr)
+	}
+
+	entry := log.WithField("plugin", filepath.Base(libraryPath))
+	cb := purego.NewCallback(func(level uintptr, msg *byte) {
+		text := api.GoString(msg)
+		// Levels follow the Rust log crate: 1 = error ... 5 = trace
+		switch int32(level) {
+		case 1:
+			entry.Error(text)
+		case 2:
+			entry.Warn(text)
+		case 3:
+			entry.Info(text)
+		case 4:
+			entry.Debug(text)
+		default:
+			entry.Trace(text)
+		}
+	})
+	actual, _ := logCallbacks.LoadOrStore(libraryPath, cb)
+	return actual.(uintptr)
+}
+
 // loadFunc loads a single function from the library
 func loadFunc(libHandle uintptr, name string, fptr interface{}) error {
 	defer func() {
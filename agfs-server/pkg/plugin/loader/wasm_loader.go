@@ -5,6 +5,8 @@ import (
 	"fmt"
 	"os"
 	"path/filepath"
+	"sort"
+	"strings"
 	"sync"
 
 	"github.com/c4pt0r/agfs/agfs-server/pkg/filesystem"
@@ -28,13 +30,16 @@ type LoadedWASMPlugin struct {
 // WASMPluginLoader manages loading and unloading of WASM plugins
 type WASMPluginLoader struct {
 	loadedPlugins map[string]*LoadedWASMPlugin
+	queues        *api.QueueStore // Shared by all plugins, so queues outlive reloads
 	mu            sync.RWMutex
 }
 
-// NewWASMPluginLoader creates a new WASM plugin loader
-func NewWASMPluginLoader() *WASMPluginLoader {
+// NewWASMPluginLoader creates a new WASM plugin loader whose plugins'
+// persistent host queues are kept in queueDir (empty = none)
+func NewWASMPluginLoader(queueDir string) *WASMPluginLoader {
 	return &WASMPluginLoader{
 		loadedPlugins: make(map[string]*LoadedWASMPlugin),
+		queues:        api.NewQueueStore(queueDir),
 	}
 }
 
@@ -103,75 +108,24 @@ func (wl *WASMPluginLoader) LoadWASMPlugin(wasmPath string, poolConfig api.PoolC
 		fs = nil // Will be handled by api functions
 	}
 
-	_, err = r.NewHostModuleBuilder("env").
-			NewFunctionBuilder().
-			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr uint32, offset, size int64) uint64 {
-				return api.HostFSRead(ctx, mod, []uint64{uint64(pathPtr), uint64(offset), uint64(size)}, fs)[0]
-			}).
-			Export("host_fs_read").
-			NewFunctionBuilder().
-			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr, dataPtr, dataLen uint32) uint64 {
-				return api.HostFSWrite(ctx, mod, []uint64{uint64(pathPtr), uint64(dataPtr), uint64(dataLen)}, fs)[0]
-			}).
-			Export("host_fs_write").
-			NewFunctionBuilder().
-			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr uint32) uint64 {
-				return api.HostFSStat(ctx, mod, []uint64{uint64(pathPtr)}, fs)[0]
-			}).
-			Export("host_fs_stat").
-			NewFunctionBuilder().
-			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr uint32) uint64 {
-				return api.HostFSReadDir(ctx, mod, []uint64{uint64(pathPtr)}, fs)[0]
-			}).
-			Export("host_fs_readdir").
-			NewFunctionBuilder().
-			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr uint32) uint32 {
-				return uint32(api.HostFSCreate(ctx, mod, []uint64{uint64(pathPtr)}, fs)[0])
-			}).
-			Export("host_fs_create").
-			NewFunctionBuilder().
-			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr, perm uint32) uint32 {
-				return uint32(api.HostFSMkdir(ctx, mod, []uint64{uint64(pathPtr), uint64(perm)}, fs)[0])
-			}).
-			Export("host_fs_mkdir").
-			NewFunctionBuilder().
-			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr uint32) uint32 {
-				return uint32(api.HostFSRemove(ctx, mod, []uint64{uint64(pathPtr)}, fs)[0])
-			}).
-			Export("host_fs_remove").
-			NewFunctionBuilder().
-			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr uint32) uint32 {
-				return uint32(api.HostFSRemoveAll(ctx, mod, []uint64{uint64(pathPtr)}, fs)[0])
-			}).
-			Export("host_fs_remove_all").
-			NewFunctionBuilder().
-			WithFunc(func(ctx context.Context, mod wazeroapi.Module, oldPathPtr, newPathPtr uint32) uint32 {
-				return uint32(api.HostFSRename(ctx, mod, []uint64{uint64(oldPathPtr), uint64(newPathPtr)}, fs)[0])
-			}).
-			Export("host_fs_rename").
-			NewFunctionBuilder().
-			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr, mode uint32) uint32 {
-				return uint32(api.HostFSChmod(ctx, mod, []uint64{uint64(pathPtr), uint64(mode)}, fs)[0])
-			}).
-			Export("host_fs_chmod").
-			NewFunctionBuilder().
-			WithFunc(func(ctx context.Context, mod wazeroapi.Module, requestPtr uint32) uint64 {
-				return api.HostHTTPRequest(ctx, mod, []uint64{uint64(requestPtr)})[0]
-			}).
-			Export("host_http_request").
-			Instantiate(ctx)
-	if err != nil {
-		r.Close(ctx)
-		return nil, fmt.Errorf("failed to instantiate host filesystem module: %w", err)
-	}
+	// Filled on Initialize, from the mount config's allowlist and the
+	// capabilities the configured plugin declares
+	mounts := &api.MountAccess{}
+	// Namespaced by the plugin's name once it is known
+	queues := wl.queues.Access()
 
-	// Compile and instantiate the WASM module
+	// Compile the WASM module first: the host module stubs the imports it lacks
 	compiledModule, err := r.CompileModule(ctx, wasmBytes)
 	if err != nil {
 		r.Close(ctx)
 		return nil, fmt.Errorf("failed to compile WASM module: %w", err)
 	}
 
+	if err := instantiateHostModule(ctx, r, compiledModule, fs, mounts, queues, poolConfig.Embedder, poolConfig.Completer); err != nil {
+		r.Close(ctx)
+		return nil, err
+	}
+
 	// Instantiate the module without filesystem access
 	// WASM plugins are not allowed to access the local filesystem
 	config := wazero.NewModuleConfig().
@@ -209,6 +163,37 @@ func (wl *WASMPluginLoader) LoadWASMPlugin(wasmPath string, poolConfig api.PoolC
 		}
 	}
 
+	queues.SetNamespace(pluginName)
+
+	// Log what the plugin declares it will contact, e.g. {"http_hosts":[...]}
+	// Plugins without http_hosts have unrestricted egress. Declared mounts
+	// take effect on Initialize, limited to the mount config's allowlist.
+	if capsFunc := module.ExportedFunction("plugin_capabilities"); capsFunc != nil {
+		if capsResults, err := capsFunc.Call(ctx); err == nil && len(capsResults) > 0 {
+			if capsStr, ok := api.ReadStringFromWASMMemory(module, uint32(capsResults[0])); ok {
+				log.Infof("WASM plugin %s declares capabilities: %s", pluginName, capsStr)
+			}
+		}
+	}
+
+	// Log the build manifest (SDK version, build hash, signature) if exported
+	if manifestFunc := module.ExportedFunction("plugin_manifest"); manifestFunc != nil {
+		if manifestResults, err := manifestFunc.Call(ctx); err == nil && len(manifestResults) > 0 {
+			if manifestStr, ok := api.ReadStringFromWASMMemory(module, uint32(manifestResults[0])); ok {
+				log.Infof("WASM plugin %s manifest: %s", pluginName, manifestStr)
+			}
+		}
+	}
+
+	// Log which code is mounted: SDK and plugin versions, git hash, build time
+	if buildInfoFunc := module.ExportedFunction("plugin_build_info"); buildInfoFunc != nil {
+		if buildInfoResults, err := buildInfoFunc.Call(ctx); err == nil && len(buildInfoResults) > 0 {
+			if buildInfoStr, ok := api.ReadStringFromWASMMemory(module, uint32(buildInfoResults[0])); ok {
+				log.Infof("WASM plugin %s build info: %s", pluginName, buildInfoStr)
+			}
+		}
+	}
+
 	// Close the initial module as we'll use the instance pool instead
 	module.Close(ctx)
 
@@ -222,6 +207,7 @@ func (wl *WASMPluginLoader) LoadWASMPlugin(wasmPath string, poolConfig api.PoolC
 		r.Close(ctx)
 		return nil, fmt.Errorf("failed to create WASM plugin wrapper: %w", err)
 	}
+	wasmPlugin.SetMountAccess(mounts)
 
 	// Track loaded plugin (don't save module as it's already closed)
 	loaded := &LoadedWASMPlugin{
@@ -236,6 +222,200 @@ func (wl *WASMPluginLoader) LoadWASMPlugin(wasmPath string, poolConfig api.PoolC
 	return wasmPlugin, nil
 }
 
+// instantiateHostModule registers the "env" host functions WASM plugins
+// import. With a nil fs the host filesystem calls fail; host_mount_call only
+// reaches the paths granted in mounts, host_queue_call the queues of the
+// plugin's namespace. With a nil embedder host_embed fails, and with a nil
+// completer host_ai_complete. Imports of guest the server does not provide
+// are stubbed, and host_capabilities lists the ones that work.
+func instantiateHostModule(ctx context.Context, r wazero.Runtime, guest wazero.CompiledModule, fs filesystem.FileSystem, mounts *api.MountAccess, queues *api.QueueAccess, embedder api.Embedder, completer api.Completer) error {
+	// Filled in once the module is built, before the plugin can ask
+	var available []string
+	builder := r.NewHostModuleBuilder("env").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr uint32, offset, size int64) uint64 {
+				return api.HostFSRead(ctx, mod, []uint64{uint64(pathPtr), uint64(offset), uint64(size)}, fs)[0]
+			}).
+			Export("host_fs_read").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr, dataPtr, dataLen uint32) uint64 {
+				return api.HostFSWrite(ctx, mod, []uint64{uint64(pathPtr), uint64(dataPtr), uint64(dataLen)}, fs)[0]
+			}).
+			Export("host_fs_write").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr uint32) uint64 {
+				return api.HostFSStat(ctx, mod, []uint64{uint64(pathPtr)}, fs)[0]
+			}).
+			Export("host_fs_stat").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr uint32) uint64 {
+				return api.HostFSRealPath(ctx, mod, []uint64{uint64(pathPtr)}, fs)[0]
+			}).
+			Export("host_fs_realpath").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr uint32) uint64 {
+				return api.HostFSReadDir(ctx, mod, []uint64{uint64(pathPtr)}, fs)[0]
+			}).
+			Export("host_fs_readdir").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr uint32) uint32 {
+				return uint32(api.HostFSCreate(ctx, mod, []uint64{uint64(pathPtr)}, fs)[0])
+			}).
+			Export("host_fs_create").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr, perm uint32) uint32 {
+				return uint32(api.HostFSMkdir(ctx, mod, []uint64{uint64(pathPtr), uint64(perm)}, fs)[0])
+			}).
+			Export("host_fs_mkdir").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr uint32) uint32 {
+				return uint32(api.HostFSRemove(ctx, mod, []uint64{uint64(pathPtr)}, fs)[0])
+			}).
+			Export("host_fs_remove").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr uint32) uint32 {
+				return uint32(api.HostFSRemoveAll(ctx, mod, []uint64{uint64(pathPtr)}, fs)[0])
+			}).
+			Export("host_fs_remove_all").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, oldPathPtr, newPathPtr uint32) uint32 {
+				return uint32(api.HostFSRename(ctx, mod, []uint64{uint64(oldPathPtr), uint64(newPathPtr)}, fs)[0])
+			}).
+			Export("host_fs_rename").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr, mode uint32) uint32 {
+				return uint32(api.HostFSChmod(ctx, mod, []uint64{uint64(pathPtr), uint64(mode)}, fs)[0])
+			}).
+			Export("host_fs_chmod").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr, dataPtr, dataLen uint32, offset int64) uint64 {
+				return api.HostFSWriteAt(ctx, mod, []uint64{uint64(pathPtr), uint64(dataPtr), uint64(dataLen), uint64(offset)}, fs)[0]
+			}).
+			Export("host_fs_write_at").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, pathPtr uint32, size int64) uint32 {
+				return uint32(api.HostFSTruncate(ctx, mod, []uint64{uint64(pathPtr), uint64(size)}, fs)[0])
+			}).
+			Export("host_fs_truncate").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, requestPtr uint32) uint64 {
+				return api.HostMountCall(ctx, mod, []uint64{uint64(requestPtr)}, fs, mounts)[0]
+			}).
+			Export("host_mount_call").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, requestPtr uint32) uint64 {
+				return api.HostQueueCall(ctx, mod, []uint64{uint64(requestPtr)}, queues)[0]
+			}).
+			Export("host_queue_call").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, requestPtr uint32) uint64 {
+				return api.HostEmbed(ctx, mod, []uint64{uint64(requestPtr)}, embedder)[0]
+			}).
+			Export("host_embed").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, requestPtr uint32) uint64 {
+				return api.HostAIComplete(ctx, mod, []uint64{uint64(requestPtr)}, completer)[0]
+			}).
+			Export("host_ai_complete").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, requestPtr uint32) uint64 {
+				return api.HostImageTransform(ctx, mod, []uint64{uint64(requestPtr)})[0]
+			}).
+			Export("host_image_transform").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, requestPtr uint32) uint64 {
+				return api.HostHTTPRequest(ctx, mod, []uint64{uint64(requestPtr)})[0]
+			}).
+			Export("host_http_request").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context) int64 {
+				return api.HostDeadline(ctx)
+			}).
+			Export("host_deadline_ms").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, ms int64) int32 {
+				return api.HostSleep(ctx, ms)
+			}).
+			Export("host_sleep_ms").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context) int64 {
+				return api.HostNow(ctx)
+			}).
+			Export("host_now_ms").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, dataPtr, dataLen uint32) uint64 {
+				return api.HostCryptoSHA256(ctx, mod, []uint64{uint64(dataPtr), uint64(dataLen)})[0]
+			}).
+			Export("host_crypto_sha256").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, keyPtr, keyLen, dataPtr, dataLen uint32) uint64 {
+				return api.HostCryptoHMACSHA256(ctx, mod, []uint64{uint64(keyPtr), uint64(keyLen), uint64(dataPtr), uint64(dataLen)})[0]
+			}).
+			Export("host_crypto_hmac_sha256").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, keyPtr, keyLen, dataPtr, dataLen uint32) uint64 {
+				return api.HostCryptoRSASignSHA256(ctx, mod, []uint64{uint64(keyPtr), uint64(keyLen), uint64(dataPtr), uint64(dataLen)})[0]
+			}).
+			Export("host_crypto_rsa_sign_sha256").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module, keyPtr, keyLen, dataPtr, dataLen, sigPtr, sigLen uint32) uint32 {
+				return uint32(api.HostCryptoRSAVerifySHA256(ctx, mod, []uint64{uint64(keyPtr), uint64(keyLen), uint64(dataPtr), uint64(dataLen), uint64(sigPtr), uint64(sigLen)})[0])
+			}).
+			Export("host_crypto_rsa_verify_sha256").
+			NewFunctionBuilder().
+			WithFunc(func(ctx context.Context, mod wazeroapi.Module) uint64 {
+				return api.HostCapabilities(ctx, mod, available)[0]
+			}).
+			Export("host_capabilities")
+
+	compiled, err := builder.Compile(ctx)
+	if err != nil {
+		return fmt.Errorf("failed to compile host filesystem module: %w", err)
+	}
+	provided := compiled.ExportedFunctions()
+	compiled.Close(ctx)
+
+	stubMissingImports(builder, guest, provided)
+	if _, err := builder.Instantiate(ctx); err != nil {
+		return fmt.Errorf("failed to instantiate host filesystem module: %w", err)
+	}
+
+	for name := range provided {
+		switch {
+		case fs == nil && (strings.HasPrefix(name, "host_fs_") || name == "host_mount_call"),
+			queues == nil && name == "host_queue_call",
+			embedder == nil && name == "host_embed",
+			completer == nil && name == "host_ai_complete":
+			// Registered, but every call fails
+		default:
+			available = append(available, name)
+		}
+	}
+	sort.Strings(available)
+	return nil
+}
+
+// stubMissingImports adds an "env" function for each import of guest the
+// server does not provide, so a plugin built for a newer server still loads.
+// Calling a stub fails the call; plugins check host_capabilities first.
+func stubMissingImports(builder wazero.HostModuleBuilder, guest wazero.CompiledModule, provided map[string]wazeroapi.FunctionDefinition) {
+	for _, def := range guest.ImportedFunctions() {
+		module, name, _ := def.Import()
+		if module != "env" {
+			continue
+		}
+		if _, ok := provided[name]; ok {
+			continue
+		}
+		log.Warnf("WASM plugin imports %s, which this server does not provide; calls to it will fail", name)
+		builder.NewFunctionBuilder().
+			WithGoModuleFunction(wazeroapi.GoModuleFunc(func(ctx context.Context, mod wazeroapi.Module, stack []uint64) {
+				panic(fmt.Errorf("host import %s is not provided by this server", name))
+			}), def.ParamTypes(), def.ResultTypes()).
+			Export(name)
+	}
+}
+
 // UnloadWASMPlugin unloads a WASM plugin (decrements ref count, unloads when reaches 0)
 func (wl *WASMPluginLoader) UnloadWASMPlugin(wasmPath string) error {
 	wl.mu.Lock()
@@ -0,0 +1,191 @@
+package loader
+
+import (
+	"bytes"
+	"context"
+	"crypto/ed25519"
+	"encoding/base64"
+	"encoding/binary"
+	"encoding/json"
+	"errors"
+	"fmt"
+	"os"
+
+	"github.com/c4pt0r/agfs/agfs-server/pkg/plugin/api"
+	"github.com/tetratelabs/wazero"
+	"github.com/tetratelabs/wazero/imports/wasi_snapshot_preview1"
+)
+
+// ErrNoManifest is returned for plugins built without a plugin_manifest export
+var ErrNoManifest = errors.New("plugin does not export plugin_manifest")
+
+// ErrNoBuildInfo is returned for plugins without an agfs_build_info section
+var ErrNoBuildInfo = errors.New("plugin has no agfs_build_info section")
+
+// buildInfoSection is the custom section the SDK embeds build info in
+const buildInfoSection = "agfs_build_info"
+
+// WASMManifest describes a WASM plugin as reported by its plugin_manifest export
+type WASMManifest struct {
+	Name         string          `json:"name"`
+	Version      string          `json:"version"`
+	SDKVersion   string          `json:"sdk_version"`
+	BuildHash    string          `json:"build_hash,omitempty"`
+	Capabilities json.RawMessage `json:"capabilities,omitempty"`
+	Signature    string          `json:"signature,omitempty"`
+
+	// raw is the JSON the plugin returned, kept for signing and verification
+	raw []byte
+}
+
+// ReadWASMManifest instantiates the plugin at wasmPath without a host
+// filesystem and returns its manifest. The plugin is not initialized.
+func ReadWASMManifest(wasmPath string) (*WASMManifest, error) {
+	wasmBytes, err := os.ReadFile(wasmPath)
+	if err != nil {
+		return nil, fmt.Errorf("failed to read WASM file %s: %w", wasmPath, err)
+	}
+
+	ctx := context.Background()
+	r := wazero.NewRuntime(ctx)
+	defer r.Close(ctx)
+
+	if _, err := wasi_snapshot_preview1.Instantiate(ctx, r); err != nil {
+		return nil, fmt.Errorf("failed to instantiate WASI: %w", err)
+	}
+	compiled, err := r.CompileModule(ctx, wasmBytes)
+	if err != nil {
+		return nil, fmt.Errorf("failed to compile WASM module: %w", err)
+	}
+	if err := instantiateHostModule(ctx, r, compiled, nil, nil, nil, nil, nil); err != nil {
+		return nil, err
+	}
+
+	module, err := r.InstantiateModule(ctx, compiled, wazero.NewModuleConfig())
+	if err != nil {
+		return nil, fmt.Errorf("failed to instantiate WASM module: %w", err)
+	}
+	defer module.Close(ctx)
+
+	if newFunc := module.ExportedFunction("plugin_new"); newFunc != nil {
+		if _, err := newFunc.Call(ctx); err != nil {
+			return nil, fmt.Errorf("failed to call plugin_new: %w", err)
+		}
+	}
+
+	manifestFunc := module.ExportedFunction("plugin_manifest")
+	if manifestFunc == nil {
+		return nil, ErrNoManifest
+	}
+	results, err := manifestFunc.Call(ctx)
+	if err != nil || len(results) == 0 {
+		return nil, fmt.Errorf("failed to call plugin_manifest: %v", err)
+	}
+	manifestStr, ok := api.ReadStringFromWASMMemory(module, uint32(results[0]))
+	if !ok {
+		return nil, fmt.Errorf("failed to read plugin_manifest result")
+	}
+	return ParseWASMManifest([]byte(manifestStr))
+}
+
+// ReadWASMBuildInfo returns the JSON of the agfs_build_info custom section of
+// the plugin at wasmPath: SDK and plugin versions, git hash and build time.
+// The plugin is not run, so this works for plugins that fail to load.
+func ReadWASMBuildInfo(wasmPath string) (json.RawMessage, error) {
+	wasmBytes, err := os.ReadFile(wasmPath)
+	if err != nil {
+		return nil, fmt.Errorf("failed to read WASM file %s: %w", wasmPath, err)
+	}
+	section, found, err := customSection(wasmBytes, buildInfoSection)
+	if err != nil {
+		return nil, err
+	}
+	if !found {
+		return nil, ErrNoBuildInfo
+	}
+	if !json.Valid(section) {
+		return nil, fmt.Errorf("invalid %s section", buildInfoSection)
+	}
+	return json.RawMessage(section), nil
+}
+
+// customSection returns the payload of the first custom section called name,
+// or false if the module has none
+func customSection(wasm []byte, name string) ([]byte, bool, error) {
+	if len(wasm) < 8 || !bytes.Equal(wasm[:4], []byte("\x00asm")) {
+		return nil, false, errors.New("not a WASM module")
+	}
+	rest := wasm[8:]
+	for len(rest) > 0 {
+		id := rest[0]
+		size, n := binary.Uvarint(rest[1:])
+		if n <= 0 || size > uint64(len(rest)-1-n) {
+			return nil, false, errors.New("truncated WASM section")
+		}
+		body := rest[1+n : 1+n+int(size)]
+		rest = rest[1+n+int(size):]
+		if id != 0 {
+			continue
+		}
+		nameLen, n := binary.Uvarint(body)
+		if n <= 0 || nameLen > uint64(len(body)-n) {
+			return nil, false, errors.New("truncated WASM custom section")
+		}
+		if string(body[n:n+int(nameLen)]) == name {
+			return body[n+int(nameLen):], true, nil
+		}
+	}
+	return nil, false, nil
+}
+
+// ParseWASMManifest decodes the JSON returned by plugin_manifest
+func ParseWASMManifest(data []byte) (*WASMManifest, error) {
+	var m WASMManifest
+	if err := json.Unmarshal(data, &m); err != nil {
+		return nil, fmt.Errorf("invalid plugin manifest: %w", err)
+	}
+	m.raw = append([]byte(nil), data...)
+	return &m, nil
+}
+
+// SignedPayload returns the bytes covered by the signature: the manifest
+// JSON without its "signature" field, with object keys sorted
+func (m *WASMManifest) SignedPayload() ([]byte, error) {
+	dec := json.NewDecoder(bytes.NewReader(m.raw))
+	dec.UseNumber()
+	var fields map[string]interface{}
+	if err := dec.Decode(&fields); err != nil {
+		return nil, fmt.Errorf("invalid plugin manifest: %w", err)
+	}
+	delete(fields, "signature")
+	return json.Marshal(fields)
+}
+
+// Sign returns the base64 Ed25519 signature of the manifest, to be embedded
+// by rebuilding the plugin with AGFS_MANIFEST_SIGNATURE set to it
+func (m *WASMManifest) Sign(key ed25519.PrivateKey) (string, error) {
+	payload, err := m.SignedPayload()
+	if err != nil {
+		return "", err
+	}
+	return base64.StdEncoding.EncodeToString(ed25519.Sign(key, payload)), nil
+}
+
+// Verify checks the embedded signature against key
+func (m *WASMManifest) Verify(key ed25519.PublicKey) error {
+	if m.Signature == "" {
+		return errors.New("plugin manifest is not signed")
+	}
+	sig, err := base64.StdEncoding.DecodeString(m.Signature)
+	if err != nil {
+		return fmt.Errorf("invalid manifest signature encoding: %w", err)
+	}
+	payload, err := m.SignedPayload()
+	if err != nil {
+		return err
+	}
+	if !ed25519.Verify(key, payload, sig) {
+		return errors.New("plugin manifest signature does not match")
+	}
+	return nil
+}
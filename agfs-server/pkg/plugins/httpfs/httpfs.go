@@ -276,7 +276,9 @@ func (fs *HTTPFS) serveFile(w http.ResponseWriter, r *http.Request, pfsPath stri
 
 	// Set headers
 	w.Header().Set("Content-Type", contentType)
-	w.Header().Set("Content-Length", fmt.Sprintf("%d", info.Size))
+	if info.Size != filesystem.SizeUnknown {
+		w.Header().Set("Content-Length", fmt.Sprintf("%d", info.Size))
+	}
 	w.Header().Set("Last-Modified", info.ModTime.Format(http.TimeFormat))
 
 	// Copy content
@@ -367,7 +369,7 @@ func (fs *HTTPFS) serveDirectory(w http.ResponseWriter, r *http.Request, pfsPath
             {{range .Files}}
             <tr>
                 <td><a href="{{.URL}}">{{.Name}}</a></td>
-                <td class="size">{{if .IsDir}}-{{else}}{{.Size}}{{end}}</td>
+                <td class="size">{{if or .IsDir (lt .Size 0)}}-{{else}}{{.Size}}{{end}}</td>
                 <td>{{.ModTime}}</td>
             </tr>
             {{end}}
@@ -5,6 +5,7 @@ import (
 	"io"
 	"os"
 	"path/filepath"
+	"strings"
 	"sync"
 	"time"
 
@@ -583,6 +584,49 @@ func (fs *LocalFS) Readlink(linkPath string) (string, error) {
 	return target, nil
 }
 
+// RealPath resolves the symbolic links in path, failing if they lead outside
+// the base directory or to nothing
+func (fs *LocalFS) RealPath(path string) (string, error) {
+	fs.mu.RLock()
+	defer fs.mu.RUnlock()
+
+	base, err := filepath.EvalSymlinks(fs.basePath)
+	if err != nil {
+		return "", fmt.Errorf("failed to resolve base path: %w", err)
+	}
+	return fs.realPath(base, filepath.Clean("/"+path))
+}
+
+// realPath resolves clean virtual path p against base, the resolved base
+// directory; components that do not exist are appended to their parent's
+// real path
+func (fs *LocalFS) realPath(base, p string) (string, error) {
+	localPath := fs.resolvePath(p)
+	resolvedPath, err := filepath.EvalSymlinks(localPath)
+	if err != nil {
+		if !os.IsNotExist(err) {
+			return "", fmt.Errorf("failed to resolve path: %w", err)
+		}
+		if _, lerr := os.Lstat(localPath); lerr == nil {
+			return "", filesystem.NewPermissionDeniedError("realpath", p, "dangling symbolic link")
+		}
+		if p == "/" {
+			return "", fmt.Errorf("base path does not exist: %s", fs.basePath)
+		}
+		parent, err := fs.realPath(base, filepath.Dir(p))
+		if err != nil {
+			return "", err
+		}
+		return filepath.Join(parent, filepath.Base(p)), nil
+	}
+
+	rel, err := filepath.Rel(base, resolvedPath)
+	if err != nil || rel == ".." || strings.HasPrefix(rel, ".."+string(filepath.Separator)) {
+		return "", filesystem.NewPermissionDeniedError("realpath", p, "symbolic link leads outside the local directory")
+	}
+	return filepath.ToSlash(filepath.Join("/", rel)), nil
+}
+
 // OpenStream implements the Streamer interface for streaming file reads
 func (fs *LocalFS) OpenStream(path string) (filesystem.StreamReader, error) {
 	localPath := fs.resolvePath(path)
@@ -729,3 +729,49 @@ func TestLocalFSRemoveAll(t *testing.T) {
 		t.Error("Directory should be removed")
 	}
 }
+
+func TestLocalFSRealPath(t *testing.T) {
+	dir, cleanup := setupTestDir(t)
+	defer cleanup()
+	outside, cleanupOutside := setupTestDir(t)
+	defer cleanupOutside()
+
+	fs := newTestFS(t, dir)
+	if err := fs.Mkdir("/docs", 0755); err != nil {
+		t.Fatalf("Mkdir failed: %v", err)
+	}
+	for link, target := range map[string]string{
+		"out":     outside,
+		"shared":  filepath.Join(dir, "docs"),
+		"missing": filepath.Join(dir, "nowhere"),
+	} {
+		if err := os.Symlink(target, filepath.Join(dir, link)); err != nil {
+			t.Fatalf("Symlink failed: %v", err)
+		}
+	}
+
+	tests := []struct {
+		path string
+		want string
+	}{
+		{"/docs/a.txt", "/docs/a.txt"},
+		{"/shared/a.txt", "/docs/a.txt"},
+		{"/shared/../docs", "/docs"},
+		{"/new/dir/file.txt", "/new/dir/file.txt"},
+	}
+	for _, tt := range tests {
+		got, err := fs.RealPath(tt.path)
+		if err != nil {
+			t.Errorf("RealPath(%s) failed: %v", tt.path, err)
+		} else if got != tt.want {
+			t.Errorf("RealPath(%s) = %s, want %s", tt.path, got, tt.want)
+		}
+	}
+
+	// Links out of the directory and dangling links are refused
+	for _, path := range []string{"/out", "/out/passwd", "/missing/file.txt"} {
+		if got, err := fs.RealPath(path); err == nil {
+			t.Errorf("RealPath(%s) = %s, expected an error", path, got)
+		}
+	}
+}